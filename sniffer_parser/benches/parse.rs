@@ -0,0 +1,238 @@
+//! Parsing throughput over a representative frame mix
+//!
+//! Run with `cargo bench --bench parse`: every frame of the mix is parsed repeatedly and the mean
+//! time per frame is reported, with and without payload retention, then with the zero-copy
+//! parsing
+
+use std::hint::black_box;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, Instant};
+
+use pnet::packet::arp::{ArpHardwareTypes, ArpOperations, MutableArpPacket};
+use pnet::packet::ethernet::{EtherType, EtherTypes, EthernetPacket, MutableEthernetPacket};
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::ipv4::MutableIpv4Packet;
+use pnet::packet::ipv6::MutableIpv6Packet;
+use pnet::packet::tcp::{MutableTcpPacket, TcpFlags};
+use pnet::packet::udp::MutableUdpPacket;
+use pnet::packet::Packet;
+use pnet::util::MacAddr;
+use sniffer_parser::packet_ref::{parse_ethernet_frame_ref, TransportLayerRef};
+use sniffer_parser::{cleanup_sniffing_state, parse_ethernet_frame, set_payload_retention};
+
+const ROUNDS: usize = 100_000;
+
+const IPV4_HEADER_LENGTH: usize = 20;
+const IPV6_HEADER_LENGTH: usize = 40;
+const UDP_HEADER_LENGTH: usize = 8;
+const TCP_HEADER_LENGTH: usize = 20;
+
+const DNS_QUERY: &[u8] = &[
+    0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x07, b'e', b'x', b'a',
+    b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00, 0x00, 0x01, 0x00, 0x01,
+];
+
+const HTTP_REQUEST: &[u8] = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\nAccept: */*\r\n\r\n";
+
+fn main() {
+    let frames = frame_mix();
+
+    for retention in [false, true] {
+        set_payload_retention(retention);
+        println!("payload retention: {}", retention);
+
+        let mut total = Duration::ZERO;
+        for (name, frame) in &frames {
+            let elapsed = time_frame(frame);
+            total += elapsed;
+            println!(
+                "  {:<16} {:>8.1} ns/frame",
+                name,
+                elapsed.as_nanos() as f64 / ROUNDS as f64
+            );
+        }
+
+        let bytes: usize = frames.iter().map(|(_, frame)| frame.len()).sum();
+        println!(
+            "  {:<16} {:>8.1} ns/frame, {:.1} MB/s",
+            "mix",
+            total.as_nanos() as f64 / (ROUNDS * frames.len()) as f64,
+            (ROUNDS * bytes) as f64 / total.as_secs_f64() / 1e6
+        );
+    }
+
+    println!("zero-copy");
+    for (name, frame) in &frames {
+        let elapsed = time_frame_ref(frame);
+        println!(
+            "  {:<16} {:>8.1} ns/frame",
+            name,
+            elapsed.as_nanos() as f64 / ROUNDS as f64
+        );
+    }
+}
+
+/// Parse a frame `ROUNDS` times, after a warm-up
+fn time_frame(frame: &[u8]) -> Duration {
+    let ethernet = EthernetPacket::new(frame).unwrap();
+    for id in 0..ROUNDS / 10 {
+        black_box(parse_ethernet_frame(&ethernet, id));
+    }
+    cleanup_sniffing_state();
+
+    let start = Instant::now();
+    for id in 0..ROUNDS {
+        black_box(parse_ethernet_frame(&ethernet, id));
+    }
+    let elapsed = start.elapsed();
+    cleanup_sniffing_state();
+
+    elapsed
+}
+
+/// Split a frame into borrowed views `ROUNDS` times, reading the transport ports as a consumer
+/// would, after a warm-up
+fn time_frame_ref(frame: &[u8]) -> Duration {
+    let parse = |id| {
+        let packet = parse_ethernet_frame_ref(frame, id);
+        black_box(
+            packet
+                .get_transport_layer_packet()
+                .map(|transport| match transport {
+                    TransportLayerRef::Tcp(tcp) => (tcp.get_source(), tcp.get_destination()),
+                    TransportLayerRef::Udp(udp) => (udp.get_source(), udp.get_destination()),
+                    _ => (0, 0),
+                }),
+        );
+    };
+
+    for id in 0..ROUNDS / 10 {
+        parse(id);
+    }
+
+    let start = Instant::now();
+    for id in 0..ROUNDS {
+        parse(id);
+    }
+    start.elapsed()
+}
+
+/// ARP request, DNS query, HTTP request, full-size TCP segments over IPv4 and IPv6, unknown frame
+fn frame_mix() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("arp", arp_frame()),
+        (
+            "ipv4/udp/dns",
+            ipv4_frame(
+                IpNextHeaderProtocols::Udp,
+                &udp_segment(4444, 53, DNS_QUERY),
+            ),
+        ),
+        (
+            "ipv4/tcp/http",
+            ipv4_frame(
+                IpNextHeaderProtocols::Tcp,
+                &tcp_segment(4444, 80, HTTP_REQUEST),
+            ),
+        ),
+        (
+            "ipv4/tcp/1460",
+            ipv4_frame(
+                IpNextHeaderProtocols::Tcp,
+                &tcp_segment(5555, 8080, &[0xab; 1460]),
+            ),
+        ),
+        (
+            "ipv6/tcp/1440",
+            ipv6_frame(
+                IpNextHeaderProtocols::Tcp,
+                &tcp_segment(6666, 8080, &[0xcd; 1440]),
+            ),
+        ),
+        (
+            "unknown",
+            ethernet_frame(EtherType::new(0x88b5), &[0u8; 46]),
+        ),
+    ]
+}
+
+///////////////////// Frame builders
+
+fn ethernet_frame(ethertype: EtherType, payload: &[u8]) -> Vec<u8> {
+    let mut buffer = vec![0u8; 14 + payload.len()];
+    let mut ethernet_packet = MutableEthernetPacket::new(&mut buffer).unwrap();
+    ethernet_packet.set_destination(MacAddr::new(11, 11, 11, 11, 11, 11));
+    ethernet_packet.set_source(MacAddr::new(10, 10, 10, 10, 10, 10));
+    ethernet_packet.set_ethertype(ethertype);
+    ethernet_packet.set_payload(payload);
+    buffer
+}
+
+fn arp_frame() -> Vec<u8> {
+    let mut buffer = [0u8; 28];
+    let mut arp_packet = MutableArpPacket::new(&mut buffer).unwrap();
+    arp_packet.set_hardware_type(ArpHardwareTypes::Ethernet);
+    arp_packet.set_protocol_type(EtherTypes::Ipv4);
+    arp_packet.set_hw_addr_len(6);
+    arp_packet.set_proto_addr_len(4);
+    arp_packet.set_operation(ArpOperations::Request);
+    arp_packet.set_sender_hw_addr(MacAddr::new(10, 10, 10, 10, 10, 10));
+    arp_packet.set_sender_proto_addr(Ipv4Addr::new(10, 10, 10, 10));
+    arp_packet.set_target_hw_addr(MacAddr::zero());
+    arp_packet.set_target_proto_addr(Ipv4Addr::new(11, 11, 11, 11));
+
+    ethernet_frame(EtherTypes::Arp, arp_packet.packet())
+}
+
+fn ipv4_frame(protocol: IpNextHeaderProtocol, payload: &[u8]) -> Vec<u8> {
+    let mut buffer = vec![0u8; IPV4_HEADER_LENGTH + payload.len()];
+    let mut ipv4_packet = MutableIpv4Packet::new(&mut buffer).unwrap();
+    ipv4_packet.set_version(4);
+    ipv4_packet.set_header_length(5);
+    ipv4_packet.set_total_length((IPV4_HEADER_LENGTH + payload.len()) as u16);
+    ipv4_packet.set_ttl(64);
+    ipv4_packet.set_next_level_protocol(protocol);
+    ipv4_packet.set_source(Ipv4Addr::new(10, 10, 10, 10));
+    ipv4_packet.set_destination(Ipv4Addr::new(11, 11, 11, 11));
+    ipv4_packet.set_payload(payload);
+
+    ethernet_frame(EtherTypes::Ipv4, &buffer)
+}
+
+fn ipv6_frame(next_header: IpNextHeaderProtocol, payload: &[u8]) -> Vec<u8> {
+    let mut buffer = vec![0u8; IPV6_HEADER_LENGTH + payload.len()];
+    let mut ipv6_packet = MutableIpv6Packet::new(&mut buffer).unwrap();
+    ipv6_packet.set_version(6);
+    ipv6_packet.set_payload_length(payload.len() as u16);
+    ipv6_packet.set_next_header(next_header);
+    ipv6_packet.set_hop_limit(64);
+    ipv6_packet.set_source(Ipv6Addr::new(10, 10, 10, 10, 10, 10, 10, 10));
+    ipv6_packet.set_destination(Ipv6Addr::new(11, 11, 11, 11, 11, 11, 11, 11));
+    ipv6_packet.set_payload(payload);
+
+    ethernet_frame(EtherTypes::Ipv6, &buffer)
+}
+
+fn udp_segment(source: u16, destination: u16, payload: &[u8]) -> Vec<u8> {
+    let mut buffer = vec![0u8; UDP_HEADER_LENGTH + payload.len()];
+    let mut udp_packet = MutableUdpPacket::new(&mut buffer).unwrap();
+    udp_packet.set_source(source);
+    udp_packet.set_destination(destination);
+    udp_packet.set_length((UDP_HEADER_LENGTH + payload.len()) as u16);
+    udp_packet.set_payload(payload);
+    buffer
+}
+
+fn tcp_segment(source: u16, destination: u16, payload: &[u8]) -> Vec<u8> {
+    let mut buffer = vec![0u8; TCP_HEADER_LENGTH + payload.len()];
+    let mut tcp_packet = MutableTcpPacket::new(&mut buffer).unwrap();
+    tcp_packet.set_source(source);
+    tcp_packet.set_destination(destination);
+    tcp_packet.set_sequence(1);
+    tcp_packet.set_acknowledgement(1);
+    tcp_packet.set_data_offset(5);
+    tcp_packet.set_flags(TcpFlags::ACK | TcpFlags::PSH);
+    tcp_packet.set_window(65535);
+    tcp_packet.set_payload(payload);
+    buffer
+}
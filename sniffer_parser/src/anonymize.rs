@@ -0,0 +1,632 @@
+//! Address anonymization
+//!
+//! IP and MAC addresses are replaced by pseudonyms with a prefix-preserving scheme (the one of
+//! Crypto-PAn): bit `i` of an address is flipped according to a keyed hash of its first `i` bits,
+//! so two addresses sharing a `n`-bit prefix get pseudonyms sharing exactly a `n`-bit prefix and
+//! the subnet structure survives. The key is drawn for every `Anonymizer`, the pseudonyms are
+//! consistent for its whole lifetime only.
+//!
+//! MAC group addresses (broadcast and multicast) are left unchanged, as they don't identify a
+//! host; the vendor part (OUI) of the others can be kept. The raw frame, the payload copies of
+//! the layers and the DoH bodies are dropped, since they contain the original addresses. The DHCPv6 DUIDs embedding a
+//! MAC address get its pseudonym, the other DUIDs are replaced by a keyed hash
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use pnet::util::MacAddr;
+
+use crate::serializable_packet::application::{
+    address_to_reverse_name, reverse_name_to_address, CustomResourceData, HttpContentType,
+    SerializableDnsPacket,
+};
+use crate::serializable_packet::transport::SerializableEmbeddedPacket;
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+const OUI_BITS: u32 = 24;
+/// DUID types embedding a link-layer address, after a time for DUID-LLT
+const DUID_LLT: u16 = 1;
+const DUID_LL: u16 = 3;
+const ETHERNET_HARDWARE_TYPE: u16 = 1;
+
+/// Pseudonymization of the addresses of parsed packets
+pub struct Anonymizer {
+    key: RandomState,
+    keep_oui: bool,
+    ip_addresses: HashMap<IpAddr, IpAddr>,
+    mac_addresses: HashMap<MacAddr, MacAddr>,
+}
+
+impl Anonymizer {
+    /// Build an anonymizer with a new key, keeping or not the OUI of the MAC addresses
+    pub fn new(keep_oui: bool) -> Self {
+        Anonymizer {
+            key: RandomState::new(),
+            keep_oui,
+            ip_addresses: HashMap::new(),
+            mac_addresses: HashMap::new(),
+        }
+    }
+
+    /// Replace every address of a packet by its pseudonym
+    pub fn anonymize(&mut self, packet: &mut ParsedPacket) {
+        packet.set_raw_frame(None);
+
+        for layer in packet.layers_mut() {
+            self.anonymize_layer(layer);
+        }
+    }
+
+    fn anonymize_layer(&mut self, layer: &mut SerializablePacket) {
+        match layer {
+            SerializablePacket::EthernetPacket(ethernet_packet) => {
+                ethernet_packet.source = self.mac(ethernet_packet.source);
+                ethernet_packet.destination = self.mac(ethernet_packet.destination);
+                ethernet_packet.payload.clear();
+            }
+            SerializablePacket::PppoePacket(pppoe_packet) => {
+                pppoe_packet.source = self.mac(pppoe_packet.source);
+                pppoe_packet.destination = self.mac(pppoe_packet.destination);
+            }
+            SerializablePacket::SllPacket(sll_packet) => {
+                sll_packet.link_layer_address = sll_packet
+                    .link_layer_address
+                    .map(|address| self.mac(address));
+            }
+            SerializablePacket::Dot11Packet(dot11_packet) => {
+                dot11_packet.receiver = self.mac(dot11_packet.receiver);
+                dot11_packet.transmitter =
+                    dot11_packet.transmitter.map(|address| self.mac(address));
+                dot11_packet.bssid = dot11_packet.bssid.map(|address| self.mac(address));
+            }
+            SerializablePacket::UnknownPacket(unknown_packet) => {
+                unknown_packet.source = self.mac(unknown_packet.source);
+                unknown_packet.destination = self.mac(unknown_packet.destination);
+                unknown_packet.payload.clear();
+            }
+            SerializablePacket::ArpPacket(arp_packet) => {
+                arp_packet.sender_hw_addr = self.mac(arp_packet.sender_hw_addr);
+                arp_packet.sender_proto_addr = self.ipv4(arp_packet.sender_proto_addr);
+                arp_packet.target_hw_addr = self.mac(arp_packet.target_hw_addr);
+                arp_packet.target_proto_addr = self.ipv4(arp_packet.target_proto_addr);
+            }
+            SerializablePacket::Ipv4Packet(ipv4_packet) => {
+                ipv4_packet.source = self.ipv4(ipv4_packet.source);
+                ipv4_packet.destination = self.ipv4(ipv4_packet.destination);
+                ipv4_packet.payload.clear();
+                if let Some(encapsulated) = &mut ipv4_packet.encapsulated {
+                    self.anonymize_layer(encapsulated);
+                }
+            }
+            SerializablePacket::Ipv6Packet(ipv6_packet) => {
+                ipv6_packet.source = self.ipv6(ipv6_packet.source);
+                ipv6_packet.destination = self.ipv6(ipv6_packet.destination);
+                ipv6_packet.payload.clear();
+                if let Some(encapsulated) = &mut ipv6_packet.encapsulated {
+                    self.anonymize_layer(encapsulated);
+                }
+            }
+            SerializablePacket::TcpPacket(tcp_packet) => tcp_packet.payload.clear(),
+            SerializablePacket::UdpPacket(udp_packet) => udp_packet.payload.clear(),
+            SerializablePacket::IcmpPacket(icmp_packet) => {
+                self.anonymize_embedded(&mut icmp_packet.original_packet)
+            }
+            SerializablePacket::Icmpv6Packet(icmpv6_packet) => {
+                self.anonymize_embedded(&mut icmpv6_packet.original_packet)
+            }
+            SerializablePacket::IgmpPacket(igmp_packet) => {
+                let sources = igmp_packet.sources.iter_mut().chain(
+                    igmp_packet
+                        .records
+                        .iter_mut()
+                        .flat_map(|record| record.sources.iter_mut()),
+                );
+
+                for source in sources {
+                    *source = self.ipv4(*source);
+                }
+            }
+            SerializablePacket::GtpPacket(gtp_packet) => {
+                if let Some(encapsulated) = &mut gtp_packet.encapsulated {
+                    self.anonymize_layer(encapsulated);
+                }
+            }
+            SerializablePacket::OspfPacket(ospf_packet) => {
+                ospf_packet.router_id = self.ipv4(ospf_packet.router_id);
+                if let Some(hello) = &mut ospf_packet.hello {
+                    // 0.0.0.0 stands for no (backup) designated router
+                    for router in [
+                        &mut hello.designated_router,
+                        &mut hello.backup_designated_router,
+                    ] {
+                        if !router.is_unspecified() {
+                            *router = self.ipv4(*router);
+                        }
+                    }
+                    for neighbor in hello.neighbors.iter_mut() {
+                        *neighbor = self.ipv4(*neighbor);
+                    }
+                }
+            }
+            SerializablePacket::DnsPacket(dns_packet) => self.anonymize_dns(dns_packet),
+            // The body of a DoH message is dropped, the DNS message decoded from it is kept
+            SerializablePacket::HttpRequestPacket(http_packet) => {
+                if let Some(dns_message) = &mut http_packet.dns_message {
+                    self.anonymize_dns(dns_message);
+                    http_packet.payload = HttpContentType::None;
+                }
+            }
+            SerializablePacket::HttpResponsePacket(http_packet) => {
+                if let Some(dns_message) = &mut http_packet.dns_message {
+                    self.anonymize_dns(dns_message);
+                    http_packet.payload = HttpContentType::None;
+                }
+            }
+            SerializablePacket::StunPacket(stun_packet) => {
+                for address in [
+                    &mut stun_packet.mapped_address,
+                    &mut stun_packet.xor_mapped_address,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    *address = SocketAddr::new(self.ip(address.ip()), address.port());
+                }
+            }
+            SerializablePacket::NbnsPacket(nbns_packet) => {
+                for address in nbns_packet.addresses.iter_mut() {
+                    *address = self.ipv4(*address);
+                }
+            }
+            SerializablePacket::Dhcpv6Packet(dhcpv6_packet) => {
+                for duid in [
+                    &mut dhcpv6_packet.client_duid,
+                    &mut dhcpv6_packet.server_duid,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    *duid = self.duid(duid);
+                }
+
+                let addresses = dhcpv6_packet
+                    .ia_na
+                    .iter_mut()
+                    .flat_map(|ia_na| ia_na.addresses.iter_mut())
+                    .chain(dhcpv6_packet.dns_servers.iter_mut());
+                for address in addresses {
+                    *address = self.ipv6(*address);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn anonymize_embedded(&mut self, embedded_packet: &mut Option<SerializableEmbeddedPacket>) {
+        if let Some(embedded_packet) = embedded_packet {
+            embedded_packet.source = self.ip(embedded_packet.source);
+            embedded_packet.destination = self.ip(embedded_packet.destination);
+        }
+    }
+
+    fn anonymize_dns(&mut self, dns_packet: &mut SerializableDnsPacket) {
+        for question in dns_packet.questions.iter_mut() {
+            self.anonymize_reverse_name(&mut question.query_name, &mut question.reverse_address);
+        }
+
+        let records = dns_packet
+            .answers
+            .iter_mut()
+            .chain(dns_packet.nameservers.iter_mut())
+            .chain(dns_packet.additional.iter_mut());
+
+        for record in records {
+            self.anonymize_reverse_name(&mut record.name, &mut record.reverse_address);
+            match &mut record.data {
+                CustomResourceData::A(a) => a.address = self.ipv4(a.address),
+                CustomResourceData::AAAA(aaaa) => aaaa.address = self.ipv6(aaaa.address),
+                _ => (),
+            }
+        }
+    }
+
+    /// Replace the address of a reverse lookup name (in `in-addr.arpa` or `ip6.arpa`) by its
+    /// pseudonym, in the name and in the address decoded from it
+    fn anonymize_reverse_name(&mut self, name: &mut String, reverse_address: &mut Option<IpAddr>) {
+        if let Some(address) = reverse_name_to_address(name) {
+            let pseudonym = self.ip(address);
+            *name = address_to_reverse_name(pseudonym);
+            *reverse_address = reverse_address.map(|_| pseudonym);
+        }
+    }
+
+    /// Get the pseudonym of an IP address
+    pub fn ip(&mut self, address: IpAddr) -> IpAddr {
+        if let Some(pseudonym) = self.ip_addresses.get(&address) {
+            return *pseudonym;
+        }
+
+        let pseudonym = match address {
+            IpAddr::V4(ipv4) => {
+                IpAddr::V4(Ipv4Addr::from(
+                    self.pseudonymize(u32::from(ipv4) as u128, 32, 0) as u32,
+                ))
+            }
+            IpAddr::V6(ipv6) => {
+                IpAddr::V6(Ipv6Addr::from(self.pseudonymize(u128::from(ipv6), 128, 0)))
+            }
+        };
+        self.ip_addresses.insert(address, pseudonym);
+
+        pseudonym
+    }
+
+    /// Get the pseudonym of an IPv4 address
+    pub fn ipv4(&mut self, address: Ipv4Addr) -> Ipv4Addr {
+        match self.ip(IpAddr::V4(address)) {
+            IpAddr::V4(pseudonym) => pseudonym,
+            IpAddr::V6(_) => unreachable!(),
+        }
+    }
+
+    /// Get the pseudonym of an IPv6 address
+    pub fn ipv6(&mut self, address: Ipv6Addr) -> Ipv6Addr {
+        match self.ip(IpAddr::V6(address)) {
+            IpAddr::V6(pseudonym) => pseudonym,
+            IpAddr::V4(_) => unreachable!(),
+        }
+    }
+
+    /// Get the pseudonym of a MAC address, group addresses being kept
+    pub fn mac(&mut self, address: MacAddr) -> MacAddr {
+        if address.is_multicast() {
+            return address;
+        }
+        if let Some(pseudonym) = self.mac_addresses.get(&address) {
+            return *pseudonym;
+        }
+
+        let octets = address.octets();
+        let mut value = [0u8; 16];
+        value[10..].copy_from_slice(&octets);
+
+        let kept_bits = match self.keep_oui {
+            true => OUI_BITS,
+            false => 0,
+        };
+        let pseudonymized = self.pseudonymize(u128::from_be_bytes(value), 48, kept_bits);
+        let octets = pseudonymized.to_be_bytes();
+
+        // The group bit must stay cleared, for the pseudonym to still be a unicast address
+        let pseudonym = MacAddr::new(
+            octets[10] & !0x01,
+            octets[11],
+            octets[12],
+            octets[13],
+            octets[14],
+            octets[15],
+        );
+        self.mac_addresses.insert(address, pseudonym);
+
+        pseudonym
+    }
+
+    /// Get the pseudonym of a DUID in hexadecimal: the one of its Ethernet address for a DUID-LLT
+    /// or DUID-LL, a keyed hash of the whole DUID otherwise
+    fn duid(&mut self, duid: &str) -> String {
+        let mut bytes: Vec<u8> = (0..duid.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(duid.get(i..i + 2)?, 16).ok())
+            .collect::<Option<_>>()
+            .unwrap_or_default();
+        let field = |offset: usize| u16::from_be_bytes([bytes[offset], bytes[offset + 1]]);
+        let header = (bytes.len() >= 4).then(|| (field(0), field(2)));
+
+        let address_offset = match (header, bytes.len()) {
+            (Some((DUID_LLT, ETHERNET_HARDWARE_TYPE)), 14) => 8,
+            (Some((DUID_LL, ETHERNET_HARDWARE_TYPE)), 10) => 4,
+            _ => return format!("{:016x}", self.key.hash_one(duid)),
+        };
+        let octets = &mut bytes[address_offset..];
+        let address = MacAddr::new(
+            octets[0], octets[1], octets[2], octets[3], octets[4], octets[5],
+        );
+        octets.copy_from_slice(&self.mac(address).octets());
+
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Flip each bit of a `bits`-bit value after the first `kept_bits` ones, according to the
+    /// keyed hash of the bits preceding it
+    fn pseudonymize(&self, value: u128, bits: u32, kept_bits: u32) -> u128 {
+        let mut pseudonym = 0;
+
+        for i in 0..bits {
+            let bit = (value >> (bits - 1 - i)) & 1;
+            let flip = match i < kept_bits {
+                true => 0,
+                false => {
+                    let prefix = value.checked_shr(bits - i).unwrap_or(0);
+                    (self.key.hash_one((bits, i, prefix)) & 1) as u128
+                }
+            };
+
+            pseudonym = (pseudonym << 1) | (bit ^ flip);
+        }
+
+        pseudonym
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::util::MacAddr;
+    use simple_dns::{
+        rdata::{RData, A},
+        Name, Packet as DnsPacket, Question, ResourceRecord, CLASS, TYPE,
+    };
+
+    use super::Anonymizer;
+    use crate::dhcpv6::Dhcpv6Message;
+    use crate::http::handle_http_packet;
+    use crate::nbns::tests::build_test_name_query_response;
+    use crate::nbns::NbnsMessage;
+    use crate::serializable_packet::application::{
+        address_to_reverse_name, reverse_name_to_address, CustomResourceData, HttpContentType,
+        SerializableDhcpv6Packet, SerializableDnsPacket, SerializableNbnsPacket,
+    };
+    use crate::serializable_packet::util::{get_dest_ip, get_source_ip, get_source_mac};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+    use crate::test_util::{build_test_ipv4_frame, build_test_udp_packet, CLIENT, SERVER};
+    use crate::{set_payload_retention, FlowContext, HttpPacketType};
+
+    #[test]
+    fn consistent_prefix_preserving_addresses() {
+        let mut anonymizer = Anonymizer::new(false);
+
+        let udp = IpNextHeaderProtocols::Udp;
+        let mut first = build_test_ipv4_frame(Ipv4Addr::new(192, 168, 1, 3), SERVER, udp, &[]);
+        let mut second = build_test_ipv4_frame(Ipv4Addr::new(192, 168, 1, 4), SERVER, udp, &[]);
+        anonymizer.anonymize(&mut first);
+        anonymizer.anonymize(&mut second);
+
+        // Same destination, same pseudonym
+        let destination = get_dest_ip(&first).unwrap();
+        assert_ne!(destination, "11.11.11.11");
+        assert_eq!(get_dest_ip(&second).unwrap(), destination);
+        assert_eq!(get_source_mac(&first), get_source_mac(&second));
+
+        // 192.168.1.3 and 192.168.1.4 share their first 29 bits
+        let first_source: Ipv4Addr = get_source_ip(&first).unwrap().parse().unwrap();
+        let second_source: Ipv4Addr = get_source_ip(&second).unwrap().parse().unwrap();
+        let common = u32::from(first_source) ^ u32::from(second_source);
+        assert_eq!(common.leading_zeros(), 29);
+    }
+
+    #[test]
+    fn ipv6_and_mac_pseudonyms() {
+        let mut anonymizer = Anonymizer::new(true);
+
+        let first = anonymizer.ip(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)));
+        let second = anonymizer.ip(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 1)));
+        match (first, second) {
+            (IpAddr::V6(first), IpAddr::V6(second)) => {
+                let common = u128::from(first) ^ u128::from(second);
+                assert_eq!(common.leading_zeros(), 63);
+            }
+            _ => unreachable!(),
+        }
+
+        let mac = anonymizer.mac(MacAddr::new(0x00, 0x1b, 0x21, 0x12, 0x34, 0x56));
+        assert_eq!(&mac.octets()[..3], &[0x00, 0x1b, 0x21]);
+        assert_eq!(anonymizer.mac(MacAddr::broadcast()), MacAddr::broadcast());
+    }
+
+    #[test]
+    fn payload_copies_dropped() {
+        set_payload_retention(true);
+        // Inner address in the payload
+        let mut packet = build_test_udp_packet((CLIENT, 4444), (SERVER, 9999), &[10, 10, 10, 10]);
+        set_payload_retention(false);
+        Anonymizer::new(false).anonymize(&mut packet);
+
+        match packet.get_network_layer_packet() {
+            Some(SerializablePacket::Ipv4Packet(ipv4_packet)) => {
+                assert!(ipv4_packet.payload.is_empty())
+            }
+            _ => unreachable!(),
+        }
+        match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::UdpPacket(udp_packet)) => {
+                assert!(udp_packet.payload.is_empty())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn dns_reverse_lookup_names() {
+        let ipv4_address = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let ipv6_address = IpAddr::V6("2001:db8::567:89ab".parse().unwrap());
+        let ipv4_name = address_to_reverse_name(ipv4_address);
+        let ipv6_name = address_to_reverse_name(ipv6_address);
+        assert_eq!(ipv4_name, "1.0.0.10.in-addr.arpa");
+        assert_eq!(reverse_name_to_address(&ipv6_name), Some(ipv6_address));
+
+        let mut reply = DnsPacket::new_reply(0x1234);
+        for name in [&ipv4_name, &ipv6_name] {
+            reply.questions.push(Question::new(
+                Name::new_unchecked(name),
+                TYPE::PTR.into(),
+                CLASS::IN.into(),
+                false,
+            ));
+        }
+        reply.answers.push(ResourceRecord::new(
+            Name::new_unchecked(&ipv4_name),
+            CLASS::IN,
+            10,
+            RData::PTR(Name::new_unchecked("host.example.com").into()),
+        ));
+        let bytes = reply.build_bytes_vec().unwrap();
+        let mut packet = ParsedPacket::new(0);
+        packet.set_application_layer_packet(Some(SerializablePacket::DnsPacket(
+            SerializableDnsPacket::from(&dns_parser::Packet::parse(&bytes).unwrap()),
+        )));
+
+        let mut anonymizer = Anonymizer::new(false);
+        anonymizer.anonymize(&mut packet);
+
+        match packet.get_application_layer_packet() {
+            Some(SerializablePacket::DnsPacket(dns_packet)) => {
+                for (question, address) in dns_packet
+                    .questions
+                    .iter()
+                    .zip([ipv4_address, ipv6_address])
+                {
+                    let pseudonym = anonymizer.ip(address);
+                    assert_eq!(question.reverse_address, Some(pseudonym));
+                    assert_eq!(question.query_name, address_to_reverse_name(pseudonym));
+                }
+                assert_eq!(
+                    dns_packet.answers[0].name,
+                    dns_packet.questions[0].query_name
+                );
+                let display = dns_packet.to_string();
+                assert!(!display.contains(&ipv4_name) && !display.contains(&ipv6_name));
+                assert!(!display.contains("10.0.0.1"));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn doh_message() {
+        let address = Ipv4Addr::new(93, 184, 216, 34);
+        let mut reply = DnsPacket::new_reply(0);
+        reply.answers.push(ResourceRecord::new(
+            Name::new_unchecked("example.com"),
+            CLASS::IN,
+            60,
+            RData::A(A::from(address)),
+        ));
+        let dns_message = reply.build_bytes_vec().unwrap();
+        let response = [
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/dns-message\r\n\
+                Content-Length: {}\r\n\r\n",
+                dns_message.len()
+            )
+            .as_bytes(),
+            &dns_message,
+        ]
+        .concat();
+
+        let mut packet = ParsedPacket::new(0);
+        handle_http_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                80,
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+            ),
+            HttpPacketType::Response,
+            false,
+            &response,
+            &mut packet,
+        );
+
+        let mut anonymizer = Anonymizer::new(false);
+        anonymizer.anonymize(&mut packet);
+
+        match packet.get_application_layer_packet() {
+            Some(SerializablePacket::HttpResponsePacket(http_packet)) => {
+                let dns_message = http_packet.dns_message.as_ref().unwrap();
+                match &dns_message.answers[0].data {
+                    CustomResourceData::A(a) => assert_eq!(a.address, anonymizer.ipv4(address)),
+                    _ => unreachable!(),
+                }
+                assert!(matches!(http_packet.payload, HttpContentType::None));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn nbns_addresses() {
+        let address = Ipv4Addr::new(192, 168, 1, 10);
+        let response = build_test_name_query_response(address);
+        let message = NbnsMessage::parse(&response).unwrap();
+        let mut packet = ParsedPacket::new(0);
+        packet.set_application_layer_packet(Some(SerializablePacket::NbnsPacket(
+            SerializableNbnsPacket::from(&message),
+        )));
+
+        let mut anonymizer = Anonymizer::new(false);
+        anonymizer.anonymize(&mut packet);
+
+        match packet.get_application_layer_packet() {
+            Some(SerializablePacket::NbnsPacket(nbns_packet)) => {
+                assert_ne!(nbns_packet.addresses, [address]);
+                assert_eq!(nbns_packet.addresses, [anonymizer.ipv4(address)]);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn dhcpv6_identifiers_and_addresses() {
+        let client_mac = MacAddr::new(0x00, 0x11, 0x22, 0x33, 0x44, 0x55);
+        let address: Ipv6Addr = "2001:db8::1:2".parse().unwrap();
+        let dns_server: Ipv6Addr = "2001:db8::53".parse().unwrap();
+
+        // Client DUID-LLT of the MAC address, server DUID-EN
+        let mut reply = vec![7, 0x12, 0x34, 0x56, 0x00, 0x01, 0x00, 0x0e];
+        reply.extend_from_slice(&[0x00, 0x01, 0x00, 0x01, 0x2a, 0x3b, 0x4c, 0x5d]);
+        reply.extend_from_slice(&client_mac.octets());
+        reply.extend_from_slice(&[0x00, 0x02, 0x00, 0x0a, 0x00, 0x02, 0x00, 0x00, 0x00, 0x09]);
+        reply.extend_from_slice(&[0x0a, 0x0b, 0x0c, 0x0d]);
+        reply.extend_from_slice(&[0x00, 0x03, 0x00, 0x28, 0x00, 0x00, 0x00, 0x01]);
+        reply.extend_from_slice(&[0x00; 8]);
+        reply.extend_from_slice(&[0x00, 0x05, 0x00, 0x18]);
+        reply.extend_from_slice(&address.octets());
+        reply.extend_from_slice(&[0x00; 8]);
+        reply.extend_from_slice(&[0x00, 0x17, 0x00, 0x10]);
+        reply.extend_from_slice(&dns_server.octets());
+
+        let message = Dhcpv6Message::parse(&reply).unwrap();
+        let mut packet = ParsedPacket::new(0);
+        packet.set_application_layer_packet(Some(SerializablePacket::Dhcpv6Packet(
+            SerializableDhcpv6Packet::new(&message, reply.len()),
+        )));
+
+        let mut anonymizer = Anonymizer::new(false);
+        anonymizer.anonymize(&mut packet);
+
+        match packet.get_application_layer_packet() {
+            Some(SerializablePacket::Dhcpv6Packet(dhcpv6_packet)) => {
+                let pseudonym: String = anonymizer
+                    .mac(client_mac)
+                    .octets()
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect();
+                assert_eq!(
+                    dhcpv6_packet.client_duid,
+                    Some(format!("000100012a3b4c5d{}", pseudonym))
+                );
+                let server_duid = dhcpv6_packet.server_duid.as_deref().unwrap();
+                assert!(!server_duid.contains("0a0b0c0d"));
+                assert_eq!(dhcpv6_packet.ia_na[0].addresses, [anonymizer.ipv6(address)]);
+                assert_ne!(dhcpv6_packet.ia_na[0].addresses, [address]);
+                assert_eq!(dhcpv6_packet.dns_servers, [anonymizer.ipv6(dns_server)]);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
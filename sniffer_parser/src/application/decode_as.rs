@@ -0,0 +1,275 @@
+//! Decode As overrides
+//!
+//! A rule forces a dissector on the packets of a transport protocol from or to a port, e.g.
+//! `tcp.port==9999,http`, or on the packets of a flow in either direction, its client endpoint
+//! first, e.g. `udp.flow==10.0.0.1:5000-10.0.0.2:6000,syslog`. The rules are consulted before the
+//! well-known ports, the first one selecting a packet winning
+
+use std::cell::RefCell;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+use crate::serializable_packet::util::{contains_sctp, contains_tcp, contains_udp};
+use crate::serializable_packet::ParsedPacket;
+
+use super::FlowContext;
+
+thread_local!(
+    static DECODE_AS_RULES: RefCell<Vec<DecodeAsRule>> = const { RefCell::new(vec![]) };
+);
+
+/// Transport protocol of the packets selected by a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeAsTransport {
+    Tcp,
+    Udp,
+    Sctp,
+}
+
+/// Packets of a transport protocol selected by a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeAsSelector {
+    /// Packets from or to a port, the server one
+    Port(u16),
+    /// Packets between a client and a server endpoint
+    Flow(SocketAddr, SocketAddr),
+}
+
+/// Application-layer dissectors a rule may force
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dissector {
+    Http,
+    Tls,
+    Dtls,
+    Quic,
+    Dns,
+    Smtp,
+    Pop3,
+    Imap,
+    Ldap,
+    Kerberos,
+    Stun,
+    Telnet,
+    Nbns,
+    WireGuard,
+    Gtp,
+    Syslog,
+    Modbus,
+    Dhcpv6,
+}
+
+/// Dissector names accepted by the rules, the protocol names of the filters
+const DISSECTORS: &[(&str, Dissector)] = &[
+    ("http", Dissector::Http),
+    ("tls", Dissector::Tls),
+    ("dtls", Dissector::Dtls),
+    ("quic", Dissector::Quic),
+    ("dns", Dissector::Dns),
+    ("smtp", Dissector::Smtp),
+    ("pop3", Dissector::Pop3),
+    ("imap", Dissector::Imap),
+    ("ldap", Dissector::Ldap),
+    ("kerberos", Dissector::Kerberos),
+    ("stun", Dissector::Stun),
+    ("telnet", Dissector::Telnet),
+    ("nbns", Dissector::Nbns),
+    ("wireguard", Dissector::WireGuard),
+    ("gtp", Dissector::Gtp),
+    ("syslog", Dissector::Syslog),
+    ("modbus", Dissector::Modbus),
+    ("dhcpv6", Dissector::Dhcpv6),
+];
+
+/// Dissector forced on the packets selected by a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeAsRule {
+    pub transport: DecodeAsTransport,
+    pub selector: DecodeAsSelector,
+    pub dissector: Dissector,
+}
+
+impl DecodeAsRule {
+    /// Check if the rule selects a packet, getting whether it is sent to the server side: the port
+    /// of the rule, or the second endpoint of its flow
+    fn select(&self, transport: DecodeAsTransport, flow: &FlowContext) -> Option<bool> {
+        if transport != self.transport {
+            return None;
+        }
+
+        match self.selector {
+            DecodeAsSelector::Port(port) => (flow.source_port == port || flow.dest_port == port)
+                .then_some(flow.dest_port == port),
+            DecodeAsSelector::Flow(client, server) => {
+                let source = SocketAddr::new(flow.source_ip, flow.source_port);
+                let dest = SocketAddr::new(flow.dest_ip, flow.dest_port);
+                match (source, dest) {
+                    _ if (source, dest) == (client, server) => Some(true),
+                    _ if (source, dest) == (server, client) => Some(false),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for DecodeAsRule {
+    type Err = String;
+
+    /// Parse a `<transport>.port==<port>,<dissector>` or
+    /// `<transport>.flow==<client>-<server>,<dissector>` rule, the transport being `tcp`, `udp` or
+    /// `sctp` and the endpoints `<ip>:<port>` (`[<ip>]:<port>` for IPv6)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid decode-as rule: {}", s);
+        let (selector, dissector) = s.rsplit_once(',').ok_or_else(invalid)?;
+        let (field, value) = selector.split_once("==").ok_or_else(invalid)?;
+        let (transport, field) = field.trim().split_once('.').ok_or_else(invalid)?;
+        let value = value.trim();
+
+        let transport = match transport {
+            "tcp" => DecodeAsTransport::Tcp,
+            "udp" => DecodeAsTransport::Udp,
+            "sctp" => DecodeAsTransport::Sctp,
+            _ => return Err(format!("unknown transport protocol: {}", transport)),
+        };
+        let selector = match field {
+            "port" => DecodeAsSelector::Port(
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid port: {}", value))?,
+            ),
+            "flow" => {
+                let (client, server) = value.split_once('-').ok_or_else(invalid)?;
+                let endpoint = |endpoint: &str| {
+                    endpoint
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("invalid endpoint: {}", endpoint))
+                };
+                DecodeAsSelector::Flow(endpoint(client)?, endpoint(server)?)
+            }
+            _ => return Err(format!("unknown decode-as field: {}", field)),
+        };
+        let dissector = DISSECTORS
+            .iter()
+            .find(|(name, _)| *name == dissector.trim())
+            .map(|&(_, dissector)| dissector)
+            .ok_or(format!("unknown dissector: {}", dissector))?;
+
+        Ok(DecodeAsRule {
+            transport,
+            selector,
+            dissector,
+        })
+    }
+}
+
+/// Set the rules forcing a dissector on some packets, in priority order (none by default)
+pub fn set_decode_as_rules(rules: Vec<DecodeAsRule>) {
+    DECODE_AS_RULES.with(|decode_as_rules| *decode_as_rules.borrow_mut() = rules);
+}
+
+/// Get the dissector forced on a packet by the first rule selecting it, with whether the packet
+/// is sent to the server side
+pub(crate) fn decode_as_dissector(
+    flow: &FlowContext,
+    parsed_packet: &ParsedPacket,
+) -> Option<(Dissector, bool)> {
+    let transport = match parsed_packet {
+        packet if contains_tcp(packet) => DecodeAsTransport::Tcp,
+        packet if contains_udp(packet) => DecodeAsTransport::Udp,
+        packet if contains_sctp(packet) => DecodeAsTransport::Sctp,
+        _ => return None,
+    };
+
+    DECODE_AS_RULES.with(|rules| {
+        rules
+            .borrow()
+            .iter()
+            .find_map(|rule| Some((rule.dissector, rule.select(transport, flow)?)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use pnet::packet::tcp::{MutableTcpPacket, TcpFlags};
+
+    use super::{
+        set_decode_as_rules, DecodeAsRule, DecodeAsSelector, DecodeAsTransport, Dissector,
+    };
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+    use crate::transport::handle_tcp_packet;
+
+    #[test]
+    fn http_forced_on_port() {
+        let request = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        assert!(tcp_packet(4444, 9999, request)
+            .get_application_layer_packet()
+            .is_none());
+
+        set_decode_as_rules(vec!["tcp.port==9999,http".parse().unwrap()]);
+        let parsed_packet = tcp_packet(5555, 9999, request);
+        set_decode_as_rules(vec![]);
+
+        match parsed_packet.get_application_layer_packet() {
+            Some(SerializablePacket::HttpRequestPacket(http_packet)) => {
+                assert_eq!(http_packet.method, "GET");
+                assert_eq!(http_packet.path, "/index.html");
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn decode_as_rules_parsed() {
+        assert_eq!(
+            "tcp.port==9999,http".parse(),
+            Ok(DecodeAsRule {
+                transport: DecodeAsTransport::Tcp,
+                selector: DecodeAsSelector::Port(9999),
+                dissector: Dissector::Http,
+            })
+        );
+        assert_eq!(
+            "udp.flow==10.0.0.1:5000-[::1]:6000,syslog".parse(),
+            Ok(DecodeAsRule {
+                transport: DecodeAsTransport::Udp,
+                selector: DecodeAsSelector::Flow(
+                    "10.0.0.1:5000".parse().unwrap(),
+                    "[::1]:6000".parse().unwrap()
+                ),
+                dissector: Dissector::Syslog,
+            })
+        );
+        assert!("tcp.port==9999".parse::<DecodeAsRule>().is_err());
+        assert!("icmp.port==9999,http".parse::<DecodeAsRule>().is_err());
+        assert!("tcp.port==99999,http".parse::<DecodeAsRule>().is_err());
+        assert!("tcp.flow==10.0.0.1-10.0.0.2,http"
+            .parse::<DecodeAsRule>()
+            .is_err());
+        assert!("tcp.port==9999,gopher".parse::<DecodeAsRule>().is_err());
+    }
+
+    ///////////////////// Utils
+
+    /// Build a TCP segment between 10.10.10.10 and 11.11.11.11, parsed
+    fn tcp_packet(source: u16, destination: u16, payload: &[u8]) -> ParsedPacket {
+        let mut tcp_buffer = vec![0u8; 20 + payload.len()];
+        let mut tcp_packet = MutableTcpPacket::new(&mut tcp_buffer).unwrap();
+        tcp_packet.set_source(source);
+        tcp_packet.set_destination(destination);
+        tcp_packet.set_data_offset(5);
+        tcp_packet.set_flags(TcpFlags::ACK | TcpFlags::PSH);
+        tcp_packet.set_payload(payload);
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_tcp_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+            &tcp_buffer,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+}
@@ -0,0 +1,317 @@
+//! DHCPv6 Packet parsing
+//!
+//! A DHCPv6 message between a client (port 546) and a server or relay agent (port 547) starts with
+//! its type on one byte and a transaction identifier on three bytes; relay agents prepend the hop
+//! count and the link and peer addresses instead of the identifier. Options follow, each one made
+//! of a code and a length on two big-endian bytes: the DUIDs identifying the client and the
+//! server, the non-temporary address associations (IA_NA) nesting their addresses as options, the
+//! recursive DNS servers...
+
+use std::net::Ipv6Addr;
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::SerializableDhcpv6Packet, ParsedPacket, SerializablePacket,
+};
+use crate::tlv::{Endianness, TlvParser};
+
+use super::FlowContext;
+
+/// DHCPv6 Message Types
+#[allow(non_snake_case)]
+pub mod Dhcpv6MessageTypes {
+    pub const SOLICIT: u8 = 1;
+    pub const ADVERTISE: u8 = 2;
+    pub const REQUEST: u8 = 3;
+    pub const CONFIRM: u8 = 4;
+    pub const RENEW: u8 = 5;
+    pub const REBIND: u8 = 6;
+    pub const REPLY: u8 = 7;
+    pub const RELEASE: u8 = 8;
+    pub const DECLINE: u8 = 9;
+    pub const RECONFIGURE: u8 = 10;
+    pub const INFORMATION_REQUEST: u8 = 11;
+    pub const RELAY_FORW: u8 = 12;
+    pub const RELAY_REPL: u8 = 13;
+}
+
+/// DHCPv6 Option Codes
+#[allow(non_snake_case)]
+pub mod Dhcpv6OptionCodes {
+    pub const CLIENT_ID: u16 = 1;
+    pub const SERVER_ID: u16 = 2;
+    pub const IA_NA: u16 = 3;
+    pub const IA_ADDR: u16 = 5;
+    pub const DNS_SERVERS: u16 = 23;
+}
+
+/// Header of a client or server message: type and transaction identifier
+const HEADER_LENGTH: usize = 4;
+/// Header of a relay agent message: type, hop count, link and peer addresses
+const RELAY_HEADER_LENGTH: usize = 34;
+/// IAID, T1 and T2 of an IA_NA, before its options
+const IA_NA_HEADER_LENGTH: usize = 12;
+/// Address, preferred and valid lifetimes of an IA Address, before its options
+const IA_ADDR_HEADER_LENGTH: usize = 24;
+
+/// Address leased in an IA_NA
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dhcpv6IaAddress {
+    pub address: Ipv6Addr,
+    pub preferred_lifetime: u32,
+    pub valid_lifetime: u32,
+}
+
+/// Identity Association for Non-temporary Addresses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dhcpv6IaNa {
+    pub iaid: u32,
+    /// Times after which the client contacts the server, then any server, to extend the leases
+    pub t1: u32,
+    pub t2: u32,
+    pub addresses: Vec<Dhcpv6IaAddress>,
+}
+
+/// Fields of a DHCPv6 message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dhcpv6Message {
+    pub message_type: u8,
+    /// Transaction identifier, absent from the relay agent messages
+    pub transaction_id: Option<u32>,
+    /// DUID of the Client Identifier option
+    pub client_id: Option<Vec<u8>>,
+    /// DUID of the Server Identifier option
+    pub server_id: Option<Vec<u8>>,
+    pub ia_na: Vec<Dhcpv6IaNa>,
+    pub dns_servers: Vec<Ipv6Addr>,
+    /// Codes of the options, in order
+    pub option_codes: Vec<u16>,
+}
+
+impl Dhcpv6Message {
+    /// Parse a DHCPv6 message, `None` if its header or one of its options is truncated
+    pub fn parse(packet: &[u8]) -> Option<Dhcpv6Message> {
+        let message_type = *packet.first()?;
+        let (transaction_id, options) = match message_type {
+            Dhcpv6MessageTypes::RELAY_FORW | Dhcpv6MessageTypes::RELAY_REPL => {
+                (None, packet.get(RELAY_HEADER_LENGTH..)?)
+            }
+            _ => {
+                let header = packet.get(..HEADER_LENGTH)?;
+                let transaction_id = u32::from_be_bytes([0, header[1], header[2], header[3]]);
+                (Some(transaction_id), &packet[HEADER_LENGTH..])
+            }
+        };
+
+        let mut message = Dhcpv6Message {
+            message_type,
+            transaction_id,
+            client_id: None,
+            server_id: None,
+            ia_na: vec![],
+            dns_servers: vec![],
+            option_codes: vec![],
+        };
+
+        for option in TlvParser::new(options, 2, 2, Endianness::Big) {
+            let option = option.ok()?;
+            let code = option.tlv_type as u16;
+            message.option_codes.push(code);
+
+            match code {
+                Dhcpv6OptionCodes::CLIENT_ID => message.client_id = Some(option.value.to_vec()),
+                Dhcpv6OptionCodes::SERVER_ID => message.server_id = Some(option.value.to_vec()),
+                Dhcpv6OptionCodes::IA_NA => message.ia_na.push(parse_ia_na(option.value)?),
+                Dhcpv6OptionCodes::DNS_SERVERS => {
+                    message.dns_servers = parse_addresses(option.value)?
+                }
+                _ => {}
+            }
+        }
+
+        Some(message)
+    }
+}
+
+/// Parse the header of an IA_NA and the IA Address options it nests
+fn parse_ia_na(value: &[u8]) -> Option<Dhcpv6IaNa> {
+    let header = value.get(..IA_NA_HEADER_LENGTH)?;
+    let mut ia_na = Dhcpv6IaNa {
+        iaid: read_u32(&header[0..4]),
+        t1: read_u32(&header[4..8]),
+        t2: read_u32(&header[8..12]),
+        addresses: vec![],
+    };
+
+    for option in TlvParser::new(&value[IA_NA_HEADER_LENGTH..], 2, 2, Endianness::Big) {
+        let option = option.ok()?;
+        if option.tlv_type as u16 != Dhcpv6OptionCodes::IA_ADDR {
+            continue;
+        }
+
+        let address = option.value.get(..IA_ADDR_HEADER_LENGTH)?;
+        let octets: [u8; 16] = address[..16].try_into().unwrap();
+        ia_na.addresses.push(Dhcpv6IaAddress {
+            address: Ipv6Addr::from(octets),
+            preferred_lifetime: read_u32(&address[16..20]),
+            valid_lifetime: read_u32(&address[20..24]),
+        });
+    }
+
+    Some(ia_na)
+}
+
+/// Parse a list of IPv6 addresses, `None` if its length is not a multiple of 16 bytes
+fn parse_addresses(value: &[u8]) -> Option<Vec<Ipv6Addr>> {
+    if !value.len().is_multiple_of(16) {
+        return None;
+    }
+
+    Some(
+        value
+            .chunks_exact(16)
+            .map(|octets| Ipv6Addr::from(<[u8; 16]>::try_from(octets).unwrap()))
+            .collect(),
+    )
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Build a DHCPv6 packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_dhcpv6_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    if let Some(dhcpv6_message) = Dhcpv6Message::parse(packet) {
+        debug!(
+            "DHCPv6 Packet: {}:{} > {}:{}; Type: {}, Transaction ID: {:?}, Length: {}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            dhcpv6_message.message_type,
+            dhcpv6_message.transaction_id,
+            packet.len(),
+        );
+
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::Dhcpv6Packet(
+            SerializableDhcpv6Packet::new(&dhcpv6_message, packet.len()),
+        )));
+    } else {
+        debug!("Malformed DHCPv6 Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed DHCPv6 Packet".to_string(),
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv6Addr};
+
+    use super::{handle_dhcpv6_packet, Dhcpv6Message, Dhcpv6MessageTypes, FlowContext};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    /// DUID-LLT: hardware type 1 (Ethernet), time and link-layer address
+    const CLIENT_DUID: [u8; 14] = [
+        0x00, 0x01, 0x00, 0x01, 0x2a, 0x3b, 0x4c, 0x5d, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55,
+    ];
+
+    #[test]
+    fn solicit_message() {
+        let mut solicit = vec![Dhcpv6MessageTypes::SOLICIT, 0x12, 0x34, 0x56];
+        // Client Identifier
+        solicit.extend_from_slice(&[0x00, 0x01, 0x00, 0x0e]);
+        solicit.extend_from_slice(&CLIENT_DUID);
+        // Elapsed Time
+        solicit.extend_from_slice(&[0x00, 0x08, 0x00, 0x02, 0x00, 0x00]);
+        // IA_NA without address, IAID 0x0a0b0c0d
+        solicit.extend_from_slice(&[0x00, 0x03, 0x00, 0x0c, 0x0a, 0x0b, 0x0c, 0x0d]);
+        solicit.extend_from_slice(&[0u8; 8]);
+
+        let message = Dhcpv6Message::parse(&solicit).unwrap();
+        assert_eq!(message.transaction_id, Some(0x123456));
+        assert_eq!(message.option_codes, [1, 8, 3]);
+        assert_eq!(message.ia_na[0].iaid, 0x0a0b0c0d);
+
+        match dhcpv6_packet(&solicit).get_application_layer_packet() {
+            Some(SerializablePacket::Dhcpv6Packet(dhcpv6_packet)) => {
+                assert_eq!(dhcpv6_packet.message_type, 1);
+                assert_eq!(dhcpv6_packet.message_type_name, "SOLICIT");
+                assert_eq!(
+                    dhcpv6_packet.client_duid.as_deref(),
+                    Some("000100012a3b4c5d001122334455")
+                );
+                assert_eq!(dhcpv6_packet.server_duid, None);
+                assert_eq!(dhcpv6_packet.length, 44);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn reply_addresses() {
+        let address: Ipv6Addr = "2001:db8::1:2".parse().unwrap();
+        let dns_server: Ipv6Addr = "2001:db8::53".parse().unwrap();
+
+        let mut reply = vec![Dhcpv6MessageTypes::REPLY, 0x12, 0x34, 0x56];
+        // IA_NA with an IA Address
+        reply.extend_from_slice(&[0x00, 0x03, 0x00, 0x28, 0x0a, 0x0b, 0x0c, 0x0d]);
+        reply.extend_from_slice(&[0x00, 0x00, 0x0e, 0x10, 0x00, 0x00, 0x15, 0x18]);
+        reply.extend_from_slice(&[0x00, 0x05, 0x00, 0x18]);
+        reply.extend_from_slice(&address.octets());
+        reply.extend_from_slice(&[0x00, 0x00, 0x1c, 0x20, 0x00, 0x00, 0x2a, 0x30]);
+        // DNS Recursive Name Server
+        reply.extend_from_slice(&[0x00, 0x17, 0x00, 0x10]);
+        reply.extend_from_slice(&dns_server.octets());
+
+        let message = Dhcpv6Message::parse(&reply).unwrap();
+        let ia_na = &message.ia_na[0];
+        assert_eq!((ia_na.t1, ia_na.t2), (3600, 5400));
+        assert_eq!(ia_na.addresses[0].address, address);
+        assert_eq!(ia_na.addresses[0].preferred_lifetime, 7200);
+        assert_eq!(ia_na.addresses[0].valid_lifetime, 10800);
+        assert_eq!(message.dns_servers, [dns_server]);
+    }
+
+    #[test]
+    fn malformed_dhcpv6_packet() {
+        let mut solicit = vec![Dhcpv6MessageTypes::SOLICIT, 0x12, 0x34, 0x56];
+        solicit.extend_from_slice(&[0x00, 0x01, 0x00, 0x0e]);
+        solicit.extend_from_slice(&CLIENT_DUID);
+
+        for packet in [&solicit[..2], &solicit[..12]] {
+            match dhcpv6_packet(packet).get_application_layer_packet() {
+                Some(SerializablePacket::MalformedPacket(str)) => {
+                    assert_eq!(str, "Malformed DHCPv6 Packet")
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    ///////////////////// Utils
+
+    fn dhcpv6_packet(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_dhcpv6_packet(
+            &FlowContext::new(
+                IpAddr::V6("fe80::211:22ff:fe33:4455".parse().unwrap()),
+                546,
+                IpAddr::V6("ff02::1:2".parse().unwrap()),
+                547,
+            ),
+            payload,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+}
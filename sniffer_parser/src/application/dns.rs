@@ -1,24 +1,45 @@
 //! DNS Packet parsing
 
-use dns_parser::Packet as DnsPacket;
+use dns_parser::{Packet as DnsPacket, QueryType, Question, RData, ResourceRecord};
 use log::debug;
-use std::net::IpAddr;
 
 use crate::serializable_packet::{
-    application::SerializableDnsPacket, ParsedPacket, SerializablePacket,
+    application::{MdnsService, SerializableDnsPacket},
+    ParsedPacket, SerializablePacket,
 };
 
+use super::hint::looks_like_dns;
+use super::FlowContext;
+
+/// Length from which a label is unusually long (at most 63)
+const LONG_LABEL_LENGTH: usize = 40;
+/// Length from which the subdomain of a query name is long enough to measure its entropy
+const ENTROPY_MIN_LENGTH: usize = 16;
+/// Entropy of the subdomain characters, in bits per character, from which it looks random
+const HIGH_ENTROPY: f64 = 3.5;
+/// Length from which a label of letters and digits may be base32 or base64 encoded data
+const ENCODED_LABEL_MIN_LENGTH: usize = 16;
+/// Minimum share of digits of an encoded-looking label, rare in the words of a host name
+const ENCODED_LABEL_MIN_DIGITS: f64 = 0.1;
+/// Name of the PTR records enumerating the service types of a network, not service instances
+const SERVICE_TYPE_ENUMERATION: &str = "_services._dns-sd._udp.local";
+const HEADER_LENGTH: usize = 12;
+/// Shortest question: root name, type and class
+const MIN_QUESTION_LENGTH: usize = 5;
+/// Shortest resource record: root name, type, class, TTL and data length
+const MIN_RECORD_LENGTH: usize = 11;
+
 /// Build a DNS packet from a transport-layer packet, save it in a Parsed Packet
-pub fn handle_dns_packet(
-    source_ip: IpAddr,
-    source_port: u16,
-    dest_ip: IpAddr,
-    dest_port: u16,
-    packet: &[u8],
-    parsed_packet: &mut ParsedPacket,
-) {
-    if let Ok(dns_packet) = 
-        DnsPacket::parse(packet) {
+pub fn handle_dns_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    if let Ok(dns_packet) = parse_dns_message(packet) {
         debug!(
             "DNS Packet: {}:{} > {}:{}; ID: {}, Questions: {}, Answers: {}, Authority: {}, Additional: {}",
             source_ip,
@@ -43,85 +64,313 @@ pub fn handle_dns_packet(
     }
 }
 
+/// Build a Multicast DNS packet from a transport-layer packet, with the service instances it
+/// announces, save it in a Parsed Packet
+pub fn handle_mdns_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    if let Ok(dns_packet) = parse_dns_message(packet) {
+        let mdns_services = mdns_services(&dns_packet);
+        debug!(
+            "mDNS Packet: {}:{} > {}:{}; ID: {}, Questions: {}, Answers: {}, Services: {}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            dns_packet.header.id,
+            dns_packet.header.questions,
+            dns_packet.header.answers,
+            mdns_services.len(),
+        );
+
+        let mut serializable_packet = SerializableDnsPacket::from(&dns_packet);
+        serializable_packet.mdns_services = Some(mdns_services);
+        parsed_packet
+            .set_application_layer_packet(Some(SerializablePacket::DnsPacket(serializable_packet)));
+    } else {
+        debug!("Malformed mDNS Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed mDNS Packet".to_string(),
+        )));
+    }
+}
+
+/// Group the records of a Multicast DNS message by service instance (DNS-SD): the instances
+/// pointed to by a PTR record or owning a SRV record, with the target and port of their SRV
+/// record and the key/value pairs of their TXT record
+pub(crate) fn mdns_services(dns_packet: &DnsPacket) -> Vec<MdnsService> {
+    let records: Vec<&ResourceRecord> = dns_packet
+        .answers
+        .iter()
+        .chain(&dns_packet.nameservers)
+        .chain(&dns_packet.additional)
+        .collect();
+
+    let mut services: Vec<MdnsService> = vec![];
+    for record in &records {
+        let instance = match &record.data {
+            RData::PTR(ptr) if is_service_name(&record.name.to_string()) => ptr.0.to_string(),
+            RData::SRV(_) => record.name.to_string(),
+            _ => continue,
+        };
+        let service_type = match instance.split_once('.') {
+            Some((_, service_type)) if is_service_name(service_type) => service_type.to_string(),
+            _ => continue,
+        };
+        if services
+            .iter()
+            .all(|service| !service.instance.eq_ignore_ascii_case(&instance))
+        {
+            services.push(MdnsService {
+                instance,
+                service_type,
+                target: None,
+                port: None,
+                txt: vec![],
+            });
+        }
+    }
+
+    for service in &mut services {
+        for record in &records {
+            if !record
+                .name
+                .to_string()
+                .eq_ignore_ascii_case(&service.instance)
+            {
+                continue;
+            }
+            match &record.data {
+                RData::SRV(srv) => {
+                    service.target = Some(srv.target.to_string());
+                    service.port = Some(srv.port);
+                }
+                RData::TXT(txt) => {
+                    service.txt = txt
+                        .iter()
+                        .filter(|string| !string.is_empty())
+                        .map(|string| {
+                            let string = String::from_utf8_lossy(string);
+                            match string.split_once('=') {
+                                Some((key, value)) => (key.to_string(), value.to_string()),
+                                None => (string.to_string(), String::new()),
+                            }
+                        })
+                        .collect()
+                }
+                _ => {}
+            }
+        }
+    }
+
+    services
+}
+
+/// Check if a name is a DNS-SD service type or subtype, e.g. `_http._tcp.local`
+fn is_service_name(name: &str) -> bool {
+    name.starts_with('_') && !name.eq_ignore_ascii_case(SERVICE_TYPE_ENUMERATION)
+}
+
+/// Check if a payload received on another port than the DNS one is a DNS message: shaped like a
+/// query or response with a single question, and parsed as such
+pub(crate) fn is_dns_message(payload: &[u8]) -> bool {
+    looks_like_dns(payload) && parse_dns_message(payload).is_ok()
+}
+
+/// Parse a DNS message, rejecting it when the bytes following its header cannot hold the
+/// questions and records it counts: the parser reserves room for all of them upfront
+pub(crate) fn parse_dns_message(payload: &[u8]) -> Result<DnsPacket<'_>, dns_parser::Error> {
+    let header = payload
+        .get(..HEADER_LENGTH)
+        .ok_or(dns_parser::Error::HeaderTooShort)?;
+    let count = |offset: usize| u16::from_be_bytes([header[offset], header[offset + 1]]) as usize;
+    let min_length =
+        count(4) * MIN_QUESTION_LENGTH + (count(6) + count(8) + count(10)) * MIN_RECORD_LENGTH;
+    if min_length > payload.len() - HEADER_LENGTH {
+        return Err(dns_parser::Error::UnexpectedEOF);
+    }
+
+    DnsPacket::parse(payload)
+}
+
+/// Score, from 0 to 1, the likelihood that DNS queries tunnel data (heuristic), with the reasons
+/// found: long or high-entropy subdomain labels, encoded-looking labels, TXT or NULL queries. The
+/// score of a packet is the highest score of its questions
+pub(crate) fn tunneling_indicators(questions: &[Question]) -> (f32, Vec<String>) {
+    questions
+        .iter()
+        .map(question_tunneling_indicators)
+        .max_by(|(score, _), (other, _)| score.total_cmp(other))
+        .unwrap_or((0.0, vec![]))
+}
+
+/// Score the tunneling indicators of a single question
+fn question_tunneling_indicators(question: &Question) -> (f32, Vec<String>) {
+    let query_name = question.qname.to_string();
+    let labels: Vec<&str> = query_name.split('.').filter(|l| !l.is_empty()).collect();
+    // The labels left of the registered domain (e.g. `www` in `www.example.com`)
+    let subdomain = &labels[..labels.len().saturating_sub(2)];
+    let mut score = 0.0;
+    let mut reasons = vec![];
+
+    let longest_label = subdomain.iter().map(|label| label.len()).max().unwrap_or(0);
+    if longest_label >= LONG_LABEL_LENGTH {
+        score += 0.3;
+        reasons.push(format!("long label ({} chars)", longest_label));
+    }
+
+    let subdomain_chars = subdomain.concat();
+    if subdomain_chars.len() >= ENTROPY_MIN_LENGTH {
+        let entropy = shannon_entropy(subdomain_chars.as_bytes());
+        if entropy >= HIGH_ENTROPY {
+            score += 0.3;
+            reasons.push(format!("high entropy ({:.2} bits/char)", entropy));
+        }
+    }
+
+    if subdomain.iter().any(|label| is_encoded_label(label)) {
+        score += 0.2;
+        reasons.push("base32/base64-looking label".to_string());
+    }
+
+    if matches!(question.qtype, QueryType::TXT | QueryType::NULL) {
+        score += 0.2;
+        reasons.push(format!("{:?} query", question.qtype));
+    }
+
+    (f32::min(score, 1.0), reasons)
+}
+
+/// Get the Shannon entropy of a byte string, in bits per byte
+fn shannon_entropy(bytes: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in bytes {
+        counts[byte as usize] += 1;
+    }
+
+    let length = bytes.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let probability = count as f64 / length;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+/// Check if a label looks like base32 or base64 encoded data: long, made of letters and digits
+/// (and the `-`, `_` or `=` of the URL-safe alphabets), with a share of digits
+fn is_encoded_label(label: &str) -> bool {
+    if label.len() < ENCODED_LABEL_MIN_LENGTH
+        || !label
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'='))
+    {
+        return false;
+    }
+
+    let digits = label.bytes().filter(u8::is_ascii_digit).count();
+    digits as f64 / label.len() as f64 >= ENCODED_LABEL_MIN_DIGITS
+}
+
 #[cfg(test)]
 mod tests {
-    use std::net::{IpAddr, Ipv4Addr};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
     use dns_parser::{Packet as ParseDnsPacket, RData as ParseRData};
     use simple_dns::{
-        rdata::{RData as NewRData, A as NewA},
-        Name, Packet as NewDnsPacket, Question, ResourceRecord, CLASS, TYPE,
+        rdata::{RData as NewRData, A as NewA, PTR as NewPtr, SRV as NewSrv, TXT as NewTxt},
+        Name, Packet as NewDnsPacket, PacketFlag, Question, ResourceRecord, CLASS, RCODE, TYPE,
     };
 
     use crate::serializable_packet::{
-        application::CustomResourceData, ParsedPacket, SerializablePacket,
+        application::{
+            dns_class_to_string, dns_query_class_to_string, dns_query_type_to_string,
+            reverse_name_to_address, CustomResourceData,
+        },
+        ParsedPacket, SerializablePacket,
     };
 
-    use super::handle_dns_packet;
+    use super::{handle_dns_packet, handle_mdns_packet, FlowContext};
     const ID: u16 = 0x1234;
 
     #[test]
     fn empty_dns_query() {
-        let dns_packet = NewDnsPacket::new_query(ID, false);
+        let dns_packet = NewDnsPacket::new_query(ID);
         let mut parsed_packet = ParsedPacket::new(0);
 
         handle_dns_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            53,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                53,
+            ),
             dns_packet.build_bytes_vec().unwrap().as_slice(),
             &mut parsed_packet,
         );
 
         match parsed_packet.get_application_layer_packet().unwrap() {
             SerializablePacket::DnsPacket(new_dns_packet) => {
-                assert_eq!(new_dns_packet.header.id, dns_packet.header.id);
-                assert_eq!(new_dns_packet.header.query, dns_packet.header.query);
+                assert_eq!(new_dns_packet.header.id, dns_packet.id());
+                assert_eq!(
+                    new_dns_packet.header.query,
+                    !dns_packet.has_flags(PacketFlag::RESPONSE)
+                );
                 assert_eq!(
                     new_dns_packet.header.opcode,
-                    format!("{:?}", dns_packet.header.opcode)
+                    format!("QUERY ({})", dns_packet.opcode() as u16)
                 );
                 assert_eq!(
                     new_dns_packet.header.authoritative,
-                    dns_packet.header.authoritative_answer
+                    dns_packet.has_flags(PacketFlag::AUTHORITATIVE_ANSWER)
+                );
+                assert_eq!(
+                    new_dns_packet.header.truncated,
+                    dns_packet.has_flags(PacketFlag::TRUNCATION)
                 );
-                assert_eq!(new_dns_packet.header.truncated, dns_packet.header.truncated);
                 assert_eq!(
                     new_dns_packet.header.recursion_desired,
-                    dns_packet.header.recursion_desired
+                    dns_packet.has_flags(PacketFlag::RECURSION_DESIRED)
                 );
                 assert_eq!(
                     new_dns_packet.header.recursion_available,
-                    dns_packet.header.recursion_available
+                    dns_packet.has_flags(PacketFlag::RECURSION_AVAILABLE)
                 );
                 assert_eq!(
                     new_dns_packet.header.authenticated_data,
-                    dns_packet.header.authentic_data
+                    dns_packet.has_flags(PacketFlag::AUTHENTIC_DATA)
                 );
                 assert_eq!(
                     new_dns_packet.header.checking_disabled,
-                    dns_packet.header.checking_disabled
+                    dns_packet.has_flags(PacketFlag::CHECKING_DISABLED)
                 );
                 assert_eq!(
                     new_dns_packet.header.response_code,
-                    format!("{:?}", dns_packet.header.response_code)
+                    format!("NOERROR ({})", dns_packet.rcode() as u16)
                 );
                 assert_eq!(
-                    new_dns_packet.header.num_questions,
-                    dns_packet.header.questions_count
+                    new_dns_packet.header.num_questions as usize,
+                    dns_packet.questions.len()
                 );
                 assert_eq!(
-                    new_dns_packet.header.num_answers,
-                    dns_packet.header.answers_count
+                    new_dns_packet.header.num_answers as usize,
+                    dns_packet.answers.len()
                 );
                 assert_eq!(
-                    new_dns_packet.header.num_nameservers,
-                    dns_packet.header.name_servers_count
+                    new_dns_packet.header.num_nameservers as usize,
+                    dns_packet.name_servers.len()
                 );
                 assert_eq!(
-                    new_dns_packet.header.num_additional,
-                    dns_packet.header.additional_records_count
+                    new_dns_packet.header.num_additional as usize,
+                    dns_packet.additional_records.len()
                 );
 
                 assert_eq!(
@@ -141,7 +390,7 @@ mod tests {
 
     #[test]
     fn dns_query_with_some_questions() {
-        let mut dns_packet = NewDnsPacket::new_query(ID, false);
+        let mut dns_packet = NewDnsPacket::new_query(ID);
         let question = Question::new(
             Name::new_unchecked("_srv._udp.local"),
             TYPE::TXT.into(),
@@ -150,15 +399,16 @@ mod tests {
         );
 
         dns_packet.questions.push(question);
-        dns_packet.header.questions_count = 1;
 
         let dns_packet_bytes = dns_packet.build_bytes_vec().unwrap();
         let mut parsed_packet = ParsedPacket::new(0);
         handle_dns_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            53,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                53,
+            ),
             dns_packet_bytes.as_slice(),
             &mut parsed_packet,
         );
@@ -179,11 +429,11 @@ mod tests {
                 );
                 assert_eq!(
                     new_question.query_type,
-                    format!("{:?}", dns_packet.questions[0].qtype)
+                    dns_query_type_to_string(dns_packet.questions[0].qtype)
                 );
                 assert_eq!(
                     new_question.query_class,
-                    format!("{:?}", dns_packet.questions[0].qclass)
+                    dns_query_class_to_string(dns_packet.questions[0].qclass)
                 );
             }
             _ => unreachable!(),
@@ -201,15 +451,16 @@ mod tests {
         );
 
         dns_packet.answers.push(resource);
-        dns_packet.header.answers_count = 1;
 
         let dns_packet_bytes = dns_packet.build_bytes_vec().unwrap();
         let mut parsed_packet = ParsedPacket::new(0);
         handle_dns_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            53,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                53,
+            ),
             dns_packet_bytes.as_slice(),
             &mut parsed_packet,
         );
@@ -225,7 +476,10 @@ mod tests {
                     new_answer.multicast_unique,
                     dns_packet.answers[0].multicast_unique
                 );
-                assert_eq!(new_answer.class, format!("{:?}", dns_packet.answers[0].cls));
+                assert_eq!(
+                    new_answer.class,
+                    dns_class_to_string(dns_packet.answers[0].cls)
+                );
                 assert_eq!(new_answer.ttl, dns_packet.answers[0].ttl);
 
                 let new_answer_data = &new_dns_packet.answers[0].data;
@@ -252,15 +506,16 @@ mod tests {
         );
 
         dns_packet.additional_records.push(resource);
-        dns_packet.header.additional_records_count = 1;
 
         let dns_packet_bytes = dns_packet.build_bytes_vec().unwrap();
         let mut parsed_packet = ParsedPacket::new(0);
         handle_dns_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            53,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                53,
+            ),
             dns_packet_bytes.as_slice(),
             &mut parsed_packet,
         );
@@ -281,7 +536,7 @@ mod tests {
                 );
                 assert_eq!(
                     new_additional.class,
-                    format!("{:?}", dns_packet.additional[0].cls)
+                    dns_class_to_string(dns_packet.additional[0].cls)
                 );
                 assert_eq!(new_additional.ttl, dns_packet.additional[0].ttl);
 
@@ -309,15 +564,16 @@ mod tests {
         );
 
         dns_packet.name_servers.push(resource);
-        dns_packet.header.name_servers_count = 1;
 
         let dns_packet_bytes = dns_packet.build_bytes_vec().unwrap();
         let mut parsed_packet = ParsedPacket::new(0);
         handle_dns_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            53,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                53,
+            ),
             dns_packet_bytes.as_slice(),
             &mut parsed_packet,
         );
@@ -341,7 +597,7 @@ mod tests {
                 );
                 assert_eq!(
                     new_nameserver.class,
-                    format!("{:?}", dns_packet.nameservers[0].cls)
+                    dns_class_to_string(dns_packet.nameservers[0].cls)
                 );
                 assert_eq!(new_nameserver.ttl, dns_packet.nameservers[0].ttl);
 
@@ -358,15 +614,257 @@ mod tests {
         }
     }
 
+    #[test]
+    fn dns_mx_query_names() {
+        let mut dns_packet = NewDnsPacket::new_query(ID);
+        dns_packet.questions.push(Question::new(
+            Name::new_unchecked("example.com"),
+            TYPE::MX.into(),
+            CLASS::IN.into(),
+            false,
+        ));
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_dns_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                53,
+            ),
+            dns_packet.build_bytes_vec().unwrap().as_slice(),
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::DnsPacket(new_dns_packet) => {
+                assert_eq!(new_dns_packet.header.opcode, "QUERY (0)");
+                assert_eq!(new_dns_packet.questions[0].query_type, "MX (15)");
+                assert_eq!(new_dns_packet.questions[0].query_class, "IN (1)");
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn dns_nxdomain_reply_names() {
+        let mut dns_packet = NewDnsPacket::new_reply(ID);
+        *dns_packet.rcode_mut() = RCODE::NameError;
+        dns_packet.questions.push(Question::new(
+            Name::new_unchecked("missing.example.com"),
+            TYPE::A.into(),
+            CLASS::IN.into(),
+            false,
+        ));
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_dns_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                53,
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+            ),
+            dns_packet.build_bytes_vec().unwrap().as_slice(),
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::DnsPacket(new_dns_packet) => {
+                assert_eq!(new_dns_packet.header.response_code, "NXDOMAIN (3)");
+                assert_eq!(new_dns_packet.questions[0].query_type, "A (1)");
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn dns_ptr_reverse_addresses() {
+        let ipv6_name = "b.a.9.8.7.6.5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa";
+        let mut dns_packet = NewDnsPacket::new_reply(ID);
+        for name in ["1.0.0.10.in-addr.arpa", ipv6_name] {
+            dns_packet.questions.push(Question::new(
+                Name::new_unchecked(name),
+                TYPE::PTR.into(),
+                CLASS::IN.into(),
+                false,
+            ));
+        }
+        dns_packet.answers.push(ResourceRecord::new(
+            Name::new_unchecked("1.0.0.10.in-addr.arpa"),
+            CLASS::IN,
+            10,
+            NewRData::PTR(NewPtr(Name::new_unchecked("host.example.com"))),
+        ));
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_dns_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                53,
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+            ),
+            dns_packet.build_bytes_vec().unwrap().as_slice(),
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::DnsPacket(new_dns_packet) => {
+                let ipv4_address = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+                let ipv6_address = IpAddr::V6("2001:db8::567:89ab".parse::<Ipv6Addr>().unwrap());
+                assert_eq!(
+                    new_dns_packet.questions[0].reverse_address,
+                    Some(ipv4_address)
+                );
+                assert_eq!(
+                    new_dns_packet.questions[1].reverse_address,
+                    Some(ipv6_address)
+                );
+                assert_eq!(
+                    new_dns_packet.answers[0].reverse_address,
+                    Some(ipv4_address)
+                );
+                assert!(new_dns_packet.to_string().ends_with(&format!(
+                    "Reverse Lookups: 1.0.0.10.in-addr.arpa (10.0.0.1), {} (2001:db8::567:89ab)",
+                    ipv6_name
+                )));
+            }
+            _ => unreachable!(),
+        }
+
+        assert_eq!(reverse_name_to_address("0.10.in-addr.arpa"), None);
+        assert_eq!(reverse_name_to_address("256.0.0.10.in-addr.arpa"), None);
+        assert_eq!(reverse_name_to_address("1.0.0.10.example.com"), None);
+    }
+
+    #[test]
+    fn dns_tunneling_score() {
+        let parse_query = |name: &str, query_type: TYPE| {
+            let mut dns_packet = NewDnsPacket::new_query(ID);
+            dns_packet.questions.push(Question::new(
+                Name::new_unchecked(name),
+                query_type.into(),
+                CLASS::IN.into(),
+                false,
+            ));
+
+            let mut parsed_packet = ParsedPacket::new(0);
+            handle_dns_packet(
+                &FlowContext::new(
+                    IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                    4444,
+                    IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                    53,
+                ),
+                dns_packet.build_bytes_vec().unwrap().as_slice(),
+                &mut parsed_packet,
+            );
+            match parsed_packet.get_application_layer_packet().unwrap() {
+                SerializablePacket::DnsPacket(new_dns_packet) => new_dns_packet.clone(),
+                _ => unreachable!(),
+            }
+        };
+
+        let normal_query = parse_query("www.example.com", TYPE::A);
+        assert_eq!(normal_query.tunneling_score, 0.0);
+        assert!(normal_query.tunneling_reasons.is_empty());
+
+        // Base32 encoded data in a single label
+        let tunneling_query = parse_query(
+            "mfrggzdfmztwq2lknnwg23tpobyxe43uov3ho6dzpiytemzugu3dooby.t.example.com",
+            TYPE::TXT,
+        );
+        assert_eq!(tunneling_query.tunneling_score, 1.0);
+        assert_eq!(tunneling_query.tunneling_reasons.len(), 4);
+        assert_eq!(
+            tunneling_query.tunneling_reasons[0],
+            "long label (56 chars)"
+        );
+        assert_eq!(tunneling_query.tunneling_reasons[3], "TXT query");
+    }
+
+    #[test]
+    fn mdns_service_discovery() {
+        let mut mdns_packet = NewDnsPacket::new_reply(0);
+        mdns_packet.set_flags(PacketFlag::AUTHORITATIVE_ANSWER);
+        mdns_packet.answers.push(ResourceRecord::new(
+            Name::new_unchecked("_http._tcp.local"),
+            CLASS::IN,
+            4500,
+            NewRData::PTR(NewPtr(Name::new_unchecked("Printer._http._tcp.local"))),
+        ));
+        mdns_packet.additional_records.push(ResourceRecord::new(
+            Name::new_unchecked("Printer._http._tcp.local"),
+            CLASS::IN,
+            120,
+            NewRData::SRV(NewSrv {
+                priority: 0,
+                weight: 0,
+                port: 8080,
+                target: Name::new_unchecked("printer.local"),
+            }),
+        ));
+        mdns_packet.additional_records.push(ResourceRecord::new(
+            Name::new_unchecked("Printer._http._tcp.local"),
+            CLASS::IN,
+            4500,
+            NewRData::TXT(
+                NewTxt::new()
+                    .with_string("path=/status")
+                    .unwrap()
+                    .with_string("secure")
+                    .unwrap(),
+            ),
+        ));
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_mdns_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                5353,
+                IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)),
+                5353,
+            ),
+            &mdns_packet.build_bytes_vec().unwrap(),
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::DnsPacket(new_dns_packet) => {
+                let mdns_services = new_dns_packet.mdns_services.as_ref().unwrap();
+                assert_eq!(mdns_services.len(), 1);
+                assert_eq!(mdns_services[0].instance, "Printer._http._tcp.local");
+                assert_eq!(mdns_services[0].service_type, "_http._tcp.local");
+                assert_eq!(mdns_services[0].target.as_deref(), Some("printer.local"));
+                assert_eq!(mdns_services[0].port, Some(8080));
+                assert_eq!(
+                    mdns_services[0].txt,
+                    [
+                        ("path".to_string(), "/status".to_string()),
+                        ("secure".to_string(), String::new())
+                    ]
+                );
+                assert!(new_dns_packet.to_string().ends_with(
+                    "\n\tmDNS Services: Printer._http._tcp.local -> printer.local:8080 \
+                    [path=/status, secure]"
+                ));
+            }
+            _ => unreachable!(),
+        }
+    }
+
     #[test]
     fn malformed_dns_packet() {
         let malformed_dns_packet = [0, 1, 2, 3, 0, 1, 2, 3];
         let mut parsed_packet = ParsedPacket::new(0);
         handle_dns_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            53,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                53,
+            ),
             malformed_dns_packet.as_slice(),
             &mut parsed_packet,
         );
@@ -376,4 +874,29 @@ mod tests {
             _ => unreachable!(),
         };
     }
+
+    #[test]
+    fn inflated_record_counts() {
+        // Header of a response counting 65535 questions and answers, followed by a single question
+        let mut dns_packet = vec![0x12, 0x34, 0x81, 0x80, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0];
+        dns_packet.extend_from_slice(b"\x07example\x03com\x00\x00\x01\x00\x01");
+        assert!(super::parse_dns_message(&dns_packet).is_err());
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_dns_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                53,
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+            ),
+            &dns_packet,
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::MalformedPacket(str) => assert_eq!(str, "Malformed DNS Packet"),
+            _ => unreachable!(),
+        };
+    }
 }
@@ -0,0 +1,201 @@
+//! DTLS Packet parsing
+
+use log::debug;
+use tls_parser::{parse_dtls_raw_record, parse_dtls_record_with_header, TlsRecordType};
+
+use crate::serializable_packet::application::{
+    CustomDtlsMessage, CustomEncryptedMessage, SerializableDtlsPacket,
+};
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use super::FlowContext;
+
+/// DTLS Record Header Length (content type, version, epoch, sequence number, length)
+const DTLS_RECORD_HEADER_LENGTH: usize = 13;
+
+/// Check if a UDP payload starts with a DTLS record header
+///
+/// DTLS has no well-known port, so records are recognized by their content type (ChangeCipherSpec,
+/// Alert, Handshake, ApplicationData, Heartbeat) followed by a DTLS 1.0/1.2/1.3 version and a
+/// length fitting in the payload
+pub fn is_dtls_record(packet: &[u8]) -> bool {
+    if packet.len() < DTLS_RECORD_HEADER_LENGTH {
+        return false;
+    }
+
+    let content_type_known = (20..=24).contains(&packet[0]);
+    let version_known = packet[1] == 0xfe && matches!(packet[2], 0xff | 0xfd | 0xfc);
+    let length = u16::from_be_bytes([packet[11], packet[12]]) as usize;
+
+    content_type_known && version_known && DTLS_RECORD_HEADER_LENGTH + length <= packet.len()
+}
+
+/// Build a DTLS packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_dtls_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    let mut dtls_packet: Option<SerializableDtlsPacket> = None;
+    let mut remaining = packet;
+
+    while !remaining.is_empty() {
+        let (rem, record) = match parse_dtls_raw_record(remaining) {
+            Ok(result) => result,
+            Err(_) => break,
+        };
+
+        debug!(
+            "DTLS Record Packet: {}:{} > {}:{}; Version: {}, Record Type: {:?}, Epoch: {}, Len: {}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            record.header.version,
+            record.header.content_type,
+            record.header.epoch,
+            record.header.length
+        );
+
+        let current =
+            dtls_packet.get_or_insert_with(|| SerializableDtlsPacket::new(&record.header));
+
+        // Records of epoch 0 are sent before the handshake completes, later ones are encrypted
+        match (record.header.epoch, record.header.content_type) {
+            (0, TlsRecordType::ApplicationData) | (1.., _) => {
+                current
+                    .messages
+                    .push(CustomDtlsMessage::Encrypted(CustomEncryptedMessage::new(
+                        record.fragment,
+                        record.header.version,
+                        record.header.content_type,
+                    )));
+            }
+            _ => match parse_dtls_record_with_header(record.fragment, &record.header) {
+                Ok((_, messages)) => current
+                    .messages
+                    .extend(messages.iter().map(CustomDtlsMessage::from)),
+                Err(_) => break,
+            },
+        }
+
+        remaining = rem;
+    }
+
+    match dtls_packet {
+        Some(dtls_packet) if !dtls_packet.messages.is_empty() => {
+            parsed_packet
+                .set_application_layer_packet(Some(SerializablePacket::DtlsPacket(dtls_packet)));
+        }
+        _ => {
+            debug!("Malformed DTLS Packet");
+            parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed DTLS Packet".to_string(),
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use tls_parser::TlsVersion;
+
+    use super::{handle_dtls_packet, is_dtls_record, FlowContext};
+    use crate::serializable_packet::{
+        application::{CustomDtlsHandshakeMessage, CustomDtlsMessage},
+        ParsedPacket, SerializablePacket,
+    };
+
+    const CLIENT_HELLO: &[u8] = &[
+        // Record header: Handshake, DTLS 1.2, epoch 0, sequence number 0, length 85
+        0x16, 0xfe, 0xfd, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x55,
+        // Handshake header: ClientHello, length 73, message seq 0, fragment offset 0, fragment length 73
+        0x01, 0x00, 0x00, 0x49, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x49,
+        // Client version, random
+        0xfe, 0xfd, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c,
+        0x0d, 0x0e, 0x0f, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b,
+        0x1c, 0x1d, 0x1e, 0x1f,
+        // Session ID, cookie, cipher suites, compression methods
+        0x00, 0x00, 0x00, 0x02, 0xc0, 0x2b, 0x01, 0x00,
+        // Extensions: SNI (example.com), ALPN (h2)
+        0x00, 0x1d, 0x00, 0x00, 0x00, 0x10, 0x00, 0x0e, 0x00, 0x00, 0x0b, 0x65, 0x78, 0x61, 0x6d,
+        0x70, 0x6c, 0x65, 0x2e, 0x63, 0x6f, 0x6d, 0x00, 0x10, 0x00, 0x05, 0x00, 0x03, 0x02, 0x68,
+        0x32,
+    ];
+
+    const TLS_ALERT: &[u8] = &[0x15, 0x03, 0x01, 0x00, 0x02, 0x02, 0x46];
+
+    #[test]
+    fn dtls_record_detection() {
+        assert!(is_dtls_record(CLIENT_HELLO));
+        assert!(!is_dtls_record(TLS_ALERT));
+        assert!(!is_dtls_record(&CLIENT_HELLO[..20]));
+    }
+
+    #[test]
+    fn valid_client_hello_dtls_packet() {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_dtls_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                5684,
+            ),
+            CLIENT_HELLO,
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::DtlsPacket(new_dtls_packet) => {
+                assert_eq!(new_dtls_packet.version, format!("{}", TlsVersion::DTls12));
+                assert_eq!(new_dtls_packet.epoch, 0);
+                assert_eq!(new_dtls_packet.sequence_number, 0);
+                assert_eq!(new_dtls_packet.length, 85);
+                assert_eq!(new_dtls_packet.messages.len(), 1);
+
+                match &new_dtls_packet.messages[0] {
+                    CustomDtlsMessage::Handshake(CustomDtlsHandshakeMessage::ClientHello(
+                        new_message,
+                    )) => {
+                        assert_eq!(new_message.version, format!("{}", TlsVersion::DTls12));
+                        assert_eq!(new_message.random, (0..32).collect::<Vec<u8>>());
+                        assert_eq!(new_message.session_id, None);
+                        assert!(new_message.cookie.is_empty());
+                        assert_eq!(new_message.ciphers.len(), 1);
+                        assert!(new_message.extensions[0].contains("example.com"));
+                        assert_eq!(new_message.extensions[1], "ALPN: [\"h2\"]");
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_dtls_packet() {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_dtls_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                5684,
+            ),
+            &CLIENT_HELLO[..40],
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::MalformedPacket(str) => assert_eq!(str, "Malformed DTLS Packet"),
+            _ => unreachable!(),
+        };
+    }
+}
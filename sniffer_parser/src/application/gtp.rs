@@ -0,0 +1,236 @@
+//! GTP-U Packet parsing
+//!
+//! GTP-U carries the user traffic of mobile networks between the base stations and the core
+//! network. Its header holds the version, the message type, the length of what follows the first
+//! 8 bytes and the Tunnel Endpoint Identifier (TEID); when one of the E, S or PN flags is set, 4
+//! more bytes carry the sequence number, the N-PDU number and the type of the first extension
+//! header. A G-PDU message carries an IPv4 or IPv6 packet, parsed one encapsulation level deeper
+
+use log::debug;
+
+use crate::network::parse_tunneled_payload;
+use crate::serializable_packet::{
+    application::SerializableGtpPacket, ParsedPacket, SerializablePacket,
+};
+
+use super::FlowContext;
+
+/// GTP-U Message Types
+#[allow(non_snake_case)]
+pub mod GtpMessageTypes {
+    pub const ECHO_REQUEST: u8 = 1;
+    pub const ECHO_RESPONSE: u8 = 2;
+    pub const ERROR_INDICATION: u8 = 26;
+    pub const SUPPORTED_EXTENSION_HEADERS_NOTIFICATION: u8 = 31;
+    pub const END_MARKER: u8 = 254;
+    pub const G_PDU: u8 = 255;
+}
+
+/// Mandatory part of the header, before the optional fields
+const GTP_HEADER_LENGTH: usize = 8;
+/// Sequence number, N-PDU number and next extension header type
+const OPTIONAL_FIELDS_LENGTH: usize = 4;
+
+const PROTOCOL_TYPE_FLAG: u8 = 0x10;
+const EXTENSION_HEADER_FLAG: u8 = 0x04;
+const SEQUENCE_NUMBER_FLAG: u8 = 0x02;
+const N_PDU_NUMBER_FLAG: u8 = 0x01;
+
+/// Fields of a GTP-U header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GtpHeader {
+    pub version: u8,
+    pub message_type: u8,
+    /// Length of the message after the mandatory part of the header
+    pub length: u16,
+    pub teid: u32,
+    pub sequence_number: Option<u16>,
+}
+
+impl GtpHeader {
+    /// Parse the header of a GTP-U message, with its payload after the extension headers; `None`
+    /// if it is not a GTP-U version 1 message or if it is truncated
+    pub fn parse(packet: &[u8]) -> Option<(GtpHeader, &[u8])> {
+        if packet.len() < GTP_HEADER_LENGTH
+            || packet[0] >> 5 != 1
+            || packet[0] & PROTOCOL_TYPE_FLAG == 0
+        {
+            return None;
+        }
+
+        let flags = packet[0];
+        let length = u16::from_be_bytes([packet[2], packet[3]]);
+        let message = packet.get(..GTP_HEADER_LENGTH + length as usize)?;
+        let mut header = GtpHeader {
+            version: flags >> 5,
+            message_type: packet[1],
+            length,
+            teid: u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]),
+            sequence_number: None,
+        };
+
+        let mut offset = GTP_HEADER_LENGTH;
+        if flags & (EXTENSION_HEADER_FLAG | SEQUENCE_NUMBER_FLAG | N_PDU_NUMBER_FLAG) != 0 {
+            let optional_fields = message.get(offset..offset + OPTIONAL_FIELDS_LENGTH)?;
+            if flags & SEQUENCE_NUMBER_FLAG != 0 {
+                header.sequence_number =
+                    Some(u16::from_be_bytes([optional_fields[0], optional_fields[1]]));
+            }
+            offset += OPTIONAL_FIELDS_LENGTH;
+
+            // Each extension header counts its length in 4-byte units, ending with the type of
+            // the next one
+            let mut next_extension_type = optional_fields[3];
+            while flags & EXTENSION_HEADER_FLAG != 0 && next_extension_type != 0 {
+                let extension_length = *message.get(offset)? as usize * 4;
+                if extension_length == 0 {
+                    return None;
+                }
+                let extension = message.get(offset..offset + extension_length)?;
+                next_extension_type = extension[extension_length - 1];
+                offset += extension_length;
+            }
+        }
+
+        Some((header, &message[offset..]))
+    }
+}
+
+/// Build a GTP-U packet from a transport-layer packet, with the IP packet of a G-PDU, save it in a
+/// Parsed Packet
+pub fn handle_gtp_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    if let Some((gtp_header, payload)) = GtpHeader::parse(packet) {
+        debug!(
+            "GTP-U Packet: {}:{} > {}:{}; Type: {}, TEID: {:#010x}, Length: {}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            gtp_header.message_type,
+            gtp_header.teid,
+            gtp_header.length,
+        );
+
+        let mut gtp_packet = SerializableGtpPacket::from(&gtp_header);
+        if gtp_header.message_type == GtpMessageTypes::G_PDU {
+            gtp_packet.encapsulated = parse_tunneled_payload(payload).map(Box::new);
+        }
+
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::GtpPacket(gtp_packet)));
+    } else {
+        debug!("Malformed GTP-U Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed GTP-U Packet".to_string(),
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use pnet::packet::ip::IpNextHeaderProtocols;
+
+    use super::{handle_gtp_packet, FlowContext, GtpHeader, GtpMessageTypes};
+    use crate::serializable_packet::{MalformedReason, ParsedPacket, SerializablePacket};
+    use crate::test_util::{build_test_ipv4_packet, build_test_udp_datagram, CLIENT, SERVER};
+    use crate::{set_max_encapsulation_depth, DEFAULT_MAX_ENCAPSULATION_DEPTH};
+
+    #[test]
+    fn g_pdu_with_inner_ipv4() {
+        let g_pdu = build_test_g_pdu();
+        let (header, payload) = GtpHeader::parse(&g_pdu).unwrap();
+        assert_eq!(header.teid, 0x0badcafe);
+        assert_eq!(header.sequence_number, Some(7));
+        assert_eq!(payload.len(), 28);
+
+        match gtp_packet(&g_pdu).get_application_layer_packet() {
+            Some(SerializablePacket::GtpPacket(gtp_packet)) => {
+                assert_eq!(gtp_packet.message_type, GtpMessageTypes::G_PDU);
+                assert_eq!(gtp_packet.message_type_name, "G-PDU");
+                assert_eq!(gtp_packet.teid, 0x0badcafe);
+                match gtp_packet.encapsulated.as_deref() {
+                    Some(SerializablePacket::Ipv4Packet(ipv4_packet)) => {
+                        assert_eq!(ipv4_packet.source, Ipv4Addr::new(172, 16, 0, 1));
+                        assert_eq!(ipv4_packet.destination, Ipv4Addr::new(8, 8, 8, 8));
+                    }
+                    _ => unreachable!(),
+                }
+                assert!(gtp_packet.to_string().contains("Encapsulated IPv4 Packet"));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn g_pdu_max_encapsulation_depth() {
+        set_max_encapsulation_depth(0);
+        let parsed_packet = gtp_packet(&build_test_g_pdu());
+        set_max_encapsulation_depth(DEFAULT_MAX_ENCAPSULATION_DEPTH);
+
+        match parsed_packet.get_application_layer_packet() {
+            Some(SerializablePacket::GtpPacket(gtp_packet)) => {
+                match gtp_packet.encapsulated.as_deref() {
+                    Some(SerializablePacket::MalformedPacket(str)) => {
+                        assert_eq!(str, &MalformedReason::MaxDepthExceeded.to_string())
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_gtp_packet() {
+        let g_pdu = build_test_g_pdu();
+        let mut version_0 = g_pdu.clone();
+        version_0[0] &= 0x1f;
+        for packet in [&g_pdu[..30], &version_0[..]] {
+            match gtp_packet(packet).get_application_layer_packet() {
+                Some(SerializablePacket::MalformedPacket(str)) => {
+                    assert_eq!(str, "Malformed GTP-U Packet")
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    ///////////////////// Utils
+
+    /// Build a G-PDU with a sequence number, carrying an IPv4 packet with an empty UDP header
+    fn build_test_g_pdu() -> Vec<u8> {
+        let udp_datagram = build_test_udp_datagram(4444, 53, &[]);
+        let ip_packet = build_test_ipv4_packet(
+            Ipv4Addr::new(172, 16, 0, 1),
+            Ipv4Addr::new(8, 8, 8, 8),
+            64,
+            IpNextHeaderProtocols::Udp,
+            &udp_datagram,
+        );
+
+        let mut g_pdu = vec![0x32, GtpMessageTypes::G_PDU, 0x00, 32];
+        g_pdu.extend_from_slice(&0x0badcafeu32.to_be_bytes());
+        g_pdu.extend_from_slice(&[0x00, 0x07, 0x00, 0x00]);
+        g_pdu.extend_from_slice(&ip_packet);
+        g_pdu
+    }
+
+    fn gtp_packet(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_gtp_packet(
+            &FlowContext::new(IpAddr::V4(CLIENT), 2152, IpAddr::V4(SERVER), 2152),
+            payload,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+}
@@ -0,0 +1,165 @@
+//! Application protocol guesses
+//!
+//! UDP datagrams on ports without a parser keep an empty application layer; lightweight shape
+//! detectors still guess their protocol from the first bytes of the payload. A guess is only a
+//! hint shown on the UDP packet (e.g. `RTP?`), the payload is not parsed
+
+use std::ops::RangeInclusive;
+
+use super::quic::is_known_version;
+use super::wireguard::looks_like_wireguard;
+
+const DNS_HEADER_LENGTH: usize = 12;
+const RTP_HEADER_LENGTH: usize = 12;
+const QUIC_LONG_HEADER_MIN_LENGTH: usize = 7;
+
+/// Guess the application protocol of a UDP payload from its shape
+pub fn udp_protocol_hint(payload: &[u8]) -> Option<String> {
+    let protocol = if looks_like_dns(payload) {
+        "DNS"
+    } else if looks_like_quic(payload) {
+        "QUIC"
+    } else if looks_like_wireguard(payload) {
+        "WireGuard"
+    } else if looks_like_rtp(payload) {
+        "RTP"
+    } else {
+        return None;
+    };
+
+    Some(format!("{}?", protocol))
+}
+
+/// Check if a payload starts with a DNS header with a single question, whose name is well formed
+pub fn looks_like_dns(payload: &[u8]) -> bool {
+    has_dns_shape(payload, 1..=1)
+}
+
+/// Check if a payload starts with a plausible DNS header, whose first question name is well formed
+/// when it has questions: the confirmation of a DNS message received on the DNS port
+pub fn looks_like_dns_message(payload: &[u8]) -> bool {
+    has_dns_shape(payload, 0..=64)
+}
+
+/// Check if a payload starts with a DNS header with a number of questions in the range, the name
+/// of the first one well formed
+fn has_dns_shape(payload: &[u8], questions: RangeInclusive<u16>) -> bool {
+    let header = match payload.get(..DNS_HEADER_LENGTH) {
+        Some(header) => header,
+        None => return false,
+    };
+    let opcode = (header[2] >> 3) & 0x0f;
+    let reserved = header[3] & 0x40;
+    let count = |index: usize| u16::from_be_bytes([header[index], header[index + 1]]);
+    if opcode > 5
+        || reserved != 0
+        || !questions.contains(&count(4))
+        || (6..12).step_by(2).any(|i| count(i) > 64)
+    {
+        return false;
+    }
+    if count(4) == 0 {
+        return true;
+    }
+
+    // Question name as uncompressed labels, followed by its type and class
+    let mut offset = DNS_HEADER_LENGTH;
+    loop {
+        match payload.get(offset) {
+            Some(0) => return payload.len() >= offset + 5,
+            Some(&length) if length <= 63 => offset += 1 + length as usize,
+            _ => return false,
+        }
+    }
+}
+
+/// Check if a payload starts with a QUIC long header of a known version
+pub fn looks_like_quic(payload: &[u8]) -> bool {
+    match payload.get(..QUIC_LONG_HEADER_MIN_LENGTH) {
+        Some(header) => {
+            let version = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+            header[0] & 0xc0 == 0xc0 && is_known_version(version)
+        }
+        None => false,
+    }
+}
+
+/// Get the sequence number of the RTP header a payload starts with, if it looks like one
+pub fn rtp_sequence_number(payload: &[u8]) -> Option<u16> {
+    looks_like_rtp(payload).then(|| u16::from_be_bytes([payload[2], payload[3]]))
+}
+
+/// Check if a payload starts with an RTP version 2 header whose CSRC list and padding fit in it,
+/// with a payload type outside of the range conflicting with RTCP
+pub fn looks_like_rtp(payload: &[u8]) -> bool {
+    let header = match payload.get(..RTP_HEADER_LENGTH) {
+        Some(header) => header,
+        None => return false,
+    };
+    let version = header[0] >> 6;
+    let padding = header[0] & 0x20 != 0;
+    let csrc_count = (header[0] & 0x0f) as usize;
+    let payload_type = header[1] & 0x7f;
+
+    let header_length = RTP_HEADER_LENGTH + 4 * csrc_count;
+    let padding_length = match padding {
+        true => payload.last().copied().unwrap_or_default() as usize,
+        false => 0,
+    };
+
+    version == 2
+        && !(72..=76).contains(&payload_type)
+        && header_length + padding_length <= payload.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::udp_protocol_hint;
+
+    // RTP version 2, PCMU (payload type 0), sequence 1, timestamp 160, then 8 samples
+    const RTP_PACKET: &[u8] = &[
+        0x80, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xa0, 0x12, 0x34, 0x56, 0x78, 0xff, 0xfe, 0x7f,
+        0x7e, 0xff, 0xfe, 0x7f, 0x7e,
+    ];
+
+    // Query of example.com A
+    const DNS_QUERY: &[u8] = &[
+        0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 7, b'e', b'x',
+        b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0, 0x00, 0x01, 0x00, 0x01,
+    ];
+
+    #[test]
+    fn shaped_payloads() {
+        assert_eq!(udp_protocol_hint(RTP_PACKET).as_deref(), Some("RTP?"));
+        assert_eq!(udp_protocol_hint(DNS_QUERY).as_deref(), Some("DNS?"));
+        assert_eq!(
+            udp_protocol_hint(&[0xc3, 0x00, 0x00, 0x00, 0x01, 0x08, 0x00, 0x00]).as_deref(),
+            Some("QUIC?")
+        );
+        let mut wireguard_initiation = [0u8; 148];
+        wireguard_initiation[0] = 0x01;
+        assert_eq!(
+            udp_protocol_hint(&wireguard_initiation).as_deref(),
+            Some("WireGuard?")
+        );
+
+        // RTCP sender report, and an RTP header claiming more CSRCs than the payload holds
+        assert_eq!(
+            udp_protocol_hint(&[&[0x80, 0xc8][..], &RTP_PACKET[2..]].concat()),
+            None
+        );
+        assert_eq!(
+            udp_protocol_hint(&[&[0x8f][..], &RTP_PACKET[1..]].concat()),
+            None
+        );
+    }
+
+    #[test]
+    fn random_payload() {
+        let random = [
+            0x3f, 0x9a, 0x71, 0x04, 0xd2, 0x5e, 0x0b, 0xc8, 0x66, 0x13, 0xaf, 0x2d, 0x90, 0x47,
+        ];
+        assert_eq!(udp_protocol_hint(&random), None);
+        assert_eq!(udp_protocol_hint(&[]), None);
+    }
+}
@@ -0,0 +1,346 @@
+//! Kerberos Packet parsing
+//!
+//! Kerberos messages (RFC 4120) are DER-encoded, each one tagged with its application number.
+//! The KDC exchanges (AS and TGS requests and replies) and the errors are decoded enough to get
+//! the realm and the client and server principal names, along with the code of the errors; the
+//! encrypted parts are left out. Over TCP, the messages are prefixed with their 4-byte length
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::SerializableKerberosPacket, ParsedPacket, SerializablePacket,
+};
+
+use super::ldap::{read_element, read_integer, read_string, BerElement};
+use super::FlowContext;
+
+const TCP_LENGTH_PREFIX: usize = 4;
+const GENERAL_STRING: u8 = 0x1b;
+const SEQUENCE: u8 = 0x30;
+
+/// Kerberos Message Types, application tag numbers
+#[allow(non_snake_case)]
+pub mod KerberosMessageTypes {
+    pub const AS_REQ: u8 = 10;
+    pub const AS_REP: u8 = 11;
+    pub const TGS_REQ: u8 = 12;
+    pub const TGS_REP: u8 = 13;
+    pub const AP_REQ: u8 = 14;
+    pub const AP_REP: u8 = 15;
+    pub const KRB_ERROR: u8 = 30;
+}
+
+/// Decoded fields of a Kerberos message
+#[derive(Debug, Default)]
+pub struct KerberosMessage {
+    pub message_type: u8,
+    /// Realm of the request or of the error, of the client for the replies
+    pub realm: Option<String>,
+    pub cname: Option<Vec<String>>,
+    pub sname: Option<Vec<String>>,
+    pub error_code: Option<u32>,
+}
+
+/// Build a Kerberos packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_kerberos_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    if packet.is_empty() {
+        return;
+    }
+
+    if let Some(message) = parse_kerberos_message(packet) {
+        debug!(
+            "Kerberos Packet: {}:{} > {}:{}; Message type: {}, Realm: {:?}",
+            source_ip, source_port, dest_ip, dest_port, message.message_type, message.realm
+        );
+
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::KerberosPacket(
+            SerializableKerberosPacket::from(&message),
+        )));
+    } else {
+        debug!("Malformed Kerberos Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed Kerberos Packet".to_string(),
+        )));
+    }
+}
+
+/// Parse a Kerberos message, skipping the length prefix of the messages sent over TCP
+pub fn parse_kerberos_message(packet: &[u8]) -> Option<KerberosMessage> {
+    let packet = match packet.first() {
+        Some(tag) if is_message_tag(*tag) => packet,
+        _ => packet.get(TCP_LENGTH_PREFIX..)?,
+    };
+
+    let (_, message) = read_element(packet).ok()?;
+    if !is_message_tag(message.tag) {
+        return None;
+    }
+    let message_type = message.tag & 0x1f;
+    let (_, sequence) = read_element(message.content).ok()?;
+    let fields = read_fields(&sequence)?;
+    let field = |number: u8| {
+        fields
+            .iter()
+            .find(|(field_number, _)| *field_number == number)
+            .map(|(_, element)| element)
+    };
+
+    let mut kerberos_message = KerberosMessage {
+        message_type,
+        ..Default::default()
+    };
+    match message_type {
+        // KDC-REQ: the names are in the request body
+        KerberosMessageTypes::AS_REQ | KerberosMessageTypes::TGS_REQ => {
+            let body = read_fields(field(4)?)?;
+            for (number, element) in &body {
+                match number {
+                    1 => kerberos_message.cname = Some(read_principal_name(element)?),
+                    2 => kerberos_message.realm = Some(read_kerberos_string(element)?),
+                    3 => kerberos_message.sname = Some(read_principal_name(element)?),
+                    _ => (),
+                }
+            }
+        }
+        // KDC-REP: the server name is in the ticket
+        KerberosMessageTypes::AS_REP | KerberosMessageTypes::TGS_REP => {
+            kerberos_message.realm = Some(read_kerberos_string(field(3)?)?);
+            kerberos_message.cname = Some(read_principal_name(field(4)?)?);
+
+            let (_, ticket) = read_element(field(5)?.content).ok()?;
+            let (_, ticket) = read_element(ticket.content).ok()?;
+            let ticket = read_fields(&ticket)?;
+            if let Some((_, sname)) = ticket.iter().find(|(number, _)| *number == 2) {
+                kerberos_message.sname = Some(read_principal_name(sname)?);
+            }
+        }
+        KerberosMessageTypes::KRB_ERROR => {
+            kerberos_message.error_code = Some(read_integer(field(6)?.content).ok()?);
+            kerberos_message.realm = Some(read_kerberos_string(field(9)?)?);
+            kerberos_message.sname = Some(read_principal_name(field(10)?)?);
+            if let Some(cname) = field(8) {
+                kerberos_message.cname = Some(read_principal_name(cname)?);
+            }
+        }
+        _ => (),
+    }
+
+    Some(kerberos_message)
+}
+
+/// Check if a tag is the application tag of a known Kerberos message
+fn is_message_tag(tag: u8) -> bool {
+    let message_type = tag & 0x1f;
+    let known = (KerberosMessageTypes::AS_REQ..=KerberosMessageTypes::AP_REP)
+        .contains(&message_type)
+        || message_type == KerberosMessageTypes::KRB_ERROR;
+
+    tag & 0xe0 == 0x60 && known
+}
+
+/// Read the explicitly tagged fields of a SEQUENCE: their context tag number and inner element
+fn read_fields<'a>(sequence: &BerElement<'a>) -> Option<Vec<(u8, BerElement<'a>)>> {
+    if sequence.tag != SEQUENCE {
+        return None;
+    }
+
+    let mut fields = vec![];
+    let mut remaining = sequence.content;
+    while !remaining.is_empty() {
+        let (rem, field) = read_element(remaining).ok()?;
+        let (_, inner) = read_element(field.content).ok()?;
+        fields.push((field.tag & 0x1f, inner));
+        remaining = rem;
+    }
+
+    Some(fields)
+}
+
+/// Read `PrincipalName ::= SEQUENCE { name-type [0] Int32, name-string [1] SEQUENCE OF
+/// KerberosString }`, getting its components
+fn read_principal_name(element: &BerElement) -> Option<Vec<String>> {
+    let fields = read_fields(element)?;
+    let (_, names) = fields.iter().find(|(number, _)| *number == 1)?;
+
+    let mut components = vec![];
+    let mut remaining = names.content;
+    while !remaining.is_empty() {
+        let (rem, component) = read_element(remaining).ok()?;
+        components.push(read_kerberos_string(&component)?);
+        remaining = rem;
+    }
+
+    Some(components)
+}
+
+/// Read a `KerberosString`, a realm or a component of a principal name
+fn read_kerberos_string(element: &BerElement) -> Option<String> {
+    match element.tag {
+        GENERAL_STRING => Some(read_string(element.content)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{handle_kerberos_packet, FlowContext};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    #[test]
+    fn as_request_over_tcp() {
+        let as_req = build_test_as_request();
+        let mut packet = (as_req.len() as u32).to_be_bytes().to_vec();
+        packet.extend_from_slice(&as_req);
+
+        match kerberos_packet(&packet)
+            .get_application_layer_packet()
+            .unwrap()
+        {
+            SerializablePacket::KerberosPacket(kerberos_packet) => {
+                assert_eq!(kerberos_packet.message_type, "AS-REQ (10)");
+                assert_eq!(kerberos_packet.cname.as_deref(), Some("alice"));
+                assert_eq!(kerberos_packet.realm.as_deref(), Some("EXAMPLE.COM"));
+                assert_eq!(kerberos_packet.sname.as_deref(), Some("krbtgt/EXAMPLE.COM"));
+                assert_eq!(kerberos_packet.error_code, None);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn krb_error_over_udp() {
+        // Preauthentication required, for alice
+        let error = der(
+            0x7e,
+            &der(
+                0x30,
+                &[
+                    der(0xa0, &der(0x02, &[5])),
+                    der(0xa1, &der(0x02, &[30])),
+                    der(0xa4, &der(0x18, b"20240101000000Z")),
+                    der(0xa5, &der(0x02, &[0])),
+                    der(0xa6, &der(0x02, &[25])),
+                    der(0xa8, &principal_name(1, &["alice"])),
+                    der(0xa9, &der(0x1b, b"EXAMPLE.COM")),
+                    der(0xaa, &principal_name(2, &["krbtgt", "EXAMPLE.COM"])),
+                ]
+                .concat(),
+            ),
+        );
+
+        match kerberos_packet(&error)
+            .get_application_layer_packet()
+            .unwrap()
+        {
+            SerializablePacket::KerberosPacket(kerberos_packet) => {
+                assert_eq!(kerberos_packet.message_type, "KRB-ERROR (30)");
+                assert_eq!(
+                    kerberos_packet.error_code.as_deref(),
+                    Some("KDC_ERR_PREAUTH_REQUIRED (25)")
+                );
+                assert_eq!(kerberos_packet.cname.as_deref(), Some("alice"));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_kerberos_packet() {
+        let as_req = build_test_as_request();
+
+        match kerberos_packet(&as_req[..as_req.len() - 3])
+            .get_application_layer_packet()
+            .unwrap()
+        {
+            SerializablePacket::MalformedPacket(str) => {
+                assert_eq!(str, "Malformed Kerberos Packet")
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    ///////////////////// Utils
+
+    fn kerberos_packet(packet: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_kerberos_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                88,
+            ),
+            packet,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+
+    /// DER element of a tag and its content (short or 1-byte long length)
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut element = match content.len() {
+            0..=0x7f => vec![tag, content.len() as u8],
+            length => vec![tag, 0x81, length as u8],
+        };
+        element.extend_from_slice(content);
+        element
+    }
+
+    fn principal_name(name_type: u8, components: &[&str]) -> Vec<u8> {
+        let components: Vec<u8> = components
+            .iter()
+            .flat_map(|component| der(0x1b, component.as_bytes()))
+            .collect();
+        der(
+            0x30,
+            &[
+                der(0xa0, &der(0x02, &[name_type])),
+                der(0xa1, &der(0x30, &components)),
+            ]
+            .concat(),
+        )
+    }
+
+    /// AS-REQ of alice@EXAMPLE.COM for krbtgt/EXAMPLE.COM, without preauthentication
+    fn build_test_as_request() -> Vec<u8> {
+        let body = der(
+            0x30,
+            &[
+                der(0xa0, &der(0x03, &[0x00, 0x40, 0x81, 0x00, 0x10])),
+                der(0xa1, &principal_name(1, &["alice"])),
+                der(0xa2, &der(0x1b, b"EXAMPLE.COM")),
+                der(0xa3, &principal_name(2, &["krbtgt", "EXAMPLE.COM"])),
+                der(0xa5, &der(0x18, b"20370913024805Z")),
+                der(0xa7, &der(0x02, &[0x12, 0x34, 0x56, 0x78])),
+                der(
+                    0xa8,
+                    &der(0x30, &[der(0x02, &[18]), der(0x02, &[17])].concat()),
+                ),
+            ]
+            .concat(),
+        );
+
+        der(
+            0x6a,
+            &der(
+                0x30,
+                &[
+                    der(0xa1, &der(0x02, &[5])),
+                    der(0xa2, &der(0x02, &[10])),
+                    der(0xa4, &body),
+                ]
+                .concat(),
+            ),
+        )
+    }
+}
@@ -0,0 +1,596 @@
+//! LDAP Packet parsing
+//!
+//! LDAP messages (RFC 4511) are BER-encoded: each element is a tag, a length and its content.
+//! Bind and search operations are decoded along with the result of their responses, the other
+//! operations are only identified by their name. The password of simple binds is never kept
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::SerializableLdapPacket, ParsedPacket, SerializablePacket,
+};
+
+use super::FlowContext;
+
+/// Nesting of the search filters past which a message is malformed, bounding the recursion
+const MAX_FILTER_DEPTH: usize = 32;
+
+/// BER Universal Tags
+#[allow(non_snake_case)]
+mod BerTags {
+    pub const BOOLEAN: u8 = 0x01;
+    pub const INTEGER: u8 = 0x02;
+    pub const OCTET_STRING: u8 = 0x04;
+    pub const ENUMERATED: u8 = 0x0a;
+    pub const SEQUENCE: u8 = 0x30;
+}
+
+/// LDAP Protocol Operations, application tag numbers
+#[allow(non_snake_case)]
+pub mod LdapOperations {
+    pub const BIND_REQUEST: u8 = 0;
+    pub const BIND_RESPONSE: u8 = 1;
+    pub const UNBIND_REQUEST: u8 = 2;
+    pub const SEARCH_REQUEST: u8 = 3;
+    pub const SEARCH_RESULT_ENTRY: u8 = 4;
+    pub const SEARCH_RESULT_DONE: u8 = 5;
+    pub const MODIFY_REQUEST: u8 = 6;
+    pub const MODIFY_RESPONSE: u8 = 7;
+    pub const ADD_REQUEST: u8 = 8;
+    pub const ADD_RESPONSE: u8 = 9;
+    pub const DEL_REQUEST: u8 = 10;
+    pub const DEL_RESPONSE: u8 = 11;
+    pub const MODIFY_DN_REQUEST: u8 = 12;
+    pub const MODIFY_DN_RESPONSE: u8 = 13;
+    pub const COMPARE_REQUEST: u8 = 14;
+    pub const COMPARE_RESPONSE: u8 = 15;
+    pub const ABANDON_REQUEST: u8 = 16;
+    pub const SEARCH_RESULT_REFERENCE: u8 = 19;
+    pub const EXTENDED_REQUEST: u8 = 23;
+    pub const EXTENDED_RESPONSE: u8 = 24;
+    pub const INTERMEDIATE_RESPONSE: u8 = 25;
+}
+
+/// Errors occurring during the parsing of a LDAP message
+#[derive(Debug)]
+pub enum LdapError {
+    Truncated,
+    UnexpectedTag(u8),
+    InvalidLength,
+    FilterTooDeep,
+}
+
+/// Authentication choice of a bind request
+#[derive(Debug)]
+pub enum LdapAuthentication {
+    /// Simple authentication, only the length of the password is kept
+    Simple(usize),
+    Sasl(String),
+    Unknown(u8),
+}
+
+/// Result of a response operation
+#[derive(Debug)]
+pub struct LdapResult {
+    pub result_code: u32,
+    pub matched_dn: String,
+    pub diagnostic_message: String,
+}
+
+/// Decoded protocol operation of a LDAP message
+#[derive(Debug)]
+pub enum LdapOperation {
+    BindRequest {
+        version: u32,
+        name: String,
+        authentication: LdapAuthentication,
+    },
+    BindResponse(LdapResult),
+    SearchRequest {
+        base_object: String,
+        scope: u32,
+        filter: String,
+    },
+    SearchResultEntry {
+        object_name: String,
+    },
+    /// Response operation carrying a result (search done, modify, add, delete, ...)
+    Response {
+        tag_number: u8,
+        result: LdapResult,
+    },
+    /// Request operation whose first field is the DN of the entry (modify, add, delete, ...)
+    Request {
+        tag_number: u8,
+        entry: Option<String>,
+    },
+}
+
+/// LDAP Message: its identifier and the operation it carries
+#[derive(Debug)]
+pub struct LdapMessage {
+    pub message_id: u32,
+    pub operation: LdapOperation,
+}
+
+/// BER element: tag and content
+pub(crate) struct BerElement<'a> {
+    pub tag: u8,
+    pub content: &'a [u8],
+}
+
+/// Build a LDAP packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_ldap_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    if packet.is_empty() {
+        return;
+    }
+
+    let messages = parse_ldap_messages(packet);
+
+    if messages.is_empty() {
+        debug!("Malformed LDAP Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed LDAP Packet".to_string(),
+        )));
+        return;
+    }
+
+    debug!(
+        "LDAP Packet: {}:{} > {}:{}; Messages: {:?}",
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        messages
+            .iter()
+            .map(|m| (m.message_id, ldap_operation_tag(&m.operation)))
+            .collect::<Vec<_>>()
+    );
+
+    parsed_packet.set_application_layer_packet(Some(SerializablePacket::LdapPacket(
+        SerializableLdapPacket::from(messages.as_slice()),
+    )));
+}
+
+/// Parse the LDAP messages of a segment, stopping at the first incomplete or invalid one
+pub fn parse_ldap_messages(packet: &[u8]) -> Vec<LdapMessage> {
+    let mut messages = vec![];
+    let mut remaining = packet;
+
+    while !remaining.is_empty() {
+        match parse_ldap_message(remaining) {
+            Ok((rem, message)) => {
+                messages.push(message);
+                remaining = rem;
+            }
+            Err(_) => break,
+        }
+    }
+
+    messages
+}
+
+/// Parse one `LDAPMessage ::= SEQUENCE { messageID, protocolOp, controls }`
+fn parse_ldap_message(packet: &[u8]) -> Result<(&[u8], LdapMessage), LdapError> {
+    let (rem, message) = read_element(packet)?;
+    expect_tag(&message, BerTags::SEQUENCE)?;
+
+    let (content, message_id) = read_element(message.content)?;
+    expect_tag(&message_id, BerTags::INTEGER)?;
+    let message_id = read_integer(message_id.content)?;
+
+    let (_, operation) = read_element(content)?;
+    if operation.tag & 0xc0 != 0x40 {
+        return Err(LdapError::UnexpectedTag(operation.tag));
+    }
+
+    Ok((
+        rem,
+        LdapMessage {
+            message_id,
+            operation: parse_operation(&operation)?,
+        },
+    ))
+}
+
+fn parse_operation(operation: &BerElement) -> Result<LdapOperation, LdapError> {
+    let tag_number = operation.tag & 0x1f;
+
+    match tag_number {
+        LdapOperations::BIND_REQUEST => {
+            let (rem, version) = read_element(operation.content)?;
+            expect_tag(&version, BerTags::INTEGER)?;
+            let (rem, name) = read_element(rem)?;
+            expect_tag(&name, BerTags::OCTET_STRING)?;
+            let (_, authentication) = read_element(rem)?;
+
+            let authentication = match authentication.tag {
+                0x80 => LdapAuthentication::Simple(authentication.content.len()),
+                0xa3 => {
+                    let (_, mechanism) = read_element(authentication.content)?;
+                    LdapAuthentication::Sasl(read_string(mechanism.content))
+                }
+                tag => LdapAuthentication::Unknown(tag & 0x1f),
+            };
+
+            Ok(LdapOperation::BindRequest {
+                version: read_integer(version.content)?,
+                name: read_string(name.content),
+                authentication,
+            })
+        }
+        LdapOperations::BIND_RESPONSE => Ok(LdapOperation::BindResponse(parse_result(
+            operation.content,
+        )?)),
+        LdapOperations::SEARCH_REQUEST => {
+            let (rem, base_object) = read_element(operation.content)?;
+            expect_tag(&base_object, BerTags::OCTET_STRING)?;
+            let (rem, scope) = read_element(rem)?;
+            expect_tag(&scope, BerTags::ENUMERATED)?;
+
+            // derefAliases, sizeLimit, timeLimit, typesOnly
+            let (rem, _) = read_element(rem)?;
+            let (rem, _) = read_element(rem)?;
+            let (rem, _) = read_element(rem)?;
+            let (rem, types_only) = read_element(rem)?;
+            expect_tag(&types_only, BerTags::BOOLEAN)?;
+
+            let (_, filter) = read_element(rem)?;
+
+            Ok(LdapOperation::SearchRequest {
+                base_object: read_string(base_object.content),
+                scope: read_integer(scope.content)?,
+                filter: parse_filter(&filter, 0)?,
+            })
+        }
+        LdapOperations::SEARCH_RESULT_ENTRY => {
+            let (_, object_name) = read_element(operation.content)?;
+            expect_tag(&object_name, BerTags::OCTET_STRING)?;
+
+            Ok(LdapOperation::SearchResultEntry {
+                object_name: read_string(object_name.content),
+            })
+        }
+        LdapOperations::SEARCH_RESULT_DONE
+        | LdapOperations::MODIFY_RESPONSE
+        | LdapOperations::ADD_RESPONSE
+        | LdapOperations::DEL_RESPONSE
+        | LdapOperations::MODIFY_DN_RESPONSE
+        | LdapOperations::COMPARE_RESPONSE
+        | LdapOperations::EXTENDED_RESPONSE => Ok(LdapOperation::Response {
+            tag_number,
+            result: parse_result(operation.content)?,
+        }),
+        LdapOperations::MODIFY_REQUEST
+        | LdapOperations::ADD_REQUEST
+        | LdapOperations::MODIFY_DN_REQUEST
+        | LdapOperations::COMPARE_REQUEST => {
+            let (_, entry) = read_element(operation.content)?;
+            expect_tag(&entry, BerTags::OCTET_STRING)?;
+
+            Ok(LdapOperation::Request {
+                tag_number,
+                entry: Some(read_string(entry.content)),
+            })
+        }
+        // The DN is the whole (primitive) content of a delete request
+        LdapOperations::DEL_REQUEST => Ok(LdapOperation::Request {
+            tag_number,
+            entry: Some(read_string(operation.content)),
+        }),
+        _ => Ok(LdapOperation::Request {
+            tag_number,
+            entry: None,
+        }),
+    }
+}
+
+/// Parse `LDAPResult ::= SEQUENCE { resultCode, matchedDN, diagnosticMessage, referral }`
+fn parse_result(content: &[u8]) -> Result<LdapResult, LdapError> {
+    let (rem, result_code) = read_element(content)?;
+    expect_tag(&result_code, BerTags::ENUMERATED)?;
+    let (rem, matched_dn) = read_element(rem)?;
+    expect_tag(&matched_dn, BerTags::OCTET_STRING)?;
+    let (_, diagnostic_message) = read_element(rem)?;
+    expect_tag(&diagnostic_message, BerTags::OCTET_STRING)?;
+
+    Ok(LdapResult {
+        result_code: read_integer(result_code.content)?,
+        matched_dn: read_string(matched_dn.content),
+        diagnostic_message: read_string(diagnostic_message.content),
+    })
+}
+
+/// Render a search filter nested at `depth` in its string representation (RFC 4515)
+fn parse_filter(filter: &BerElement, depth: usize) -> Result<String, LdapError> {
+    if depth > MAX_FILTER_DEPTH {
+        return Err(LdapError::FilterTooDeep);
+    }
+    let attribute_value = |content: &[u8]| -> Result<(String, String), LdapError> {
+        let (rem, attribute) = read_element(content)?;
+        let (_, value) = read_element(rem)?;
+        Ok((read_string(attribute.content), read_string(value.content)))
+    };
+
+    match filter.tag {
+        0xa0 | 0xa1 => {
+            let operator = if filter.tag == 0xa0 { '&' } else { '|' };
+            let mut filters = String::new();
+            let mut remaining = filter.content;
+            while !remaining.is_empty() {
+                let (rem, element) = read_element(remaining)?;
+                filters.push_str(&parse_filter(&element, depth + 1)?);
+                remaining = rem;
+            }
+            Ok(format!("({}{})", operator, filters))
+        }
+        0xa2 => {
+            let (_, element) = read_element(filter.content)?;
+            Ok(format!("(!{})", parse_filter(&element, depth + 1)?))
+        }
+        0xa3 | 0xa5 | 0xa6 | 0xa8 => {
+            let (attribute, value) = attribute_value(filter.content)?;
+            let operator = match filter.tag {
+                0xa3 => "=",
+                0xa5 => ">=",
+                0xa6 => "<=",
+                _ => "~=",
+            };
+            Ok(format!("({}{}{})", attribute, operator, value))
+        }
+        0xa4 => {
+            let (rem, attribute) = read_element(filter.content)?;
+            let (_, substrings) = read_element(rem)?;
+
+            let (mut initial, mut any, mut last) = (String::new(), vec![], String::new());
+            let mut remaining = substrings.content;
+            while !remaining.is_empty() {
+                let (rem, substring) = read_element(remaining)?;
+                match substring.tag {
+                    0x80 => initial = read_string(substring.content),
+                    0x81 => any.push(read_string(substring.content)),
+                    _ => last = read_string(substring.content),
+                }
+                remaining = rem;
+            }
+
+            let mut parts = vec![initial];
+            parts.extend(any);
+            parts.push(last);
+            Ok(format!(
+                "({}={})",
+                read_string(attribute.content),
+                parts.join("*")
+            ))
+        }
+        0x87 => Ok(format!("({}=*)", read_string(filter.content))),
+        0xa9 => Ok("(extensibleMatch)".to_owned()),
+        tag => Err(LdapError::UnexpectedTag(tag)),
+    }
+}
+
+/// Get the application tag number of an operation
+pub fn ldap_operation_tag(operation: &LdapOperation) -> u8 {
+    match operation {
+        LdapOperation::BindRequest { .. } => LdapOperations::BIND_REQUEST,
+        LdapOperation::BindResponse(_) => LdapOperations::BIND_RESPONSE,
+        LdapOperation::SearchRequest { .. } => LdapOperations::SEARCH_REQUEST,
+        LdapOperation::SearchResultEntry { .. } => LdapOperations::SEARCH_RESULT_ENTRY,
+        LdapOperation::Response { tag_number, .. } => *tag_number,
+        LdapOperation::Request { tag_number, .. } => *tag_number,
+    }
+}
+
+/// Read a BER element (definite length only), returning the bytes following it
+pub(crate) fn read_element(packet: &[u8]) -> Result<(&[u8], BerElement<'_>), LdapError> {
+    let tag = *packet.first().ok_or(LdapError::Truncated)?;
+    let first_length = *packet.get(1).ok_or(LdapError::Truncated)?;
+
+    let (length, header_length) = match first_length {
+        0..=0x7f => (first_length as usize, 2),
+        0x81..=0x84 => {
+            let length_bytes = (first_length & 0x7f) as usize;
+            let bytes = packet
+                .get(2..2 + length_bytes)
+                .ok_or(LdapError::Truncated)?;
+            let length = bytes
+                .iter()
+                .fold(0usize, |acc, byte| (acc << 8) | *byte as usize);
+            (length, 2 + length_bytes)
+        }
+        _ => return Err(LdapError::InvalidLength),
+    };
+
+    let content = packet
+        .get(header_length..header_length + length)
+        .ok_or(LdapError::Truncated)?;
+
+    Ok((
+        &packet[header_length + length..],
+        BerElement { tag, content },
+    ))
+}
+
+fn expect_tag(element: &BerElement, tag: u8) -> Result<(), LdapError> {
+    match element.tag == tag {
+        true => Ok(()),
+        false => Err(LdapError::UnexpectedTag(element.tag)),
+    }
+}
+
+pub(crate) fn read_integer(content: &[u8]) -> Result<u32, LdapError> {
+    if content.is_empty() || content.len() > 4 {
+        return Err(LdapError::InvalidLength);
+    }
+
+    Ok(content
+        .iter()
+        .fold(0u32, |acc, byte| (acc << 8) | *byte as u32))
+}
+
+pub(crate) fn read_string(content: &[u8]) -> String {
+    String::from_utf8_lossy(content).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{handle_ldap_packet, FlowContext};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    // Simple bind of "cn=admin,dc=example,dc=com" with password "secret"
+    const BIND_REQUEST: &[u8] = &[
+        0x30, 0x2c, 0x02, 0x01, 0x01, 0x60, 0x27, 0x02, 0x01, 0x03, 0x04, 0x1a, 0x63, 0x6e, 0x3d,
+        0x61, 0x64, 0x6d, 0x69, 0x6e, 0x2c, 0x64, 0x63, 0x3d, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c,
+        0x65, 0x2c, 0x64, 0x63, 0x3d, 0x63, 0x6f, 0x6d, 0x80, 0x06, 0x73, 0x65, 0x63, 0x72, 0x65,
+        0x74,
+    ];
+
+    // Bind response: invalidCredentials
+    const BIND_RESPONSE: &[u8] = &[
+        0x30, 0x0c, 0x02, 0x01, 0x01, 0x61, 0x07, 0x0a, 0x01, 0x31, 0x04, 0x00, 0x04, 0x00,
+    ];
+
+    // Search of "dc=example,dc=com" (whole subtree) with filter (&(objectClass=person)(cn=j*))
+    const SEARCH_REQUEST: &[u8] = &[
+        0x30, 0x4d, 0x02, 0x01, 0x02, 0x63, 0x48, 0x04, 0x11, 0x64, 0x63, 0x3d, 0x65, 0x78, 0x61,
+        0x6d, 0x70, 0x6c, 0x65, 0x2c, 0x64, 0x63, 0x3d, 0x63, 0x6f, 0x6d, 0x0a, 0x01, 0x02, 0x0a,
+        0x01, 0x00, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00, 0x01, 0x01, 0x00, 0xa0, 0x22, 0xa3, 0x15,
+        0x04, 0x0b, 0x6f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x43, 0x6c, 0x61, 0x73, 0x73, 0x04, 0x06,
+        0x70, 0x65, 0x72, 0x73, 0x6f, 0x6e, 0xa4, 0x09, 0x04, 0x02, 0x63, 0x6e, 0x30, 0x03, 0x80,
+        0x01, 0x6a, 0x30, 0x00,
+    ];
+
+    #[test]
+    fn simple_bind_request() {
+        let parsed_packet = ldap_packet(4444, 389, BIND_REQUEST);
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::LdapPacket(new_ldap_packet) => {
+                assert_eq!(new_ldap_packet.messages.len(), 1);
+
+                let message = &new_ldap_packet.messages[0];
+                assert_eq!(message.message_id, 1);
+                assert_eq!(message.operation, "bindRequest (0)");
+                assert_eq!(message.dn.as_deref(), Some("cn=admin,dc=example,dc=com"));
+                assert_eq!(message.version, Some(3));
+                assert_eq!(
+                    message.authentication.as_deref(),
+                    Some("simple (6 bytes redacted)")
+                );
+                assert!(!new_ldap_packet.to_string().contains("secret"));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn bind_response_result_code() {
+        let parsed_packet = ldap_packet(389, 4444, BIND_RESPONSE);
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::LdapPacket(new_ldap_packet) => {
+                let message = &new_ldap_packet.messages[0];
+                assert_eq!(message.operation, "bindResponse (1)");
+                assert_eq!(
+                    message.result_code.as_deref(),
+                    Some("invalidCredentials (49)")
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn search_request_filter() {
+        let parsed_packet = ldap_packet(4444, 389, SEARCH_REQUEST);
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::LdapPacket(new_ldap_packet) => {
+                let message = &new_ldap_packet.messages[0];
+                assert_eq!(message.operation, "searchRequest (3)");
+                assert_eq!(message.dn.as_deref(), Some("dc=example,dc=com"));
+                assert_eq!(message.scope.as_deref(), Some("wholeSubtree (2)"));
+                assert_eq!(
+                    message.filter.as_deref(),
+                    Some("(&(objectClass=person)(cn=j*))")
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn nested_filter_depth() {
+        let parsed_packet = ldap_packet(4444, 389, &build_test_search_request(32));
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::LdapPacket(new_ldap_packet) => assert_eq!(
+                new_ldap_packet.messages[0].filter.as_deref(),
+                Some(format!("{}(cn=*){}", "(!".repeat(32), ")".repeat(32)).as_str())
+            ),
+            _ => unreachable!(),
+        }
+
+        let parsed_packet = ldap_packet(4444, 389, &build_test_search_request(33));
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::MalformedPacket(str) => assert_eq!(str, "Malformed LDAP Packet"),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_ldap_packet() {
+        let parsed_packet = ldap_packet(4444, 389, &BIND_REQUEST[..20]);
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::MalformedPacket(str) => assert_eq!(str, "Malformed LDAP Packet"),
+            _ => unreachable!(),
+        }
+    }
+
+    ///////////////////// Utils
+
+    /// Build a search request of the root whose filter is (cn=*) negated `negations` times
+    fn build_test_search_request(negations: usize) -> Vec<u8> {
+        let mut filter = vec![0x87, 0x02, b'c', b'n'];
+        for _ in 0..negations {
+            filter.splice(0..0, [0xa2, filter.len() as u8]);
+        }
+
+        let mut operation = vec![
+            0x04, 0x00, 0x0a, 0x01, 0x02, 0x0a, 0x01, 0x00, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00,
+            0x01, 0x01, 0x00,
+        ];
+        operation.extend(filter);
+        operation.extend([0x30, 0x00]);
+
+        let mut message = vec![0x02, 0x01, 0x02, 0x63, operation.len() as u8];
+        message.extend(operation);
+        message.splice(0..0, [0x30, message.len() as u8]);
+        message
+    }
+
+    fn ldap_packet(source_port: u16, dest_port: u16, payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ldap_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                source_port,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                dest_port,
+            ),
+            payload,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+}
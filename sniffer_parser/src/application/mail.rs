@@ -0,0 +1,568 @@
+//! POP3 and IMAP Packet parsing
+//!
+//! Both protocols are line-based dialogs, split by direction: client segments carry commands
+//! (`USER alice` for POP3, tagged ones like `a1 LOGIN alice secret` for IMAP), server segments
+//! carry responses (`+OK`/`-ERR` status lines for POP3, tagged or untagged `* OK`/`* BYE` ones for
+//! IMAP). The state of each session is tracked to recognize the encrypted bytes following an
+//! accepted `STLS` (POP3) or `STARTTLS` (IMAP), and the continuation data of IMAP: the literals
+//! (`{n}` ending a line, followed by n bytes) and the SASL exchange of an `AUTHENTICATE`
+
+use std::time::SystemTime;
+
+use log::debug;
+
+use crate::capture_time;
+use crate::serializable_packet::{
+    application::{
+        ImapCommand, ImapResponse, Pop3Command, Pop3Response, SerializableImapPacket,
+        SerializablePop3Packet,
+    },
+    ParsedPacket, SerializablePacket,
+};
+
+use super::{FlowContext, WellKnownPorts, ACTIVE_MAIL_SESSIONS};
+
+/// Status indicators of the POP3 responses
+const POP3_OK: &str = "+OK";
+const POP3_ERR: &str = "-ERR";
+
+/// Conditions of the IMAP status responses
+const IMAP_CONDITIONS: [&str; 5] = ["OK", "NO", "BAD", "BYE", "PREAUTH"];
+
+/// State of a POP3 or IMAP session, between a client and a server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MailSessionState {
+    Command,
+    /// STARTTLS sent, with the tag of the IMAP command
+    StartTlsRequested(Option<String>),
+    /// IMAP AUTHENTICATE sent, with its tag: the client lines are SASL responses until its
+    /// completion
+    Authenticating(String),
+    Encrypted,
+}
+
+/// POP3 or IMAP session, between a client and a server
+#[derive(Debug)]
+pub(crate) struct MailSession {
+    state: MailSessionState,
+    /// Bytes left of the IMAP literal announced by the current line of each direction, `None`
+    /// outside of such a line
+    client_literal: Option<usize>,
+    server_literal: Option<usize>,
+    pub(crate) last_touch: SystemTime,
+}
+
+/// Build a POP3 packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_pop3_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    if packet.is_empty() {
+        return;
+    }
+
+    let from_client = flow.dest_port == WellKnownPorts::POP3_PORT;
+    let pop3_packet = with_session(flow, from_client, |session| {
+        match (&session.state, from_client) {
+            (MailSessionState::Encrypted, _) => Ok(SerializablePop3Packet::Encrypted(packet.len())),
+            (_, true) => parse_pop3_commands(packet).map(|commands| {
+                if commands.last().is_some_and(|command| command.starttls) {
+                    session.state = MailSessionState::StartTlsRequested(None);
+                }
+                SerializablePop3Packet::Commands(commands)
+            }),
+            (_, false) => parse_pop3_response(packet).map(|response| {
+                match response {
+                    Some(response) => {
+                        if let MailSessionState::StartTlsRequested(_) = session.state {
+                            session.state = match response.ok {
+                                true => MailSessionState::Encrypted,
+                                false => MailSessionState::Command,
+                            };
+                        }
+                        SerializablePop3Packet::Response(response)
+                    }
+                    // Continuation of a multi-line response (e.g. the message sent for RETR)
+                    None => SerializablePop3Packet::MessageData(packet.len()),
+                }
+            }),
+        }
+    });
+
+    match pop3_packet {
+        Ok(pop3_packet) => {
+            debug!(
+                "POP3 Packet: {}:{} > {}:{}; {:?}",
+                flow.source_ip, flow.source_port, flow.dest_ip, flow.dest_port, pop3_packet
+            );
+
+            parsed_packet
+                .set_application_layer_packet(Some(SerializablePacket::Pop3Packet(pop3_packet)));
+        }
+        Err(_) => {
+            debug!("Malformed POP3 Packet");
+            parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed POP3 Packet".to_string(),
+            )));
+        }
+    }
+}
+
+/// Build an IMAP packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_imap_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    if packet.is_empty() {
+        return;
+    }
+
+    let from_client = flow.dest_port == WellKnownPorts::IMAP_PORT;
+    let imap_packet = with_session(flow, from_client, |session| {
+        match (&session.state, from_client) {
+            (MailSessionState::Encrypted, _) => Ok(SerializableImapPacket::Encrypted(packet.len())),
+            (MailSessionState::Authenticating(_), true) => {
+                Ok(SerializableImapPacket::Continuation(packet.len()))
+            }
+            (_, true) => {
+                let lines = split_imap_lines(packet, &mut session.client_literal)?;
+                if lines.is_empty() {
+                    return Ok(SerializableImapPacket::Continuation(packet.len()));
+                }
+                parse_imap_commands(&lines).map(|commands| {
+                    match commands.last() {
+                        Some(command) if command.starttls => {
+                            session.state =
+                                MailSessionState::StartTlsRequested(Some(command.tag.clone()))
+                        }
+                        Some(command) if command.command == "AUTHENTICATE" => {
+                            session.state = MailSessionState::Authenticating(command.tag.clone())
+                        }
+                        _ => (),
+                    }
+                    SerializableImapPacket::Commands(commands)
+                })
+            }
+            (_, false) => {
+                let lines = split_imap_lines(packet, &mut session.server_literal)?;
+                if lines.is_empty() {
+                    return Ok(SerializableImapPacket::Continuation(packet.len()));
+                }
+                parse_imap_responses(&lines).map(|responses| {
+                    match &session.state {
+                        MailSessionState::StartTlsRequested(Some(tag)) => {
+                            let completion = responses.iter().find(|response| &response.tag == tag);
+                            if let Some(completion) = completion {
+                                session.state = match completion.status.as_deref() {
+                                    Some("OK") => MailSessionState::Encrypted,
+                                    _ => MailSessionState::Command,
+                                };
+                            }
+                        }
+                        MailSessionState::Authenticating(tag)
+                            if responses.iter().any(|response| &response.tag == tag) =>
+                        {
+                            session.state = MailSessionState::Command
+                        }
+                        _ => (),
+                    }
+                    SerializableImapPacket::Responses(responses)
+                })
+            }
+        }
+    });
+
+    match imap_packet {
+        Ok(imap_packet) => {
+            debug!(
+                "IMAP Packet: {}:{} > {}:{}; {:?}",
+                flow.source_ip, flow.source_port, flow.dest_ip, flow.dest_port, imap_packet
+            );
+
+            parsed_packet
+                .set_application_layer_packet(Some(SerializablePacket::ImapPacket(imap_packet)));
+        }
+        Err(_) => {
+            debug!("Malformed IMAP Packet");
+            parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed IMAP Packet".to_string(),
+            )));
+        }
+    }
+}
+
+/// Run a parser with the state of the session of a packet, keyed by (client, server)
+fn with_session<T>(
+    flow: &FlowContext,
+    from_client: bool,
+    parse: impl FnOnce(&mut MailSession) -> Result<T, ()>,
+) -> Result<T, ()> {
+    let source = (flow.source_ip, flow.source_port);
+    let dest = (flow.dest_ip, flow.dest_port);
+    let session = match from_client {
+        true => (source, dest),
+        false => (dest, source),
+    };
+
+    ACTIVE_MAIL_SESSIONS.with(|sessions| {
+        let mut sessions = sessions.borrow_mut();
+        let session = sessions.entry(session).or_insert_with(|| MailSession {
+            state: MailSessionState::Command,
+            client_literal: None,
+            server_literal: None,
+            last_touch: capture_time(),
+        });
+        session.last_touch = capture_time();
+        parse(session)
+    })
+}
+
+/// Split the payload in CRLF-terminated lines, the last one may be incomplete
+fn split_lines(packet: &[u8]) -> Result<Vec<&str>, ()> {
+    let text = std::str::from_utf8(packet).map_err(|_| ())?;
+
+    Ok(text.split("\r\n").filter(|line| !line.is_empty()).collect())
+}
+
+/// Parse the commands sent by a POP3 client (several of them when pipelining)
+fn parse_pop3_commands(packet: &[u8]) -> Result<Vec<Pop3Command>, ()> {
+    let mut commands = vec![];
+
+    for line in split_lines(packet)? {
+        let (keyword, argument) = match line.split_once(' ') {
+            Some((keyword, argument)) => (keyword, Some(argument.trim().to_owned())),
+            None => (line, None),
+        };
+
+        if keyword.is_empty() || !keyword.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(());
+        }
+
+        let keyword = keyword.to_ascii_uppercase();
+        commands.push(Pop3Command {
+            starttls: keyword == "STLS",
+            keyword,
+            argument,
+        });
+    }
+
+    match commands.is_empty() {
+        true => Err(()),
+        false => Ok(commands),
+    }
+}
+
+/// Parse the response sent by a POP3 server, `None` for the lines of a multi-line response
+/// following its status line
+fn parse_pop3_response(packet: &[u8]) -> Result<Option<Pop3Response>, ()> {
+    let lines = split_lines(packet)?;
+    let (status, lines) = lines.split_first().ok_or(())?;
+
+    let (indicator, text) = status.split_once(' ').unwrap_or((status, ""));
+    let ok = match indicator {
+        POP3_OK => true,
+        POP3_ERR => false,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(Pop3Response {
+        ok,
+        text: text.to_owned(),
+        lines: lines.iter().map(|line| line.to_string()).collect(),
+    }))
+}
+
+/// Split an IMAP payload in lines, leaving out the bytes of the literals, along with the lines
+/// started in a previous segment: the text following a literal continues the line announcing it.
+/// `literal` tracks the literal of the current line across the segments of a direction
+fn split_imap_lines(packet: &[u8], literal: &mut Option<usize>) -> Result<Vec<String>, ()> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    let mut continued = literal.is_some();
+    let mut rest = packet;
+
+    loop {
+        if let Some(length) = *literal {
+            let skipped = length.min(rest.len());
+            rest = &rest[skipped..];
+            *literal = Some(length - skipped);
+            if skipped < length {
+                break;
+            }
+        }
+
+        let Some(end) = rest.windows(2).position(|bytes| bytes == b"\r\n") else {
+            line.push_str(std::str::from_utf8(rest).map_err(|_| ())?);
+            break;
+        };
+        let text = std::str::from_utf8(&rest[..end]).map_err(|_| ())?;
+        line.push_str(text);
+        rest = &rest[end + 2..];
+
+        *literal = literal_length(text);
+        if literal.is_none() {
+            if !continued && !line.is_empty() {
+                lines.push(line.clone());
+            }
+            line.clear();
+            continued = false;
+        }
+    }
+
+    if !continued && !line.is_empty() {
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+/// Get the length of the literal announced at the end of a line, `{n}` or `{n+}` (RFC 7888)
+fn literal_length(line: &str) -> Option<usize> {
+    let start = line.strip_suffix('}')?.rfind('{')?;
+    let length = line[start + 1..line.len() - 1].trim_end_matches('+');
+
+    match !length.is_empty() && length.chars().all(|c| c.is_ascii_digit()) {
+        true => length.parse().ok(),
+        false => None,
+    }
+}
+
+/// Parse the tagged commands sent by an IMAP client
+fn parse_imap_commands(lines: &[String]) -> Result<Vec<ImapCommand>, ()> {
+    let mut commands = vec![];
+
+    for line in lines {
+        let (tag, rest) = line.split_once(' ').ok_or(())?;
+        let (command, arguments) = match rest.split_once(' ') {
+            Some((command, arguments)) => (command, Some(arguments.trim().to_owned())),
+            None => (rest, None),
+        };
+
+        if !is_imap_tag(tag)
+            || command.is_empty()
+            || !command.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return Err(());
+        }
+
+        let command = command.to_ascii_uppercase();
+        commands.push(ImapCommand {
+            tag: tag.to_owned(),
+            starttls: command == "STARTTLS",
+            command,
+            arguments,
+        });
+    }
+
+    match commands.is_empty() {
+        true => Err(()),
+        false => Ok(commands),
+    }
+}
+
+/// Check if a word is a valid IMAP command tag, `*` and `+` being reserved to the server
+fn is_imap_tag(word: &str) -> bool {
+    !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_graphic() && c != '*' && c != '+')
+}
+
+/// Parse the responses sent by an IMAP server: untagged (`*`), continuation requests (`+`) or
+/// tagged completions of a command
+fn parse_imap_responses(lines: &[String]) -> Result<Vec<ImapResponse>, ()> {
+    let mut responses = vec![];
+
+    for line in lines {
+        let (tag, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let (condition, text) = rest.split_once(' ').unwrap_or((rest, ""));
+
+        let status = IMAP_CONDITIONS
+            .iter()
+            .find(|status| condition.eq_ignore_ascii_case(status))
+            .map(|status| status.to_string());
+        let text = match status {
+            Some(_) => text,
+            None => rest,
+        };
+
+        responses.push(ImapResponse {
+            tag: tag.to_owned(),
+            status,
+            text: text.to_owned(),
+        });
+    }
+
+    match responses.is_empty() {
+        true => Err(()),
+        false => Ok(responses),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{handle_imap_packet, handle_pop3_packet, FlowContext};
+    use crate::serializable_packet::{
+        application::{SerializableImapPacket, SerializablePop3Packet},
+        ParsedPacket, SerializablePacket,
+    };
+
+    const CLIENT: (IpAddr, u16) = (IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)), 4444);
+    const SERVER: IpAddr = IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11));
+
+    #[test]
+    fn pop3_user_command() {
+        let parsed_packet = pop3_packet(true, b"USER alice\r\n");
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::Pop3Packet(SerializablePop3Packet::Commands(commands)) => {
+                assert_eq!(commands.len(), 1);
+                assert_eq!(commands[0].keyword, "USER");
+                assert_eq!(commands[0].argument.as_deref(), Some("alice"));
+                assert!(!commands[0].starttls);
+            }
+            _ => unreachable!(),
+        }
+
+        match pop3_packet(false, b"+OK send PASS\r\n").get_application_layer_packet() {
+            Some(SerializablePacket::Pop3Packet(SerializablePop3Packet::Response(response))) => {
+                assert!(response.ok);
+                assert_eq!(response.text, "send PASS");
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn imap_login_command() {
+        let parsed_packet = imap_packet(true, b"a1 LOGIN alice secret\r\n");
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::ImapPacket(SerializableImapPacket::Commands(commands)) => {
+                assert_eq!(commands.len(), 1);
+                assert_eq!(commands[0].tag, "a1");
+                assert_eq!(commands[0].command, "LOGIN");
+                assert_eq!(commands[0].arguments.as_deref(), Some("alice secret"));
+            }
+            _ => unreachable!(),
+        }
+
+        let parsed_packet = imap_packet(
+            false,
+            b"* CAPABILITY IMAP4rev1\r\na1 OK LOGIN completed\r\n",
+        );
+        match parsed_packet.get_application_layer_packet() {
+            Some(SerializablePacket::ImapPacket(SerializableImapPacket::Responses(responses))) => {
+                assert_eq!(responses[0].tag, "*");
+                assert_eq!(responses[0].status, None);
+                assert_eq!(responses[0].text, "CAPABILITY IMAP4rev1");
+                assert_eq!(responses[1].tag, "a1");
+                assert_eq!(responses[1].status.as_deref(), Some("OK"));
+                assert_eq!(responses[1].text, "LOGIN completed");
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn imap_encrypted_after_starttls() {
+        let client = (CLIENT.0, 5555);
+        let mut parsed_packet = ParsedPacket::new(0);
+        let flow = FlowContext::new(client.0, client.1, SERVER, 143);
+        handle_imap_packet(&flow, b"a2 STARTTLS\r\n", &mut parsed_packet);
+        let flow = FlowContext::new(SERVER, 143, client.0, client.1);
+        handle_imap_packet(
+            &flow,
+            b"a2 OK Begin TLS negotiation now\r\n",
+            &mut parsed_packet,
+        );
+
+        let flow = FlowContext::new(client.0, client.1, SERVER, 143);
+        handle_imap_packet(&flow, &[0x16, 0x03, 0x01, 0x00, 0x05], &mut parsed_packet);
+        match parsed_packet.get_application_layer_packet() {
+            Some(SerializablePacket::ImapPacket(SerializableImapPacket::Encrypted(length))) => {
+                assert_eq!(*length, 5)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn imap_literal_across_segments() {
+        imap_packet(true, b"a3 FETCH 1 BODY[]\r\n");
+
+        let parsed_packet = imap_packet(false, b"* 1 FETCH (BODY[] {8}\r\nFrom: ");
+        match parsed_packet.get_application_layer_packet() {
+            Some(SerializablePacket::ImapPacket(SerializableImapPacket::Responses(responses))) => {
+                assert_eq!(responses.len(), 1);
+                assert_eq!(responses[0].text, "1 FETCH (BODY[] {8}");
+            }
+            _ => unreachable!(),
+        }
+
+        match imap_packet(false, &[0xe9, 0xe9]).get_application_layer_packet() {
+            Some(SerializablePacket::ImapPacket(SerializableImapPacket::Continuation(length))) => {
+                assert_eq!(*length, 2)
+            }
+            _ => unreachable!(),
+        }
+
+        let parsed_packet = imap_packet(false, b")\r\na3 OK FETCH completed\r\n");
+        match parsed_packet.get_application_layer_packet() {
+            Some(SerializablePacket::ImapPacket(SerializableImapPacket::Responses(responses))) => {
+                assert_eq!(responses.len(), 1);
+                assert_eq!(responses[0].tag, "a3");
+                assert_eq!(responses[0].status.as_deref(), Some("OK"));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn imap_authenticate_continuation() {
+        imap_packet(true, b"a1 AUTHENTICATE PLAIN\r\n");
+        imap_packet(false, b"+ \r\n");
+
+        match imap_packet(true, b"AGFsaWNlAHNlY3JldA==\r\n").get_application_layer_packet() {
+            Some(SerializablePacket::ImapPacket(SerializableImapPacket::Continuation(length))) => {
+                assert_eq!(*length, 22)
+            }
+            _ => unreachable!(),
+        }
+
+        imap_packet(false, b"a1 OK Success\r\n");
+        match imap_packet(true, b"a2 SELECT INBOX\r\n").get_application_layer_packet() {
+            Some(SerializablePacket::ImapPacket(SerializableImapPacket::Commands(commands))) => {
+                assert_eq!(commands[0].command, "SELECT")
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_pop3_packet() {
+        match pop3_packet(true, b"US3R alice\r\n").get_application_layer_packet() {
+            Some(SerializablePacket::MalformedPacket(str)) => {
+                assert_eq!(str, "Malformed POP3 Packet")
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    ///////////////////// Utils
+
+    fn pop3_packet(from_client: bool, payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        let flow = match from_client {
+            true => FlowContext::new(CLIENT.0, CLIENT.1, SERVER, 110),
+            false => FlowContext::new(SERVER, 110, CLIENT.0, CLIENT.1),
+        };
+        handle_pop3_packet(&flow, payload, &mut parsed_packet);
+        parsed_packet
+    }
+
+    fn imap_packet(from_client: bool, payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        let flow = match from_client {
+            true => FlowContext::new(CLIENT.0, CLIENT.1, SERVER, 143),
+            false => FlowContext::new(SERVER, 143, CLIENT.0, CLIENT.1),
+        };
+        handle_imap_packet(&flow, payload, &mut parsed_packet);
+        parsed_packet
+    }
+}
@@ -1,37 +1,284 @@
 //! Application layer Packet parsing
 
-use std::{cell::RefCell, collections::HashMap, net::IpAddr};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    net::IpAddr,
+    time::{Duration, SystemTime},
+};
+
+use log::{debug, warn};
 
-use crate::serializable_packet::ParsedPacket;
+use crate::capture_time;
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
 
 use self::{
-    dns::handle_dns_packet, 
-    http::handle_http_packet, 
+    decode_as::{decode_as_dissector, Dissector},
+    dhcpv6::handle_dhcpv6_packet,
+    dns::{handle_dns_packet, handle_mdns_packet},
+    dtls::handle_dtls_packet,
+    gtp::handle_gtp_packet,
+    hint::looks_like_dns_message,
+    http::handle_http_packet,
+    kerberos::handle_kerberos_packet,
+    ldap::handle_ldap_packet,
+    mail::{handle_imap_packet, handle_pop3_packet, MailSession},
+    modbus::handle_modbus_packet,
+    nbns::handle_nbns_packet,
+    quic::handle_quic_packet,
+    smtp::{handle_smtp_packet, SmtpSession},
+    stun::handle_stun_packet,
+    syslog::handle_syslog_packet,
+    telnet::handle_telnet_packet,
     tls::handle_tls_packet,
-    modbus::handle_modbus_packet
+    wireguard::handle_wireguard_packet,
 };
 
+pub mod decode_as;
+pub mod dhcpv6;
 pub mod dns;
+pub mod dtls;
+pub mod gtp;
+pub mod hint;
 pub mod http;
+pub mod kerberos;
+pub mod ldap;
+pub mod mail;
+pub mod nbns;
+pub mod quic;
+pub mod smtp;
+pub mod stun;
+pub mod syslog;
+pub mod telnet;
 pub mod tls;
 pub mod modbus;
+pub mod wireguard;
+
+/// Source and destination endpoints identifying one direction of a flow
+pub(crate) type FlowKey = ((IpAddr, u16), (IpAddr, u16));
+
+/// Reassembly buffer of a flow, with the capture time of the last segment appended to it
+pub(crate) struct ActiveParser {
+    payload: Vec<u8>,
+    last_touch: SystemTime,
+}
 
 thread_local!(
-    pub(crate) static ACTIVE_HTTP_PARSERS: RefCell<
-        HashMap<((IpAddr, u16), (IpAddr, u16)), Vec<u8>>,
-    > = RefCell::new(HashMap::new());
-    pub(crate) static ACTIVE_TLS_PARSERS: RefCell<
-        HashMap<((IpAddr, u16), (IpAddr, u16)), Vec<u8>>,
-    > = RefCell::new(HashMap::new());
+    pub(crate) static ACTIVE_HTTP_PARSERS: RefCell<HashMap<FlowKey, ActiveParser>> =
+        RefCell::new(HashMap::new());
+    pub(crate) static ACTIVE_TLS_PARSERS: RefCell<HashMap<FlowKey, ActiveParser>> =
+        RefCell::new(HashMap::new());
+    pub(crate) static ACTIVE_SMTP_SESSIONS: RefCell<HashMap<FlowKey, SmtpSession>> =
+        RefCell::new(HashMap::new());
+    pub(crate) static ACTIVE_MAIL_SESSIONS: RefCell<HashMap<FlowKey, MailSession>> =
+        RefCell::new(HashMap::new());
+    pub(crate) static ACTIVE_FLOWS: RefCell<HashMap<FlowKey, FlowState>> =
+        RefCell::new(HashMap::new());
 );
 
+/// Direction of a packet, relative to the endpoint which sent the first packet of its flow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowDirection {
+    Forward,
+    Reverse,
+}
+
+/// Packet and payload byte counts of one direction of a flow
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FlowCounters {
+    pub packets: usize,
+    pub bytes: usize,
+}
+
+/// Counters of both directions of a flow, keyed by (initiator, responder)
+pub(crate) struct FlowState {
+    forward: FlowCounters,
+    reverse: FlowCounters,
+    last_touch: SystemTime,
+}
+
+/// Transport-layer context of a packet handed to the application-layer handlers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowContext {
+    pub source_ip: IpAddr,
+    pub source_port: u16,
+    pub dest_ip: IpAddr,
+    pub dest_port: u16,
+    pub direction: FlowDirection,
+    /// Counts of the direction of this packet, including it
+    pub sent: FlowCounters,
+    /// Counts of the opposite direction
+    pub received: FlowCounters,
+    /// Whether this packet carries the first payload bytes of the flow, in either direction
+    pub is_first_payload: bool,
+}
+
+impl FlowContext {
+    /// Build the context of a packet opening a new flow, without tracking it
+    pub fn new(source_ip: IpAddr, source_port: u16, dest_ip: IpAddr, dest_port: u16) -> Self {
+        FlowContext {
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            direction: FlowDirection::Forward,
+            sent: FlowCounters::default(),
+            received: FlowCounters::default(),
+            is_first_payload: true,
+        }
+    }
+
+    /// Account a packet in its flow, returning its context
+    pub fn track(
+        source_ip: IpAddr,
+        source_port: u16,
+        dest_ip: IpAddr,
+        dest_port: u16,
+        payload_length: usize,
+    ) -> Self {
+        let source = (source_ip, source_port);
+        let dest = (dest_ip, dest_port);
+
+        ACTIVE_FLOWS.with(|flows| {
+            let mut flows = flows.borrow_mut();
+            let (key, direction) = match flows.contains_key(&(dest, source)) {
+                true => ((dest, source), FlowDirection::Reverse),
+                false => ((source, dest), FlowDirection::Forward),
+            };
+
+            let state = flows.entry(key).or_insert_with(|| FlowState {
+                forward: FlowCounters::default(),
+                reverse: FlowCounters::default(),
+                last_touch: capture_time(),
+            });
+            state.last_touch = capture_time();
+
+            let is_first_payload =
+                payload_length > 0 && state.forward.bytes == 0 && state.reverse.bytes == 0;
+
+            let (sent, received) = match direction {
+                FlowDirection::Forward => (&mut state.forward, state.reverse),
+                FlowDirection::Reverse => (&mut state.reverse, state.forward),
+            };
+            sent.packets += 1;
+            sent.bytes += payload_length;
+
+            FlowContext {
+                source_ip,
+                source_port,
+                dest_ip,
+                dest_port,
+                direction,
+                sent: *sent,
+                received,
+                is_first_payload,
+            }
+        })
+    }
+}
+
+/// Append a segment to the reassembly buffer of a flow, returning the accumulated payload
+pub(crate) fn append_to_parser<'a>(
+    parsers: &'a mut HashMap<FlowKey, ActiveParser>,
+    key: FlowKey,
+    packet: &[u8],
+) -> &'a mut Vec<u8> {
+    let parser = parsers.entry(key).or_insert_with(|| ActiveParser {
+        payload: vec![],
+        last_touch: capture_time(),
+    });
+    parser.payload.extend_from_slice(packet);
+    parser.last_touch = capture_time();
+
+    &mut parser.payload
+}
+
+/// Remove both directions of a flow from the active parsers
+pub(crate) fn evict_flow(source: (IpAddr, u16), dest: (IpAddr, u16)) {
+    let evict = |parsers: &RefCell<HashMap<FlowKey, ActiveParser>>| {
+        let mut parsers = parsers.borrow_mut();
+        parsers.remove(&(source, dest));
+        parsers.remove(&(dest, source));
+    };
+
+    ACTIVE_HTTP_PARSERS.with(evict);
+    ACTIVE_TLS_PARSERS.with(evict);
+    ACTIVE_SMTP_SESSIONS.with(|sessions| {
+        let mut sessions = sessions.borrow_mut();
+        sessions.remove(&(source, dest));
+        sessions.remove(&(dest, source));
+    });
+    ACTIVE_MAIL_SESSIONS.with(|sessions| {
+        let mut sessions = sessions.borrow_mut();
+        sessions.remove(&(source, dest));
+        sessions.remove(&(dest, source));
+    });
+    ACTIVE_FLOWS.with(|flows| {
+        let mut flows = flows.borrow_mut();
+        flows.remove(&(source, dest));
+        flows.remove(&(dest, source));
+    });
+}
+
+/// Remove the active parsers, sessions and flows which have not received data for at least
+/// `max_age` at the capture time `now`, and the IPv6 datagrams not reassembled before their
+/// timeout
+pub fn prune_stale(max_age: Duration, now: SystemTime) {
+    // State touched after `now`, by a packet captured out of order, is kept
+    let is_fresh = |last_touch: SystemTime| {
+        now.duration_since(last_touch)
+            .map_or(true, |age| age < max_age)
+    };
+    let prune = |parsers: &RefCell<HashMap<FlowKey, ActiveParser>>| {
+        parsers
+            .borrow_mut()
+            .retain(|_, parser| is_fresh(parser.last_touch));
+    };
+
+    ACTIVE_HTTP_PARSERS.with(prune);
+    ACTIVE_TLS_PARSERS.with(prune);
+    ACTIVE_SMTP_SESSIONS.with(|sessions| {
+        sessions
+            .borrow_mut()
+            .retain(|_, session| is_fresh(session.last_touch));
+    });
+    ACTIVE_MAIL_SESSIONS.with(|sessions| {
+        sessions
+            .borrow_mut()
+            .retain(|_, session| is_fresh(session.last_touch));
+    });
+    ACTIVE_FLOWS.with(|flows| {
+        flows
+            .borrow_mut()
+            .retain(|_, flow| is_fresh(flow.last_touch));
+    });
+    crate::expire_ipv6_datagrams(now);
+}
+
 /// IANA Well Known TCP/UDP Ports
 #[allow(non_snake_case)]
 mod WellKnownPorts {
     pub const HTTP_PORT: u16 = 80;
     pub const TLS_PORT: u16 = 443;
+    pub const QUIC_PORT: u16 = 443;
     pub const DNS_PORT: u16 = 53;
+    pub const MDNS_PORT: u16 = 5353;
     pub const MODBUS_PORT: u16 = 502;
+    pub const SMTP_PORT: u16 = 25;
+    pub const SMTP_SUBMISSION_PORT: u16 = 587;
+    pub const SMTPS_PORT: u16 = 465;
+    pub const POP3_PORT: u16 = 110;
+    pub const IMAP_PORT: u16 = 143;
+    pub const LDAP_PORT: u16 = 389;
+    pub const STUN_PORT: u16 = 3478;
+    pub const TELNET_PORT: u16 = 23;
+    pub const KERBEROS_PORT: u16 = 88;
+    pub const NBNS_PORT: u16 = 137;
+    pub const WIREGUARD_PORT: u16 = 51820;
+    pub const GTP_PORT: u16 = 2152;
+    pub const SYSLOG_PORT: u16 = 514;
+    pub const DHCPV6_CLIENT_PORT: u16 = 546;
+    pub const DHCPV6_SERVER_PORT: u16 = 547;
 }
 
 
@@ -52,6 +299,7 @@ mod HeaderNamesValues {
     pub const TRANSFER_ENCODING: &str = "Transfer-Encoding";
     pub const CONTENT_TYPE: &str = "Content-Type";
     pub const CONTENT_LENGTH: &str = "Content-Length";
+    pub const CONTENT_DISPOSITION: &str = "Content-Disposition";
     pub const CHUNKED: &str = "chunked";
 }
 
@@ -63,58 +311,340 @@ pub enum HttpPacketType {
 
 /// Build an application-layer packet from a transport-layer one, save it in a Parsed Packet
 pub fn handle_application_protocol(
-    source_ip: IpAddr,
-    source_port: u16,
-    dest_ip: IpAddr,
-    dest_port: u16,
+    flow: &FlowContext,
     is_fin: bool,
     packet: &[u8],
     parsed_packet: &mut ParsedPacket,
 ) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    let decoded_as = decode_as_dissector(flow, parsed_packet);
+    if let Some((dissector, to_server)) = decoded_as {
+        handle_decoded_as(dissector, to_server, flow, is_fin, packet, parsed_packet);
+    }
+
     match (source_port, dest_port) {
+        // Dissector forced by a Decode As rule, whatever the ports
+        _ if decoded_as.is_some() => (),
         (WellKnownPorts::HTTP_PORT, _) | (_, WellKnownPorts::HTTP_PORT) => {
             let http_type = match dest_port {
                 WellKnownPorts::HTTP_PORT => HttpPacketType::Request,
                 _ => HttpPacketType::Response,
             };
 
-            handle_http_packet(
-                source_ip,
-                source_port,
-                dest_ip,
-                dest_port,
-                http_type,
-                is_fin,
-                packet,
-                parsed_packet,
-            )
+            handle_http_packet(flow, http_type, is_fin, packet, parsed_packet)
         }
-        (WellKnownPorts::TLS_PORT, _) | (_, WellKnownPorts::TLS_PORT) => handle_tls_packet(
-            source_ip,
-            source_port,
-            dest_ip,
-            dest_port,
-            packet,
-            parsed_packet,
-        ),
-        (WellKnownPorts::DNS_PORT, _) | (_, WellKnownPorts::DNS_PORT) 
-        => handle_dns_packet(
-            source_ip,
-            source_port,
-            dest_ip,
-            dest_port,
-            packet,
-            parsed_packet,
-        ),
-        (WellKnownPorts::MODBUS_PORT, _) | (_, WellKnownPorts::MODBUS_PORT) => 
-        handle_modbus_packet(
+        (WellKnownPorts::TLS_PORT, _)
+        | (_, WellKnownPorts::TLS_PORT)
+        | (WellKnownPorts::SMTPS_PORT, _)
+        | (_, WellKnownPorts::SMTPS_PORT) => handle_tls_packet(flow, packet, parsed_packet),
+        (WellKnownPorts::DNS_PORT, _) | (_, WellKnownPorts::DNS_PORT) => {
+            // The port alone does not make a DNS message, the heuristics may still guess it
+            if looks_like_dns_message(packet) {
+                handle_dns_packet(flow, packet, parsed_packet)
+            } else if !packet.is_empty() {
+                debug!(
+                    "Not a DNS message on the DNS port: {}:{} > {}:{}; length: {}",
+                    source_ip,
+                    source_port,
+                    dest_ip,
+                    dest_port,
+                    packet.len()
+                );
+            }
+        }
+        (WellKnownPorts::MDNS_PORT, _) | (_, WellKnownPorts::MDNS_PORT) => {
+            handle_mdns_packet(flow, packet, parsed_packet)
+        }
+        (WellKnownPorts::SMTP_PORT, _)
+        | (_, WellKnownPorts::SMTP_PORT)
+        | (WellKnownPorts::SMTP_SUBMISSION_PORT, _)
+        | (_, WellKnownPorts::SMTP_SUBMISSION_PORT) => {
+            handle_smtp_packet(flow, packet, parsed_packet)
+        }
+        (WellKnownPorts::POP3_PORT, _) | (_, WellKnownPorts::POP3_PORT) => {
+            handle_pop3_packet(flow, packet, parsed_packet)
+        }
+        (WellKnownPorts::IMAP_PORT, _) | (_, WellKnownPorts::IMAP_PORT) => {
+            handle_imap_packet(flow, packet, parsed_packet)
+        }
+        (WellKnownPorts::LDAP_PORT, _) | (_, WellKnownPorts::LDAP_PORT) => {
+            handle_ldap_packet(flow, packet, parsed_packet)
+        }
+        (WellKnownPorts::STUN_PORT, _) | (_, WellKnownPorts::STUN_PORT) => {
+            handle_stun_packet(flow, packet, parsed_packet)
+        }
+        (WellKnownPorts::TELNET_PORT, _) | (_, WellKnownPorts::TELNET_PORT) => {
+            handle_telnet_packet(flow, packet, parsed_packet)
+        }
+        (WellKnownPorts::KERBEROS_PORT, _) | (_, WellKnownPorts::KERBEROS_PORT) => {
+            handle_kerberos_packet(flow, packet, parsed_packet)
+        }
+        (WellKnownPorts::NBNS_PORT, _) | (_, WellKnownPorts::NBNS_PORT) => {
+            handle_nbns_packet(flow, packet, parsed_packet)
+        }
+        (WellKnownPorts::WIREGUARD_PORT, _) | (_, WellKnownPorts::WIREGUARD_PORT) => {
+            handle_wireguard_packet(flow, packet, parsed_packet)
+        }
+        (WellKnownPorts::GTP_PORT, _) | (_, WellKnownPorts::GTP_PORT) => {
+            handle_gtp_packet(flow, packet, parsed_packet)
+        }
+        (WellKnownPorts::SYSLOG_PORT, _) | (_, WellKnownPorts::SYSLOG_PORT) => {
+            handle_syslog_packet(flow, packet, parsed_packet)
+        }
+        (WellKnownPorts::DHCPV6_CLIENT_PORT, _)
+        | (_, WellKnownPorts::DHCPV6_CLIENT_PORT)
+        | (WellKnownPorts::DHCPV6_SERVER_PORT, _)
+        | (_, WellKnownPorts::DHCPV6_SERVER_PORT) => {
+            handle_dhcpv6_packet(flow, packet, parsed_packet)
+        }
+        (WellKnownPorts::MODBUS_PORT, _) | (_, WellKnownPorts::MODBUS_PORT) => {
+            handle_modbus_packet(flow, packet, parsed_packet)
+        }
+        _ => {
+            if !packet.is_empty() {
+                debug!(
+                    "Unknown application protocol: {}:{} > {}:{}; length: {}",
+                    source_ip,
+                    source_port,
+                    dest_ip,
+                    dest_port,
+                    packet.len()
+                );
+            }
+        }
+    }
+
+    if let Some(SerializablePacket::MalformedPacket(reason)) =
+        parsed_packet.get_application_layer_packet()
+    {
+        warn!(
+            "{}: {}:{} > {}:{}; length: {}",
+            reason,
             source_ip,
             source_port,
             dest_ip,
             dest_port,
-            packet,
-            parsed_packet,
-        ),
-        _ => (),
+            packet.len()
+        );
+    }
+
+    if is_fin {
+        evict_flow((source_ip, source_port), (dest_ip, dest_port));
+    }
+}
+
+/// Build an application-layer packet with the dissector forced by a Decode As rule, the packets
+/// sent to the server side being the HTTP requests
+fn handle_decoded_as(
+    dissector: Dissector,
+    to_server: bool,
+    flow: &FlowContext,
+    is_fin: bool,
+    packet: &[u8],
+    parsed_packet: &mut ParsedPacket,
+) {
+    match dissector {
+        Dissector::Http => {
+            let http_type = match to_server {
+                true => HttpPacketType::Request,
+                false => HttpPacketType::Response,
+            };
+            handle_http_packet(flow, http_type, is_fin, packet, parsed_packet)
+        }
+        Dissector::Tls => handle_tls_packet(flow, packet, parsed_packet),
+        Dissector::Dtls => handle_dtls_packet(flow, packet, parsed_packet),
+        Dissector::Quic => handle_quic_packet(flow, packet, parsed_packet),
+        Dissector::Dns => handle_dns_packet(flow, packet, parsed_packet),
+        Dissector::Smtp => handle_smtp_packet(flow, packet, parsed_packet),
+        Dissector::Pop3 => handle_pop3_packet(flow, packet, parsed_packet),
+        Dissector::Imap => handle_imap_packet(flow, packet, parsed_packet),
+        Dissector::Ldap => handle_ldap_packet(flow, packet, parsed_packet),
+        Dissector::Kerberos => handle_kerberos_packet(flow, packet, parsed_packet),
+        Dissector::Stun => handle_stun_packet(flow, packet, parsed_packet),
+        Dissector::Telnet => handle_telnet_packet(flow, packet, parsed_packet),
+        Dissector::Nbns => handle_nbns_packet(flow, packet, parsed_packet),
+        Dissector::WireGuard => handle_wireguard_packet(flow, packet, parsed_packet),
+        Dissector::Gtp => handle_gtp_packet(flow, packet, parsed_packet),
+        Dissector::Syslog => handle_syslog_packet(flow, packet, parsed_packet),
+        Dissector::Dhcpv6 => handle_dhcpv6_packet(flow, packet, parsed_packet),
+        Dissector::Modbus => handle_modbus_packet(flow, packet, parsed_packet),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    use pnet::packet::ethernet::EtherTypes;
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::packet::tcp::TcpFlags;
+
+    use super::{
+        handle_application_protocol, prune_stale, FlowContext, FlowCounters, FlowDirection,
+        ACTIVE_HTTP_PARSERS, ACTIVE_MAIL_SESSIONS, ACTIVE_SMTP_SESSIONS,
+    };
+    use crate::serializable_packet::ParsedPacket;
+    use crate::test_util::{
+        build_test_ethernet_frame, build_test_ipv4_packet, build_test_tcp_segment, CLIENT, SERVER,
+    };
+    use crate::{parse_pcap_record, LinkTypes, PcapRecord};
+
+    const PARTIAL_REQUEST: &[u8] = b"GET / HTTP/1.1\r\nHost: ";
+
+    #[test]
+    fn fin_evicts_active_parser() {
+        let client = IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10));
+        let server = IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11));
+        let mut parsed_packet = ParsedPacket::new(0);
+
+        handle_application_protocol(
+            &FlowContext::new(client, 4444, server, 80),
+            false,
+            PARTIAL_REQUEST,
+            &mut parsed_packet,
+        );
+        handle_application_protocol(
+            &FlowContext::new(client, 5555, server, 80),
+            false,
+            PARTIAL_REQUEST,
+            &mut parsed_packet,
+        );
+        handle_application_protocol(
+            &FlowContext::new(server, 80, client, 4444),
+            true,
+            &[],
+            &mut parsed_packet,
+        );
+
+        ACTIVE_HTTP_PARSERS.with(|parsers| {
+            let parsers = parsers.borrow();
+            assert!(!parsers.contains_key(&((client, 4444), (server, 80))));
+            assert!(parsers.contains_key(&((client, 5555), (server, 80))));
+        });
+    }
+
+    #[test]
+    fn prune_stale_parsers() {
+        let client = IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10));
+        let server = IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11));
+        let mut parsed_packet = ParsedPacket::new(0);
+
+        handle_application_protocol(
+            &FlowContext::new(client, 4444, server, 80),
+            false,
+            PARTIAL_REQUEST,
+            &mut parsed_packet,
+        );
+
+        prune_stale(Duration::from_secs(3600), SystemTime::now());
+        ACTIVE_HTTP_PARSERS.with(|parsers| assert_eq!(parsers.borrow().len(), 1));
+
+        prune_stale(Duration::ZERO, SystemTime::now());
+        ACTIVE_HTTP_PARSERS.with(|parsers| assert!(parsers.borrow().is_empty()));
+    }
+
+    #[test]
+    fn parsers_aged_in_capture_time() {
+        let captured = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let tcp_segment = build_test_tcp_segment(6666, 80, TcpFlags::ACK, 0, 0, PARTIAL_REQUEST);
+        let ip_packet =
+            build_test_ipv4_packet(CLIENT, SERVER, 64, IpNextHeaderProtocols::Tcp, &tcp_segment);
+        let frame = build_test_ethernet_frame(EtherTypes::Ipv4, &ip_packet);
+        let record = PcapRecord {
+            link_type: LinkTypes::ETHERNET,
+            timestamp: captured,
+            captured_length: frame.len() as u32,
+            original_length: frame.len() as u32,
+            data: frame,
+        };
+        parse_pcap_record(&record, 0);
+
+        // Idle for a minute of capture time, although captured years ago
+        let key = ((IpAddr::V4(CLIENT), 6666), (IpAddr::V4(SERVER), 80));
+        let max_age = Duration::from_secs(120);
+        prune_stale(max_age, captured + Duration::from_secs(60));
+        ACTIVE_HTTP_PARSERS.with(|parsers| assert!(parsers.borrow().contains_key(&key)));
+
+        prune_stale(max_age, captured + max_age);
+        ACTIVE_HTTP_PARSERS.with(|parsers| assert!(!parsers.borrow().contains_key(&key)));
+    }
+
+    #[test]
+    fn prune_stale_sessions() {
+        let client = IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10));
+        let server = IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11));
+        let mut parsed_packet = ParsedPacket::new(0);
+
+        handle_application_protocol(
+            &FlowContext::new(client, 4444, server, 25),
+            false,
+            b"EHLO client\r\n",
+            &mut parsed_packet,
+        );
+        handle_application_protocol(
+            &FlowContext::new(client, 4444, server, 143),
+            false,
+            b"a1 NOOP\r\n",
+            &mut parsed_packet,
+        );
+
+        prune_stale(Duration::from_secs(3600), SystemTime::now());
+        ACTIVE_SMTP_SESSIONS.with(|sessions| assert_eq!(sessions.borrow().len(), 1));
+        ACTIVE_MAIL_SESSIONS.with(|sessions| assert_eq!(sessions.borrow().len(), 1));
+
+        prune_stale(Duration::ZERO, SystemTime::now());
+        ACTIVE_SMTP_SESSIONS.with(|sessions| assert!(sessions.borrow().is_empty()));
+        ACTIVE_MAIL_SESSIONS.with(|sessions| assert!(sessions.borrow().is_empty()));
+    }
+
+    #[test]
+    fn first_payload_flag_once_per_flow() {
+        let client = IpAddr::V4(Ipv4Addr::new(10, 10, 10, 12));
+        let server = IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11));
+
+        let syn = FlowContext::track(client, 4444, server, 80, 0);
+        assert!(!syn.is_first_payload);
+        assert_eq!(syn.direction, FlowDirection::Forward);
+
+        let request = FlowContext::track(client, 4444, server, 80, 100);
+        assert!(request.is_first_payload);
+        assert_eq!(
+            request.sent,
+            FlowCounters {
+                packets: 2,
+                bytes: 100
+            }
+        );
+
+        let response = FlowContext::track(server, 80, client, 4444, 200);
+        assert!(!response.is_first_payload);
+        assert_eq!(response.direction, FlowDirection::Reverse);
+        assert_eq!(
+            response.sent,
+            FlowCounters {
+                packets: 1,
+                bytes: 200
+            }
+        );
+        assert_eq!(
+            response.received,
+            FlowCounters {
+                packets: 2,
+                bytes: 100
+            }
+        );
+
+        assert!(!FlowContext::track(client, 4444, server, 80, 100).is_first_payload);
+        assert!(FlowContext::track(client, 5555, server, 80, 100).is_first_payload);
     }
 }
@@ -1,38 +1,39 @@
-use std::net::IpAddr;
+use super::{FlowContext, WellKnownPorts};
 
 use log::debug;
 
+use crate::serializable_packet::util::contains_tcp;
 use crate::serializable_packet::{application::SerializableModbusPacket, ParsedPacket, SerializablePacket};
 
-pub fn handle_modbus_packet(
-    source_ip: IpAddr,
-    source_port: u16,
-    dest_ip: IpAddr,
-    dest_port: u16,
-    packet: &[u8],
-    parsed_packet: &mut ParsedPacket,
-) {
-    if let Ok(modbus_packet) = 
-    ModbusPacket::parse(packet) {
-        debug!(
-            "Modbus Packet: ",
-
-        );
-
+pub fn handle_modbus_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    // Modbus TCP prefixes the PDU with the MBAP header, Modbus RTU frames remain over UDP
+    let modbus_packet = match contains_tcp(parsed_packet) {
+        true => parse_modbus_tcp(packet),
+        false => ModbusPacket::parse(packet),
+    };
+    if let Ok(modbus_packet) = modbus_packet {
+        debug!("Modbus Packet: ",);
+
+        let mut serializable_packet = SerializableModbusPacket::from(&modbus_packet);
+        serializable_packet.is_response = flow.source_port == WellKnownPorts::MODBUS_PORT;
         parsed_packet.set_application_layer_packet(Some(SerializablePacket::ModbusPacket(
-            SerializableModbusPacket::from(&modbus_packet),
+            serializable_packet,
         )));
     } else {
-        debug!("Malformed DNS Packet");
+        debug!("Malformed Modbus Packet");
         parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
-            "Malformed DNS Packet".to_string(),
+            "Malformed Modbus Packet".to_string(),
         )));
     }
-
 }
 
 use std::convert::TryInto;
 
+/// Offset of the end of the Length field of the MBAP header, from which it counts the bytes
+const MBAP_LENGTH_END: usize = 6;
+
+/// Bit set in the function code of an exception response, over the function code of its request
+pub const EXCEPTION_FLAG: u8 = 0x80;
 
 #[derive(Debug)]
 pub enum ModbusError {
@@ -47,6 +48,8 @@ pub struct ModbusPacket {
     pub function_code: u8,
     pub data: Vec<u8>,
     pub crc: Option<u16>,
+    /// Transaction ID of the MBAP header, shared by a request and its response (Modbus TCP only)
+    pub transaction_id: Option<u16>,
 }
 
 pub trait Parse {
@@ -81,6 +84,7 @@ impl Parse for ModbusPacket {
             function_code,
             data,
             crc: Some(crc),
+            transaction_id: None,
         })
     }
 }
@@ -112,6 +116,7 @@ pub fn parse_modbus_rtu(payload: &[u8]) -> Result<ModbusPacket, ModbusError> {
         function_code,
         data,
         crc: Some(crc),
+        transaction_id: None,
     })
 }
 
@@ -128,19 +133,27 @@ pub fn parse_modbus_tcp(payload: &[u8]) -> Result<ModbusPacket, ModbusError> {
     let unit_id = payload[6];
     let function_code = payload[7];
 
-    // Verify the function code is valid (standard Modbus function codes range from 1 to 127)
-    if function_code == 0 || function_code > 127 {
+    // The length counts the Unit ID, Function Code and data, which must all be received
+    let end = MBAP_LENGTH_END + length as usize;
+    if length < 2 || end > payload.len() {
+        return Err(ModbusError::InvalidLength);
+    }
+
+    // Verify the function code is valid (standard Modbus function codes range from 1 to 127, with
+    // the exception flag set in an exception response)
+    if function_code & !EXCEPTION_FLAG == 0 {
         return Err(ModbusError::InvalidFunctionCode);
     }
 
-    // Extract data
-    let data = payload[8..].to_vec();
+    // Extract data, up to the end of the PDU
+    let data = payload[8..end].to_vec();
 
     Ok(ModbusPacket {
         address: unit_id,
         function_code,
         data,
         crc: None,
+        transaction_id: Some(transaction_id),
     })
 }
 
@@ -153,4 +166,61 @@ pub fn parse_modbus_rtu_over_tcp(payload: &[u8]) -> Result<ModbusPacket, ModbusE
     parse_modbus_rtu(&payload[6..])
 }
 
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
 
+    use pnet::packet::tcp::{MutableTcpPacket, TcpFlags};
+
+    use super::{parse_modbus_tcp, ModbusError};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+    use crate::transport::handle_tcp_packet;
+
+    /// Read Holding Registers request, for 2 registers from address 0 of unit 1
+    const READ_HOLDING_REGISTERS: &[u8] = &[
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x02,
+    ];
+
+    #[test]
+    fn pdu_bounded_by_length() {
+        let mut payload = READ_HOLDING_REGISTERS.to_vec();
+        // Start of a second request of the segment
+        payload.extend_from_slice(&[0x00, 0x02, 0x00, 0x00]);
+
+        let modbus_packet = parse_modbus_tcp(&payload).unwrap();
+        assert_eq!(modbus_packet.transaction_id, Some(1));
+        assert_eq!(modbus_packet.data, [0x00, 0x00, 0x00, 0x02]);
+    }
+
+    #[test]
+    fn inflated_length() {
+        let mut payload = READ_HOLDING_REGISTERS.to_vec();
+        payload[4..6].copy_from_slice(&u16::MAX.to_be_bytes());
+        assert!(matches!(
+            parse_modbus_tcp(&payload),
+            Err(ModbusError::InvalidLength)
+        ));
+
+        let mut tcp_buffer = vec![0u8; 20 + payload.len()];
+        let mut tcp_packet = MutableTcpPacket::new(&mut tcp_buffer).unwrap();
+        tcp_packet.set_source(4444);
+        tcp_packet.set_destination(502);
+        tcp_packet.set_data_offset(5);
+        tcp_packet.set_flags(TcpFlags::ACK | TcpFlags::PSH);
+        tcp_packet.set_payload(&payload);
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_tcp_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+            &tcp_buffer,
+            &mut parsed_packet,
+        );
+        match parsed_packet.get_application_layer_packet() {
+            Some(SerializablePacket::MalformedPacket(reason)) => {
+                assert_eq!(reason, "Malformed Modbus Packet")
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,343 @@
+//! NetBIOS Name Service Packet parsing
+//!
+//! The NBNS messages (RFC 1002) reuse the DNS header, with the name operation as opcode and the
+//! NetBIOS flags. Their names are first-level encoded: the 16 bytes of the NetBIOS name (15
+//! characters padded with spaces, then a suffix giving the service) are split in half bytes,
+//! each stored as a letter from `A`. The addresses of the NB resource records are decoded
+
+use std::net::Ipv4Addr;
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::SerializableNbnsPacket, ParsedPacket, SerializablePacket,
+};
+
+use super::FlowContext;
+
+/// NBNS Opcodes
+#[allow(non_snake_case)]
+pub mod NbnsOpcodes {
+    pub const QUERY: u8 = 0;
+    pub const REGISTRATION: u8 = 5;
+    pub const RELEASE: u8 = 6;
+    pub const WACK: u8 = 7;
+    pub const REFRESH: u8 = 8;
+    pub const MULTI_HOMED_REGISTRATION: u8 = 15;
+}
+
+/// NBNS Question and Resource Record Types
+#[allow(non_snake_case)]
+pub mod NbnsRecordTypes {
+    pub const NB: u16 = 0x0020;
+    pub const NBSTAT: u16 = 0x0021;
+}
+
+/// NBNS Flags: the response bit, the NM_FLAGS and the masks of the opcode and of the RCODE
+#[allow(non_snake_case)]
+pub mod NbnsFlags {
+    pub const RESPONSE: u16 = 0x8000;
+    pub const OPCODE_MASK: u16 = 0x7800;
+    pub const AUTHORITATIVE_ANSWER: u16 = 0x0400;
+    pub const TRUNCATED: u16 = 0x0200;
+    pub const RECURSION_DESIRED: u16 = 0x0100;
+    pub const RECURSION_AVAILABLE: u16 = 0x0080;
+    pub const BROADCAST: u16 = 0x0010;
+    pub const RCODE_MASK: u16 = 0x000f;
+}
+
+const HEADER_LENGTH: usize = 12;
+/// Length of a first-level encoded NetBIOS name, twice its 16 bytes
+const ENCODED_NAME_LENGTH: usize = 32;
+const NAME_POINTER: u8 = 0xc0;
+/// Length of an NB address entry: NB_FLAGS, then the IPv4 address
+const NB_ADDRESS_LENGTH: usize = 6;
+
+/// Errors occurring during the parsing of an NBNS message
+#[derive(Debug)]
+pub enum NbnsError {
+    Truncated,
+    InvalidName,
+}
+
+/// NetBIOS name decoded from its first-level encoding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetbiosName {
+    /// Name without its padding spaces
+    pub name: String,
+    /// Last byte of the name, giving the service (e.g. 0x00 workstation, 0x20 file server)
+    pub suffix: u8,
+    /// NetBIOS scope following the name, when not empty
+    pub scope: Option<String>,
+}
+
+/// NBNS Question or Resource Record, with the addresses of the NB records
+#[derive(Debug)]
+pub struct NbnsEntry {
+    pub name: NetbiosName,
+    pub entry_type: u16,
+    pub addresses: Vec<Ipv4Addr>,
+}
+
+/// NBNS Message: header, questions and resource records
+#[derive(Debug)]
+pub struct NbnsMessage {
+    pub transaction_id: u16,
+    pub flags: u16,
+    pub questions: Vec<NbnsEntry>,
+    /// Answer, authority and additional records
+    pub records: Vec<NbnsEntry>,
+}
+
+impl NbnsMessage {
+    /// Parse an NBNS message filling a UDP payload
+    pub fn parse(packet: &[u8]) -> Result<NbnsMessage, NbnsError> {
+        let header = packet.get(..HEADER_LENGTH).ok_or(NbnsError::Truncated)?;
+        let read_u16 = |offset: usize| u16::from_be_bytes([header[offset], header[offset + 1]]);
+        let question_count = read_u16(4);
+        let record_count = read_u16(6) as usize + read_u16(8) as usize + read_u16(10) as usize;
+
+        let mut offset = HEADER_LENGTH;
+        let mut questions = vec![];
+        for _ in 0..question_count {
+            let (name, end) = parse_name(packet, offset)?;
+            let fields = packet.get(end..end + 4).ok_or(NbnsError::Truncated)?;
+            questions.push(NbnsEntry {
+                name,
+                entry_type: u16::from_be_bytes([fields[0], fields[1]]),
+                addresses: vec![],
+            });
+            offset = end + 4;
+        }
+
+        let mut records = vec![];
+        for _ in 0..record_count {
+            let (name, end) = parse_name(packet, offset)?;
+            // Type, class, TTL and data length
+            let fields = packet.get(end..end + 10).ok_or(NbnsError::Truncated)?;
+            let entry_type = u16::from_be_bytes([fields[0], fields[1]]);
+            let data_length = u16::from_be_bytes([fields[8], fields[9]]) as usize;
+            let data = packet
+                .get(end + 10..end + 10 + data_length)
+                .ok_or(NbnsError::Truncated)?;
+
+            let addresses = match entry_type {
+                NbnsRecordTypes::NB => data
+                    .chunks_exact(NB_ADDRESS_LENGTH)
+                    .map(|entry| Ipv4Addr::new(entry[2], entry[3], entry[4], entry[5]))
+                    .collect(),
+                _ => vec![],
+            };
+            records.push(NbnsEntry {
+                name,
+                entry_type,
+                addresses,
+            });
+            offset = end + 10 + data_length;
+        }
+
+        Ok(NbnsMessage {
+            transaction_id: read_u16(0),
+            flags: read_u16(2),
+            questions,
+            records,
+        })
+    }
+
+    /// Get the opcode of the name operation
+    pub fn opcode(&self) -> u8 {
+        ((self.flags & NbnsFlags::OPCODE_MASK) >> 11) as u8
+    }
+}
+
+/// Parse the encoded NetBIOS name and the scope labels at an offset, following a compression
+/// pointer once, getting the name and the offset following it
+fn parse_name(packet: &[u8], offset: usize) -> Result<(NetbiosName, usize), NbnsError> {
+    let length = *packet.get(offset).ok_or(NbnsError::Truncated)?;
+    if length & NAME_POINTER == NAME_POINTER {
+        let pointer = packet.get(offset..offset + 2).ok_or(NbnsError::Truncated)?;
+        let target = (u16::from_be_bytes([pointer[0], pointer[1]]) & 0x3fff) as usize;
+        if packet
+            .get(target)
+            .is_some_and(|&length| length & NAME_POINTER == NAME_POINTER)
+        {
+            return Err(NbnsError::InvalidName);
+        }
+        let (name, _) = parse_name(packet, target)?;
+        return Ok((name, offset + 2));
+    }
+    if length as usize != ENCODED_NAME_LENGTH {
+        return Err(NbnsError::InvalidName);
+    }
+
+    let encoded = packet
+        .get(offset + 1..offset + 1 + ENCODED_NAME_LENGTH)
+        .ok_or(NbnsError::Truncated)?;
+    let (name, suffix) = decode_first_level(encoded)?;
+
+    let mut offset = offset + 1 + ENCODED_NAME_LENGTH;
+    let mut scope_labels = vec![];
+    loop {
+        let length = *packet.get(offset).ok_or(NbnsError::Truncated)? as usize;
+        offset += 1;
+        if length == 0 {
+            break;
+        }
+        let label = packet
+            .get(offset..offset + length)
+            .ok_or(NbnsError::Truncated)?;
+        scope_labels.push(String::from_utf8_lossy(label).into_owned());
+        offset += length;
+    }
+
+    let scope = (!scope_labels.is_empty()).then(|| scope_labels.join("."));
+    Ok((
+        NetbiosName {
+            name,
+            suffix,
+            scope,
+        },
+        offset,
+    ))
+}
+
+/// Decode a first-level encoded NetBIOS name, each pair of letters from `A` giving a byte,
+/// getting the name without its padding and its suffix
+fn decode_first_level(encoded: &[u8]) -> Result<(String, u8), NbnsError> {
+    if !encoded.iter().all(|byte| (b'A'..=b'P').contains(byte)) {
+        return Err(NbnsError::InvalidName);
+    }
+
+    let bytes: Vec<u8> = encoded
+        .chunks_exact(2)
+        .map(|pair| ((pair[0] - b'A') << 4) | (pair[1] - b'A'))
+        .collect();
+    let name = String::from_utf8_lossy(&bytes[..15]);
+
+    Ok((name.trim_end_matches(' ').to_string(), bytes[15]))
+}
+
+/// Build a NBNS packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_nbns_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    if let Ok(nbns_message) = NbnsMessage::parse(packet) {
+        debug!(
+            "NBNS Packet: {}:{} > {}:{}; ID: {}, Opcode: {}, Questions: {}, Records: {}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            nbns_message.transaction_id,
+            nbns_message.opcode(),
+            nbns_message.questions.len(),
+            nbns_message.records.len(),
+        );
+
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::NbnsPacket(
+            SerializableNbnsPacket::from(&nbns_message),
+        )));
+    } else {
+        debug!("Malformed NBNS Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed NBNS Packet".to_string(),
+        )));
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{handle_nbns_packet, FlowContext};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    // Broadcast name query for WORKSTATION<00>
+    const NAME_QUERY: &[u8] = &[
+        0x81, 0x2c, 0x01, 0x10, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, b'F', b'H',
+        b'E', b'P', b'F', b'C', b'E', b'L', b'F', b'D', b'F', b'E', b'E', b'B', b'F', b'E', b'E',
+        b'J', b'E', b'P', b'E', b'O', b'C', b'A', b'C', b'A', b'C', b'A', b'C', b'A', b'A', b'A',
+        0x00, 0x00, 0x20, 0x00, 0x01,
+    ];
+
+    #[test]
+    fn name_query() {
+        match nbns_packet(NAME_QUERY, 137).get_application_layer_packet() {
+            Some(SerializablePacket::NbnsPacket(nbns_packet)) => {
+                assert_eq!(nbns_packet.transaction_id, 0x812c);
+                assert!(!nbns_packet.is_response);
+                assert_eq!(nbns_packet.opcode, "Name Query (0)");
+                assert_eq!(nbns_packet.flags, vec!["RD", "B"]);
+                assert_eq!(nbns_packet.name.as_deref(), Some("WORKSTATION"));
+                assert_eq!(nbns_packet.name_suffix, Some(0x00));
+                assert_eq!(nbns_packet.record_type.as_deref(), Some("NB (0x0020)"));
+                assert!(nbns_packet.addresses.is_empty());
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn name_query_response() {
+        let response = build_test_name_query_response(Ipv4Addr::new(192, 168, 1, 10));
+
+        match nbns_packet(&response, 4444).get_application_layer_packet() {
+            Some(SerializablePacket::NbnsPacket(nbns_packet)) => {
+                assert!(nbns_packet.is_response);
+                assert_eq!(nbns_packet.flags, vec!["AA", "RD"]);
+                assert_eq!(nbns_packet.name.as_deref(), Some("WORKSTATION"));
+                assert_eq!(nbns_packet.addresses, vec![Ipv4Addr::new(192, 168, 1, 10)]);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_nbns_packet() {
+        let mut invalid_name = NAME_QUERY.to_vec();
+        invalid_name[13] = b'z';
+
+        for packet in [&NAME_QUERY[..30], invalid_name.as_slice()] {
+            match nbns_packet(packet, 137).get_application_layer_packet() {
+                Some(SerializablePacket::MalformedPacket(str)) => {
+                    assert_eq!(str, "Malformed NBNS Packet")
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    ///////////////////// Utils
+
+    /// Build the positive response to the name query: the queried name as an NB answer with the
+    /// address of its owner
+    pub fn build_test_name_query_response(address: Ipv4Addr) -> Vec<u8> {
+        let mut response = NAME_QUERY.to_vec();
+        response[2..4].copy_from_slice(&[0x85, 0x00]);
+        response[4..8].copy_from_slice(&[0x00, 0x00, 0x00, 0x01]);
+        response.extend_from_slice(&[0x00, 0x04, 0x93, 0xe0, 0x00, 0x06, 0x00, 0x00]);
+        response.extend_from_slice(&address.octets());
+        response
+    }
+
+    fn nbns_packet(payload: &[u8], dest_port: u16) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_nbns_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)),
+                137,
+                IpAddr::V4(Ipv4Addr::new(192, 168, 1, 255)),
+                dest_port,
+            ),
+            payload,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+}
@@ -0,0 +1,279 @@
+//! QUIC Packet parsing
+//!
+//! Only the unprotected part of long-header packets is decoded (version, connection IDs, token
+//! length), the protected payload is left untouched
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::SerializableQuicPacket, ParsedPacket, SerializablePacket,
+};
+
+use super::{FlowContext, WellKnownPorts};
+
+/// QUIC Versions
+#[allow(non_snake_case)]
+pub mod QuicVersions {
+    pub const NEGOTIATION: u32 = 0x00000000;
+    pub const V1: u32 = 0x00000001;
+    pub const V2: u32 = 0x6b3343cf;
+}
+
+const LONG_HEADER_BIT: u8 = 0x80;
+const FIXED_BIT: u8 = 0x40;
+const MAX_CONNECTION_ID_LENGTH: usize = 20;
+
+/// QUIC Long Header Packet Types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuicPacketType {
+    Initial,
+    ZeroRtt,
+    Handshake,
+    Retry,
+    VersionNegotiation,
+}
+
+/// Errors occurring during the parsing of a QUIC long header
+#[derive(Debug)]
+pub enum QuicError {
+    NotLongHeader,
+    Truncated,
+    InvalidConnectionIdLength(usize),
+}
+
+/// Unprotected fields of a QUIC long header packet
+#[derive(Debug)]
+pub struct QuicLongHeader {
+    pub packet_type: QuicPacketType,
+    pub version: u32,
+    pub destination_connection_id: Vec<u8>,
+    pub source_connection_id: Vec<u8>,
+    pub token_length: Option<u64>,
+    pub length: Option<u64>,
+}
+
+impl QuicLongHeader {
+    /// Parse the long header at the start of a UDP payload
+    pub fn parse(packet: &[u8]) -> Result<QuicLongHeader, QuicError> {
+        let first = *packet.first().ok_or(QuicError::Truncated)?;
+        if first & LONG_HEADER_BIT == 0 {
+            return Err(QuicError::NotLongHeader);
+        }
+
+        let version_bytes = packet.get(1..5).ok_or(QuicError::Truncated)?;
+        let version = u32::from_be_bytes(version_bytes.try_into().unwrap());
+        let mut offset = 5;
+
+        let destination_connection_id = read_connection_id(packet, &mut offset, version)?;
+        let source_connection_id = read_connection_id(packet, &mut offset, version)?;
+
+        let packet_type = packet_type(first, version);
+        let mut token_length = None;
+        let mut length = None;
+
+        if is_known_version(version) {
+            if packet_type == QuicPacketType::Initial {
+                let token = read_varint(packet, &mut offset)?;
+                offset = offset
+                    .checked_add(token as usize)
+                    .filter(|end| *end <= packet.len())
+                    .ok_or(QuicError::Truncated)?;
+                token_length = Some(token);
+            }
+
+            if packet_type != QuicPacketType::Retry {
+                length = Some(read_varint(packet, &mut offset)?);
+            }
+        }
+
+        Ok(QuicLongHeader {
+            packet_type,
+            version,
+            destination_connection_id,
+            source_connection_id,
+            token_length,
+            length,
+        })
+    }
+}
+
+/// Check if a UDP datagram on the QUIC port starts with a QUIC long header
+pub fn is_quic_long_header(source_port: u16, dest_port: u16, packet: &[u8]) -> bool {
+    let on_quic_port =
+        source_port == WellKnownPorts::QUIC_PORT || dest_port == WellKnownPorts::QUIC_PORT;
+
+    match packet.first() {
+        Some(first) => {
+            on_quic_port
+                && first & LONG_HEADER_BIT != 0
+                && first & FIXED_BIT != 0
+                && packet.len() >= 7
+        }
+        None => false,
+    }
+}
+
+/// Check if the fields following the connection IDs are known for a QUIC version
+pub fn is_known_version(version: u32) -> bool {
+    matches!(version, QuicVersions::V1 | QuicVersions::V2) || version >> 8 == 0xff0000
+}
+
+/// Get the packet type from the first byte, whose encoding depends on the version
+fn packet_type(first: u8, version: u32) -> QuicPacketType {
+    let type_bits = (first >> 4) & 0x03;
+
+    match (version, type_bits) {
+        (QuicVersions::NEGOTIATION, _) => QuicPacketType::VersionNegotiation,
+        (QuicVersions::V2, 0) => QuicPacketType::Retry,
+        (QuicVersions::V2, 1) => QuicPacketType::Initial,
+        (QuicVersions::V2, 2) => QuicPacketType::ZeroRtt,
+        (QuicVersions::V2, _) => QuicPacketType::Handshake,
+        (_, 0) => QuicPacketType::Initial,
+        (_, 1) => QuicPacketType::ZeroRtt,
+        (_, 2) => QuicPacketType::Handshake,
+        _ => QuicPacketType::Retry,
+    }
+}
+
+fn read_connection_id(
+    packet: &[u8],
+    offset: &mut usize,
+    version: u32,
+) -> Result<Vec<u8>, QuicError> {
+    let length = *packet.get(*offset).ok_or(QuicError::Truncated)? as usize;
+    if is_known_version(version) && length > MAX_CONNECTION_ID_LENGTH {
+        return Err(QuicError::InvalidConnectionIdLength(length));
+    }
+
+    let connection_id = packet
+        .get(*offset + 1..*offset + 1 + length)
+        .ok_or(QuicError::Truncated)?;
+    *offset += 1 + length;
+
+    Ok(connection_id.to_vec())
+}
+
+/// Read a variable-length integer (RFC 9000 16)
+fn read_varint(packet: &[u8], offset: &mut usize) -> Result<u64, QuicError> {
+    let first = *packet.get(*offset).ok_or(QuicError::Truncated)?;
+    let length = 1 << (first >> 6);
+    let bytes = packet
+        .get(*offset..*offset + length)
+        .ok_or(QuicError::Truncated)?;
+
+    let value = bytes[1..]
+        .iter()
+        .fold((first & 0x3f) as u64, |acc, byte| (acc << 8) | *byte as u64);
+    *offset += length;
+
+    Ok(value)
+}
+
+/// Build a QUIC packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_quic_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    if let Ok(quic_header) = QuicLongHeader::parse(packet) {
+        debug!(
+            "QUIC Packet: {}:{} > {}:{}; Type: {:?}, Version: {:#010x}, DCID: {:02x?}, SCID: {:02x?}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            quic_header.packet_type,
+            quic_header.version,
+            quic_header.destination_connection_id,
+            quic_header.source_connection_id,
+        );
+
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::QuicPacket(
+            SerializableQuicPacket::from(&quic_header),
+        )));
+    } else {
+        debug!("Malformed QUIC Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed QUIC Packet".to_string(),
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{handle_quic_packet, is_quic_long_header, FlowContext};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    // Client Initial header from RFC 9001 Appendix A.2, followed by part of the protected payload
+    const V1_INITIAL: &[u8] = &[
+        0xc3, 0x00, 0x00, 0x00, 0x01, 0x08, 0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08, 0x00,
+        0x00, 0x44, 0x9e, 0x7b, 0x9a, 0xec, 0x34, 0xd1, 0xb1, 0xc9, 0x8d, 0xd7, 0x68, 0x9f, 0xb8,
+    ];
+
+    #[test]
+    fn quic_long_header_detection() {
+        assert!(is_quic_long_header(4444, 443, V1_INITIAL));
+        assert!(!is_quic_long_header(4444, 8443, V1_INITIAL));
+        assert!(!is_quic_long_header(
+            4444,
+            443,
+            &[0x43, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00]
+        ));
+    }
+
+    #[test]
+    fn v1_initial_quic_packet() {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_quic_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                443,
+            ),
+            V1_INITIAL,
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::QuicPacket(new_quic_packet) => {
+                assert_eq!(new_quic_packet.version, "QUICv1 (0x00000001)");
+                assert_eq!(new_quic_packet.packet_type, "Initial");
+                assert_eq!(
+                    new_quic_packet.destination_connection_id,
+                    vec![0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08]
+                );
+                assert!(new_quic_packet.source_connection_id.is_empty());
+                assert_eq!(new_quic_packet.token_length, Some(0));
+                assert_eq!(new_quic_packet.length, Some(0x049e));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_quic_packet() {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_quic_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                443,
+            ),
+            &V1_INITIAL[..10],
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::MalformedPacket(str) => assert_eq!(str, "Malformed QUIC Packet"),
+            _ => unreachable!(),
+        };
+    }
+}
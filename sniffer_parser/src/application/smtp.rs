@@ -0,0 +1,463 @@
+//! SMTP Packet parsing
+//!
+//! Each segment is split in lines: client segments carry commands, server segments carry numeric
+//! replies. The state of each session is tracked to recognize the message content sent after
+//! `DATA`, the responses to the challenges of `AUTH`, the reply lines split across segments and
+//! the encrypted bytes following a successful `STARTTLS`
+
+use std::time::SystemTime;
+
+use log::debug;
+
+use crate::capture_time;
+use crate::serializable_packet::{
+    application::{SerializableSmtpPacket, SmtpCommand, SmtpResponse},
+    ParsedPacket, SerializablePacket,
+};
+
+use super::{FlowContext, WellKnownPorts, ACTIVE_SMTP_SESSIONS};
+
+/// SMTP Reply Codes driving the session state
+#[allow(non_snake_case)]
+mod ReplyCodes {
+    pub const SERVICE_READY: u16 = 220;
+    pub const AUTH_CHALLENGE: u16 = 334;
+    pub const START_MAIL_INPUT: u16 = 354;
+}
+
+const END_OF_DATA: &[u8] = b"\r\n.\r\n";
+/// Line ending the mail data, when it starts a segment
+const END_OF_DATA_LINE: &[u8] = b".\r\n";
+
+/// State of an SMTP session, between a client and a server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SmtpSessionState {
+    Command,
+    DataRequested,
+    Data,
+    /// Challenge sent during AUTH, answered by the next client line
+    AuthChallenged,
+    StartTlsRequested,
+    Encrypted,
+}
+
+/// SMTP session, between a client and a server
+#[derive(Debug)]
+pub(crate) struct SmtpSession {
+    state: SmtpSessionState,
+    /// Last segment of the mail data ended a line
+    data_line_ended: bool,
+    /// Last segment of the server ended within a reply line, continued by the next one
+    reply_split: bool,
+    pub(crate) last_touch: SystemTime,
+}
+
+/// Build a SMTP packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_smtp_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    if packet.is_empty() {
+        return;
+    }
+
+    let from_client = is_smtp_port(dest_port);
+    let session = match from_client {
+        true => ((source_ip, source_port), (dest_ip, dest_port)),
+        false => ((dest_ip, dest_port), (source_ip, source_port)),
+    };
+
+    ACTIVE_SMTP_SESSIONS.with(|sessions| {
+        let mut sessions = sessions.borrow_mut();
+        let session = sessions.entry(session).or_insert_with(|| SmtpSession {
+            state: SmtpSessionState::Command,
+            data_line_ended: false,
+            reply_split: false,
+            last_touch: capture_time(),
+        });
+        session.last_touch = capture_time();
+
+        let smtp_packet = match (session.state, from_client) {
+            (SmtpSessionState::Encrypted, _) => Ok(SerializableSmtpPacket::Encrypted(packet.len())),
+            (SmtpSessionState::Data, true) => {
+                if packet.ends_with(END_OF_DATA)
+                    || (session.data_line_ended && packet == END_OF_DATA_LINE)
+                {
+                    session.state = SmtpSessionState::Command;
+                }
+                session.data_line_ended = packet.ends_with(b"\r\n");
+                Ok(SerializableSmtpPacket::MailData(packet.len()))
+            }
+            (SmtpSessionState::AuthChallenged, true) => {
+                Ok(SerializableSmtpPacket::Continuation(packet.len()))
+            }
+            (_, true) => parse_commands(packet).map(|commands| {
+                match commands.last().map(|c| c.verb.as_str()) {
+                    Some("DATA") => session.state = SmtpSessionState::DataRequested,
+                    Some("STARTTLS") => session.state = SmtpSessionState::StartTlsRequested,
+                    _ => (),
+                }
+                SerializableSmtpPacket::Commands(commands)
+            }),
+            (_, false) => {
+                // The start of the segment ends a reply line of the previous one
+                let replies = match session.reply_split {
+                    true => match packet.windows(2).position(|bytes| bytes == b"\r\n") {
+                        Some(end) => &packet[end + 2..],
+                        None => &[],
+                    },
+                    false => packet,
+                };
+                session.reply_split = !packet.ends_with(b"\r\n");
+
+                match replies.is_empty() {
+                    true => Ok(SerializableSmtpPacket::Continuation(packet.len())),
+                    false => parse_responses(replies).map(|responses| {
+                        session.state = next_state(session.state, responses.last().map(|r| r.code));
+                        if session.state == SmtpSessionState::Data {
+                            session.data_line_ended = true;
+                        }
+                        SerializableSmtpPacket::Responses(responses)
+                    }),
+                }
+            }
+        };
+
+        match smtp_packet {
+            Ok(smtp_packet) => {
+                debug!(
+                    "SMTP Packet: {}:{} > {}:{}; {:?}",
+                    source_ip, source_port, dest_ip, dest_port, smtp_packet
+                );
+
+                parsed_packet.set_application_layer_packet(Some(SerializablePacket::SmtpPacket(
+                    smtp_packet,
+                )));
+            }
+            Err(_) => {
+                debug!("Malformed SMTP Packet");
+                parsed_packet.set_application_layer_packet(Some(
+                    SerializablePacket::MalformedPacket("Malformed SMTP Packet".to_string()),
+                ));
+            }
+        }
+    });
+}
+
+/// Get the state of a session following the last reply of a server segment
+fn next_state(state: SmtpSessionState, code: Option<u16>) -> SmtpSessionState {
+    match (state, code) {
+        (SmtpSessionState::DataRequested, Some(ReplyCodes::START_MAIL_INPUT)) => {
+            SmtpSessionState::Data
+        }
+        (SmtpSessionState::StartTlsRequested, Some(ReplyCodes::SERVICE_READY)) => {
+            SmtpSessionState::Encrypted
+        }
+        (SmtpSessionState::DataRequested, _) | (SmtpSessionState::StartTlsRequested, _) => {
+            SmtpSessionState::Command
+        }
+        (_, Some(ReplyCodes::AUTH_CHALLENGE)) => SmtpSessionState::AuthChallenged,
+        (SmtpSessionState::AuthChallenged, _) => SmtpSessionState::Command,
+        (state, _) => state,
+    }
+}
+
+/// Check if a port is used by SMTP in plaintext (relay or submission)
+pub fn is_smtp_port(port: u16) -> bool {
+    port == WellKnownPorts::SMTP_PORT || port == WellKnownPorts::SMTP_SUBMISSION_PORT
+}
+
+/// Split the payload in CRLF-terminated lines, the last one may be incomplete
+fn split_lines(packet: &[u8]) -> Result<Vec<&str>, ()> {
+    let text = std::str::from_utf8(packet).map_err(|_| ())?;
+
+    Ok(text.split("\r\n").filter(|line| !line.is_empty()).collect())
+}
+
+/// Parse the commands sent by a client (several of them when pipelining)
+fn parse_commands(packet: &[u8]) -> Result<Vec<SmtpCommand>, ()> {
+    let mut commands = vec![];
+
+    for line in split_lines(packet)? {
+        let (verb, argument) = match line.split_once(' ') {
+            Some((verb, argument)) => (verb, Some(argument.trim().to_owned())),
+            None => (line, None),
+        };
+
+        if verb.is_empty() || !verb.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(());
+        }
+
+        let verb = verb.to_ascii_uppercase();
+        let sender = match verb.as_str() {
+            "MAIL" => argument.as_deref().and_then(|a| get_path(a, "FROM:")),
+            _ => None,
+        };
+        let recipient = match verb.as_str() {
+            "RCPT" => argument.as_deref().and_then(|a| get_path(a, "TO:")),
+            _ => None,
+        };
+
+        commands.push(SmtpCommand {
+            verb,
+            argument,
+            sender,
+            recipient,
+        });
+    }
+
+    match commands.is_empty() {
+        true => Err(()),
+        false => Ok(commands),
+    }
+}
+
+/// Get the mailbox of a `FROM:<path>` / `TO:<path>` argument
+fn get_path(argument: &str, prefix: &str) -> Option<String> {
+    match argument.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => (),
+        _ => return None,
+    }
+
+    let path = argument[prefix.len()..].trim_start();
+    let path = path.split(' ').next().unwrap_or("");
+
+    Some(
+        path.trim_start_matches('<')
+            .trim_end_matches('>')
+            .to_owned(),
+    )
+}
+
+/// Parse the replies sent by a server, joining the lines of multi-line replies
+fn parse_responses(packet: &[u8]) -> Result<Vec<SmtpResponse>, ()> {
+    let mut responses: Vec<SmtpResponse> = vec![];
+    let mut continued = false;
+
+    for line in split_lines(packet)? {
+        let code = line
+            .get(..3)
+            .filter(|code| code.chars().all(|c| c.is_ascii_digit()))
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or(())?;
+
+        let (separator, text) = match line.get(3..4) {
+            Some(separator) => (separator, line[4..].to_owned()),
+            None => (" ", String::new()),
+        };
+
+        match responses.last_mut() {
+            Some(response) if continued && response.code == code => response.lines.push(text),
+            _ => responses.push(SmtpResponse {
+                code,
+                lines: vec![text],
+            }),
+        }
+
+        continued = separator == "-";
+    }
+
+    match responses.is_empty() {
+        true => Err(()),
+        false => Ok(responses),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{handle_smtp_packet, FlowContext};
+    use crate::serializable_packet::{
+        application::SerializableSmtpPacket, ParsedPacket, SerializablePacket,
+    };
+
+    const CLIENT: (IpAddr, u16) = (IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)), 4444);
+    const SERVER: (IpAddr, u16) = (IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)), 25);
+
+    #[test]
+    fn mail_from_command() {
+        let parsed_packet = client_packet(b"MAIL FROM:<alice@example.com> SIZE=1024\r\n");
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::SmtpPacket(SerializableSmtpPacket::Commands(commands)) => {
+                assert_eq!(commands.len(), 1);
+                assert_eq!(commands[0].verb, "MAIL");
+                assert_eq!(
+                    commands[0].argument.as_deref(),
+                    Some("FROM:<alice@example.com> SIZE=1024")
+                );
+                assert_eq!(commands[0].sender.as_deref(), Some("alice@example.com"));
+                assert_eq!(commands[0].recipient, None);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn pipelined_rcpt_commands() {
+        let parsed_packet =
+            client_packet(b"RCPT TO:<bob@example.com>\r\nrcpt to:<carol@example.com>\r\n");
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::SmtpPacket(SerializableSmtpPacket::Commands(commands)) => {
+                assert_eq!(commands[0].recipient.as_deref(), Some("bob@example.com"));
+                assert_eq!(commands[1].verb, "RCPT");
+                assert_eq!(commands[1].recipient.as_deref(), Some("carol@example.com"));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn multiline_ok_response() {
+        let parsed_packet =
+            server_packet(b"250-mail.example.com\r\n250-PIPELINING\r\n250 STARTTLS\r\n");
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::SmtpPacket(SerializableSmtpPacket::Responses(responses)) => {
+                assert_eq!(responses.len(), 1);
+                assert_eq!(responses[0].code, 250);
+                assert_eq!(
+                    responses[0].lines,
+                    vec!["mail.example.com", "PIPELINING", "STARTTLS"]
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn data_content_after_start_mail_input() {
+        client_packet(b"DATA\r\n");
+        server_packet(b"354 End data with <CR><LF>.<CR><LF>\r\n");
+
+        match client_packet(b"Subject: hi\r\n\r\nhello\r\n.\r\n").get_application_layer_packet() {
+            Some(SerializablePacket::SmtpPacket(SerializableSmtpPacket::MailData(length))) => {
+                assert_eq!(*length, 25)
+            }
+            _ => unreachable!(),
+        }
+
+        match client_packet(b"QUIT\r\n").get_application_layer_packet() {
+            Some(SerializablePacket::SmtpPacket(SerializableSmtpPacket::Commands(commands))) => {
+                assert_eq!(commands[0].verb, "QUIT")
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn encrypted_after_starttls() {
+        client_packet(b"STARTTLS\r\n");
+        server_packet(b"220 2.0.0 Ready to start TLS\r\n");
+
+        match client_packet(&[0x16, 0x03, 0x01, 0x00, 0x05]).get_application_layer_packet() {
+            Some(SerializablePacket::SmtpPacket(SerializableSmtpPacket::Encrypted(length))) => {
+                assert_eq!(*length, 5)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn end_of_data_segment() {
+        client_packet(b"DATA\r\n");
+        server_packet(b"354 End data with <CR><LF>.<CR><LF>\r\n");
+        client_packet(b"Subject: hi\r\n\r\nhello\r\n");
+
+        match client_packet(b".\r\n").get_application_layer_packet() {
+            Some(SerializablePacket::SmtpPacket(SerializableSmtpPacket::MailData(length))) => {
+                assert_eq!(*length, 3)
+            }
+            _ => unreachable!(),
+        }
+
+        match client_packet(b"QUIT\r\n").get_application_layer_packet() {
+            Some(SerializablePacket::SmtpPacket(SerializableSmtpPacket::Commands(commands))) => {
+                assert_eq!(commands[0].verb, "QUIT")
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn auth_challenge_responses() {
+        client_packet(b"AUTH LOGIN\r\n");
+        server_packet(b"334 VXNlcm5hbWU6\r\n");
+
+        match client_packet(b"YWxpY2U=\r\n").get_application_layer_packet() {
+            Some(SerializablePacket::SmtpPacket(SerializableSmtpPacket::Continuation(length))) => {
+                assert_eq!(*length, 10)
+            }
+            _ => unreachable!(),
+        }
+
+        server_packet(b"235 2.7.0 Authentication successful\r\n");
+        match client_packet(b"MAIL FROM:<alice@example.com>\r\n").get_application_layer_packet() {
+            Some(SerializablePacket::SmtpPacket(SerializableSmtpPacket::Commands(commands))) => {
+                assert_eq!(commands[0].verb, "MAIL")
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn reply_line_split_across_segments() {
+        server_packet(b"250-mail.example.com\r\n250-SIZE 1000");
+
+        match server_packet(b"0000\r\n").get_application_layer_packet() {
+            Some(SerializablePacket::SmtpPacket(SerializableSmtpPacket::Continuation(length))) => {
+                assert_eq!(*length, 6)
+            }
+            _ => unreachable!(),
+        }
+
+        server_packet(b"250-PIPE");
+        match server_packet(b"LINING\r\n250 STARTTLS\r\n").get_application_layer_packet() {
+            Some(SerializablePacket::SmtpPacket(SerializableSmtpPacket::Responses(responses))) => {
+                assert_eq!(responses.len(), 1);
+                assert_eq!(responses[0].code, 250);
+                assert_eq!(responses[0].lines, vec!["STARTTLS"]);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_smtp_packet() {
+        match server_packet(b"hello\r\n").get_application_layer_packet() {
+            Some(SerializablePacket::MalformedPacket(str)) => {
+                assert_eq!(str, "Malformed SMTP Packet")
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    ///////////////////// Utils
+
+    fn client_packet(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_smtp_packet(
+            &FlowContext::new(CLIENT.0, CLIENT.1, SERVER.0, SERVER.1),
+            payload,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+
+    fn server_packet(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_smtp_packet(
+            &FlowContext::new(SERVER.0, SERVER.1, CLIENT.0, CLIENT.1),
+            payload,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+}
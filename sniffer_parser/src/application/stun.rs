@@ -0,0 +1,354 @@
+//! STUN Packet parsing
+//!
+//! The STUN header (RFC 8489) is decoded along with the address and text attributes; the
+//! addresses carried by XOR-MAPPED-ADDRESS are un-XORed with the magic cookie and transaction ID
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::SerializableStunPacket, ParsedPacket, SerializablePacket,
+};
+use crate::tlv::{Endianness, TlvParser};
+
+use super::FlowContext;
+
+/// STUN Methods
+#[allow(non_snake_case)]
+pub mod StunMethods {
+    pub const BINDING: u16 = 0x001;
+    pub const ALLOCATE: u16 = 0x003;
+    pub const REFRESH: u16 = 0x004;
+    pub const SEND: u16 = 0x006;
+    pub const DATA: u16 = 0x007;
+    pub const CREATE_PERMISSION: u16 = 0x008;
+    pub const CHANNEL_BIND: u16 = 0x009;
+}
+
+/// STUN Attribute Types
+#[allow(non_snake_case)]
+pub mod StunAttributeTypes {
+    pub const MAPPED_ADDRESS: u16 = 0x0001;
+    pub const USERNAME: u16 = 0x0006;
+    pub const MESSAGE_INTEGRITY: u16 = 0x0008;
+    pub const ERROR_CODE: u16 = 0x0009;
+    pub const XOR_MAPPED_ADDRESS: u16 = 0x0020;
+    pub const PRIORITY: u16 = 0x0024;
+    pub const USE_CANDIDATE: u16 = 0x0025;
+    pub const SOFTWARE: u16 = 0x8022;
+    pub const FINGERPRINT: u16 = 0x8028;
+    pub const ICE_CONTROLLED: u16 = 0x8029;
+    pub const ICE_CONTROLLING: u16 = 0x802a;
+}
+
+pub const MAGIC_COOKIE: u32 = 0x2112a442;
+
+const HEADER_LENGTH: usize = 20;
+const IPV4_FAMILY: u8 = 0x01;
+const IPV6_FAMILY: u8 = 0x02;
+
+/// STUN Message Classes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StunClass {
+    Request,
+    Indication,
+    SuccessResponse,
+    ErrorResponse,
+}
+
+/// Errors occurring during the parsing of a STUN message
+#[derive(Debug)]
+pub enum StunError {
+    Truncated,
+    InvalidMessageType(u16),
+    InvalidMagicCookie(u32),
+    InvalidAddressFamily(u8),
+    InvalidText,
+}
+
+/// STUN Attribute, only the value of the known ones is decoded
+#[derive(Debug)]
+pub enum StunAttribute {
+    MappedAddress(SocketAddr),
+    XorMappedAddress(SocketAddr),
+    Username(String),
+    Software(String),
+    Other(u16, usize),
+}
+
+/// STUN Message: header and attributes
+#[derive(Debug)]
+pub struct StunMessage {
+    pub class: StunClass,
+    pub method: u16,
+    pub length: u16,
+    pub transaction_id: [u8; 12],
+    pub attributes: Vec<StunAttribute>,
+}
+
+impl StunMessage {
+    /// Parse a STUN message filling a UDP payload
+    pub fn parse(packet: &[u8]) -> Result<StunMessage, StunError> {
+        let header = packet.get(..HEADER_LENGTH).ok_or(StunError::Truncated)?;
+
+        let message_type = u16::from_be_bytes([header[0], header[1]]);
+        if message_type & 0xc000 != 0 {
+            return Err(StunError::InvalidMessageType(message_type));
+        }
+
+        let magic_cookie = u32::from_be_bytes(header[4..8].try_into().unwrap());
+        if magic_cookie != MAGIC_COOKIE {
+            return Err(StunError::InvalidMagicCookie(magic_cookie));
+        }
+
+        let length = u16::from_be_bytes([header[2], header[3]]);
+        let body = packet
+            .get(HEADER_LENGTH..HEADER_LENGTH + length as usize)
+            .filter(|_| length % 4 == 0)
+            .ok_or(StunError::Truncated)?;
+        let transaction_id: [u8; 12] = header[8..].try_into().unwrap();
+
+        Ok(StunMessage {
+            class: message_class(message_type),
+            method: message_method(message_type),
+            length,
+            transaction_id,
+            attributes: parse_attributes(body, &transaction_id)?,
+        })
+    }
+}
+
+/// Get the class from the C1 (bit 8) and C0 (bit 4) bits of the message type
+fn message_class(message_type: u16) -> StunClass {
+    match ((message_type >> 7) & 0x02) | ((message_type >> 4) & 0x01) {
+        0 => StunClass::Request,
+        1 => StunClass::Indication,
+        2 => StunClass::SuccessResponse,
+        _ => StunClass::ErrorResponse,
+    }
+}
+
+/// Get the method from the message type, whose bits are interleaved with the class bits
+fn message_method(message_type: u16) -> u16 {
+    (message_type & 0x000f) | ((message_type & 0x00e0) >> 1) | ((message_type & 0x3e00) >> 2)
+}
+
+fn parse_attributes(
+    body: &[u8],
+    transaction_id: &[u8; 12],
+) -> Result<Vec<StunAttribute>, StunError> {
+    // Values are padded to a multiple of 4 bytes
+    TlvParser::new(body, 2, 2, Endianness::Big)
+        .aligned(4)
+        .map(|attribute| {
+            let attribute = attribute.map_err(|_| StunError::Truncated)?;
+            let value = attribute.value;
+            Ok(match attribute.tlv_type as u16 {
+                StunAttributeTypes::MAPPED_ADDRESS => {
+                    StunAttribute::MappedAddress(read_address(value)?)
+                }
+                StunAttributeTypes::XOR_MAPPED_ADDRESS => StunAttribute::XorMappedAddress(
+                    xor_address(read_address(value)?, transaction_id),
+                ),
+                StunAttributeTypes::USERNAME => StunAttribute::Username(read_text(value)?),
+                StunAttributeTypes::SOFTWARE => StunAttribute::Software(read_text(value)?),
+                other => StunAttribute::Other(other, value.len()),
+            })
+        })
+        .collect()
+}
+
+/// Read the family, port and address of a MAPPED-ADDRESS like value
+fn read_address(value: &[u8]) -> Result<SocketAddr, StunError> {
+    let header = value.get(..4).ok_or(StunError::Truncated)?;
+    let port = u16::from_be_bytes([header[2], header[3]]);
+
+    let ip = match header[1] {
+        IPV4_FAMILY => {
+            let octets: [u8; 4] = value
+                .get(4..8)
+                .ok_or(StunError::Truncated)?
+                .try_into()
+                .unwrap();
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        IPV6_FAMILY => {
+            let octets: [u8; 16] = value
+                .get(4..20)
+                .ok_or(StunError::Truncated)?
+                .try_into()
+                .unwrap();
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        family => return Err(StunError::InvalidAddressFamily(family)),
+    };
+
+    Ok(SocketAddr::new(ip, port))
+}
+
+/// Decode an XOR-MAPPED-ADDRESS: the port is XORed with the most significant half of the magic
+/// cookie, the address with the magic cookie (followed by the transaction ID for IPv6)
+fn xor_address(address: SocketAddr, transaction_id: &[u8; 12]) -> SocketAddr {
+    let port = address.port() ^ (MAGIC_COOKIE >> 16) as u16;
+
+    let ip = match address.ip() {
+        IpAddr::V4(ip) => IpAddr::V4(Ipv4Addr::from(u32::from(ip) ^ MAGIC_COOKIE)),
+        IpAddr::V6(ip) => {
+            let mut key = [0u8; 16];
+            key[..4].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+            key[4..].copy_from_slice(transaction_id);
+
+            let mut octets = ip.octets();
+            octets
+                .iter_mut()
+                .zip(key.iter())
+                .for_each(|(octet, key)| *octet ^= key);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+    };
+
+    SocketAddr::new(ip, port)
+}
+
+fn read_text(value: &[u8]) -> Result<String, StunError> {
+    std::str::from_utf8(value)
+        .map(|text| text.to_owned())
+        .map_err(|_| StunError::InvalidText)
+}
+
+/// Build a STUN packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_stun_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    if let Ok(stun_message) = StunMessage::parse(packet) {
+        debug!(
+            "STUN Packet: {}:{} > {}:{}; Class: {:?}, Method: {:#05x}, Transaction ID: {:02x?}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            stun_message.class,
+            stun_message.method,
+            stun_message.transaction_id,
+        );
+
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::StunPacket(
+            SerializableStunPacket::from(&stun_message),
+        )));
+    } else {
+        debug!("Malformed STUN Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed STUN Packet".to_string(),
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    use super::{handle_stun_packet, FlowContext};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    // Sample IPv4 Binding Response from RFC 5769 2.2
+    const BINDING_RESPONSE: &[u8] = &[
+        0x01, 0x01, 0x00, 0x3c, 0x21, 0x12, 0xa4, 0x42, 0xb7, 0xe7, 0xa7, 0x01, 0xbc, 0x34, 0xd6,
+        0x86, 0xfa, 0x87, 0xdf, 0xae, 0x80, 0x22, 0x00, 0x0b, 0x74, 0x65, 0x73, 0x74, 0x20, 0x76,
+        0x65, 0x63, 0x74, 0x6f, 0x72, 0x20, 0x00, 0x20, 0x00, 0x08, 0x00, 0x01, 0xa1, 0x47, 0xe1,
+        0x12, 0xa6, 0x43, 0x00, 0x08, 0x00, 0x14, 0x2b, 0x91, 0xf5, 0x99, 0xfd, 0x9e, 0x90, 0xc3,
+        0x8c, 0x74, 0x89, 0xf9, 0x2a, 0xf9, 0xba, 0x53, 0xf0, 0x6b, 0xe7, 0xd7, 0x80, 0x28, 0x00,
+        0x04, 0xc0, 0x7d, 0x4c, 0x96,
+    ];
+
+    // Binding Request with the USERNAME of the RFC 5769 2.1 sample
+    const BINDING_REQUEST: &[u8] = &[
+        0x00, 0x01, 0x00, 0x10, 0x21, 0x12, 0xa4, 0x42, 0xb7, 0xe7, 0xa7, 0x01, 0xbc, 0x34, 0xd6,
+        0x86, 0xfa, 0x87, 0xdf, 0xae, 0x00, 0x06, 0x00, 0x09, 0x65, 0x76, 0x74, 0x6a, 0x3a, 0x68,
+        0x36, 0x76, 0x59, 0x20, 0x20, 0x20,
+    ];
+
+    #[test]
+    fn binding_response_xor_mapped_address() {
+        match stun_packet(BINDING_RESPONSE).get_application_layer_packet() {
+            Some(SerializablePacket::StunPacket(stun_packet)) => {
+                assert_eq!(
+                    stun_packet.message_type,
+                    "Binding Success Response (0x0101)"
+                );
+                assert_eq!(
+                    stun_packet.transaction_id,
+                    vec![0xb7, 0xe7, 0xa7, 0x01, 0xbc, 0x34, 0xd6, 0x86, 0xfa, 0x87, 0xdf, 0xae]
+                );
+                assert_eq!(
+                    stun_packet.xor_mapped_address,
+                    Some(SocketAddr::new(
+                        IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+                        32853
+                    ))
+                );
+                assert_eq!(stun_packet.software.as_deref(), Some("test vector"));
+                assert_eq!(
+                    stun_packet.attributes,
+                    vec![
+                        "SOFTWARE (0x8022)",
+                        "XOR-MAPPED-ADDRESS (0x0020)",
+                        "MESSAGE-INTEGRITY (0x0008)",
+                        "FINGERPRINT (0x8028)"
+                    ]
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn binding_request_username() {
+        match stun_packet(BINDING_REQUEST).get_application_layer_packet() {
+            Some(SerializablePacket::StunPacket(stun_packet)) => {
+                assert_eq!(stun_packet.message_type, "Binding Request (0x0001)");
+                assert_eq!(stun_packet.username.as_deref(), Some("evtj:h6vY"));
+                assert_eq!(stun_packet.xor_mapped_address, None);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_stun_packet() {
+        let mut wrong_cookie = BINDING_REQUEST.to_vec();
+        wrong_cookie[4] = 0;
+
+        for packet in [&BINDING_REQUEST[..30], wrong_cookie.as_slice()] {
+            match stun_packet(packet).get_application_layer_packet() {
+                Some(SerializablePacket::MalformedPacket(str)) => {
+                    assert_eq!(str, "Malformed STUN Packet")
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    ///////////////////// Utils
+
+    fn stun_packet(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_stun_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                3478,
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+            ),
+            payload,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+}
@@ -0,0 +1,365 @@
+//! Syslog Packet parsing
+//!
+//! A syslog message starts with its priority between angle brackets, the facility times 8 plus
+//! the severity. The legacy BSD format (RFC 3164) follows with a `Mmm dd hh:mm:ss` timestamp, the
+//! hostname and the tag of the application, up to a colon, before the free-form message. The
+//! RFC 5424 format follows with its version, `1`, then space-separated timestamp, hostname,
+//! application name, process and message identifiers, structured data, and message, `-` standing
+//! for a missing field. The version digit after the priority tells the two formats apart
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::SerializableSyslogPacket, ParsedPacket, SerializablePacket,
+};
+
+use super::FlowContext;
+
+/// Highest priority: facility 23 (local7), severity 7 (debug)
+const MAX_PRIORITY: u8 = 191;
+/// Length of a RFC 3164 timestamp, e.g. `Oct  9 22:33:20`
+const BSD_TIMESTAMP_LENGTH: usize = 15;
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+/// Longest tag of a RFC 3164 message
+const MAX_TAG_LENGTH: usize = 32;
+
+/// Format of a syslog message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyslogFormat {
+    Rfc3164,
+    Rfc5424,
+}
+
+/// Fields of a syslog message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyslogMessage {
+    pub format: SyslogFormat,
+    pub priority: u8,
+    pub version: Option<u8>,
+    pub timestamp: Option<String>,
+    pub hostname: Option<String>,
+    /// Application name of a RFC 5424 message, tag of a RFC 3164 one
+    pub app_name: Option<String>,
+    pub proc_id: Option<String>,
+    pub msg_id: Option<String>,
+    pub structured_data: Option<String>,
+    pub message: String,
+}
+
+impl SyslogMessage {
+    /// Parse a syslog message in either format, `None` without a valid priority
+    pub fn parse(packet: &[u8]) -> Option<SyslogMessage> {
+        let text = String::from_utf8_lossy(packet);
+        let text = text.trim_end_matches(['\n', '\r', '\0']);
+
+        let (priority, rest) = parse_priority(text)?;
+        let rfc5424_message = rest
+            .split_once(' ')
+            .filter(|(version, _)| is_version(version))
+            .and_then(|(version, rest)| parse_rfc5424(priority, version.parse().ok()?, rest));
+        Some(rfc5424_message.unwrap_or_else(|| parse_rfc3164(priority, rest)))
+    }
+
+    pub fn facility(&self) -> u8 {
+        self.priority >> 3
+    }
+
+    pub fn severity(&self) -> u8 {
+        self.priority & 0x07
+    }
+}
+
+/// Split the `<PRI>` prefix of a message, a decimal number of 1 to 3 digits without leading zero
+fn parse_priority(text: &str) -> Option<(u8, &str)> {
+    let (priority, rest) = text.strip_prefix('<')?.split_once('>')?;
+    if priority.is_empty()
+        || priority.len() > 3
+        || !priority.bytes().all(|b| b.is_ascii_digit())
+        || (priority.len() > 1 && priority.starts_with('0'))
+    {
+        return None;
+    }
+
+    let priority = priority.parse().ok().filter(|&p| p <= MAX_PRIORITY)?;
+    Some((priority, rest))
+}
+
+/// Check if a field is a RFC 5424 version, a decimal number of 1 to 3 digits without leading zero
+fn is_version(field: &str) -> bool {
+    (1..=3).contains(&field.len())
+        && field.bytes().all(|b| b.is_ascii_digit())
+        && !field.starts_with('0')
+}
+
+/// Parse the header fields, the structured data and the message of a RFC 5424 message
+fn parse_rfc5424(priority: u8, version: u8, rest: &str) -> Option<SyslogMessage> {
+    let mut fields = rest.splitn(5, ' ');
+    let mut header_field = || {
+        let field = fields.next()?;
+        Some((field != "-").then(|| field.to_owned()))
+    };
+    let (timestamp, hostname, app_name, proc_id) = (
+        header_field()?,
+        header_field()?,
+        header_field()?,
+        header_field()?,
+    );
+    let (msg_id, rest) = fields.next()?.split_once(' ')?;
+
+    let (structured_data, message) = split_structured_data(rest)?;
+    let message = message.strip_prefix(' ').unwrap_or(message);
+    Some(SyslogMessage {
+        format: SyslogFormat::Rfc5424,
+        priority,
+        version: Some(version),
+        timestamp,
+        hostname,
+        app_name,
+        proc_id,
+        msg_id: (msg_id != "-").then(|| msg_id.to_owned()),
+        structured_data: (structured_data != "-").then(|| structured_data.to_owned()),
+        message: message.trim_start_matches('\u{feff}').to_owned(),
+    })
+}
+
+/// Split the structured data of a RFC 5424 message, `-` or a sequence of `[...]` elements whose
+/// quoted parameter values may hold escaped `"`, `\` and `]`, from the message following it
+fn split_structured_data(text: &str) -> Option<(&str, &str)> {
+    if text.starts_with('-') {
+        return Some(text.split_at(1));
+    }
+
+    let bytes = text.as_bytes();
+    let mut end = 0;
+    while bytes.get(end) == Some(&b'[') {
+        let (mut in_value, mut escaped) = (false, false);
+        end += 1;
+        loop {
+            match (bytes.get(end)?, in_value, escaped) {
+                (_, true, true) => escaped = false,
+                (b'\\', true, false) => escaped = true,
+                (b'"', _, false) => in_value = !in_value,
+                (b']', false, _) => break,
+                _ => (),
+            }
+            end += 1;
+        }
+        end += 1;
+    }
+
+    match end {
+        0 => None,
+        _ => Some(text.split_at(end)),
+    }
+}
+
+/// Parse the timestamp, hostname, tag and message of a RFC 3164 message; a message without a
+/// valid timestamp is all content, as a relay would forward it
+fn parse_rfc3164(priority: u8, rest: &str) -> SyslogMessage {
+    let mut syslog_message = SyslogMessage {
+        format: SyslogFormat::Rfc3164,
+        priority,
+        version: None,
+        timestamp: None,
+        hostname: None,
+        app_name: None,
+        proc_id: None,
+        msg_id: None,
+        structured_data: None,
+        message: rest.to_owned(),
+    };
+
+    let Some(timestamp) = rest
+        .get(..BSD_TIMESTAMP_LENGTH)
+        .filter(|t| is_bsd_timestamp(t))
+    else {
+        return syslog_message;
+    };
+    let rest = rest[BSD_TIMESTAMP_LENGTH..].trim_start_matches(' ');
+    let (hostname, content) = rest.split_once(' ').unwrap_or((rest, ""));
+    syslog_message.timestamp = Some(timestamp.to_owned());
+    syslog_message.hostname = Some(hostname.to_owned());
+    syslog_message.message = content.to_owned();
+
+    // The tag ends at the first character not alphanumeric, usually `[` before the process
+    // identifier or `:`
+    let tag_length = content
+        .find(|c: char| !c.is_ascii_alphanumeric() && !"-_./".contains(c))
+        .unwrap_or(content.len());
+    if tag_length == 0 || tag_length > MAX_TAG_LENGTH {
+        return syslog_message;
+    }
+    let (tag, rest) = content.split_at(tag_length);
+    let (proc_id, rest) = match rest.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+        Some((proc_id, rest)) => (Some(proc_id.to_owned()), rest),
+        None => (None, rest),
+    };
+    if let Some(message) = rest.strip_prefix(':') {
+        syslog_message.app_name = Some(tag.to_owned());
+        syslog_message.proc_id = proc_id;
+        syslog_message.message = message.trim_start_matches(' ').to_owned();
+    }
+
+    syslog_message
+}
+
+/// Check if a text is a `Mmm dd hh:mm:ss` timestamp, the day padded with a space
+fn is_bsd_timestamp(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let digits = |range: std::ops::Range<usize>| bytes[range].iter().all(u8::is_ascii_digit);
+
+    MONTHS.iter().any(|month| month.as_bytes() == &bytes[..3])
+        && bytes[3] == b' '
+        && (bytes[4] == b' ' || bytes[4].is_ascii_digit())
+        && bytes[5].is_ascii_digit()
+        && bytes[6] == b' '
+        && digits(7..9)
+        && bytes[9] == b':'
+        && digits(10..12)
+        && bytes[12] == b':'
+        && digits(13..15)
+}
+
+/// Build a syslog packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_syslog_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    if let Some(syslog_message) = SyslogMessage::parse(packet) {
+        debug!(
+            "Syslog Packet: {}:{} > {}:{}; Priority: {}, Format: {:?}, Length: {}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            syslog_message.priority,
+            syslog_message.format,
+            packet.len(),
+        );
+
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::SyslogPacket(
+            SerializableSyslogPacket::new(&syslog_message, packet.len()),
+        )));
+    } else {
+        debug!("Malformed Syslog Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed Syslog Packet".to_string(),
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{handle_syslog_packet, FlowContext, SyslogFormat, SyslogMessage};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    #[test]
+    fn rfc3164_message() {
+        let message =
+            b"<34>Oct 11 22:14:15 mymachine su[230]: 'su root' failed for lonvick on /dev/pts/8\n";
+
+        match syslog_packet(message).get_application_layer_packet() {
+            Some(SerializablePacket::SyslogPacket(syslog_packet)) => {
+                assert_eq!(syslog_packet.format, "RFC 3164");
+                assert_eq!((syslog_packet.facility, syslog_packet.severity), (4, 2));
+                assert_eq!(syslog_packet.facility_name, "auth");
+                assert_eq!(syslog_packet.severity_name, "crit");
+                assert_eq!(syslog_packet.timestamp.as_deref(), Some("Oct 11 22:14:15"));
+                assert_eq!(syslog_packet.hostname.as_deref(), Some("mymachine"));
+                assert_eq!(syslog_packet.app_name.as_deref(), Some("su"));
+                assert_eq!(syslog_packet.proc_id.as_deref(), Some("230"));
+                assert_eq!(
+                    syslog_packet.message,
+                    "'su root' failed for lonvick on /dev/pts/8"
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        // Without timestamp, all content, even starting with a number
+        let message = SyslogMessage::parse(b"<13>10 minutes before restart").unwrap();
+        assert_eq!(message.format, SyslogFormat::Rfc3164);
+        assert_eq!(message.hostname, None);
+        assert_eq!(message.message, "10 minutes before restart");
+    }
+
+    #[test]
+    fn rfc5424_message() {
+        let message = b"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 \
+            [exampleSDID@32473 iut=\"3\" eventSource=\"App\\]lication\"] \xef\xbb\xbfAn application event";
+
+        match syslog_packet(message).get_application_layer_packet() {
+            Some(SerializablePacket::SyslogPacket(syslog_packet)) => {
+                assert_eq!(syslog_packet.format, "RFC 5424");
+                assert_eq!(syslog_packet.version, Some(1));
+                assert_eq!(syslog_packet.facility_name, "local4");
+                assert_eq!(syslog_packet.severity_name, "notice");
+                assert_eq!(
+                    syslog_packet.timestamp.as_deref(),
+                    Some("2003-10-11T22:14:15.003Z")
+                );
+                assert_eq!(
+                    syslog_packet.hostname.as_deref(),
+                    Some("mymachine.example.com")
+                );
+                assert_eq!(syslog_packet.app_name.as_deref(), Some("evntslog"));
+                assert_eq!(syslog_packet.proc_id, None);
+                assert_eq!(syslog_packet.msg_id.as_deref(), Some("ID47"));
+                assert_eq!(
+                    syslog_packet.structured_data.as_deref(),
+                    Some("[exampleSDID@32473 iut=\"3\" eventSource=\"App\\]lication\"]")
+                );
+                assert_eq!(syslog_packet.message, "An application event");
+            }
+            _ => unreachable!(),
+        }
+
+        let message = SyslogMessage::parse(b"<14>1 - - - - - -").unwrap();
+        assert_eq!(message.format, SyslogFormat::Rfc5424);
+        assert_eq!((message.hostname, message.structured_data), (None, None));
+        assert!(message.message.is_empty());
+    }
+
+    #[test]
+    fn malformed_syslog_packet() {
+        for packet in [
+            &b"hello"[..],
+            b"<192>Oct 11 22:14:15 host tag: msg",
+            b"<034>msg",
+            b"<>msg",
+        ] {
+            match syslog_packet(packet).get_application_layer_packet() {
+                Some(SerializablePacket::MalformedPacket(str)) => {
+                    assert_eq!(str, "Malformed Syslog Packet")
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    ///////////////////// Utils
+
+    fn syslog_packet(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_syslog_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                514,
+            ),
+            payload,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+}
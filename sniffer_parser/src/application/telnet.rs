@@ -0,0 +1,229 @@
+//! Telnet Packet parsing
+//!
+//! The Telnet stream (RFC 854) interleaves data with commands introduced by the IAC byte: option
+//! negotiations (WILL, WONT, DO, DONT followed by the option), subnegotiations (SB, the option and
+//! its parameters up to IAC SE) and single commands. A data byte 0xFF is sent doubled (IAC IAC)
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::SerializableTelnetPacket, ParsedPacket, SerializablePacket,
+};
+
+use super::FlowContext;
+
+/// Telnet Commands
+#[allow(non_snake_case)]
+pub mod TelnetCommands {
+    pub const SE: u8 = 240;
+    pub const NOP: u8 = 241;
+    pub const DATA_MARK: u8 = 242;
+    pub const BREAK: u8 = 243;
+    pub const INTERRUPT_PROCESS: u8 = 244;
+    pub const ABORT_OUTPUT: u8 = 245;
+    pub const ARE_YOU_THERE: u8 = 246;
+    pub const ERASE_CHARACTER: u8 = 247;
+    pub const ERASE_LINE: u8 = 248;
+    pub const GO_AHEAD: u8 = 249;
+    pub const SB: u8 = 250;
+    pub const WILL: u8 = 251;
+    pub const WONT: u8 = 252;
+    pub const DO: u8 = 253;
+    pub const DONT: u8 = 254;
+    pub const IAC: u8 = 255;
+}
+
+/// Telnet Options
+#[allow(non_snake_case)]
+pub mod TelnetOptions {
+    pub const BINARY_TRANSMISSION: u8 = 0;
+    pub const ECHO: u8 = 1;
+    pub const SUPPRESS_GO_AHEAD: u8 = 3;
+    pub const STATUS: u8 = 5;
+    pub const TIMING_MARK: u8 = 6;
+    pub const TERMINAL_TYPE: u8 = 24;
+    pub const WINDOW_SIZE: u8 = 31;
+    pub const TERMINAL_SPEED: u8 = 32;
+    pub const REMOTE_FLOW_CONTROL: u8 = 33;
+    pub const LINEMODE: u8 = 34;
+    pub const ENVIRONMENT: u8 = 36;
+    pub const NEW_ENVIRONMENT: u8 = 39;
+}
+
+/// Command of a Telnet stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TelnetCommand {
+    /// WILL, WONT, DO or DONT, with the negotiated option
+    Negotiation(u8, u8),
+    /// Option and parameters of a subnegotiation
+    Subnegotiation(u8, Vec<u8>),
+    /// Command without option (NOP, Go Ahead, ...)
+    Command(u8),
+}
+
+/// Errors occurring during the parsing of a Telnet segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TelnetError {
+    /// The segment ends in the middle of a command
+    Truncated,
+    /// Subnegotiation not terminated by IAC SE
+    UnterminatedSubnegotiation,
+}
+
+/// Telnet segment, split in commands and data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TelnetMessage {
+    pub commands: Vec<TelnetCommand>,
+    pub data: Vec<u8>,
+}
+
+impl TelnetMessage {
+    pub fn parse(packet: &[u8]) -> Result<TelnetMessage, TelnetError> {
+        let mut commands = vec![];
+        let mut data = vec![];
+        let mut bytes = packet.iter().copied();
+
+        while let Some(byte) = bytes.next() {
+            if byte != TelnetCommands::IAC {
+                data.push(byte);
+                continue;
+            }
+
+            match bytes.next().ok_or(TelnetError::Truncated)? {
+                TelnetCommands::IAC => data.push(TelnetCommands::IAC),
+                command @ (TelnetCommands::WILL
+                | TelnetCommands::WONT
+                | TelnetCommands::DO
+                | TelnetCommands::DONT) => {
+                    let option = bytes.next().ok_or(TelnetError::Truncated)?;
+                    commands.push(TelnetCommand::Negotiation(command, option));
+                }
+                TelnetCommands::SB => {
+                    let option = bytes.next().ok_or(TelnetError::Truncated)?;
+                    let mut parameters = vec![];
+
+                    loop {
+                        match bytes.next() {
+                            Some(TelnetCommands::IAC) => match bytes.next() {
+                                Some(TelnetCommands::SE) => break,
+                                Some(TelnetCommands::IAC) => parameters.push(TelnetCommands::IAC),
+                                _ => return Err(TelnetError::UnterminatedSubnegotiation),
+                            },
+                            Some(byte) => parameters.push(byte),
+                            None => return Err(TelnetError::UnterminatedSubnegotiation),
+                        }
+                    }
+
+                    commands.push(TelnetCommand::Subnegotiation(option, parameters));
+                }
+                command => commands.push(TelnetCommand::Command(command)),
+            }
+        }
+
+        Ok(TelnetMessage { commands, data })
+    }
+}
+
+/// Build a Telnet packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_telnet_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    if packet.is_empty() {
+        return;
+    }
+
+    if let Ok(telnet_message) = TelnetMessage::parse(packet) {
+        debug!(
+            "Telnet Packet: {}:{} > {}:{}; Commands: {:?}, Data length: {}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            telnet_message.commands,
+            telnet_message.data.len(),
+        );
+
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::TelnetPacket(
+            SerializableTelnetPacket::from(&telnet_message),
+        )));
+    } else {
+        debug!("Malformed Telnet Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed Telnet Packet".to_string(),
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{handle_telnet_packet, FlowContext, TelnetCommand, TelnetCommands, TelnetMessage};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    // IAC DO ECHO, "login: " with an escaped 0xFF, IAC SB TERMINAL-TYPE SEND IAC SE
+    const NEGOTIATION_AND_DATA: &[u8] = &[
+        0xff, 0xfd, 0x01, b'l', b'o', b'g', b'i', b'n', 0xff, 0xff, b':', b' ', 0xff, 0xfa, 0x18,
+        0x01, 0xff, 0xf0,
+    ];
+
+    #[test]
+    fn do_echo_and_data() {
+        let message = TelnetMessage::parse(NEGOTIATION_AND_DATA).unwrap();
+        assert_eq!(
+            message.commands,
+            vec![
+                TelnetCommand::Negotiation(TelnetCommands::DO, 1),
+                TelnetCommand::Subnegotiation(24, vec![0x01]),
+            ]
+        );
+        assert_eq!(message.data, b"login\xff: ");
+
+        match telnet_packet(NEGOTIATION_AND_DATA).get_application_layer_packet() {
+            Some(SerializablePacket::TelnetPacket(telnet_packet)) => {
+                assert_eq!(
+                    telnet_packet.commands,
+                    vec!["DO Echo (1)", "SB Terminal Type (24): [01]"]
+                );
+                assert_eq!(telnet_packet.data, "login.: ");
+                assert_eq!(telnet_packet.data_length, 8);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_telnet_packet() {
+        for packet in [&[b'a', 0xff][..], &[0xff, 0xfa, 0x18, 0x01, 0xff]] {
+            match telnet_packet(packet).get_application_layer_packet() {
+                Some(SerializablePacket::MalformedPacket(str)) => {
+                    assert_eq!(str, "Malformed Telnet Packet")
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    ///////////////////// Utils
+
+    fn telnet_packet(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_telnet_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                23,
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+            ),
+            payload,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+}
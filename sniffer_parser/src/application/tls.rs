@@ -1,40 +1,90 @@
 //! TLS Packet parsing
 
-use std::net::IpAddr;
-
 use log::debug;
 use log::error;
 use log::warn;
 use tls_parser::nom::error::ErrorKind;
 use tls_parser::parse_tls_plaintext;
 use tls_parser::parse_tls_record_header;
+use tls_parser::parse_tls_record_with_header;
 use tls_parser::{parse_tls_encrypted, TlsMessage, TlsMessageHandshake};
+use tls_parser::{TlsRecordHeader, TlsRecordType};
 
 use crate::serializable_packet::application::*;
 use crate::serializable_packet::ParsedPacket;
 use crate::serializable_packet::SerializablePacket;
 use crate::ACTIVE_TLS_PARSERS;
 
+use super::{append_to_parser, FlowContext};
+
+const RECORD_HEADER_LENGTH: usize = 5;
+const HANDSHAKE_HEADER_LENGTH: usize = 4;
+
+/// Outcome of the reassembly of a handshake message fragmented over several records
+enum HandshakeReassembly {
+    /// The first record holds whole handshake messages, or is not a handshake record
+    Unfragmented,
+    /// The records holding the end of the fragmented message are not received yet
+    Incomplete,
+    /// Header of the merged record, merged fragments and length of the records they came from
+    Reassembled(TlsRecordHeader, Vec<u8>, usize),
+}
+
 /// Build a TLS packet from a transport-layer packet, save it in a Parsed Packet
-pub fn handle_tls_packet(
-    source_ip: IpAddr,
-    source_port: u16,
-    dest_ip: IpAddr,
-    dest_port: u16,
-    packet: &[u8],
-    parsed_packet: &mut ParsedPacket,
-) {
+pub fn handle_tls_packet(flow: &FlowContext, packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
     ACTIVE_TLS_PARSERS.with(|parsers| {
         let mut parsers = parsers.borrow_mut();
-        let current_payload = parsers
-            .entry(((source_ip, source_port), (dest_ip, dest_port)))
-            .and_modify(|payload| payload.append(packet.to_vec().as_mut()))
-            .or_insert(packet.to_vec());
+        let current_payload = append_to_parser(
+            &mut parsers,
+            ((source_ip, source_port), (dest_ip, dest_port)),
+            packet,
+        );
 
         let mut tls_packet = SerializableTlsPacket::default();
         let mut custom_messages = vec![];
 
         while !current_payload.is_empty() {
+            match reassemble_handshake(current_payload) {
+                HandshakeReassembly::Incomplete => break,
+                HandshakeReassembly::Reassembled(header, fragments, end) => {
+                    match parse_tls_record_with_header(&fragments, &header) {
+                        Ok((_, messages)) => {
+                            debug!(
+                                "TLS Reassembled Handshake: {}:{} > {}:{}; Version: {}, Len: {}, Records Len: {}",
+                                source_ip, source_port, dest_ip, dest_port, header.version, header.len, end
+                            );
+                            parse_messages(messages, &mut custom_messages);
+                        },
+                        Err(_) => {
+                            warn!(
+                                "TLS Malformed reassembled handshake: {}:{} > {}:{}; Length: {}",
+                                source_ip, source_port, dest_ip, dest_port, header.len
+                            );
+                        },
+                    }
+
+                    tls_packet.set_version(header.version);
+                    tls_packet.set_length(header.len);
+                    tls_packet.add_record(&header);
+
+                    current_payload.drain(..end);
+                    if current_payload.is_empty() {
+                        parsers.remove(&((source_ip, source_port), (dest_ip, dest_port)));
+                        break;
+                    }
+                    continue;
+                },
+                HandshakeReassembly::Unfragmented => (),
+            }
+
             let result = parse_tls_plaintext(current_payload);
             match result {
                 Ok((rem, record)) => {
@@ -46,6 +96,7 @@ pub fn handle_tls_packet(
                     }
 
                     parse_messages(record.msg, &mut custom_messages);
+                    tls_packet.add_record(&record.hdr);
 
                     if rem.is_empty() {
                         tls_packet.set_version(record.hdr.version);
@@ -132,6 +183,7 @@ pub fn handle_tls_packet(
                     custom_messages.push(CustomTlsMessage::Encrypted(
                         CustomEncryptedMessage::new(record.msg.blob, record.hdr.version, record.hdr.record_type)
                     ));
+                    tls_packet.add_record(&record.hdr);
 
                     if rem.is_empty() {
                         tls_packet.set_version(record.hdr.version);
@@ -207,19 +259,86 @@ pub fn handle_tls_packet(
         }
 
         if !custom_messages.is_empty() {
+            tls_packet.set_messages(custom_messages);
+            tls_packet.set_server_certificate();
+            tls_packet.set_fingerprints();
+
             parsed_packet.set_application_layer_packet(Some(
-                SerializablePacket::TlsPacket(
-                    SerializableTlsPacket {
-                        version: tls_packet.version,
-                        messages: custom_messages,
-                        length: tls_packet.length,
-                    }
-                ),
+                SerializablePacket::TlsPacket(tls_packet),
             ));
         }
     });
 }
 
+/// Merge the consecutive handshake records at the start of the buffer when the first one ends in
+/// the middle of a handshake message, as large certificate chains do (RFC 8446 5.1)
+fn reassemble_handshake(buffer: &[u8]) -> HandshakeReassembly {
+    let mut first_header: Option<TlsRecordHeader> = None;
+    let mut fragments = vec![];
+    let mut records = 0;
+    let mut offset = 0;
+
+    loop {
+        let header = match parse_tls_record_header(&buffer[offset..]) {
+            Ok((_, header)) => header,
+            Err(_) if first_header.is_some() => return HandshakeReassembly::Incomplete,
+            Err(_) => return HandshakeReassembly::Unfragmented,
+        };
+        if header.record_type != TlsRecordType::Handshake {
+            return HandshakeReassembly::Unfragmented;
+        }
+
+        let start = offset + RECORD_HEADER_LENGTH;
+        let fragment = match buffer.get(start..start + header.len as usize) {
+            Some(fragment) => fragment,
+            None if first_header.is_some() => return HandshakeReassembly::Incomplete,
+            None => return HandshakeReassembly::Unfragmented,
+        };
+        fragments.extend_from_slice(fragment);
+        offset = start + fragment.len();
+        records += 1;
+        first_header.get_or_insert(header);
+
+        match ends_on_message_boundary(&fragments) {
+            Some(true) => break,
+            Some(false) => continue,
+            None => return HandshakeReassembly::Unfragmented,
+        }
+    }
+
+    match (records, first_header) {
+        (1, _) | (_, None) => HandshakeReassembly::Unfragmented,
+        (_, Some(header)) => {
+            let header = TlsRecordHeader {
+                len: fragments.len() as u16,
+                ..header
+            };
+            HandshakeReassembly::Reassembled(header, fragments, offset)
+        }
+    }
+}
+
+/// Check if handshake data ends with a whole message, `None` when a message is too long to be
+/// reassembled (or more likely, when the data is encrypted)
+fn ends_on_message_boundary(fragments: &[u8]) -> Option<bool> {
+    let mut offset = 0;
+
+    while offset < fragments.len() {
+        let header = match fragments.get(offset..offset + HANDSHAKE_HEADER_LENGTH) {
+            Some(header) => header,
+            None => return Some(false),
+        };
+
+        let length = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        offset += HANDSHAKE_HEADER_LENGTH + length;
+        if offset > u16::MAX as usize {
+            return None;
+        }
+    }
+
+    Some(offset == fragments.len())
+}
+
 fn parse_messages(messages: Vec<TlsMessage>, custom_messages: &mut Vec<CustomTlsMessage>) {
     for msg in &messages {
         match msg {
@@ -367,7 +486,7 @@ mod tests {
         ParsedPacket, SerializablePacket,
     };
 
-    use super::handle_tls_packet;
+    use super::{handle_tls_packet, FlowContext};
 
     const SERVER_HELLO: &[u8] = &[
         0x16, 0x03, 0x03, 0x00, 0x52, 0x02, 0x00, 0x00, 0x4e, 0x03, 0x03, 0x6a, 0x24, 0x0b, 0x23,
@@ -497,18 +616,58 @@ mod tests {
 
     const ALERT: &[u8] = &[0x15, 0x03, 0x01, 0x00, 0x02, 0x02, 0x46];
 
+    const APPLICATION_DATA: &[u8] = &[
+        0x17, 0x03, 0x03, 0x00, 0x08, 0x9c, 0x41, 0x07, 0xe2, 0x5d, 0x13, 0xa8, 0x6f,
+    ];
+
     const UNKNOWN_RECORD: &[u8] = &[0x63, 0x0e, 0x00, 0x00, 0x03, 0x0f, 0xf8, 0xec];
 
     const TOO_LARGE_RECORD: &[u8] = &[0x17, 0x03, 0x03, 0x40, 0x11, 0x0f, 0xf8, 0xec];
 
+    // Leaf certificate of www.example.com (SANs: www.example.com, example.com, 192.0.2.1) issued
+    // by "Example Test CA", valid during 2024
+    const LEAF_CERTIFICATE: &[u8] = &[
+        0x30, 0x82, 0x01, 0xa6, 0x30, 0x82, 0x01, 0x4d, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x02,
+        0x12, 0x34, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x30,
+        0x1a, 0x31, 0x18, 0x30, 0x16, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x0f, 0x45, 0x78, 0x61,
+        0x6d, 0x70, 0x6c, 0x65, 0x20, 0x54, 0x65, 0x73, 0x74, 0x20, 0x43, 0x41, 0x30, 0x1e, 0x17,
+        0x0d, 0x32, 0x34, 0x30, 0x31, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x5a, 0x17,
+        0x0d, 0x32, 0x35, 0x30, 0x31, 0x30, 0x31, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x5a, 0x30,
+        0x2c, 0x31, 0x18, 0x30, 0x16, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x0f, 0x77, 0x77, 0x77,
+        0x2e, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x2e, 0x63, 0x6f, 0x6d, 0x31, 0x10, 0x30,
+        0x0e, 0x06, 0x03, 0x55, 0x04, 0x0a, 0x0c, 0x07, 0x45, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65,
+        0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08,
+        0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00, 0x04, 0xeb, 0x67, 0x37,
+        0x90, 0x2a, 0xbd, 0x7b, 0xcb, 0xba, 0x34, 0xa7, 0xb4, 0x5e, 0xc1, 0xae, 0xef, 0x66, 0xa7,
+        0xdb, 0x26, 0x53, 0x9b, 0x53, 0x60, 0x80, 0x5f, 0x1a, 0x59, 0xdd, 0xba, 0x6e, 0x02, 0xfb,
+        0x4a, 0x4f, 0xec, 0xda, 0x8b, 0x50, 0x06, 0xfe, 0x01, 0xfa, 0xcb, 0xaa, 0x0c, 0x6c, 0x82,
+        0x94, 0x18, 0x21, 0x90, 0x4e, 0xba, 0x0a, 0xbf, 0x58, 0x2c, 0xb9, 0x61, 0x4b, 0xa4, 0xff,
+        0x50, 0xa3, 0x71, 0x30, 0x6f, 0x30, 0x2d, 0x06, 0x03, 0x55, 0x1d, 0x11, 0x04, 0x26, 0x30,
+        0x24, 0x82, 0x0f, 0x77, 0x77, 0x77, 0x2e, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x2e,
+        0x63, 0x6f, 0x6d, 0x82, 0x0b, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x2e, 0x63, 0x6f,
+        0x6d, 0x87, 0x04, 0xc0, 0x00, 0x02, 0x01, 0x30, 0x1d, 0x06, 0x03, 0x55, 0x1d, 0x0e, 0x04,
+        0x16, 0x04, 0x14, 0x3b, 0x1d, 0x27, 0x7e, 0x06, 0xe9, 0x02, 0xaa, 0x70, 0x16, 0x56, 0x0e,
+        0x2f, 0x6c, 0xc9, 0xd4, 0x9a, 0xc2, 0x7e, 0xa0, 0x30, 0x1f, 0x06, 0x03, 0x55, 0x1d, 0x23,
+        0x04, 0x18, 0x30, 0x16, 0x80, 0x14, 0x95, 0x68, 0xcb, 0xc2, 0xfc, 0x17, 0xef, 0x55, 0xab,
+        0x71, 0x81, 0x2d, 0x96, 0x13, 0x09, 0xf1, 0x59, 0xb3, 0xf6, 0xa7, 0x30, 0x0a, 0x06, 0x08,
+        0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x03, 0x47, 0x00, 0x30, 0x44, 0x02, 0x20,
+        0x4b, 0xa2, 0x73, 0xa4, 0x2f, 0xed, 0xc0, 0xf4, 0x36, 0x97, 0x67, 0x2e, 0x76, 0xba, 0xc1,
+        0x67, 0x59, 0xc5, 0x58, 0x4b, 0xe1, 0x0e, 0xeb, 0xc1, 0x45, 0x00, 0x93, 0x92, 0x51, 0xdb,
+        0x1c, 0x73, 0x02, 0x20, 0x14, 0xda, 0xfb, 0x27, 0x88, 0x5e, 0xc4, 0xe4, 0x29, 0xa6, 0xd8,
+        0x23, 0x9d, 0xea, 0x0b, 0x4a, 0x31, 0x6f, 0xe7, 0xf9, 0xd0, 0x8b, 0x53, 0xa8, 0xe3, 0xdf,
+        0xa6, 0x1e, 0xd8, 0xdf, 0x46, 0xd1,
+    ];
+
     #[test]
     fn valid_server_hello_tls_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
         handle_tls_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            443,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                443,
+            ),
             SERVER_HELLO,
             &mut parsed_packet,
         );
@@ -562,10 +721,12 @@ mod tests {
     fn valid_server_done_tls_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
         handle_tls_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            443,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                443,
+            ),
             SERVER_HELLO_DONE,
             &mut parsed_packet,
         );
@@ -600,10 +761,12 @@ mod tests {
     fn valid_server_key_exchange_tls_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
         handle_tls_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            443,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                443,
+            ),
             SERVER_KEY_EXCHANGE,
             &mut parsed_packet,
         );
@@ -668,10 +831,12 @@ mod tests {
     fn valid_change_cipher_spec_tls_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
         handle_tls_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            443,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                443,
+            ),
             CHANGE_CIPHER_SPEC,
             &mut parsed_packet,
         );
@@ -702,10 +867,12 @@ mod tests {
     fn valid_client_hello_tls_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
         handle_tls_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            443,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                443,
+            ),
             CLIENT_HELLO,
             &mut parsed_packet,
         );
@@ -766,14 +933,58 @@ mod tests {
         }
     }
 
+    #[test]
+    fn ja3_fingerprints() {
+        for (hello, expected_ja3, expected_ja3s) in [
+            (CLIENT_HELLO, Some("02aa4679df284f240695da144b70c288"), None),
+            (SERVER_HELLO, None, Some("860fcf58fd757e26aa8911e5eaff6b53")),
+        ] {
+            let mut parsed_packet = ParsedPacket::new(0);
+            handle_tls_packet(
+                &FlowContext::new(
+                    IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                    4444,
+                    IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                    443,
+                ),
+                hello,
+                &mut parsed_packet,
+            );
+
+            match parsed_packet.get_application_layer_packet().unwrap() {
+                SerializablePacket::TlsPacket(new_tls_packet) => {
+                    assert_eq!(new_tls_packet.ja3.as_deref(), expected_ja3);
+                    assert_eq!(new_tls_packet.ja3s.as_deref(), expected_ja3s);
+                    match &new_tls_packet.messages[0] {
+                        CustomTlsMessage::Handshake(CustomHandshakeMessage::ClientHello(
+                            message,
+                        )) => assert_eq!(
+                            message.ja3,
+                            "771,4865-4867-4866-49195-49199-52393-52392-49196-49200-49162-49161-\
+                            49171-49172-156-157-47-53,0-23-65281-10-11-16-5-34-51-42-43-13-45-28-41,\
+                            29-23-24-25-256-257,0"
+                        ),
+                        CustomTlsMessage::Handshake(CustomHandshakeMessage::ServerHello(
+                            message,
+                        )) => assert_eq!(message.ja3s, "771,49199,65281-0-11-35-5-23-16"),
+                        _ => unreachable!(),
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
     #[test]
     fn valid_client_key_exchange_tls_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
         handle_tls_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            443,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                443,
+            ),
             CLIENT_KEY_EXCHANGE,
             &mut parsed_packet,
         );
@@ -816,10 +1027,12 @@ mod tests {
     fn valid_certificate_status_tls_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
         handle_tls_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            443,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                443,
+            ),
             CERTIFICATE_STATUS,
             &mut parsed_packet,
         );
@@ -855,10 +1068,12 @@ mod tests {
     fn valid_alert_tls_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
         handle_tls_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            443,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                443,
+            ),
             ALERT,
             &mut parsed_packet,
         );
@@ -888,14 +1103,75 @@ mod tests {
         }
     }
 
+    #[test]
+    fn fatal_alert_record() {
+        match server_tls_packet(ALERT).get_application_layer_packet() {
+            Some(SerializablePacket::TlsPacket(tls_packet)) => {
+                assert_eq!(tls_packet.records.len(), 1);
+                assert_eq!(tls_packet.records[0].content_type, "alert (21)");
+                assert_eq!(tls_packet.records[0].version, "Tls10");
+                assert_eq!(tls_packet.records[0].length, 2);
+
+                match &tls_packet.messages[..] {
+                    [CustomTlsMessage::Alert(alert)] => {
+                        assert_eq!(alert.severity, "Fatal");
+                        assert_eq!(alert.description, "ProtocolVersion");
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn application_data_record() {
+        let mut payload = APPLICATION_DATA.to_vec();
+        payload.extend_from_slice(CHANGE_CIPHER_SPEC);
+
+        match server_tls_packet(&payload).get_application_layer_packet() {
+            Some(SerializablePacket::TlsPacket(tls_packet)) => {
+                let records: Vec<(&str, &str, u16)> = tls_packet
+                    .records
+                    .iter()
+                    .map(|record| {
+                        (
+                            record.content_type.as_str(),
+                            record.version.as_str(),
+                            record.length,
+                        )
+                    })
+                    .collect();
+                assert_eq!(
+                    records,
+                    vec![
+                        ("application_data (23)", "Tls12", 8),
+                        ("change_cipher_spec (20)", "Tls12", 1),
+                    ]
+                );
+
+                match &tls_packet.messages[0] {
+                    CustomTlsMessage::Encrypted(message) => {
+                        assert_eq!(message.message_type, "ApplicationData");
+                        assert_eq!(message.data, &APPLICATION_DATA[5..]);
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
     #[test]
     fn unknown_tls_record() {
         let mut parsed_packet = ParsedPacket::new(0);
         handle_tls_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            443,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                443,
+            ),
             UNKNOWN_RECORD,
             &mut parsed_packet,
         );
@@ -930,10 +1206,12 @@ mod tests {
     fn too_large_tls_record() {
         let mut parsed_packet = ParsedPacket::new(0);
         handle_tls_packet(
-            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
-            4444,
-            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
-            443,
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                443,
+            ),
             TOO_LARGE_RECORD,
             &mut parsed_packet,
         );
@@ -963,4 +1241,98 @@ mod tests {
             _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn certificate_tls_packet() {
+        let record = build_test_tls_record(&build_test_certificate_message());
+        let parsed_packet = server_tls_packet(&record);
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::TlsPacket(new_tls_packet) => {
+                let certificate = new_tls_packet.server_certificate.as_ref().unwrap();
+                assert_eq!(
+                    certificate.subject_common_name.as_deref(),
+                    Some("www.example.com")
+                );
+                assert_eq!(
+                    certificate.issuer_common_name.as_deref(),
+                    Some("Example Test CA")
+                );
+                assert_eq!(certificate.not_before, "Jan  1 00:00:00 2024 +00:00");
+                assert_eq!(certificate.not_after, "Jan  1 00:00:00 2025 +00:00");
+                assert_eq!(
+                    certificate.subject_alternative_names,
+                    vec!["DNS:www.example.com", "DNS:example.com", "IP:192.0.2.1"]
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn fragmented_certificate_tls_packet() {
+        let message = build_test_certificate_message();
+        let mut records = build_test_tls_record(&message[..200]);
+        records.extend(build_test_tls_record(&message[200..]));
+
+        // The second record is split across TCP segments
+        let (first_segment, second_segment) = records.split_at(300);
+        assert!(server_tls_packet(first_segment)
+            .get_application_layer_packet()
+            .is_none());
+
+        match server_tls_packet(second_segment).get_application_layer_packet() {
+            Some(SerializablePacket::TlsPacket(new_tls_packet)) => {
+                assert_eq!(new_tls_packet.length as usize, message.len());
+                assert_eq!(
+                    new_tls_packet
+                        .server_certificate
+                        .as_ref()
+                        .and_then(|c| c.subject_common_name.as_deref()),
+                    Some("www.example.com")
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    ///////////////////// Utils
+
+    fn server_tls_packet(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_tls_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                443,
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                5555,
+            ),
+            payload,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+
+    /// Build a Certificate handshake message carrying the leaf certificate only
+    fn build_test_certificate_message() -> Vec<u8> {
+        let length = |length: usize| (length as u32).to_be_bytes()[1..].to_vec();
+
+        let mut certificate_list = length(LEAF_CERTIFICATE.len());
+        certificate_list.extend_from_slice(LEAF_CERTIFICATE);
+
+        let mut body = length(certificate_list.len());
+        body.extend(certificate_list);
+
+        let mut message = vec![0x0b];
+        message.extend(length(body.len()));
+        message.extend(body);
+        message
+    }
+
+    fn build_test_tls_record(fragment: &[u8]) -> Vec<u8> {
+        let mut record = vec![0x16, 0x03, 0x03];
+        record.extend_from_slice(&(fragment.len() as u16).to_be_bytes());
+        record.extend_from_slice(fragment);
+        record
+    }
 }
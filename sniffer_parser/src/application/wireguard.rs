@@ -0,0 +1,214 @@
+//! WireGuard Packet parsing
+//!
+//! Every WireGuard message starts with its type on one byte followed by three reserved zero bytes.
+//! The handshake messages have a fixed length and carry the little-endian indices the peers chose
+//! for the session; the transport data messages carry the receiver index and a nonce counter
+//! before the encrypted packet, padded to 16 bytes
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::SerializableWireGuardPacket, ParsedPacket, SerializablePacket,
+};
+
+use super::FlowContext;
+
+/// WireGuard Message Types
+#[allow(non_snake_case)]
+pub mod WireGuardMessageTypes {
+    pub const HANDSHAKE_INITIATION: u8 = 1;
+    pub const HANDSHAKE_RESPONSE: u8 = 2;
+    pub const COOKIE_REPLY: u8 = 3;
+    pub const TRANSPORT_DATA: u8 = 4;
+}
+
+const HANDSHAKE_INITIATION_LENGTH: usize = 148;
+const HANDSHAKE_RESPONSE_LENGTH: usize = 92;
+const COOKIE_REPLY_LENGTH: usize = 64;
+/// Header of a transport data message, followed by the encrypted packet and its 16 bytes tag
+const TRANSPORT_HEADER_LENGTH: usize = 16;
+const TRANSPORT_TAG_LENGTH: usize = 16;
+
+/// Fixed fields of a WireGuard message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WireGuardMessage {
+    pub message_type: u8,
+    /// Index chosen by the sender, in the handshake initiation and response
+    pub sender_index: Option<u32>,
+    /// Index chosen by the peer, in the messages other than the handshake initiation
+    pub receiver_index: Option<u32>,
+    /// Nonce of a transport data message
+    pub counter: Option<u64>,
+    pub length: usize,
+}
+
+impl WireGuardMessage {
+    /// Parse the fixed fields of a message, `None` if it has not the shape of a WireGuard message
+    pub fn parse(packet: &[u8]) -> Option<WireGuardMessage> {
+        if !looks_like_wireguard(packet) {
+            return None;
+        }
+
+        let index = |offset: usize| {
+            u32::from_le_bytes([
+                packet[offset],
+                packet[offset + 1],
+                packet[offset + 2],
+                packet[offset + 3],
+            ])
+        };
+        let mut message = WireGuardMessage {
+            message_type: packet[0],
+            sender_index: None,
+            receiver_index: None,
+            counter: None,
+            length: packet.len(),
+        };
+
+        match message.message_type {
+            WireGuardMessageTypes::HANDSHAKE_INITIATION => message.sender_index = Some(index(4)),
+            WireGuardMessageTypes::HANDSHAKE_RESPONSE => {
+                message.sender_index = Some(index(4));
+                message.receiver_index = Some(index(8));
+            }
+            WireGuardMessageTypes::COOKIE_REPLY => message.receiver_index = Some(index(4)),
+            _ => {
+                message.receiver_index = Some(index(4));
+                message.counter = Some(index(8) as u64 | (index(12) as u64) << 32);
+            }
+        }
+
+        Some(message)
+    }
+}
+
+/// Check if a payload has the type, reserved bytes and length of a WireGuard message
+pub fn looks_like_wireguard(payload: &[u8]) -> bool {
+    if payload.len() < 4 || payload[1..4] != [0, 0, 0] {
+        return false;
+    }
+
+    match payload[0] {
+        WireGuardMessageTypes::HANDSHAKE_INITIATION => payload.len() == HANDSHAKE_INITIATION_LENGTH,
+        WireGuardMessageTypes::HANDSHAKE_RESPONSE => payload.len() == HANDSHAKE_RESPONSE_LENGTH,
+        WireGuardMessageTypes::COOKIE_REPLY => payload.len() == COOKIE_REPLY_LENGTH,
+        WireGuardMessageTypes::TRANSPORT_DATA => {
+            payload.len() >= TRANSPORT_HEADER_LENGTH + TRANSPORT_TAG_LENGTH
+                && (payload.len() - TRANSPORT_HEADER_LENGTH).is_multiple_of(16)
+        }
+        _ => false,
+    }
+}
+
+/// Build a WireGuard packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_wireguard_packet(
+    flow: &FlowContext,
+    packet: &[u8],
+    parsed_packet: &mut ParsedPacket,
+) {
+    let FlowContext {
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        ..
+    } = *flow;
+
+    if let Some(wireguard_message) = WireGuardMessage::parse(packet) {
+        debug!(
+            "WireGuard Packet: {}:{} > {}:{}; Type: {}, Length: {}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            wireguard_message.message_type,
+            wireguard_message.length,
+        );
+
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::WireGuardPacket(
+            SerializableWireGuardPacket::from(&wireguard_message),
+        )));
+    } else {
+        debug!("Malformed WireGuard Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed WireGuard Packet".to_string(),
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{handle_wireguard_packet, FlowContext, WireGuardMessage, WireGuardMessageTypes};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    #[test]
+    fn handshake_initiation() {
+        let mut initiation = [0u8; 148];
+        initiation[0] = WireGuardMessageTypes::HANDSHAKE_INITIATION;
+        initiation[4..8].copy_from_slice(&0x12345678u32.to_le_bytes());
+
+        let message = WireGuardMessage::parse(&initiation).unwrap();
+        assert_eq!(message.sender_index, Some(0x12345678));
+        assert_eq!(message.receiver_index, None);
+
+        match wireguard_packet(&initiation).get_application_layer_packet() {
+            Some(SerializablePacket::WireGuardPacket(wireguard_packet)) => {
+                assert_eq!(wireguard_packet.message_type, 1);
+                assert_eq!(wireguard_packet.message_type_name, "Handshake Initiation");
+                assert_eq!(wireguard_packet.sender_index, Some(0x12345678));
+                assert_eq!(wireguard_packet.length, 148);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn transport_data_counter() {
+        let mut transport = [0u8; 16 + 16 + 32];
+        transport[0] = WireGuardMessageTypes::TRANSPORT_DATA;
+        transport[4..8].copy_from_slice(&7u32.to_le_bytes());
+        transport[8..16].copy_from_slice(&42u64.to_le_bytes());
+
+        let message = WireGuardMessage::parse(&transport).unwrap();
+        assert_eq!(
+            (message.receiver_index, message.counter),
+            (Some(7), Some(42))
+        );
+    }
+
+    #[test]
+    fn malformed_wireguard_packet() {
+        let mut initiation = [0u8; 148];
+        initiation[0] = WireGuardMessageTypes::HANDSHAKE_INITIATION;
+        for packet in [
+            &initiation[..100],
+            &[&[1, 0, 0, 1][..], &initiation[4..]].concat(),
+        ] {
+            match wireguard_packet(packet).get_application_layer_packet() {
+                Some(SerializablePacket::MalformedPacket(str)) => {
+                    assert_eq!(str, "Malformed WireGuard Packet")
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    ///////////////////// Utils
+
+    fn wireguard_packet(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_wireguard_packet(
+            &FlowContext::new(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                4444,
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                51820,
+            ),
+            payload,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+}
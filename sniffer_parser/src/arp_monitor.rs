@@ -0,0 +1,185 @@
+//! ARP conflict detection
+//!
+//! The IP to MAC bindings announced by the senders of the ARP packets are recorded; an IP address
+//! claimed by another MAC address while its binding is still fresh is a conflict, the sign of a
+//! duplicate address or of ARP spoofing. A binding unseen for longer than the rebinding delay is
+//! replaced silently, like the address of an expired DHCP lease given to another host
+
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::time::{Duration, SystemTime};
+
+use pnet::util::MacAddr;
+
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+/// IP address claimed by a new MAC address while bound to another one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArpConflict {
+    pub ip: Ipv4Addr,
+    pub old_mac: MacAddr,
+    pub new_mac: MacAddr,
+}
+
+/// MAC address bound to an IP address, with the time it was last announced
+#[derive(Debug, Clone, Copy)]
+struct ArpBinding {
+    mac: MacAddr,
+    last_seen: Option<SystemTime>,
+}
+
+/// IP to MAC bindings, fed with every parsed packet in capture order
+#[derive(Debug)]
+pub struct ArpMonitor {
+    rebind_after: Duration,
+    bindings: HashMap<Ipv4Addr, ArpBinding>,
+}
+
+impl ArpMonitor {
+    /// Build a monitor accepting a new MAC address for an IP address unseen for `rebind_after`
+    pub fn new(rebind_after: Duration) -> Self {
+        ArpMonitor {
+            rebind_after,
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Record the binding announced by the sender of an ARP packet, getting the conflict when
+    /// its IP address is bound to another MAC address; the probes (sender 0.0.0.0) bind nothing
+    pub fn update(&mut self, packet: &ParsedPacket) -> Option<ArpConflict> {
+        let arp_packet = match packet.get_network_layer_packet() {
+            Some(SerializablePacket::ArpPacket(arp_packet)) => arp_packet,
+            _ => return None,
+        };
+        let (ip, mac) = (arp_packet.sender_proto_addr, arp_packet.sender_hw_addr);
+        if ip.is_unspecified() {
+            return None;
+        }
+
+        let timestamp = packet.get_timestamp();
+        let binding = ArpBinding {
+            mac,
+            last_seen: timestamp,
+        };
+        let old_binding = self.bindings.insert(ip, binding)?;
+        if old_binding.mac == mac || self.is_stale(&old_binding, timestamp) {
+            return None;
+        }
+
+        Some(ArpConflict {
+            ip,
+            old_mac: old_binding.mac,
+            new_mac: mac,
+        })
+    }
+
+    /// Get the MAC address bound to an IP address
+    pub fn binding(&self, ip: Ipv4Addr) -> Option<MacAddr> {
+        self.bindings.get(&ip).map(|binding| binding.mac)
+    }
+
+    /// Check if a binding was last seen more than the rebinding delay before `now`
+    fn is_stale(&self, binding: &ArpBinding, now: Option<SystemTime>) -> bool {
+        match (binding.last_seen, now) {
+            (Some(last_seen), Some(now)) => now
+                .duration_since(last_seen)
+                .is_ok_and(|elapsed| elapsed > self.rebind_after),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use pnet::packet::arp::{ArpHardwareTypes, ArpOperations, MutableArpPacket};
+    use pnet::packet::ethernet::EtherTypes;
+    use pnet::packet::Packet;
+    use pnet::util::MacAddr;
+
+    use super::{ArpConflict, ArpMonitor};
+    use crate::handle_arp_packet;
+    use crate::serializable_packet::ParsedPacket;
+
+    const GATEWAY: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 1);
+    const GATEWAY_MAC: MacAddr = MacAddr(0x02, 0x00, 0x00, 0x00, 0x00, 0x01);
+    const ATTACKER_MAC: MacAddr = MacAddr(0x02, 0x00, 0x00, 0x00, 0x00, 0x66);
+
+    #[test]
+    fn conflicting_replies() {
+        let mut monitor = ArpMonitor::new(Duration::from_secs(3600));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        assert_eq!(
+            monitor.update(&build_test_arp_reply(GATEWAY, GATEWAY_MAC, start)),
+            None
+        );
+        assert_eq!(
+            monitor.update(&build_test_arp_reply(
+                GATEWAY,
+                ATTACKER_MAC,
+                start + Duration::from_secs(10)
+            )),
+            Some(ArpConflict {
+                ip: GATEWAY,
+                old_mac: GATEWAY_MAC,
+                new_mac: ATTACKER_MAC,
+            })
+        );
+        assert_eq!(monitor.binding(GATEWAY), Some(ATTACKER_MAC));
+    }
+
+    #[test]
+    fn stale_binding_replaced() {
+        let mut monitor = ArpMonitor::new(Duration::from_secs(3600));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        monitor.update(&build_test_arp_reply(GATEWAY, GATEWAY_MAC, start));
+        assert_eq!(
+            monitor.update(&build_test_arp_reply(
+                GATEWAY,
+                ATTACKER_MAC,
+                start + Duration::from_secs(7200)
+            )),
+            None
+        );
+        assert_eq!(monitor.binding(GATEWAY), Some(ATTACKER_MAC));
+
+        // Probes do not bind their unspecified sender address
+        monitor.update(&build_test_arp_reply(
+            Ipv4Addr::UNSPECIFIED,
+            GATEWAY_MAC,
+            start,
+        ));
+        assert_eq!(monitor.binding(Ipv4Addr::UNSPECIFIED), None);
+    }
+
+    ///////////////////// Utils
+
+    /// Build an ARP reply from the sender to 192.168.1.10
+    fn build_test_arp_reply(ip: Ipv4Addr, mac: MacAddr, timestamp: SystemTime) -> ParsedPacket {
+        let mut arp_buffer = [0u8; 28];
+        let mut arp_packet = MutableArpPacket::new(&mut arp_buffer).unwrap();
+        arp_packet.set_hardware_type(ArpHardwareTypes::Ethernet);
+        arp_packet.set_protocol_type(EtherTypes::Ipv4);
+        arp_packet.set_hw_addr_len(6);
+        arp_packet.set_proto_addr_len(4);
+        arp_packet.set_operation(ArpOperations::Reply);
+        arp_packet.set_sender_hw_addr(mac);
+        arp_packet.set_sender_proto_addr(ip);
+        arp_packet.set_target_hw_addr(MacAddr::new(0x02, 0, 0, 0, 0, 0x10));
+        arp_packet.set_target_proto_addr(Ipv4Addr::new(192, 168, 1, 10));
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_arp_packet(
+            arp_packet.packet(),
+            mac,
+            MacAddr::new(0x02, 0, 0, 0, 0, 0x10),
+            &mut parsed_packet,
+        );
+        parsed_packet.set_timestamp(Some(timestamp));
+        parsed_packet
+    }
+}
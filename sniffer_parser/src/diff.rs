@@ -0,0 +1,183 @@
+//! Field-by-field comparison of parsed packets
+//!
+//! Each layer of the packets is compared through its serialized representation, so the paths of
+//! the differing fields are those of the JSON output (e.g. `networkLayerPacket.packet.ttl`). A
+//! layer present in a single packet, a layer of another type, or an array of another length is
+//! reported as a whole. The packet ID and timestamp are not compared
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+/// Field whose value differs between two packets, absent in one of them when `None`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+    pub path: String,
+    pub left: Option<Value>,
+    pub right: Option<Value>,
+}
+
+impl fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = |value: &Option<Value>| match value {
+            Some(value) => value.to_string(),
+            None => "<none>".to_string(),
+        };
+        write!(
+            f,
+            "{}: {} != {}",
+            self.path,
+            value(&self.left),
+            value(&self.right)
+        )
+    }
+}
+
+/// Get the fields differing between two packets, layer by layer from the link layer up
+pub fn diff(a: &ParsedPacket, b: &ParsedPacket) -> Vec<FieldDiff> {
+    let layers = [
+        (
+            "linkLayerPacket",
+            a.get_link_layer_packet(),
+            b.get_link_layer_packet(),
+        ),
+        (
+            "networkLayerPacket",
+            a.get_network_layer_packet(),
+            b.get_network_layer_packet(),
+        ),
+        (
+            "transportLayerPacket",
+            a.get_transport_layer_packet(),
+            b.get_transport_layer_packet(),
+        ),
+        (
+            "applicationLayerPacket",
+            a.get_application_layer_packet(),
+            b.get_application_layer_packet(),
+        ),
+    ];
+
+    let mut diffs = vec![];
+    for (path, left, right) in layers {
+        let (left, right) = (left.map(layer_value), right.map(layer_value));
+        match (left, right) {
+            (Some(left), Some(right)) if left["type"] == right["type"] => {
+                diff_values(path.to_string(), &left, &right, &mut diffs)
+            }
+            (None, None) => (),
+            (left, right) => diffs.push(FieldDiff {
+                path: path.to_string(),
+                left,
+                right,
+            }),
+        }
+    }
+
+    diffs
+}
+
+fn layer_value(layer: &SerializablePacket) -> Value {
+    serde_json::to_value(layer).unwrap_or(Value::Null)
+}
+
+/// Compare two values, recursing into the objects and the arrays of the same length
+fn diff_values(path: String, left: &Value, right: &Value, diffs: &mut Vec<FieldDiff>) {
+    match (left, right) {
+        (Value::Object(left_fields), Value::Object(right_fields)) => {
+            for (key, left_field) in left_fields {
+                let field_path = format!("{}.{}", path, key);
+                match right_fields.get(key) {
+                    Some(right_field) => diff_values(field_path, left_field, right_field, diffs),
+                    None => diffs.push(FieldDiff {
+                        path: field_path,
+                        left: Some(left_field.clone()),
+                        right: None,
+                    }),
+                }
+            }
+            for (key, right_field) in right_fields {
+                if !left_fields.contains_key(key) {
+                    diffs.push(FieldDiff {
+                        path: format!("{}.{}", path, key),
+                        left: None,
+                        right: Some(right_field.clone()),
+                    });
+                }
+            }
+        }
+        (Value::Array(left_items), Value::Array(right_items))
+            if left_items.len() == right_items.len() =>
+        {
+            for (index, (left_item, right_item)) in left_items.iter().zip(right_items).enumerate() {
+                diff_values(format!("{}[{}]", path, index), left_item, right_item, diffs);
+            }
+        }
+        _ if left != right => diffs.push(FieldDiff {
+            path,
+            left: Some(left.clone()),
+            right: Some(right.clone()),
+        }),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pnet::packet::ethernet::EtherTypes;
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use serde_json::json;
+
+    use super::{diff, FieldDiff};
+    use crate::serializable_packet::ParsedPacket;
+    use crate::test_util::{
+        build_test_ethernet_frame, build_test_ipv4_packet, build_test_udp_datagram,
+        parse_test_frame, CLIENT, SERVER,
+    };
+
+    #[test]
+    fn packets_differing_in_ttl() {
+        let original = build_test_udp_packet(64, 0);
+        let forwarded = build_test_udp_packet(63, 1);
+
+        assert!(diff(&original, &original).is_empty());
+        assert_eq!(
+            diff(&original, &forwarded),
+            vec![FieldDiff {
+                path: "networkLayerPacket.packet.ttl".to_string(),
+                left: Some(json!(64)),
+                right: Some(json!(63)),
+            }]
+        );
+    }
+
+    #[test]
+    fn packet_missing_layer() {
+        let original = build_test_udp_packet(64, 0);
+        let mut truncated = original.clone();
+        truncated.set_transport_layer_packet(None);
+
+        let diffs = diff(&original, &truncated);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].path, "transportLayerPacket");
+        assert_eq!(diffs[0].left.as_ref().unwrap()["type"], "UdpPacket");
+        assert_eq!(diffs[0].right, None);
+    }
+
+    ///////////////////// Utils
+
+    /// Build a UDP datagram from 10.10.10.10:4444 to 11.11.11.11:9999, with the given TTL
+    fn build_test_udp_packet(ttl: u8, id: usize) -> ParsedPacket {
+        let udp_datagram = build_test_udp_datagram(4444, 9999, &[]);
+        let ip_packet = build_test_ipv4_packet(
+            CLIENT,
+            SERVER,
+            ttl,
+            IpNextHeaderProtocols::Udp,
+            &udp_datagram,
+        );
+        parse_test_frame(&build_test_ethernet_frame(EtherTypes::Ipv4, &ip_packet), id)
+    }
+}
@@ -0,0 +1,206 @@
+//! DNS response time tracking
+//!
+//! Queries are recorded with their capture timestamp, keyed by transaction ID, client and server
+//! endpoints and query name; the matching response gets the elapsed time attached. Queries left
+//! unanswered for longer than the timeout are forgotten
+
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+use crate::serializable_packet::util::{get_dest_endpoint, get_source_endpoint};
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+/// Identifier of a DNS query, shared by its response
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DnsQueryKey {
+    pub transaction_id: u16,
+    pub client: (IpAddr, u16),
+    pub server: (IpAddr, u16),
+    pub query_name: String,
+}
+
+/// Pending DNS queries, fed with every parsed packet in capture order
+#[derive(Debug)]
+pub struct DnsTracker {
+    timeout: Duration,
+    queries: HashMap<DnsQueryKey, SystemTime>,
+    /// Queries in capture order, with their timestamp, the oldest first
+    deadlines: VecDeque<(SystemTime, DnsQueryKey)>,
+}
+
+impl DnsTracker {
+    /// Build a tracker forgetting the queries unanswered after `timeout`
+    pub fn new(timeout: Duration) -> Self {
+        DnsTracker {
+            timeout,
+            queries: HashMap::new(),
+            deadlines: VecDeque::new(),
+        }
+    }
+
+    /// Record a query, or attach its response time to a response; packets without timestamp are
+    /// ignored
+    pub fn update(&mut self, packet: &mut ParsedPacket) {
+        let timestamp = match packet.get_timestamp() {
+            Some(timestamp) => timestamp,
+            None => return,
+        };
+        self.expire(timestamp);
+
+        let source = get_source_endpoint(packet);
+        let dest = get_dest_endpoint(packet);
+
+        let dns_packet = packet.layers_mut().find_map(|layer| match layer {
+            SerializablePacket::DnsPacket(dns_packet) => Some(dns_packet),
+            _ => None,
+        });
+        let (dns_packet, source, dest) = match (dns_packet, source, dest) {
+            (Some(dns_packet), Some(source), Some(dest)) => (dns_packet, source, dest),
+            _ => return,
+        };
+
+        let query_name = dns_packet
+            .questions
+            .first()
+            .map(|question| question.query_name.clone())
+            .unwrap_or_default();
+        let (client, server) = match dns_packet.header.query {
+            true => (source, dest),
+            false => (dest, source),
+        };
+        let key = DnsQueryKey {
+            transaction_id: dns_packet.header.id,
+            client,
+            server,
+            query_name,
+        };
+
+        match dns_packet.header.query {
+            true => {
+                self.queries.insert(key.clone(), timestamp);
+                self.deadlines.push_back((timestamp, key));
+            }
+            false => {
+                if let Some(query_timestamp) = self.queries.remove(&key) {
+                    let response_time = timestamp
+                        .duration_since(query_timestamp)
+                        .unwrap_or_default();
+                    dns_packet.response_time_ms = Some(response_time.as_secs_f64() * 1000.0);
+                }
+            }
+        }
+    }
+
+    /// Forget the queries older than the timeout at the given time
+    pub fn expire(&mut self, now: SystemTime) {
+        while let Some((query_timestamp, key)) = self.deadlines.front() {
+            if now
+                .duration_since(*query_timestamp)
+                .map_or(true, |elapsed| elapsed <= self.timeout)
+            {
+                break;
+            }
+            // The query may have been answered, or sent again since
+            if self.queries.get(key) == Some(query_timestamp) {
+                self.queries.remove(key);
+            }
+            self.deadlines.pop_front();
+        }
+    }
+
+    /// Get the number of queries waiting for their response
+    pub fn pending(&self) -> usize {
+        self.queries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::DnsTracker;
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+    use crate::test_util::{build_test_udp_packet, timestamped, CLIENT, SERVER};
+
+    const DNS_QUERY: &[u8] = &[
+        0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x07, b'e', b'x',
+        b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00, 0x00, 0x01, 0x00, 0x01,
+    ];
+
+    #[test]
+    fn query_response_time() {
+        let mut tracker = DnsTracker::new(Duration::from_secs(5));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        let mut query = build_test_dns_packet(false, start);
+        tracker.update(&mut query);
+        assert_eq!(tracker.pending(), 1);
+
+        let mut response = build_test_dns_packet(true, start + Duration::from_millis(25));
+        tracker.update(&mut response);
+        assert_eq!(tracker.pending(), 0);
+
+        match response.get_application_layer_packet() {
+            Some(SerializablePacket::DnsPacket(dns_packet)) => {
+                let response_time_ms = dns_packet.response_time_ms.unwrap();
+                assert!((response_time_ms - 25.0).abs() < 1e-6);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn unanswered_query_expires() {
+        let mut tracker = DnsTracker::new(Duration::from_secs(5));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        tracker.update(&mut build_test_dns_packet(false, start));
+        tracker.expire(start + Duration::from_secs(1));
+        assert_eq!(tracker.pending(), 1);
+
+        let mut response = build_test_dns_packet(true, start + Duration::from_secs(6));
+        tracker.update(&mut response);
+        assert_eq!(tracker.pending(), 0);
+
+        match response.get_application_layer_packet() {
+            Some(SerializablePacket::DnsPacket(dns_packet)) => {
+                assert_eq!(dns_packet.response_time_ms, None)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn resent_query_kept() {
+        let mut tracker = DnsTracker::new(Duration::from_secs(5));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        tracker.update(&mut build_test_dns_packet(false, start));
+        tracker.update(&mut build_test_dns_packet(
+            false,
+            start + Duration::from_secs(4),
+        ));
+        tracker.expire(start + Duration::from_secs(6));
+        assert_eq!(tracker.pending(), 1);
+
+        tracker.expire(start + Duration::from_secs(10));
+        assert_eq!(tracker.pending(), 0);
+    }
+
+    ///////////////////// Utils
+
+    /// Build the query for example.com, or its (empty) response
+    fn build_test_dns_packet(is_response: bool, timestamp: SystemTime) -> ParsedPacket {
+        let mut dns = DNS_QUERY.to_vec();
+        let packet = match is_response {
+            true => {
+                dns[2] = 0x81;
+                dns[3] = 0x80;
+                build_test_udp_packet((SERVER, 53), (CLIENT, 4444), &dns)
+            }
+            false => build_test_udp_packet((CLIENT, 4444), (SERVER, 53), &dns),
+        };
+        timestamped(packet, timestamp)
+    }
+}
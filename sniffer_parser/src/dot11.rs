@@ -0,0 +1,418 @@
+//! IEEE 802.11 (WiFi) parsing
+//!
+//! Monitor-mode captures (LINKTYPE_IEEE802_11_RADIOTAP) prefix every 802.11 frame with a radiotap
+//! header, whose present flags announce the reception fields that follow: the signal strength
+//! and the channel are decoded. The frame control and the addresses of the 802.11 header are
+//! decoded for every frame, along with the SSID of the beacons and of the probes; the unprotected
+//! data frames carrying LLC/SNAP encapsulated IP or ARP packets are passed to the network layer
+
+use log::{debug, warn};
+use pnet::packet::ethernet::{EtherType, EtherTypes};
+use pnet::util::MacAddr;
+
+use crate::network::{handle_arp_packet, handle_ipv4_packet, handle_ipv6_packet};
+use crate::serializable_packet::{
+    dot11_frame_type_to_string, dot11_subtype_to_string, ParsedPacket, SerializableDot11Packet,
+    SerializablePacket,
+};
+use crate::tlv::{Endianness, TlvParser};
+use crate::RAW_FRAME_RETENTION;
+
+const RADIOTAP_HEADER_MIN_LENGTH: usize = 8;
+const DOT11_ADDRESS_LENGTH: usize = 6;
+const FCS_LENGTH: usize = 4;
+const LLC_SNAP_HEADER: [u8; 6] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00];
+
+/// Radiotap present flags of the fields which are decoded or precede them
+const RADIOTAP_TSFT: u32 = 1 << 0;
+const RADIOTAP_FLAGS: u32 = 1 << 1;
+const RADIOTAP_RATE: u32 = 1 << 2;
+const RADIOTAP_CHANNEL: u32 = 1 << 3;
+const RADIOTAP_FHSS: u32 = 1 << 4;
+const RADIOTAP_ANTENNA_SIGNAL: u32 = 1 << 5;
+const RADIOTAP_EXTENDED: u32 = 1 << 31;
+/// Radiotap flag telling that the frame ends with its FCS
+const RADIOTAP_FLAG_FCS: u8 = 0x10;
+
+/// 802.11 frame control flags
+const TO_DS: u8 = 0x01;
+const FROM_DS: u8 = 0x02;
+const PROTECTED: u8 = 0x40;
+
+/// 802.11 Frame Types
+#[allow(non_snake_case)]
+pub mod Dot11FrameTypes {
+    pub const MANAGEMENT: u8 = 0;
+    pub const CONTROL: u8 = 1;
+    pub const DATA: u8 = 2;
+}
+
+/// 802.11 Management Frame Subtypes
+#[allow(non_snake_case)]
+pub mod Dot11ManagementSubtypes {
+    pub const ASSOCIATION_REQUEST: u8 = 0;
+    pub const ASSOCIATION_RESPONSE: u8 = 1;
+    pub const REASSOCIATION_REQUEST: u8 = 2;
+    pub const REASSOCIATION_RESPONSE: u8 = 3;
+    pub const PROBE_REQUEST: u8 = 4;
+    pub const PROBE_RESPONSE: u8 = 5;
+    pub const BEACON: u8 = 8;
+    pub const ATIM: u8 = 9;
+    pub const DISASSOCIATION: u8 = 10;
+    pub const AUTHENTICATION: u8 = 11;
+    pub const DEAUTHENTICATION: u8 = 12;
+    pub const ACTION: u8 = 13;
+}
+
+/// 802.11 Control Frame Subtypes
+#[allow(non_snake_case)]
+pub mod Dot11ControlSubtypes {
+    pub const BLOCK_ACK_REQUEST: u8 = 8;
+    pub const BLOCK_ACK: u8 = 9;
+    pub const PS_POLL: u8 = 10;
+    pub const RTS: u8 = 11;
+    pub const CTS: u8 = 12;
+    pub const ACK: u8 = 13;
+    pub const CF_END: u8 = 14;
+}
+
+/// 802.11 Data Frame Subtypes
+#[allow(non_snake_case)]
+pub mod Dot11DataSubtypes {
+    pub const DATA: u8 = 0;
+    pub const NULL: u8 = 4;
+    pub const QOS_DATA: u8 = 8;
+    pub const QOS_NULL: u8 = 12;
+}
+
+/// Reception fields of a radiotap header
+#[derive(Debug, Default)]
+struct RadiotapFields {
+    present_flags: u32,
+    flags: u8,
+    signal_dbm: Option<i8>,
+    channel_frequency: Option<u16>,
+}
+
+/// Parse a radiotap frame obtaining the packet link-layer and, for the data frames, network-layer
+/// representations
+pub fn parse_radiotap_frame(frame: &[u8], id: usize) -> ParsedPacket {
+    let mut parsed_packet = ParsedPacket::new(id);
+
+    if RAW_FRAME_RETENTION.with(|retention| retention.get()) {
+        parsed_packet.set_raw_frame(Some(frame.to_vec()));
+    }
+
+    let (radiotap_length, radiotap) = match parse_radiotap_header(frame) {
+        Some(header) => header,
+        None => {
+            warn!("Malformed Radiotap Packet: length: {}", frame.len());
+            parsed_packet.set_link_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed Radiotap Packet".to_string(),
+            )));
+            return parsed_packet;
+        }
+    };
+
+    let mut dot11_frame = &frame[radiotap_length..];
+    if radiotap.flags & RADIOTAP_FLAG_FCS != 0 {
+        dot11_frame = &dot11_frame[..dot11_frame.len().saturating_sub(FCS_LENGTH)];
+    }
+
+    handle_dot11_frame(dot11_frame, radiotap, frame.len(), &mut parsed_packet);
+
+    parsed_packet
+}
+
+/// Read the length and the decoded fields of a radiotap header, `None` when truncated or shorter
+/// than the fixed fields
+fn parse_radiotap_header(frame: &[u8]) -> Option<(usize, RadiotapFields)> {
+    let header = frame.get(..RADIOTAP_HEADER_MIN_LENGTH)?;
+    let length = u16::from_le_bytes([header[2], header[3]]) as usize;
+    if length < RADIOTAP_HEADER_MIN_LENGTH {
+        return None;
+    }
+    let header = frame.get(..length)?;
+    if header[0] != 0 {
+        return None;
+    }
+
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes = header.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let mut fields = RadiotapFields {
+        present_flags: read_u32(4)?,
+        ..Default::default()
+    };
+
+    // The fields follow the (possibly extended) present flags, each aligned on its size
+    let mut offset = 8;
+    let mut present = fields.present_flags;
+    while present & RADIOTAP_EXTENDED != 0 {
+        present = read_u32(offset)?;
+        offset += 4;
+    }
+    let mut field = |flag: u32, alignment: usize, size: usize| -> Option<Option<&[u8]>> {
+        if fields.present_flags & flag == 0 {
+            return Some(None);
+        }
+        offset = offset.next_multiple_of(alignment);
+        let value = header.get(offset..offset + size)?;
+        offset += size;
+        Some(Some(value))
+    };
+
+    field(RADIOTAP_TSFT, 8, 8)?;
+    let flags = field(RADIOTAP_FLAGS, 1, 1)?.map(|value| value[0]);
+    field(RADIOTAP_RATE, 1, 1)?;
+    let channel =
+        field(RADIOTAP_CHANNEL, 2, 4)?.map(|value| u16::from_le_bytes([value[0], value[1]]));
+    field(RADIOTAP_FHSS, 1, 2)?;
+    let signal = field(RADIOTAP_ANTENNA_SIGNAL, 1, 1)?.map(|value| value[0] as i8);
+
+    fields.flags = flags.unwrap_or_default();
+    fields.channel_frequency = channel;
+    fields.signal_dbm = signal;
+
+    Some((length, fields))
+}
+
+/// Build an 802.11 packet from the frame following the radiotap header, save it in a Parsed
+/// Packet, passing the payload of the data frames to the network layer
+fn handle_dot11_frame(
+    frame: &[u8],
+    radiotap: RadiotapFields,
+    length: usize,
+    parsed_packet: &mut ParsedPacket,
+) {
+    let (address_count, header_length) = match dot11_header_layout(frame) {
+        Some(layout) => layout,
+        None => {
+            warn!("Malformed 802.11 Packet: length: {}", frame.len());
+            parsed_packet.set_link_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed 802.11 Packet".to_string(),
+            )));
+            return;
+        }
+    };
+    let frame_type = (frame[0] >> 2) & 0x03;
+    let subtype = frame[0] >> 4;
+    let flags = frame[1];
+    let ds = flags & (TO_DS | FROM_DS);
+
+    // Addresses 1 to 3 follow the frame control and the duration, address 4 the sequence control
+    let address = |index: usize| -> Option<MacAddr> {
+        if index > address_count {
+            return None;
+        }
+        let offset = match index {
+            4 => 24,
+            _ => 4 + (index - 1) * DOT11_ADDRESS_LENGTH,
+        };
+        let bytes = &frame[offset..offset + DOT11_ADDRESS_LENGTH];
+        Some(MacAddr::new(
+            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5],
+        ))
+    };
+    let bssid = match (frame_type, ds) {
+        (Dot11FrameTypes::CONTROL, _) => None,
+        (_, 0) => address(3),
+        (_, TO_DS) => address(1),
+        (_, FROM_DS) => address(2),
+        _ => None,
+    };
+
+    let body = &frame[header_length..];
+    let ssid = match (frame_type, subtype) {
+        (Dot11FrameTypes::MANAGEMENT, Dot11ManagementSubtypes::BEACON)
+        | (Dot11FrameTypes::MANAGEMENT, Dot11ManagementSubtypes::PROBE_RESPONSE) => {
+            // Timestamp, beacon interval and capabilities precede the information elements
+            body.get(12..).and_then(read_ssid)
+        }
+        (Dot11FrameTypes::MANAGEMENT, Dot11ManagementSubtypes::PROBE_REQUEST) => read_ssid(body),
+        _ => None,
+    };
+
+    let receiver = address(1).unwrap();
+    let transmitter = address(2);
+    parsed_packet.set_link_layer_packet(Some(SerializablePacket::Dot11Packet(
+        SerializableDot11Packet {
+            present_flags: radiotap.present_flags,
+            signal_dbm: radiotap.signal_dbm,
+            channel_frequency: radiotap.channel_frequency,
+            frame_type: dot11_frame_type_to_string(frame_type),
+            subtype: dot11_subtype_to_string(frame_type, subtype),
+            flags,
+            receiver,
+            transmitter,
+            bssid,
+            ssid,
+            length,
+        },
+    )));
+
+    if frame_type != Dot11FrameTypes::DATA || flags & PROTECTED != 0 {
+        return;
+    }
+    let payload = match body.strip_prefix(&LLC_SNAP_HEADER[..]) {
+        Some(payload) if payload.len() >= 2 => payload,
+        _ => return,
+    };
+    let ethertype = EtherType(u16::from_be_bytes([payload[0], payload[1]]));
+    let payload = &payload[2..];
+
+    match ethertype {
+        EtherTypes::Ipv4 => handle_ipv4_packet(payload, parsed_packet),
+        EtherTypes::Ipv6 => handle_ipv6_packet(payload, parsed_packet),
+        EtherTypes::Arp => handle_arp_packet(
+            payload,
+            transmitter.unwrap_or_else(MacAddr::zero),
+            receiver,
+            parsed_packet,
+        ),
+        _ => debug!(
+            "Unknown 802.11 data: ethertype: {:?} length: {}",
+            ethertype,
+            payload.len()
+        ),
+    }
+}
+
+/// Get the number of addresses and the length of the 802.11 header of a frame, `None` when
+/// truncated
+fn dot11_header_layout(frame: &[u8]) -> Option<(usize, usize)> {
+    let (frame_control, flags) = (frame.first()?, frame.get(1)?);
+    let frame_type = (frame_control >> 2) & 0x03;
+    let subtype = frame_control >> 4;
+
+    let (address_count, header_length) = match frame_type {
+        Dot11FrameTypes::CONTROL => match subtype {
+            Dot11ControlSubtypes::CTS | Dot11ControlSubtypes::ACK => (1, 10),
+            _ => (2, 16),
+        },
+        Dot11FrameTypes::DATA => {
+            let address_count = if flags & (TO_DS | FROM_DS) == TO_DS | FROM_DS {
+                4
+            } else {
+                3
+            };
+            let qos_length = if subtype & 0x08 != 0 { 2 } else { 0 };
+            (
+                address_count,
+                24 + (address_count - 3) * DOT11_ADDRESS_LENGTH + qos_length,
+            )
+        }
+        _ => (3, 24),
+    };
+
+    (frame.len() >= header_length).then_some((address_count, header_length))
+}
+
+/// Read the SSID element among the information elements of a management frame
+fn read_ssid(elements: &[u8]) -> Option<String> {
+    TlvParser::new(elements, 1, 1, Endianness::Big)
+        .map_while(Result::ok)
+        .find(|element| element.tlv_type == 0)
+        .map(|element| String::from_utf8_lossy(element.value).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use pnet::util::MacAddr;
+
+    use crate::pcap::tests::build_test_pcap_with_link_type;
+    use crate::serializable_packet::SerializablePacket;
+    use crate::{parse_pcap_record, parse_radiotap_frame, LinkTypes, PcapReader};
+
+    const BSSID: [u8; 6] = [0x02, 0x11, 0x22, 0x33, 0x44, 0x55];
+
+    #[test]
+    fn beacon_ssid() {
+        let pcap = build_test_pcap_with_link_type(
+            LinkTypes::IEEE802_11_RADIOTAP,
+            &[(1_600_000_000, 0, build_test_beacon_frame())],
+        );
+        let mut reader = PcapReader::new(pcap.as_slice()).unwrap();
+        let record = reader.next_record().unwrap().unwrap();
+        let parsed_packet = parse_pcap_record(&record, 0);
+
+        match parsed_packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::Dot11Packet(dot11_packet) => {
+                assert_eq!(dot11_packet.frame_type, "Management (0)");
+                assert_eq!(dot11_packet.subtype, "Beacon (8)");
+                assert_eq!(dot11_packet.ssid.as_deref(), Some("TheWire"));
+                assert_eq!(dot11_packet.signal_dbm, Some(-42));
+                assert_eq!(dot11_packet.channel_frequency, Some(2437));
+                assert_eq!(dot11_packet.receiver, MacAddr::broadcast());
+                let bssid =
+                    MacAddr::new(BSSID[0], BSSID[1], BSSID[2], BSSID[3], BSSID[4], BSSID[5]);
+                assert_eq!(dot11_packet.transmitter, Some(bssid));
+                assert_eq!(dot11_packet.bssid, Some(bssid));
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(parsed_packet.protocol_stack(), vec!["802.11"]);
+    }
+
+    #[test]
+    fn malformed_radiotap_packet() {
+        let beacon = build_test_beacon_frame();
+
+        match parse_radiotap_frame(&beacon[..12], 0)
+            .get_link_layer_packet()
+            .unwrap()
+        {
+            SerializablePacket::MalformedPacket(str) => {
+                assert_eq!(str, "Malformed Radiotap Packet")
+            }
+            _ => unreachable!(),
+        }
+        match parse_radiotap_frame(&beacon[..20], 0)
+            .get_link_layer_packet()
+            .unwrap()
+        {
+            SerializablePacket::MalformedPacket(str) => assert_eq!(str, "Malformed 802.11 Packet"),
+            _ => unreachable!(),
+        }
+
+        // Radiotap length shorter than the fixed fields
+        for length in [0, 7] {
+            let frame = [0x00, 0x00, length, 0x00, 0x00, 0x00, 0x00, 0x00];
+            match parse_radiotap_frame(&frame, 0)
+                .get_link_layer_packet()
+                .unwrap()
+            {
+                SerializablePacket::MalformedPacket(str) => {
+                    assert_eq!(str, "Malformed Radiotap Packet")
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    ///////////////////// Utils
+
+    /// Build the beacon of the TheWire network on channel 6, received at -42 dBm
+    fn build_test_beacon_frame() -> Vec<u8> {
+        // Radiotap: flags, channel (2437 MHz, 2 GHz OFDM) and antenna signal
+        let mut frame = vec![0x00, 0x00, 0x0f, 0x00, 0x2a, 0x00, 0x00, 0x00];
+        frame.extend_from_slice(&[0x00, 0x00, 0x85, 0x09, 0xc0, 0x00, 0xd6]);
+
+        // Frame control, duration, DA, SA, BSSID, sequence control
+        frame.extend_from_slice(&[0x80, 0x00, 0x00, 0x00]);
+        frame.extend_from_slice(&[0xff; 6]);
+        frame.extend_from_slice(&BSSID);
+        frame.extend_from_slice(&BSSID);
+        frame.extend_from_slice(&[0x10, 0x00]);
+
+        // Timestamp, beacon interval, capabilities, then the SSID and DS parameter set elements
+        frame.extend_from_slice(&[0x00; 8]);
+        frame.extend_from_slice(&[0x64, 0x00, 0x11, 0x04]);
+        frame.extend_from_slice(&[0x00, 0x07]);
+        frame.extend_from_slice(b"TheWire");
+        frame.extend_from_slice(&[0x03, 0x01, 0x06]);
+
+        frame
+    }
+}
@@ -0,0 +1,269 @@
+//! Packet filtering
+//!
+//! A filter is a small BPF-like expression evaluated on parsed packets: a list of primitives,
+//! all of which must match, each one optionally negated with `not`
+//! - a protocol name: `ether`, `pppoe`, `sll`, `wlan`, `arp`, `ip`, `ip6`, `icmp`, `icmp6`, `igmp`,
+//!   `ospf`, `sctp`, `tcp`, `udp`, `http`, `tls`, `dtls`, `quic`, `dns`, `smtp`, `pop3`, `imap`,
+//!   `ldap`, `kerberos`, `stun`, `telnet`, `nbns`, `wireguard`, `gtp`, `modbus`, `syslog`,
+//!   `dhcpv6`, `malformed`, `unknown`
+//! - `host <address>`, `src host <address>`, `dst host <address>` (IP or MAC address)
+//! - `port <port>`, `src port <port>`, `dst port <port>`
+//!
+//! For instance `tcp and port 80 and not host 10.0.0.1`
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::serializable_packet::util::{
+    contains_arp, contains_dhcpv6, contains_dns, contains_dot11, contains_dtls, contains_ethernet,
+    contains_gtp, contains_http, contains_icmp, contains_icmp6, contains_igmp, contains_imap,
+    contains_ipv4, contains_ipv6, contains_kerberos, contains_ldap, contains_malformed,
+    contains_modbus, contains_nbns, contains_ospf, contains_pop3, contains_pppoe, contains_quic,
+    contains_sctp, contains_sll, contains_smtp, contains_stun, contains_syslog, contains_tcp,
+    contains_telnet, contains_tls, contains_udp, contains_unknokn, contains_wireguard, get_dest_ip,
+    get_dest_mac, get_dest_port, get_source_ip, get_source_mac, get_source_port,
+};
+use crate::serializable_packet::ParsedPacket;
+
+/// Direction of an address or port primitive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Source,
+    Destination,
+    Any,
+}
+
+/// Single test of a filter expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Primitive {
+    Protocol(String),
+    Host(Direction, String),
+    Port(Direction, u16),
+}
+
+/// Filter expression: conjunction of (possibly negated) primitives, empty matching every packet
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PacketFilter {
+    primitives: Vec<(bool, Primitive)>,
+}
+
+/// Check of the presence of a protocol in a parsed packet
+type ContainsProtocol = fn(&ParsedPacket) -> bool;
+
+/// Protocol names accepted by filters, with the function checking their presence
+const PROTOCOLS: &[(&str, ContainsProtocol)] = &[
+    ("ether", contains_ethernet),
+    ("pppoe", contains_pppoe),
+    ("sll", contains_sll),
+    ("wlan", contains_dot11),
+    ("arp", contains_arp),
+    ("ip", contains_ipv4),
+    ("ip6", contains_ipv6),
+    ("icmp", contains_icmp),
+    ("icmp6", contains_icmp6),
+    ("igmp", contains_igmp),
+    ("ospf", contains_ospf),
+    ("sctp", contains_sctp),
+    ("tcp", contains_tcp),
+    ("udp", contains_udp),
+    ("http", contains_http),
+    ("tls", contains_tls),
+    ("dtls", contains_dtls),
+    ("quic", contains_quic),
+    ("dns", contains_dns),
+    ("smtp", contains_smtp),
+    ("pop3", contains_pop3),
+    ("imap", contains_imap),
+    ("ldap", contains_ldap),
+    ("kerberos", contains_kerberos),
+    ("stun", contains_stun),
+    ("telnet", contains_telnet),
+    ("nbns", contains_nbns),
+    ("wireguard", contains_wireguard),
+    ("gtp", contains_gtp),
+    ("modbus", contains_modbus),
+    ("syslog", contains_syslog),
+    ("dhcpv6", contains_dhcpv6),
+    ("malformed", contains_malformed),
+    ("unknown", contains_unknokn),
+];
+
+impl PacketFilter {
+    /// Check if a parsed packet matches every primitive of the filter
+    pub fn matches(&self, packet: &ParsedPacket) -> bool {
+        self.primitives
+            .iter()
+            .all(|(negated, primitive)| primitive.matches(packet) != *negated)
+    }
+}
+
+impl Primitive {
+    fn matches(&self, packet: &ParsedPacket) -> bool {
+        match self {
+            Primitive::Protocol(name) => PROTOCOLS
+                .iter()
+                .find(|(protocol, _)| protocol == name)
+                .is_some_and(|(_, contains)| contains(packet)),
+            Primitive::Host(direction, address) => {
+                let source = [get_source_ip(packet), get_source_mac(packet)];
+                let dest = [get_dest_ip(packet), get_dest_mac(packet)];
+                let is_address = |a: &Option<String>| a.as_deref() == Some(address.as_str());
+
+                match direction {
+                    Direction::Source => source.iter().any(is_address),
+                    Direction::Destination => dest.iter().any(is_address),
+                    Direction::Any => source.iter().chain(dest.iter()).any(is_address),
+                }
+            }
+            Primitive::Port(direction, port) => {
+                let port = Some(port.to_string());
+
+                match direction {
+                    Direction::Source => get_source_port(packet) == port,
+                    Direction::Destination => get_dest_port(packet) == port,
+                    Direction::Any => {
+                        get_source_port(packet) == port || get_dest_port(packet) == port
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for PacketFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut primitives = vec![];
+        let mut tokens = s.split_whitespace();
+
+        while let Some(token) = tokens.next() {
+            let mut token = token;
+            let mut negated = false;
+            if token == "and" && !primitives.is_empty() {
+                token = tokens.next().ok_or("missing primitive after and")?;
+            }
+            while token == "not" {
+                negated = !negated;
+                token = tokens.next().ok_or("missing primitive after not")?;
+            }
+
+            let direction = match token {
+                "src" => Direction::Source,
+                "dst" => Direction::Destination,
+                _ => Direction::Any,
+            };
+            if direction != Direction::Any {
+                token = tokens
+                    .next()
+                    .ok_or(format!("missing host or port after {}", token))?;
+            }
+
+            let primitive = match token {
+                "host" => Primitive::Host(
+                    direction,
+                    tokens
+                        .next()
+                        .ok_or("missing address after host")?
+                        .to_owned(),
+                ),
+                "port" => {
+                    let port = tokens.next().ok_or("missing number after port")?;
+                    Primitive::Port(
+                        direction,
+                        port.parse()
+                            .map_err(|_| format!("invalid port: {}", port))?,
+                    )
+                }
+                _ if direction != Direction::Any => {
+                    return Err(format!("expected host or port, found: {}", token))
+                }
+                name if PROTOCOLS.iter().any(|(protocol, _)| *protocol == name) => {
+                    Primitive::Protocol(name.to_owned())
+                }
+                other => return Err(format!("unknown filter primitive: {}", other)),
+            };
+
+            primitives.push((negated, primitive));
+        }
+
+        Ok(PacketFilter { primitives })
+    }
+}
+
+impl fmt::Display for PacketFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let primitives: Vec<String> = self
+            .primitives
+            .iter()
+            .map(|(negated, primitive)| {
+                let primitive = match primitive {
+                    Primitive::Protocol(name) => name.clone(),
+                    Primitive::Host(direction, address) => {
+                        format!("{}host {}", direction_prefix(*direction), address)
+                    }
+                    Primitive::Port(direction, port) => {
+                        format!("{}port {}", direction_prefix(*direction), port)
+                    }
+                };
+                match negated {
+                    true => format!("not {}", primitive),
+                    false => primitive,
+                }
+            })
+            .collect();
+
+        write!(f, "{}", primitives.join(" and "))
+    }
+}
+
+fn direction_prefix(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Source => "src ",
+        Direction::Destination => "dst ",
+        Direction::Any => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PacketFilter;
+    use crate::serializable_packet::ParsedPacket;
+    use crate::test_util::{self, CLIENT, SERVER};
+
+    #[test]
+    fn parse_filter_expression() {
+        let filter: PacketFilter = "udp and not src port 53 and dst host 11.11.11.11"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            filter.to_string(),
+            "udp and not src port 53 and dst host 11.11.11.11"
+        );
+
+        assert!("port http".parse::<PacketFilter>().is_err());
+        assert!("src tcp".parse::<PacketFilter>().is_err());
+        assert!("gopher".parse::<PacketFilter>().is_err());
+    }
+
+    #[test]
+    fn filter_matches_packet() {
+        let packet = build_test_udp_packet();
+
+        let matches = |filter: &str| filter.parse::<PacketFilter>().unwrap().matches(&packet);
+        assert!(matches(""));
+        assert!(matches("udp"));
+        assert!(matches("ip and port 4444"));
+        assert!(matches("src host 10.10.10.10 and dst port 5555"));
+        assert!(matches("host 0b:0b:0b:0b:0b:0b"));
+        assert!(!matches("tcp"));
+        assert!(!matches("udp and not port 5555"));
+        assert!(!matches("dst host 10.10.10.10"));
+    }
+
+    ///////////////////// Utils
+
+    fn build_test_udp_packet() -> ParsedPacket {
+        test_util::build_test_udp_packet((CLIENT, 4444), (SERVER, 5555), &[])
+    }
+}
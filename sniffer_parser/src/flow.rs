@@ -0,0 +1,345 @@
+//! Flow tracking
+//!
+//! Packets are grouped in bidirectional flows identified by their transport protocol and
+//! endpoints. For IPv6, a non-zero flow label (RFC 6437) is also part of the flow identifier, so
+//! that packets whose ports can't be read (e.g. behind extension headers) are still grouped. As
+//! each endpoint chooses the label of its own direction, a flow is identified by the label of its
+//! first packet, and the first label seen in the other direction is joined to the oldest flow
+//! between the same endpoints not answered yet. The loss of the TCP and RTP flows is estimated
+//! from their sequence gaps
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::time::SystemTime;
+
+use crate::loss::{LossEstimator, LossStats};
+use crate::serializable_packet::util::{get_flow_label, get_ip_addresses, get_ports};
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+const IPV6_HEADER_LENGTH: usize = 40;
+
+/// Identifier of a bidirectional flow, the lower endpoint first
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FlowKey {
+    pub protocol: String,
+    pub lower: (IpAddr, u16),
+    pub upper: (IpAddr, u16),
+    /// Non-zero flow label of the first packet of an IPv6 flow
+    pub flow_label: Option<u32>,
+}
+
+impl FlowKey {
+    /// Build the flow identifier of an IP packet, `None` for non-IP packets
+    pub fn from_packet(packet: &ParsedPacket) -> Option<FlowKey> {
+        let protocol = match packet.get_network_layer_packet() {
+            Some(SerializablePacket::Ipv4Packet(ipv4_packet)) => {
+                ipv4_packet.next_level_protocol.clone()
+            }
+            Some(SerializablePacket::Ipv6Packet(ipv6_packet)) => ipv6_packet.next_header.clone(),
+            _ => return None,
+        };
+
+        let (source, dest) = endpoints(packet)?;
+        let (lower, upper) = match source <= dest {
+            true => (source, dest),
+            false => (dest, source),
+        };
+
+        Some(FlowKey {
+            protocol,
+            lower,
+            upper,
+            flow_label: get_flow_label(packet).filter(|label| *label != 0),
+        })
+    }
+}
+
+/// Get the source and destination addresses and ports of a packet, the ports defaulting to 0
+fn endpoints(packet: &ParsedPacket) -> Option<((IpAddr, u16), (IpAddr, u16))> {
+    let (source, dest) = get_ip_addresses(packet)?;
+    let (source_port, dest_port) = get_ports(packet).unwrap_or((0, 0));
+
+    Some(((source, source_port), (dest, dest_port)))
+}
+
+impl fmt::Display for FlowKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} <> {}",
+            self.protocol,
+            SocketAddr::from(self.lower),
+            SocketAddr::from(self.upper)
+        )?;
+
+        if let Some(flow_label) = self.flow_label {
+            write!(f, " (flow label {:#07x})", flow_label)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Packet and byte counts of a flow (IP header and payload), with the estimated loss of the TCP
+/// and RTP flows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowStats {
+    pub packets: usize,
+    pub bytes: usize,
+    pub first_seen: Option<SystemTime>,
+    pub last_seen: Option<SystemTime>,
+    pub loss: Option<LossStats>,
+}
+
+impl fmt::Display for FlowStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} packets, {} bytes", self.packets, self.bytes)?;
+
+        if let Some(loss) = self.loss {
+            write!(f, ", {:.1}% lost", loss.percentage())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Table of the flows seen in a capture
+#[derive(Debug, Default)]
+pub struct FlowTable {
+    flows: HashMap<FlowKey, FlowStats>,
+    /// Identifiers of the IPv6 flows, by the flow label of their other direction
+    reverse_labels: HashMap<FlowKey, FlowKey>,
+    /// Identifiers of the labelled IPv6 flows whose other direction wasn't seen yet, by their
+    /// identifier without flow label and the endpoint expected to answer
+    unanswered: HashMap<(FlowKey, (IpAddr, u16)), FlowKey>,
+    loss: LossEstimator,
+}
+
+impl FlowTable {
+    pub fn new() -> Self {
+        FlowTable {
+            flows: HashMap::new(),
+            reverse_labels: HashMap::new(),
+            unanswered: HashMap::new(),
+            loss: LossEstimator::default(),
+        }
+    }
+
+    /// Account a packet in its flow, returning the flow identifier (`None` for non-IP packets)
+    pub fn update(&mut self, packet: &ParsedPacket) -> Option<FlowKey> {
+        let (_, dest) = endpoints(packet)?;
+        let key = self.flow_key(FlowKey::from_packet(packet)?, dest);
+        let bytes = match packet.get_network_layer_packet() {
+            Some(SerializablePacket::Ipv4Packet(ipv4_packet)) => ipv4_packet.total_length as usize,
+            Some(SerializablePacket::Ipv6Packet(ipv6_packet)) => {
+                IPV6_HEADER_LENGTH + ipv6_packet.payload_length as usize
+            }
+            _ => 0,
+        };
+
+        let stats = self.flows.entry(key.clone()).or_insert(FlowStats {
+            packets: 0,
+            bytes: 0,
+            first_seen: packet.get_timestamp(),
+            last_seen: None,
+            loss: None,
+        });
+        stats.packets += 1;
+        stats.bytes += bytes;
+        stats.last_seen = packet.get_timestamp();
+        self.loss.update(&key, packet);
+        stats.loss = self.loss.loss(&key).or(stats.loss);
+
+        Some(key)
+    }
+
+    /// Get the identifier of the flow of a packet sent to `dest`, joining the first flow label of
+    /// the other direction of an IPv6 flow to it
+    fn flow_key(&mut self, key: FlowKey, dest: (IpAddr, u16)) -> FlowKey {
+        if key.flow_label.is_none() || self.flows.contains_key(&key) {
+            return key;
+        }
+        if let Some(flow_key) = self.reverse_labels.get(&key) {
+            return flow_key.clone();
+        }
+
+        let unlabelled = FlowKey {
+            flow_label: None,
+            ..key.clone()
+        };
+        let source = match dest == key.lower {
+            true => key.upper,
+            false => key.lower,
+        };
+        match self.unanswered.remove(&(unlabelled.clone(), source)) {
+            Some(flow_key) => {
+                self.reverse_labels.insert(key, flow_key.clone());
+                flow_key
+            }
+            None => {
+                self.unanswered
+                    .entry((unlabelled, dest))
+                    .or_insert_with(|| key.clone());
+                key
+            }
+        }
+    }
+
+    /// Get the statistics of a flow
+    pub fn get(&self, key: &FlowKey) -> Option<&FlowStats> {
+        self.flows.get(key)
+    }
+
+    /// Get the estimated loss of a TCP or RTP flow
+    pub fn loss(&self, key: &FlowKey) -> Option<LossStats> {
+        self.flows.get(key).and_then(|stats| stats.loss)
+    }
+
+    /// Get the number of flows
+    pub fn len(&self) -> usize {
+        self.flows.len()
+    }
+
+    /// Check if no flow was seen
+    pub fn is_empty(&self) -> bool {
+        self.flows.is_empty()
+    }
+
+    /// Iterate over the flows, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&FlowKey, &FlowStats)> {
+        self.flows.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+
+    use pnet::packet::ethernet::EtherTypes;
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::packet::tcp::TcpFlags;
+
+    use super::FlowTable;
+    use crate::serializable_packet::ParsedPacket;
+    use crate::test_util::{self, build_test_ethernet_frame, parse_test_frame, CLIENT, SERVER};
+
+    #[test]
+    fn ipv6_flow_label_grouping() {
+        let mut flow_table = FlowTable::new();
+
+        let first = flow_table
+            .update(&build_test_ipv6_packet(0, 0x12345, false))
+            .unwrap();
+        let second = flow_table
+            .update(&build_test_ipv6_packet(1, 0x12345, false))
+            .unwrap();
+        let other = flow_table
+            .update(&build_test_ipv6_packet(2, 0x54321, false))
+            .unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first.flow_label, Some(0x12345));
+        assert_ne!(first, other);
+        assert_eq!(flow_table.len(), 2);
+        assert_eq!(flow_table.get(&first).unwrap().packets, 2);
+        assert_eq!(
+            first.to_string(),
+            "Hopopt (0) [a:a:a:a:a:a:a:0]:0 <> [b:b:b:b:b:b:b:0]:0 (flow label 0x12345)"
+        );
+    }
+
+    #[test]
+    fn zero_flow_label_ignored() {
+        let mut flow_table = FlowTable::new();
+
+        let key = flow_table
+            .update(&build_test_ipv6_packet(0, 0, false))
+            .unwrap();
+        assert_eq!(key.flow_label, None);
+    }
+
+    #[test]
+    fn ipv6_flow_label_per_direction() {
+        let mut flow_table = FlowTable::new();
+
+        let request = flow_table
+            .update(&build_test_ipv6_packet(0, 0x12345, false))
+            .unwrap();
+        let reply = flow_table
+            .update(&build_test_ipv6_packet(1, 0x54321, true))
+            .unwrap();
+        let next_request = flow_table
+            .update(&build_test_ipv6_packet(2, 0x12345, false))
+            .unwrap();
+        let next_reply = flow_table
+            .update(&build_test_ipv6_packet(3, 0x54321, true))
+            .unwrap();
+
+        assert_eq!(request, reply);
+        assert_eq!(request, next_request);
+        assert_eq!(request, next_reply);
+        assert_eq!(request.flow_label, Some(0x12345));
+        assert_eq!(flow_table.len(), 1);
+        assert_eq!(flow_table.get(&request).unwrap().packets, 4);
+    }
+
+    #[test]
+    fn tcp_flow_loss() {
+        let mut flow_table = FlowTable::new();
+
+        let key = flow_table
+            .update(&build_test_tcp_packet(false, 1000, 100))
+            .unwrap();
+        flow_table.update(&build_test_tcp_packet(true, 5000, 10));
+        // The segment from 1100 to 1200 is never captured
+        for sequence in (1200..8300).step_by(100) {
+            flow_table.update(&build_test_tcp_packet(false, sequence, 100));
+        }
+
+        assert_eq!(flow_table.len(), 1);
+        let stats = flow_table.get(&key).unwrap();
+        assert_eq!(
+            stats.loss.map(|loss| (loss.expected, loss.lost)),
+            Some((7310, 100))
+        );
+        assert_eq!(stats.to_string(), "73 packets, 10130 bytes, 1.4% lost");
+    }
+
+    ///////////////////// Utils
+
+    /// Build a segment from 10.10.10.10:4444 to 11.11.11.11:5555, or back when reversed
+    fn build_test_tcp_packet(reversed: bool, sequence: u32, payload_length: usize) -> ParsedPacket {
+        let (source, destination) = match reversed {
+            true => ((SERVER, 5555), (CLIENT, 4444)),
+            false => ((CLIENT, 4444), (SERVER, 5555)),
+        };
+        let flags = TcpFlags::ACK | TcpFlags::PSH;
+        let payload = vec![0u8; payload_length];
+        test_util::build_test_tcp_packet(source, destination, flags, sequence, &payload)
+    }
+
+    /// Build an IPv6 packet whose hop-by-hop extension header hides the transport header, sent
+    /// back by the destination host when reversed
+    fn build_test_ipv6_packet(id: usize, flow_label: u32, reversed: bool) -> ParsedPacket {
+        let (source, destination) = match reversed {
+            true => (
+                Ipv6Addr::new(11, 11, 11, 11, 11, 11, 11, 0),
+                Ipv6Addr::new(10, 10, 10, 10, 10, 10, 10, 0),
+            ),
+            false => (
+                Ipv6Addr::new(10, 10, 10, 10, 10, 10, 10, 0),
+                Ipv6Addr::new(11, 11, 11, 11, 11, 11, 11, 0),
+            ),
+        };
+
+        let ip_packet = test_util::build_test_ipv6_packet(
+            source,
+            destination,
+            flow_label,
+            IpNextHeaderProtocols::Hopopt,
+            &[0x06, 0x00, 0x01, 0x04, 0x00, 0x00, 0x00, 0x00],
+        );
+        parse_test_frame(&build_test_ethernet_frame(EtherTypes::Ipv6, &ip_packet), id)
+    }
+}
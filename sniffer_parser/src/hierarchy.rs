@@ -0,0 +1,207 @@
+//! Protocol hierarchy statistics
+//!
+//! Parsed packets are aggregated in a tree of their protocol stacks, like Wireshark's Protocol
+//! Hierarchy: every node counts the packets whose stack starts with the path leading to it.
+//! Bytes are counted from the headers: the Ethernet header plus the length of the ARP or IP
+//! packet, so the Ethernet padding is not included
+
+use std::fmt;
+
+use crate::serializable_packet::util::get_frame_length;
+use crate::serializable_packet::ParsedPacket;
+
+/// Protocol of the hierarchy, with the packets carrying it at this position of their stack
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HierarchyNode {
+    pub protocol: &'static str,
+    pub packets: usize,
+    pub bytes: usize,
+    /// Protocols encapsulated in this one, by order of appearance
+    pub children: Vec<HierarchyNode>,
+}
+
+/// Tree of the protocol stacks seen in a capture
+#[derive(Debug, Default)]
+pub struct ProtocolHierarchy {
+    packets: usize,
+    bytes: usize,
+    roots: Vec<HierarchyNode>,
+}
+
+impl ProtocolHierarchy {
+    pub fn new() -> Self {
+        ProtocolHierarchy {
+            packets: 0,
+            bytes: 0,
+            roots: vec![],
+        }
+    }
+
+    /// Account a packet in every node of its protocol stack
+    pub fn add(&mut self, packet: &ParsedPacket) {
+        let bytes = get_frame_length(packet);
+        self.packets += 1;
+        self.bytes += bytes;
+
+        let mut nodes = &mut self.roots;
+        for protocol in packet.protocol_stack() {
+            let index = match nodes.iter().position(|node| node.protocol == protocol) {
+                Some(index) => index,
+                None => {
+                    nodes.push(HierarchyNode {
+                        protocol,
+                        packets: 0,
+                        bytes: 0,
+                        children: vec![],
+                    });
+                    nodes.len() - 1
+                }
+            };
+
+            let node = &mut nodes[index];
+            node.packets += 1;
+            node.bytes += bytes;
+            nodes = &mut node.children;
+        }
+    }
+
+    /// Get the number of packets accounted
+    pub fn packets(&self) -> usize {
+        self.packets
+    }
+
+    /// Get the number of bytes accounted
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Get the outermost protocols, by order of appearance
+    pub fn roots(&self) -> &[HierarchyNode] {
+        &self.roots
+    }
+
+    fn fmt_nodes(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        nodes: &[HierarchyNode],
+        depth: usize,
+    ) -> fmt::Result {
+        for node in nodes {
+            writeln!(
+                f,
+                "{:indent$}{:<width$} packets: {:>8} ({:>5.1}%)  bytes: {:>10} ({:>5.1}%)",
+                "",
+                node.protocol,
+                node.packets,
+                percentage(node.packets, self.packets),
+                node.bytes,
+                percentage(node.bytes, self.bytes),
+                indent = 2 * depth,
+                width = 12 - 2 * depth.min(4),
+            )?;
+            self.fmt_nodes(f, &node.children, depth + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for ProtocolHierarchy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Protocol Hierarchy: {} packets, {} bytes",
+            self.packets, self.bytes
+        )?;
+        self.fmt_nodes(f, &self.roots, 1)
+    }
+}
+
+fn percentage(part: usize, total: usize) -> f64 {
+    match total {
+        0 => 0.0,
+        total => 100.0 * part as f64 / total as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pnet::packet::arp::ArpOperations;
+    use pnet::packet::ethernet::EtherTypes;
+    use pnet::util::MacAddr;
+
+    use super::ProtocolHierarchy;
+    use crate::serializable_packet::ParsedPacket;
+    use crate::test_util::{self, build_test_ethernet_frame, parse_test_frame, CLIENT, SERVER};
+
+    #[test]
+    fn hierarchy_of_packet_mix() {
+        let mut hierarchy = ProtocolHierarchy::new();
+        hierarchy.add(&build_test_udp_packet(53, 30));
+        hierarchy.add(&build_test_udp_packet(53, 30));
+        hierarchy.add(&build_test_udp_packet(9999, 10));
+        hierarchy.add(&build_test_arp_packet());
+
+        assert_eq!(hierarchy.packets(), 4);
+        assert_eq!(hierarchy.bytes(), 3 * (14 + 28) + 2 * 30 + 10 + 14 + 28);
+
+        let roots = hierarchy.roots();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].protocol, "Ethernet");
+        assert_eq!(roots[0].packets, 4);
+
+        let network = &roots[0].children;
+        assert_eq!(
+            network
+                .iter()
+                .map(|n| (n.protocol, n.packets))
+                .collect::<Vec<_>>(),
+            vec![("IPv4", 3), ("ARP", 1)]
+        );
+        assert_eq!(network[1].bytes, 14 + 28);
+        assert!(network[1].children.is_empty());
+
+        let udp = &network[0].children[0];
+        assert_eq!((udp.protocol, udp.packets), ("UDP", 3));
+        assert_eq!(udp.children.len(), 1);
+        assert_eq!(udp.children[0].protocol, "DNS");
+        assert_eq!(udp.children[0].packets, 2);
+        assert_eq!(udp.children[0].bytes, 2 * (14 + 28 + 30));
+    }
+
+    #[test]
+    fn hierarchy_display() {
+        let mut hierarchy = ProtocolHierarchy::new();
+        hierarchy.add(&build_test_arp_packet());
+
+        assert_eq!(
+            hierarchy.to_string(),
+            "Protocol Hierarchy: 1 packets, 42 bytes\n  \
+            Ethernet   packets:        1 (100.0%)  bytes:         42 (100.0%)\n    \
+            ARP      packets:        1 (100.0%)  bytes:         42 (100.0%)\n"
+        );
+    }
+
+    ///////////////////// Utils
+
+    const DNS_QUERY: &[u8] = &[
+        0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x07, b'e', b'x',
+        b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00, 0x00, 0x01, 0x00, 0x01,
+    ];
+
+    /// Build a UDP packet whose payload is a DNS query (truncated to `length` bytes) when the
+    /// destination port is 53
+    fn build_test_udp_packet(dest_port: u16, length: usize) -> ParsedPacket {
+        let payload = &[DNS_QUERY, &[0u8; 30]].concat()[..length];
+        test_util::build_test_udp_packet((CLIENT, 4444), (SERVER, dest_port), payload)
+    }
+
+    fn build_test_arp_packet() -> ParsedPacket {
+        let arp_packet = test_util::build_test_arp_packet(
+            ArpOperations::Request,
+            (MacAddr::new(10, 10, 10, 10, 10, 10), CLIENT),
+            (MacAddr::zero(), SERVER),
+        );
+        parse_test_frame(&build_test_ethernet_frame(EtherTypes::Arp, &arp_packet), 0)
+    }
+}
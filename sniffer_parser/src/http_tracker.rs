@@ -0,0 +1,171 @@
+//! HTTP response time tracking
+//!
+//! Requests are recorded with their capture timestamp, queued by client and server endpoints; as
+//! HTTP/1.1 answers the requests of a connection in order, each response gets the time elapsed
+//! since the oldest pending request of its connection. Requests left unanswered for longer than
+//! the timeout are forgotten
+
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+use crate::serializable_packet::util::{get_dest_endpoint, get_source_endpoint};
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+/// Client and server endpoints of an HTTP connection
+pub type HttpConnectionKey = ((IpAddr, u16), (IpAddr, u16));
+
+/// Pending HTTP requests, fed with every parsed packet in capture order
+#[derive(Debug)]
+pub struct HttpTracker {
+    timeout: Duration,
+    requests: HashMap<HttpConnectionKey, VecDeque<SystemTime>>,
+}
+
+impl HttpTracker {
+    /// Build a tracker forgetting the requests unanswered after `timeout`
+    pub fn new(timeout: Duration) -> Self {
+        HttpTracker {
+            timeout,
+            requests: HashMap::new(),
+        }
+    }
+
+    /// Record a request, or attach its response time to a response; packets without timestamp
+    /// are ignored
+    pub fn update(&mut self, packet: &mut ParsedPacket) {
+        let timestamp = match packet.get_timestamp() {
+            Some(timestamp) => timestamp,
+            None => return,
+        };
+        self.expire(timestamp);
+
+        let (source, dest) = match (get_source_endpoint(packet), get_dest_endpoint(packet)) {
+            (Some(source), Some(dest)) => (source, dest),
+            _ => return,
+        };
+
+        let http_packet = packet.layers_mut().find(|layer| {
+            matches!(
+                layer,
+                SerializablePacket::HttpRequestPacket(_)
+                    | SerializablePacket::HttpResponsePacket(_)
+            )
+        });
+        match http_packet {
+            Some(SerializablePacket::HttpRequestPacket(_)) => {
+                self.requests
+                    .entry((source, dest))
+                    .or_default()
+                    .push_back(timestamp);
+            }
+            Some(SerializablePacket::HttpResponsePacket(http_packet)) => {
+                let request_timestamp = self
+                    .requests
+                    .get_mut(&(dest, source))
+                    .and_then(|requests| requests.pop_front());
+                if let Some(request_timestamp) = request_timestamp {
+                    let response_time = timestamp
+                        .duration_since(request_timestamp)
+                        .unwrap_or_default();
+                    http_packet.response_time_ms = Some(response_time.as_secs_f64() * 1000.0);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Forget the requests older than the timeout at the given time
+    pub fn expire(&mut self, now: SystemTime) {
+        let timeout = self.timeout;
+        self.requests.retain(|_, requests| {
+            requests.retain(|request_timestamp| {
+                now.duration_since(*request_timestamp)
+                    .map_or(true, |elapsed| elapsed <= timeout)
+            });
+            !requests.is_empty()
+        });
+    }
+
+    /// Get the number of requests waiting for their response
+    pub fn pending(&self) -> usize {
+        self.requests.values().map(VecDeque::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use pnet::packet::tcp::TcpFlags;
+
+    use super::HttpTracker;
+    use crate::serializable_packet::application::HttpStatusClass;
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+    use crate::test_util::{build_test_tcp_packet, timestamped, CLIENT, SERVER};
+
+    const HTTP_REQUEST: &[u8] = b"GET /status HTTP/1.1\r\nHost: example.com\r\n\r\n";
+    const HTTP_RESPONSE: &[u8] = b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n";
+
+    #[test]
+    fn request_response_time() {
+        let mut tracker = HttpTracker::new(Duration::from_secs(30));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        let mut request = build_test_http_packet(false, HTTP_REQUEST, start);
+        tracker.update(&mut request);
+        assert_eq!(tracker.pending(), 1);
+
+        let mut response =
+            build_test_http_packet(true, HTTP_RESPONSE, start + Duration::from_millis(120));
+        tracker.update(&mut response);
+        assert_eq!(tracker.pending(), 0);
+
+        match response.get_application_layer_packet() {
+            Some(SerializablePacket::HttpResponsePacket(http_packet)) => {
+                assert_eq!(http_packet.code, 503);
+                assert_eq!(http_packet.status_class, Some(HttpStatusClass::ServerError));
+                let response_time_ms = http_packet.response_time_ms.unwrap();
+                assert!((response_time_ms - 120.0).abs() < 1e-6);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn unanswered_request_expires() {
+        let mut tracker = HttpTracker::new(Duration::from_secs(30));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        tracker.update(&mut build_test_http_packet(false, HTTP_REQUEST, start));
+        let mut response =
+            build_test_http_packet(true, HTTP_RESPONSE, start + Duration::from_secs(31));
+        tracker.update(&mut response);
+        assert_eq!(tracker.pending(), 0);
+
+        match response.get_application_layer_packet() {
+            Some(SerializablePacket::HttpResponsePacket(http_packet)) => {
+                assert_eq!(http_packet.response_time_ms, None)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    ///////////////////// Utils
+
+    /// Build a segment from the client 10.10.10.10:4444 to the server 11.11.11.11:80, or in the
+    /// other direction for a response
+    fn build_test_http_packet(
+        is_response: bool,
+        payload: &[u8],
+        timestamp: SystemTime,
+    ) -> ParsedPacket {
+        let (source, destination) = match is_response {
+            true => ((SERVER, 80), (CLIENT, 4444)),
+            false => ((CLIENT, 4444), (SERVER, 80)),
+        };
+        let flags = TcpFlags::ACK | TcpFlags::PSH;
+        let packet = build_test_tcp_packet(source, destination, flags, 0, payload);
+        timestamped(packet, timestamp)
+    }
+}
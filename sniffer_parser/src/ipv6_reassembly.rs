@@ -0,0 +1,495 @@
+//! IPv6 fragment reassembly
+//!
+//! An IPv6 datagram too large for the path is split by its source into fragments, each carrying a
+//! Fragment extension header with the identification of the datagram, the offset of the fragment
+//! in 8-byte units and the M flag, unset on the last fragment. Fragments are buffered per source,
+//! destination and identification until the datagram is complete, then the datagram is rebuilt as
+//! an unfragmented IPv6 packet for the transport-layer parsing: the base header of the first
+//! fragment directly followed by the reassembled payload, the extension headers before the
+//! Fragment header being dropped. Fragments received again identical are ignored; datagrams
+//! growing beyond the size bound, with overlapping fragments or not completed before the timeout
+//! are dropped
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::net::Ipv6Addr;
+use std::time::{Duration, SystemTime};
+
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::ipv6::Ipv6Packet;
+use pnet::packet::Packet;
+
+const IPV6_HEADER_LENGTH: usize = 40;
+const FRAGMENT_HEADER_LENGTH: usize = 8;
+/// Largest fragmentable part of a datagram, bounded by the payload length of its IPv6 header
+pub const MAX_DATAGRAM_SIZE: usize = u16::MAX as usize;
+/// Time after which a datagram not completed is dropped (RFC 8200)
+pub const DEFAULT_REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Identifier of a fragmented IPv6 datagram, shared by its fragments
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ipv6FragmentKey {
+    pub source: Ipv6Addr,
+    pub destination: Ipv6Addr,
+    pub identification: u32,
+}
+
+/// Fields of the Fragment extension header of a packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FragmentHeader {
+    /// Offset of the Fragment header in the packet, after the unfragmentable headers
+    header_offset: usize,
+    next_header: u8,
+    /// Offset of the fragment in the datagram, in bytes
+    fragment_offset: usize,
+    more_fragments: bool,
+    identification: u32,
+}
+
+/// Fragments received of a datagram
+#[derive(Debug)]
+struct PartialDatagram {
+    first_timestamp: SystemTime,
+    /// Base header of the first fragment, with the next header of the reassembled payload
+    base_header: Option<(Vec<u8>, u8)>,
+    fragments: Vec<(usize, Vec<u8>)>,
+    /// Length of the fragmentable part, known once the last fragment is received
+    total_length: Option<usize>,
+}
+
+impl PartialDatagram {
+    fn new(first_timestamp: SystemTime) -> Self {
+        PartialDatagram {
+            first_timestamp,
+            base_header: None,
+            fragments: vec![],
+            total_length: None,
+        }
+    }
+
+    /// Check if a fragment was already received, identical
+    fn contains(&self, offset: usize, data: &[u8]) -> bool {
+        self.fragments
+            .iter()
+            .any(|(start, fragment)| *start == offset && fragment == data)
+    }
+
+    /// Add a fragment, `false` if it overlaps a fragment or goes beyond the last one
+    fn insert(&mut self, offset: usize, data: &[u8], more_fragments: bool) -> bool {
+        let end = offset + data.len();
+        if self
+            .fragments
+            .iter()
+            .any(|(start, fragment)| offset < start + fragment.len() && *start < end)
+        {
+            return false;
+        }
+        match (self.total_length, more_fragments) {
+            (Some(total_length), _) if end > total_length => return false,
+            (Some(_), false) => return false,
+            (None, false) => {
+                if self
+                    .fragments
+                    .iter()
+                    .any(|(start, fragment)| start + fragment.len() > end)
+                {
+                    return false;
+                }
+                self.total_length = Some(end);
+            }
+            _ => (),
+        }
+
+        self.fragments.push((offset, data.to_vec()));
+        true
+    }
+
+    fn buffered_length(&self) -> usize {
+        self.fragments
+            .iter()
+            .map(|(_, fragment)| fragment.len())
+            .sum()
+    }
+
+    /// Rebuild the unfragmented packet once every fragment is received
+    fn reassemble(&mut self) -> Option<Vec<u8>> {
+        let total_length = self.total_length?;
+        if self.base_header.is_none() || self.buffered_length() != total_length {
+            return None;
+        }
+
+        self.fragments.sort_by_key(|(offset, _)| *offset);
+        let (mut packet, next_header) = self.base_header.take()?;
+        packet[6] = next_header;
+        for (_, fragment) in &self.fragments {
+            packet.extend_from_slice(fragment);
+        }
+        let payload_length = (packet.len() - IPV6_HEADER_LENGTH) as u16;
+        packet[4..6].copy_from_slice(&payload_length.to_be_bytes());
+        Some(packet)
+    }
+}
+
+/// Buffer of the fragmented IPv6 datagrams, fed with every IPv6 packet in capture order
+#[derive(Debug)]
+pub struct Ipv6Reassembler {
+    max_size: usize,
+    timeout: Duration,
+    datagrams: HashMap<Ipv6FragmentKey, PartialDatagram>,
+    /// Datagrams in the order of their first fragment, with its timestamp, the oldest first
+    deadlines: VecDeque<(SystemTime, Ipv6FragmentKey)>,
+    dropped: usize,
+}
+
+impl Ipv6Reassembler {
+    /// Build a reassembler dropping the datagrams longer than `max_size` bytes of fragmentable
+    /// part, at most `MAX_DATAGRAM_SIZE`, or not completed after `timeout`
+    pub fn new(max_size: usize, timeout: Duration) -> Self {
+        Ipv6Reassembler {
+            max_size: max_size.min(MAX_DATAGRAM_SIZE),
+            timeout,
+            datagrams: HashMap::new(),
+            deadlines: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Add a fragment of a datagram, getting the reassembled packet once it is complete; packets
+    /// without Fragment header are ignored
+    pub fn add(&mut self, packet: &[u8], timestamp: SystemTime) -> Option<Vec<u8>> {
+        self.expire(timestamp);
+
+        let ipv6_packet = Ipv6Packet::new(packet)?;
+        let fragment_header = parse_fragment_header(&ipv6_packet)?;
+        let data_start = fragment_header.header_offset + FRAGMENT_HEADER_LENGTH;
+        let data_end =
+            (IPV6_HEADER_LENGTH + ipv6_packet.get_payload_length() as usize).min(packet.len());
+        let data = packet.get(data_start..data_end)?;
+        if fragment_header.more_fragments && !data.len().is_multiple_of(8) {
+            return None;
+        }
+
+        let key = Ipv6FragmentKey {
+            source: ipv6_packet.get_source(),
+            destination: ipv6_packet.get_destination(),
+            identification: fragment_header.identification,
+        };
+        let datagram = match self.datagrams.entry(key.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                self.deadlines.push_back((timestamp, key.clone()));
+                entry.insert(PartialDatagram::new(timestamp))
+            }
+        };
+        if datagram.contains(fragment_header.fragment_offset, data) {
+            return None;
+        }
+
+        let fits = fragment_header.fragment_offset + data.len() <= self.max_size
+            && datagram.buffered_length() + data.len() <= self.max_size;
+        if !fits
+            || !datagram.insert(
+                fragment_header.fragment_offset,
+                data,
+                fragment_header.more_fragments,
+            )
+        {
+            self.datagrams.remove(&key);
+            self.dropped += 1;
+            return None;
+        }
+        if fragment_header.fragment_offset == 0 {
+            datagram.base_header = Some((
+                packet[..IPV6_HEADER_LENGTH].to_vec(),
+                fragment_header.next_header,
+            ));
+        }
+
+        let reassembled = datagram.reassemble()?;
+        self.datagrams.remove(&key);
+        Some(reassembled)
+    }
+
+    /// Drop the datagrams whose first fragment is older than the timeout at the given time
+    pub fn expire(&mut self, now: SystemTime) {
+        while let Some((first_timestamp, key)) = self.deadlines.front() {
+            if now
+                .duration_since(*first_timestamp)
+                .map_or(true, |elapsed| elapsed <= self.timeout)
+            {
+                break;
+            }
+            // The datagram may have been completed or dropped, and started again since
+            if self
+                .datagrams
+                .get(key)
+                .is_some_and(|datagram| datagram.first_timestamp == *first_timestamp)
+            {
+                self.datagrams.remove(key);
+                self.dropped += 1;
+            }
+            self.deadlines.pop_front();
+        }
+    }
+
+    /// Get the number of datagrams waiting for fragments
+    pub fn pending(&self) -> usize {
+        self.datagrams.len()
+    }
+
+    /// Get the number of datagrams dropped before their reassembly
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+/// Find the Fragment header after the Hop-by-Hop, Routing and Destination Options headers
+fn parse_fragment_header(ipv6_packet: &Ipv6Packet) -> Option<FragmentHeader> {
+    let packet = ipv6_packet.packet();
+    let mut next_header = ipv6_packet.get_next_header();
+    let mut offset = IPV6_HEADER_LENGTH;
+
+    loop {
+        match next_header {
+            IpNextHeaderProtocols::Ipv6Frag => {
+                let header = packet.get(offset..offset + FRAGMENT_HEADER_LENGTH)?;
+                let offset_and_flags = u16::from_be_bytes([header[2], header[3]]);
+                return Some(FragmentHeader {
+                    header_offset: offset,
+                    next_header: header[0],
+                    fragment_offset: (offset_and_flags >> 3) as usize * 8,
+                    more_fragments: offset_and_flags & 1 == 1,
+                    identification: u32::from_be_bytes([
+                        header[4], header[5], header[6], header[7],
+                    ]),
+                });
+            }
+            IpNextHeaderProtocols::Hopopt
+            | IpNextHeaderProtocols::Ipv6Route
+            | IpNextHeaderProtocols::Ipv6Opts => {
+                let header = packet.get(offset..offset + 2)?;
+                next_header = IpNextHeaderProtocol(header[0]);
+                offset += (header[1] as usize + 1) * 8;
+            }
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use pnet::packet::ethernet::EtherTypes;
+    use pnet::packet::ip::IpNextHeaderProtocols;
+
+    use super::{Ipv6Reassembler, DEFAULT_REASSEMBLY_TIMEOUT};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+    use crate::test_util::{self, build_test_ethernet_frame, build_test_ipv6_packet};
+    use crate::{
+        handle_ipv6_packet, parse_pcap_record, set_ipv6_reassembly, LinkTypes, PcapRecord,
+    };
+
+    #[test]
+    fn fragmented_udp_datagram() {
+        let mut reassembler = Ipv6Reassembler::new(65535, Duration::from_secs(60));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let datagram = build_test_udp_datagram(1000);
+
+        // The last fragment is received first
+        assert_eq!(
+            reassembler.add(&build_test_fragment(&datagram[504..], 504, false), start),
+            None
+        );
+        assert_eq!(reassembler.pending(), 1);
+        let reassembled = reassembler
+            .add(
+                &build_test_fragment(&datagram[..504], 0, true),
+                start + Duration::from_millis(1),
+            )
+            .unwrap();
+        assert_eq!((reassembler.pending(), reassembler.dropped()), (0, 0));
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ipv6_packet(&reassembled, &mut parsed_packet);
+        match parsed_packet.get_network_layer_packet() {
+            Some(SerializablePacket::Ipv6Packet(ipv6_packet)) => {
+                assert_eq!(ipv6_packet.payload_length, 1008);
+                assert_eq!(ipv6_packet.next_header, "Udp (17)");
+            }
+            _ => unreachable!(),
+        }
+        match parsed_packet.get_transport_layer_packet() {
+            Some(SerializablePacket::UdpPacket(udp_packet)) => {
+                assert_eq!((udp_packet.source, udp_packet.destination), (4444, 9999));
+                assert_eq!(udp_packet.length, 1008);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn expired_and_overlapping_fragments() {
+        let mut reassembler = Ipv6Reassembler::new(65535, Duration::from_secs(60));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let datagram = build_test_udp_datagram(1000);
+
+        reassembler.add(&build_test_fragment(&datagram[..504], 0, true), start);
+        let late = start + Duration::from_secs(61);
+        assert_eq!(
+            reassembler.add(&build_test_fragment(&datagram[504..], 504, false), late),
+            None
+        );
+        assert_eq!((reassembler.pending(), reassembler.dropped()), (1, 1));
+
+        assert_eq!(
+            reassembler.add(&build_test_fragment(&datagram[496..], 496, true), late),
+            None
+        );
+        assert_eq!((reassembler.pending(), reassembler.dropped()), (0, 2));
+    }
+
+    #[test]
+    fn oversized_datagram_dropped() {
+        let mut reassembler = Ipv6Reassembler::new(512, Duration::from_secs(60));
+        let datagram = build_test_udp_datagram(1000);
+
+        assert_eq!(
+            reassembler.add(
+                &build_test_fragment(&datagram[504..], 504, false),
+                SystemTime::UNIX_EPOCH
+            ),
+            None
+        );
+        assert_eq!((reassembler.pending(), reassembler.dropped()), (0, 1));
+    }
+
+    #[test]
+    fn duplicate_fragment_ignored() {
+        let mut reassembler = Ipv6Reassembler::new(65535, Duration::from_secs(60));
+        let datagram = build_test_udp_datagram(1000);
+        let first_fragment = build_test_fragment(&datagram[..504], 0, true);
+
+        assert_eq!(reassembler.add(&first_fragment, UNIX_EPOCH), None);
+        assert_eq!(reassembler.add(&first_fragment, UNIX_EPOCH), None);
+        assert!(reassembler
+            .add(
+                &build_test_fragment(&datagram[504..], 504, false),
+                UNIX_EPOCH
+            )
+            .is_some());
+        assert_eq!((reassembler.pending(), reassembler.dropped()), (0, 0));
+    }
+
+    #[test]
+    fn size_bound_capped() {
+        let mut reassembler = Ipv6Reassembler::new(usize::MAX, Duration::from_secs(60));
+        let datagram = build_test_udp_datagram(70_000);
+
+        reassembler.add(
+            &build_test_fragment(&datagram[..40_000], 0, true),
+            UNIX_EPOCH,
+        );
+        assert_eq!(
+            reassembler.add(
+                &build_test_fragment(&datagram[40_000..], 40_000, false),
+                UNIX_EPOCH
+            ),
+            None
+        );
+        assert_eq!((reassembler.pending(), reassembler.dropped()), (0, 1));
+    }
+
+    #[test]
+    fn reassembled_when_parsed() {
+        let datagram = build_test_udp_datagram(1000);
+
+        set_ipv6_reassembly(true);
+        let mut first_fragment = ParsedPacket::new(0);
+        handle_ipv6_packet(
+            &build_test_fragment(&datagram[..504], 0, true),
+            &mut first_fragment,
+        );
+        let mut last_fragment = ParsedPacket::new(1);
+        handle_ipv6_packet(
+            &build_test_fragment(&datagram[504..], 504, false),
+            &mut last_fragment,
+        );
+        set_ipv6_reassembly(false);
+
+        assert!(!matches!(
+            first_fragment.get_transport_layer_packet(),
+            Some(SerializablePacket::UdpPacket(_))
+        ));
+        match last_fragment.get_transport_layer_packet() {
+            Some(SerializablePacket::UdpPacket(udp_packet)) => {
+                assert_eq!((udp_packet.source, udp_packet.destination), (4444, 9999));
+                assert_eq!(udp_packet.length, 1008);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn expired_in_capture_time() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let datagram = build_test_udp_datagram(1000);
+        let record = |fragment: Vec<u8>, timestamp: SystemTime| {
+            let frame = build_test_ethernet_frame(EtherTypes::Ipv6, &fragment);
+            PcapRecord {
+                link_type: LinkTypes::ETHERNET,
+                timestamp,
+                captured_length: frame.len() as u32,
+                original_length: frame.len() as u32,
+                data: frame,
+            }
+        };
+
+        // Parsed right after one another, but captured further apart than the timeout
+        set_ipv6_reassembly(true);
+        parse_pcap_record(
+            &record(build_test_fragment(&datagram[..504], 0, true), start),
+            0,
+        );
+        let last_fragment = parse_pcap_record(
+            &record(
+                build_test_fragment(&datagram[504..], 504, false),
+                start + DEFAULT_REASSEMBLY_TIMEOUT + Duration::from_secs(1),
+            ),
+            1,
+        );
+        set_ipv6_reassembly(false);
+
+        assert!(!matches!(
+            last_fragment.get_transport_layer_packet(),
+            Some(SerializablePacket::UdpPacket(_))
+        ));
+    }
+
+    ///////////////////// Utils
+
+    /// Build a UDP datagram from port 4444 to port 9999 carrying `length` bytes
+    fn build_test_udp_datagram(length: usize) -> Vec<u8> {
+        test_util::build_test_udp_datagram(4444, 9999, &vec![0xab; length])
+    }
+
+    /// Build an IPv6 packet from 2001:db8::1 to 2001:db8::2 carrying a fragment of the UDP
+    /// datagram 0x1234, behind a Destination Options header
+    fn build_test_fragment(fragment: &[u8], offset: u16, more_fragments: bool) -> Vec<u8> {
+        let mut extension_headers = vec![IpNextHeaderProtocols::Ipv6Frag.0, 0, 1, 4, 0, 0, 0, 0];
+        extension_headers.push(IpNextHeaderProtocols::Udp.0);
+        extension_headers.push(0);
+        // The offset in 8-byte units is shifted by 3 bits, leaving the offset in bytes
+        extension_headers.extend_from_slice(&(offset | more_fragments as u16).to_be_bytes());
+        extension_headers.extend_from_slice(&0x1234u32.to_be_bytes());
+
+        build_test_ipv6_packet(
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1),
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2),
+            0,
+            IpNextHeaderProtocols::Ipv6Opts,
+            &[&extension_headers[..], fragment].concat(),
+        )
+    }
+}
@@ -4,24 +4,83 @@
 //! and represents the parsed packet data at the different levels of the TCP/IP stack
 
 mod application;
+mod dot11;
 mod network;
+mod ospf;
+mod pcap;
+mod pcapng;
+mod pppoe;
+mod sctp;
+mod sll;
+#[cfg(test)]
+pub(crate) mod test_util;
 mod transport;
 
 pub use crate::application::*;
+pub use crate::dot11::*;
 pub use crate::network::*;
+pub use crate::ospf::*;
+pub use crate::pcap::*;
+pub use crate::pcapng::*;
+pub use crate::pppoe::*;
+pub use crate::sctp::*;
 use crate::serializable_packet::SerializableUnknownPacket;
+pub use crate::sll::*;
 pub use crate::transport::*;
 
+pub mod anonymize;
+pub mod arp_monitor;
+pub mod diff;
+pub mod dns_tracker;
+pub mod filter;
+pub mod flow;
+pub mod hierarchy;
+pub mod http_tracker;
+pub mod ipv6_reassembly;
+pub mod loss;
+pub mod merge;
+pub mod meter;
+pub mod modbus_tracker;
+pub mod msgpack;
+pub mod offload;
+pub mod packet_ref;
+pub mod pipeline;
+pub mod profile;
 pub mod serializable_packet;
+pub mod tcp_seq_tracker;
+pub mod tlv;
+pub mod top_talkers;
+pub mod traceroute;
+
+use std::cell::{Cell, RefCell};
+use std::time::SystemTime;
 
-use log::debug;
+use ipv6_reassembly::{Ipv6Reassembler, DEFAULT_REASSEMBLY_TIMEOUT, MAX_DATAGRAM_SIZE};
+use log::{debug, warn};
 use pnet::packet::ethernet::{EtherTypes, EthernetPacket};
 use pnet::packet::Packet;
 use pnet::util::MacAddr;
+use profile::{profiled, ProfileLayer};
+use serializable_packet::util::{contains_malformed, extract_strings};
 use serializable_packet::ParsedPacket;
 use serializable_packet::SerializableEthernetPacket;
 use serializable_packet::SerializablePacket;
 
+/// Default number of packets a network-layer packet may be encapsulated in
+pub const DEFAULT_MAX_ENCAPSULATION_DEPTH: usize = 8;
+
+thread_local!(
+    static RAW_FRAME_RETENTION: Cell<bool> = const { Cell::new(false) };
+    static PAYLOAD_RETENTION: Cell<bool> = const { Cell::new(false) };
+    static MAX_ENCAPSULATION_DEPTH: Cell<usize> =
+        const { Cell::new(DEFAULT_MAX_ENCAPSULATION_DEPTH) };
+    static STRING_EXTRACTION: Cell<Option<usize>> = const { Cell::new(None) };
+    static CREDENTIAL_REDACTION: Cell<bool> = const { Cell::new(false) };
+    static PARSE_PROFILING: Cell<bool> = const { Cell::new(false) };
+    static IPV6_REASSEMBLER: RefCell<Option<Ipv6Reassembler>> = const { RefCell::new(None) };
+    static CAPTURE_TIME: Cell<Option<SystemTime>> = const { Cell::new(None) };
+);
+
 /// Ethernet Header Length
 #[allow(non_snake_case)]
 pub mod HeaderLength {
@@ -32,58 +91,247 @@ pub mod HeaderLength {
 pub fn cleanup_sniffing_state() {
     ACTIVE_HTTP_PARSERS.with(|parsers| parsers.borrow_mut().clear());
     ACTIVE_TLS_PARSERS.with(|parsers| parsers.borrow_mut().clear());
+    ACTIVE_SMTP_SESSIONS.with(|sessions| sessions.borrow_mut().clear());
+    ACTIVE_MAIL_SESSIONS.with(|sessions| sessions.borrow_mut().clear());
+    ACTIVE_FLOWS.with(|flows| flows.borrow_mut().clear());
+    IPV6_REASSEMBLER.with(|reassembler| {
+        if let Some(reassembler) = reassembler.borrow_mut().as_mut() {
+            *reassembler = Ipv6Reassembler::new(MAX_DATAGRAM_SIZE, DEFAULT_REASSEMBLY_TIMEOUT);
+        }
+    });
+}
+
+/// Enable or disable the copy of the raw frame bytes in the parsed packets (disabled by default)
+pub fn set_raw_frame_retention(enabled: bool) {
+    RAW_FRAME_RETENTION.with(|retention| retention.set(enabled));
+}
+
+/// Enable or disable the copy of the payloads in the Ethernet, IP, TCP and UDP representations,
+/// needed for the hexdumps of the alternate display (disabled by default)
+pub fn set_payload_retention(enabled: bool) {
+    PAYLOAD_RETENTION.with(|retention| retention.set(enabled));
+}
+
+/// Check if the payloads are copied in the packet representations
+pub(crate) fn is_payload_retained() -> bool {
+    PAYLOAD_RETENTION.with(|retention| retention.get())
+}
+
+/// Set the number of packets a network-layer packet may be encapsulated in, the deeper ones are
+/// reported as malformed instead of being parsed (`DEFAULT_MAX_ENCAPSULATION_DEPTH` by default)
+pub fn set_max_encapsulation_depth(depth: usize) {
+    MAX_ENCAPSULATION_DEPTH.with(|max_depth| max_depth.set(depth));
+}
+
+/// Get the number of packets a network-layer packet may be encapsulated in
+pub(crate) fn max_encapsulation_depth() -> usize {
+    MAX_ENCAPSULATION_DEPTH.with(|max_depth| max_depth.get())
+}
+
+/// Enable, with the minimum length of the extracted strings, or disable the extraction of the
+/// printable strings of the unparsed payloads (disabled by default)
+pub fn set_string_extraction(min_len: Option<usize>) {
+    STRING_EXTRACTION.with(|extraction| extraction.set(min_len));
+}
+
+/// Get the printable strings of an unparsed payload, if their extraction is enabled and any is found
+pub(crate) fn extracted_strings(payload: &[u8]) -> Option<Vec<String>> {
+    let min_len = STRING_EXTRACTION.with(|extraction| extraction.get())?;
+    let strings = extract_strings(payload, min_len);
+    (!strings.is_empty()).then_some(strings)
+}
+
+/// Enable or disable the redaction of the HTTP cookie values and credentials, replaced by their
+/// length (disabled by default)
+pub fn set_credential_redaction(enabled: bool) {
+    CREDENTIAL_REDACTION.with(|redaction| redaction.set(enabled));
+}
+
+/// Check if the HTTP cookie values and credentials are redacted
+pub(crate) fn is_credential_redacted() -> bool {
+    CREDENTIAL_REDACTION.with(|redaction| redaction.get())
+}
+
+/// Enable or disable the timing of the parse of each layer, accumulated in the profile got with
+/// `profile::take_parse_profile` (disabled by default)
+pub fn set_parse_profiling(enabled: bool) {
+    PARSE_PROFILING.with(|profiling| profiling.set(enabled));
+}
+
+/// Check if the parse of each layer is timed
+pub(crate) fn is_parse_profiled() -> bool {
+    PARSE_PROFILING.with(|profiling| profiling.get())
+}
+
+/// Get the capture time of the packet being parsed, the current time when it has none
+pub(crate) fn capture_time() -> SystemTime {
+    CAPTURE_TIME
+        .with(|time| time.get())
+        .unwrap_or_else(SystemTime::now)
+}
+
+/// Enable or disable the reassembly of the fragmented IPv6 datagrams, each one parsed in place of
+/// the fragment completing it (disabled by default)
+pub fn set_ipv6_reassembly(enabled: bool) {
+    IPV6_REASSEMBLER.with(|reassembler| {
+        *reassembler.borrow_mut() =
+            enabled.then(|| Ipv6Reassembler::new(MAX_DATAGRAM_SIZE, DEFAULT_REASSEMBLY_TIMEOUT));
+    });
+}
+
+/// Add an IPv6 fragment to the datagrams being reassembled at its capture time, getting the
+/// datagram it completes
+pub(crate) fn reassembled_ipv6_datagram(packet: &[u8]) -> Option<Vec<u8>> {
+    IPV6_REASSEMBLER.with(|reassembler| {
+        reassembler
+            .borrow_mut()
+            .as_mut()?
+            .add(packet, capture_time())
+    })
+}
+
+/// Drop the IPv6 datagrams not completed before the reassembly timeout at the capture time `now`
+pub(crate) fn expire_ipv6_datagrams(now: SystemTime) {
+    IPV6_REASSEMBLER.with(|reassembler| {
+        if let Some(reassembler) = reassembler.borrow_mut().as_mut() {
+            reassembler.expire(now);
+        }
+    });
 }
 
 /// Parse ethernet frame obtaining the packet link-layer and network-layer representations
 pub fn parse_ethernet_frame(ethernet: &EthernetPacket, id: usize) -> ParsedPacket {
-    let mut parsed_packet = ParsedPacket::new(id);
-
-    parsed_packet.set_link_layer_packet(Some(SerializablePacket::EthernetPacket(
-        SerializableEthernetPacket::from(ethernet),
-    )));
-
-    match ethernet.get_ethertype() {
-        EtherTypes::Ipv4 => handle_ipv4_packet(ethernet.payload(), &mut parsed_packet),
-        EtherTypes::Ipv6 => handle_ipv6_packet(ethernet.payload(), &mut parsed_packet),
-        EtherTypes::Arp => handle_arp_packet(
-            ethernet.payload(),
-            ethernet.get_source(),
-            ethernet.get_destination(),
-            &mut parsed_packet,
-        ),
-        _ => {
-            debug!(
-                "Unknown packet: {} > {}; ethertype: {:?} length: {}",
+    profiled(ProfileLayer::Link, || {
+        let mut parsed_packet = ParsedPacket::new(id);
+
+        if RAW_FRAME_RETENTION.with(|retention| retention.get()) {
+            parsed_packet.set_raw_frame(Some(ethernet.packet().to_vec()));
+        }
+
+        let ethertype = ethernet.get_ethertype();
+        if matches!(
+            ethertype,
+            EtherTypes::Ipv4 | EtherTypes::Ipv6 | EtherTypes::Arp
+        ) {
+            parsed_packet.set_link_layer_packet(Some(SerializablePacket::EthernetPacket(
+                SerializableEthernetPacket::from(ethernet),
+            )));
+        }
+
+        match ethertype {
+            EtherTypes::Ipv4 => handle_ipv4_packet(ethernet.payload(), &mut parsed_packet),
+            EtherTypes::Ipv6 => handle_ipv6_packet(ethernet.payload(), &mut parsed_packet),
+            EtherTypes::Arp => handle_arp_packet(
+                ethernet.payload(),
+                ethernet.get_source(),
+                ethernet.get_destination(),
+                &mut parsed_packet,
+            ),
+            EtherTypes::PppoeDiscovery | EtherTypes::PppoeSession => {
+                handle_pppoe_packet(ethernet, &mut parsed_packet)
+            }
+            _ => {
+                debug!(
+                    "Unknown packet: {} > {}; ethertype: {:?} length: {}",
+                    ethernet.get_source(),
+                    ethernet.get_destination(),
+                    ethernet.get_ethertype(),
+                    ethernet.packet().len()
+                );
+
+                parsed_packet.set_link_layer_packet(Some(SerializablePacket::UnknownPacket(
+                    SerializableUnknownPacket::from(ethernet),
+                )));
+            }
+        }
+
+        if let Some(SerializablePacket::MalformedPacket(reason)) =
+            parsed_packet.get_network_layer_packet()
+        {
+            warn!(
+                "{}: {} > {}; length: {}",
+                reason,
                 ethernet.get_source(),
                 ethernet.get_destination(),
-                ethernet.get_ethertype(),
                 ethernet.packet().len()
             );
+        }
 
-            parsed_packet.set_link_layer_packet(Some(SerializablePacket::UnknownPacket(
-                SerializableUnknownPacket::from(ethernet),
+        parsed_packet
+    })
+}
+
+/// Parse a pcap record obtaining the packet representations along with its capture timestamp,
+/// with the decoder of its link type (Ethernet unless Linux cooked capture or radiotap); a record
+/// captured shorter than its frame is tagged as a truncated capture; the reassembly buffers and
+/// sessions it touches are aged from its capture time
+pub fn parse_pcap_record(record: &PcapRecord, id: usize) -> ParsedPacket {
+    CAPTURE_TIME.with(|time| time.set(Some(record.timestamp)));
+    let mut parsed_packet = match (record.link_type, EthernetPacket::new(&record.data)) {
+        (LinkTypes::LINUX_SLL, _) => {
+            profiled(ProfileLayer::Link, || parse_sll_frame(&record.data, id))
+        }
+        (LinkTypes::IEEE802_11_RADIOTAP, _) => profiled(ProfileLayer::Link, || {
+            parse_radiotap_frame(&record.data, id)
+        }),
+        (_, Some(ethernet)) => parse_ethernet_frame(&ethernet, id),
+        (_, None) => {
+            warn!("Malformed Ethernet Packet: length: {}", record.data.len());
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_link_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed Ethernet Packet".to_string(),
             )));
+            parsed_packet
         }
-    }
+    };
+    CAPTURE_TIME.with(|time| time.set(None));
 
+    parsed_packet.set_timestamp(Some(record.timestamp));
+    if record.captured_length < record.original_length {
+        parsed_packet.set_truncated_capture(true);
+        if contains_malformed(&parsed_packet) {
+            debug!(
+                "Malformed layer of packet {} due to its truncated capture: {} of {} bytes",
+                id, record.captured_length, record.original_length
+            );
+        }
+    }
     parsed_packet
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parse_ethernet_frame;
-    use crate::serializable_packet::SerializablePacket;
+    use std::cell::RefCell;
+    use std::sync::Once;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+
+    use crate::pcap::tests::build_test_pcap;
+    use crate::serializable_packet::util::contains_malformed;
+    use crate::serializable_packet::{FrameType, SerializableEthernetPacket, SerializablePacket};
+    use crate::test_util::{
+        build_test_ethernet_frame, build_test_ipv4_packet, build_test_tcp_segment, CLIENT, SERVER,
+    };
+    use crate::{
+        parse_ethernet_frame, parse_pcap_record, set_payload_retention, set_raw_frame_retention,
+        LinkTypes, PcapReader, PcapRecord,
+    };
     use pnet::packet::ethernet::EtherType;
     use pnet::packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::packet::tcp::TcpFlags;
     use pnet::packet::Packet;
     use pnet::util::MacAddr;
 
     #[test]
     fn valid_ethernet_packet() {
-        let mut ethernet_buffer = [0u8; 42];
-        let ethernet_packet = build_test_ethernet_packet(ethernet_buffer.as_mut_slice());
+        let frame = build_test_ethernet_frame(EtherTypes::Arp, &[0; 28]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
 
+        set_payload_retention(true);
         let parsed_packet = parse_ethernet_frame(&ethernet_packet, 0);
+        set_payload_retention(false);
         match parsed_packet.get_link_layer_packet().unwrap() {
             SerializablePacket::EthernetPacket(new_ethernet_packet) => {
                 assert_eq!(
@@ -104,10 +352,125 @@ mod tests {
         }
     }
 
+    #[test]
+    fn runt_ethernet_frame() {
+        let frame = build_test_ethernet_frame(EtherTypes::Arp, &[0; 26]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+
+        let parsed_packet = parse_ethernet_frame(&ethernet_packet, 0);
+        match parsed_packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::EthernetPacket(new_ethernet_packet) => {
+                assert!(new_ethernet_packet.is_runt);
+                assert!(!new_ethernet_packet.is_jumbo);
+                assert!(new_ethernet_packet
+                    .to_string()
+                    .ends_with("\n\tRunt Frame (40 bytes)"));
+            }
+            _ => unreachable!(),
+        }
+
+        // 60 bytes captured, 64 bytes on-wire with the FCS
+        let frame = build_test_ethernet_frame(EtherTypes::Arp, &[0; 46]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+        match parse_ethernet_frame(&ethernet_packet, 0).get_link_layer_packet() {
+            Some(SerializablePacket::EthernetPacket(new_ethernet_packet)) => {
+                assert!(!new_ethernet_packet.is_runt)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn jumbo_ethernet_frame() {
+        let frame = build_test_ethernet_frame(EtherTypes::Arp, &[0; 8986]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+
+        let parsed_packet = parse_ethernet_frame(&ethernet_packet, 0);
+        match parsed_packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::EthernetPacket(new_ethernet_packet) => {
+                assert!(new_ethernet_packet.is_jumbo);
+                assert!(!new_ethernet_packet.is_runt);
+                assert!(new_ethernet_packet
+                    .to_string()
+                    .ends_with("\n\tJumbo Frame (9000 bytes)"));
+            }
+            _ => unreachable!(),
+        }
+
+        // 1514 bytes captured, 1518 bytes on-wire with the FCS
+        let frame = build_test_ethernet_frame(EtherTypes::Arp, &[0; 1500]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+        match parse_ethernet_frame(&ethernet_packet, 0).get_link_layer_packet() {
+            Some(SerializablePacket::EthernetPacket(new_ethernet_packet)) => {
+                assert!(!new_ethernet_packet.is_jumbo)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn tagged_ethernet_frame_not_jumbo() {
+        // 1518 bytes captured, 1522 bytes on-wire with the FCS: the maximum of a tagged frame
+        let mut ethernet_buffer = vec![0u8; 1518];
+        let mut ethernet_packet = MutableEthernetPacket::new(&mut ethernet_buffer).unwrap();
+        ethernet_packet.set_ethertype(EtherTypes::Vlan);
+        ethernet_packet.set_payload(&[0x00, 0x0a, 0x08, 0x00]);
+        let new_ethernet_packet = SerializableEthernetPacket::from(&ethernet_packet.to_immutable());
+        assert!(!new_ethernet_packet.is_jumbo);
+
+        // The same length untagged is jumbo
+        let frame = build_test_ethernet_frame(EtherTypes::Arp, &[0; 1504]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+        match parse_ethernet_frame(&ethernet_packet, 0).get_link_layer_packet() {
+            Some(SerializablePacket::EthernetPacket(new_ethernet_packet)) => {
+                assert!(new_ethernet_packet.is_jumbo)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn ethernet_frame_types() {
+        for (destination, frame_type) in [
+            (MacAddr::broadcast(), FrameType::Broadcast),
+            (
+                MacAddr::new(0x01, 0x00, 0x5e, 0x00, 0x00, 0xfb),
+                FrameType::Ipv4Multicast,
+            ),
+            (
+                MacAddr::new(0x33, 0x33, 0x00, 0x00, 0x00, 0x01),
+                FrameType::Ipv6Multicast,
+            ),
+            (
+                MacAddr::new(0x01, 0x80, 0xc2, 0x00, 0x00, 0x00),
+                FrameType::Multicast,
+            ),
+            (
+                MacAddr::new(0x00, 0x1b, 0x21, 0xaa, 0xbb, 0xcc),
+                FrameType::Unicast,
+            ),
+        ] {
+            let mut ethernet_buffer = [0u8; 60];
+            let mut ethernet_packet = MutableEthernetPacket::new(&mut ethernet_buffer).unwrap();
+            ethernet_packet.set_destination(destination);
+            ethernet_packet.set_ethertype(EtherTypes::Arp);
+
+            match parse_ethernet_frame(&ethernet_packet.to_immutable(), 0).get_link_layer_packet() {
+                Some(SerializablePacket::EthernetPacket(new_ethernet_packet)) => {
+                    assert_eq!(new_ethernet_packet.frame_type, frame_type);
+                    assert!(new_ethernet_packet
+                        .to_string()
+                        .contains(&format!("Destination: {} ({:?})", destination, frame_type)));
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
     #[test]
     fn unknown_ethernet_packet() {
-        let mut ethernet_buffer = [0u8; 42];
-        let ethernet_packet = build_test_unknown_ethernet_packet(ethernet_buffer.as_mut_slice());
+        let frame = build_test_ethernet_frame(EtherType(0x9999), &[0; 28]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
 
         let parsed_packet = parse_ethernet_frame(&ethernet_packet, 0);
         match parsed_packet.get_link_layer_packet().unwrap() {
@@ -127,25 +490,245 @@ mod tests {
         }
     }
 
+    #[test]
+    fn http_protocol_stack() {
+        let frame = build_test_http_frame();
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+
+        let parsed_packet = parse_ethernet_frame(&ethernet_packet, 0);
+        assert_eq!(
+            parsed_packet.protocol_stack(),
+            vec!["Ethernet", "IPv4", "TCP", "HTTP"]
+        );
+    }
+
+    #[test]
+    fn arp_protocol_stack() {
+        let frame = build_test_ethernet_frame(EtherTypes::Arp, &[0; 28]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+        assert_eq!(
+            parse_ethernet_frame(&ethernet_packet, 0).protocol_stack(),
+            vec!["Ethernet", "ARP"]
+        );
+
+        let frame = build_test_ethernet_frame(EtherType(0x9999), &[0; 28]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+        assert_eq!(
+            parse_ethernet_frame(&ethernet_packet, 0).protocol_stack(),
+            vec!["Unknown"]
+        );
+    }
+
+    #[test]
+    fn packet_size_bytes() {
+        let frame = build_test_http_frame();
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+
+        let parsed_packet = parse_ethernet_frame(&ethernet_packet, 0);
+        let frame_length = ethernet_packet.packet().len();
+        assert_eq!(parsed_packet.size_bytes(), frame_length);
+        let layer_size = |layer: Option<&SerializablePacket>| layer.unwrap().size_bytes();
+        assert_eq!(
+            layer_size(parsed_packet.get_network_layer_packet()),
+            frame_length - 14
+        );
+        assert_eq!(
+            layer_size(parsed_packet.get_transport_layer_packet()),
+            frame_length - 34
+        );
+        assert_eq!(layer_size(parsed_packet.get_application_layer_packet()), 0);
+
+        let frame = build_test_ethernet_frame(EtherTypes::Arp, &[0; 28]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+        let parsed_packet = parse_ethernet_frame(&ethernet_packet, 0);
+        assert_eq!(parsed_packet.size_bytes(), 42);
+        assert_eq!(layer_size(parsed_packet.get_network_layer_packet()), 28);
+
+        let frame = build_test_ethernet_frame(EtherType(0x9999), &[0; 28]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+        assert_eq!(parse_ethernet_frame(&ethernet_packet, 0).size_bytes(), 42);
+    }
+
+    #[test]
+    fn pcap_record_timestamp_preserved() {
+        let frame = build_test_ethernet_frame(EtherTypes::Arp, &[0; 28]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+        let pcap = build_test_pcap(&[(1_600_000_000, 123_456, ethernet_packet.packet().to_vec())]);
+
+        let mut reader = PcapReader::new(pcap.as_slice()).unwrap();
+        let record = reader.next_record().unwrap().unwrap();
+        let parsed_packet = parse_pcap_record(&record, 0);
+
+        assert_eq!(
+            parsed_packet.get_timestamp(),
+            Some(UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_000))
+        );
+        assert!(matches!(
+            parsed_packet.get_link_layer_packet(),
+            Some(SerializablePacket::EthernetPacket(_))
+        ));
+    }
+
+    #[test]
+    fn parsed_packet_without_timestamp() {
+        let frame = build_test_ethernet_frame(EtherTypes::Arp, &[0; 28]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+
+        let parsed_packet = parse_ethernet_frame(&ethernet_packet, 0);
+        assert!(parsed_packet.get_timestamp().is_none());
+    }
+
+    #[test]
+    fn raw_frame_not_retained_by_default() {
+        let frame = build_test_ethernet_frame(EtherTypes::Arp, &[0; 28]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+
+        let parsed_packet = parse_ethernet_frame(&ethernet_packet, 0);
+        assert!(parsed_packet.get_raw_frame().is_none());
+        assert!(parsed_packet.to_pcap_record(UNIX_EPOCH).is_empty());
+    }
+
+    #[test]
+    fn payload_not_retained_by_default() {
+        let frame = build_test_ethernet_frame(EtherTypes::Arp, &[0; 28]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+
+        let parsed_packet = parse_ethernet_frame(&ethernet_packet, 0);
+        match parsed_packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::EthernetPacket(new_ethernet_packet) => {
+                assert!(new_ethernet_packet.payload.is_empty())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn pcap_record_round_trip() {
+        let frame = build_test_ethernet_frame(EtherTypes::Arp, &[0; 28]);
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+        let input = build_test_pcap(&[(1_600_000_000, 123_456, ethernet_packet.packet().to_vec())]);
+
+        set_raw_frame_retention(true);
+        let mut reader = PcapReader::new(input.as_slice()).unwrap();
+        let record = reader.next_record().unwrap().unwrap();
+        let parsed_packet = parse_pcap_record(&record, 0);
+        set_raw_frame_retention(false);
+
+        let mut output = build_test_pcap(&[]);
+        output.extend(parsed_packet.to_pcap_record(parsed_packet.get_timestamp().unwrap()));
+
+        assert_eq!(
+            parsed_packet.get_raw_frame(),
+            Some(ethernet_packet.packet())
+        );
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn truncated_capture_record() {
+        let frame = build_test_http_frame();
+        let ethernet_packet = EthernetPacket::new(&frame).unwrap();
+        // Snaplen cutting the frame in the middle of the TCP header
+        let record = PcapRecord {
+            link_type: LinkTypes::ETHERNET,
+            timestamp: UNIX_EPOCH,
+            captured_length: 40,
+            original_length: ethernet_packet.packet().len() as u32,
+            data: ethernet_packet.packet()[..40].to_vec(),
+        };
+
+        let parsed_packet = parse_pcap_record(&record, 0);
+        assert!(parsed_packet.is_truncated_capture());
+        assert!(contains_malformed(&parsed_packet));
+        assert!(parsed_packet.to_string().contains("Truncated Capture\n"));
+
+        let record = PcapRecord {
+            captured_length: record.original_length,
+            data: ethernet_packet.packet().to_vec(),
+            ..record
+        };
+        assert!(!parse_pcap_record(&record, 1).is_truncated_capture());
+    }
+
+    #[test]
+    fn malformed_pcap_record() {
+        let pcap = build_test_pcap(&[(0, 0, vec![1, 2, 3])]);
+
+        let mut reader = PcapReader::new(pcap.as_slice()).unwrap();
+        let record = reader.next_record().unwrap().unwrap();
+        let parsed_packet = parse_pcap_record(&record, 0);
+
+        match parsed_packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::MalformedPacket(str) => {
+                assert_eq!(str, "Malformed Ethernet Packet")
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_pcap_record_logged() {
+        let pcap = build_test_pcap(&[(0, 0, vec![1, 2, 3])]);
+
+        let mut reader = PcapReader::new(pcap.as_slice()).unwrap();
+        let record = reader.next_record().unwrap().unwrap();
+        let logs = capture_logs(|| {
+            parse_pcap_record(&record, 0);
+        });
+
+        assert_eq!(
+            logs,
+            vec![(
+                Level::Warn,
+                "Malformed Ethernet Packet: length: 3".to_string()
+            )]
+        );
+    }
+
     ///////////////////// Utils
 
-    fn build_test_ethernet_packet<'a>(ethernet_buffer: &'a mut [u8]) -> EthernetPacket<'a> {
-        let mut ethernet_packet = MutableEthernetPacket::new(ethernet_buffer).unwrap();
+    thread_local!(
+        static CAPTURED_LOGS: RefCell<Vec<(Level, String)>> = const { RefCell::new(vec![]) };
+    );
 
-        ethernet_packet.set_destination(MacAddr::new(11, 11, 11, 11, 11, 11));
-        ethernet_packet.set_source(MacAddr::new(10, 10, 10, 10, 10, 10));
-        ethernet_packet.set_ethertype(EtherTypes::Arp);
+    /// Logger saving the records of each thread, so that parallel tests don't mix their output
+    struct CapturingLogger;
 
-        ethernet_packet.consume_to_immutable()
+    impl Log for CapturingLogger {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            CAPTURED_LOGS.with(|logs| {
+                logs.borrow_mut()
+                    .push((record.level(), record.args().to_string()))
+            });
+        }
+
+        fn flush(&self) {}
     }
 
-    fn build_test_unknown_ethernet_packet<'a>(ethernet_buffer: &'a mut [u8]) -> EthernetPacket<'a> {
-        let mut ethernet_packet = MutableEthernetPacket::new(ethernet_buffer).unwrap();
+    fn capture_logs<F: FnOnce()>(f: F) -> Vec<(Level, String)> {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&CapturingLogger).unwrap();
+            log::set_max_level(LevelFilter::Trace);
+        });
+
+        CAPTURED_LOGS.with(|logs| logs.borrow_mut().clear());
+        f();
+        CAPTURED_LOGS.with(|logs| logs.take())
+    }
 
-        ethernet_packet.set_destination(MacAddr::new(11, 11, 11, 11, 11, 11));
-        ethernet_packet.set_source(MacAddr::new(10, 10, 10, 10, 10, 10));
-        ethernet_packet.set_ethertype(EtherType(0x9999));
+    const HTTP_REQUEST: &[u8] = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
 
-        ethernet_packet.consume_to_immutable()
+    /// Build the frame of an HTTP request from the client to the server
+    fn build_test_http_frame() -> Vec<u8> {
+        let tcp_segment =
+            build_test_tcp_segment(4444, 80, TcpFlags::ACK | TcpFlags::PSH, 0, 0, HTTP_REQUEST);
+        let ip_packet =
+            build_test_ipv4_packet(CLIENT, SERVER, 64, IpNextHeaderProtocols::Tcp, &tcp_segment);
+        build_test_ethernet_frame(EtherTypes::Ipv4, &ip_packet)
     }
 }
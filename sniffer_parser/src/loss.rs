@@ -0,0 +1,342 @@
+//! Packet loss estimation
+//!
+//! Each direction of a flow is followed in its sequence space: the bytes of the TCP segments,
+//! or the 16-bit sequence numbers of the RTP headers, extended across their wrap-around. A packet
+//! starting after the highest sequence number seen leaves a gap, which the later packets may fill
+//! (retransmissions or reordered packets); a gap left unfilled after a window of packets of its
+//! direction is counted as lost. The loss of a flow is the share of its sequence space lost.
+//! Past the maximum number of flows, the flow seen first is forgotten
+
+use std::collections::{HashMap, VecDeque};
+
+use pnet::packet::tcp::TcpFlags;
+
+use crate::flow::FlowKey;
+use crate::serializable_packet::util::get_source_endpoint;
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+/// Packets of a direction after which a gap it left unfilled is counted as lost
+pub const DEFAULT_LOSS_WINDOW: usize = 64;
+/// Flows followed at most by default
+pub const DEFAULT_MAX_FLOWS: usize = 65536;
+/// Origin of the extended sequence numbers, so that the first packet may be followed by earlier
+/// ones
+const EXTENDED_ORIGIN: u64 = 1 << 48;
+
+/// Lost and expected units of a flow: bytes for TCP, packets for RTP
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LossStats {
+    pub expected: u64,
+    pub lost: u64,
+}
+
+impl LossStats {
+    /// Get the percentage of the expected units lost
+    pub fn percentage(&self) -> f64 {
+        match self.expected {
+            0 => 0.0,
+            expected => self.lost as f64 * 100.0 / expected as f64,
+        }
+    }
+}
+
+/// Range of sequence numbers missing from a direction, with the packets seen since it was left
+#[derive(Debug)]
+struct Gap {
+    start: u64,
+    end: u64,
+    age: usize,
+}
+
+/// Extended sequence space of a direction of a flow
+#[derive(Debug)]
+struct SequenceSpace {
+    /// Bits of the sequence numbers: 32 for TCP, 16 for RTP
+    bits: u32,
+    first: u64,
+    next: u64,
+    gaps: Vec<Gap>,
+    lost: u64,
+}
+
+impl SequenceSpace {
+    /// Build the sequence space of a direction from its first packet
+    fn new(bits: u32, sequence: u64, length: u64) -> Self {
+        SequenceSpace {
+            bits,
+            first: EXTENDED_ORIGIN + sequence,
+            next: EXTENDED_ORIGIN + sequence + length,
+            gaps: vec![],
+            lost: 0,
+        }
+    }
+
+    /// Extend a sequence number to the 64-bit value closest to the next one expected
+    fn extend(&self, sequence: u64) -> u64 {
+        let modulus = 1u64 << self.bits;
+        let offset = sequence.wrapping_sub(self.next) & (modulus - 1);
+        match offset < modulus / 2 {
+            true => self.next + offset,
+            false => self.next.saturating_sub(modulus - offset),
+        }
+    }
+
+    /// Account a packet covering `length` sequence numbers from `sequence`, aging the open gaps
+    /// and counting those left unfilled for a whole window as lost
+    fn update(&mut self, sequence: u64, length: u64, window: usize) {
+        let start = self.extend(sequence);
+        let end = start + length;
+
+        for gap in self.gaps.iter_mut() {
+            gap.age += 1;
+        }
+        self.gaps = self
+            .gaps
+            .drain(..)
+            .flat_map(|gap| {
+                let before = (gap.start < start.min(gap.end)).then(|| Gap {
+                    end: start.min(gap.end),
+                    ..gap
+                });
+                let after = (end.max(gap.start) < gap.end).then(|| Gap {
+                    start: end.max(gap.start),
+                    ..gap
+                });
+                before.into_iter().chain(after)
+            })
+            .collect();
+
+        if start > self.next {
+            self.gaps.push(Gap {
+                start: self.next,
+                end: start,
+                age: 0,
+            });
+        }
+        self.first = self.first.min(start);
+        self.next = self.next.max(end);
+
+        let lost: u64 = self
+            .gaps
+            .iter()
+            .filter(|gap| gap.age >= window)
+            .map(|gap| gap.end - gap.start)
+            .sum();
+        self.lost += lost;
+        self.gaps.retain(|gap| gap.age < window);
+    }
+}
+
+/// Sequence spaces of the directions of a flow: from its lower endpoint, from its upper one
+#[derive(Debug, Default)]
+struct FlowSequences {
+    from_lower: Option<SequenceSpace>,
+    from_upper: Option<SequenceSpace>,
+}
+
+/// Sequence gaps of the TCP and RTP flows, fed with every parsed packet in capture order
+#[derive(Debug)]
+pub struct LossEstimator {
+    window: usize,
+    max_flows: usize,
+    flows: HashMap<FlowKey, FlowSequences>,
+    /// Flows in the order they were first seen
+    order: VecDeque<FlowKey>,
+}
+
+impl Default for LossEstimator {
+    fn default() -> Self {
+        LossEstimator::new(DEFAULT_LOSS_WINDOW)
+    }
+}
+
+impl LossEstimator {
+    /// Build an estimator counting the gaps left unfilled for `window` packets as lost
+    pub fn new(window: usize) -> Self {
+        LossEstimator {
+            window,
+            max_flows: DEFAULT_MAX_FLOWS,
+            flows: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Follow at most `max_flows` flows, forgetting the one seen first past them
+    pub fn max_flows(mut self, max_flows: usize) -> Self {
+        self.max_flows = max_flows.max(1);
+        self
+    }
+
+    /// Account a TCP segment or a datagram with an RTP sequence number of the flow `key` in the
+    /// sequence space of its direction
+    pub fn update(&mut self, key: &FlowKey, packet: &ParsedPacket) {
+        let (bits, sequence, length) = match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => {
+                // A reset ends the connection wherever its sequence number points
+                if tcp_packet.flags & TcpFlags::RST as u16 != 0 {
+                    return;
+                }
+                // SYN and FIN take a sequence number each
+                let length = tcp_packet.length as u64
+                    + u64::from(tcp_packet.flags & TcpFlags::SYN as u16 != 0)
+                    + u64::from(tcp_packet.flags & TcpFlags::FIN as u16 != 0);
+                (32, tcp_packet.sequence as u64, length)
+            }
+            Some(SerializablePacket::UdpPacket(udp_packet)) => match udp_packet.rtp_sequence {
+                Some(rtp_sequence) => (16, rtp_sequence as u64, 1),
+                None => return,
+            },
+            _ => return,
+        };
+        let Some(source) = get_source_endpoint(packet) else {
+            return;
+        };
+
+        if !self.flows.contains_key(key) {
+            if self.order.len() >= self.max_flows {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.flows.remove(&oldest);
+                }
+            }
+            self.order.push_back(key.clone());
+        }
+        let flow = self.flows.entry(key.clone()).or_default();
+        let direction = match source == key.lower {
+            true => &mut flow.from_lower,
+            false => &mut flow.from_upper,
+        };
+        match direction {
+            Some(space) => space.update(sequence, length, self.window),
+            None => *direction = Some(SequenceSpace::new(bits, sequence, length)),
+        }
+    }
+
+    /// Get the lost and expected units of both directions of a flow, if any packet was accounted
+    pub fn loss(&self, key: &FlowKey) -> Option<LossStats> {
+        let flow = self.flows.get(key)?;
+        [&flow.from_lower, &flow.from_upper]
+            .into_iter()
+            .flatten()
+            .map(|space| LossStats {
+                expected: space.next - space.first,
+                lost: space.lost,
+            })
+            .reduce(|total, stats| LossStats {
+                expected: total.expected + stats.expected,
+                lost: total.lost + stats.lost,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pnet::packet::tcp::TcpFlags;
+
+    use super::{LossEstimator, LossStats};
+    use crate::flow::FlowKey;
+    use crate::serializable_packet::ParsedPacket;
+    use crate::test_util::{self, build_test_udp_packet, CLIENT, SERVER};
+
+    #[test]
+    fn tcp_permanent_gap() {
+        let mut estimator = LossEstimator::new(2);
+        let packets = [
+            build_test_tcp_packet(1000, 100),
+            // The segment from 1100 to 1200 is missing, the one from 1400 to 1500 captured late
+            build_test_tcp_packet(1200, 100),
+            build_test_tcp_packet(1300, 100),
+            build_test_tcp_packet(1500, 100),
+            build_test_tcp_packet(1400, 100),
+            build_test_tcp_packet(1600, 100),
+            build_test_tcp_packet(1700, 100),
+        ];
+        let key = FlowKey::from_packet(&packets[0]).unwrap();
+        for packet in packets.iter() {
+            estimator.update(&key, packet);
+        }
+
+        let loss = estimator.loss(&key).unwrap();
+        assert_eq!(
+            loss,
+            LossStats {
+                expected: 800,
+                lost: 100
+            }
+        );
+        assert_eq!(loss.percentage(), 12.5);
+    }
+
+    #[test]
+    fn rtp_missing_sequence_numbers() {
+        let mut estimator = LossEstimator::default();
+        let mut packets = vec![];
+        for sequence in (65530..=65535).chain(0..10) {
+            // Sequence numbers 65534 and 2 are missing, 5 and 6 swapped
+            let sequence = match sequence {
+                65534 | 2 => continue,
+                5 => 6,
+                6 => 5,
+                sequence => sequence,
+            };
+            packets.push(build_test_rtp_packet(sequence));
+        }
+        let key = FlowKey::from_packet(&packets[0]).unwrap();
+        for packet in packets.iter() {
+            estimator.update(&key, packet);
+        }
+
+        // Still missing at the end of the stream, the gaps are not counted as lost yet
+        assert_eq!(
+            estimator.loss(&key),
+            Some(LossStats {
+                expected: 16,
+                lost: 0
+            })
+        );
+
+        for sequence in 10..80 {
+            estimator.update(&key, &build_test_rtp_packet(sequence));
+        }
+        let loss = estimator.loss(&key).unwrap();
+        assert_eq!(
+            loss,
+            LossStats {
+                expected: 86,
+                lost: 2
+            }
+        );
+        assert!((loss.percentage() - 2.3).abs() < 0.1);
+    }
+
+    #[test]
+    fn oldest_flow_forgotten() {
+        let mut estimator = LossEstimator::default().max_flows(1);
+        let (tcp_packet, rtp_packet) = (build_test_tcp_packet(1000, 100), build_test_rtp_packet(0));
+        let tcp_key = FlowKey::from_packet(&tcp_packet).unwrap();
+        let rtp_key = FlowKey::from_packet(&rtp_packet).unwrap();
+
+        estimator.update(&tcp_key, &tcp_packet);
+        estimator.update(&rtp_key, &rtp_packet);
+        assert_eq!(estimator.loss(&tcp_key), None);
+        assert!(estimator.loss(&rtp_key).is_some());
+    }
+
+    ///////////////////// Utils
+
+    /// Build a segment from 10.10.10.10:4444 to 11.11.11.11:5555
+    fn build_test_tcp_packet(sequence: u32, payload_length: usize) -> ParsedPacket {
+        let flags = TcpFlags::ACK | TcpFlags::PSH;
+        let payload = vec![0u8; payload_length];
+        test_util::build_test_tcp_packet((CLIENT, 4444), (SERVER, 5555), flags, sequence, &payload)
+    }
+
+    /// Build a datagram from 10.10.10.10:40002 to 11.11.11.11:51234 with an RTP header
+    fn build_test_rtp_packet(sequence: u16) -> ParsedPacket {
+        let mut rtp_payload = vec![0x80, 0x00];
+        rtp_payload.extend_from_slice(&sequence.to_be_bytes());
+        rtp_payload.extend_from_slice(&[0x00, 0x00, 0x00, 0xa0, 0x12, 0x34, 0x56, 0x78]);
+        rtp_payload.extend_from_slice(&[0xff, 0xfe, 0x7f, 0x7e]);
+
+        build_test_udp_packet((CLIENT, 40_002), (SERVER, 51_234), &rtp_payload)
+    }
+}
@@ -0,0 +1,176 @@
+//! Chronological merge of several pcap files
+//!
+//! The next record of every input is kept in a min-heap ordered by timestamp, the input index
+//! breaking ties; the earliest one is yielded and replaced by the following record of its input,
+//! so the records of all the inputs come out in timestamp order (a k-way merge). The inputs must
+//! share the same link type for their records to be decoded alike
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::Read;
+use std::time::SystemTime;
+
+use crate::parse_pcap_record;
+use crate::pcap::{PcapError, PcapReader, PcapRecord};
+use crate::serializable_packet::ParsedPacket;
+
+/// Reader over the records of several pcap files, in timestamp order
+pub struct PcapMerger<R: Read> {
+    readers: Vec<PcapReader<R>>,
+    /// Next record of each input, `None` once yielded or at the end of the input
+    next_records: Vec<Option<PcapRecord>>,
+    /// Timestamps and inputs of the next records, earliest first
+    heap: BinaryHeap<Reverse<(SystemTime, usize)>>,
+    /// Error met reading the next record of an input, yielded before any other record
+    error: Option<PcapError>,
+}
+
+impl<R: Read> PcapMerger<R> {
+    /// Build a merger over readers of the same link type, positioned on their first records
+    pub fn new(readers: Vec<PcapReader<R>>) -> Result<Self, PcapError> {
+        if let Some(first) = readers.first() {
+            let expected = first.get_link_type();
+            let mismatch = readers
+                .iter()
+                .enumerate()
+                .find(|(_, reader)| reader.get_link_type() != expected);
+            if let Some((input, reader)) = mismatch {
+                return Err(PcapError::LinkTypeMismatch {
+                    input,
+                    link_type: reader.get_link_type(),
+                    expected,
+                });
+            }
+        }
+
+        let mut merger = PcapMerger {
+            next_records: readers.iter().map(|_| None).collect(),
+            readers,
+            heap: BinaryHeap::new(),
+            error: None,
+        };
+        for input in 0..merger.readers.len() {
+            merger.advance(input);
+        }
+
+        Ok(merger)
+    }
+
+    /// Get the link type shared by the inputs
+    pub fn get_link_type(&self) -> Option<u32> {
+        self.readers.first().map(PcapReader::get_link_type)
+    }
+
+    /// Parse the merged records, numbering the packets in timestamp order and tagging each with
+    /// the index of its input
+    pub fn into_packets(self) -> impl Iterator<Item = Result<ParsedPacket, PcapError>> {
+        self.enumerate().map(|(id, record)| {
+            let (input, record) = record?;
+            let mut parsed_packet = parse_pcap_record(&record, id);
+            parsed_packet.set_source_index(Some(input));
+            Ok(parsed_packet)
+        })
+    }
+
+    /// Read the next record of an input, pushing it on the heap
+    fn advance(&mut self, input: usize) {
+        match self.readers[input].next_record() {
+            Some(Ok(record)) => {
+                self.heap.push(Reverse((record.timestamp, input)));
+                self.next_records[input] = Some(record);
+            }
+            Some(Err(e)) => self.error = Some(e),
+            None => (),
+        }
+    }
+}
+
+impl<R: Read> Iterator for PcapMerger<R> {
+    type Item = Result<(usize, PcapRecord), PcapError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+
+        let Reverse((_, input)) = self.heap.pop()?;
+        let record = self.next_records[input].take()?;
+        self.advance(input);
+
+        Some(Ok((input, record)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::PcapMerger;
+    use crate::pcap::tests::{build_test_pcap, build_test_pcap_with_link_type};
+    use crate::pcap::{LinkTypes, PcapError};
+    use crate::PcapReader;
+
+    #[test]
+    fn merge_in_timestamp_order() {
+        let frame = vec![0u8; 14];
+        let first = build_test_pcap(&[
+            (10, 0, frame.clone()),
+            (12, 0, frame.clone()),
+            (12, 500, frame.clone()),
+        ]);
+        let second = build_test_pcap(&[(11, 0, frame.clone()), (12, 0, frame.clone())]);
+        let merger = PcapMerger::new(vec![
+            PcapReader::new(first.as_slice()).unwrap(),
+            PcapReader::new(second.as_slice()).unwrap(),
+        ])
+        .unwrap();
+
+        let packets = merger
+            .into_packets()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let order: Vec<_> = packets
+            .iter()
+            .map(|packet| (packet.get_timestamp().unwrap(), packet.get_source_index()))
+            .collect();
+        let at = |seconds: u64, micros: u64| {
+            UNIX_EPOCH + Duration::from_secs(seconds) + Duration::from_micros(micros)
+        };
+        assert_eq!(
+            order,
+            vec![
+                (at(10, 0), Some(0)),
+                (at(11, 0), Some(1)),
+                (at(12, 0), Some(0)),
+                (at(12, 0), Some(1)),
+                (at(12, 500), Some(0)),
+            ]
+        );
+        assert!(packets
+            .iter()
+            .enumerate()
+            .all(|(id, packet)| packet.get_id() == id));
+    }
+
+    #[test]
+    fn merge_link_type_mismatch() {
+        let first = build_test_pcap(&[]);
+        let second = build_test_pcap_with_link_type(LinkTypes::LINUX_SLL, &[]);
+        let merger = PcapMerger::new(vec![
+            PcapReader::new(first.as_slice()).unwrap(),
+            PcapReader::new(second.as_slice()).unwrap(),
+        ]);
+
+        match merger {
+            Err(PcapError::LinkTypeMismatch {
+                input,
+                link_type,
+                expected,
+            }) => assert_eq!(
+                (input, link_type, expected),
+                (1, LinkTypes::LINUX_SLL, LinkTypes::ETHERNET)
+            ),
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Throughput metering
+//!
+//! Packets are accounted by capture timestamp in fixed-length buckets (1 s by default); the rates
+//! are computed over the buckets of a sliding window ending with the current one (10 s by
+//! default), or over the buckets seen so far when the capture is younger than the window
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::serializable_packet::util::get_frame_length;
+use crate::serializable_packet::ParsedPacket;
+
+const DEFAULT_BUCKET_LENGTH: Duration = Duration::from_secs(1);
+const DEFAULT_WINDOW_BUCKETS: u64 = 10;
+
+/// Packets accounted during a time slot
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bucket {
+    /// Index of the time slot, from the Unix epoch
+    pub index: u64,
+    pub packets: u64,
+    pub bytes: u64,
+    /// Packets by innermost protocol
+    pub protocols: HashMap<&'static str, u64>,
+}
+
+/// Rates over a window
+#[derive(Debug, Clone, PartialEq)]
+pub struct Throughput {
+    pub packets_per_second: f64,
+    pub bytes_per_second: f64,
+    /// Innermost protocol of most packets of the window
+    pub top_protocol: Option<&'static str>,
+}
+
+/// Sliding window meter of the packet and byte rates
+#[derive(Debug)]
+pub struct ThroughputMeter {
+    bucket_length: Duration,
+    window_buckets: u64,
+    first_index: Option<u64>,
+    buckets: VecDeque<Bucket>,
+}
+
+impl Default for ThroughputMeter {
+    fn default() -> Self {
+        ThroughputMeter::new(DEFAULT_BUCKET_LENGTH, DEFAULT_WINDOW_BUCKETS)
+    }
+}
+
+impl ThroughputMeter {
+    /// Build a meter over `window_buckets` buckets of `bucket_length`
+    pub fn new(bucket_length: Duration, window_buckets: u64) -> Self {
+        ThroughputMeter {
+            bucket_length,
+            window_buckets: window_buckets.max(1),
+            first_index: None,
+            buckets: VecDeque::new(),
+        }
+    }
+
+    /// Account a packet, of its length from the headers; packets without timestamp are ignored
+    pub fn add(&mut self, packet: &ParsedPacket) {
+        if let Some(timestamp) = packet.get_timestamp() {
+            let protocol = packet.protocol_stack().last().copied();
+            self.record(timestamp, get_frame_length(packet), protocol);
+        }
+    }
+
+    /// Account a packet of `bytes` captured at `timestamp`
+    pub fn record(&mut self, timestamp: SystemTime, bytes: usize, protocol: Option<&'static str>) {
+        let index = self.bucket_index(timestamp);
+        self.first_index.get_or_insert(index);
+
+        // Packets slightly out of order land in their bucket when it is still in the window
+        let bucket = match self.buckets.iter().position(|bucket| bucket.index >= index) {
+            Some(position) if self.buckets[position].index == index => &mut self.buckets[position],
+            Some(position) => {
+                self.buckets.insert(
+                    position,
+                    Bucket {
+                        index,
+                        ..Default::default()
+                    },
+                );
+                &mut self.buckets[position]
+            }
+            None => {
+                self.buckets.push_back(Bucket {
+                    index,
+                    ..Default::default()
+                });
+                self.buckets.back_mut().unwrap()
+            }
+        };
+        bucket.packets += 1;
+        bucket.bytes += bytes as u64;
+        if let Some(protocol) = protocol {
+            *bucket.protocols.entry(protocol).or_default() += 1;
+        }
+
+        let newest = self.buckets.back().map_or(index, |bucket| bucket.index);
+        while self
+            .buckets
+            .front()
+            .is_some_and(|bucket| bucket.index + self.window_buckets <= newest)
+        {
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Get the rates over the window ending at `now`
+    pub fn throughput(&self, now: SystemTime) -> Throughput {
+        let now_index = self.bucket_index(now);
+        let first_index = self.first_index.unwrap_or(now_index);
+
+        window_throughput(
+            &self.buckets,
+            now_index,
+            first_index,
+            self.window_buckets,
+            self.bucket_length,
+        )
+    }
+
+    fn bucket_index(&self, timestamp: SystemTime) -> u64 {
+        let since_epoch = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        (since_epoch.as_nanos() / self.bucket_length.as_nanos().max(1)) as u64
+    }
+}
+
+/// Compute the rates over the `window_buckets` buckets ending with the bucket `now_index`, the
+/// window starting no earlier than `first_index` (the first bucket of the capture)
+pub fn window_throughput<'a, I>(
+    buckets: I,
+    now_index: u64,
+    first_index: u64,
+    window_buckets: u64,
+    bucket_length: Duration,
+) -> Throughput
+where
+    I: IntoIterator<Item = &'a Bucket>,
+{
+    let window_start = (now_index + 1)
+        .saturating_sub(window_buckets)
+        .max(first_index);
+    let window = buckets
+        .into_iter()
+        .filter(|bucket| (window_start..=now_index).contains(&bucket.index));
+
+    let mut packets = 0;
+    let mut bytes = 0;
+    let mut protocols: HashMap<&'static str, u64> = HashMap::new();
+    for bucket in window {
+        packets += bucket.packets;
+        bytes += bucket.bytes;
+        for (protocol, count) in &bucket.protocols {
+            *protocols.entry(protocol).or_default() += count;
+        }
+    }
+
+    let span = bucket_length.as_secs_f64() * (now_index + 1).saturating_sub(window_start) as f64;
+    let rate = |count: u64| match span > 0.0 {
+        true => count as f64 / span,
+        false => 0.0,
+    };
+
+    Throughput {
+        packets_per_second: rate(packets),
+        bytes_per_second: rate(bytes),
+        top_protocol: protocols
+            .into_iter()
+            .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+            .map(|(protocol, _)| protocol),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{window_throughput, Bucket, ThroughputMeter};
+
+    #[test]
+    fn rate_over_sliding_window() {
+        let mut meter = ThroughputMeter::new(Duration::from_secs(1), 10);
+        let start = UNIX_EPOCH + Duration::from_secs(1_000);
+
+        // 20 packets of 100 bytes per second during 15 s, then a late one out of the window
+        for i in 0..15 * 20 {
+            let timestamp = start + Duration::from_millis(50 * i);
+            meter.record(timestamp, 100, Some("TCP"));
+        }
+        meter.record(start, 42, Some("ARP"));
+
+        let throughput = meter.throughput(start + Duration::from_millis(14_999));
+        assert_eq!(throughput.packets_per_second, 20.0);
+        assert_eq!(throughput.bytes_per_second, 2000.0);
+        assert_eq!(throughput.top_protocol, Some("TCP"));
+
+        // After 2 s of capture, the rates are over 2 s only
+        let mut young_meter = ThroughputMeter::default();
+        young_meter.record(start, 500, None);
+        young_meter.record(start + Duration::from_millis(1_200), 500, None);
+        let throughput = young_meter.throughput(start + Duration::from_millis(1_500));
+        assert_eq!(throughput.packets_per_second, 1.0);
+        assert_eq!(throughput.bytes_per_second, 500.0);
+        assert_eq!(throughput.top_protocol, None);
+    }
+
+    #[test]
+    fn window_throughput_of_buckets() {
+        let bucket = |index, packets, bytes| Bucket {
+            index,
+            packets,
+            bytes,
+            protocols: [("UDP", packets)].into_iter().collect(),
+        };
+        let buckets = [bucket(10, 4, 400), bucket(12, 6, 600), bucket(15, 10, 1000)];
+
+        // Window 11..=15: the bucket 10 is out
+        let throughput = window_throughput(&buckets, 15, 0, 5, Duration::from_secs(1));
+        assert_eq!(throughput.packets_per_second, 16.0 / 5.0);
+        assert_eq!(throughput.bytes_per_second, 1600.0 / 5.0);
+
+        // Half-second buckets
+        let throughput = window_throughput(&buckets, 12, 10, 5, Duration::from_millis(500));
+        assert_eq!(throughput.packets_per_second, 10.0 / 1.5);
+        assert_eq!(throughput.top_protocol, Some("UDP"));
+    }
+}
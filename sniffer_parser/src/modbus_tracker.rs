@@ -0,0 +1,251 @@
+//! Modbus TCP transaction tracking
+//!
+//! Requests are recorded with their capture timestamp, keyed by client and server endpoints and
+//! by the transaction ID of their MBAP header; the response of the same transaction gets its
+//! latency attached, and a response matching no pending request is flagged as unmatched.
+//! Requests left unanswered for longer than the timeout, or replaced by a request reusing their
+//! transaction ID, are counted as orphaned
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+use crate::serializable_packet::util::{get_dest_endpoint, get_source_endpoint};
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+/// Identifier of a Modbus TCP transaction, shared by its request and response
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModbusTransactionKey {
+    pub client: (IpAddr, u16),
+    pub server: (IpAddr, u16),
+    pub transaction_id: u16,
+}
+
+/// Pending Modbus TCP requests, fed with every parsed packet in capture order
+#[derive(Debug)]
+pub struct ModbusTransactionTracker {
+    timeout: Duration,
+    requests: HashMap<ModbusTransactionKey, SystemTime>,
+    orphaned: usize,
+}
+
+impl ModbusTransactionTracker {
+    /// Build a tracker orphaning the requests unanswered after `timeout`
+    pub fn new(timeout: Duration) -> Self {
+        ModbusTransactionTracker {
+            timeout,
+            requests: HashMap::new(),
+            orphaned: 0,
+        }
+    }
+
+    /// Record a request, or attach its latency to a response; packets without timestamp are
+    /// ignored
+    pub fn update(&mut self, packet: &mut ParsedPacket) {
+        let timestamp = match packet.get_timestamp() {
+            Some(timestamp) => timestamp,
+            None => return,
+        };
+        self.expire(timestamp);
+
+        let source = get_source_endpoint(packet);
+        let dest = get_dest_endpoint(packet);
+
+        let modbus_packet = packet.layers_mut().find_map(|layer| match layer {
+            SerializablePacket::ModbusPacket(modbus_packet) => Some(modbus_packet),
+            _ => None,
+        });
+        let (modbus_packet, source, dest) = match (modbus_packet, source, dest) {
+            (Some(modbus_packet), Some(source), Some(dest)) => (modbus_packet, source, dest),
+            _ => return,
+        };
+        let transaction_id = match modbus_packet.transaction_id {
+            Some(transaction_id) => transaction_id,
+            None => return,
+        };
+
+        let (client, server) = match modbus_packet.is_response {
+            true => (dest, source),
+            false => (source, dest),
+        };
+        let key = ModbusTransactionKey {
+            client,
+            server,
+            transaction_id,
+        };
+
+        match modbus_packet.is_response {
+            true => match self.requests.remove(&key) {
+                Some(request_timestamp) => {
+                    let latency = timestamp
+                        .duration_since(request_timestamp)
+                        .unwrap_or_default();
+                    modbus_packet.response_time_ms = Some(latency.as_secs_f64() * 1000.0);
+                }
+                None => modbus_packet.is_unmatched = true,
+            },
+            false => {
+                if self.requests.insert(key, timestamp).is_some() {
+                    self.orphaned += 1;
+                }
+            }
+        }
+    }
+
+    /// Orphan the requests older than the timeout at the given time
+    pub fn expire(&mut self, now: SystemTime) {
+        let timeout = self.timeout;
+        let pending = self.requests.len();
+        self.requests.retain(|_, request_timestamp| {
+            now.duration_since(*request_timestamp)
+                .map_or(true, |elapsed| elapsed <= timeout)
+        });
+        self.orphaned += pending - self.requests.len();
+    }
+
+    /// Get the number of requests waiting for their response
+    pub fn pending(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Get the number of requests never answered
+    pub fn orphaned(&self) -> usize {
+        self.orphaned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use pnet::packet::tcp::TcpFlags;
+
+    use super::ModbusTransactionTracker;
+    use crate::serializable_packet::application::SerializableModbusPacket;
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+    use crate::test_util::{build_test_tcp_packet, timestamped, CLIENT, SERVER};
+
+    #[test]
+    fn read_holding_registers_transaction() {
+        let mut tracker = ModbusTransactionTracker::new(Duration::from_secs(10));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        tracker.update(&mut build_test_modbus_packet(
+            false,
+            &read_holding_registers_request(1),
+            start,
+        ));
+        tracker.update(&mut build_test_modbus_packet(
+            false,
+            &read_holding_registers_request(2),
+            start + Duration::from_millis(5),
+        ));
+        assert_eq!(tracker.pending(), 2);
+
+        // The second transaction is answered first
+        let mut response = build_test_modbus_packet(
+            true,
+            &read_holding_registers_response(2),
+            start + Duration::from_millis(25),
+        );
+        tracker.update(&mut response);
+        assert_eq!(tracker.pending(), 1);
+
+        let modbus_packet = get_modbus_packet(&response);
+        assert_eq!(modbus_packet.function_code, 3);
+        assert_eq!(modbus_packet.transaction_id, Some(2));
+        assert!(modbus_packet.is_response);
+        assert!(!modbus_packet.is_unmatched);
+        assert!((modbus_packet.response_time_ms.unwrap() - 20.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn orphaned_and_unmatched_transactions() {
+        let mut tracker = ModbusTransactionTracker::new(Duration::from_secs(10));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        tracker.update(&mut build_test_modbus_packet(
+            false,
+            &read_holding_registers_request(1),
+            start,
+        ));
+        let mut response = build_test_modbus_packet(
+            true,
+            &read_holding_registers_response(1),
+            start + Duration::from_secs(11),
+        );
+        tracker.update(&mut response);
+        assert_eq!((tracker.pending(), tracker.orphaned()), (0, 1));
+
+        let modbus_packet = get_modbus_packet(&response);
+        assert!(modbus_packet.is_unmatched);
+        assert_eq!(modbus_packet.response_time_ms, None);
+    }
+
+    #[test]
+    fn exception_response_matched() {
+        let mut tracker = ModbusTransactionTracker::new(Duration::from_secs(10));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        tracker.update(&mut build_test_modbus_packet(
+            false,
+            &read_holding_registers_request(1),
+            start,
+        ));
+        // Illegal Data Address exception to Read Holding Registers
+        let mut response = build_test_modbus_packet(
+            true,
+            &[0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x01, 0x83, 0x02],
+            start + Duration::from_millis(8),
+        );
+        tracker.update(&mut response);
+        assert_eq!((tracker.pending(), tracker.orphaned()), (0, 0));
+
+        let modbus_packet = get_modbus_packet(&response);
+        assert_eq!(modbus_packet.function_code, 0x83);
+        assert_eq!(modbus_packet.exception_code, Some(0x02));
+        assert!(!modbus_packet.is_unmatched);
+        assert!((modbus_packet.response_time_ms.unwrap() - 8.0).abs() < 1e-6);
+    }
+
+    ///////////////////// Utils
+
+    /// Read Holding Registers request, for 2 registers from address 0 of unit 1
+    fn read_holding_registers_request(transaction_id: u16) -> Vec<u8> {
+        let mut request = transaction_id.to_be_bytes().to_vec();
+        request.extend_from_slice(&[0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x02]);
+        request
+    }
+
+    /// Read Holding Registers response, with 2 registers of unit 1
+    fn read_holding_registers_response(transaction_id: u16) -> Vec<u8> {
+        let mut response = transaction_id.to_be_bytes().to_vec();
+        response.extend_from_slice(&[
+            0x00, 0x00, 0x00, 0x07, 0x01, 0x03, 0x04, 0x00, 0x0a, 0x00, 0x0b,
+        ]);
+        response
+    }
+
+    fn get_modbus_packet(packet: &ParsedPacket) -> &SerializableModbusPacket {
+        match packet.get_application_layer_packet() {
+            Some(SerializablePacket::ModbusPacket(modbus_packet)) => modbus_packet,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Build a segment from the client 10.10.10.10:4444 to the server 11.11.11.11:502, or in the
+    /// other direction for a response
+    fn build_test_modbus_packet(
+        is_response: bool,
+        payload: &[u8],
+        timestamp: SystemTime,
+    ) -> ParsedPacket {
+        let (source, destination) = match is_response {
+            true => ((SERVER, 502), (CLIENT, 4444)),
+            false => ((CLIENT, 4444), (SERVER, 502)),
+        };
+        let flags = TcpFlags::ACK | TcpFlags::PSH;
+        let packet = build_test_tcp_packet(source, destination, flags, 0, payload);
+        timestamped(packet, timestamp)
+    }
+}
@@ -0,0 +1,152 @@
+//! MessagePack export of parsed packets
+//!
+//! Each packet is serialized as a MessagePack map, with the field names and the human-readable
+//! representations (addresses as strings) of the JSON output, in a record prefixed with its
+//! big-endian 4-byte length. `ParsedPacket` only implements `Serialize`: the records can't be
+//! read back as packets, only as untyped values such as `serde_json::Value`, equal to the JSON
+//! output of the packets
+
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::serializable_packet::ParsedPacket;
+
+const RECORD_LENGTH_PREFIX: usize = 4;
+
+/// Write a parsed packet as a length-delimited MessagePack record
+pub fn write_msgpack_record<W: Write>(writer: &mut W, packet: &ParsedPacket) -> io::Result<()> {
+    let mut record = vec![];
+    let mut serializer = rmp_serde::Serializer::new(&mut record)
+        .with_struct_map()
+        .with_human_readable();
+    packet
+        .serialize(&mut serializer)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let length = u32::try_from(record.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "record too long"))?;
+    writer.write_all(&length.to_be_bytes())?;
+    writer.write_all(&record)
+}
+
+/// Iterate over the length-delimited MessagePack records of a reader
+pub fn read_msgpack_records<R: Read, T: DeserializeOwned>(reader: R) -> MsgpackRecords<R, T> {
+    MsgpackRecords {
+        reader,
+        record_type: PhantomData,
+    }
+}
+
+/// Iterator over the records of a MessagePack export, ending at the end of the reader
+pub struct MsgpackRecords<R, T> {
+    reader: R,
+    record_type: PhantomData<T>,
+}
+
+impl<R: Read, T: DeserializeOwned> MsgpackRecords<R, T> {
+    /// Read the next record, `None` at the end of the export
+    fn next_record(&mut self) -> io::Result<Option<T>> {
+        let mut length = [0u8; RECORD_LENGTH_PREFIX];
+        let read = read_fully(&mut self.reader, &mut length)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if read < RECORD_LENGTH_PREFIX {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let length = u32::from_be_bytes(length) as usize;
+        let mut record = Vec::with_capacity(length.min(u16::MAX as usize));
+        (&mut self.reader)
+            .take(length as u64)
+            .read_to_end(&mut record)?;
+        if record.len() < length {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let mut deserializer =
+            rmp_serde::Deserializer::new(record.as_slice()).with_human_readable();
+        T::deserialize(&mut deserializer)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl<R: Read, T: DeserializeOwned> Iterator for MsgpackRecords<R, T> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// Fill a buffer from a reader, getting the number of bytes read before its end
+fn read_fully<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]) {
+            Ok(0) => break,
+            Ok(count) => read += count,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use serde_json::Value;
+
+    use super::{read_msgpack_records, write_msgpack_record};
+    use crate::serializable_packet::ParsedPacket;
+    use crate::test_util::{self, CLIENT, SERVER};
+
+    #[test]
+    fn msgpack_records_read_as_json_values() {
+        let mut packet = build_test_udp_packet();
+        packet.set_timestamp(Some(UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_000)));
+
+        let mut export = vec![];
+        write_msgpack_record(&mut export, &packet).unwrap();
+        write_msgpack_record(&mut export, &packet).unwrap();
+
+        let records: Vec<Value> = read_msgpack_records(export.as_slice())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        // Untyped values, the packets not being deserializable
+        let json = serde_json::to_value(&packet).unwrap();
+        assert_eq!(records, vec![json.clone(), json]);
+        assert_eq!(
+            records[0]["networkLayerPacket"]["packet"]["source"],
+            "10.10.10.10"
+        );
+    }
+
+    #[test]
+    fn truncated_msgpack_record() {
+        let mut export = vec![];
+        write_msgpack_record(&mut export, &build_test_udp_packet()).unwrap();
+
+        let mut records = read_msgpack_records::<_, Value>(&export[..export.len() - 1]);
+        assert!(records.next().unwrap().is_err());
+        assert!(read_msgpack_records::<_, Value>(&export[..2])
+            .next()
+            .unwrap()
+            .is_err());
+        assert!(read_msgpack_records::<_, Value>(&[][..]).next().is_none());
+    }
+
+    ///////////////////// Utils
+
+    /// Build a UDP datagram from 10.10.10.10:4444 to 11.11.11.11:9999
+    fn build_test_udp_packet() -> ParsedPacket {
+        test_util::build_test_udp_packet((CLIENT, 4444), (SERVER, 9999), &[])
+    }
+}
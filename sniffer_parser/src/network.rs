@@ -1,28 +1,64 @@
 //! IPv4, IPv6, and ARP Packet parsing
 
 use pnet::packet::arp::ArpPacket;
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
 use pnet::packet::ipv4::Ipv4Packet;
 use pnet::packet::ipv6::Ipv6Packet;
 use pnet::packet::Packet;
+use std::cell::Cell;
 use std::net::IpAddr;
 
 use super::*;
+use crate::profile::{profiled, ProfileLayer};
 use crate::serializable_packet::network::{
     SerializableArpPacket, SerializableIpv4Packet, SerializableIpv6Packet,
 };
+use crate::serializable_packet::MalformedReason;
 use crate::transport::*;
 
+thread_local!(
+    /// Encapsulation depth of the IP packet being parsed, for the tunnels above the network layer
+    static IP_DEPTH: Cell<usize> = const { Cell::new(0) };
+);
+
 /// Build a IPv4 packet from a data-link packet, save it in a Parsed Packet
 pub fn handle_ipv4_packet(packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    profiled(ProfileLayer::Network, || {
+        parse_ipv4(packet, 0, parsed_packet)
+    });
+}
+
+/// Build a IPv6 packet from a data-link packet, save it in a Parsed Packet
+pub fn handle_ipv6_packet(packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    profiled(ProfileLayer::Network, || {
+        parse_ipv6(packet, 0, parsed_packet)
+    });
+}
+
+/// Build a IPv4 packet encapsulated in `depth` network-layer packets, save it in a Parsed Packet
+pub(crate) fn parse_ipv4(packet: &[u8], depth: usize, parsed_packet: &mut ParsedPacket) {
+    if exceeds_max_depth(depth, parsed_packet) {
+        return;
+    }
+    IP_DEPTH.with(|ip_depth| ip_depth.set(depth));
+
     let header = Ipv4Packet::new(packet);
     if let Some(header) = header {
-        parsed_packet.set_network_layer_packet(Some(SerializablePacket::Ipv4Packet(
-            SerializableIpv4Packet::from(&header),
-        )));
+        let mut ipv4_packet = SerializableIpv4Packet::from(&header);
+        let protocol = header.get_next_level_protocol();
+        if is_tunnel_protocol(protocol) {
+            ipv4_packet.encapsulated =
+                parse_tunneled_packet(protocol, header.payload(), depth + 1, parsed_packet);
+            parsed_packet
+                .set_network_layer_packet(Some(SerializablePacket::Ipv4Packet(ipv4_packet)));
+            return;
+        }
+
+        parsed_packet.set_network_layer_packet(Some(SerializablePacket::Ipv4Packet(ipv4_packet)));
         handle_transport_protocol(
             IpAddr::V4(header.get_source()),
             IpAddr::V4(header.get_destination()),
-            header.get_next_level_protocol(),
+            protocol,
             header.payload(),
             parsed_packet,
         );
@@ -34,17 +70,35 @@ pub fn handle_ipv4_packet(packet: &[u8], parsed_packet: &mut ParsedPacket) {
     }
 }
 
-/// Build a IPv6 packet from a data-link packet, save it in a Parsed Packet
-pub fn handle_ipv6_packet(packet: &[u8], parsed_packet: &mut ParsedPacket) {
+/// Build a IPv6 packet encapsulated in `depth` network-layer packets, save it in a Parsed Packet
+pub(crate) fn parse_ipv6(packet: &[u8], depth: usize, parsed_packet: &mut ParsedPacket) {
+    if exceeds_max_depth(depth, parsed_packet) {
+        return;
+    }
+    IP_DEPTH.with(|ip_depth| ip_depth.set(depth));
+
+    // The fragment completing a datagram stands for it
+    if let Some(datagram) = reassembled_ipv6_datagram(packet) {
+        return parse_ipv6(&datagram, depth, parsed_packet);
+    }
+
     let header = Ipv6Packet::new(packet);
     if let Some(header) = header {
-        parsed_packet.set_network_layer_packet(Some(SerializablePacket::Ipv6Packet(
-            SerializableIpv6Packet::from(&header),
-        )));
+        let mut ipv6_packet = SerializableIpv6Packet::from(&header);
+        let protocol = header.get_next_header();
+        if is_tunnel_protocol(protocol) {
+            ipv6_packet.encapsulated =
+                parse_tunneled_packet(protocol, header.payload(), depth + 1, parsed_packet);
+            parsed_packet
+                .set_network_layer_packet(Some(SerializablePacket::Ipv6Packet(ipv6_packet)));
+            return;
+        }
+
+        parsed_packet.set_network_layer_packet(Some(SerializablePacket::Ipv6Packet(ipv6_packet)));
         handle_transport_protocol(
             IpAddr::V6(header.get_source()),
             IpAddr::V6(header.get_destination()),
-            header.get_next_header(),
+            protocol,
             header.payload(),
             parsed_packet,
         );
@@ -56,6 +110,63 @@ pub fn handle_ipv6_packet(packet: &[u8], parsed_packet: &mut ParsedPacket) {
     }
 }
 
+/// Check if an IP protocol number carries a tunneled IP packet: IPv4 (IP-in-IP) or IPv6 (6in4)
+fn is_tunnel_protocol(protocol: IpNextHeaderProtocol) -> bool {
+    matches!(
+        protocol,
+        IpNextHeaderProtocols::Ipv4 | IpNextHeaderProtocols::Ipv6
+    )
+}
+
+/// Parse the IP packet tunneled in the payload of another one, getting its network layer; its
+/// transport and application layers are saved in the Parsed Packet
+fn parse_tunneled_packet(
+    protocol: IpNextHeaderProtocol,
+    packet: &[u8],
+    depth: usize,
+    parsed_packet: &mut ParsedPacket,
+) -> Option<Box<SerializablePacket>> {
+    match protocol {
+        IpNextHeaderProtocols::Ipv4 => parse_ipv4(packet, depth, parsed_packet),
+        _ => parse_ipv6(packet, depth, parsed_packet),
+    }
+
+    parsed_packet
+        .get_network_layer_packet()
+        .cloned()
+        .map(Box::new)
+}
+
+/// Parse the IPv4 or IPv6 packet tunneled in an application-layer payload (GTP-U), one level
+/// deeper than the IP packet carrying the application layer, getting its network layer; `None`
+/// if the payload is not an IP packet
+pub(crate) fn parse_tunneled_payload(packet: &[u8]) -> Option<SerializablePacket> {
+    let outer_depth = IP_DEPTH.with(Cell::get);
+    let mut tunneled_packet = ParsedPacket::new(0);
+    match packet.first().map(|byte| byte >> 4) {
+        Some(4) => parse_ipv4(packet, outer_depth + 1, &mut tunneled_packet),
+        Some(6) => parse_ipv6(packet, outer_depth + 1, &mut tunneled_packet),
+        _ => return None,
+    }
+    IP_DEPTH.with(|ip_depth| ip_depth.set(outer_depth));
+
+    tunneled_packet.get_network_layer_packet().cloned()
+}
+
+/// Check if a packet is encapsulated deeper than the maximum encapsulation depth, reporting it as
+/// malformed in the network layer
+fn exceeds_max_depth(depth: usize, parsed_packet: &mut ParsedPacket) -> bool {
+    if depth <= max_encapsulation_depth() {
+        return false;
+    }
+
+    debug!("Malformed Packet: encapsulation depth: {}", depth);
+    parsed_packet.set_network_layer_packet(Some(SerializablePacket::MalformedPacket(
+        MalformedReason::MaxDepthExceeded.to_string(),
+    )));
+    true
+}
+
 /// Build a ARP packet from a data-link packet, save it in a Parsed Packet
 pub fn handle_arp_packet(
     packet: &[u8],
@@ -63,26 +174,28 @@ pub fn handle_arp_packet(
     dest: MacAddr,
     parsed_packet: &mut ParsedPacket,
 ) {
-    let header = ArpPacket::new(packet);
-    if let Some(header) = header {
-        debug!(
-            "ARP packet: {}({}) > {}({}); operation: {:?}",
-            source,
-            header.get_sender_proto_addr(),
-            dest,
-            header.get_target_proto_addr(),
-            header.get_operation()
-        );
-
-        parsed_packet.set_network_layer_packet(Some(SerializablePacket::ArpPacket(
-            SerializableArpPacket::from(&header),
-        )));
-    } else {
-        debug!("Malformed ARP Packet");
-        parsed_packet.set_network_layer_packet(Some(SerializablePacket::MalformedPacket(
-            "Malformed ARP Packet".to_string(),
-        )));
-    }
+    profiled(ProfileLayer::Network, || {
+        let header = ArpPacket::new(packet);
+        if let Some(header) = header {
+            debug!(
+                "ARP packet: {}({}) > {}({}); operation: {:?}",
+                source,
+                header.get_sender_proto_addr(),
+                dest,
+                header.get_target_proto_addr(),
+                header.get_operation()
+            );
+
+            parsed_packet.set_network_layer_packet(Some(SerializablePacket::ArpPacket(
+                SerializableArpPacket::from(&header),
+            )));
+        } else {
+            debug!("Malformed ARP Packet");
+            parsed_packet.set_network_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed ARP Packet".to_string(),
+            )));
+        }
+    })
 }
 
 #[cfg(test)]
@@ -91,16 +204,21 @@ pub mod tests {
 
     use pnet::packet::arp::{ArpHardwareTypes, ArpOperations, ArpPacket, MutableArpPacket};
     use pnet::packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
-    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
     use pnet::packet::ipv4::{Ipv4Packet, MutableIpv4Packet};
     use pnet::packet::ipv6::{Ipv6Packet, MutableIpv6Packet};
     use pnet::packet::Packet;
     use pnet::util::MacAddr;
 
-    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
-    use crate::{handle_ipv4_packet, handle_ipv6_packet};
+    use crate::serializable_packet::util::hexdump;
+    use crate::serializable_packet::{MalformedReason, ParsedPacket, SerializablePacket};
+    use crate::{
+        handle_ipv4_packet, handle_ipv6_packet, set_max_encapsulation_depth, set_payload_retention,
+        DEFAULT_MAX_ENCAPSULATION_DEPTH,
+    };
 
-    use super::handle_arp_packet;
+    use super::{handle_arp_packet, parse_ipv4};
+    use crate::serializable_packet::network::{dscp_to_string, ecn_to_string, AddressClass};
 
     #[test]
     fn valid_arp_packet() {
@@ -151,12 +269,66 @@ pub mod tests {
                     new_arp_packet.target_proto_addr,
                     arp_packet.get_target_proto_addr()
                 );
+                assert!(!new_arp_packet.is_gratuitous);
+                assert!(!new_arp_packet.is_announcement);
+                assert!(!new_arp_packet.is_probe);
                 assert_eq!(new_arp_packet.length, arp_packet.payload().len());
             }
             _ => unreachable!(),
         }
     }
 
+    #[test]
+    fn gratuitous_arp_reply() {
+        let mut ethernet_buffer = [0u8; 42];
+        let ethernet_packet = build_test_gratuitous_arp_packet(ethernet_buffer.as_mut_slice());
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_arp_packet(
+            ethernet_packet.payload(),
+            ethernet_packet.get_source(),
+            ethernet_packet.get_destination(),
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_network_layer_packet().unwrap() {
+            SerializablePacket::ArpPacket(new_arp_packet) => {
+                assert_eq!(
+                    new_arp_packet.operation,
+                    format!("ARP Reply ({})", ArpOperations::Reply.0)
+                );
+                assert!(new_arp_packet.is_gratuitous);
+                assert!(new_arp_packet.is_announcement);
+                assert!(new_arp_packet.to_string().contains("Gratuitous: true"));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn arp_probe() {
+        let mut ethernet_buffer = [0u8; 42];
+        let ethernet_packet = build_test_arp_probe_packet(ethernet_buffer.as_mut_slice());
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_arp_packet(
+            ethernet_packet.payload(),
+            ethernet_packet.get_source(),
+            ethernet_packet.get_destination(),
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_network_layer_packet().unwrap() {
+            SerializablePacket::ArpPacket(new_arp_packet) => {
+                assert!(new_arp_packet.is_probe);
+                assert!(!new_arp_packet.is_gratuitous);
+                assert!(!new_arp_packet.is_announcement);
+                assert!(new_arp_packet.to_string().contains("Probe: true"));
+            }
+            _ => unreachable!(),
+        }
+    }
+
     #[test]
     fn malformed_arp_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
@@ -213,6 +385,41 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn ip_packet_serialized_field_names() {
+        let mut ethernet_buffer = [0u8; 42];
+        let ethernet_packet = build_test_ip_packet(ethernet_buffer.as_mut_slice());
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ipv4_packet(ethernet_packet.payload(), &mut parsed_packet);
+
+        let json = serde_json::to_value(&parsed_packet).unwrap();
+        assert_eq!(json["networkLayerPacket"]["type"], "Ipv4Packet");
+
+        let fields = json["networkLayerPacket"]["packet"].as_object().unwrap();
+        assert!(fields.contains_key("headerLength"));
+        assert!(fields.contains_key("nextLevelProtocol"));
+        assert!(fields.contains_key("fragmentOffset"));
+        assert!(!fields.contains_key("header_length"));
+    }
+
+    #[test]
+    fn ip_packet_explanation() {
+        let mut ethernet_buffer = [0u8; 42];
+        let ethernet_packet = build_test_ip_packet(ethernet_buffer.as_mut_slice());
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ipv4_packet(ethernet_packet.payload(), &mut parsed_packet);
+
+        let explanation = parsed_packet.get_network_layer_packet().unwrap().explain();
+        assert_eq!(explanation.len(), 13);
+        assert!(explanation.contains(&(
+            "TTL".to_owned(),
+            "2".to_owned(),
+            "hops remaining before discard".to_owned()
+        )));
+    }
+
     #[test]
     fn malformed_ip_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
@@ -224,6 +431,174 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn ip_packet_dscp_and_ecn_names() {
+        let mut ethernet_buffer = [0u8; 42];
+        let ethernet_packet = build_test_ip_packet(ethernet_buffer.as_mut_slice());
+        let mut ip_buffer = ethernet_packet.payload().to_vec();
+        let mut ip_packet = MutableIpv4Packet::new(&mut ip_buffer).unwrap();
+        ip_packet.set_dscp(46);
+        ip_packet.set_ecn(3);
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ipv4_packet(ip_packet.packet(), &mut parsed_packet);
+
+        match parsed_packet.get_network_layer_packet().unwrap() {
+            SerializablePacket::Ipv4Packet(new_ip_packet) => {
+                assert_eq!(new_ip_packet.dscp_name, "EF");
+                assert_eq!(new_ip_packet.ecn_name, "CE");
+                assert!(new_ip_packet.to_string().contains("DSCP: 46 (EF)"));
+                assert!(new_ip_packet.to_string().contains("ECN: 3 (CE)"));
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(dscp_to_string(34), "AF41");
+        assert_eq!(dscp_to_string(48), "CS6");
+        assert_eq!(ecn_to_string(2), "ECT(0)");
+    }
+
+    #[test]
+    fn ip_address_classes() {
+        let mut ethernet_buffer = [0u8; 42];
+        let ethernet_packet = build_test_ip_packet(ethernet_buffer.as_mut_slice());
+        let mut ip_buffer = ethernet_packet.payload().to_vec();
+        let mut ip_packet = MutableIpv4Packet::new(&mut ip_buffer).unwrap();
+        ip_packet.set_source(Ipv4Addr::new(10, 0, 0, 1));
+        ip_packet.set_destination(Ipv4Addr::new(224, 0, 0, 1));
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ipv4_packet(ip_packet.packet(), &mut parsed_packet);
+
+        match parsed_packet.get_network_layer_packet().unwrap() {
+            SerializablePacket::Ipv4Packet(new_ip_packet) => {
+                assert_eq!(new_ip_packet.source_address_class, AddressClass::Private);
+                assert_eq!(
+                    new_ip_packet.destination_address_class,
+                    AddressClass::Multicast
+                );
+                assert!(new_ip_packet
+                    .to_string()
+                    .contains("Destination: 224.0.0.1 (Multicast)"));
+            }
+            _ => unreachable!(),
+        }
+
+        for (address, class) in [
+            ("172.31.255.1", AddressClass::Private),
+            ("127.0.0.1", AddressClass::Loopback),
+            ("169.254.1.1", AddressClass::LinkLocal),
+            ("255.255.255.255", AddressClass::Broadcast),
+            ("192.0.2.1", AddressClass::Reserved),
+            ("100.64.0.1", AddressClass::Reserved),
+            ("8.8.8.8", AddressClass::Public),
+        ] {
+            assert_eq!(AddressClass::from_ipv4(address.parse().unwrap()), class);
+        }
+        for (address, class) in [
+            ("fd00::1", AddressClass::UniqueLocal),
+            ("fe80::1", AddressClass::LinkLocal),
+            ("ff02::1", AddressClass::Multicast),
+            ("::1", AddressClass::Loopback),
+            ("::", AddressClass::Reserved),
+            ("2001:db8::1", AddressClass::Reserved),
+            ("::ffff:192.168.1.1", AddressClass::Private),
+            ("2a00:1450::1", AddressClass::Public),
+        ] {
+            assert_eq!(AddressClass::from_ipv6(address.parse().unwrap()), class);
+        }
+    }
+
+    #[test]
+    fn max_encapsulation_depth_exceeded() {
+        let mut ethernet_buffer = [0u8; 42];
+        let ethernet_packet = build_test_ip_packet(ethernet_buffer.as_mut_slice());
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        parse_ipv4(
+            ethernet_packet.payload(),
+            DEFAULT_MAX_ENCAPSULATION_DEPTH + 1,
+            &mut parsed_packet,
+        );
+        match parsed_packet.get_network_layer_packet().unwrap() {
+            SerializablePacket::MalformedPacket(str) => {
+                assert_eq!(str, &MalformedReason::MaxDepthExceeded.to_string())
+            }
+            _ => unreachable!(),
+        }
+        assert!(parsed_packet.get_transport_layer_packet().is_none());
+
+        set_max_encapsulation_depth(DEFAULT_MAX_ENCAPSULATION_DEPTH + 1);
+        let mut parsed_packet = ParsedPacket::new(0);
+        parse_ipv4(
+            ethernet_packet.payload(),
+            DEFAULT_MAX_ENCAPSULATION_DEPTH + 1,
+            &mut parsed_packet,
+        );
+        set_max_encapsulation_depth(DEFAULT_MAX_ENCAPSULATION_DEPTH);
+        assert!(matches!(
+            parsed_packet.get_network_layer_packet(),
+            Some(SerializablePacket::Ipv4Packet(_))
+        ));
+    }
+
+    #[test]
+    fn ipv6_in_ipv4_tunnel() {
+        let packet = build_test_6in4_packet();
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ipv4_packet(&packet, &mut parsed_packet);
+
+        match parsed_packet.get_network_layer_packet().unwrap() {
+            SerializablePacket::Ipv4Packet(ipv4_packet) => {
+                assert_eq!(ipv4_packet.next_level_protocol, "Ipv6 (41)");
+                match ipv4_packet.encapsulated.as_deref() {
+                    Some(SerializablePacket::Ipv6Packet(ipv6_packet)) => {
+                        assert_eq!(
+                            ipv6_packet.source,
+                            "2001:db8::1".parse::<Ipv6Addr>().unwrap()
+                        );
+                        assert_eq!(ipv6_packet.payload_length, 8);
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!(),
+        }
+        assert!(matches!(
+            parsed_packet.get_transport_layer_packet(),
+            Some(SerializablePacket::UdpPacket(_))
+        ));
+    }
+
+    #[test]
+    fn nested_ip_in_ip_max_depth() {
+        // IPv4 packets tunneled in each other, one level deeper than allowed
+        let mut packet = build_test_6in4_packet();
+        for _ in 0..DEFAULT_MAX_ENCAPSULATION_DEPTH {
+            packet = build_test_ip_in_ip_packet(&packet);
+        }
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ipv4_packet(&packet, &mut parsed_packet);
+
+        let mut network_layer = parsed_packet.get_network_layer_packet().unwrap();
+        for _ in 0..=DEFAULT_MAX_ENCAPSULATION_DEPTH {
+            network_layer = match network_layer {
+                SerializablePacket::Ipv4Packet(ipv4_packet) => {
+                    ipv4_packet.encapsulated.as_deref().unwrap()
+                }
+                _ => unreachable!(),
+            };
+        }
+        match network_layer {
+            SerializablePacket::MalformedPacket(str) => {
+                assert_eq!(str, &MalformedReason::MaxDepthExceeded.to_string())
+            }
+            _ => unreachable!(),
+        }
+        assert!(parsed_packet.get_transport_layer_packet().is_none());
+    }
+
     #[test]
     fn valid_ipv6_packet() {
         let mut ethernet_buffer = [0u8; 256];
@@ -262,6 +637,48 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn ipv4_payload_display() {
+        let payload = [0x11, 0x5c, 0x27, 0x0f, 0x00, 0x08, 0x00, 0x00];
+        let packet = build_test_tunnel_packet(IpNextHeaderProtocols::Udp, &payload);
+
+        set_payload_retention(true);
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ipv4_packet(&packet, &mut parsed_packet);
+        set_payload_retention(false);
+
+        let ipv4_packet = parsed_packet.get_network_layer_packet().unwrap();
+        let without_payload = format!("{}", ipv4_packet);
+        assert!(!without_payload.contains("Payload:"));
+        assert_eq!(
+            format!("{:#}", ipv4_packet),
+            expected_payload_display(&without_payload, &payload)
+        );
+    }
+
+    #[test]
+    fn ipv6_payload_display() {
+        let mut ethernet_buffer = [0u8; 256];
+        let ethernet_packet = build_test_ipv6_packet(ethernet_buffer.as_mut_slice());
+
+        set_payload_retention(true);
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ipv6_packet(ethernet_packet.payload(), &mut parsed_packet);
+        set_payload_retention(false);
+
+        let ipv6_packet = parsed_packet.get_network_layer_packet().unwrap();
+        let payload = Ipv6Packet::new(ethernet_packet.payload())
+            .unwrap()
+            .payload()
+            .to_vec();
+        let without_payload = format!("{}", ipv6_packet);
+        assert!(!without_payload.contains("Payload:"));
+        assert_eq!(
+            format!("{:#}", ipv6_packet),
+            expected_payload_display(&without_payload, &payload)
+        );
+    }
+
     #[test]
     fn malformed_ipv6_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
@@ -275,6 +692,15 @@ pub mod tests {
 
     ///////////////////// Utils
 
+    /// Expect the display without payload followed by the hexdump of the payload
+    fn expected_payload_display(without_payload: &str, payload: &[u8]) -> String {
+        let hexdump: String = hexdump(payload)
+            .lines()
+            .map(|line| format!("\n\t\t{}", line))
+            .collect();
+        format!("{}\n\tPayload:{}", without_payload, hexdump)
+    }
+
     fn build_test_arp_packet<'a>(ethernet_buffer: &'a mut [u8]) -> EthernetPacket<'a> {
         let mut ethernet_packet = MutableEthernetPacket::new(ethernet_buffer).unwrap();
 
@@ -300,6 +726,56 @@ pub mod tests {
         ethernet_packet.consume_to_immutable()
     }
 
+    fn build_test_gratuitous_arp_packet<'a>(ethernet_buffer: &'a mut [u8]) -> EthernetPacket<'a> {
+        let mut ethernet_packet = MutableEthernetPacket::new(ethernet_buffer).unwrap();
+
+        ethernet_packet.set_destination(MacAddr::broadcast());
+        ethernet_packet.set_source(MacAddr::new(10, 10, 10, 10, 10, 10));
+        ethernet_packet.set_ethertype(EtherTypes::Arp);
+
+        let mut arp_buffer = [0u8; 28];
+        let mut arp_packet = MutableArpPacket::new(&mut arp_buffer).unwrap();
+
+        arp_packet.set_hardware_type(ArpHardwareTypes::Ethernet);
+        arp_packet.set_protocol_type(EtherTypes::Ipv4);
+        arp_packet.set_operation(ArpOperations::Reply);
+
+        arp_packet.set_sender_hw_addr(MacAddr::new(10, 10, 10, 10, 10, 10));
+        arp_packet.set_sender_proto_addr(Ipv4Addr::new(10, 10, 10, 10));
+
+        arp_packet.set_target_hw_addr(MacAddr::broadcast());
+        arp_packet.set_target_proto_addr(Ipv4Addr::new(10, 10, 10, 10));
+
+        ethernet_packet.set_payload(arp_packet.packet());
+
+        ethernet_packet.consume_to_immutable()
+    }
+
+    fn build_test_arp_probe_packet<'a>(ethernet_buffer: &'a mut [u8]) -> EthernetPacket<'a> {
+        let mut ethernet_packet = MutableEthernetPacket::new(ethernet_buffer).unwrap();
+
+        ethernet_packet.set_destination(MacAddr::broadcast());
+        ethernet_packet.set_source(MacAddr::new(10, 10, 10, 10, 10, 10));
+        ethernet_packet.set_ethertype(EtherTypes::Arp);
+
+        let mut arp_buffer = [0u8; 28];
+        let mut arp_packet = MutableArpPacket::new(&mut arp_buffer).unwrap();
+
+        arp_packet.set_hardware_type(ArpHardwareTypes::Ethernet);
+        arp_packet.set_protocol_type(EtherTypes::Ipv4);
+        arp_packet.set_operation(ArpOperations::Request);
+
+        arp_packet.set_sender_hw_addr(MacAddr::new(10, 10, 10, 10, 10, 10));
+        arp_packet.set_sender_proto_addr(Ipv4Addr::UNSPECIFIED);
+
+        arp_packet.set_target_hw_addr(MacAddr::zero());
+        arp_packet.set_target_proto_addr(Ipv4Addr::new(10, 10, 10, 10));
+
+        ethernet_packet.set_payload(arp_packet.packet());
+
+        ethernet_packet.consume_to_immutable()
+    }
+
     fn build_test_ip_packet<'a>(ethernet_buffer: &'a mut [u8]) -> EthernetPacket<'a> {
         let mut ethernet_packet = MutableEthernetPacket::new(ethernet_buffer).unwrap();
 
@@ -353,4 +829,40 @@ pub mod tests {
 
         ethernet_packet.consume_to_immutable()
     }
+
+    /// Build a IPv4 packet from 192.0.2.1 to 192.0.2.2, tunneling a IPv6 packet carrying a UDP
+    /// datagram from [2001:db8::1]:4444 to [2001:db8::2]:9999
+    fn build_test_6in4_packet() -> Vec<u8> {
+        let mut ipv6_buffer = [0u8; 48];
+        ipv6_buffer[40..48].copy_from_slice(&[0x11, 0x5c, 0x27, 0x0f, 0x00, 0x08, 0x00, 0x00]);
+        let mut ipv6_packet = MutableIpv6Packet::new(&mut ipv6_buffer).unwrap();
+        ipv6_packet.set_version(6);
+        ipv6_packet.set_payload_length(8);
+        ipv6_packet.set_next_header(IpNextHeaderProtocols::Udp);
+        ipv6_packet.set_hop_limit(64);
+        ipv6_packet.set_source("2001:db8::1".parse().unwrap());
+        ipv6_packet.set_destination("2001:db8::2".parse().unwrap());
+
+        build_test_tunnel_packet(IpNextHeaderProtocols::Ipv6, &ipv6_buffer)
+    }
+
+    /// Build a IPv4 packet from 192.0.2.1 to 192.0.2.2, tunneling a IPv4 packet
+    fn build_test_ip_in_ip_packet(inner: &[u8]) -> Vec<u8> {
+        build_test_tunnel_packet(IpNextHeaderProtocols::Ipv4, inner)
+    }
+
+    fn build_test_tunnel_packet(protocol: IpNextHeaderProtocol, inner: &[u8]) -> Vec<u8> {
+        let mut ip_buffer = vec![0u8; 20 + inner.len()];
+        let mut ip_packet = MutableIpv4Packet::new(&mut ip_buffer).unwrap();
+        ip_packet.set_version(4);
+        ip_packet.set_header_length(5);
+        ip_packet.set_total_length((20 + inner.len()) as u16);
+        ip_packet.set_ttl(64);
+        ip_packet.set_next_level_protocol(protocol);
+        ip_packet.set_source(Ipv4Addr::new(192, 0, 2, 1));
+        ip_packet.set_destination(Ipv4Addr::new(192, 0, 2, 2));
+        ip_packet.set_payload(inner);
+
+        ip_buffer
+    }
 }
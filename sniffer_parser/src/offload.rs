@@ -0,0 +1,149 @@
+//! Checksum offload detection
+//!
+//! Packets captured on their way out of a host whose NIC computes the TCP and UDP checksums
+//! (checksum offload) carry a placeholder checksum, zero or garbage, which is not a corruption.
+//! Besides the zero checksum recognized while parsing, a source whose checksums are wrong every
+//! time is most likely the capturing host itself: real corruptions are occasional, and the
+//! packets received from the network are correct
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use crate::serializable_packet::util::get_source_ip;
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+/// Checksum statistics of the TCP and UDP packets sent by a host
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChecksumCounters {
+    pub valid: usize,
+    pub invalid: usize,
+}
+
+/// Tagger of the invalid checksums systematically sent by a host, fed with every parsed packet
+/// in capture order
+#[derive(Debug, Default)]
+pub struct ChecksumOffloadDetector {
+    sources: HashMap<IpAddr, ChecksumCounters>,
+}
+
+impl ChecksumOffloadDetector {
+    pub fn new() -> Self {
+        ChecksumOffloadDetector {
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Account the checksum of a TCP or UDP packet, flagging it as offloaded when invalid and
+    /// its source never sent a valid one
+    pub fn update(&mut self, packet: &mut ParsedPacket) {
+        let source = match get_source_ip(packet).and_then(|ip| ip.parse().ok()) {
+            Some(source) => source,
+            None => return,
+        };
+
+        let (checksum_valid, checksum_offload_suspected) =
+            match packet.layers_mut().find_map(|layer| match layer {
+                SerializablePacket::TcpPacket(tcp_packet) => Some((
+                    tcp_packet.checksum_valid,
+                    &mut tcp_packet.checksum_offload_suspected,
+                )),
+                SerializablePacket::UdpPacket(udp_packet) => Some((
+                    udp_packet.checksum_valid,
+                    &mut udp_packet.checksum_offload_suspected,
+                )),
+                _ => None,
+            }) {
+                Some(checksum) => checksum,
+                None => return,
+            };
+
+        let counters = self.sources.entry(source).or_default();
+        match checksum_valid {
+            true => counters.valid += 1,
+            false => {
+                counters.invalid += 1;
+                if counters.valid == 0 {
+                    *checksum_offload_suspected = true;
+                }
+            }
+        }
+    }
+
+    /// Get the checksum statistics of a source
+    pub fn counters(&self, source: IpAddr) -> ChecksumCounters {
+        self.sources.get(&source).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::packet::tcp::{self, MutableTcpPacket};
+
+    use super::{ChecksumCounters, ChecksumOffloadDetector};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+    use crate::test_util::{build_test_ipv4_frame, build_test_tcp_segment};
+
+    #[test]
+    fn systematically_wrong_source() {
+        let mut detector = ChecksumOffloadDetector::new();
+        let local = Ipv4Addr::new(10, 10, 10, 10);
+        let remote = Ipv4Addr::new(11, 11, 11, 11);
+
+        let mut outgoing = build_test_tcp_packet(local, remote, Some(0x1234));
+        let mut incoming = build_test_tcp_packet(remote, local, None);
+        detector.update(&mut outgoing);
+        detector.update(&mut incoming);
+        assert!(offload_suspected(&outgoing));
+        assert!(!offload_suspected(&incoming));
+
+        // Once a host sent a valid checksum, its invalid ones are real corruptions
+        let mut corrupted = build_test_tcp_packet(remote, local, Some(0x1234));
+        detector.update(&mut corrupted);
+        assert!(!offload_suspected(&corrupted));
+
+        assert_eq!(
+            detector.counters(IpAddr::V4(local)),
+            ChecksumCounters {
+                valid: 0,
+                invalid: 1
+            }
+        );
+        assert_eq!(
+            detector.counters(IpAddr::V4(remote)),
+            ChecksumCounters {
+                valid: 1,
+                invalid: 1
+            }
+        );
+    }
+
+    ///////////////////// Utils
+
+    fn offload_suspected(packet: &ParsedPacket) -> bool {
+        match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => {
+                tcp_packet.checksum_offload_suspected
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Build a TCP packet with the given checksum, or the right one
+    fn build_test_tcp_packet(
+        source: Ipv4Addr,
+        destination: Ipv4Addr,
+        checksum: Option<u16>,
+    ) -> ParsedPacket {
+        let mut tcp_buffer = build_test_tcp_segment(4444, 9999, 0, 0, 0, &[]);
+        let mut tcp_packet = MutableTcpPacket::new(&mut tcp_buffer).unwrap();
+        let checksum = checksum.unwrap_or_else(|| {
+            tcp::ipv4_checksum(&tcp_packet.to_immutable(), &source, &destination)
+        });
+        tcp_packet.set_checksum(checksum);
+
+        build_test_ipv4_frame(source, destination, IpNextHeaderProtocols::Tcp, &tcp_buffer)
+    }
+}
@@ -0,0 +1,165 @@
+//! OSPFv2 Packet parsing
+//!
+//! Every OSPF packet (RFC 2328, appendix A.3) starts with a 24-byte common header: version, type,
+//! length, router and area IDs, checksum and authentication. The body of the Hello packets, used
+//! to discover the neighbors and elect the designated routers, is parsed as well
+
+use std::net::IpAddr;
+
+use log::debug;
+
+use crate::serializable_packet::transport::{
+    ospf_auth_type_to_string, ospf_type_to_string, SerializableOspfHello, SerializableOspfPacket,
+};
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+use crate::transport::{read_ipv4_address, read_ipv4_addresses};
+
+const OSPF_HEADER_LENGTH: usize = 24;
+const OSPF_HELLO_LENGTH: usize = 20;
+
+/// OSPF Packet Types
+#[allow(non_snake_case)]
+pub mod OspfTypes {
+    pub const HELLO: u8 = 1;
+    pub const DATABASE_DESCRIPTION: u8 = 2;
+    pub const LINK_STATE_REQUEST: u8 = 3;
+    pub const LINK_STATE_UPDATE: u8 = 4;
+    pub const LINK_STATE_ACKNOWLEDGMENT: u8 = 5;
+}
+
+/// Build a OSPF packet from a network-layer packet, save it in a Parsed Packet
+pub fn handle_ospf_packet(
+    source: IpAddr,
+    destination: IpAddr,
+    packet: &[u8],
+    parsed_packet: &mut ParsedPacket,
+) {
+    if let Some(ospf_packet) = parse_ospf_packet(packet) {
+        debug!(
+            "OSPF packet {} -> {} (type={}, router={})",
+            source, destination, ospf_packet.ospf_type, ospf_packet.router_id
+        );
+
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::OspfPacket(ospf_packet)));
+    } else {
+        debug!("Malformed OSPF Packet");
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed OSPF Packet".to_string(),
+        )));
+    }
+}
+
+/// Parse an OSPFv2 packet, up to the length given by its header
+fn parse_ospf_packet(packet: &[u8]) -> Option<SerializableOspfPacket> {
+    let header = packet.get(..OSPF_HEADER_LENGTH)?;
+    let packet_length = u16::from_be_bytes([header[2], header[3]]);
+    let packet = packet.get(..packet_length as usize)?;
+    let body = packet.get(OSPF_HEADER_LENGTH..)?;
+
+    let ospf_type = header[1];
+    let auth_type = u16::from_be_bytes([header[14], header[15]]);
+
+    Some(SerializableOspfPacket {
+        version: header[0],
+        ospf_type: ospf_type_to_string(ospf_type),
+        packet_length,
+        router_id: read_ipv4_address(&header[4..])?,
+        area_id: read_ipv4_address(&header[8..])?,
+        checksum: u16::from_be_bytes([header[12], header[13]]),
+        auth_type: ospf_auth_type_to_string(auth_type),
+        hello: match ospf_type {
+            OspfTypes::HELLO => Some(parse_ospf_hello(body)?),
+            _ => None,
+        },
+    })
+}
+
+/// Parse the body of a Hello packet, the neighbors filling it up to its end
+fn parse_ospf_hello(body: &[u8]) -> Option<SerializableOspfHello> {
+    let hello = body.get(..OSPF_HELLO_LENGTH)?;
+    let neighbors = &body[OSPF_HELLO_LENGTH..];
+    if !neighbors.len().is_multiple_of(4) {
+        return None;
+    }
+
+    Some(SerializableOspfHello {
+        network_mask: read_ipv4_address(hello)?,
+        hello_interval: u16::from_be_bytes([hello[4], hello[5]]),
+        options: hello[6],
+        router_priority: hello[7],
+        router_dead_interval: u32::from_be_bytes([hello[8], hello[9], hello[10], hello[11]]),
+        designated_router: read_ipv4_address(&hello[12..])?,
+        backup_designated_router: read_ipv4_address(&hello[16..])?,
+        neighbors: read_ipv4_addresses(neighbors, neighbors.len() / 4)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::handle_ospf_packet;
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    // Hello from 1.1.1.1 in the backbone area, 10 s interval, with 2.2.2.2 and 3.3.3.3 as neighbors
+    const OSPF_HELLO: &[u8] = &[
+        0x02, 0x01, 0x00, 0x34, 1, 1, 1, 1, 0, 0, 0, 0, 0xe5, 0x6b, 0x00, 0x00, 0, 0, 0, 0, 0, 0,
+        0, 0, 255, 255, 255, 0, 0x00, 0x0a, 0x02, 0x01, 0x00, 0x00, 0x00, 0x28, 10, 0, 0, 1, 0, 0,
+        0, 0, 2, 2, 2, 2, 3, 3, 3, 3,
+    ];
+
+    #[test]
+    fn ospf_hello_packet() {
+        let parsed_packet = ospf_packet(OSPF_HELLO);
+
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::OspfPacket(ospf_packet) => {
+                assert_eq!(ospf_packet.version, 2);
+                assert_eq!(ospf_packet.ospf_type, "Hello (1)");
+                assert_eq!(ospf_packet.router_id, Ipv4Addr::new(1, 1, 1, 1));
+                assert_eq!(ospf_packet.area_id, Ipv4Addr::new(0, 0, 0, 0));
+                assert_eq!(ospf_packet.auth_type, "Null (0)");
+
+                let hello = ospf_packet.hello.as_ref().unwrap();
+                assert_eq!(hello.network_mask, Ipv4Addr::new(255, 255, 255, 0));
+                assert_eq!(hello.hello_interval, 10);
+                assert_eq!(hello.router_dead_interval, 40);
+                assert_eq!(hello.designated_router, Ipv4Addr::new(10, 0, 0, 1));
+                assert_eq!(
+                    hello.neighbors,
+                    vec![Ipv4Addr::new(2, 2, 2, 2), Ipv4Addr::new(3, 3, 3, 3)]
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_ospf_packet() {
+        // Length beyond the packet, and Hello body cut in the middle of a neighbor
+        let mut cut_neighbor = OSPF_HELLO[..50].to_vec();
+        cut_neighbor[3] = 50;
+
+        for packet in [&OSPF_HELLO[..40], &cut_neighbor[..]] {
+            match ospf_packet(packet).get_transport_layer_packet().unwrap() {
+                SerializablePacket::MalformedPacket(str) => {
+                    assert_eq!(str, "Malformed OSPF Packet")
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    ///////////////////// Utils
+
+    fn ospf_packet(packet: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ospf_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            IpAddr::V4(Ipv4Addr::new(224, 0, 0, 5)),
+            packet,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+}
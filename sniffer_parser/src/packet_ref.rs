@@ -0,0 +1,429 @@
+//! Zero-copy packet parsing
+//!
+//! `parse_ethernet_frame_ref` splits a frame into borrowed views of its link, network and
+//! transport layers, without allocating: the fields are only decoded when read through the
+//! views' getters. It suits embedders serializing the fields they need and dropping the packet
+//! right away; `ParsedPacketRef::to_parsed_packet` runs the owned parser on the same frame when
+//! the full representation (application layer included) is needed after all
+
+use pnet::packet::arp::ArpPacket;
+use pnet::packet::ethernet::{EtherTypes, EthernetPacket};
+use pnet::packet::icmp::IcmpPacket;
+use pnet::packet::icmpv6::Icmpv6Packet;
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::ipv4::Ipv4Packet;
+use pnet::packet::ipv6::Ipv6Packet;
+use pnet::packet::tcp::TcpPacket;
+use pnet::packet::udp::UdpPacket;
+use pnet::packet::Packet;
+
+use crate::parse_ethernet_frame;
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+/// Borrowed network-layer packet
+#[derive(Debug)]
+pub enum NetworkLayerRef<'a> {
+    Arp(ArpPacket<'a>),
+    Ipv4(Ipv4Packet<'a>),
+    Ipv6(Ipv6Packet<'a>),
+    /// Ethertype not parsed, with the Ethernet payload
+    Unknown(&'a [u8]),
+    Malformed(&'static str),
+}
+
+/// Borrowed transport-layer packet
+#[derive(Debug)]
+pub enum TransportLayerRef<'a> {
+    Tcp(TcpPacket<'a>),
+    Udp(UdpPacket<'a>),
+    Icmp(IcmpPacket<'a>),
+    Icmpv6(Icmpv6Packet<'a>),
+    /// Protocol without borrowed view (IGMP, OSPF, ...), with the IP payload
+    Other(IpNextHeaderProtocol, &'a [u8]),
+    Malformed(&'static str),
+}
+
+/// Borrowed representation of a packet at each TCP/IP layer
+#[derive(Debug)]
+pub struct ParsedPacketRef<'a> {
+    id: usize,
+    frame: &'a [u8],
+    link_layer_packet: Option<EthernetPacket<'a>>,
+    network_layer_packet: Option<NetworkLayerRef<'a>>,
+    transport_layer_packet: Option<TransportLayerRef<'a>>,
+}
+
+impl<'a> ParsedPacketRef<'a> {
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    /// Get the bytes of the whole frame
+    pub fn get_frame(&self) -> &'a [u8] {
+        self.frame
+    }
+
+    pub fn get_link_layer_packet(&self) -> Option<&EthernetPacket<'a>> {
+        self.link_layer_packet.as_ref()
+    }
+
+    pub fn get_network_layer_packet(&self) -> Option<&NetworkLayerRef<'a>> {
+        self.network_layer_packet.as_ref()
+    }
+
+    pub fn get_transport_layer_packet(&self) -> Option<&TransportLayerRef<'a>> {
+        self.transport_layer_packet.as_ref()
+    }
+
+    /// Get the payload of the TCP or UDP segment, input of the application-layer parsers
+    pub fn get_application_payload(&self) -> Option<&'a [u8]> {
+        match self.transport_layer_packet.as_ref()? {
+            TransportLayerRef::Tcp(tcp_packet) => Some(tcp_packet.payload()),
+            TransportLayerRef::Udp(udp_packet) => Some(udp_packet.payload()),
+            _ => None,
+        }
+        .map(|payload| reborrow(self.frame, payload))
+    }
+
+    /// Build the owned representation of the packet, by running the owned parser on the frame
+    /// (the application protocols state, e.g. HTTP reassembly, is updated as for any parsed frame)
+    pub fn to_parsed_packet(&self) -> ParsedPacket {
+        match &self.link_layer_packet {
+            Some(ethernet) => parse_ethernet_frame(ethernet, self.id),
+            None => {
+                let mut parsed_packet = ParsedPacket::new(self.id);
+                parsed_packet.set_link_layer_packet(Some(SerializablePacket::MalformedPacket(
+                    "Malformed Ethernet Packet".to_string(),
+                )));
+                parsed_packet
+            }
+        }
+    }
+}
+
+impl<'a> From<&ParsedPacketRef<'a>> for ParsedPacket {
+    fn from(packet: &ParsedPacketRef<'a>) -> Self {
+        packet.to_parsed_packet()
+    }
+}
+
+/// Split an Ethernet frame into borrowed views of its layers, without allocating
+pub fn parse_ethernet_frame_ref(frame: &[u8], id: usize) -> ParsedPacketRef<'_> {
+    let mut parsed_packet = ParsedPacketRef {
+        id,
+        frame,
+        link_layer_packet: None,
+        network_layer_packet: None,
+        transport_layer_packet: None,
+    };
+
+    let ethernet = match EthernetPacket::new(frame) {
+        Some(ethernet) => ethernet,
+        None => return parsed_packet,
+    };
+    let payload = reborrow(frame, ethernet.payload());
+
+    let (network_layer_packet, transport_layer_packet) = match ethernet.get_ethertype() {
+        EtherTypes::Ipv4 => match Ipv4Packet::new(payload) {
+            Some(ipv4_packet) => {
+                let transport_layer_packet = Some(transport_layer_ref(
+                    ipv4_packet.get_next_level_protocol(),
+                    reborrow(payload, ipv4_packet.payload()),
+                ));
+                (NetworkLayerRef::Ipv4(ipv4_packet), transport_layer_packet)
+            }
+            None => (NetworkLayerRef::Malformed("Malformed IPv4 Packet"), None),
+        },
+        EtherTypes::Ipv6 => match Ipv6Packet::new(payload) {
+            Some(ipv6_packet) => {
+                let transport_layer_packet = Some(transport_layer_ref(
+                    ipv6_packet.get_next_header(),
+                    reborrow(payload, ipv6_packet.payload()),
+                ));
+                (NetworkLayerRef::Ipv6(ipv6_packet), transport_layer_packet)
+            }
+            None => (NetworkLayerRef::Malformed("Malformed IPv6 Packet"), None),
+        },
+        EtherTypes::Arp => match ArpPacket::new(payload) {
+            Some(arp_packet) => (NetworkLayerRef::Arp(arp_packet), None),
+            None => (NetworkLayerRef::Malformed("Malformed ARP Packet"), None),
+        },
+        _ => (NetworkLayerRef::Unknown(payload), None),
+    };
+
+    parsed_packet.link_layer_packet = Some(ethernet);
+    parsed_packet.network_layer_packet = Some(network_layer_packet);
+    parsed_packet.transport_layer_packet = transport_layer_packet;
+    parsed_packet
+}
+
+fn transport_layer_ref(protocol: IpNextHeaderProtocol, packet: &[u8]) -> TransportLayerRef<'_> {
+    match protocol {
+        IpNextHeaderProtocols::Tcp => TcpPacket::new(packet)
+            .map(TransportLayerRef::Tcp)
+            .unwrap_or(TransportLayerRef::Malformed("Malformed TCP Packet")),
+        IpNextHeaderProtocols::Udp => UdpPacket::new(packet)
+            .map(TransportLayerRef::Udp)
+            .unwrap_or(TransportLayerRef::Malformed("Malformed UDP Packet")),
+        IpNextHeaderProtocols::Icmp => IcmpPacket::new(packet)
+            .map(TransportLayerRef::Icmp)
+            .unwrap_or(TransportLayerRef::Malformed("Malformed ICMP Packet")),
+        IpNextHeaderProtocols::Icmpv6 => Icmpv6Packet::new(packet)
+            .map(TransportLayerRef::Icmpv6)
+            .unwrap_or(TransportLayerRef::Malformed("Malformed ICMPv6 Packet")),
+        _ => TransportLayerRef::Other(protocol, packet),
+    }
+}
+
+/// Get the part of `outer` that `inner` (obtained from a view of `outer`) designates, with the
+/// lifetime of `outer`; an empty `inner`, which pnet may return as a static slice out of
+/// `outer`, is the empty end of `outer`
+fn reborrow<'a>(outer: &'a [u8], inner: &[u8]) -> &'a [u8] {
+    let offset = (inner.as_ptr() as usize).checked_sub(outer.as_ptr() as usize);
+    match offset {
+        Some(offset) if !inner.is_empty() && offset + inner.len() <= outer.len() => {
+            &outer[offset..offset + inner.len()]
+        }
+        _ => &outer[outer.len()..],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+
+    use pnet::packet::arp::ArpOperations;
+    use pnet::packet::ethernet::EtherTypes;
+    use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+    use pnet::packet::ipv4::MutableIpv4Packet;
+    use pnet::packet::tcp::TcpFlags;
+    use pnet::packet::Packet;
+    use pnet::util::MacAddr;
+
+    use super::{parse_ethernet_frame_ref, NetworkLayerRef, TransportLayerRef};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+    use crate::test_util::{
+        self, build_test_arp_packet, build_test_ethernet_frame, build_test_ipv4_packet,
+        build_test_ipv6_packet, build_test_udp_datagram, CLIENT, SERVER,
+    };
+
+    const DNS_QUERY: &[u8] = &[
+        0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x07, b'e', b'x',
+        b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00, 0x00, 0x01, 0x00, 0x01,
+    ];
+
+    #[test]
+    fn ref_and_owned_ipv4_parses_agree() {
+        for frame in [
+            build_test_ipv4_frame(IpNextHeaderProtocols::Tcp, &build_test_tcp_segment(b"data")),
+            build_test_ipv4_frame(
+                IpNextHeaderProtocols::Udp,
+                &build_test_udp_segment(DNS_QUERY),
+            ),
+        ] {
+            let packet_ref = parse_ethernet_frame_ref(&frame, 3);
+            let packet = ParsedPacket::from(&packet_ref);
+            assert_eq!(packet_ref.get_id(), packet.get_id());
+            assert_link_layers_agree(packet_ref.get_link_layer_packet().unwrap(), &packet);
+
+            match (
+                packet_ref.get_network_layer_packet().unwrap(),
+                packet.get_network_layer_packet().unwrap(),
+            ) {
+                (NetworkLayerRef::Ipv4(ipv4_ref), SerializablePacket::Ipv4Packet(ipv4_packet)) => {
+                    assert_eq!(ipv4_ref.get_version(), ipv4_packet.version);
+                    assert_eq!(ipv4_ref.get_header_length(), ipv4_packet.header_length);
+                    assert_eq!(ipv4_ref.get_total_length(), ipv4_packet.total_length);
+                    assert_eq!(ipv4_ref.get_identification(), ipv4_packet.identification);
+                    assert_eq!(ipv4_ref.get_flags(), ipv4_packet.flags);
+                    assert_eq!(ipv4_ref.get_ttl(), ipv4_packet.ttl);
+                    assert_eq!(ipv4_ref.get_checksum(), ipv4_packet.checksum);
+                    assert_eq!(ipv4_ref.get_source(), ipv4_packet.source);
+                    assert_eq!(ipv4_ref.get_destination(), ipv4_packet.destination);
+                }
+                _ => unreachable!(),
+            }
+
+            assert_transport_layers_agree(
+                packet_ref.get_transport_layer_packet().unwrap(),
+                &packet,
+            );
+            assert!(packet_ref.get_application_payload().is_some());
+        }
+    }
+
+    #[test]
+    fn ref_and_owned_ipv6_and_arp_parses_agree() {
+        let frame = build_test_ipv6_frame(
+            IpNextHeaderProtocols::Tcp,
+            &build_test_tcp_segment(&[0xcd; 64]),
+        );
+        let packet_ref = parse_ethernet_frame_ref(&frame, 0);
+        let packet = packet_ref.to_parsed_packet();
+        assert_link_layers_agree(packet_ref.get_link_layer_packet().unwrap(), &packet);
+
+        match (
+            packet_ref.get_network_layer_packet().unwrap(),
+            packet.get_network_layer_packet().unwrap(),
+        ) {
+            (NetworkLayerRef::Ipv6(ipv6_ref), SerializablePacket::Ipv6Packet(ipv6_packet)) => {
+                assert_eq!(ipv6_ref.get_traffic_class(), ipv6_packet.traffic_class);
+                assert_eq!(ipv6_ref.get_flow_label(), ipv6_packet.flow_label);
+                assert_eq!(ipv6_ref.get_payload_length(), ipv6_packet.payload_length);
+                assert_eq!(ipv6_ref.get_hop_limit(), ipv6_packet.hop_limit);
+                assert_eq!(ipv6_ref.get_source(), ipv6_packet.source);
+                assert_eq!(ipv6_ref.get_destination(), ipv6_packet.destination);
+            }
+            _ => unreachable!(),
+        }
+        assert_transport_layers_agree(packet_ref.get_transport_layer_packet().unwrap(), &packet);
+        assert_eq!(packet_ref.get_application_payload(), Some(&[0xcd; 64][..]));
+
+        let frame = build_test_arp_frame();
+        let packet_ref = parse_ethernet_frame_ref(&frame, 0);
+        let packet = packet_ref.to_parsed_packet();
+        match (
+            packet_ref.get_network_layer_packet().unwrap(),
+            packet.get_network_layer_packet().unwrap(),
+        ) {
+            (NetworkLayerRef::Arp(arp_ref), SerializablePacket::ArpPacket(arp_packet)) => {
+                assert_eq!(arp_ref.get_sender_hw_addr(), arp_packet.sender_hw_addr);
+                assert_eq!(
+                    arp_ref.get_sender_proto_addr(),
+                    arp_packet.sender_proto_addr
+                );
+                assert_eq!(arp_ref.get_target_hw_addr(), arp_packet.target_hw_addr);
+                assert_eq!(
+                    arp_ref.get_target_proto_addr(),
+                    arp_packet.target_proto_addr
+                );
+                assert_eq!(arp_ref.get_hw_addr_len(), arp_packet.hw_addr_len);
+                assert_eq!(arp_ref.get_proto_addr_len(), arp_packet.proto_addr_len);
+            }
+            _ => unreachable!(),
+        }
+        assert!(packet_ref.get_transport_layer_packet().is_none());
+        assert!(packet.get_transport_layer_packet().is_none());
+    }
+
+    #[test]
+    fn malformed_ref_layers() {
+        let frame = build_test_ethernet_frame(EtherTypes::Ipv4, &[0x45, 0x00]);
+        let packet_ref = parse_ethernet_frame_ref(&frame, 0);
+        let packet = packet_ref.to_parsed_packet();
+        match (
+            packet_ref.get_network_layer_packet().unwrap(),
+            packet.get_network_layer_packet().unwrap(),
+        ) {
+            (NetworkLayerRef::Malformed(reason), SerializablePacket::MalformedPacket(str)) => {
+                assert_eq!(reason, str)
+            }
+            _ => unreachable!(),
+        }
+
+        let packet_ref = parse_ethernet_frame_ref(&frame[..10], 0);
+        assert!(packet_ref.get_link_layer_packet().is_none());
+        match packet_ref
+            .to_parsed_packet()
+            .get_link_layer_packet()
+            .unwrap()
+        {
+            SerializablePacket::MalformedPacket(str) => {
+                assert_eq!(str, "Malformed Ethernet Packet")
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn empty_payloads() {
+        let frame = build_test_ethernet_frame(EtherTypes::Ipv4, &[]);
+        let packet_ref = parse_ethernet_frame_ref(&frame, 0);
+        assert!(packet_ref.get_link_layer_packet().is_some());
+        assert!(matches!(
+            packet_ref.get_network_layer_packet(),
+            Some(NetworkLayerRef::Malformed(_))
+        ));
+
+        let frame = build_test_ipv4_frame(IpNextHeaderProtocols::Tcp, &build_test_tcp_segment(&[]));
+        assert_eq!(frame.len(), 54);
+        let packet_ref = parse_ethernet_frame_ref(&frame, 0);
+        assert_eq!(packet_ref.get_application_payload(), Some(&[][..]));
+    }
+
+    ///////////////////// Utils
+
+    fn assert_link_layers_agree(
+        ethernet_ref: &pnet::packet::ethernet::EthernetPacket,
+        packet: &ParsedPacket,
+    ) {
+        match packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::EthernetPacket(ethernet_packet) => {
+                assert_eq!(ethernet_ref.get_source(), ethernet_packet.source);
+                assert_eq!(ethernet_ref.get_destination(), ethernet_packet.destination);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn assert_transport_layers_agree(transport_ref: &TransportLayerRef, packet: &ParsedPacket) {
+        match (transport_ref, packet.get_transport_layer_packet().unwrap()) {
+            (TransportLayerRef::Tcp(tcp_ref), SerializablePacket::TcpPacket(tcp_packet)) => {
+                assert_eq!(tcp_ref.get_source(), tcp_packet.source);
+                assert_eq!(tcp_ref.get_destination(), tcp_packet.destination);
+                assert_eq!(tcp_ref.get_sequence(), tcp_packet.sequence);
+                assert_eq!(tcp_ref.get_acknowledgement(), tcp_packet.acknowledgement);
+                assert_eq!(tcp_ref.get_data_offset(), tcp_packet.data_offset);
+                assert_eq!(tcp_ref.get_flags() as u16, tcp_packet.flags);
+                assert_eq!(tcp_ref.get_window(), tcp_packet.window);
+                assert_eq!(tcp_ref.get_checksum(), tcp_packet.checksum);
+                assert_eq!(tcp_ref.get_urgent_ptr(), tcp_packet.urgent_ptr);
+                assert_eq!(tcp_ref.get_options_raw(), tcp_packet.options);
+                assert_eq!(tcp_ref.payload().len(), tcp_packet.length);
+            }
+            (TransportLayerRef::Udp(udp_ref), SerializablePacket::UdpPacket(udp_packet)) => {
+                assert_eq!(udp_ref.get_source(), udp_packet.source);
+                assert_eq!(udp_ref.get_destination(), udp_packet.destination);
+                assert_eq!(udp_ref.get_length(), udp_packet.length);
+                assert_eq!(udp_ref.get_checksum(), udp_packet.checksum);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn build_test_arp_frame() -> Vec<u8> {
+        let arp_packet = build_test_arp_packet(
+            ArpOperations::Request,
+            (MacAddr::new(10, 10, 10, 10, 10, 10), CLIENT),
+            (MacAddr::zero(), SERVER),
+        );
+        build_test_ethernet_frame(EtherTypes::Arp, &arp_packet)
+    }
+
+    fn build_test_ipv4_frame(protocol: IpNextHeaderProtocol, payload: &[u8]) -> Vec<u8> {
+        let mut ip_packet = build_test_ipv4_packet(CLIENT, SERVER, 64, protocol, payload);
+        MutableIpv4Packet::new(&mut ip_packet)
+            .unwrap()
+            .set_identification(0x1234);
+        build_test_ethernet_frame(EtherTypes::Ipv4, &ip_packet)
+    }
+
+    fn build_test_ipv6_frame(next_header: IpNextHeaderProtocol, payload: &[u8]) -> Vec<u8> {
+        let ip_packet = build_test_ipv6_packet(
+            Ipv6Addr::new(10, 10, 10, 10, 10, 10, 10, 10),
+            Ipv6Addr::new(11, 11, 11, 11, 11, 11, 11, 11),
+            0x12345,
+            next_header,
+            payload,
+        );
+        build_test_ethernet_frame(EtherTypes::Ipv6, &ip_packet)
+    }
+
+    fn build_test_udp_segment(payload: &[u8]) -> Vec<u8> {
+        build_test_udp_datagram(4444, 53, payload)
+    }
+
+    fn build_test_tcp_segment(payload: &[u8]) -> Vec<u8> {
+        test_util::build_test_tcp_segment(4444, 9999, TcpFlags::ACK | TcpFlags::PSH, 7, 9, payload)
+    }
+}
@@ -0,0 +1,389 @@
+//! Classic pcap capture file reading
+//!
+//! Reads the global header of a libpcap capture file (byte order, timestamp resolution, link type)
+//! and iterates over its records, each carrying the capture timestamp and the raw frame bytes
+
+use std::fmt;
+use std::io::{self, Read};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Magic numbers identifying the byte order and the timestamp resolution of a capture file
+#[allow(non_snake_case)]
+pub mod PcapMagic {
+    pub const MICROSECONDS: u32 = 0xa1b2c3d4;
+    pub const NANOSECONDS: u32 = 0xa1b23c4d;
+}
+
+/// Link-layer header types (LINKTYPE_*) stored in the capture file header
+#[allow(non_snake_case)]
+pub mod LinkTypes {
+    pub const ETHERNET: u32 = 1;
+    pub const LINUX_SLL: u32 = 113;
+    pub const IEEE802_11_RADIOTAP: u32 = 127;
+}
+
+const GLOBAL_HEADER_LENGTH: usize = 24;
+const RECORD_HEADER_LENGTH: usize = 16;
+
+/// Errors occurring while reading a pcap file
+#[derive(Debug)]
+pub enum PcapError {
+    Io(io::Error),
+    InvalidMagic(u32),
+    TruncatedRecord,
+    /// Block of a pcapng file too short for its type, or with a trailing length differing from
+    /// the leading one
+    MalformedBlock(u32),
+    /// Interface ID of a pcapng packet block, with no matching Interface Description Block
+    UnknownInterface(u32),
+    /// Link type of an input differing from the one of the first input, when merging files
+    LinkTypeMismatch {
+        input: usize,
+        link_type: u32,
+        expected: u32,
+    },
+}
+
+impl fmt::Display for PcapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PcapError::Io(e) => write!(f, "I/O error: {}", e),
+            PcapError::InvalidMagic(magic) => write!(f, "Not a pcap file (magic: {:#x})", magic),
+            PcapError::TruncatedRecord => write!(f, "Truncated pcap record"),
+            PcapError::MalformedBlock(block_type) => {
+                write!(f, "Malformed pcapng block (type: {:#x})", block_type)
+            }
+            PcapError::UnknownInterface(interface_id) => {
+                write!(f, "Unknown pcapng interface {}", interface_id)
+            }
+            PcapError::LinkTypeMismatch {
+                input,
+                link_type,
+                expected,
+            } => write!(
+                f,
+                "Link type {} of input {} differs from link type {} of the first input",
+                link_type, input, expected
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for PcapError {
+    fn from(e: io::Error) -> Self {
+        PcapError::Io(e)
+    }
+}
+
+/// Range of capture timestamps, bounds included; an unset bound leaves the range open
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub since: Option<SystemTime>,
+    pub until: Option<SystemTime>,
+}
+
+impl TimeWindow {
+    /// Check if a timestamp is within the window
+    pub fn contains(&self, timestamp: SystemTime) -> bool {
+        self.since.is_none_or(|since| timestamp >= since) && !self.is_past(timestamp)
+    }
+
+    /// Check if a timestamp is after the end of the window
+    pub fn is_past(&self, timestamp: SystemTime) -> bool {
+        self.until.is_some_and(|until| timestamp > until)
+    }
+}
+
+/// A single captured frame read from a pcap file
+#[derive(Debug, Clone)]
+pub struct PcapRecord {
+    /// Link-layer header type of the frame, from the file header
+    pub link_type: u32,
+    pub timestamp: SystemTime,
+    pub captured_length: u32,
+    pub original_length: u32,
+    pub data: Vec<u8>,
+}
+
+/// Sequential reader over the records of a classic pcap file
+pub struct PcapReader<R: Read> {
+    reader: R,
+    big_endian: bool,
+    nanoseconds: bool,
+    snaplen: u32,
+    link_type: u32,
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Read the global header and build a reader positioned on the first record
+    pub fn new(mut reader: R) -> Result<Self, PcapError> {
+        let mut header = [0u8; GLOBAL_HEADER_LENGTH];
+        reader.read_exact(&mut header)?;
+
+        let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let (big_endian, nanoseconds) = match magic {
+            PcapMagic::MICROSECONDS => (false, false),
+            PcapMagic::NANOSECONDS => (false, true),
+            m if m.swap_bytes() == PcapMagic::MICROSECONDS => (true, false),
+            m if m.swap_bytes() == PcapMagic::NANOSECONDS => (true, true),
+            m => return Err(PcapError::InvalidMagic(m)),
+        };
+
+        let mut pcap_reader = PcapReader {
+            reader,
+            big_endian,
+            nanoseconds,
+            snaplen: 0,
+            link_type: 0,
+        };
+        pcap_reader.snaplen = pcap_reader.read_u32(&header[16..20]);
+        pcap_reader.link_type = pcap_reader.read_u32(&header[20..24]);
+
+        Ok(pcap_reader)
+    }
+
+    /// Get the link-layer header type of the records
+    pub fn get_link_type(&self) -> u32 {
+        self.link_type
+    }
+
+    /// Get the maximum number of bytes captured per record
+    pub fn get_snaplen(&self) -> u32 {
+        self.snaplen
+    }
+
+    /// Read the next record, `None` when the end of the file is reached
+    pub fn next_record(&mut self) -> Option<Result<PcapRecord, PcapError>> {
+        let mut header = [0u8; RECORD_HEADER_LENGTH];
+        match read_full(&mut self.reader, &mut header) {
+            Ok(0) => return None,
+            Ok(RECORD_HEADER_LENGTH) => (),
+            Ok(_) => return Some(Err(PcapError::TruncatedRecord)),
+            Err(e) => return Some(Err(PcapError::Io(e))),
+        }
+
+        let seconds = self.read_u32(&header[0..4]);
+        let fraction = self.read_u32(&header[4..8]);
+        let captured_length = self.read_u32(&header[8..12]);
+        let original_length = self.read_u32(&header[12..16]);
+
+        // Read up to the captured length, so that a corrupt one allocates the bytes present only
+        let mut data = vec![];
+        match (&mut self.reader)
+            .take(captured_length as u64)
+            .read_to_end(&mut data)
+        {
+            Ok(length) if length == captured_length as usize => (),
+            _ => return Some(Err(PcapError::TruncatedRecord)),
+        }
+
+        let fraction = if self.nanoseconds {
+            Duration::from_nanos(fraction as u64)
+        } else {
+            Duration::from_micros(fraction as u64)
+        };
+
+        Some(Ok(PcapRecord {
+            link_type: self.link_type,
+            timestamp: UNIX_EPOCH + Duration::from_secs(seconds as u64) + fraction,
+            captured_length,
+            original_length,
+            data,
+        }))
+    }
+
+    fn read_u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Iterate over the records within a time window, numbered by their position in the file;
+    /// as the records are chronological, the reading stops at the first one past the window
+    pub fn records_within(
+        self,
+        window: TimeWindow,
+    ) -> impl Iterator<Item = Result<(usize, PcapRecord), PcapError>> {
+        records_within(self, window)
+    }
+}
+
+/// Number the records by their position, keeping the ones within a time window up to the first
+/// one past it
+pub(crate) fn records_within(
+    records: impl Iterator<Item = Result<PcapRecord, PcapError>>,
+    window: TimeWindow,
+) -> impl Iterator<Item = Result<(usize, PcapRecord), PcapError>> {
+    records
+        .enumerate()
+        .take_while(
+            move |(_, record)| !matches!(record, Ok(record) if window.is_past(record.timestamp)),
+        )
+        .filter(
+            move |(_, record)| !matches!(record, Ok(record) if !window.contains(record.timestamp)),
+        )
+        .map(|(index, record)| record.map(|record| (index, record)))
+}
+
+impl<R: Read> Iterator for PcapReader<R> {
+    type Item = Result<PcapRecord, PcapError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record()
+    }
+}
+
+/// Encode a little-endian record with microsecond timestamp, as found in a classic pcap file
+pub fn encode_pcap_record(timestamp: SystemTime, data: &[u8]) -> Vec<u8> {
+    let since_epoch = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let length = data.len() as u32;
+
+    let mut record = Vec::with_capacity(RECORD_HEADER_LENGTH + data.len());
+    record.extend_from_slice(&(since_epoch.as_secs() as u32).to_le_bytes());
+    record.extend_from_slice(&since_epoch.subsec_micros().to_le_bytes());
+    record.extend_from_slice(&length.to_le_bytes());
+    record.extend_from_slice(&length.to_le_bytes());
+    record.extend_from_slice(data);
+
+    record
+}
+
+/// Fill the buffer as much as possible, returning the number of bytes read
+pub(crate) fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(read)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{LinkTypes, PcapError, PcapMagic, PcapReader, TimeWindow};
+
+    #[test]
+    fn read_little_endian_pcap() {
+        let pcap = build_test_pcap(&[(1_600_000_000, 123_456, vec![1, 2, 3, 4])]);
+        let mut reader = PcapReader::new(pcap.as_slice()).unwrap();
+
+        assert_eq!(reader.get_link_type(), LinkTypes::ETHERNET);
+        assert_eq!(reader.get_snaplen(), 65535);
+
+        let record = reader.next_record().unwrap().unwrap();
+        assert_eq!(
+            record.timestamp,
+            UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_000)
+        );
+        assert_eq!(record.captured_length, 4);
+        assert_eq!(record.original_length, 4);
+        assert_eq!(record.data, vec![1, 2, 3, 4]);
+
+        assert!(reader.next_record().is_none());
+    }
+
+    #[test]
+    fn invalid_pcap_magic() {
+        let mut pcap = build_test_pcap(&[]);
+        pcap[0] = 0;
+
+        match PcapReader::new(pcap.as_slice()) {
+            Err(PcapError::InvalidMagic(_)) => (),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn truncated_pcap_record() {
+        let mut pcap = build_test_pcap(&[(0, 0, vec![1, 2, 3, 4])]);
+        pcap.truncate(pcap.len() - 2);
+        let mut reader = PcapReader::new(pcap.as_slice()).unwrap();
+
+        match reader.next_record() {
+            Some(Err(PcapError::TruncatedRecord)) => (),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn inflated_captured_length() {
+        let mut pcap = build_test_pcap(&[(0, 0, vec![1, 2, 3, 4])]);
+        // Captured length of the record
+        pcap[32..36].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut reader = PcapReader::new(pcap.as_slice()).unwrap();
+
+        match reader.next_record() {
+            Some(Err(PcapError::TruncatedRecord)) => (),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn records_within_time_window() {
+        let mut pcap = build_test_pcap(&[
+            (100, 0, vec![0]),
+            (200, 0, vec![1]),
+            (250, 500, vec![2]),
+            (300, 0, vec![3]),
+            (400, 0, vec![4]),
+        ]);
+        // Truncated record, only reached when reading past the end of the window
+        pcap.extend_from_slice(&[0u8; 4]);
+        let reader = PcapReader::new(pcap.as_slice()).unwrap();
+
+        let window = TimeWindow {
+            since: Some(UNIX_EPOCH + Duration::from_secs(200)),
+            until: Some(UNIX_EPOCH + Duration::from_secs(300)),
+        };
+        let records: Vec<_> = reader
+            .records_within(window)
+            .map(|record| record.map(|(index, record)| (index, record.data[0])))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records, vec![(1, 1), (2, 2), (3, 3)]);
+        assert!(!window.contains(UNIX_EPOCH + Duration::from_secs(199)));
+        assert!(window.is_past(UNIX_EPOCH + Duration::new(300, 1)));
+    }
+
+    ///////////////////// Utils
+
+    pub fn build_test_pcap(records: &[(u32, u32, Vec<u8>)]) -> Vec<u8> {
+        build_test_pcap_with_link_type(LinkTypes::ETHERNET, records)
+    }
+
+    pub fn build_test_pcap_with_link_type(
+        link_type: u32,
+        records: &[(u32, u32, Vec<u8>)],
+    ) -> Vec<u8> {
+        let mut pcap = vec![];
+        pcap.extend_from_slice(&PcapMagic::MICROSECONDS.to_le_bytes());
+        pcap.extend_from_slice(&2u16.to_le_bytes());
+        pcap.extend_from_slice(&4u16.to_le_bytes());
+        pcap.extend_from_slice(&0i32.to_le_bytes());
+        pcap.extend_from_slice(&0u32.to_le_bytes());
+        pcap.extend_from_slice(&65535u32.to_le_bytes());
+        pcap.extend_from_slice(&link_type.to_le_bytes());
+
+        for (seconds, microseconds, data) in records {
+            pcap.extend_from_slice(&seconds.to_le_bytes());
+            pcap.extend_from_slice(&microseconds.to_le_bytes());
+            pcap.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            pcap.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            pcap.extend_from_slice(data);
+        }
+
+        pcap
+    }
+}
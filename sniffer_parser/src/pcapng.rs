@@ -0,0 +1,416 @@
+//! pcapng capture file reading
+//!
+//! Reads the blocks of a pcapng capture file: the Section Header Block gives the byte order of
+//! its section, the Interface Description Blocks the link type and timestamp resolution of each
+//! interface, and the Enhanced Packet Blocks the captured frames, yielded as pcap records. The
+//! other blocks (name resolution, statistics, ...) are skipped
+
+use std::io::Read;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::pcap::{read_full, records_within, PcapError, PcapRecord, TimeWindow};
+use crate::tlv::{Endianness, TlvParser};
+
+/// Types of the pcapng blocks
+#[allow(non_snake_case)]
+pub mod PcapngBlockTypes {
+    pub const INTERFACE_DESCRIPTION: u32 = 0x00000001;
+    pub const ENHANCED_PACKET: u32 = 0x00000006;
+    /// Same in both byte orders, so that a section is recognized before knowing its byte order
+    pub const SECTION_HEADER: u32 = 0x0a0d0d0a;
+}
+
+/// Magic number of the Section Header Block, giving the byte order of the section
+pub const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b3c4d;
+
+/// Block type and total length, leading every block
+const BLOCK_HEADER_LENGTH: usize = 8;
+/// Total length repeated at the end of every block
+const BLOCK_TRAILER_LENGTH: usize = 4;
+const INTERFACE_DESCRIPTION_LENGTH: usize = 8;
+const ENHANCED_PACKET_LENGTH: usize = 20;
+
+const OPTION_END: u16 = 0;
+const OPTION_IF_TSRESOL: u16 = 9;
+
+/// Timestamp units per second of an interface without `if_tsresol` option: microseconds
+const DEFAULT_UNITS_PER_SECOND: u64 = 1_000_000;
+
+/// Check if the leading bytes of a capture file are the ones of a pcapng file
+pub fn is_pcapng(header: &[u8]) -> bool {
+    header.len() >= 4
+        && u32::from_le_bytes([header[0], header[1], header[2], header[3]])
+            == PcapngBlockTypes::SECTION_HEADER
+}
+
+/// Interface described in the current section
+#[derive(Debug, Clone, Copy)]
+struct PcapngInterface {
+    link_type: u32,
+    /// Timestamp resolution, from the `if_tsresol` option
+    units_per_second: u64,
+}
+
+/// Sequential reader over the packets of a pcapng file
+pub struct PcapngReader<R: Read> {
+    reader: R,
+    big_endian: bool,
+    /// Interfaces of the current section, indexed by interface ID
+    interfaces: Vec<PcapngInterface>,
+}
+
+impl<R: Read> PcapngReader<R> {
+    /// Read the Section Header Block and build a reader positioned on the following block
+    pub fn new(reader: R) -> Result<Self, PcapError> {
+        let mut pcapng_reader = PcapngReader {
+            reader,
+            big_endian: false,
+            interfaces: vec![],
+        };
+
+        match pcapng_reader.read_block() {
+            Some(Ok((PcapngBlockTypes::SECTION_HEADER, _))) => Ok(pcapng_reader),
+            Some(Ok((block_type, _))) => Err(PcapError::InvalidMagic(block_type)),
+            Some(Err(e)) => Err(e),
+            None => Err(PcapError::TruncatedRecord),
+        }
+    }
+
+    /// Read the next packet, `None` when the end of the file is reached
+    pub fn next_record(&mut self) -> Option<Result<PcapRecord, PcapError>> {
+        loop {
+            let (block_type, body) = match self.read_block()? {
+                Ok(block) => block,
+                Err(e) => return Some(Err(e)),
+            };
+
+            match block_type {
+                // A new section describes its own interfaces
+                PcapngBlockTypes::SECTION_HEADER => self.interfaces.clear(),
+                PcapngBlockTypes::INTERFACE_DESCRIPTION => {
+                    match self.parse_interface_description(&body) {
+                        Ok(interface) => self.interfaces.push(interface),
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+                PcapngBlockTypes::ENHANCED_PACKET => {
+                    return Some(self.parse_enhanced_packet(&body))
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Iterate over the packets within a time window, numbered by their position in the file;
+    /// as the packets are chronological, the reading stops at the first one past the window
+    pub fn records_within(
+        self,
+        window: TimeWindow,
+    ) -> impl Iterator<Item = Result<(usize, PcapRecord), PcapError>> {
+        records_within(self, window)
+    }
+
+    /// Read the type and the body of the next block, `None` when the end of the file is reached;
+    /// the byte order is updated by a Section Header Block
+    fn read_block(&mut self) -> Option<Result<(u32, Vec<u8>), PcapError>> {
+        let mut header = [0u8; BLOCK_HEADER_LENGTH];
+        match read_full(&mut self.reader, &mut header) {
+            Ok(0) => return None,
+            Ok(BLOCK_HEADER_LENGTH) => (),
+            Ok(_) => return Some(Err(PcapError::TruncatedRecord)),
+            Err(e) => return Some(Err(PcapError::Io(e))),
+        }
+
+        // The byte order of a section is read before the length of its header
+        let mut byte_order_magic = [0u8; 4];
+        let block_type = self.read_u32(&header[0..4]);
+        let section_header = block_type == PcapngBlockTypes::SECTION_HEADER;
+        if section_header {
+            if self.reader.read_exact(&mut byte_order_magic).is_err() {
+                return Some(Err(PcapError::TruncatedRecord));
+            }
+            self.big_endian = match u32::from_le_bytes(byte_order_magic) {
+                PCAPNG_BYTE_ORDER_MAGIC => false,
+                m if m.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+                m => return Some(Err(PcapError::InvalidMagic(m))),
+            };
+        }
+
+        let total_length = self.read_u32(&header[4..8]) as usize;
+        let body_start = match section_header {
+            true => byte_order_magic.len(),
+            false => 0,
+        };
+        if !total_length.is_multiple_of(4)
+            || total_length < BLOCK_HEADER_LENGTH + body_start + BLOCK_TRAILER_LENGTH
+        {
+            return Some(Err(PcapError::MalformedBlock(block_type)));
+        }
+
+        // Read up to the total length, so that a corrupt one allocates the bytes present only
+        let body_length = total_length - BLOCK_HEADER_LENGTH;
+        let mut body = byte_order_magic[..body_start].to_vec();
+        match (&mut self.reader)
+            .take((body_length - body_start) as u64)
+            .read_to_end(&mut body)
+        {
+            Ok(_) if body.len() == body_length => (),
+            _ => return Some(Err(PcapError::TruncatedRecord)),
+        }
+
+        let trailer = body.split_off(body.len() - BLOCK_TRAILER_LENGTH);
+        if self.read_u32(&trailer) as usize != total_length {
+            return Some(Err(PcapError::MalformedBlock(block_type)));
+        }
+
+        Some(Ok((block_type, body)))
+    }
+
+    fn parse_interface_description(&self, body: &[u8]) -> Result<PcapngInterface, PcapError> {
+        if body.len() < INTERFACE_DESCRIPTION_LENGTH {
+            return Err(PcapError::MalformedBlock(
+                PcapngBlockTypes::INTERFACE_DESCRIPTION,
+            ));
+        }
+
+        let mut interface = PcapngInterface {
+            link_type: self.read_u16(&body[0..2]) as u32,
+            units_per_second: DEFAULT_UNITS_PER_SECOND,
+        };
+
+        let endianness = match self.big_endian {
+            true => Endianness::Big,
+            false => Endianness::Little,
+        };
+        // Values are padded to a multiple of 4 bytes; a truncated option ends the options
+        let options = TlvParser::new(&body[INTERFACE_DESCRIPTION_LENGTH..], 2, 2, endianness)
+            .aligned(4)
+            .map_while(Result::ok)
+            .take_while(|option| option.tlv_type != OPTION_END as u32);
+        for option in options {
+            let value = option.value;
+            if option.tlv_type == OPTION_IF_TSRESOL as u32 && value.len() == 1 {
+                // Negative power of 2 if the most significant bit is set, of 10 otherwise
+                interface.units_per_second = match value[0] {
+                    resolution if resolution & 0x80 != 0 => 1u64
+                        .checked_shl((resolution & 0x7f) as u32)
+                        .unwrap_or(u64::MAX),
+                    resolution => 10u64.saturating_pow(resolution as u32),
+                };
+            }
+        }
+
+        Ok(interface)
+    }
+
+    fn parse_enhanced_packet(&self, body: &[u8]) -> Result<PcapRecord, PcapError> {
+        if body.len() < ENHANCED_PACKET_LENGTH {
+            return Err(PcapError::MalformedBlock(PcapngBlockTypes::ENHANCED_PACKET));
+        }
+
+        let interface_id = self.read_u32(&body[0..4]);
+        let interface = self
+            .interfaces
+            .get(interface_id as usize)
+            .ok_or(PcapError::UnknownInterface(interface_id))?;
+
+        let units = (self.read_u32(&body[4..8]) as u64) << 32 | self.read_u32(&body[8..12]) as u64;
+        let captured_length = self.read_u32(&body[12..16]);
+        let original_length = self.read_u32(&body[16..20]);
+        let data = body
+            .get(ENHANCED_PACKET_LENGTH..ENHANCED_PACKET_LENGTH + captured_length as usize)
+            .ok_or(PcapError::MalformedBlock(PcapngBlockTypes::ENHANCED_PACKET))?;
+
+        let seconds = units / interface.units_per_second;
+        let nanoseconds = (units % interface.units_per_second) as u128 * 1_000_000_000
+            / interface.units_per_second as u128;
+        // A corrupt timestamp may lie past the latest time representable
+        let timestamp = UNIX_EPOCH
+            .checked_add(Duration::new(seconds, nanoseconds as u32))
+            .ok_or(PcapError::MalformedBlock(PcapngBlockTypes::ENHANCED_PACKET))?;
+
+        Ok(PcapRecord {
+            link_type: interface.link_type,
+            timestamp,
+            captured_length,
+            original_length,
+            data: data.to_vec(),
+        })
+    }
+
+    fn read_u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn read_u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+}
+
+impl<R: Read> Iterator for PcapngReader<R> {
+    type Item = Result<PcapRecord, PcapError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{is_pcapng, PcapngBlockTypes, PcapngReader, PCAPNG_BYTE_ORDER_MAGIC};
+    use crate::parse_pcap_record;
+    use crate::pcap::{LinkTypes, PcapError};
+    use crate::serializable_packet::SerializablePacket;
+
+    /// Ethernet frame from 10:10:10:10:10:10 to ff:ff:ff:ff:ff:ff, carrying an empty ARP payload
+    const FRAME: [u8; 14] = [
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x08, 0x06,
+    ];
+
+    #[test]
+    fn read_little_endian_pcapng() {
+        let pcapng = build_test_pcapng(false, None, 1_600_000_000_123_456);
+        assert!(is_pcapng(&pcapng));
+        let mut reader = PcapngReader::new(pcapng.as_slice()).unwrap();
+
+        let record = reader.next_record().unwrap().unwrap();
+        assert_eq!(record.link_type, LinkTypes::ETHERNET);
+        assert_eq!(
+            record.timestamp,
+            UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_000)
+        );
+        assert_eq!((record.captured_length, record.original_length), (14, 60));
+        assert_eq!(record.data, FRAME);
+        assert!(reader.next_record().is_none());
+
+        let parsed_packet = parse_pcap_record(&record, 0);
+        match parsed_packet.get_link_layer_packet() {
+            Some(SerializablePacket::EthernetPacket(ethernet_packet)) => {
+                assert_eq!(ethernet_packet.source.to_string(), "10:10:10:10:10:10");
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(parsed_packet.get_timestamp(), Some(record.timestamp));
+    }
+
+    #[test]
+    fn read_big_endian_pcapng_nanoseconds() {
+        let pcapng = build_test_pcapng(true, Some(9), 1_600_000_000_123_456_789);
+        let mut reader = PcapngReader::new(pcapng.as_slice()).unwrap();
+
+        let record = reader.next_record().unwrap().unwrap();
+        assert_eq!(
+            record.timestamp,
+            UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_789)
+        );
+        assert_eq!(record.data, FRAME);
+        assert!(reader.next_record().is_none());
+    }
+
+    #[test]
+    fn pcapng_packet_of_unknown_interface() {
+        let mut pcapng = build_test_pcapng(false, None, 0);
+        // Interface ID of the Enhanced Packet Block, following the SHB and the IDB
+        pcapng[28 + 20 + 8] = 1;
+        let mut reader = PcapngReader::new(pcapng.as_slice()).unwrap();
+
+        match reader.next_record() {
+            Some(Err(PcapError::UnknownInterface(1))) => (),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn pcapng_timestamp_overflow() {
+        let pcapng = build_test_pcapng(false, Some(0), u64::MAX);
+        let mut reader = PcapngReader::new(pcapng.as_slice()).unwrap();
+
+        match reader.next_record() {
+            Some(Err(PcapError::MalformedBlock(PcapngBlockTypes::ENHANCED_PACKET))) => (),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn inflated_block_total_length() {
+        let mut pcapng = build_test_pcapng(false, None, 0);
+        // Total length of the Enhanced Packet Block, following the SHB and the IDB
+        pcapng[28 + 20 + 4..28 + 20 + 8].copy_from_slice(&0xffff_fffcu32.to_le_bytes());
+        let mut reader = PcapngReader::new(pcapng.as_slice()).unwrap();
+
+        match reader.next_record() {
+            Some(Err(PcapError::TruncatedRecord)) => (),
+            _ => unreachable!(),
+        }
+    }
+
+    ///////////////////// Utils
+
+    /// Build a pcapng file with an Ethernet interface, optionally with an `if_tsresol` option,
+    /// and one packet of the frame at a timestamp in the units of the interface
+    fn build_test_pcapng(big_endian: bool, tsresol: Option<u8>, timestamp: u64) -> Vec<u8> {
+        let u16_bytes = |value: u16| match big_endian {
+            true => value.to_be_bytes(),
+            false => value.to_le_bytes(),
+        };
+        let u32_bytes = |value: u32| match big_endian {
+            true => value.to_be_bytes(),
+            false => value.to_le_bytes(),
+        };
+        let block = |block_type: u32, body: Vec<u8>| {
+            let total_length = (8 + body.len() + 4) as u32;
+            [
+                &u32_bytes(block_type)[..],
+                &u32_bytes(total_length),
+                &body,
+                &u32_bytes(total_length),
+            ]
+            .concat()
+        };
+
+        let mut section_header = u32_bytes(PCAPNG_BYTE_ORDER_MAGIC).to_vec();
+        section_header.extend_from_slice(&u16_bytes(1));
+        section_header.extend_from_slice(&u16_bytes(0));
+        section_header.extend_from_slice(&[0xff; 8]);
+
+        let mut interface_description = u16_bytes(LinkTypes::ETHERNET as u16).to_vec();
+        interface_description.extend_from_slice(&u16_bytes(0));
+        interface_description.extend_from_slice(&u32_bytes(65535));
+        if let Some(tsresol) = tsresol {
+            interface_description.extend_from_slice(&u16_bytes(9));
+            interface_description.extend_from_slice(&u16_bytes(1));
+            interface_description.extend_from_slice(&[tsresol, 0, 0, 0]);
+            interface_description.extend_from_slice(&[0; 4]);
+        }
+
+        let mut enhanced_packet = u32_bytes(0).to_vec();
+        enhanced_packet.extend_from_slice(&u32_bytes((timestamp >> 32) as u32));
+        enhanced_packet.extend_from_slice(&u32_bytes(timestamp as u32));
+        enhanced_packet.extend_from_slice(&u32_bytes(FRAME.len() as u32));
+        enhanced_packet.extend_from_slice(&u32_bytes(60));
+        enhanced_packet.extend_from_slice(&FRAME);
+        enhanced_packet.extend_from_slice(&[0; 2]);
+
+        [
+            block(PcapngBlockTypes::SECTION_HEADER, section_header),
+            block(
+                PcapngBlockTypes::INTERFACE_DESCRIPTION,
+                interface_description,
+            ),
+            block(PcapngBlockTypes::ENHANCED_PACKET, enhanced_packet),
+        ]
+        .concat()
+    }
+}
@@ -0,0 +1,131 @@
+//! Packet processing pipelines
+//!
+//! A pipeline chains stages applied in order to every parsed packet: filters dropping packets,
+//! maps transforming them (e.g. to enrich or tag them) and inspections observing them. For
+//! instance `Pipeline::new().filter(contains_tcp).map(tag).run(packets)`
+
+use crate::serializable_packet::ParsedPacket;
+
+/// Stage of a pipeline, returning `None` when the packet is dropped
+type Stage<'a> = Box<dyn FnMut(ParsedPacket) -> Option<ParsedPacket> + 'a>;
+
+/// Chain of stages applied to parsed packets
+#[derive(Default)]
+pub struct Pipeline<'a> {
+    stages: Vec<Stage<'a>>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub fn new() -> Self {
+        Pipeline { stages: vec![] }
+    }
+
+    /// Keep only the packets matching the predicate
+    pub fn filter<F>(mut self, mut predicate: F) -> Self
+    where
+        F: FnMut(&ParsedPacket) -> bool + 'a,
+    {
+        self.stages
+            .push(Box::new(move |packet| predicate(&packet).then_some(packet)));
+        self
+    }
+
+    /// Transform every packet
+    pub fn map<F>(mut self, mut transform: F) -> Self
+    where
+        F: FnMut(ParsedPacket) -> ParsedPacket + 'a,
+    {
+        self.stages
+            .push(Box::new(move |packet| Some(transform(packet))));
+        self
+    }
+
+    /// Observe every packet, without modifying it
+    pub fn inspect<F>(mut self, mut observe: F) -> Self
+    where
+        F: FnMut(&ParsedPacket) + 'a,
+    {
+        self.stages.push(Box::new(move |packet| {
+            observe(&packet);
+            Some(packet)
+        }));
+        self
+    }
+
+    /// Apply every stage to a packet, returning `None` when one of them drops it
+    pub fn process(&mut self, packet: ParsedPacket) -> Option<ParsedPacket> {
+        self.stages
+            .iter_mut()
+            .try_fold(packet, |packet, stage| stage(packet))
+    }
+
+    /// Apply the pipeline to a sequence of packets, lazily
+    pub fn run<I>(self, packets: I) -> PipelineIter<'a, I::IntoIter>
+    where
+        I: IntoIterator<Item = ParsedPacket>,
+    {
+        PipelineIter {
+            pipeline: self,
+            packets: packets.into_iter(),
+        }
+    }
+}
+
+/// Iterator over the packets coming out of a pipeline
+pub struct PipelineIter<'a, I> {
+    pipeline: Pipeline<'a>,
+    packets: I,
+}
+
+impl<'a, I: Iterator<Item = ParsedPacket>> Iterator for PipelineIter<'a, I> {
+    type Item = ParsedPacket;
+
+    fn next(&mut self) -> Option<ParsedPacket> {
+        let pipeline = &mut self.pipeline;
+        self.packets.find_map(|packet| pipeline.process(packet))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::Pipeline;
+    use crate::serializable_packet::ParsedPacket;
+
+    #[test]
+    fn filter_then_map() {
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+        let mut seen = vec![];
+
+        let packets: Vec<ParsedPacket> = Pipeline::new()
+            .inspect(|packet| seen.push(packet.get_id()))
+            .filter(|packet| packet.get_id() % 2 == 0)
+            .map(|mut packet| {
+                packet.set_timestamp(Some(timestamp));
+                packet
+            })
+            .run((0..6).map(ParsedPacket::new))
+            .collect();
+
+        assert_eq!(
+            packets.iter().map(|p| p.get_id()).collect::<Vec<_>>(),
+            vec![0, 2, 4]
+        );
+        assert!(packets.iter().all(|p| p.get_timestamp() == Some(timestamp)));
+        assert_eq!(seen, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn stages_applied_in_order() {
+        let mut pipeline = Pipeline::new()
+            .map(|_| ParsedPacket::new(7))
+            .filter(|packet| packet.get_id() == 7);
+
+        assert_eq!(pipeline.process(ParsedPacket::new(0)).unwrap().get_id(), 7);
+        assert!(Pipeline::new()
+            .filter(|_| false)
+            .process(ParsedPacket::new(0))
+            .is_none());
+    }
+}
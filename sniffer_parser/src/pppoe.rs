@@ -0,0 +1,232 @@
+//! PPPoE Packet parsing
+//!
+//! DSL links carry PPP over Ethernet (RFC 2516): a 6-byte header (version, type, code, session ID,
+//! length) follows the Ethernet header, the discovery stage (ethertype 0x8863) setting up the
+//! session whose packets (ethertype 0x8864) carry PPP frames. The PPPoE packet takes the place of
+//! the Ethernet packet as the link-layer representation; the IPv4 and IPv6 packets carried by PPP
+//! are parsed as the network layer
+
+use log::debug;
+use pnet::packet::ethernet::{EtherTypes, EthernetPacket};
+use pnet::packet::Packet;
+
+use crate::network::{handle_ipv4_packet, handle_ipv6_packet};
+use crate::serializable_packet::{
+    ppp_protocol_to_string, pppoe_code_to_string, ParsedPacket, SerializablePacket,
+    SerializablePppoePacket,
+};
+
+pub(crate) const PPPOE_HEADER_LENGTH: usize = 6;
+const PPP_PROTOCOL_LENGTH: usize = 2;
+
+/// PPP Protocols
+#[allow(non_snake_case)]
+pub mod PppProtocols {
+    pub const IPV4: u16 = 0x0021;
+    pub const IPV6: u16 = 0x0057;
+    pub const IPCP: u16 = 0x8021;
+    pub const IPV6CP: u16 = 0x8057;
+    pub const LCP: u16 = 0xc021;
+    pub const PAP: u16 = 0xc023;
+    pub const CHAP: u16 = 0xc223;
+}
+
+/// PPPoE Codes
+#[allow(non_snake_case)]
+pub mod PppoeCodes {
+    pub const SESSION_DATA: u8 = 0x00;
+    pub const PADO: u8 = 0x07;
+    pub const PADI: u8 = 0x09;
+    pub const PADR: u8 = 0x19;
+    pub const PADS: u8 = 0x65;
+    pub const PADT: u8 = 0xa7;
+}
+
+/// Build a PPPoE packet from an Ethernet frame, save it in a Parsed Packet along with the IP
+/// packet carried in a session
+pub fn handle_pppoe_packet(ethernet: &EthernetPacket, parsed_packet: &mut ParsedPacket) {
+    let session = ethernet.get_ethertype() == EtherTypes::PppoeSession;
+
+    match parse_pppoe_packet(ethernet, session) {
+        Some((pppoe_packet, ppp_protocol, ppp_payload)) => {
+            debug!(
+                "PPPoE packet: {} > {}; code: {} session: {:#06x}",
+                ethernet.get_source(),
+                ethernet.get_destination(),
+                pppoe_packet.code,
+                pppoe_packet.session_id
+            );
+
+            parsed_packet
+                .set_link_layer_packet(Some(SerializablePacket::PppoePacket(pppoe_packet)));
+            match ppp_protocol {
+                Some(PppProtocols::IPV4) => handle_ipv4_packet(ppp_payload, parsed_packet),
+                Some(PppProtocols::IPV6) => handle_ipv6_packet(ppp_payload, parsed_packet),
+                _ => (),
+            }
+        }
+        None => {
+            debug!("Malformed PPPoE Packet");
+            parsed_packet.set_link_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed PPPoE Packet".to_string(),
+            )));
+        }
+    }
+}
+
+/// Parse the PPPoE header, and the PPP protocol of a session packet, up to the length given by
+/// the header (the Ethernet padding is left out); get the PPP protocol and payload along with the
+/// packet
+fn parse_pppoe_packet<'a>(
+    ethernet: &'a EthernetPacket,
+    session: bool,
+) -> Option<(SerializablePppoePacket, Option<u16>, &'a [u8])> {
+    let packet = ethernet.payload();
+    let header = packet.get(..PPPOE_HEADER_LENGTH)?;
+    let length = u16::from_be_bytes([header[4], header[5]]);
+    let payload = packet.get(PPPOE_HEADER_LENGTH..PPPOE_HEADER_LENGTH + length as usize)?;
+
+    let (ppp_protocol, ppp_payload) = match session {
+        true => {
+            let protocol = payload.get(..PPP_PROTOCOL_LENGTH)?;
+            (
+                Some(u16::from_be_bytes([protocol[0], protocol[1]])),
+                &payload[PPP_PROTOCOL_LENGTH..],
+            )
+        }
+        false => (None, payload),
+    };
+
+    Some((
+        SerializablePppoePacket {
+            destination: ethernet.get_destination(),
+            source: ethernet.get_source(),
+            version: header[0] >> 4,
+            pppoe_type: header[0] & 0x0f,
+            code: pppoe_code_to_string(header[1]),
+            session_id: u16::from_be_bytes([header[2], header[3]]),
+            length,
+            ppp_protocol: ppp_protocol.map(ppp_protocol_to_string),
+        },
+        ppp_protocol,
+        ppp_payload,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use pnet::packet::ethernet::{EtherType, EtherTypes};
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::util::MacAddr;
+
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+    use crate::test_util::{
+        build_test_ethernet_frame, build_test_ipv4_packet, build_test_udp_datagram,
+        parse_test_frame,
+    };
+
+    #[test]
+    fn pppoe_session_with_ipv4() {
+        let parsed_packet = pppoe_frame(EtherTypes::PppoeSession, 0x00, &ppp_ipv4_frame(), 4);
+
+        match parsed_packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::PppoePacket(pppoe_packet) => {
+                assert_eq!(pppoe_packet.source, MacAddr::new(10, 10, 10, 10, 10, 10));
+                assert_eq!(pppoe_packet.version, 1);
+                assert_eq!(pppoe_packet.pppoe_type, 1);
+                assert_eq!(pppoe_packet.code, "Session Data (0x00)");
+                assert_eq!(pppoe_packet.session_id, 0x1234);
+                assert_eq!(pppoe_packet.length, 2 + 28);
+                assert_eq!(pppoe_packet.ppp_protocol.as_deref(), Some("IPv4 (0x0021)"));
+            }
+            _ => unreachable!(),
+        }
+        match parsed_packet.get_network_layer_packet().unwrap() {
+            SerializablePacket::Ipv4Packet(ipv4_packet) => {
+                assert_eq!(ipv4_packet.source, Ipv4Addr::new(100, 64, 0, 1));
+                assert_eq!(ipv4_packet.destination, Ipv4Addr::new(8, 8, 8, 8));
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(parsed_packet.protocol_stack(), vec!["PPPoE", "IPv4", "UDP"]);
+    }
+
+    #[test]
+    fn pppoe_discovery_packet() {
+        // PADI with a Service-Name tag, and no PPP payload
+        let tags = [0x01, 0x01, 0x00, 0x00];
+        let parsed_packet = pppoe_frame(EtherTypes::PppoeDiscovery, 0x09, &tags, 0);
+
+        match parsed_packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::PppoePacket(pppoe_packet) => {
+                assert_eq!(pppoe_packet.code, "PADI (0x09)");
+                assert_eq!(pppoe_packet.session_id, 0);
+                assert_eq!(pppoe_packet.length, 4);
+                assert_eq!(pppoe_packet.ppp_protocol, None);
+            }
+            _ => unreachable!(),
+        }
+        assert!(parsed_packet.get_network_layer_packet().is_none());
+    }
+
+    #[test]
+    fn malformed_pppoe_packet() {
+        // Length beyond the frame, and session payload too short for the PPP protocol
+        for length in [0x00ffu16, 1] {
+            let mut frame = build_test_pppoe_frame(EtherTypes::PppoeSession, 0x00, &[0x00; 8], 0);
+            frame[18..20].copy_from_slice(&length.to_be_bytes());
+            let parsed_packet = parse_test_frame(&frame, 0);
+            match parsed_packet.get_link_layer_packet().unwrap() {
+                SerializablePacket::MalformedPacket(str) => {
+                    assert_eq!(str, "Malformed PPPoE Packet")
+                }
+                _ => unreachable!(),
+            }
+            assert!(parsed_packet.get_network_layer_packet().is_none());
+        }
+    }
+
+    ///////////////////// Utils
+
+    /// Build a PPP frame carrying an IPv4 packet of 100.64.0.1 to 8.8.8.8, with a UDP datagram
+    fn ppp_ipv4_frame() -> Vec<u8> {
+        let udp_datagram = build_test_udp_datagram(4444, 9999, &[]);
+        let ip_packet = build_test_ipv4_packet(
+            Ipv4Addr::new(100, 64, 0, 1),
+            Ipv4Addr::new(8, 8, 8, 8),
+            64,
+            IpNextHeaderProtocols::Udp,
+            &udp_datagram,
+        );
+
+        [&[0x00, 0x21][..], &ip_packet].concat()
+    }
+
+    fn pppoe_frame(ethertype: EtherType, code: u8, payload: &[u8], padding: usize) -> ParsedPacket {
+        let frame = build_test_pppoe_frame(ethertype, code, payload, padding);
+        parse_test_frame(&frame, 0)
+    }
+
+    /// Build an Ethernet frame carrying a PPPoE packet of session 0x1234 (none for the discovery
+    /// packets), followed by some padding
+    fn build_test_pppoe_frame(
+        ethertype: EtherType,
+        code: u8,
+        payload: &[u8],
+        padding: usize,
+    ) -> Vec<u8> {
+        let session_id: u16 = match ethertype {
+            EtherTypes::PppoeSession => 0x1234,
+            _ => 0,
+        };
+        let mut pppoe_buffer = vec![0x11, code];
+        pppoe_buffer.extend_from_slice(&session_id.to_be_bytes());
+        pppoe_buffer.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        pppoe_buffer.extend_from_slice(payload);
+        pppoe_buffer.resize(pppoe_buffer.len() + padding, 0);
+
+        build_test_ethernet_frame(ethertype, &pppoe_buffer)
+    }
+}
@@ -0,0 +1,200 @@
+//! Per-layer parse timing
+//!
+//! When enabled with `set_parse_profiling`, the parse of each layer is timed with the monotonic
+//! clock and accumulated in a thread-local `ParseProfile`. A layer is charged its own time only:
+//! the time spent parsing the layers it carries is charged to them. The accumulators are fixed
+//! counters, nothing is allocated per packet; when disabled, a parse only checks the flag
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::is_parse_profiled;
+
+thread_local!(
+    static PARSE_PROFILE: RefCell<ParseProfile> = const { RefCell::new(ParseProfile::new()) };
+    /// Time spent in the layers carried by the layer being parsed
+    static NESTED_TIME: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+);
+
+/// Layers of the packets, in the order they are parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileLayer {
+    Link,
+    Network,
+    Transport,
+    Application,
+}
+
+impl ProfileLayer {
+    pub const ALL: [ProfileLayer; 4] = [
+        ProfileLayer::Link,
+        ProfileLayer::Network,
+        ProfileLayer::Transport,
+        ProfileLayer::Application,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ProfileLayer::Link => "Link",
+            ProfileLayer::Network => "Network",
+            ProfileLayer::Transport => "Transport",
+            ProfileLayer::Application => "Application",
+        }
+    }
+}
+
+/// Parses and time spent in one layer
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LayerProfile {
+    pub parses: usize,
+    pub time: Duration,
+}
+
+/// Time spent in the parse of each layer
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParseProfile {
+    layers: [LayerProfile; 4],
+}
+
+impl ParseProfile {
+    pub const fn new() -> Self {
+        ParseProfile {
+            layers: [LayerProfile {
+                parses: 0,
+                time: Duration::ZERO,
+            }; 4],
+        }
+    }
+
+    /// Get the parses and time of a layer
+    pub fn layer(&self, layer: ProfileLayer) -> LayerProfile {
+        self.layers[layer as usize]
+    }
+
+    /// Get the time spent in all the layers
+    pub fn total_time(&self) -> Duration {
+        self.layers.iter().map(|layer| layer.time).sum()
+    }
+
+    fn record(&mut self, layer: ProfileLayer, time: Duration) {
+        let layer = &mut self.layers[layer as usize];
+        layer.parses += 1;
+        layer.time += time;
+    }
+}
+
+impl fmt::Display for ParseProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total_time = self.total_time().as_secs_f64();
+        writeln!(f, "Parse Profile:")?;
+        for layer in ProfileLayer::ALL {
+            let LayerProfile { parses, time } = self.layer(layer);
+            let mean = match parses {
+                0 => Duration::ZERO,
+                parses => time / parses as u32,
+            };
+            let share = match total_time {
+                0.0 => 0.0,
+                total_time => time.as_secs_f64() / total_time * 100.0,
+            };
+            writeln!(
+                f,
+                "  {:<12} parses: {:>8}  time: {:>12.3} ms  mean: {:>9.3} us  {:>5.1}%",
+                layer.name(),
+                parses,
+                time.as_secs_f64() * 1000.0,
+                mean.as_secs_f64() * 1_000_000.0,
+                share,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the profile accumulated since the last call, resetting it
+pub fn take_parse_profile() -> ParseProfile {
+    PARSE_PROFILE.with(|profile| profile.replace(ParseProfile::new()))
+}
+
+/// Run the parse of a layer, charging its own time to the layer when profiling is enabled
+pub(crate) fn profiled<T>(layer: ProfileLayer, parse: impl FnOnce() -> T) -> T {
+    if !is_parse_profiled() {
+        return parse();
+    }
+
+    let outer_nested_time = NESTED_TIME.with(|nested| nested.replace(Duration::ZERO));
+    let start = Instant::now();
+    let parsed = parse();
+    let elapsed = start.elapsed();
+    let nested_time = NESTED_TIME.with(|nested| nested.replace(outer_nested_time + elapsed));
+
+    PARSE_PROFILE.with(|profile| {
+        profile
+            .borrow_mut()
+            .record(layer, elapsed.saturating_sub(nested_time))
+    });
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pnet::packet::tcp::TcpFlags;
+
+    use super::{take_parse_profile, ProfileLayer};
+    use crate::set_parse_profiling;
+    use crate::test_util::{build_test_tcp_packet, build_test_udp_packet, CLIENT, SERVER};
+
+    const HTTP_REQUEST: &[u8] = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+    /// DTLS 1.2 record of a fatal handshake_failure Alert
+    const DTLS_ALERT: &[u8] = &[
+        0x15, 0xfe, 0xfd, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x02, 0x28,
+    ];
+
+    #[test]
+    fn layers_of_http_request_timed() {
+        take_parse_profile();
+        set_parse_profiling(true);
+        parse_test_http_request();
+        set_parse_profiling(false);
+        parse_test_http_request();
+
+        let profile = take_parse_profile();
+        for layer in ProfileLayer::ALL {
+            assert_eq!(profile.layer(layer).parses, 1);
+            assert!(profile.layer(layer).time > Duration::ZERO);
+        }
+        assert_eq!(
+            profile.total_time(),
+            ProfileLayer::ALL
+                .iter()
+                .map(|&layer| profile.layer(layer).time)
+                .sum::<Duration>()
+        );
+        assert_eq!(profile.to_string().lines().count(), 5);
+        assert_eq!(take_parse_profile().layer(ProfileLayer::Link).parses, 0);
+    }
+
+    #[test]
+    fn dtls_record_timed() {
+        take_parse_profile();
+        set_parse_profiling(true);
+        let parsed_packet = build_test_udp_packet((CLIENT, 4444), (SERVER, 4433), DTLS_ALERT);
+        set_parse_profiling(false);
+
+        assert!(parsed_packet.get_application_layer_packet().is_some());
+        let profile = take_parse_profile();
+        assert_eq!(profile.layer(ProfileLayer::Application).parses, 1);
+        assert!(profile.layer(ProfileLayer::Application).time > Duration::ZERO);
+    }
+
+    ///////////////////// Utils
+
+    fn parse_test_http_request() {
+        let flags = TcpFlags::ACK | TcpFlags::PSH;
+        build_test_tcp_packet((CLIENT, 4444), (SERVER, 80), flags, 0, HTTP_REQUEST);
+    }
+}
@@ -0,0 +1,291 @@
+//! SCTP Packet parsing
+//!
+//! Every SCTP packet (RFC 9260, section 3) starts with a 12-byte common header: ports,
+//! verification tag and checksum, followed by chunks made of a type, flags and a length counting
+//! their 4-byte header, each padded to a multiple of 4 bytes. The DATA chunks carry the user
+//! messages with the Payload Protocol Identifier (PPID) of their upper-layer protocol: the payload
+//! of the first unfragmented one is dispatched on its PPID, as IP dispatches on the protocol
+//! number
+
+use std::net::IpAddr;
+
+use log::debug;
+
+use crate::application::dtls::handle_dtls_packet;
+use crate::application::{handle_application_protocol, FlowContext};
+use crate::profile::{profiled, ProfileLayer};
+use crate::serializable_packet::transport::{
+    sctp_chunk_type_to_string, sctp_ppid_to_string, SerializableSctpChunk, SerializableSctpData,
+    SerializableSctpPacket,
+};
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+const SCTP_HEADER_LENGTH: usize = 12;
+const CHUNK_HEADER_LENGTH: usize = 4;
+/// Chunk header, TSN, stream identifier and sequence number, and PPID of a DATA chunk
+const DATA_CHUNK_HEADER_LENGTH: usize = 16;
+/// Beginning and ending fragment flags of a DATA chunk, both set for an unfragmented message
+const UNFRAGMENTED_FLAGS: u8 = 0x03;
+
+/// Payload Protocol Identifier and payload of a DATA chunk
+type UserMessage<'a> = (u32, &'a [u8]);
+
+/// SCTP Chunk Types
+#[allow(non_snake_case)]
+pub mod SctpChunkTypes {
+    pub const DATA: u8 = 0;
+    pub const INIT: u8 = 1;
+    pub const INIT_ACK: u8 = 2;
+    pub const SACK: u8 = 3;
+    pub const HEARTBEAT: u8 = 4;
+    pub const HEARTBEAT_ACK: u8 = 5;
+    pub const ABORT: u8 = 6;
+    pub const SHUTDOWN: u8 = 7;
+    pub const SHUTDOWN_ACK: u8 = 8;
+    pub const ERROR: u8 = 9;
+    pub const COOKIE_ECHO: u8 = 10;
+    pub const COOKIE_ACK: u8 = 11;
+    pub const SHUTDOWN_COMPLETE: u8 = 14;
+}
+
+/// SCTP Payload Protocol Identifiers, as assigned by IANA
+#[allow(non_snake_case)]
+pub mod PayloadProtocolIdentifiers {
+    /// Left to the ports to tell
+    pub const UNSPECIFIED: u32 = 0;
+    pub const IUA: u32 = 1;
+    pub const M2UA: u32 = 2;
+    pub const M3UA: u32 = 3;
+    pub const SUA: u32 = 4;
+    pub const M2PA: u32 = 5;
+    pub const H248: u32 = 7;
+    pub const S1AP: u32 = 18;
+    pub const X2AP: u32 = 27;
+    pub const DIAMETER: u32 = 46;
+    pub const DIAMETER_DTLS: u32 = 47;
+    pub const WEBRTC_DCEP: u32 = 50;
+    pub const WEBRTC_STRING: u32 = 51;
+    pub const WEBRTC_BINARY: u32 = 53;
+    pub const NGAP: u32 = 60;
+    pub const XNAP: u32 = 61;
+    pub const F1AP: u32 = 62;
+}
+
+/// Build a SCTP packet from a network-layer packet, the payload of its first unfragmented DATA
+/// chunk being dispatched to the dissector of its protocol, save it in a Parsed Packet
+pub fn handle_sctp_packet(
+    source: IpAddr,
+    destination: IpAddr,
+    packet: &[u8],
+    parsed_packet: &mut ParsedPacket,
+) {
+    let Some((sctp_packet, user_message)) = parse_sctp_packet(packet) else {
+        debug!("Malformed SCTP Packet");
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed SCTP Packet".to_string(),
+        )));
+        return;
+    };
+
+    debug!(
+        "SCTP Packet: {}:{} > {}:{}; chunks: {}",
+        source,
+        sctp_packet.source,
+        destination,
+        sctp_packet.destination,
+        sctp_packet.chunks.len()
+    );
+    let (source_port, dest_port) = (sctp_packet.source, sctp_packet.destination);
+    parsed_packet.set_transport_layer_packet(Some(SerializablePacket::SctpPacket(sctp_packet)));
+
+    if let Some((ppid, payload)) = user_message {
+        let flow = FlowContext::track(source, source_port, destination, dest_port, payload.len());
+        profiled(ProfileLayer::Application, || {
+            handle_sctp_payload(&flow, ppid, payload, parsed_packet)
+        });
+    }
+}
+
+/// Pass the user message of a DATA chunk to the dissector registered for its Payload Protocol
+/// Identifier, if any
+fn handle_sctp_payload(
+    flow: &FlowContext,
+    ppid: u32,
+    payload: &[u8],
+    parsed_packet: &mut ParsedPacket,
+) {
+    match ppid {
+        PayloadProtocolIdentifiers::UNSPECIFIED => {
+            handle_application_protocol(flow, false, payload, parsed_packet)
+        }
+        PayloadProtocolIdentifiers::DIAMETER_DTLS => {
+            handle_dtls_packet(flow, payload, parsed_packet)
+        }
+        _ => debug!(
+            "SCTP payload without dissector: {}, length: {}",
+            sctp_ppid_to_string(ppid),
+            payload.len()
+        ),
+    }
+}
+
+/// Parse the common header and the chunks of a SCTP packet, getting the PPID and the payload of
+/// its first unfragmented DATA chunk
+fn parse_sctp_packet(packet: &[u8]) -> Option<(SerializableSctpPacket, Option<UserMessage<'_>>)> {
+    let header = packet.get(..SCTP_HEADER_LENGTH)?;
+    let mut sctp_packet = SerializableSctpPacket {
+        source: u16::from_be_bytes([header[0], header[1]]),
+        destination: u16::from_be_bytes([header[2], header[3]]),
+        verification_tag: u32::from_be_bytes([header[4], header[5], header[6], header[7]]),
+        checksum: u32::from_be_bytes([header[8], header[9], header[10], header[11]]),
+        chunks: vec![],
+        length: packet.len(),
+    };
+
+    let mut user_message = None;
+    let mut offset = SCTP_HEADER_LENGTH;
+    while offset < packet.len() {
+        let chunk_header = packet.get(offset..offset + CHUNK_HEADER_LENGTH)?;
+        let (chunk_type, flags) = (chunk_header[0], chunk_header[1]);
+        let length = u16::from_be_bytes([chunk_header[2], chunk_header[3]]);
+        if (length as usize) < CHUNK_HEADER_LENGTH {
+            return None;
+        }
+        let chunk = packet.get(offset..offset + length as usize)?;
+
+        let data = match chunk_type {
+            SctpChunkTypes::DATA => {
+                let data_header = chunk.get(..DATA_CHUNK_HEADER_LENGTH)?;
+                let ppid = u32::from_be_bytes([
+                    data_header[12],
+                    data_header[13],
+                    data_header[14],
+                    data_header[15],
+                ]);
+                let payload = &chunk[DATA_CHUNK_HEADER_LENGTH..];
+                if user_message.is_none() && flags & UNFRAGMENTED_FLAGS == UNFRAGMENTED_FLAGS {
+                    user_message = Some((ppid, payload));
+                }
+
+                Some(SerializableSctpData {
+                    tsn: u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]),
+                    stream_identifier: u16::from_be_bytes([chunk[8], chunk[9]]),
+                    stream_sequence_number: u16::from_be_bytes([chunk[10], chunk[11]]),
+                    payload_protocol_identifier: ppid,
+                    payload_protocol: sctp_ppid_to_string(ppid),
+                    payload_length: payload.len(),
+                })
+            }
+            _ => None,
+        };
+        sctp_packet.chunks.push(SerializableSctpChunk {
+            chunk_type: sctp_chunk_type_to_string(chunk_type),
+            flags,
+            length,
+            data,
+        });
+
+        // The padding of the last chunk may be missing
+        offset += (length as usize).next_multiple_of(4);
+    }
+
+    Some((sctp_packet, user_message))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{handle_sctp_packet, PayloadProtocolIdentifiers, SctpChunkTypes};
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    #[test]
+    fn data_chunk_payload_protocol() {
+        let parsed_packet = sctp_packet(&build_test_sctp_packet(
+            36412,
+            PayloadProtocolIdentifiers::S1AP,
+            &[0x00, 0x11, 0x00, 0x2f, 0x00],
+        ));
+
+        match parsed_packet.get_transport_layer_packet() {
+            Some(SerializablePacket::SctpPacket(sctp_packet)) => {
+                assert_eq!(sctp_packet.destination, 36412);
+                assert_eq!(sctp_packet.verification_tag, 0xcafef00d);
+                assert_eq!(sctp_packet.chunks.len(), 1);
+                assert_eq!(sctp_packet.chunks[0].chunk_type, "DATA (0)");
+
+                let data = sctp_packet.chunks[0].data.as_ref().unwrap();
+                assert_eq!((data.tsn, data.stream_identifier), (1, 2));
+                assert_eq!(data.payload_protocol_identifier, 18);
+                assert_eq!(data.payload_protocol, "S1AP (18)");
+                assert_eq!(data.payload_length, 5);
+            }
+            _ => unreachable!(),
+        }
+        assert!(parsed_packet.get_application_layer_packet().is_none());
+    }
+
+    #[test]
+    fn unspecified_payload_protocol_dispatched_by_port() {
+        // Echo request of a Modbus TCP client, on the Modbus port
+        let parsed_packet = sctp_packet(&build_test_sctp_packet(
+            502,
+            PayloadProtocolIdentifiers::UNSPECIFIED,
+            &[
+                0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x00, 0x00, 0x01,
+            ],
+        ));
+
+        assert!(matches!(
+            parsed_packet.get_application_layer_packet(),
+            Some(SerializablePacket::ModbusPacket(_))
+        ));
+    }
+
+    #[test]
+    fn malformed_sctp_packet() {
+        let packet = build_test_sctp_packet(36412, PayloadProtocolIdentifiers::S1AP, &[0; 8]);
+        let mut zero_length_chunk = packet.clone();
+        zero_length_chunk[14..16].copy_from_slice(&[0, 0]);
+
+        for packet in [&packet[..8], &packet[..30], &zero_length_chunk[..]] {
+            match sctp_packet(packet).get_transport_layer_packet() {
+                Some(SerializablePacket::MalformedPacket(str)) => {
+                    assert_eq!(str, "Malformed SCTP Packet")
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    ///////////////////// Utils
+
+    /// Build a SCTP packet with an unfragmented DATA chunk of TSN 1 on stream 2
+    fn build_test_sctp_packet(dest_port: u16, ppid: u32, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![];
+        packet.extend_from_slice(&4444u16.to_be_bytes());
+        packet.extend_from_slice(&dest_port.to_be_bytes());
+        packet.extend_from_slice(&0xcafef00du32.to_be_bytes());
+        packet.extend_from_slice(&[0; 4]);
+
+        packet.extend_from_slice(&[SctpChunkTypes::DATA, 0x03]);
+        packet.extend_from_slice(&(16 + payload.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&1u32.to_be_bytes());
+        packet.extend_from_slice(&[0x00, 0x02, 0x00, 0x00]);
+        packet.extend_from_slice(&ppid.to_be_bytes());
+        packet.extend_from_slice(payload);
+        packet.resize(packet.len().next_multiple_of(4), 0);
+        packet
+    }
+
+    fn sctp_packet(packet: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_sctp_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+            packet,
+            &mut parsed_packet,
+        );
+        parsed_packet
+    }
+}
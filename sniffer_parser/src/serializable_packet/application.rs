@@ -1,25 +1,55 @@
 //! Application level Packets Representation
 
 use std::{
-    fmt, net::{Ipv4Addr, Ipv6Addr}, str::from_utf8
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    str::from_utf8,
 };
 
-use dns_parser::{Header as DnsHeader, Packet as DnsPacket, Question, RData, ResourceRecord};
+use dns_parser::{
+    Class, Header as DnsHeader, Opcode, Packet as DnsPacket, QueryClass, QueryType, Question,
+    RData, ResourceRecord, ResponseCode,
+};
 use httparse::{Request, Response};
 use serde::Serialize;
 use tls_parser::{
-    parse_dh_params, parse_ec_parameters, parse_ecdh_params, parse_tls_extensions, ECParameters,
+    parse_dh_params, parse_ec_parameters, parse_ecdh_params, parse_tls_extensions, DTLSClientHello,
+    DTLSMessage, DTLSMessageHandshake, DTLSMessageHandshakeBody, DTLSRecordHeader, ECParameters,
     ECParametersContent, ECPoint, ExplicitPrimeContent, NamedGroup, ServerDHParams,
     ServerECDHParams, TlsCertificateContents, TlsCertificateRequestContents,
     TlsCertificateStatusContents, TlsClientHelloContents, TlsClientKeyExchangeContents,
     TlsExtension, TlsHelloRetryRequestContents, TlsMessageAlert, TlsMessageHeartbeat,
-    TlsNewSessionTicketContent, TlsNextProtocolContent, TlsRecordType, TlsServerHelloContents,
-    TlsServerHelloV13Draft18Contents, TlsServerKeyExchangeContents, TlsVersion,
+    TlsNewSessionTicketContent, TlsNextProtocolContent, TlsRecordHeader, TlsRecordType,
+    TlsServerHelloContents, TlsServerHelloV13Draft18Contents, TlsServerKeyExchangeContents,
+    TlsVersion,
+};
+use x509_parser::{
+    extensions::GeneralName, parse_x509_certificate, prelude::X509Certificate, x509::X509Name,
 };
-use x509_parser::{parse_x509_certificate, prelude::X509Certificate};
 
+use super::network::write_encapsulated;
+use super::util::{decode_base64, md5_hex};
+use super::SerializablePacket;
+use crate::dhcpv6::{Dhcpv6Message, Dhcpv6MessageTypes};
+use crate::dns::tunneling_indicators;
+use crate::gtp::{GtpHeader, GtpMessageTypes};
+use crate::is_credential_redacted;
+use crate::kerberos::{KerberosMessage, KerberosMessageTypes};
+use crate::ldap::{
+    ldap_operation_tag, LdapAuthentication, LdapMessage, LdapOperation, LdapOperations, LdapResult,
+};
 use crate::modbus::{self, ModbusPacket};
-
+use crate::nbns::{NbnsFlags, NbnsMessage, NbnsOpcodes, NbnsRecordTypes};
+use crate::quic::{QuicLongHeader, QuicPacketType, QuicVersions};
+use crate::stun::{StunAttribute, StunAttributeTypes, StunClass, StunMessage, StunMethods};
+use crate::syslog::{SyslogFormat, SyslogMessage};
+use crate::telnet::{TelnetCommand, TelnetCommands, TelnetMessage, TelnetOptions};
+use crate::tlv::{Endianness, TlvParser};
+use crate::wireguard::{WireGuardMessage, WireGuardMessageTypes};
+
+/// TLS extensions listing the elliptic curves and point formats of a Client Hello
+const SUPPORTED_GROUPS_EXTENSION: u32 = 10;
+const EC_POINT_FORMATS_EXTENSION: u32 = 11;
 
 /// HTTP Body content
 #[derive(Serialize, Debug, Clone)]
@@ -38,33 +68,159 @@ pub enum HttpContentType {
 
 /// HTTP Request Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableHttpRequestPacket {
     pub method: String,
     pub path: String,
+    /// Minor version of `HTTP/1.x`, 0 when missing from a malformed request line
     pub version: u8,
     pub headers: Vec<(String, String)>,
     pub payload: HttpContentType,
+    pub parts: Vec<MultipartPart>,
+    /// Request line with a known method, a valid path and an `HTTP/1.0` or `HTTP/1.1` version
+    pub well_formed: bool,
+    /// Why the request is not well formed, e.g. `unknown method "GETX"`
+    pub malformed_reason: Option<String>,
+    /// Name and value of the cookies of the `Cookie` headers
+    pub cookies: Vec<(String, String)>,
+    pub authorization: Option<HttpAuthorization>,
+    /// Body typed `application/dns-message`: DNS over HTTP(S)
+    pub is_doh: bool,
+    /// DNS message carried by a DoH body
+    pub dns_message: Option<SerializableDnsPacket>,
+}
+
+/// Credentials of an `Authorization` header, the secrets left out
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum HttpAuthorization {
+    /// `Basic` scheme, with the user of the decoded credentials (`None` if malformed)
+    Basic {
+        username: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Bearer {
+        token_present: bool,
+    },
+    Other {
+        scheme: String,
+    },
+}
+
+impl HttpAuthorization {
+    /// Parse the value of an `Authorization` header: `<scheme> <credentials>`
+    pub fn parse(value: &str) -> Self {
+        let (scheme, credentials) = value.trim().split_once(' ').unwrap_or((value.trim(), ""));
+        let credentials = credentials.trim();
+
+        match scheme.to_ascii_lowercase().as_str() {
+            "basic" => HttpAuthorization::Basic {
+                username: decode_base64(credentials)
+                    .and_then(|decoded| String::from_utf8(decoded).ok())
+                    .and_then(|decoded| Some(decoded.split_once(':')?.0.to_owned())),
+            },
+            "bearer" => HttpAuthorization::Bearer {
+                token_present: !credentials.is_empty(),
+            },
+            _ => HttpAuthorization::Other {
+                scheme: scheme.to_owned(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for HttpAuthorization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpAuthorization::Basic {
+                username: Some(username),
+            } => write!(f, "Basic, username: {:?}", username),
+            HttpAuthorization::Basic { username: None } => write!(f, "Basic, malformed"),
+            HttpAuthorization::Bearer { token_present } => {
+                write!(f, "Bearer, token present: {}", token_present)
+            }
+            HttpAuthorization::Other { scheme } => write!(f, "{}", scheme),
+        }
+    }
+}
+
+/// Get the headers of an HTTP message, with the cookie and credential values redacted if enabled
+fn http_headers(headers: &[httparse::Header]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|header| {
+            let value = from_utf8(header.value).unwrap_or("Not valid UTF8");
+            let is_credential = ["Cookie", "Set-Cookie", "Authorization"]
+                .iter()
+                .any(|name| header.name.eq_ignore_ascii_case(name));
+            let value = match is_credential && is_credential_redacted() {
+                true => redacted(value),
+                false => value.to_owned(),
+            };
+            (header.name.to_string(), value)
+        })
+        .collect()
+}
+
+/// Get the values of the headers with a name, ignoring its case
+fn header_values<'h>(
+    headers: &'h [httparse::Header],
+    name: &'h str,
+) -> impl Iterator<Item = &'h str> {
+    headers
+        .iter()
+        .filter(move |header| header.name.eq_ignore_ascii_case(name))
+        .filter_map(|header| from_utf8(header.value).ok())
+}
+
+/// Parse a `name=value` cookie pair, redacting the value if enabled
+fn parse_cookie(pair: &str) -> Option<(String, String)> {
+    let (name, value) = pair.trim().split_once('=')?;
+    let value = match is_credential_redacted() {
+        true => redacted(value),
+        false => value.to_owned(),
+    };
+    Some((name.trim().to_owned(), value))
+}
+
+fn redacted(value: &str) -> String {
+    format!("({} bytes redacted)", value.len())
+}
+
+/// Part of a `multipart/form-data` body: a form field or an uploaded file
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MultipartPart {
+    pub name: Option<String>,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub length: usize,
 }
 
 impl<'a, 'b> SerializableHttpRequestPacket {
-    pub fn new(packet: &Request<'a, 'b>, payload: HttpContentType) -> Self {
+    pub fn new(
+        packet: &Request<'a, 'b>,
+        payload: HttpContentType,
+        parts: Vec<MultipartPart>,
+    ) -> Self {
         SerializableHttpRequestPacket {
             method: packet.method.unwrap().to_owned(),
             path: packet.path.unwrap().to_owned(),
             version: packet.version.unwrap(),
-            headers: packet
-                .headers
-                .iter()
-                .map(|header| {
-                    (
-                        header.name.to_string(),
-                        from_utf8(header.value)
-                            .unwrap_or("Not valid UTF8")
-                            .to_owned(),
-                    )
-                })
-                .collect(),
+            headers: http_headers(packet.headers),
             payload,
+            parts,
+            well_formed: true,
+            malformed_reason: None,
+            cookies: header_values(packet.headers, "Cookie")
+                .flat_map(|cookies| cookies.split(';'))
+                .filter_map(parse_cookie)
+                .collect(),
+            authorization: header_values(packet.headers, "Authorization")
+                .next()
+                .map(HttpAuthorization::parse),
+            is_doh: false,
+            dns_message: None,
         }
     }
 }
@@ -84,18 +240,74 @@ impl fmt::Display for SerializableHttpRequestPacket {
             self.version,
             self.headers,
             self.payload
-        )
+        )?;
+
+        if let Some(malformed_reason) = &self.malformed_reason {
+            write!(f, "\n\tMalformed: {}", malformed_reason)?;
+        }
+        for part in &self.parts {
+            write!(
+                f,
+                "\n\tPart: name: {:?}, filename: {:?}, content type: {:?}, length: {}",
+                part.name, part.filename, part.content_type, part.length
+            )?;
+        }
+        if !self.cookies.is_empty() {
+            write!(f, "\n\tCookies: {:?}", self.cookies)?;
+        }
+        if let Some(authorization) = &self.authorization {
+            write!(f, "\n\tAuthorization: {}", authorization)?;
+        }
+        write_doh_message(f, self.is_doh, &self.dns_message)
+    }
+}
+
+/// Class of an HTTP status code, given by its first digit
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpStatusClass {
+    Informational,
+    Success,
+    Redirection,
+    ClientError,
+    ServerError,
+}
+
+impl HttpStatusClass {
+    /// Get the class of a status code, `None` outside of 100-599
+    pub fn from_code(code: u16) -> Option<Self> {
+        match code {
+            100..=199 => Some(HttpStatusClass::Informational),
+            200..=299 => Some(HttpStatusClass::Success),
+            300..=399 => Some(HttpStatusClass::Redirection),
+            400..=499 => Some(HttpStatusClass::ClientError),
+            500..=599 => Some(HttpStatusClass::ServerError),
+            _ => None,
+        }
     }
 }
 
 /// HTTP Response Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableHttpResponsePacket {
     pub version: u8,
     pub code: u16,
+    pub status_class: Option<HttpStatusClass>,
     pub reason: String,
     pub headers: Vec<(String, String)>,
     pub payload: HttpContentType,
+    /// Name and value of the cookies set by the `Set-Cookie` headers
+    pub cookies: Vec<(String, String)>,
+    /// Time elapsed since the request of the connection answered, set by the HTTP tracker
+    pub response_time_ms: Option<f64>,
+    /// Body typed `application/dns-message`: DNS over HTTP(S)
+    pub is_doh: bool,
+    /// DNS message carried by a DoH body
+    pub dns_message: Option<SerializableDnsPacket>,
+    /// Bytes of the body once the chunks are merged and the `Content-Encoding` decoded, before
+    /// any charset decoding: left out of the output, the payload rendering them
+    #[serde(skip)]
+    pub body: Vec<u8>,
 }
 
 impl<'a, 'b> SerializableHttpResponsePacket {
@@ -103,20 +315,18 @@ impl<'a, 'b> SerializableHttpResponsePacket {
         SerializableHttpResponsePacket {
             version: packet.version.unwrap(),
             code: packet.code.unwrap(),
+            status_class: HttpStatusClass::from_code(packet.code.unwrap()),
             reason: packet.reason.unwrap().to_owned(),
-            headers: packet
-                .headers
-                .iter()
-                .map(|header| {
-                    (
-                        header.name.to_string(),
-                        from_utf8(header.value)
-                            .unwrap_or("Not valid UTF8")
-                            .to_owned(),
-                    )
-                })
-                .collect(),
+            headers: http_headers(packet.headers),
             payload,
+            // The attributes following the cookie of a `Set-Cookie` header are left out
+            cookies: header_values(packet.headers, "Set-Cookie")
+                .filter_map(|cookie| parse_cookie(cookie.split(';').next()?))
+                .collect(),
+            response_time_ms: None,
+            is_doh: false,
+            dns_message: None,
+            body: vec![],
         }
     }
 }
@@ -136,7 +346,34 @@ impl fmt::Display for SerializableHttpResponsePacket {
             self.reason,
             self.headers,
             self.payload
-        )
+        )?;
+
+        if let Some(status_class) = self.status_class {
+            write!(f, "\n\tStatus Class: {:?}", status_class)?;
+        }
+        if !self.cookies.is_empty() {
+            write!(f, "\n\tCookies: {:?}", self.cookies)?;
+        }
+        if let Some(response_time_ms) = self.response_time_ms {
+            write!(f, "\n\tResponse Time: {:.3} ms", response_time_ms)?;
+        }
+        write_doh_message(f, self.is_doh, &self.dns_message)
+    }
+}
+
+/// Show the DNS message of a DoH request or response, on indented lines
+fn write_doh_message(
+    f: &mut fmt::Formatter<'_>,
+    is_doh: bool,
+    dns_message: &Option<SerializableDnsPacket>,
+) -> fmt::Result {
+    match (is_doh, dns_message) {
+        (_, Some(dns_message)) => {
+            let dns_message = dns_message.to_string().replace('\n', "\n\t");
+            write!(f, "\n\tDoH {}", dns_message)
+        }
+        (true, None) => write!(f, "\n\tDoH Message: malformed"),
+        (false, None) => Ok(()),
     }
 }
 
@@ -151,7 +388,39 @@ impl fmt::Display for SerializableTlsPacket {
             self.version,
             self.messages,
             self.length
-        )
+        )?;
+
+        for record in &self.records {
+            write!(
+                f,
+                "\n\tRecord: {}, Version: {}, Length: {}",
+                record.content_type, record.version, record.length
+            )?;
+        }
+        if let Some(certificate) = &self.server_certificate {
+            write!(
+                f,
+                "\n\tServer Certificate: \n\
+                \t\tSubject CN: {}\n\
+                \t\tIssuer CN: {}\n\
+                \t\tNot Before: {}\n\
+                \t\tNot After: {}\n\
+                \t\tSubject Alternative Names: {:?}",
+                certificate.subject_common_name.as_deref().unwrap_or("-"),
+                certificate.issuer_common_name.as_deref().unwrap_or("-"),
+                certificate.not_before,
+                certificate.not_after,
+                certificate.subject_alternative_names
+            )?;
+        }
+        if let Some(ja3) = &self.ja3 {
+            write!(f, "\n\tJA3: {}", ja3)?;
+        }
+        if let Some(ja3s) = &self.ja3s {
+            write!(f, "\n\tJA3S: {}", ja3s)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -165,10 +434,18 @@ pub enum TlsMalformedError {
 
 /// TLS Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableTlsPacket {
     pub version: String,
     pub messages: Vec<CustomTlsMessage>,
     pub length: u16,
+    /// Headers of the records, in order
+    pub records: Vec<CustomTlsRecord>,
+    pub server_certificate: Option<Certificate>,
+    /// MD5 digest of the JA3 string of the first Client Hello
+    pub ja3: Option<String>,
+    /// MD5 digest of the JA3S string of the first Server Hello
+    pub ja3s: Option<String>,
 }
 
 impl SerializableTlsPacket {
@@ -187,6 +464,38 @@ impl SerializableTlsPacket {
         self.length = length;
     }
 
+    /// Add the header of a transported TLS record
+    pub fn add_record(&mut self, header: &TlsRecordHeader) {
+        self.records.push(CustomTlsRecord::new(header));
+    }
+
+    /// Set the leaf certificate of the first Certificate message, if any
+    pub fn set_server_certificate(&mut self) {
+        self.server_certificate = self.messages.iter().find_map(|message| match message {
+            CustomTlsMessage::Handshake(CustomHandshakeMessage::Certificate(certificate)) => {
+                certificate.certificates.first().cloned()
+            }
+            _ => None,
+        });
+    }
+
+    /// Set the JA3 and JA3S fingerprints of the first Client Hello and Server Hello messages, if any
+    pub fn set_fingerprints(&mut self) {
+        for message in &self.messages {
+            match message {
+                CustomTlsMessage::Handshake(CustomHandshakeMessage::ClientHello(hello)) => {
+                    self.ja3
+                        .get_or_insert_with(|| md5_hex(hello.ja3.as_bytes()));
+                }
+                CustomTlsMessage::Handshake(CustomHandshakeMessage::ServerHello(hello)) => {
+                    self.ja3s
+                        .get_or_insert_with(|| md5_hex(hello.ja3s.as_bytes()));
+                }
+                _ => (),
+            }
+        }
+    }
+
     /// Check if TLS packet is not initialized
     pub fn is_default(&self) -> bool {
         self.length == 0 && self.messages.is_empty() && self.version == "".to_owned()
@@ -199,10 +508,47 @@ impl Default for SerializableTlsPacket {
             version: "".to_owned(),
             messages: vec![],
             length: 0,
+            records: vec![],
+            server_certificate: None,
+            ja3: None,
+            ja3s: None,
+        }
+    }
+}
+
+/// TLS Record Header
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomTlsRecord {
+    pub content_type: String,
+    pub version: String,
+    pub length: u16,
+}
+
+impl CustomTlsRecord {
+    pub fn new(header: &TlsRecordHeader) -> Self {
+        CustomTlsRecord {
+            content_type: tls_content_type_to_string(header.record_type),
+            version: format!("{}", header.version),
+            length: header.len,
         }
     }
 }
 
+/// Get the name of the content type of a TLS record (RFC 8446 B.1)
+pub fn tls_content_type_to_string(record_type: TlsRecordType) -> String {
+    let name = match record_type {
+        TlsRecordType::ChangeCipherSpec => "change_cipher_spec",
+        TlsRecordType::Alert => "alert",
+        TlsRecordType::Handshake => "handshake",
+        TlsRecordType::ApplicationData => "application_data",
+        TlsRecordType::Heartbeat => "heartbeat",
+        _ => "unknown",
+    };
+
+    format!("{} ({})", name, record_type.0)
+}
+
 /// Types of TLS Messages
 #[derive(Serialize, Debug, Clone)]
 #[serde(tag = "type")]
@@ -242,6 +588,7 @@ pub enum CustomHandshakeMessage {
 
 /// TLS Alert Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CustomAlertMessage {
     pub severity: String,
     pub description: String,
@@ -258,6 +605,7 @@ impl CustomAlertMessage {
 
 /// TLS Heartbeat Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CustomHeartbeatMessage {
     pub heartbeat_type: String,
     pub payload: Vec<u8>,
@@ -276,6 +624,7 @@ impl CustomHeartbeatMessage {
 
 /// TLS Client Hello Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct ClientHelloMessage {
     pub version: String,
     pub rand_time: u32,
@@ -284,6 +633,8 @@ pub struct ClientHelloMessage {
     pub ciphers: Vec<String>,
     pub compressions: Vec<String>,
     pub extensions: Vec<String>,
+    /// JA3 string: version, ciphers, extensions, elliptic curves and point formats
+    pub ja3: String,
 }
 
 impl ClientHelloMessage {
@@ -299,10 +650,69 @@ impl ClientHelloMessage {
                 Ok((_, exts)) => parse_custom_tls_extensions(exts),
                 Err(_) => vec!["Error parsing".to_owned()],
             },
+            ja3: ja3_string(message),
         }
     }
 }
 
+/// Get the JA3 string of a Client Hello, the fields in decimal: version, ciphers, extension types,
+/// elliptic curves and point formats
+fn ja3_string(message: &TlsClientHelloContents) -> String {
+    let extensions: Vec<_> = TlvParser::new(message.ext.unwrap_or(b""), 2, 2, Endianness::Big)
+        .map_while(Result::ok)
+        .collect();
+    let extension_value = |extension_type| {
+        extensions
+            .iter()
+            .find(|extension| extension.tlv_type == extension_type)
+            .map_or(&[][..], |extension| extension.value)
+    };
+    // Lists preceded by their length on two bytes and one byte
+    let curves = extension_value(SUPPORTED_GROUPS_EXTENSION)
+        .get(2..)
+        .unwrap_or(&[])
+        .chunks_exact(2)
+        .map(|group| u16::from_be_bytes([group[0], group[1]]));
+    let point_formats = extension_value(EC_POINT_FORMATS_EXTENSION)
+        .get(1..)
+        .unwrap_or(&[])
+        .iter()
+        .map(|&format| format as u16);
+
+    format!(
+        "{},{},{},{},{}",
+        message.version.0,
+        ja3_field(message.ciphers.iter().map(|cipher| cipher.0)),
+        ja3_field(extensions.iter().map(|extension| extension.tlv_type as u16)),
+        ja3_field(curves),
+        ja3_field(point_formats),
+    )
+}
+
+/// Get the JA3S string of a Server Hello, the fields in decimal: version, cipher and extension
+/// types
+fn ja3s_string(message: &TlsServerHelloContents) -> String {
+    let extension_types = TlvParser::new(message.ext.unwrap_or(b""), 2, 2, Endianness::Big)
+        .map_while(Result::ok)
+        .map(|extension| extension.tlv_type as u16);
+
+    format!(
+        "{},{},{}",
+        message.version.0,
+        message.cipher.0,
+        ja3_field(extension_types)
+    )
+}
+
+/// Join the values of a JA3 field with dashes, without the GREASE values (RFC 8701)
+fn ja3_field(values: impl Iterator<Item = u16>) -> String {
+    values
+        .filter(|value| value & 0x0f0f != 0x0a0a || value >> 8 != value & 0xff)
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
 /// Get custom TLS extension contained in TLS packet
 pub(crate) fn parse_custom_tls_extensions(exts: Vec<TlsExtension>) -> Vec<String> {
     let mut new_extensions = vec![];
@@ -488,6 +898,7 @@ fn parse_signature_algorithm(data: &u16) -> String {
 
 /// TLS Server Hello Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct ServerHelloMessage {
     pub version: String,
     pub rand_time: u32,
@@ -496,6 +907,8 @@ pub struct ServerHelloMessage {
     pub cipher: String,
     pub compression: String,
     pub extensions: Vec<String>,
+    /// JA3S string: version, cipher and extensions
+    pub ja3s: String,
 }
 
 impl ServerHelloMessage {
@@ -511,12 +924,14 @@ impl ServerHelloMessage {
                 Ok((_, exts)) => parse_custom_tls_extensions(exts),
                 Err(_) => vec!["Error parsing".to_owned()],
             },
+            ja3s: ja3s_string(message),
         }
     }
 }
 
 /// TLS Certificate details
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Certificate {
     pub signature_algorithm: String,
     pub signature_value: Vec<u8>,
@@ -528,10 +943,23 @@ pub struct Certificate {
     // pub subject_pki: String,
     pub validity: String,
     pub version: String,
+
+    pub subject_common_name: Option<String>,
+    pub issuer_common_name: Option<String>,
+    pub not_before: String,
+    pub not_after: String,
+    pub subject_alternative_names: Vec<String>,
 }
 
 impl Certificate {
     fn new(cert: &X509Certificate) -> Self {
+        let common_name = |name: &X509Name| {
+            name.iter_common_name()
+                .next()
+                .and_then(|cn| cn.as_str().ok())
+                .map(|cn| cn.to_owned())
+        };
+
         Certificate {
             signature_algorithm: cert.signature_algorithm.oid().to_id_string(),
             signature_value: cert.signature_value.data.to_vec(),
@@ -553,12 +981,49 @@ impl Certificate {
                 cert.validity.not_before, cert.validity.not_after
             ),
             version: cert.version.to_string(),
+
+            subject_common_name: common_name(cert.subject()),
+            issuer_common_name: common_name(cert.issuer()),
+            not_before: cert.validity.not_before.to_string(),
+            not_after: cert.validity.not_after.to_string(),
+            subject_alternative_names: match cert.subject_alternative_name() {
+                Ok(Some(san)) => san
+                    .value
+                    .general_names
+                    .iter()
+                    .filter_map(general_name_to_string)
+                    .collect(),
+                _ => vec![],
+            },
         }
     }
 }
 
+/// Get a Subject Alternative Name, `None` for the name types without a textual form
+fn general_name_to_string(name: &GeneralName) -> Option<String> {
+    match name {
+        GeneralName::DNSName(name) => Some(format!("DNS:{}", name)),
+        GeneralName::RFC822Name(name) => Some(format!("email:{}", name)),
+        GeneralName::URI(uri) => Some(format!("URI:{}", uri)),
+        GeneralName::IPAddress(address) => match address.len() {
+            4 => Some(format!(
+                "IP:{}",
+                Ipv4Addr::from(<[u8; 4]>::try_from(*address).unwrap())
+            )),
+            16 => Some(format!(
+                "IP:{}",
+                Ipv6Addr::from(<[u8; 16]>::try_from(*address).unwrap())
+            )),
+            _ => None,
+        },
+        GeneralName::DirectoryName(name) => Some(format!("DirName:{}", name)),
+        _ => None,
+    }
+}
+
 /// TLS Certificate Message: list of certificates
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CertificateMessage {
     pub certificates: Vec<Certificate>,
 }
@@ -585,6 +1050,7 @@ impl CertificateMessage {
 
 /// TLS Certificate Revocation Request: list hash algorithms
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CertificateRequestMessage {
     pub sig_hash_algos: Vec<u16>,
 }
@@ -603,6 +1069,7 @@ impl CertificateRequestMessage {
 
 /// TLS Certificate Status Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CertificateStatusMessage {
     pub status_type: String,
     pub data: Vec<u8>,
@@ -622,6 +1089,7 @@ impl CertificateStatusMessage {
 
 /// TLS Certificate Verify Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CertificateVerifyMessage {
     pub data: Vec<u8>,
 }
@@ -646,6 +1114,7 @@ pub enum ClientParameters {
 
 /// Client Parameters for TLS Elliptic-Curve Diffie-Hellman
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct ClientEcdhParameters {
     pub point: Vec<u8>,
 }
@@ -660,6 +1129,7 @@ impl ClientEcdhParameters {
 
 /// TLS Client Key Exchange Message with parameters
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct ClientKeyExchangeMessage {
     pub parameters: ClientParameters,
 }
@@ -696,6 +1166,7 @@ impl ClientKeyExchangeMessage {
 
 /// TLS Finished Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct FinishedMessage {
     pub data: Vec<u8>,
 }
@@ -710,6 +1181,7 @@ impl FinishedMessage {
 
 /// TLS Hello Retry Request Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct HelloRetryRequestMessage {
     pub cipher: String,
     pub extensions: Vec<String>,
@@ -731,6 +1203,7 @@ impl HelloRetryRequestMessage {
 
 /// TLS New Session ticket Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct NewSessionTicketMessage {
     pub ticket: Vec<u8>,
     pub ticket_lifetime_hint: u32,
@@ -747,6 +1220,7 @@ impl NewSessionTicketMessage {
 
 /// TLS New Protocol Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct NextProtocolMessage {
     pub selected_protocol: Vec<u8>,
     pub padding: Vec<u8>,
@@ -763,6 +1237,7 @@ impl NextProtocolMessage {
 
 /// TLS Server Done message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct ServerDoneMessage {
     pub data: Vec<u8>,
 }
@@ -777,6 +1252,7 @@ impl ServerDoneMessage {
 
 /// TLS Server Hello V13Draft18 Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct ServerHelloV13Draft18Message {
     pub version: String,
     pub random: Vec<u8>,
@@ -810,6 +1286,7 @@ pub enum ServerParameters {
 
 /// TLS Server Parameters for Elliptic Curve Diffie-Hellman
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct ServerEcdhParameters {
     pub public_point: Vec<u8>,
     pub curve: ServerEcParameters,
@@ -826,6 +1303,7 @@ impl ServerEcdhParameters {
 
 /// TLS Server Parameters for Diffie-Hellman
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct ServerDhParameters {
     pub prime_modulus: Vec<u8>,
     pub generator: Vec<u8>,
@@ -852,6 +1330,7 @@ pub enum CustomEcContent {
 
 /// TLS Custom Named Group
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CustomNamedGroup {
     pub group: String,
 }
@@ -866,6 +1345,7 @@ impl CustomNamedGroup {
 
 /// TLS Custom Explicit Prime
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CustomExplicitPrime {
     pub prime_p: Vec<u8>,
     pub curve: (Vec<u8>, Vec<u8>),
@@ -888,6 +1368,7 @@ impl CustomExplicitPrime {
 
 /// TLS Server Elliptic Curve Parameters
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct ServerEcParameters {
     pub ec_type: String,
     pub ec_content: CustomEcContent,
@@ -911,6 +1392,7 @@ impl ServerEcParameters {
 
 /// TLS Server Key Exchange Message with parameters
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct ServerKeyExchangeMessage {
     pub parameters: ServerParameters,
 }
@@ -943,6 +1425,7 @@ impl ServerKeyExchangeMessage {
 
 /// TLS Custom Encrypted Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CustomEncryptedMessage {
     pub version: String,
     pub message_type: String,
@@ -961,6 +1444,7 @@ impl CustomEncryptedMessage {
 
 /// TLS Application Data Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CustomApplicationDataMessage {
     pub data: Vec<u8>,
 }
@@ -975,6 +1459,7 @@ impl CustomApplicationDataMessage {
 
 /// TLS Malformed Data Message
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CustomMalformedMessage {
     pub version: String,
     pub message_type: String,
@@ -1004,18 +1489,171 @@ impl CustomMalformedMessage {
     }
 }
 
+/// DTLS Packet Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableDtlsPacket {
+    pub version: String,
+    pub epoch: u16,
+    pub sequence_number: u64,
+    pub messages: Vec<CustomDtlsMessage>,
+    pub length: u16,
+}
+
+impl SerializableDtlsPacket {
+    /// Build a DTLS packet from the header of its first record
+    pub fn new(header: &DTLSRecordHeader) -> Self {
+        SerializableDtlsPacket {
+            version: format!("{}", header.version),
+            epoch: header.epoch,
+            sequence_number: header.sequence_number,
+            messages: vec![],
+            length: header.length,
+        }
+    }
+}
+
+impl fmt::Display for SerializableDtlsPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "DTLS Packet: \n\
+            \tVersion: {}\n\
+            \tEpoch: {}\n\
+            \tSequence Number: {}\n\
+            \tMessages: {:?}\n\
+            \tLength: {}",
+            self.version, self.epoch, self.sequence_number, self.messages, self.length
+        )
+    }
+}
+
+/// Types of DTLS Messages
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum CustomDtlsMessage {
+    ChangeCipherSpec,
+    Alert(CustomAlertMessage),
+    Handshake(CustomDtlsHandshakeMessage),
+    ApplicationData(CustomApplicationDataMessage),
+    Heartbeat(CustomHeartbeatMessage),
+
+    Encrypted(CustomEncryptedMessage),
+}
+
+impl From<&DTLSMessage<'_>> for CustomDtlsMessage {
+    fn from(message: &DTLSMessage<'_>) -> Self {
+        match message {
+            DTLSMessage::Handshake(handshake) => {
+                CustomDtlsMessage::Handshake(CustomDtlsHandshakeMessage::from(handshake))
+            }
+            DTLSMessage::ChangeCipherSpec => CustomDtlsMessage::ChangeCipherSpec,
+            DTLSMessage::Alert(alert) => CustomDtlsMessage::Alert(CustomAlertMessage::new(alert)),
+            DTLSMessage::ApplicationData(data) => {
+                CustomDtlsMessage::ApplicationData(CustomApplicationDataMessage::new(data.blob))
+            }
+            DTLSMessage::Heartbeat(heartbeat) => {
+                CustomDtlsMessage::Heartbeat(CustomHeartbeatMessage::new(heartbeat))
+            }
+        }
+    }
+}
+
+/// Types of DTLS Handshake Messages
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "subType", content = "content")]
+pub enum CustomDtlsHandshakeMessage {
+    ClientHello(DtlsClientHelloMessage),
+    HelloVerifyRequest(HelloVerifyRequestMessage),
+    ServerHello(ServerHelloMessage),
+    Other(String),
+}
+
+impl From<&DTLSMessageHandshake<'_>> for CustomDtlsHandshakeMessage {
+    fn from(handshake: &DTLSMessageHandshake<'_>) -> Self {
+        match &handshake.body {
+            DTLSMessageHandshakeBody::ClientHello(hello) => {
+                CustomDtlsHandshakeMessage::ClientHello(DtlsClientHelloMessage::new(
+                    hello,
+                    handshake.message_seq,
+                ))
+            }
+            DTLSMessageHandshakeBody::HelloVerifyRequest(request) => {
+                CustomDtlsHandshakeMessage::HelloVerifyRequest(HelloVerifyRequestMessage {
+                    server_version: format!("{}", request.server_version),
+                    cookie: request.cookie.to_vec(),
+                })
+            }
+            DTLSMessageHandshakeBody::ServerHello(hello) => {
+                CustomDtlsHandshakeMessage::ServerHello(ServerHelloMessage::new(hello))
+            }
+            _ => CustomDtlsHandshakeMessage::Other(format!("{}", handshake.msg_type)),
+        }
+    }
+}
+
+/// DTLS Client Hello Message
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DtlsClientHelloMessage {
+    pub version: String,
+    pub message_seq: u16,
+    pub random: Vec<u8>,
+    pub session_id: Option<Vec<u8>>,
+    pub cookie: Vec<u8>,
+    pub ciphers: Vec<String>,
+    pub compressions: Vec<String>,
+    pub extensions: Vec<String>,
+}
+
+impl DtlsClientHelloMessage {
+    pub fn new(message: &DTLSClientHello, message_seq: u16) -> Self {
+        DtlsClientHelloMessage {
+            version: format!("{}", message.version),
+            message_seq,
+            random: message.random.to_vec(),
+            session_id: message.session_id.map(|v| v.to_vec()),
+            cookie: message.cookie.to_vec(),
+            ciphers: message.ciphers.iter().map(|c| format!("{:?}", c)).collect(),
+            compressions: message.comp.iter().map(|c| format!("{:?}", c)).collect(),
+            extensions: match parse_tls_extensions(message.ext.unwrap_or(b"")) {
+                Ok((_, exts)) => parse_custom_tls_extensions(exts),
+                Err(_) => vec!["Error parsing".to_owned()],
+            },
+        }
+    }
+}
+
+/// DTLS Hello Verify Request Message
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HelloVerifyRequestMessage {
+    pub server_version: String,
+    pub cookie: Vec<u8>,
+}
+
 /// DNS Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableDnsPacket {
     pub header: CustomDnsHeader,
     pub questions: Vec<CustomQuestion>,
     pub answers: Vec<CustomResourceRecord>,
     pub nameservers: Vec<CustomResourceRecord>,
     pub additional: Vec<CustomResourceRecord>,
+    /// Time elapsed since the matching query, for responses tracked by a `DnsTracker`
+    pub response_time_ms: Option<f64>,
+    /// Likelihood, from 0 to 1, that the queries tunnel data through DNS (heuristic)
+    pub tunneling_score: f32,
+    /// Indicators behind the tunneling score, e.g. `long label (52 chars)`
+    pub tunneling_reasons: Vec<String>,
+    /// Service instances announced by a Multicast DNS message, `None` for unicast DNS
+    pub mdns_services: Option<Vec<MdnsService>>,
 }
 
 impl<'a> From<&DnsPacket<'a>> for SerializableDnsPacket {
     fn from(dns_packet: &DnsPacket<'a>) -> Self {
+        let (tunneling_score, tunneling_reasons) = tunneling_indicators(&dns_packet.questions);
         SerializableDnsPacket {
             header: CustomDnsHeader::from(&dns_packet.header),
             questions: dns_packet
@@ -1038,6 +1676,10 @@ impl<'a> From<&DnsPacket<'a>> for SerializableDnsPacket {
                 .iter()
                 .map(|r| CustomResourceRecord::from(r))
                 .collect(),
+            response_time_ms: None,
+            tunneling_score,
+            tunneling_reasons,
+            mdns_services: None,
         }
     }
 }
@@ -1056,32 +1698,173 @@ impl fmt::Display for SerializableDnsPacket {
             self.answers,
             self.nameservers,
             self.additional
-        )
+        )?;
+
+        let mut reverse_lookups: Vec<String> = vec![];
+        let names = self
+            .questions
+            .iter()
+            .filter_map(|q| Some((&q.query_name, q.reverse_address?)))
+            .chain(
+                self.answers
+                    .iter()
+                    .filter_map(|rr| Some((&rr.name, rr.reverse_address?))),
+            );
+        for (name, address) in names {
+            let lookup = format!("{} ({})", name, address);
+            if !reverse_lookups.contains(&lookup) {
+                reverse_lookups.push(lookup);
+            }
+        }
+        if !reverse_lookups.is_empty() {
+            write!(f, "\n\tReverse Lookups: {}", reverse_lookups.join(", "))?;
+        }
+        if let Some(response_time_ms) = self.response_time_ms {
+            write!(f, "\n\tResponse Time: {:.3} ms", response_time_ms)?;
+        }
+        if self.tunneling_score > 0.0 {
+            write!(
+                f,
+                "\n\tTunneling Score: {:.2} (heuristic: {})",
+                self.tunneling_score,
+                self.tunneling_reasons.join(", ")
+            )?;
+        }
+        if let Some(mdns_services) = &self.mdns_services {
+            let services: Vec<String> = mdns_services.iter().map(|s| s.to_string()).collect();
+            write!(f, "\n\tmDNS Services: {}", services.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Service instance discovered through Multicast DNS, e.g. `Printer._http._tcp.local`
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MdnsService {
+    pub instance: String,
+    /// Service type of the instance, e.g. `_http._tcp.local`
+    pub service_type: String,
+    /// Host and port of the SRV record of the instance, if in the message
+    pub target: Option<String>,
+    pub port: Option<u16>,
+    /// Key/value pairs of the TXT record of the instance, a key without `=` having an empty value
+    pub txt: Vec<(String, String)>,
+}
+
+impl fmt::Display for MdnsService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.instance)?;
+        match (&self.target, self.port) {
+            (Some(target), Some(port)) => write!(f, " -> {}:{}", target, port)?,
+            (Some(target), None) => write!(f, " -> {}", target)?,
+            _ => (),
+        }
+        if !self.txt.is_empty() {
+            let txt: Vec<String> = self
+                .txt
+                .iter()
+                .map(|(key, value)| match value.is_empty() {
+                    true => key.clone(),
+                    false => format!("{}={}", key, value),
+                })
+                .collect();
+            write!(f, " [{}]", txt.join(", "))?;
+        }
+
+        Ok(())
     }
 }
 
 /// DNS Query request
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CustomQuestion {
     pub query_name: String,
     pub prefer_unicast: bool,
     pub query_type: String,
     pub query_class: String,
+    /// Address looked up by a PTR query of an `in-addr.arpa` or `ip6.arpa` name
+    pub reverse_address: Option<IpAddr>,
 }
 
 impl From<&Question<'_>> for CustomQuestion {
     fn from(question: &Question<'_>) -> Self {
+        let query_name = question.qname.to_string();
         CustomQuestion {
-            query_name: question.qname.to_string(),
+            reverse_address: match question.qtype {
+                QueryType::PTR => reverse_name_to_address(&query_name),
+                _ => None,
+            },
+            query_name,
             prefer_unicast: question.prefer_unicast,
-            query_type: format!("{:?}", question.qtype),
-            query_class: format!("{:?}", question.qclass),
+            query_type: dns_query_type_to_string(question.qtype),
+            query_class: dns_query_class_to_string(question.qclass),
+        }
+    }
+}
+
+/// Get the address of a reverse lookup name: the 4 bytes of an IPv4 address in reverse order
+/// under `in-addr.arpa`, or the 32 nibbles of an IPv6 address in reverse order under `ip6.arpa`;
+/// `None` for the names of the partial zones
+pub fn reverse_name_to_address(name: &str) -> Option<IpAddr> {
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    if let Some(labels) = name.strip_suffix(".in-addr.arpa") {
+        let mut octets = labels
+            .split('.')
+            .map(|label| match label.len() {
+                1..=3 if label.bytes().all(|b| b.is_ascii_digit()) => label.parse::<u8>().ok(),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        octets.reverse();
+        let octets: [u8; 4] = octets.try_into().ok()?;
+        Some(IpAddr::V4(Ipv4Addr::from(octets)))
+    } else if let Some(labels) = name.strip_suffix(".ip6.arpa") {
+        let nibbles = labels
+            .split('.')
+            .map(|label| match label.len() {
+                1 => u8::from_str_radix(label, 16).ok(),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        if nibbles.len() != 32 {
+            return None;
+        }
+        let mut octets = [0u8; 16];
+        for (octet, pair) in octets.iter_mut().zip(nibbles.rchunks(2)) {
+            *octet = pair[1] << 4 | pair[0];
+        }
+        Some(IpAddr::V6(Ipv6Addr::from(octets)))
+    } else {
+        None
+    }
+}
+
+/// Get the reverse lookup name of an address, the inverse of `reverse_name_to_address`
+pub fn address_to_reverse_name(address: IpAddr) -> String {
+    match address {
+        IpAddr::V4(ipv4) => {
+            let [a, b, c, d] = ipv4.octets();
+            format!("{}.{}.{}.{}.in-addr.arpa", d, c, b, a)
+        }
+        IpAddr::V6(ipv6) => {
+            let nibbles: Vec<String> = ipv6
+                .octets()
+                .iter()
+                .rev()
+                .flat_map(|octet| [octet & 0x0f, octet >> 4])
+                .map(|nibble| format!("{:x}", nibble))
+                .collect();
+            format!("{}.ip6.arpa", nibbles.join("."))
         }
     }
 }
 
 /// DNS Header
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CustomDnsHeader {
     pub id: u16,
     pub query: bool,
@@ -1104,14 +1887,14 @@ impl From<&DnsHeader> for CustomDnsHeader {
         CustomDnsHeader {
             id: header.id,
             query: header.query,
-            opcode: format!("{:?}", header.opcode),
+            opcode: dns_opcode_to_string(header.opcode),
             authoritative: header.authoritative,
             truncated: header.truncated,
             recursion_desired: header.recursion_desired,
             recursion_available: header.recursion_available,
             authenticated_data: header.authenticated_data,
             checking_disabled: header.checking_disabled,
-            response_code: format!("{:?}", header.response_code),
+            response_code: dns_response_code_to_string(header.response_code),
             num_questions: header.questions,
             num_answers: header.answers,
             num_nameservers: header.nameservers,
@@ -1120,22 +1903,91 @@ impl From<&DnsHeader> for CustomDnsHeader {
     }
 }
 
+/// Get DNS Query Type
+pub fn dns_query_type_to_string(query_type: QueryType) -> String {
+    let name = match query_type {
+        QueryType::All => "ANY".to_string(),
+        _ => format!("{:?}", query_type),
+    };
+
+    format!("{} ({})", name, query_type as u16)
+}
+
+/// Get DNS Query Class
+pub fn dns_query_class_to_string(query_class: QueryClass) -> String {
+    let name = match query_class {
+        QueryClass::Any => "ANY".to_string(),
+        _ => format!("{:?}", query_class),
+    };
+
+    format!("{} ({})", name, query_class as u16)
+}
+
+/// Get DNS Resource Record Class
+pub fn dns_class_to_string(class: Class) -> String {
+    format!("{:?} ({})", class, class as u16)
+}
+
+/// Get DNS Opcode
+pub fn dns_opcode_to_string(opcode: Opcode) -> String {
+    let code: u16 = opcode.into();
+    let name = match code {
+        0 => "QUERY",
+        1 => "IQUERY",
+        2 => "STATUS",
+        4 => "NOTIFY",
+        5 => "UPDATE",
+        6 => "DSO",
+        _ => "Unknown",
+    };
+
+    format!("{} ({})", name, code)
+}
+
+/// Get DNS Response Code
+pub fn dns_response_code_to_string(response_code: ResponseCode) -> String {
+    let code: u8 = response_code.into();
+    let name = match code {
+        0 => "NOERROR",
+        1 => "FORMERR",
+        2 => "SERVFAIL",
+        3 => "NXDOMAIN",
+        4 => "NOTIMP",
+        5 => "REFUSED",
+        6 => "YXDOMAIN",
+        7 => "YXRRSET",
+        8 => "NXRRSET",
+        9 => "NOTAUTH",
+        10 => "NOTZONE",
+        _ => "Unknown",
+    };
+
+    format!("{} ({})", name, code)
+}
+
 /// DNS Resource Record
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct CustomResourceRecord {
     pub name: String,
     pub multicast_unique: bool,
     pub class: String,
     pub ttl: u32,
     pub data: CustomResourceData,
+    /// Address named by the owner of a PTR record, under `in-addr.arpa` or `ip6.arpa`
+    pub reverse_address: Option<IpAddr>,
 }
 
 impl From<&ResourceRecord<'_>> for CustomResourceRecord {
     fn from(rr: &ResourceRecord<'_>) -> Self {
         CustomResourceRecord {
+            reverse_address: match rr.data {
+                RData::PTR(_) => reverse_name_to_address(&rr.name.to_string()),
+                _ => None,
+            },
             name: rr.name.to_string(),
             multicast_unique: rr.multicast_unique,
-            class: format!("{:?}", rr.cls),
+            class: dns_class_to_string(rr.cls),
             ttl: rr.ttl,
             data: CustomResourceData::from(&rr.data),
         }
@@ -1206,24 +2058,28 @@ impl From<&RData<'_>> for CustomResourceData {
 
 /// DNS Resource Data of type A (IPv4)
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct A {
     pub address: Ipv4Addr,
 }
 
 /// DNS Resource Data of type AAAA (IPv6)
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Aaaa {
     pub address: Ipv6Addr,
 }
 
 /// DNS Resource Data of type Cname
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Cname {
     pub name: String,
 }
 
 /// DNS Resource Data of type MX
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Mx {
     pub preference: u16,
     pub exchange: String,
@@ -1231,18 +2087,21 @@ pub struct Mx {
 
 /// DNS Resource Data of type NS
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Ns {
     pub name: String,
 }
 
 /// DNS Resource Data of type PTR
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Ptr {
     pub name: String,
 }
 
 /// DNS Resource Data of type SOA
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Soa {
     pub primary_ns: String,
     pub mailbox: String,
@@ -1255,6 +2114,7 @@ pub struct Soa {
 
 /// DNS Resource Data of type SRV
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Srv {
     pub priority: u16,
     pub weight: u16,
@@ -1264,23 +2124,36 @@ pub struct Srv {
 
 /// DNS Resource Data of type TXT
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Txt {
     pub data: Vec<u8>,
 }
 
 /// DNS Unknown Resource Data
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Unknown {
     pub data: Vec<u8>,
 }
 
 /// Modbus Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableModbusPacket {
     pub address: u8,
     pub function_code: u8,
     pub data: Vec<u8>,
     pub crc: Option<u16>,  // CRC is None for Modbus TCP
+    /// Transaction ID of the MBAP header (Modbus TCP only)
+    pub transaction_id: Option<u16>,
+    /// Exception code of an exception response (Modbus TCP only)
+    pub exception_code: Option<u8>,
+    /// Sent by the server port
+    pub is_response: bool,
+    /// Time elapsed since the request of the same transaction, for a response
+    pub response_time_ms: Option<f64>,
+    /// Response to no pending request of its transaction
+    pub is_unmatched: bool,
 }
 
 impl From<&ModbusPacket> for SerializableModbusPacket {
@@ -1290,7 +2163,1246 @@ impl From<&ModbusPacket> for SerializableModbusPacket {
             function_code: modbus_packet.function_code,
             data: modbus_packet.data.clone(),
             crc: modbus_packet.crc,
+            transaction_id: modbus_packet.transaction_id,
+            exception_code: match modbus_packet.function_code & modbus::EXCEPTION_FLAG {
+                0 => None,
+                _ => modbus_packet.data.first().copied(),
+            },
+            is_response: false,
+            response_time_ms: None,
+            is_unmatched: false,
+        }
+    }
+}
+
+/// QUIC Packet Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableQuicPacket {
+    pub version: String,
+    pub packet_type: String,
+    pub destination_connection_id: Vec<u8>,
+    pub source_connection_id: Vec<u8>,
+    pub token_length: Option<u64>,
+    pub length: Option<u64>,
+}
+
+impl From<&QuicLongHeader> for SerializableQuicPacket {
+    fn from(header: &QuicLongHeader) -> Self {
+        SerializableQuicPacket {
+            version: quic_version_to_string(header.version),
+            packet_type: match header.packet_type {
+                QuicPacketType::Initial => "Initial",
+                QuicPacketType::ZeroRtt => "0-RTT",
+                QuicPacketType::Handshake => "Handshake",
+                QuicPacketType::Retry => "Retry",
+                QuicPacketType::VersionNegotiation => "VersionNegotiation",
+            }
+            .to_owned(),
+            destination_connection_id: header.destination_connection_id.clone(),
+            source_connection_id: header.source_connection_id.clone(),
+            token_length: header.token_length,
+            length: header.length,
+        }
+    }
+}
+
+impl fmt::Display for SerializableQuicPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "QUIC Packet: \n\
+            \tVersion: {}\n\
+            \tPacket Type: {}\n\
+            \tDestination Connection ID: {:02x?}\n\
+            \tSource Connection ID: {:02x?}\n\
+            \tToken Length: {:?}\n\
+            \tLength: {:?}",
+            self.version,
+            self.packet_type,
+            self.destination_connection_id,
+            self.source_connection_id,
+            self.token_length,
+            self.length
+        )
+    }
+}
+
+/// Get QUIC Version
+pub fn quic_version_to_string(version: u32) -> String {
+    match version {
+        QuicVersions::NEGOTIATION => format!("Negotiation ({:#010x})", version),
+        QuicVersions::V1 => format!("QUICv1 ({:#010x})", version),
+        QuicVersions::V2 => format!("QUICv2 ({:#010x})", version),
+        v if v >> 8 == 0xff0000 => format!("Draft-{} ({:#010x})", v & 0xff, version),
+        _ => format!("Unknown ({:#010x})", version),
+    }
+}
+
+/// SMTP Packet Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", content = "content")]
+pub enum SerializableSmtpPacket {
+    Commands(Vec<SmtpCommand>),
+    Responses(Vec<SmtpResponse>),
+    MailData(usize),
+    /// Length of the data continuing the dialog: the response to an AUTH challenge, or the rest
+    /// of a reply line split across segments
+    Continuation(usize),
+    Encrypted(usize),
+}
+
+impl fmt::Display for SerializableSmtpPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializableSmtpPacket::Commands(commands) => {
+                write!(f, "SMTP Packet: \n\tCommands: {:?}", commands)
+            }
+            SerializableSmtpPacket::Responses(responses) => {
+                write!(f, "SMTP Packet: \n\tResponses: {:?}", responses)
+            }
+            SerializableSmtpPacket::MailData(length) => {
+                write!(f, "SMTP Packet: \n\tMail Data Length: {}", length)
+            }
+            SerializableSmtpPacket::Continuation(length) => {
+                write!(f, "SMTP Packet: \n\tContinuation Length: {}", length)
+            }
+            SerializableSmtpPacket::Encrypted(length) => {
+                write!(f, "SMTP Packet: \n\tEncrypted Length: {}", length)
+            }
+        }
+    }
+}
+
+/// SMTP Command, with the envelope sender/recipient of MAIL/RCPT
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SmtpCommand {
+    pub verb: String,
+    pub argument: Option<String>,
+    pub sender: Option<String>,
+    pub recipient: Option<String>,
+}
+
+/// SMTP Reply, one entry per line of multi-line replies
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SmtpResponse {
+    pub code: u16,
+    pub lines: Vec<String>,
+}
+
+/// POP3 Packet Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", content = "content")]
+pub enum SerializablePop3Packet {
+    Commands(Vec<Pop3Command>),
+    Response(Pop3Response),
+    MessageData(usize),
+    Encrypted(usize),
+}
+
+impl fmt::Display for SerializablePop3Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializablePop3Packet::Commands(commands) => {
+                write!(f, "POP3 Packet: \n\tCommands: {:?}", commands)
+            }
+            SerializablePop3Packet::Response(response) => {
+                write!(f, "POP3 Packet: \n\tResponse: {:?}", response)
+            }
+            SerializablePop3Packet::MessageData(length) => {
+                write!(f, "POP3 Packet: \n\tMessage Data Length: {}", length)
+            }
+            SerializablePop3Packet::Encrypted(length) => {
+                write!(f, "POP3 Packet: \n\tEncrypted Length: {}", length)
+            }
+        }
+    }
+}
+
+/// POP3 Command, flagged when it starts TLS (STLS)
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Pop3Command {
+    pub keyword: String,
+    pub argument: Option<String>,
+    pub starttls: bool,
+}
+
+/// POP3 Response: +OK or -ERR status line, followed by the lines of multi-line responses
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Pop3Response {
+    pub ok: bool,
+    pub text: String,
+    pub lines: Vec<String>,
+}
+
+/// IMAP Packet Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", content = "content")]
+pub enum SerializableImapPacket {
+    Commands(Vec<ImapCommand>),
+    Responses(Vec<ImapResponse>),
+    /// Length of the data continuing the dialog: the bytes of a literal, or the SASL responses
+    /// of an AUTHENTICATE
+    Continuation(usize),
+    Encrypted(usize),
+}
+
+impl fmt::Display for SerializableImapPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializableImapPacket::Commands(commands) => {
+                write!(f, "IMAP Packet: \n\tCommands: {:?}", commands)
+            }
+            SerializableImapPacket::Responses(responses) => {
+                write!(f, "IMAP Packet: \n\tResponses: {:?}", responses)
+            }
+            SerializableImapPacket::Continuation(length) => {
+                write!(f, "IMAP Packet: \n\tContinuation Length: {}", length)
+            }
+            SerializableImapPacket::Encrypted(length) => {
+                write!(f, "IMAP Packet: \n\tEncrypted Length: {}", length)
+            }
+        }
+    }
+}
+
+/// IMAP tagged Command, flagged when it starts TLS (STARTTLS)
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImapCommand {
+    pub tag: String,
+    pub command: String,
+    pub arguments: Option<String>,
+    pub starttls: bool,
+}
+
+/// IMAP Response, tagged `*` when untagged and `+` for continuation requests, with its status
+/// condition (OK, NO, BAD, BYE, PREAUTH) if any
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImapResponse {
+    pub tag: String,
+    pub status: Option<String>,
+    pub text: String,
+}
+
+/// LDAP Packet Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableLdapPacket {
+    pub messages: Vec<CustomLdapMessage>,
+}
+
+impl From<&[LdapMessage]> for SerializableLdapPacket {
+    fn from(messages: &[LdapMessage]) -> Self {
+        SerializableLdapPacket {
+            messages: messages.iter().map(CustomLdapMessage::from).collect(),
+        }
+    }
+}
+
+impl fmt::Display for SerializableLdapPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LDAP Packet: ")?;
+        for message in &self.messages {
+            write!(
+                f,
+                "\n\tMessage ID: {}\n\
+                \tOperation: {}\n\
+                \tDN: {:?}",
+                message.message_id, message.operation, message.dn
+            )?;
+
+            let details = [
+                ("Version", message.version.map(|v| v.to_string())),
+                ("Authentication", message.authentication.clone()),
+                ("Scope", message.scope.clone()),
+                ("Filter", message.filter.clone()),
+                ("Result Code", message.result_code.clone()),
+                ("Diagnostic Message", message.diagnostic_message.clone()),
+            ];
+            for (name, value) in details {
+                if let Some(value) = value {
+                    write!(f, "\n\t{}: {}", name, value)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// LDAP Message, with the decoded fields of bind and search operations
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomLdapMessage {
+    pub message_id: u32,
+    pub operation: String,
+    pub dn: Option<String>,
+    pub version: Option<u32>,
+    pub authentication: Option<String>,
+    pub scope: Option<String>,
+    pub filter: Option<String>,
+    pub result_code: Option<String>,
+    pub diagnostic_message: Option<String>,
+}
+
+impl From<&LdapMessage> for CustomLdapMessage {
+    fn from(message: &LdapMessage) -> Self {
+        let mut custom_message = CustomLdapMessage {
+            message_id: message.message_id,
+            operation: ldap_operation_to_string(ldap_operation_tag(&message.operation)),
+            dn: None,
+            version: None,
+            authentication: None,
+            scope: None,
+            filter: None,
+            result_code: None,
+            diagnostic_message: None,
+        };
+
+        let set_result = |custom_message: &mut CustomLdapMessage, result: &LdapResult| {
+            custom_message.dn = Some(result.matched_dn.clone()).filter(|dn| !dn.is_empty());
+            custom_message.result_code = Some(ldap_result_code_to_string(result.result_code));
+            custom_message.diagnostic_message =
+                Some(result.diagnostic_message.clone()).filter(|m| !m.is_empty());
+        };
+
+        match &message.operation {
+            LdapOperation::BindRequest {
+                version,
+                name,
+                authentication,
+            } => {
+                custom_message.dn = Some(name.clone());
+                custom_message.version = Some(*version);
+                custom_message.authentication = Some(match authentication {
+                    LdapAuthentication::Simple(0) => "anonymous".to_owned(),
+                    LdapAuthentication::Simple(length) => {
+                        format!("simple ({} bytes redacted)", length)
+                    }
+                    LdapAuthentication::Sasl(mechanism) => format!("sasl ({})", mechanism),
+                    LdapAuthentication::Unknown(tag) => format!("unknown ({})", tag),
+                });
+            }
+            LdapOperation::SearchRequest {
+                base_object,
+                scope,
+                filter,
+            } => {
+                custom_message.dn = Some(base_object.clone());
+                custom_message.scope = Some(ldap_scope_to_string(*scope));
+                custom_message.filter = Some(filter.clone());
+            }
+            LdapOperation::SearchResultEntry { object_name } => {
+                custom_message.dn = Some(object_name.clone());
+            }
+            LdapOperation::BindResponse(result) | LdapOperation::Response { result, .. } => {
+                set_result(&mut custom_message, result)
+            }
+            LdapOperation::Request { entry, .. } => custom_message.dn = entry.clone(),
+        }
+
+        custom_message
+    }
+}
+
+/// Get LDAP Protocol Operation name
+pub fn ldap_operation_to_string(tag_number: u8) -> String {
+    let name = match tag_number {
+        LdapOperations::BIND_REQUEST => "bindRequest",
+        LdapOperations::BIND_RESPONSE => "bindResponse",
+        LdapOperations::UNBIND_REQUEST => "unbindRequest",
+        LdapOperations::SEARCH_REQUEST => "searchRequest",
+        LdapOperations::SEARCH_RESULT_ENTRY => "searchResEntry",
+        LdapOperations::SEARCH_RESULT_DONE => "searchResDone",
+        LdapOperations::MODIFY_REQUEST => "modifyRequest",
+        LdapOperations::MODIFY_RESPONSE => "modifyResponse",
+        LdapOperations::ADD_REQUEST => "addRequest",
+        LdapOperations::ADD_RESPONSE => "addResponse",
+        LdapOperations::DEL_REQUEST => "delRequest",
+        LdapOperations::DEL_RESPONSE => "delResponse",
+        LdapOperations::MODIFY_DN_REQUEST => "modDNRequest",
+        LdapOperations::MODIFY_DN_RESPONSE => "modDNResponse",
+        LdapOperations::COMPARE_REQUEST => "compareRequest",
+        LdapOperations::COMPARE_RESPONSE => "compareResponse",
+        LdapOperations::ABANDON_REQUEST => "abandonRequest",
+        LdapOperations::SEARCH_RESULT_REFERENCE => "searchResRef",
+        LdapOperations::EXTENDED_REQUEST => "extendedReq",
+        LdapOperations::EXTENDED_RESPONSE => "extendedResp",
+        LdapOperations::INTERMEDIATE_RESPONSE => "intermediateResponse",
+        _ => "unknown",
+    };
+
+    format!("{} ({})", name, tag_number)
+}
+
+/// Get LDAP Search Scope name
+pub fn ldap_scope_to_string(scope: u32) -> String {
+    let name = match scope {
+        0 => "baseObject",
+        1 => "singleLevel",
+        2 => "wholeSubtree",
+        _ => "unknown",
+    };
+
+    format!("{} ({})", name, scope)
+}
+
+/// Get LDAP Result Code name
+pub fn ldap_result_code_to_string(result_code: u32) -> String {
+    let name = match result_code {
+        0 => "success",
+        1 => "operationsError",
+        2 => "protocolError",
+        3 => "timeLimitExceeded",
+        4 => "sizeLimitExceeded",
+        5 => "compareFalse",
+        6 => "compareTrue",
+        7 => "authMethodNotSupported",
+        8 => "strongerAuthRequired",
+        10 => "referral",
+        11 => "adminLimitExceeded",
+        12 => "unavailableCriticalExtension",
+        13 => "confidentialityRequired",
+        14 => "saslBindInProgress",
+        16 => "noSuchAttribute",
+        17 => "undefinedAttributeType",
+        18 => "inappropriateMatching",
+        19 => "constraintViolation",
+        20 => "attributeOrValueExists",
+        21 => "invalidAttributeSyntax",
+        32 => "noSuchObject",
+        33 => "aliasProblem",
+        34 => "invalidDNSyntax",
+        36 => "aliasDereferencingProblem",
+        48 => "inappropriateAuthentication",
+        49 => "invalidCredentials",
+        50 => "insufficientAccessRights",
+        51 => "busy",
+        52 => "unavailable",
+        53 => "unwillingToPerform",
+        54 => "loopDetect",
+        64 => "namingViolation",
+        65 => "objectClassViolation",
+        66 => "notAllowedOnNonLeaf",
+        67 => "notAllowedOnRDN",
+        68 => "entryAlreadyExists",
+        69 => "objectClassModsProhibited",
+        71 => "affectsMultipleDSAs",
+        80 => "other",
+        _ => "unknown",
+    };
+
+    format!("{} ({})", name, result_code)
+}
+
+/// NetBIOS Name Service Packet Representation, with the name of its first question (or record)
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableNbnsPacket {
+    pub transaction_id: u16,
+    pub is_response: bool,
+    pub opcode: String,
+    /// NM_FLAGS set, e.g. `B` for a broadcast
+    pub flags: Vec<String>,
+    pub rcode: u8,
+    /// NetBIOS name decoded from its first-level encoding, without its padding
+    pub name: Option<String>,
+    /// Service of the name, e.g. 0x00 workstation, 0x20 file server
+    pub name_suffix: Option<u8>,
+    pub scope: Option<String>,
+    pub record_type: Option<String>,
+    /// Addresses of the NB records
+    pub addresses: Vec<Ipv4Addr>,
+}
+
+impl From<&NbnsMessage> for SerializableNbnsPacket {
+    fn from(message: &NbnsMessage) -> Self {
+        let flags = [
+            (NbnsFlags::AUTHORITATIVE_ANSWER, "AA"),
+            (NbnsFlags::TRUNCATED, "TC"),
+            (NbnsFlags::RECURSION_DESIRED, "RD"),
+            (NbnsFlags::RECURSION_AVAILABLE, "RA"),
+            (NbnsFlags::BROADCAST, "B"),
+        ]
+        .into_iter()
+        .filter(|(flag, _)| message.flags & flag != 0)
+        .map(|(_, name)| name.to_string())
+        .collect();
+        let entry = message.questions.first().or(message.records.first());
+
+        SerializableNbnsPacket {
+            transaction_id: message.transaction_id,
+            is_response: message.flags & NbnsFlags::RESPONSE != 0,
+            opcode: nbns_opcode_to_string(message.opcode()),
+            flags,
+            rcode: (message.flags & NbnsFlags::RCODE_MASK) as u8,
+            name: entry.map(|entry| entry.name.name.clone()),
+            name_suffix: entry.map(|entry| entry.name.suffix),
+            scope: entry.and_then(|entry| entry.name.scope.clone()),
+            record_type: entry.map(|entry| nbns_record_type_to_string(entry.entry_type)),
+            addresses: message
+                .records
+                .iter()
+                .flat_map(|record| record.addresses.iter().copied())
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for SerializableNbnsPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "NBNS Packet: \n\
+            \tTransaction ID: {:#06x}\n\
+            \tResponse: {}\n\
+            \tOpcode: {}\n\
+            \tFlags: {:?}\n\
+            \tRCODE: {}",
+            self.transaction_id, self.is_response, self.opcode, self.flags, self.rcode
+        )?;
+
+        if let (Some(name), Some(suffix)) = (&self.name, self.name_suffix) {
+            write!(f, "\n\tName: {}<{:02x}>", name, suffix)?;
+        }
+        if let Some(scope) = &self.scope {
+            write!(f, "\n\tScope: {}", scope)?;
+        }
+        if let Some(record_type) = &self.record_type {
+            write!(f, "\n\tType: {}", record_type)?;
+        }
+        if !self.addresses.is_empty() {
+            write!(f, "\n\tAddresses: {:?}", self.addresses)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get NBNS Opcode
+pub fn nbns_opcode_to_string(opcode: u8) -> String {
+    let name = match opcode {
+        NbnsOpcodes::QUERY => "Name Query",
+        NbnsOpcodes::REGISTRATION => "Name Registration",
+        NbnsOpcodes::RELEASE => "Name Release",
+        NbnsOpcodes::WACK => "WACK",
+        NbnsOpcodes::REFRESH => "Name Refresh",
+        NbnsOpcodes::MULTI_HOMED_REGISTRATION => "Multi-Homed Name Registration",
+        _ => "Unknown",
+    };
+
+    format!("{} ({})", name, opcode)
+}
+
+/// Get NBNS Question or Resource Record Type
+pub fn nbns_record_type_to_string(record_type: u16) -> String {
+    let name = match record_type {
+        NbnsRecordTypes::NB => "NB",
+        NbnsRecordTypes::NBSTAT => "NBSTAT",
+        _ => "Unknown",
+    };
+
+    format!("{} ({:#06x})", name, record_type)
+}
+
+/// STUN Packet Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableStunPacket {
+    pub message_type: String,
+    pub length: u16,
+    pub transaction_id: Vec<u8>,
+    pub mapped_address: Option<SocketAddr>,
+    pub xor_mapped_address: Option<SocketAddr>,
+    pub username: Option<String>,
+    pub software: Option<String>,
+    pub attributes: Vec<String>,
+}
+
+impl From<&StunMessage> for SerializableStunPacket {
+    fn from(message: &StunMessage) -> Self {
+        let mut stun_packet = SerializableStunPacket {
+            message_type: stun_message_type_to_string(message.class, message.method),
+            length: message.length,
+            transaction_id: message.transaction_id.to_vec(),
+            mapped_address: None,
+            xor_mapped_address: None,
+            username: None,
+            software: None,
+            attributes: vec![],
+        };
+
+        for attribute in &message.attributes {
+            let attribute_type = match attribute {
+                StunAttribute::MappedAddress(address) => {
+                    stun_packet.mapped_address = Some(*address);
+                    StunAttributeTypes::MAPPED_ADDRESS
+                }
+                StunAttribute::XorMappedAddress(address) => {
+                    stun_packet.xor_mapped_address = Some(*address);
+                    StunAttributeTypes::XOR_MAPPED_ADDRESS
+                }
+                StunAttribute::Username(username) => {
+                    stun_packet.username = Some(username.clone());
+                    StunAttributeTypes::USERNAME
+                }
+                StunAttribute::Software(software) => {
+                    stun_packet.software = Some(software.clone());
+                    StunAttributeTypes::SOFTWARE
+                }
+                StunAttribute::Other(attribute_type, _) => *attribute_type,
+            };
+            stun_packet
+                .attributes
+                .push(stun_attribute_type_to_string(attribute_type));
         }
+
+        stun_packet
+    }
+}
+
+impl fmt::Display for SerializableStunPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "STUN Packet: \n\
+            \tMessage Type: {}\n\
+            \tLength: {}\n\
+            \tTransaction ID: {:02x?}",
+            self.message_type, self.length, self.transaction_id
+        )?;
+
+        let details = [
+            ("Mapped Address", self.mapped_address.map(|a| a.to_string())),
+            (
+                "XOR Mapped Address",
+                self.xor_mapped_address.map(|a| a.to_string()),
+            ),
+            ("Username", self.username.clone()),
+            ("Software", self.software.clone()),
+        ];
+        for (name, value) in details {
+            if let Some(value) = value {
+                write!(f, "\n\t{}: {}", name, value)?;
+            }
+        }
+
+        write!(f, "\n\tAttributes: {:?}", self.attributes)
     }
 }
 
+/// Get STUN Message Type, from its method and class
+pub fn stun_message_type_to_string(class: StunClass, method: u16) -> String {
+    let method_name = match method {
+        StunMethods::BINDING => "Binding",
+        StunMethods::ALLOCATE => "Allocate",
+        StunMethods::REFRESH => "Refresh",
+        StunMethods::SEND => "Send",
+        StunMethods::DATA => "Data",
+        StunMethods::CREATE_PERMISSION => "CreatePermission",
+        StunMethods::CHANNEL_BIND => "ChannelBind",
+        _ => "Unknown",
+    };
+    let class_name = match class {
+        StunClass::Request => "Request",
+        StunClass::Indication => "Indication",
+        StunClass::SuccessResponse => "Success Response",
+        StunClass::ErrorResponse => "Error Response",
+    };
+
+    // The class bits C1 and C0 are interleaved with the method bits
+    let class_bits = match class {
+        StunClass::Request => 0x0000,
+        StunClass::Indication => 0x0010,
+        StunClass::SuccessResponse => 0x0100,
+        StunClass::ErrorResponse => 0x0110,
+    };
+    let message_type =
+        (method & 0x000f) | ((method & 0x0070) << 1) | ((method & 0x0f80) << 2) | class_bits;
+
+    format!("{} {} ({:#06x})", method_name, class_name, message_type)
+}
+
+/// Get STUN Attribute Type
+pub fn stun_attribute_type_to_string(attribute_type: u16) -> String {
+    let name = match attribute_type {
+        StunAttributeTypes::MAPPED_ADDRESS => "MAPPED-ADDRESS",
+        StunAttributeTypes::USERNAME => "USERNAME",
+        StunAttributeTypes::MESSAGE_INTEGRITY => "MESSAGE-INTEGRITY",
+        StunAttributeTypes::ERROR_CODE => "ERROR-CODE",
+        StunAttributeTypes::XOR_MAPPED_ADDRESS => "XOR-MAPPED-ADDRESS",
+        StunAttributeTypes::PRIORITY => "PRIORITY",
+        StunAttributeTypes::USE_CANDIDATE => "USE-CANDIDATE",
+        StunAttributeTypes::SOFTWARE => "SOFTWARE",
+        StunAttributeTypes::FINGERPRINT => "FINGERPRINT",
+        StunAttributeTypes::ICE_CONTROLLED => "ICE-CONTROLLED",
+        StunAttributeTypes::ICE_CONTROLLING => "ICE-CONTROLLING",
+        _ => "Unknown",
+    };
+
+    format!("{} ({:#06x})", name, attribute_type)
+}
+
+/// Telnet Packet Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableTelnetPacket {
+    pub commands: Vec<String>,
+    /// Data with the non-printable characters replaced by dots
+    pub data: String,
+    pub data_length: usize,
+}
+
+impl From<&TelnetMessage> for SerializableTelnetPacket {
+    fn from(message: &TelnetMessage) -> Self {
+        SerializableTelnetPacket {
+            commands: message
+                .commands
+                .iter()
+                .map(telnet_command_to_string)
+                .collect(),
+            data: message
+                .data
+                .iter()
+                .map(|&byte| match byte {
+                    b'\r' | b'\n' | b'\t' | 0x20..=0x7e => byte as char,
+                    _ => '.',
+                })
+                .collect(),
+            data_length: message.data.len(),
+        }
+    }
+}
+
+impl fmt::Display for SerializableTelnetPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Telnet Packet: \n\
+            \tCommands: {:?}\n\
+            \tData Length: {}\n\
+            \tData: {:?}",
+            self.commands, self.data_length, self.data
+        )
+    }
+}
+
+/// Get Telnet Command, with its option
+pub fn telnet_command_to_string(command: &TelnetCommand) -> String {
+    match command {
+        TelnetCommand::Negotiation(command, option) => format!(
+            "{} {}",
+            telnet_command_name(*command),
+            telnet_option_to_string(*option)
+        ),
+        TelnetCommand::Subnegotiation(option, parameters) => format!(
+            "SB {}: {:02x?}",
+            telnet_option_to_string(*option),
+            parameters
+        ),
+        TelnetCommand::Command(command) => {
+            format!("{} ({})", telnet_command_name(*command), command)
+        }
+    }
+}
+
+fn telnet_command_name(command: u8) -> &'static str {
+    match command {
+        TelnetCommands::SE => "SE",
+        TelnetCommands::NOP => "NOP",
+        TelnetCommands::DATA_MARK => "Data Mark",
+        TelnetCommands::BREAK => "Break",
+        TelnetCommands::INTERRUPT_PROCESS => "Interrupt Process",
+        TelnetCommands::ABORT_OUTPUT => "Abort Output",
+        TelnetCommands::ARE_YOU_THERE => "Are You There",
+        TelnetCommands::ERASE_CHARACTER => "Erase Character",
+        TelnetCommands::ERASE_LINE => "Erase Line",
+        TelnetCommands::GO_AHEAD => "Go Ahead",
+        TelnetCommands::SB => "SB",
+        TelnetCommands::WILL => "WILL",
+        TelnetCommands::WONT => "WONT",
+        TelnetCommands::DO => "DO",
+        TelnetCommands::DONT => "DONT",
+        _ => "Unknown",
+    }
+}
+
+/// Get Telnet Option
+pub fn telnet_option_to_string(option: u8) -> String {
+    let name = match option {
+        TelnetOptions::BINARY_TRANSMISSION => "Binary Transmission",
+        TelnetOptions::ECHO => "Echo",
+        TelnetOptions::SUPPRESS_GO_AHEAD => "Suppress Go Ahead",
+        TelnetOptions::STATUS => "Status",
+        TelnetOptions::TIMING_MARK => "Timing Mark",
+        TelnetOptions::TERMINAL_TYPE => "Terminal Type",
+        TelnetOptions::WINDOW_SIZE => "Window Size",
+        TelnetOptions::TERMINAL_SPEED => "Terminal Speed",
+        TelnetOptions::REMOTE_FLOW_CONTROL => "Remote Flow Control",
+        TelnetOptions::LINEMODE => "Linemode",
+        TelnetOptions::ENVIRONMENT => "Environment",
+        TelnetOptions::NEW_ENVIRONMENT => "New Environment",
+        _ => "Unknown",
+    };
+
+    format!("{} ({})", name, option)
+}
+
+/// Kerberos Packet Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableKerberosPacket {
+    pub message_type: String,
+    /// Realm of the request or of the error, of the client for the replies
+    pub realm: Option<String>,
+    /// Client principal name, components separated by slashes
+    pub cname: Option<String>,
+    /// Server principal name, components separated by slashes
+    pub sname: Option<String>,
+    pub error_code: Option<String>,
+}
+
+impl From<&KerberosMessage> for SerializableKerberosPacket {
+    fn from(message: &KerberosMessage) -> Self {
+        SerializableKerberosPacket {
+            message_type: kerberos_message_type_to_string(message.message_type),
+            realm: message.realm.clone(),
+            cname: message.cname.as_ref().map(|name| name.join("/")),
+            sname: message.sname.as_ref().map(|name| name.join("/")),
+            error_code: message.error_code.map(kerberos_error_code_to_string),
+        }
+    }
+}
+
+impl fmt::Display for SerializableKerberosPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Kerberos Packet: \n\tMessage Type: {}",
+            self.message_type
+        )?;
+
+        let details = [
+            ("Realm", &self.realm),
+            ("Client Name", &self.cname),
+            ("Server Name", &self.sname),
+            ("Error Code", &self.error_code),
+        ];
+        for (name, value) in details {
+            if let Some(value) = value {
+                write!(f, "\n\t{}: {}", name, value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Get Kerberos Message Type
+pub fn kerberos_message_type_to_string(message_type: u8) -> String {
+    let name = match message_type {
+        KerberosMessageTypes::AS_REQ => "AS-REQ",
+        KerberosMessageTypes::AS_REP => "AS-REP",
+        KerberosMessageTypes::TGS_REQ => "TGS-REQ",
+        KerberosMessageTypes::TGS_REP => "TGS-REP",
+        KerberosMessageTypes::AP_REQ => "AP-REQ",
+        KerberosMessageTypes::AP_REP => "AP-REP",
+        KerberosMessageTypes::KRB_ERROR => "KRB-ERROR",
+        _ => "Unknown",
+    };
+
+    format!("{} ({})", name, message_type)
+}
+
+/// Get Kerberos Error Code name
+pub fn kerberos_error_code_to_string(error_code: u32) -> String {
+    let name = match error_code {
+        0 => "KDC_ERR_NONE",
+        6 => "KDC_ERR_C_PRINCIPAL_UNKNOWN",
+        7 => "KDC_ERR_S_PRINCIPAL_UNKNOWN",
+        14 => "KDC_ERR_ETYPE_NOSUPP",
+        18 => "KDC_ERR_CLIENT_REVOKED",
+        23 => "KDC_ERR_KEY_EXPIRED",
+        24 => "KDC_ERR_PREAUTH_FAILED",
+        25 => "KDC_ERR_PREAUTH_REQUIRED",
+        31 => "KRB_AP_ERR_BAD_INTEGRITY",
+        32 => "KRB_AP_ERR_TKT_EXPIRED",
+        37 => "KRB_AP_ERR_SKEW",
+        41 => "KRB_AP_ERR_MODIFIED",
+        52 => "KRB_ERR_RESPONSE_TOO_BIG",
+        60 => "KRB_ERR_GENERIC",
+        68 => "KDC_ERR_WRONG_REALM",
+        _ => "Unknown",
+    };
+
+    format!("{} ({})", name, error_code)
+}
+
+/// WireGuard Packet Representation, the fixed fields of its message
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableWireGuardPacket {
+    pub message_type: u8,
+    pub message_type_name: String,
+    pub sender_index: Option<u32>,
+    pub receiver_index: Option<u32>,
+    pub counter: Option<u64>,
+    pub length: usize,
+}
+
+impl From<&WireGuardMessage> for SerializableWireGuardPacket {
+    fn from(message: &WireGuardMessage) -> Self {
+        SerializableWireGuardPacket {
+            message_type: message.message_type,
+            message_type_name: wireguard_message_type_name(message.message_type).to_owned(),
+            sender_index: message.sender_index,
+            receiver_index: message.receiver_index,
+            counter: message.counter,
+            length: message.length,
+        }
+    }
+}
+
+impl fmt::Display for SerializableWireGuardPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "WireGuard Packet: \n\
+            \tType: {} ({})\n\
+            \tLength: {}",
+            self.message_type_name, self.message_type, self.length
+        )?;
+
+        if let Some(sender_index) = self.sender_index {
+            write!(f, "\n\tSender Index: {:#010x}", sender_index)?;
+        }
+        if let Some(receiver_index) = self.receiver_index {
+            write!(f, "\n\tReceiver Index: {:#010x}", receiver_index)?;
+        }
+        if let Some(counter) = self.counter {
+            write!(f, "\n\tCounter: {}", counter)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn wireguard_message_type_name(message_type: u8) -> &'static str {
+    match message_type {
+        WireGuardMessageTypes::HANDSHAKE_INITIATION => "Handshake Initiation",
+        WireGuardMessageTypes::HANDSHAKE_RESPONSE => "Handshake Response",
+        WireGuardMessageTypes::COOKIE_REPLY => "Cookie Reply",
+        WireGuardMessageTypes::TRANSPORT_DATA => "Transport Data",
+        _ => "Unknown",
+    }
+}
+
+/// Syslog Packet Representation, in either the RFC 3164 or the RFC 5424 format
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableSyslogPacket {
+    pub format: String,
+    pub priority: u8,
+    pub facility: u8,
+    pub facility_name: String,
+    pub severity: u8,
+    pub severity_name: String,
+    /// Version of a RFC 5424 message
+    pub version: Option<u8>,
+    pub timestamp: Option<String>,
+    pub hostname: Option<String>,
+    /// Application name of a RFC 5424 message, tag of a RFC 3164 one
+    pub app_name: Option<String>,
+    pub proc_id: Option<String>,
+    pub msg_id: Option<String>,
+    pub structured_data: Option<String>,
+    pub message: String,
+    pub length: usize,
+}
+
+impl SerializableSyslogPacket {
+    pub fn new(message: &SyslogMessage, length: usize) -> Self {
+        SerializableSyslogPacket {
+            format: match message.format {
+                SyslogFormat::Rfc3164 => "RFC 3164".to_owned(),
+                SyslogFormat::Rfc5424 => "RFC 5424".to_owned(),
+            },
+            priority: message.priority,
+            facility: message.facility(),
+            facility_name: syslog_facility_name(message.facility()).to_owned(),
+            severity: message.severity(),
+            severity_name: syslog_severity_name(message.severity()).to_owned(),
+            version: message.version,
+            timestamp: message.timestamp.clone(),
+            hostname: message.hostname.clone(),
+            app_name: message.app_name.clone(),
+            proc_id: message.proc_id.clone(),
+            msg_id: message.msg_id.clone(),
+            structured_data: message.structured_data.clone(),
+            message: message.message.clone(),
+            length,
+        }
+    }
+}
+
+impl fmt::Display for SerializableSyslogPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Syslog Packet: \n\
+            \tFormat: {}\n\
+            \tFacility: {} ({})\n\
+            \tSeverity: {} ({})",
+            self.format, self.facility_name, self.facility, self.severity_name, self.severity
+        )?;
+
+        let fields = [
+            ("Timestamp", &self.timestamp),
+            ("Hostname", &self.hostname),
+            ("Application", &self.app_name),
+            ("Process ID", &self.proc_id),
+            ("Message ID", &self.msg_id),
+            ("Structured Data", &self.structured_data),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                write!(f, "\n\t{}: {}", name, value)?;
+            }
+        }
+        write!(
+            f,
+            "\n\tMessage: {:?}\n\tLength: {}",
+            self.message, self.length
+        )
+    }
+}
+
+/// Get the keyword of a syslog facility
+pub fn syslog_facility_name(facility: u8) -> &'static str {
+    match facility {
+        0 => "kern",
+        1 => "user",
+        2 => "mail",
+        3 => "daemon",
+        4 => "auth",
+        5 => "syslog",
+        6 => "lpr",
+        7 => "news",
+        8 => "uucp",
+        9 => "cron",
+        10 => "authpriv",
+        11 => "ftp",
+        12 => "ntp",
+        13 => "audit",
+        14 => "alert",
+        15 => "clock",
+        16 => "local0",
+        17 => "local1",
+        18 => "local2",
+        19 => "local3",
+        20 => "local4",
+        21 => "local5",
+        22 => "local6",
+        23 => "local7",
+        _ => "Unknown",
+    }
+}
+
+/// Get the keyword of a syslog severity
+pub fn syslog_severity_name(severity: u8) -> &'static str {
+    match severity {
+        0 => "emerg",
+        1 => "alert",
+        2 => "crit",
+        3 => "err",
+        4 => "warning",
+        5 => "notice",
+        6 => "info",
+        7 => "debug",
+        _ => "Unknown",
+    }
+}
+
+/// DHCPv6 Packet Representation, the DUIDs in hexadecimal
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableDhcpv6Packet {
+    pub message_type: u8,
+    pub message_type_name: String,
+    pub transaction_id: Option<u32>,
+    pub client_duid: Option<String>,
+    pub server_duid: Option<String>,
+    pub ia_na: Vec<SerializableDhcpv6IaNa>,
+    pub dns_servers: Vec<Ipv6Addr>,
+    pub option_codes: Vec<u16>,
+    pub length: usize,
+}
+
+/// DHCPv6 IA_NA Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableDhcpv6IaNa {
+    pub iaid: u32,
+    pub t1: u32,
+    pub t2: u32,
+    pub addresses: Vec<Ipv6Addr>,
+}
+
+impl SerializableDhcpv6Packet {
+    pub fn new(message: &Dhcpv6Message, length: usize) -> Self {
+        let duid = |duid: &Option<Vec<u8>>| {
+            duid.as_ref()
+                .map(|duid| duid.iter().map(|byte| format!("{:02x}", byte)).collect())
+        };
+
+        SerializableDhcpv6Packet {
+            message_type: message.message_type,
+            message_type_name: dhcpv6_message_type_name(message.message_type).to_owned(),
+            transaction_id: message.transaction_id,
+            client_duid: duid(&message.client_id),
+            server_duid: duid(&message.server_id),
+            ia_na: message
+                .ia_na
+                .iter()
+                .map(|ia_na| SerializableDhcpv6IaNa {
+                    iaid: ia_na.iaid,
+                    t1: ia_na.t1,
+                    t2: ia_na.t2,
+                    addresses: ia_na
+                        .addresses
+                        .iter()
+                        .map(|address| address.address)
+                        .collect(),
+                })
+                .collect(),
+            dns_servers: message.dns_servers.clone(),
+            option_codes: message.option_codes.clone(),
+            length,
+        }
+    }
+}
+
+impl fmt::Display for SerializableDhcpv6Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "DHCPv6 Packet: \n\
+            \tType: {} ({})\n\
+            \tLength: {}",
+            self.message_type_name, self.message_type, self.length
+        )?;
+
+        if let Some(transaction_id) = self.transaction_id {
+            write!(f, "\n\tTransaction ID: {:#08x}", transaction_id)?;
+        }
+        if let Some(client_duid) = &self.client_duid {
+            write!(f, "\n\tClient DUID: {}", client_duid)?;
+        }
+        if let Some(server_duid) = &self.server_duid {
+            write!(f, "\n\tServer DUID: {}", server_duid)?;
+        }
+        for ia_na in &self.ia_na {
+            write!(
+                f,
+                "\n\tIA_NA: IAID {:#010x}, T1 {}, T2 {}",
+                ia_na.iaid, ia_na.t1, ia_na.t2
+            )?;
+            for address in &ia_na.addresses {
+                write!(f, "\n\t\tAddress: {}", address)?;
+            }
+        }
+        for dns_server in &self.dns_servers {
+            write!(f, "\n\tDNS Server: {}", dns_server)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the name of a DHCPv6 message type, as in RFC 8415
+pub fn dhcpv6_message_type_name(message_type: u8) -> &'static str {
+    match message_type {
+        Dhcpv6MessageTypes::SOLICIT => "SOLICIT",
+        Dhcpv6MessageTypes::ADVERTISE => "ADVERTISE",
+        Dhcpv6MessageTypes::REQUEST => "REQUEST",
+        Dhcpv6MessageTypes::CONFIRM => "CONFIRM",
+        Dhcpv6MessageTypes::RENEW => "RENEW",
+        Dhcpv6MessageTypes::REBIND => "REBIND",
+        Dhcpv6MessageTypes::REPLY => "REPLY",
+        Dhcpv6MessageTypes::RELEASE => "RELEASE",
+        Dhcpv6MessageTypes::DECLINE => "DECLINE",
+        Dhcpv6MessageTypes::RECONFIGURE => "RECONFIGURE",
+        Dhcpv6MessageTypes::INFORMATION_REQUEST => "INFORMATION-REQUEST",
+        Dhcpv6MessageTypes::RELAY_FORW => "RELAY-FORW",
+        Dhcpv6MessageTypes::RELAY_REPL => "RELAY-REPL",
+        _ => "Unknown",
+    }
+}
+
+/// GTP-U Packet Representation, with the network layer of the IP packet a G-PDU carries
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableGtpPacket {
+    pub version: u8,
+    pub message_type: u8,
+    pub message_type_name: String,
+    pub teid: u32,
+    pub sequence_number: Option<u16>,
+    pub length: u16,
+    /// Network-layer packet tunneled in a G-PDU
+    pub encapsulated: Option<Box<SerializablePacket>>,
+}
+
+impl From<&GtpHeader> for SerializableGtpPacket {
+    fn from(header: &GtpHeader) -> Self {
+        SerializableGtpPacket {
+            version: header.version,
+            message_type: header.message_type,
+            message_type_name: gtp_message_type_name(header.message_type).to_owned(),
+            teid: header.teid,
+            sequence_number: header.sequence_number,
+            length: header.length,
+            encapsulated: None,
+        }
+    }
+}
+
+impl fmt::Display for SerializableGtpPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "GTP-U Packet: \n\
+            \tVersion: {}\n\
+            \tType: {} ({})\n\
+            \tTEID: {:#010x}\n\
+            \tLength: {}",
+            self.version, self.message_type_name, self.message_type, self.teid, self.length
+        )?;
+
+        if let Some(sequence_number) = self.sequence_number {
+            write!(f, "\n\tSequence Number: {}", sequence_number)?;
+        }
+
+        write_encapsulated(f, &self.encapsulated)
+    }
+}
+
+fn gtp_message_type_name(message_type: u8) -> &'static str {
+    match message_type {
+        GtpMessageTypes::ECHO_REQUEST => "Echo Request",
+        GtpMessageTypes::ECHO_RESPONSE => "Echo Response",
+        GtpMessageTypes::ERROR_INDICATION => "Error Indication",
+        GtpMessageTypes::SUPPORTED_EXTENSION_HEADERS_NOTIFICATION => {
+            "Supported Extension Headers Notification"
+        }
+        GtpMessageTypes::END_MARKER => "End Marker",
+        GtpMessageTypes::G_PDU => "G-PDU",
+        _ => "Unknown",
+    }
+}
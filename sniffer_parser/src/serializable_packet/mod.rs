@@ -6,34 +6,69 @@
 //! - transport_layer_packet
 //! - application_layer_packet
 //!
+//! Serialized field names are camelCase at every level. Each layer is an object tagged with the
+//! representation it holds, `{"type": "Ipv4Packet", "packet": {...}}`, and the enums nested in
+//! representations carry their variant name in a `type` (or `subType`) field
+//!
 
 pub mod application;
 pub mod network;
+pub mod row;
 pub mod transport;
 pub mod util;
+pub mod visitor;
 
 use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use application::SerializableModbusPacket;
+use pnet::packet::arp::ArpPacket;
+use pnet::packet::ethernet::{EtherType, EtherTypes};
+use pnet::packet::icmp::echo_reply::EchoReplyPacket;
+use pnet::packet::icmp::echo_request::EchoRequestPacket;
+use pnet::packet::icmp::IcmpPacket;
+use pnet::packet::icmpv6::Icmpv6Packet;
+use pnet::packet::ipv6::Ipv6Packet;
 use pnet::packet::Packet;
 use pnet::{packet::ethernet::EthernetPacket, util::MacAddr};
 use serde::Serialize;
 
 use self::application::{
-    SerializableDnsPacket, SerializableHttpRequestPacket, SerializableHttpResponsePacket,
-    SerializableTlsPacket
+    SerializableDhcpv6Packet, SerializableDnsPacket, SerializableDtlsPacket, SerializableGtpPacket,
+    SerializableHttpRequestPacket, SerializableHttpResponsePacket, SerializableImapPacket,
+    SerializableKerberosPacket, SerializableLdapPacket, SerializableNbnsPacket,
+    SerializablePop3Packet, SerializableQuicPacket, SerializableSmtpPacket, SerializableStunPacket,
+    SerializableSyslogPacket, SerializableTelnetPacket, SerializableTlsPacket,
+    SerializableWireGuardPacket,
 };
 use self::network::{SerializableArpPacket, SerializableIpv4Packet, SerializableIpv6Packet};
 use self::transport::{
     SerializableEchoReplyPacket, SerializableEchoRequestPacket, SerializableIcmpPacket,
-    SerializableIcmpv6Packet, SerializableTcpPacket, SerializableUdpPacket,
+    SerializableIcmpv6Packet, SerializableIgmpPacket, SerializableOspfPacket,
+    SerializableSctpPacket, SerializableTcpPacket, SerializableUdpPacket,
+};
+use self::util::hexdump;
+use crate::dot11::{
+    Dot11ControlSubtypes, Dot11DataSubtypes, Dot11FrameTypes, Dot11ManagementSubtypes,
 };
+use crate::pcap::encode_pcap_record;
+use crate::pppoe::{PppProtocols, PppoeCodes, PPPOE_HEADER_LENGTH};
+use crate::sll::SllPacketTypes;
+use crate::{extracted_strings, is_payload_retained};
 
 /// Data structure containing representations of the packet at each TCP/IP layer
 #[derive(Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ParsedPacket {
     id: usize,
+    timestamp: Option<SystemTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw_frame: Option<Vec<u8>>,
+    /// Index of the capture file the packet was read from, when merging several
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_index: Option<usize>,
+    /// Record captured shorter than the frame (snaplen), so that its upper layers may be cut off
+    truncated_capture: bool,
     link_layer_packet: Option<SerializablePacket>,
     network_layer_packet: Option<SerializablePacket>,
     transport_layer_packet: Option<SerializablePacket>,
@@ -44,6 +79,10 @@ impl ParsedPacket {
     pub fn new(id: usize) -> Self {
         ParsedPacket {
             id,
+            timestamp: None,
+            raw_frame: None,
+            source_index: None,
+            truncated_capture: false,
             link_layer_packet: None,
             network_layer_packet: None,
             transport_layer_packet: None,
@@ -56,6 +95,98 @@ impl ParsedPacket {
         self.id
     }
 
+    /// Get packet capture timestamp
+    pub fn get_timestamp(&self) -> Option<SystemTime> {
+        self.timestamp
+    }
+
+    /// Set packet capture timestamp
+    pub fn set_timestamp(&mut self, timestamp: Option<SystemTime>) {
+        self.timestamp = timestamp;
+    }
+
+    /// Get the raw bytes of the frame, only retained when enabled with `set_raw_frame_retention`
+    pub fn get_raw_frame(&self) -> Option<&[u8]> {
+        self.raw_frame.as_deref()
+    }
+
+    /// Set the raw bytes of the frame
+    pub fn set_raw_frame(&mut self, raw_frame: Option<Vec<u8>>) {
+        self.raw_frame = raw_frame;
+    }
+
+    /// Get the index of the capture file the packet was read from, when merging several
+    pub fn get_source_index(&self) -> Option<usize> {
+        self.source_index
+    }
+
+    /// Set the index of the capture file the packet was read from
+    pub fn set_source_index(&mut self, source_index: Option<usize>) {
+        self.source_index = source_index;
+    }
+
+    /// Check if the record of the packet was captured shorter than the frame, a malformed upper
+    /// layer being then due to the snaplen rather than to a corruption
+    pub fn is_truncated_capture(&self) -> bool {
+        self.truncated_capture
+    }
+
+    /// Set if the record of the packet was captured shorter than the frame
+    pub fn set_truncated_capture(&mut self, truncated_capture: bool) {
+        self.truncated_capture = truncated_capture;
+    }
+
+    /// Get the names of the protocols of the populated layers, from the link layer up
+    /// (e.g. `["Ethernet", "IPv4", "TCP", "HTTP"]`)
+    pub fn protocol_stack(&self) -> Vec<&'static str> {
+        [
+            &self.link_layer_packet,
+            &self.network_layer_packet,
+            &self.transport_layer_packet,
+            &self.application_layer_packet,
+        ]
+        .into_iter()
+        .flatten()
+        .map(SerializablePacket::protocol_name)
+        .collect()
+    }
+
+    /// Get the on-wire size of the packet in bytes: the size of its outermost layer with a known
+    /// size, usually the whole frame
+    pub fn size_bytes(&self) -> usize {
+        [
+            &self.link_layer_packet,
+            &self.network_layer_packet,
+            &self.transport_layer_packet,
+        ]
+        .into_iter()
+        .flatten()
+        .map(SerializablePacket::size_bytes)
+        .find(|&size| size > 0)
+        .unwrap_or(0)
+    }
+
+    /// Get the representations of the populated layers, from the link layer up
+    pub fn layers_mut(&mut self) -> impl Iterator<Item = &mut SerializablePacket> {
+        [
+            &mut self.link_layer_packet,
+            &mut self.network_layer_packet,
+            &mut self.transport_layer_packet,
+            &mut self.application_layer_packet,
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Re-serialize the retained raw frame as a pcap record (microsecond resolution) captured at
+    /// the given timestamp, empty if the raw frame was not retained
+    pub fn to_pcap_record(&self, timestamp: SystemTime) -> Vec<u8> {
+        match &self.raw_frame {
+            Some(raw_frame) => encode_pcap_record(timestamp, raw_frame),
+            None => vec![],
+        }
+    }
+
     /// Get link layer packet representation
     pub fn get_link_layer_packet(&self) -> Option<&SerializablePacket> {
         self.link_layer_packet.as_ref()
@@ -103,26 +234,51 @@ impl ParsedPacket {
     }
 }
 
+/// The alternate flag (`{:#}`) renders payloads as hexdumps, through the `DebugDisplay` of each
+/// layer (the payloads are only available when enabled with `set_payload_retention`)
 impl fmt::Display for ParsedPacket {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         //writeln!(f, "ParsedPacket ID: {}", self.id)?;
+        if let Some(timestamp) = self.timestamp {
+            let since_epoch = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+            writeln!(
+                f,
+                "Timestamp: {}.{:06}",
+                since_epoch.as_secs(),
+                since_epoch.subsec_micros()
+            )?;
+        }
+        if let Some(source_index) = self.source_index {
+            writeln!(f, "Source File: {}", source_index)?;
+        }
+        if self.truncated_capture {
+            writeln!(f, "Truncated Capture")?;
+        }
         if let Some(link_layer_packet) = &self.link_layer_packet {
-            writeln!(f, "Link Layer Packet: {}", link_layer_packet)?;
+            write!(f, "Link Layer Packet: ")?;
+            fmt::Display::fmt(link_layer_packet, f)?;
+            writeln!(f)?;
         } else {
             writeln!(f, "Link Layer Packet: None")?;
         }
         if let Some(network_layer_packet) = &self.network_layer_packet {
-            writeln!(f, "   Network Layer Packet: {}", network_layer_packet)?;
+            write!(f, "   Network Layer Packet: ")?;
+            fmt::Display::fmt(network_layer_packet, f)?;
+            writeln!(f)?;
         } else {
             writeln!(f, "   Network Layer Packet: None")?;
         }
         if let Some(transport_layer_packet) = &self.transport_layer_packet {
-            writeln!(f, "       Transport Layer Packet: {}", transport_layer_packet)?;
+            write!(f, "       Transport Layer Packet: ")?;
+            fmt::Display::fmt(transport_layer_packet, f)?;
+            writeln!(f)?;
         } else {
             writeln!(f, "       Transport Layer Packet: None")?;
         }
         if let Some(application_layer_packet) = &self.application_layer_packet {
-            writeln!(f, "           Application Layer Packet: {}", application_layer_packet)?;
+            write!(f, "           Application Layer Packet: ")?;
+            fmt::Display::fmt(application_layer_packet, f)?;
+            writeln!(f)?;
         } else {
             writeln!(f, "           Application Layer Packet: None")?;
         }
@@ -135,6 +291,9 @@ impl fmt::Display for ParsedPacket {
 #[serde(tag = "type", content = "packet")]
 pub enum SerializablePacket {
     EthernetPacket(SerializableEthernetPacket),
+    PppoePacket(SerializablePppoePacket),
+    SllPacket(SerializableSllPacket),
+    Dot11Packet(SerializableDot11Packet),
     ArpPacket(SerializableArpPacket),
     Ipv4Packet(SerializableIpv4Packet),
     Ipv6Packet(SerializableIpv6Packet),
@@ -142,52 +301,243 @@ pub enum SerializablePacket {
     EchoRequestPacket(SerializableEchoRequestPacket),
     IcmpPacket(SerializableIcmpPacket),
     Icmpv6Packet(SerializableIcmpv6Packet),
+    IgmpPacket(SerializableIgmpPacket),
+    OspfPacket(SerializableOspfPacket),
+    SctpPacket(SerializableSctpPacket),
     TcpPacket(SerializableTcpPacket),
     UdpPacket(SerializableUdpPacket),
     HttpRequestPacket(SerializableHttpRequestPacket),
     HttpResponsePacket(SerializableHttpResponsePacket),
     TlsPacket(SerializableTlsPacket),
+    DtlsPacket(SerializableDtlsPacket),
+    QuicPacket(SerializableQuicPacket),
     DnsPacket(SerializableDnsPacket),
     ModbusPacket(SerializableModbusPacket),
+    SmtpPacket(SerializableSmtpPacket),
+    Pop3Packet(SerializablePop3Packet),
+    ImapPacket(SerializableImapPacket),
+    LdapPacket(SerializableLdapPacket),
+    KerberosPacket(SerializableKerberosPacket),
+    StunPacket(SerializableStunPacket),
+    TelnetPacket(SerializableTelnetPacket),
+    NbnsPacket(SerializableNbnsPacket),
+    WireGuardPacket(SerializableWireGuardPacket),
+    GtpPacket(SerializableGtpPacket),
+    SyslogPacket(SerializableSyslogPacket),
+    Dhcpv6Packet(SerializableDhcpv6Packet),
 
     MalformedPacket(String),
     UnknownPacket(SerializableUnknownPacket),
 }
 
+/// Reasons of the malformed packets detected by the parser itself rather than by a decoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MalformedReason {
+    /// Packet encapsulated deeper than the maximum encapsulation depth
+    MaxDepthExceeded,
+}
+
+impl fmt::Display for MalformedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MalformedReason::MaxDepthExceeded => write!(f, "Maximum Encapsulation Depth Exceeded"),
+        }
+    }
+}
+
+impl SerializablePacket {
+    /// Get the name of the protocol represented by the packet
+    pub fn protocol_name(&self) -> &'static str {
+        match self {
+            SerializablePacket::EthernetPacket(_) => "Ethernet",
+            SerializablePacket::PppoePacket(_) => "PPPoE",
+            SerializablePacket::SllPacket(_) => "SLL",
+            SerializablePacket::Dot11Packet(_) => "802.11",
+            SerializablePacket::ArpPacket(_) => "ARP",
+            SerializablePacket::Ipv4Packet(_) => "IPv4",
+            SerializablePacket::Ipv6Packet(_) => "IPv6",
+            SerializablePacket::EchoReplyPacket(_)
+            | SerializablePacket::EchoRequestPacket(_)
+            | SerializablePacket::IcmpPacket(_) => "ICMP",
+            SerializablePacket::Icmpv6Packet(_) => "ICMPv6",
+            SerializablePacket::IgmpPacket(_) => "IGMP",
+            SerializablePacket::OspfPacket(_) => "OSPF",
+            SerializablePacket::SctpPacket(_) => "SCTP",
+            SerializablePacket::TcpPacket(_) => "TCP",
+            SerializablePacket::UdpPacket(_) => "UDP",
+            SerializablePacket::HttpRequestPacket(_)
+            | SerializablePacket::HttpResponsePacket(_) => "HTTP",
+            SerializablePacket::TlsPacket(_) => "TLS",
+            SerializablePacket::DtlsPacket(_) => "DTLS",
+            SerializablePacket::QuicPacket(_) => "QUIC",
+            SerializablePacket::DnsPacket(_) => "DNS",
+            SerializablePacket::ModbusPacket(_) => "Modbus",
+            SerializablePacket::SmtpPacket(_) => "SMTP",
+            SerializablePacket::Pop3Packet(_) => "POP3",
+            SerializablePacket::ImapPacket(_) => "IMAP",
+            SerializablePacket::LdapPacket(_) => "LDAP",
+            SerializablePacket::KerberosPacket(_) => "Kerberos",
+            SerializablePacket::StunPacket(_) => "STUN",
+            SerializablePacket::TelnetPacket(_) => "Telnet",
+            SerializablePacket::NbnsPacket(_) => "NBNS",
+            SerializablePacket::WireGuardPacket(_) => "WireGuard",
+            SerializablePacket::GtpPacket(_) => "GTP-U",
+            SerializablePacket::SyslogPacket(_) => "Syslog",
+            SerializablePacket::Dhcpv6Packet(_) => "DHCPv6",
+            SerializablePacket::MalformedPacket(_) => "Malformed",
+            SerializablePacket::UnknownPacket(_) => "Unknown",
+        }
+    }
+
+    /// Get the size in bytes of the layer, header and payload: the captured bytes from its first
+    /// header byte to the end of what it carries. The application-layer and malformed packets,
+    /// whose size is not recorded, have a size of 0
+    pub fn size_bytes(&self) -> usize {
+        match self {
+            SerializablePacket::EthernetPacket(pkt) => pkt.length,
+            SerializablePacket::PppoePacket(pkt) => {
+                EthernetPacket::minimum_packet_size() + PPPOE_HEADER_LENGTH + pkt.length as usize
+            }
+            SerializablePacket::SllPacket(pkt) => pkt.length,
+            SerializablePacket::Dot11Packet(pkt) => pkt.length,
+            SerializablePacket::ArpPacket(pkt) => ArpPacket::minimum_packet_size() + pkt.length,
+            SerializablePacket::Ipv4Packet(pkt) => pkt.header_length as usize * 4 + pkt.length,
+            SerializablePacket::Ipv6Packet(pkt) => Ipv6Packet::minimum_packet_size() + pkt.length,
+            SerializablePacket::EchoReplyPacket(pkt) => {
+                EchoReplyPacket::minimum_packet_size() + pkt.length
+            }
+            SerializablePacket::EchoRequestPacket(pkt) => {
+                EchoRequestPacket::minimum_packet_size() + pkt.length
+            }
+            SerializablePacket::IcmpPacket(pkt) => IcmpPacket::minimum_packet_size() + pkt.length,
+            SerializablePacket::Icmpv6Packet(pkt) => {
+                Icmpv6Packet::minimum_packet_size() + pkt.length
+            }
+            SerializablePacket::IgmpPacket(pkt) => pkt.length,
+            SerializablePacket::OspfPacket(pkt) => pkt.packet_length as usize,
+            SerializablePacket::SctpPacket(pkt) => pkt.length,
+            SerializablePacket::TcpPacket(pkt) => pkt.data_offset as usize * 4 + pkt.length,
+            SerializablePacket::UdpPacket(pkt) => pkt.length as usize,
+            SerializablePacket::UnknownPacket(pkt) => pkt.length,
+            _ => 0,
+        }
+    }
+
+    /// Get the field, value and meaning of each header field, none for the representations
+    /// without explanation
+    pub fn explain(&self) -> Vec<(String, String, String)> {
+        match self {
+            SerializablePacket::EthernetPacket(pkt) => pkt.explain(),
+            SerializablePacket::ArpPacket(pkt) => pkt.explain(),
+            SerializablePacket::Ipv4Packet(pkt) => pkt.explain(),
+            SerializablePacket::Ipv6Packet(pkt) => pkt.explain(),
+            SerializablePacket::TcpPacket(pkt) => pkt.explain(),
+            SerializablePacket::UdpPacket(pkt) => pkt.explain(),
+            _ => vec![],
+        }
+    }
+}
+
 // Implémentez le trait Display pour SerializablePacket
 impl fmt::Display for SerializablePacket {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            SerializablePacket::EthernetPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::EthernetPacket(pkt) => fmt::Display::fmt(pkt, f),
+            SerializablePacket::PppoePacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::SllPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::Dot11Packet(pkt) => write!(f, "{}", pkt),
             SerializablePacket::ArpPacket(pkt) => write!(f, "{}", pkt),
-            SerializablePacket::Ipv4Packet(pkt) => write!(f, "{}", pkt),
-            SerializablePacket::Ipv6Packet(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::Ipv4Packet(pkt) => fmt::Display::fmt(pkt, f),
+            SerializablePacket::Ipv6Packet(pkt) => fmt::Display::fmt(pkt, f),
             SerializablePacket::EchoReplyPacket(pkt) => write!(f, "{:?}", pkt),
             SerializablePacket::EchoRequestPacket(pkt) => write!(f, "{:?}", pkt),
             SerializablePacket::IcmpPacket(pkt) => write!(f, "{:?}", pkt),
             SerializablePacket::Icmpv6Packet(pkt) => write!(f, "{:?}", pkt),
-            SerializablePacket::TcpPacket(pkt) => write!(f, "{}", pkt),
-            SerializablePacket::UdpPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::IgmpPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::OspfPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::SctpPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::TcpPacket(pkt) => fmt::Display::fmt(pkt, f),
+            SerializablePacket::UdpPacket(pkt) => fmt::Display::fmt(pkt, f),
             SerializablePacket::HttpRequestPacket(pkt) => write!(f, "{}", pkt),
             SerializablePacket::HttpResponsePacket(pkt) => write!(f, "{}", pkt),
             SerializablePacket::TlsPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::DtlsPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::QuicPacket(pkt) => write!(f, "{}", pkt),
             SerializablePacket::DnsPacket(pkt) => write!(f, "{}", pkt),
             SerializablePacket::MalformedPacket(s) => write!(f, "Malformed Packet: {}", s),
-            SerializablePacket::UnknownPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::UnknownPacket(pkt) => fmt::Display::fmt(pkt, f),
             SerializablePacket::ModbusPacket(pkt) => write!(f, "{:?}", pkt),
+            SerializablePacket::SmtpPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::Pop3Packet(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::ImapPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::LdapPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::KerberosPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::StunPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::TelnetPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::NbnsPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::WireGuardPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::GtpPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::SyslogPacket(pkt) => write!(f, "{}", pkt),
+            SerializablePacket::Dhcpv6Packet(pkt) => write!(f, "{}", pkt),
         }
     }
 }
 
-
+/// Length of the frame check sequence ending the Ethernet frames, usually left out of captures
+const ETHERNET_FCS_LENGTH: usize = 4;
+/// Minimum on-wire length of an Ethernet frame, FCS included
+const ETHERNET_MIN_FRAME_LENGTH: usize = 64;
+/// Maximum on-wire length of an untagged Ethernet frame, FCS included
+const ETHERNET_MAX_FRAME_LENGTH: usize = 1518;
+/// Length of an 802.1Q tag, raising the maximum length of the frames carrying it
+const VLAN_TAG_LENGTH: usize = 4;
 
 /// Ethernet Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableEthernetPacket {
     pub destination: MacAddr,
     pub source: MacAddr,
     pub ethertype: String,
     pub payload: Vec<u8>,
+    /// Length of the frame, header included
+    pub length: usize,
+    /// Frame shorter than 64 bytes on-wire, i.e. the captured length plus the FCS missing from
+    /// the capture; the frames sent by the capturing host are often captured before their padding
+    pub is_runt: bool,
+    /// Frame longer than 1518 bytes on-wire, plus 4 bytes per 802.1Q tag, i.e. the captured
+    /// length plus the FCS missing from the capture; the frames sent by the capturing host may be
+    /// captured before their segmentation by the NIC
+    pub is_jumbo: bool,
+    /// Kind of the destination address
+    pub frame_type: FrameType,
+}
+
+/// Kind of the destination address of a frame, telling how many hosts receive it
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+    Unicast,
+    /// ff:ff:ff:ff:ff:ff
+    Broadcast,
+    /// 01:00:5e, followed by the low 23 bits of an IPv4 multicast group (RFC 1112)
+    Ipv4Multicast,
+    /// 33:33, followed by the low 32 bits of an IPv6 multicast group (RFC 2464)
+    Ipv6Multicast,
+    /// Group bit, the lowest of the first octet, set
+    Multicast,
+}
+
+impl FrameType {
+    /// Get the kind of a destination address
+    pub fn from_destination(destination: MacAddr) -> Self {
+        match destination.octets() {
+            [0xff, 0xff, 0xff, 0xff, 0xff, 0xff] => FrameType::Broadcast,
+            [0x01, 0x00, 0x5e, octet, ..] if octet & 0x80 == 0 => FrameType::Ipv4Multicast,
+            [0x33, 0x33, ..] => FrameType::Ipv6Multicast,
+            [octet, ..] if octet & 0x01 != 0 => FrameType::Multicast,
+            _ => FrameType::Unicast,
+        }
+    }
 }
 
 impl<'a> From<&EthernetPacket<'a>> for SerializableEthernetPacket {
@@ -196,9 +546,34 @@ impl<'a> From<&EthernetPacket<'a>> for SerializableEthernetPacket {
             destination: packet.get_destination(),
             source: packet.get_source(),
             ethertype: packet.get_ethertype().to_string(),
-            payload: packet.payload().to_vec(),
+            payload: retained_payload(packet.payload()),
+            length: packet.packet().len(),
+            is_runt: packet.packet().len() + ETHERNET_FCS_LENGTH < ETHERNET_MIN_FRAME_LENGTH,
+            is_jumbo: packet.packet().len() + ETHERNET_FCS_LENGTH
+                > ETHERNET_MAX_FRAME_LENGTH + VLAN_TAG_LENGTH * vlan_tag_count(packet.packet()),
+            frame_type: FrameType::from_destination(packet.get_destination()),
+        }
+    }
+}
+
+/// Count the 802.1Q tags following the addresses of a frame, the stacked ones (802.1ad) included
+fn vlan_tag_count(frame: &[u8]) -> usize {
+    let mut count = 0;
+    while let Some(tpid) = frame.get(12 + VLAN_TAG_LENGTH * count..14 + VLAN_TAG_LENGTH * count) {
+        match EtherType(u16::from_be_bytes([tpid[0], tpid[1]])) {
+            EtherTypes::Vlan | EtherTypes::PBridge | EtherTypes::QinQ => count += 1,
+            _ => break,
         }
     }
+    count
+}
+
+/// Copy the payload of a packet, only when enabled with `set_payload_retention`
+fn retained_payload(payload: &[u8]) -> Vec<u8> {
+    match is_payload_retained() {
+        true => payload.to_vec(),
+        false => vec![],
+    }
 }
 
 /// Trait for displaying in different ways
@@ -209,47 +584,307 @@ pub trait DebugDisplay {
 
 impl DebugDisplay for SerializableEthernetPacket {
     fn display_with_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display_without_payload(f)?;
+        write!(f, "\n\tPayload:")?;
+        write_hexdump(f, &self.payload)
+    }
+
+    fn display_without_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "Ethernet Packet: \n\
-            \tDestination: {}\n\
+            \tDestination: {} ({:?})\n\
             \tSource: {}\n\
-            \tEthertype: {}\n\
-            \tPayload: {:?}",
-            self.destination,
-            self.source,
-            self.ethertype,
-            self.payload
-        )
+            \tEthertype: {}",
+            self.destination, self.frame_type, self.source, self.ethertype
+        )?;
+        if self.is_runt {
+            write!(f, "\n\tRunt Frame ({} bytes)", self.length)?;
+        }
+        if self.is_jumbo {
+            write!(f, "\n\tJumbo Frame ({} bytes)", self.length)?;
+        }
+
+        Ok(())
     }
+}
 
-    fn display_without_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl fmt::Display for SerializableEthernetPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            self.display_with_payload(f)
+        } else {
+            self.display_without_payload(f)
+        }
+    }
+}
+
+impl SerializableEthernetPacket {
+    /// Get the field, value and meaning of each header field
+    pub fn explain(&self) -> Vec<(String, String, String)> {
+        vec![
+            explained(
+                "Destination",
+                self.destination,
+                "hardware address of the receiver, ff:ff:ff:ff:ff:ff for all the hosts",
+            ),
+            explained("Source", self.source, "hardware address of the sender"),
+            explained("Ethertype", &self.ethertype, "protocol of the payload"),
+            explained("Length", self.length, "bytes of the frame, FCS excluded"),
+        ]
+    }
+}
+
+/// Build the explanation of a field: its name, its value and a short description of its meaning
+fn explained(field: &str, value: impl fmt::Display, description: &str) -> (String, String, String) {
+    (field.to_owned(), value.to_string(), description.to_owned())
+}
+
+/// PPPoE Packet Representation, with the addresses of its Ethernet frame
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializablePppoePacket {
+    pub destination: MacAddr,
+    pub source: MacAddr,
+    pub version: u8,
+    pub pppoe_type: u8,
+    pub code: String,
+    pub session_id: u16,
+    pub length: u16,
+    /// Protocol of the PPP frame carried by a session packet
+    pub ppp_protocol: Option<String>,
+}
+
+impl fmt::Display for SerializablePppoePacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Ethernet Packet: \n\
+            "PPPoE Packet: \n\
             \tDestination: {}\n\
             \tSource: {}\n\
-            \tEthertype: {}",
+            \tVersion: {}\n\
+            \tType: {}\n\
+            \tCode: {}\n\
+            \tSession ID: {:#06x}\n\
+            \tLength: {}",
             self.destination,
             self.source,
-            self.ethertype
+            self.version,
+            self.pppoe_type,
+            self.code,
+            self.session_id,
+            self.length
+        )?;
+        if let Some(ppp_protocol) = &self.ppp_protocol {
+            write!(f, "\n\tPPP Protocol: {}", ppp_protocol)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get PPPoE Code
+pub fn pppoe_code_to_string(code: u8) -> String {
+    return match code {
+        PppoeCodes::SESSION_DATA => format!("Session Data ({:#04x})", code),
+        PppoeCodes::PADO => format!("PADO ({:#04x})", code),
+        PppoeCodes::PADI => format!("PADI ({:#04x})", code),
+        PppoeCodes::PADR => format!("PADR ({:#04x})", code),
+        PppoeCodes::PADS => format!("PADS ({:#04x})", code),
+        PppoeCodes::PADT => format!("PADT ({:#04x})", code),
+        _ => format!("Unknown ({:#04x})", code),
+    };
+}
+
+/// Get PPP Protocol
+pub fn ppp_protocol_to_string(protocol: u16) -> String {
+    return match protocol {
+        PppProtocols::IPV4 => format!("IPv4 ({:#06x})", protocol),
+        PppProtocols::IPV6 => format!("IPv6 ({:#06x})", protocol),
+        PppProtocols::IPCP => format!("IPCP ({:#06x})", protocol),
+        PppProtocols::IPV6CP => format!("IPv6CP ({:#06x})", protocol),
+        PppProtocols::LCP => format!("LCP ({:#06x})", protocol),
+        PppProtocols::PAP => format!("PAP ({:#06x})", protocol),
+        PppProtocols::CHAP => format!("CHAP ({:#06x})", protocol),
+        _ => format!("Unknown ({:#06x})", protocol),
+    };
+}
+
+/// Linux cooked capture (SLL) Packet Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableSllPacket {
+    pub packet_type: String,
+    pub arphrd_type: u16,
+    pub address_length: u16,
+    /// Address of the sender, when a 6-byte (MAC) address
+    pub link_layer_address: Option<MacAddr>,
+    pub protocol: String,
+    /// Length of the packet, header included
+    pub length: usize,
+}
+
+impl fmt::Display for SerializableSllPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "SLL Packet: \n\
+            \tPacket Type: {}\n\
+            \tARPHRD Type: {}\n\
+            \tLink-Layer Address Length: {}\n\
+            \tLink-Layer Address: {}\n\
+            \tProtocol: {}",
+            self.packet_type,
+            self.arphrd_type,
+            self.address_length,
+            match &self.link_layer_address {
+                Some(address) => address.to_string(),
+                None => "-".to_string(),
+            },
+            self.protocol
         )
     }
 }
 
-impl fmt::Display for SerializableEthernetPacket {
+/// Get SLL Packet Type
+pub fn sll_packet_type_to_string(packet_type: u16) -> String {
+    return match packet_type {
+        SllPacketTypes::HOST => format!("Host ({})", packet_type),
+        SllPacketTypes::BROADCAST => format!("Broadcast ({})", packet_type),
+        SllPacketTypes::MULTICAST => format!("Multicast ({})", packet_type),
+        SllPacketTypes::OTHER_HOST => format!("OtherHost ({})", packet_type),
+        SllPacketTypes::OUTGOING => format!("Outgoing ({})", packet_type),
+        _ => format!("Unknown ({})", packet_type),
+    };
+}
+
+/// IEEE 802.11 Packet Representation, with the reception fields of its radiotap header
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableDot11Packet {
+    pub present_flags: u32,
+    pub signal_dbm: Option<i8>,
+    pub channel_frequency: Option<u16>,
+    pub frame_type: String,
+    pub subtype: String,
+    pub flags: u8,
+    pub receiver: MacAddr,
+    pub transmitter: Option<MacAddr>,
+    pub bssid: Option<MacAddr>,
+    /// Network name of the beacons and of the probes, empty when hidden or a wildcard
+    pub ssid: Option<String>,
+    /// Length of the frame, radiotap header included
+    pub length: usize,
+}
+
+impl fmt::Display for SerializableDot11Packet {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.display_without_payload(f)
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        write!(
+            f,
+            "802.11 Packet: \n\
+            \tPresent Flags: {:#010x}\n\
+            \tSignal: {}\n\
+            \tChannel Frequency: {}\n\
+            \tFrame Type: {}\n\
+            \tSubtype: {}\n\
+            \tFlags: {:#04x}\n\
+            \tReceiver: {}\n\
+            \tTransmitter: {}\n\
+            \tBSSID: {}",
+            self.present_flags,
+            optional(self.signal_dbm.map(|signal| format!("{} dBm", signal))),
+            optional(
+                self.channel_frequency
+                    .map(|frequency| format!("{} MHz", frequency))
+            ),
+            self.frame_type,
+            self.subtype,
+            self.flags,
+            self.receiver,
+            optional(self.transmitter.map(|address| address.to_string())),
+            optional(self.bssid.map(|address| address.to_string())),
+        )?;
+
+        if let Some(ssid) = &self.ssid {
+            write!(f, "\n\tSSID: {}", ssid)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get 802.11 Frame Type
+pub fn dot11_frame_type_to_string(frame_type: u8) -> String {
+    return match frame_type {
+        Dot11FrameTypes::MANAGEMENT => format!("Management ({})", frame_type),
+        Dot11FrameTypes::CONTROL => format!("Control ({})", frame_type),
+        Dot11FrameTypes::DATA => format!("Data ({})", frame_type),
+        _ => format!("Extension ({})", frame_type),
+    };
+}
+
+/// Get 802.11 Frame Subtype
+pub fn dot11_subtype_to_string(frame_type: u8, subtype: u8) -> String {
+    let name = match (frame_type, subtype) {
+        (Dot11FrameTypes::MANAGEMENT, subtype) => match subtype {
+            Dot11ManagementSubtypes::ASSOCIATION_REQUEST => "Association Request",
+            Dot11ManagementSubtypes::ASSOCIATION_RESPONSE => "Association Response",
+            Dot11ManagementSubtypes::REASSOCIATION_REQUEST => "Reassociation Request",
+            Dot11ManagementSubtypes::REASSOCIATION_RESPONSE => "Reassociation Response",
+            Dot11ManagementSubtypes::PROBE_REQUEST => "Probe Request",
+            Dot11ManagementSubtypes::PROBE_RESPONSE => "Probe Response",
+            Dot11ManagementSubtypes::BEACON => "Beacon",
+            Dot11ManagementSubtypes::ATIM => "ATIM",
+            Dot11ManagementSubtypes::DISASSOCIATION => "Disassociation",
+            Dot11ManagementSubtypes::AUTHENTICATION => "Authentication",
+            Dot11ManagementSubtypes::DEAUTHENTICATION => "Deauthentication",
+            Dot11ManagementSubtypes::ACTION => "Action",
+            _ => "Unknown",
+        },
+        (Dot11FrameTypes::CONTROL, subtype) => match subtype {
+            Dot11ControlSubtypes::BLOCK_ACK_REQUEST => "Block Ack Request",
+            Dot11ControlSubtypes::BLOCK_ACK => "Block Ack",
+            Dot11ControlSubtypes::PS_POLL => "PS-Poll",
+            Dot11ControlSubtypes::RTS => "RTS",
+            Dot11ControlSubtypes::CTS => "CTS",
+            Dot11ControlSubtypes::ACK => "ACK",
+            Dot11ControlSubtypes::CF_END => "CF-End",
+            _ => "Unknown",
+        },
+        (Dot11FrameTypes::DATA, subtype) => match subtype {
+            Dot11DataSubtypes::DATA => "Data",
+            Dot11DataSubtypes::NULL => "Null",
+            Dot11DataSubtypes::QOS_DATA => "QoS Data",
+            Dot11DataSubtypes::QOS_NULL => "QoS Null",
+            _ => "Unknown",
+        },
+        _ => "Unknown",
+    };
+
+    format!("{} ({})", name, subtype)
+}
+
+/// Write the hexdump of a payload, one indented line per row
+fn write_hexdump(f: &mut fmt::Formatter<'_>, payload: &[u8]) -> fmt::Result {
+    for line in hexdump(payload).lines() {
+        write!(f, "\n\t\t{}", line)?;
     }
+
+    Ok(())
 }
 
 /// Unknown Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableUnknownPacket {
     pub destination: MacAddr,
     pub source: MacAddr,
     pub ethertype: String,
     pub length: usize,
+    pub payload: Vec<u8>,
+    /// Printable strings of the payload, when their extraction is enabled
+    pub strings: Option<Vec<String>>,
 }
 
 impl<'a> From<&EthernetPacket<'a>> for SerializableUnknownPacket {
@@ -259,11 +894,20 @@ impl<'a> From<&EthernetPacket<'a>> for SerializableUnknownPacket {
             source: packet.get_source(),
             ethertype: packet.get_ethertype().to_string(),
             length: packet.packet().len(),
+            payload: retained_payload(packet.payload()),
+            strings: extracted_strings(packet.payload()),
         }
     }
 }
-impl fmt::Display for SerializableUnknownPacket {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+impl DebugDisplay for SerializableUnknownPacket {
+    fn display_with_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display_without_payload(f)?;
+        write!(f, "\n\tPayload:")?;
+        write_hexdump(f, &self.payload)
+    }
+
+    fn display_without_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "Unknown Packet: \n\
@@ -275,6 +919,21 @@ impl fmt::Display for SerializableUnknownPacket {
             self.source,
             self.ethertype,
             self.length
-        )
+        )?;
+        if let Some(strings) = &self.strings {
+            write!(f, "\n\tStrings: {:?}", strings)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for SerializableUnknownPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            self.display_with_payload(f)
+        } else {
+            self.display_without_payload(f)
+        }
     }
 }
@@ -10,8 +10,11 @@ use pnet::packet::Packet;
 use pnet::util::MacAddr;
 use serde::Serialize;
 
+use super::{explained, retained_payload, write_hexdump, DebugDisplay, SerializablePacket};
+
 /// ARP Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableArpPacket {
     pub hardware_type: String,
     pub protocol_type: u16,
@@ -22,6 +25,10 @@ pub struct SerializableArpPacket {
     pub sender_proto_addr: Ipv4Addr,
     pub target_hw_addr: MacAddr,
     pub target_proto_addr: Ipv4Addr,
+    pub is_gratuitous: bool,
+    pub is_announcement: bool,
+    /// Request of a host checking that an address is free before using it (sender 0.0.0.0)
+    pub is_probe: bool,
     pub length: usize,
 }
 
@@ -41,11 +48,44 @@ impl<'a> From<&ArpPacket<'a>> for SerializableArpPacket {
             sender_proto_addr: packet.get_sender_proto_addr(),
             target_hw_addr: packet.get_target_hw_addr(),
             target_proto_addr: packet.get_target_proto_addr(),
+            is_gratuitous: is_gratuitous_arp(packet),
+            is_announcement: is_arp_announcement(packet),
+            is_probe: is_arp_probe(packet),
             length: packet.payload().len(),
         }
     }
 }
 
+/// Check if the sender announces its own address, as it also is the target one
+fn is_gratuitous_arp(packet: &ArpPacket) -> bool {
+    packet.get_sender_proto_addr() == packet.get_target_proto_addr()
+}
+
+/// Check if a gratuitous ARP is an announcement: a request not resolving any hardware address
+/// (RFC 5227), or a reply broadcast or addressed to the sender itself. Gratuitous replies sent
+/// to a single other host are not, as they are typical of cache poisoning
+fn is_arp_announcement(packet: &ArpPacket) -> bool {
+    let target_hw_addr = packet.get_target_hw_addr();
+
+    is_gratuitous_arp(packet)
+        && match packet.get_operation() {
+            ArpOperations::Request => {
+                target_hw_addr == MacAddr::zero() || target_hw_addr.is_broadcast()
+            }
+            ArpOperations::Reply => {
+                target_hw_addr.is_broadcast() || target_hw_addr == packet.get_sender_hw_addr()
+            }
+            _ => false,
+        }
+}
+
+/// Check if a request is a probe: sent by a host without address yet, to detect whether the target
+/// one is already in use (RFC 5227 duplicate address detection)
+fn is_arp_probe(packet: &ArpPacket) -> bool {
+    packet.get_operation() == ArpOperations::Request
+        && packet.get_sender_proto_addr().is_unspecified()
+}
+
 impl fmt::Display for SerializableArpPacket {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -60,6 +100,9 @@ impl fmt::Display for SerializableArpPacket {
             \tSender Proto Addr: {}\n\
             \tTarget HW Addr: {}\n\
             \tTarget Proto Addr: {}\n\
+            \tGratuitous: {}\n\
+            \tAnnouncement: {}\n\
+            \tProbe: {}\n\
             \tLength: {}",
             self.hardware_type,
             self.protocol_type,
@@ -70,13 +113,60 @@ impl fmt::Display for SerializableArpPacket {
             self.sender_proto_addr,
             self.target_hw_addr,
             self.target_proto_addr,
+            self.is_gratuitous,
+            self.is_announcement,
+            self.is_probe,
             self.length
         )
     }
 }
 
+impl SerializableArpPacket {
+    /// Get the field, value and meaning of each header field
+    pub fn explain(&self) -> Vec<(String, String, String)> {
+        vec![
+            explained(
+                "Hardware Type",
+                &self.hardware_type,
+                "type of the link-layer addresses",
+            ),
+            explained(
+                "Protocol Type",
+                format!("{:#06x}", self.protocol_type),
+                "type of the network-layer addresses, 0x0800 for IPv4",
+            ),
+            explained(
+                "Operation",
+                &self.operation,
+                "request to resolve an address, or reply resolving it",
+            ),
+            explained(
+                "Sender HW Addr",
+                self.sender_hw_addr,
+                "hardware address of the sender",
+            ),
+            explained(
+                "Sender Proto Addr",
+                self.sender_proto_addr,
+                "IP address of the sender",
+            ),
+            explained(
+                "Target HW Addr",
+                self.target_hw_addr,
+                "hardware address resolved, zero in a request",
+            ),
+            explained(
+                "Target Proto Addr",
+                self.target_proto_addr,
+                "IP address to resolve",
+            ),
+        ]
+    }
+}
+
 /// IPv6 Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableIpv6Packet {
     pub version: u8,
     pub traffic_class: u8,
@@ -86,7 +176,13 @@ pub struct SerializableIpv6Packet {
     pub hop_limit: u8,
     pub source: Ipv6Addr,
     pub destination: Ipv6Addr,
+    pub source_address_class: AddressClass,
+    pub destination_address_class: AddressClass,
     pub length: usize,
+    /// Payload, only when enabled with `set_payload_retention`
+    pub payload: Vec<u8>,
+    /// Network-layer packet tunneled in the payload (IP-in-IP, 6in4)
+    pub encapsulated: Option<Box<SerializablePacket>>,
 }
 
 impl<'a> From<&Ipv6Packet<'a>> for SerializableIpv6Packet {
@@ -104,24 +200,34 @@ impl<'a> From<&Ipv6Packet<'a>> for SerializableIpv6Packet {
             hop_limit: packet.get_hop_limit(),
             source: packet.get_source(),
             destination: packet.get_destination(),
+            source_address_class: AddressClass::from_ipv6(packet.get_source()),
+            destination_address_class: AddressClass::from_ipv6(packet.get_destination()),
             length: packet.payload().len(),
+            payload: retained_payload(packet.payload()),
+            encapsulated: None,
         }
     }
 }
 
-impl fmt::Display for SerializableIpv6Packet {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl DebugDisplay for SerializableIpv6Packet {
+    fn display_with_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display_without_payload(f)?;
+        write!(f, "\n\tPayload:")?;
+        write_hexdump(f, &self.payload)
+    }
+
+    fn display_without_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "IPv6 Packet: \n\
             \tVersion: {}\n\
             \tTraffic Class: {}\n\
-            \tFlow Label: {}\n\
+            \tFlow Label: {:#07x}\n\
             \tPayload Length: {}\n\
             \tNext Header: {}\n\
             \tHop Limit: {}\n\
-            \tSource: {}\n\
-            \tDestination: {}\n\
+            \tSource: {} ({:?})\n\
+            \tDestination: {} ({:?})\n\
             \tLength: {}",
             self.version,
             self.traffic_class,
@@ -130,19 +236,86 @@ impl fmt::Display for SerializableIpv6Packet {
             self.next_header,
             self.hop_limit,
             self.source,
+            self.source_address_class,
             self.destination,
+            self.destination_address_class,
             self.length
-        )
+        )?;
+        write_encapsulated(f, &self.encapsulated)
+    }
+}
+
+impl fmt::Display for SerializableIpv6Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            self.display_with_payload(f)
+        } else {
+            self.display_without_payload(f)
+        }
+    }
+}
+
+impl SerializableIpv6Packet {
+    /// Get the field, value and meaning of each header field
+    pub fn explain(&self) -> Vec<(String, String, String)> {
+        vec![
+            explained("Version", self.version, "IP version, always 6"),
+            explained(
+                "Traffic Class",
+                self.traffic_class,
+                "DSCP and ECN bits, for quality of service and congestion",
+            ),
+            explained(
+                "Flow Label",
+                format!("{:#07x}", self.flow_label),
+                "identifier of the flow the packet belongs to, 0 if none",
+            ),
+            explained(
+                "Payload Length",
+                self.payload_length,
+                "bytes after the 40-byte header, extension headers included",
+            ),
+            explained(
+                "Next Header",
+                &self.next_header,
+                "protocol or extension header following the header",
+            ),
+            explained("Hop Limit", self.hop_limit, "hops remaining before discard"),
+            explained("Source", self.source, "address of the sender"),
+            explained("Destination", self.destination, "address of the receiver"),
+        ]
+    }
+}
+
+/// Show the packet tunneled in an IP or GTP-U packet, on indented lines
+pub(crate) fn write_encapsulated(
+    f: &mut fmt::Formatter<'_>,
+    encapsulated: &Option<Box<SerializablePacket>>,
+) -> fmt::Result {
+    match encapsulated {
+        Some(encapsulated) => {
+            let encapsulated = match f.alternate() {
+                true => format!("{:#}", encapsulated),
+                false => encapsulated.to_string(),
+            };
+            let encapsulated = encapsulated.replace('\n', "\n\t");
+            write!(f, "\n\tEncapsulated {}", encapsulated)
+        }
+        None => Ok(()),
     }
 }
 
 /// IPv4 Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableIpv4Packet {
     pub version: u8,
     pub header_length: u8,
     pub dscp: u8,
+    /// Per-hop behavior of the DSCP, e.g. `EF`
+    pub dscp_name: String,
     pub ecn: u8,
+    pub ecn_name: String,
     pub total_length: u16,
     pub identification: u16,
     pub flags: u8,
@@ -152,7 +325,13 @@ pub struct SerializableIpv4Packet {
     pub checksum: u16,
     pub source: Ipv4Addr,
     pub destination: Ipv4Addr,
+    pub source_address_class: AddressClass,
+    pub destination_address_class: AddressClass,
     pub length: usize,
+    /// Payload, only when enabled with `set_payload_retention`
+    pub payload: Vec<u8>,
+    /// Network-layer packet tunneled in the payload (IP-in-IP, 6in4)
+    pub encapsulated: Option<Box<SerializablePacket>>,
 }
 
 impl<'a> From<&Ipv4Packet<'a>> for SerializableIpv4Packet {
@@ -161,7 +340,9 @@ impl<'a> From<&Ipv4Packet<'a>> for SerializableIpv4Packet {
             version: packet.get_version(),
             header_length: packet.get_header_length(),
             dscp: packet.get_dscp(),
+            dscp_name: dscp_to_string(packet.get_dscp()),
             ecn: packet.get_ecn(),
+            ecn_name: ecn_to_string(packet.get_ecn()),
             total_length: packet.get_total_length(),
             identification: packet.get_identification(),
             flags: packet.get_flags(),
@@ -175,20 +356,30 @@ impl<'a> From<&Ipv4Packet<'a>> for SerializableIpv4Packet {
             checksum: packet.get_checksum(),
             source: packet.get_source(),
             destination: packet.get_destination(),
+            source_address_class: AddressClass::from_ipv4(packet.get_source()),
+            destination_address_class: AddressClass::from_ipv4(packet.get_destination()),
             length: packet.payload().len(),
+            payload: retained_payload(packet.payload()),
+            encapsulated: None,
         }
     }
 }
 
-impl fmt::Display for SerializableIpv4Packet {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl DebugDisplay for SerializableIpv4Packet {
+    fn display_with_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display_without_payload(f)?;
+        write!(f, "\n\tPayload:")?;
+        write_hexdump(f, &self.payload)
+    }
+
+    fn display_without_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "IPv4 Packet: \n\
             \tVersion: {}\n\
             \tHeader Length: {}\n\
-            \tDSCP: {}\n\
-            \tECN: {}\n\
+            \tDSCP: {} ({})\n\
+            \tECN: {} ({})\n\
             \tTotal Length: {}\n\
             \tIdentification: {}\n\
             \tFlags: {}\n\
@@ -196,13 +387,15 @@ impl fmt::Display for SerializableIpv4Packet {
             \tTTL: {}\n\
             \tNext Level Protocol: {}\n\
             \tChecksum: {}\n\
-            \tSource: {}\n\
-            \tDestination: {}\n\
+            \tSource: {} ({:?})\n\
+            \tDestination: {} ({:?})\n\
             \tLength: {}",
             self.version,
             self.header_length,
             self.dscp,
+            self.dscp_name,
             self.ecn,
+            self.ecn_name,
             self.total_length,
             self.identification,
             self.flags,
@@ -211,8 +404,163 @@ impl fmt::Display for SerializableIpv4Packet {
             self.next_level_protocol,
             self.checksum,
             self.source,
+            self.source_address_class,
             self.destination,
+            self.destination_address_class,
             self.length
-        )
+        )?;
+        write_encapsulated(f, &self.encapsulated)
+    }
+}
+
+impl fmt::Display for SerializableIpv4Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            self.display_with_payload(f)
+        } else {
+            self.display_without_payload(f)
+        }
     }
-}
\ No newline at end of file
+}
+
+impl SerializableIpv4Packet {
+    /// Get the field, value and meaning of each header field
+    pub fn explain(&self) -> Vec<(String, String, String)> {
+        vec![
+            explained("Version", self.version, "IP version, always 4"),
+            explained(
+                "Header Length",
+                self.header_length,
+                "length of the header in 32-bit words, 5 without options",
+            ),
+            explained(
+                "DSCP",
+                format!("{} ({})", self.dscp, self.dscp_name),
+                "traffic class, for quality of service",
+            ),
+            explained(
+                "ECN",
+                format!("{} ({})", self.ecn, self.ecn_name),
+                "explicit congestion notification",
+            ),
+            explained(
+                "Total Length",
+                self.total_length,
+                "bytes of the packet, header included",
+            ),
+            explained(
+                "Identification",
+                self.identification,
+                "identifier shared by the fragments of a datagram",
+            ),
+            explained(
+                "Flags",
+                self.flags,
+                "2: don't fragment (DF), 1: more fragments follow (MF)",
+            ),
+            explained(
+                "Fragment Offset",
+                self.fragment_offset,
+                "position of the fragment in the datagram, in 8-byte units",
+            ),
+            explained("TTL", self.ttl, "hops remaining before discard"),
+            explained(
+                "Next Level Protocol",
+                &self.next_level_protocol,
+                "protocol of the payload",
+            ),
+            explained(
+                "Checksum",
+                format!("{:#06x}", self.checksum),
+                "checksum of the header, recomputed at each hop",
+            ),
+            explained("Source", self.source, "address of the sender"),
+            explained("Destination", self.destination, "address of the receiver"),
+        ]
+    }
+}
+
+/// Get the per-hop behavior name of a DSCP: class selectors, assured forwarding classes and drop
+/// precedences, expedited forwarding
+pub fn dscp_to_string(dscp: u8) -> String {
+    return match dscp {
+        0 => "Default".to_string(),
+        1 => "LE".to_string(),
+        44 => "VOICE-ADMIT".to_string(),
+        46 => "EF".to_string(),
+        8 | 16 | 24 | 32 | 40 | 48 | 56 => format!("CS{}", dscp >> 3),
+        10 | 12 | 14 | 18 | 20 | 22 | 26 | 28 | 30 | 34 | 36 | 38 => {
+            format!("AF{}{}", dscp >> 3, (dscp >> 1) & 0x03)
+        }
+        _ => "Unknown".to_string(),
+    };
+}
+
+/// Get the ECN codepoint name
+pub fn ecn_to_string(ecn: u8) -> String {
+    return match ecn {
+        0 => "Not-ECT".to_string(),
+        1 => "ECT(1)".to_string(),
+        2 => "ECT(0)".to_string(),
+        3 => "CE".to_string(),
+        _ => "Unknown".to_string(),
+    };
+}
+
+/// Class of an IP address, telling whether it is routed on the Internet and to which hosts
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressClass {
+    /// Private IPv4 ranges of RFC 1918
+    Private,
+    /// IPv6 Unique Local Addresses, fc00::/7
+    UniqueLocal,
+    Loopback,
+    LinkLocal,
+    Multicast,
+    Broadcast,
+    /// Unspecified, documentation, benchmarking, shared and future use ranges
+    Reserved,
+    Public,
+}
+
+impl AddressClass {
+    /// Get the class of an IPv4 address
+    pub fn from_ipv4(address: Ipv4Addr) -> Self {
+        let octets = address.octets();
+        match octets {
+            [255, 255, 255, 255] => AddressClass::Broadcast,
+            [127, ..] => AddressClass::Loopback,
+            [10, ..] | [172, 16..=31, ..] | [192, 168, ..] => AddressClass::Private,
+            [169, 254, ..] => AddressClass::LinkLocal,
+            [224..=239, ..] => AddressClass::Multicast,
+            [0, ..]
+            | [100, 64..=127, ..]
+            | [192, 0, 0 | 2, _]
+            | [198, 18 | 19, ..]
+            | [198, 51, 100, _]
+            | [203, 0, 113, _]
+            | [240..=255, ..] => AddressClass::Reserved,
+            _ => AddressClass::Public,
+        }
+    }
+
+    /// Get the class of an IPv6 address, an IPv4-mapped one having the class of its IPv4 address
+    pub fn from_ipv6(address: Ipv6Addr) -> Self {
+        if let Some(ipv4_address) = address.to_ipv4_mapped() {
+            return AddressClass::from_ipv4(ipv4_address);
+        }
+        if address.is_loopback() {
+            return AddressClass::Loopback;
+        }
+
+        let segments = address.segments();
+        match segments[0] {
+            0xfc00..=0xfdff => AddressClass::UniqueLocal,
+            0xfe80..=0xfebf => AddressClass::LinkLocal,
+            0xff00..=0xffff => AddressClass::Multicast,
+            0x2001 if segments[1] == 0x0db8 => AddressClass::Reserved,
+            0x2000..=0x3fff => AddressClass::Public,
+            _ => AddressClass::Reserved,
+        }
+    }
+}
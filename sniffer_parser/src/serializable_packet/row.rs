@@ -0,0 +1,169 @@
+//! Tabular representation of the parsed packets
+//!
+//! A row flattens a packet into the same columns whatever its layers, for CSV exports and
+//! spreadsheets: the fields a packet lacks are empty strings
+
+use std::time::UNIX_EPOCH;
+
+use pnet::packet::tcp::TcpFlags;
+
+use super::util::{get_dest_ip, get_dest_port, get_source_ip, get_source_port};
+use super::{ParsedPacket, SerializablePacket};
+
+/// Columns of the rows, in order
+pub const ROW_COLUMNS: [&str; 8] = [
+    "timestamp",
+    "source_ip",
+    "dest_ip",
+    "source_port",
+    "dest_port",
+    "protocol",
+    "length",
+    "info",
+];
+
+/// Names of the TCP flags shown in the info column, in header order
+const TCP_FLAG_NAMES: [(u8, &str); 8] = [
+    (TcpFlags::CWR, "CWR"),
+    (TcpFlags::ECE, "ECE"),
+    (TcpFlags::URG, "URG"),
+    (TcpFlags::ACK, "ACK"),
+    (TcpFlags::PSH, "PSH"),
+    (TcpFlags::RST, "RST"),
+    (TcpFlags::SYN, "SYN"),
+    (TcpFlags::FIN, "FIN"),
+];
+
+/// Conversion into a flat row of named columns
+pub trait ToRow {
+    /// Get the value of each column of `ROW_COLUMNS`, in order
+    fn to_row(&self) -> Vec<(&'static str, String)>;
+}
+
+impl ToRow for ParsedPacket {
+    fn to_row(&self) -> Vec<(&'static str, String)> {
+        let timestamp = self.get_timestamp().map(|timestamp| {
+            let since_epoch = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+            format!(
+                "{}.{:06}",
+                since_epoch.as_secs(),
+                since_epoch.subsec_micros()
+            )
+        });
+        let protocol = self.protocol_stack().last().map(|name| name.to_string());
+        let length = match self.size_bytes() {
+            0 => None,
+            size => Some(size.to_string()),
+        };
+
+        let values = [
+            timestamp,
+            get_source_ip(self),
+            get_dest_ip(self),
+            get_source_port(self),
+            get_dest_port(self),
+            protocol,
+            length,
+            Some(info(self)),
+        ];
+        ROW_COLUMNS
+            .into_iter()
+            .zip(values)
+            .map(|(column, value)| (column, value.unwrap_or_default()))
+            .collect()
+    }
+}
+
+/// Summarize the highest layer of a packet in a line, like the info column of Wireshark
+fn info(packet: &ParsedPacket) -> String {
+    let layers = [
+        packet.get_application_layer_packet(),
+        packet.get_transport_layer_packet(),
+        packet.get_network_layer_packet(),
+        packet.get_link_layer_packet(),
+    ];
+
+    match layers.into_iter().flatten().next() {
+        Some(SerializablePacket::MalformedPacket(reason)) => reason.clone(),
+        Some(SerializablePacket::HttpRequestPacket(http_packet)) => {
+            format!("{} {}", http_packet.method, http_packet.path)
+        }
+        Some(SerializablePacket::HttpResponsePacket(http_packet)) => {
+            format!("{} {}", http_packet.code, http_packet.reason)
+        }
+        Some(SerializablePacket::DnsPacket(dns_packet)) => {
+            let kind = match dns_packet.header.query {
+                true => "Query",
+                false => "Response",
+            };
+            let questions: Vec<String> = dns_packet
+                .questions
+                .iter()
+                .map(|question| format!("{} {}", question.query_type, question.query_name))
+                .collect();
+            format!("{} {}", kind, questions.join(", "))
+        }
+        Some(SerializablePacket::TcpPacket(tcp_packet)) => {
+            let flags: Vec<&str> = TCP_FLAG_NAMES
+                .iter()
+                .filter(|(flag, _)| tcp_packet.flags & *flag as u16 != 0)
+                .map(|(_, name)| *name)
+                .collect();
+            format!(
+                "[{}] Seq={} Ack={} Win={} Len={}",
+                flags.join(", "),
+                tcp_packet.relative_seq.unwrap_or(tcp_packet.sequence),
+                tcp_packet
+                    .relative_ack
+                    .unwrap_or(tcp_packet.acknowledgement),
+                tcp_packet.window,
+                tcp_packet.length
+            )
+        }
+        Some(SerializablePacket::UdpPacket(udp_packet)) => {
+            format!("Len={}", udp_packet.length.saturating_sub(8))
+        }
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use pnet::packet::tcp::TcpFlags;
+
+    use super::{ToRow, ROW_COLUMNS};
+    use crate::serializable_packet::ParsedPacket;
+    use crate::test_util::{build_test_tcp_packet, timestamped, CLIENT, SERVER};
+
+    #[test]
+    fn tcp_packet_row() {
+        let packet = timestamped(
+            build_test_tcp_packet((CLIENT, 4444), (SERVER, 443), TcpFlags::SYN, 1000, &[]),
+            UNIX_EPOCH + Duration::from_micros(1_714_564_800_000_250),
+        );
+
+        assert_eq!(
+            packet.to_row(),
+            vec![
+                ("timestamp", "1714564800.000250".to_owned()),
+                ("source_ip", "10.10.10.10".to_owned()),
+                ("dest_ip", "11.11.11.11".to_owned()),
+                ("source_port", "4444".to_owned()),
+                ("dest_port", "443".to_owned()),
+                ("protocol", "TCP".to_owned()),
+                ("length", "54".to_owned()),
+                ("info", "[SYN] Seq=1000 Ack=0 Win=1024 Len=0".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_fields_empty() {
+        let row = ParsedPacket::new(0).to_row();
+        let columns: Vec<&str> = row.iter().map(|(column, _)| *column).collect();
+        assert_eq!(columns, ROW_COLUMNS);
+        assert!(row.iter().all(|(_, value)| value.is_empty()));
+    }
+}
@@ -1,54 +1,112 @@
 //! Transport level Packets Representation
 
 use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
 
 use pnet::packet::icmp::echo_reply::EchoReplyPacket;
 use pnet::packet::icmp::echo_request::EchoRequestPacket;
 use pnet::packet::icmp::{IcmpPacket, IcmpType, IcmpTypes};
 use pnet::packet::icmpv6::{Icmpv6Packet, Icmpv6Type, Icmpv6Types};
-use pnet::packet::tcp::TcpPacket;
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::ipv4::Ipv4Packet;
+use pnet::packet::ipv6::Ipv6Packet;
+use pnet::packet::tcp::{TcpFlags, TcpPacket};
 use pnet::packet::udp::UdpPacket;
 use pnet::packet::Packet;
 use serde::Serialize;
 
+use super::{explained, retained_payload, write_hexdump, DebugDisplay};
+use crate::ospf::OspfTypes;
+use crate::sctp::{PayloadProtocolIdentifiers, SctpChunkTypes};
+use crate::transport::IgmpTypes;
+
 /// TCP Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableTcpPacket {
     pub source: u16,
     pub destination: u16,
     pub sequence: u32,
     pub acknowledgement: u32,
+    /// Sequence number relative to the initial one of the flow, when its SYN was seen
+    pub relative_seq: Option<u32>,
+    /// Acknowledgement number relative to the initial sequence number of the peer, when known
+    pub relative_ack: Option<u32>,
     pub data_offset: u8,
     pub reserved: u8,
     pub flags: u16,
     pub window: u16,
     pub checksum: u16,
+    /// Checksum verified against the IP pseudo-header
+    pub checksum_valid: bool,
+    /// Invalid checksum most likely left for the NIC to compute (checksum offload)
+    pub checksum_offload_suspected: bool,
     pub urgent_ptr: u16,
+    /// Offset in the payload of the byte following the urgent data, when URG is set with a
+    /// non-zero pointer; past the payload when the urgent data goes on in the next segments
+    pub urgent_data_end: Option<usize>,
+    /// URG set with a zero urgent pointer, pointing to no urgent data (anomaly)
+    pub is_zero_urgent_pointer: bool,
     pub options: Vec<u8>,
     pub length: usize,
+    /// Payload, only when enabled with `set_payload_retention`
+    pub payload: Vec<u8>,
+    /// Receive window of 0 advertised outside a reset: the sender cannot accept more data
+    pub is_zero_window: bool,
+    /// Segment of at most a byte sent one byte before the next expected sequence number, to probe
+    /// an idle connection (heuristic)
+    pub is_keep_alive: bool,
+    /// Segment starting before the next expected sequence number of its direction, resending
+    /// data already seen
+    pub is_retransmission: bool,
+    /// Segment starting after the next expected sequence number of its direction, leaving a gap
+    /// of data not seen yet
+    pub is_out_of_order: bool,
+    /// Printable strings of a payload left unparsed, when their extraction is enabled
+    pub strings: Option<Vec<String>>,
 }
 
 impl<'a> From<&TcpPacket<'a>> for SerializableTcpPacket {
     fn from(packet: &TcpPacket<'a>) -> Self {
+        let is_urgent = packet.get_flags() & TcpFlags::URG != 0;
         SerializableTcpPacket {
             source: packet.get_source(),
             destination: packet.get_destination(),
             sequence: packet.get_sequence(),
             acknowledgement: packet.get_acknowledgement(),
+            relative_seq: None,
+            relative_ack: None,
             data_offset: packet.get_data_offset(),
             reserved: packet.get_reserved(),
             flags: packet.get_flags() as u16,
             window: packet.get_window(),
             checksum: packet.get_checksum(),
+            checksum_valid: true,
+            checksum_offload_suspected: false,
             urgent_ptr: packet.get_urgent_ptr(),
+            urgent_data_end: (is_urgent && packet.get_urgent_ptr() != 0)
+                .then_some(packet.get_urgent_ptr() as usize),
+            is_zero_urgent_pointer: is_urgent && packet.get_urgent_ptr() == 0,
             options: packet.get_options_raw().to_vec(),
             length: packet.payload().len(),
+            payload: retained_payload(packet.payload()),
+            is_zero_window: packet.get_window() == 0 && packet.get_flags() & TcpFlags::RST == 0,
+            is_keep_alive: false,
+            is_retransmission: false,
+            is_out_of_order: false,
+            strings: None,
         }
     }
 }
 
-impl fmt::Display for SerializableTcpPacket {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl DebugDisplay for SerializableTcpPacket {
+    fn display_with_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display_without_payload(f)?;
+        write!(f, "\n\tPayload:")?;
+        write_hexdump(f, &self.payload)
+    }
+
+    fn display_without_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "TCP Packet: \n\
@@ -60,34 +118,139 @@ impl fmt::Display for SerializableTcpPacket {
             \tReserved: {}\n\
             \tFlags: {:#x}\n\
             \tWindow: {}\n\
-            \tChecksum: {:#x}\n\
+            \tChecksum: {:#x} ({})\n\
             \tUrgent Pointer: {}\n\
             \tOptions: {:?}\n\
             \tPayload Length: {}",
             self.source,
             self.destination,
-            self.sequence,
-            self.acknowledgement,
+            relative_number(self.sequence, self.relative_seq),
+            relative_number(self.acknowledgement, self.relative_ack),
             self.data_offset,
             self.reserved,
             self.flags,
             self.window,
             self.checksum,
+            checksum_status(self.checksum_valid, self.checksum_offload_suspected),
             self.urgent_ptr,
             self.options,
             self.length
-        )
+        )?;
+        if let Some(urgent_data_end) = self.urgent_data_end {
+            write!(f, "\n\tUrgent Data: payload bytes 0..{}", urgent_data_end)?;
+        }
+        if self.is_zero_urgent_pointer {
+            write!(f, "\n\tZero Urgent Pointer With URG Set")?;
+        }
+        if self.is_zero_window {
+            write!(f, "\n\tZero Window")?;
+        }
+        if self.is_keep_alive {
+            write!(f, "\n\tKeep-Alive (heuristic)")?;
+        }
+        if self.is_retransmission {
+            write!(f, "\n\tRetransmission")?;
+        }
+        if self.is_out_of_order {
+            write!(f, "\n\tOut-Of-Order")?;
+        }
+        if let Some(strings) = &self.strings {
+            write!(f, "\n\tStrings: {:?}", strings)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for SerializableTcpPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            self.display_with_payload(f)
+        } else {
+            self.display_without_payload(f)
+        }
     }
 }
 
+impl SerializableTcpPacket {
+    /// Get the field, value and meaning of each header field
+    pub fn explain(&self) -> Vec<(String, String, String)> {
+        vec![
+            explained(
+                "Source Port",
+                self.source,
+                "port of the sending application",
+            ),
+            explained(
+                "Destination Port",
+                self.destination,
+                "port of the receiving application",
+            ),
+            explained(
+                "Sequence Number",
+                relative_number(self.sequence, self.relative_seq),
+                "position of the first payload byte in the stream of the sender",
+            ),
+            explained(
+                "Acknowledgement Number",
+                relative_number(self.acknowledgement, self.relative_ack),
+                "next sequence number expected from the peer, when ACK is set",
+            ),
+            explained(
+                "Data Offset",
+                self.data_offset,
+                "length of the header in 32-bit words, 5 without options",
+            ),
+            explained(
+                "Flags",
+                format!("{:#x}", self.flags),
+                "control bits: FIN 0x1, SYN 0x2, RST 0x4, PSH 0x8, ACK 0x10, URG 0x20",
+            ),
+            explained(
+                "Window",
+                self.window,
+                "bytes the sender can receive, before any window scaling",
+            ),
+            explained(
+                "Checksum",
+                format!(
+                    "{:#x} ({})",
+                    self.checksum,
+                    checksum_status(self.checksum_valid, self.checksum_offload_suspected)
+                ),
+                "checksum of the segment and the IP pseudo-header",
+            ),
+            explained(
+                "Urgent Pointer",
+                self.urgent_ptr,
+                "offset of the end of the urgent data, when URG is set",
+            ),
+            explained("Payload Length", self.length, "bytes of data carried"),
+        ]
+    }
+}
 
 /// UDP Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableUdpPacket {
     pub source: u16,
     pub destination: u16,
     pub length: u16,
     pub checksum: u16,
+    /// Checksum verified against the IP pseudo-header (a zero checksum is valid over IPv4)
+    pub checksum_valid: bool,
+    /// Invalid checksum most likely left for the NIC to compute (checksum offload)
+    pub checksum_offload_suspected: bool,
+    /// Payload, only when enabled with `set_payload_retention`
+    pub payload: Vec<u8>,
+    /// Application protocol guessed from the shape of an unparsed payload (e.g. `RTP?`), only a
+    /// guess
+    pub protocol_hint: Option<String>,
+    /// Sequence number of the header of a payload guessed to be RTP
+    pub rtp_sequence: Option<u16>,
+    /// Printable strings of a payload left unparsed, when their extraction is enabled
+    pub strings: Option<Vec<String>>,
 }
 
 impl<'a> From<&UdpPacket<'a>> for SerializableUdpPacket {
@@ -97,42 +260,144 @@ impl<'a> From<&UdpPacket<'a>> for SerializableUdpPacket {
             destination: packet.get_destination(),
             length: packet.get_length(),
             checksum: packet.get_checksum(),
+            checksum_valid: true,
+            checksum_offload_suspected: false,
+            payload: retained_payload(packet.payload()),
+            protocol_hint: None,
+            rtp_sequence: None,
+            strings: None,
         }
     }
 }
 
-impl fmt::Display for SerializableUdpPacket {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl DebugDisplay for SerializableUdpPacket {
+    fn display_with_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display_without_payload(f)?;
+        write!(f, "\n\tPayload:")?;
+        write_hexdump(f, &self.payload)
+    }
+
+    fn display_without_payload(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "UDP Packet: \n\
             \tSource Port: {}\n\
             \tDestination Port: {}\n\
             \tLength: {}\n\
-            \tChecksum: {:#x}",
+            \tChecksum: {:#x} ({})",
             self.source,
             self.destination,
             self.length,
-            self.checksum
-        )
+            self.checksum,
+            checksum_status(self.checksum_valid, self.checksum_offload_suspected),
+        )?;
+        if let Some(protocol_hint) = &self.protocol_hint {
+            write!(f, "\n\tProtocol Hint: {} (guessed)", protocol_hint)?;
+        }
+        if let Some(rtp_sequence) = self.rtp_sequence {
+            write!(f, "\n\tRTP Sequence Number: {}", rtp_sequence)?;
+        }
+        if let Some(strings) = &self.strings {
+            write!(f, "\n\tStrings: {:?}", strings)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for SerializableUdpPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            self.display_with_payload(f)
+        } else {
+            self.display_without_payload(f)
+        }
+    }
+}
+
+impl SerializableUdpPacket {
+    /// Get the field, value and meaning of each header field
+    pub fn explain(&self) -> Vec<(String, String, String)> {
+        vec![
+            explained(
+                "Source Port",
+                self.source,
+                "port of the sending application",
+            ),
+            explained(
+                "Destination Port",
+                self.destination,
+                "port of the receiving application",
+            ),
+            explained(
+                "Length",
+                self.length,
+                "bytes of the datagram, 8-byte header included",
+            ),
+            explained(
+                "Checksum",
+                format!(
+                    "{:#x} ({})",
+                    self.checksum,
+                    checksum_status(self.checksum_valid, self.checksum_offload_suspected)
+                ),
+                "checksum of the datagram and the IP pseudo-header, 0 if unused over IPv4",
+            ),
+        ]
+    }
+}
+
+/// Show a sequence or acknowledgement number relative when possible, absolute otherwise
+fn relative_number(absolute: u32, relative: Option<u32>) -> String {
+    match relative {
+        Some(relative) => format!("{} (relative, absolute {})", relative, absolute),
+        None => absolute.to_string(),
+    }
+}
+
+/// Get the verification status of a TCP or UDP checksum
+fn checksum_status(valid: bool, offload_suspected: bool) -> &'static str {
+    match (valid, offload_suspected) {
+        (true, _) => "valid",
+        (false, true) => "offload suspected",
+        (false, false) => "invalid",
     }
 }
 
 /// ICMPv6 Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableIcmpv6Packet {
     pub icmpv6_type: String,
     pub icmpv6_code: u8,
     pub checksum: u16,
+    /// Identifier and sequence number of an Echo request or reply
+    pub echo_identifier: Option<u16>,
+    pub echo_sequence: Option<u16>,
+    pub original_packet: Option<SerializableEmbeddedPacket>,
     pub length: usize,
 }
 
 impl<'a> From<&Icmpv6Packet<'a>> for SerializableIcmpv6Packet {
     fn from(packet: &Icmpv6Packet<'a>) -> Self {
+        let echo = match packet.get_icmpv6_type() {
+            Icmpv6Types::EchoRequest | Icmpv6Types::EchoReply => echo_header(packet.payload()),
+            _ => None,
+        };
+
         SerializableIcmpv6Packet {
             icmpv6_type: icmpv6_type_to_string(packet.get_icmpv6_type()),
             icmpv6_code: packet.get_icmpv6_code().0,
             checksum: packet.get_checksum(),
+            echo_identifier: echo.map(|(identifier, _)| identifier),
+            echo_sequence: echo.map(|(_, sequence)| sequence),
+            original_packet: match packet.get_icmpv6_type() {
+                Icmpv6Types::DestinationUnreachable
+                | Icmpv6Types::PacketTooBig
+                | Icmpv6Types::TimeExceeded
+                | Icmpv6Types::ParameterProblem => embedded_ipv6_packet(packet.payload()),
+                _ => None,
+            },
             length: packet.payload().len(),
         }
     }
@@ -158,10 +423,12 @@ pub fn icmpv6_type_to_string(icmp_type: Icmpv6Type) -> String {
 
 /// ICMP Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableIcmpPacket {
     pub icmp_type: String,
     pub icmp_code: u8,
     pub checksum: u16,
+    pub original_packet: Option<SerializableEmbeddedPacket>,
     pub length: usize,
 }
 
@@ -171,11 +438,143 @@ impl<'a> From<&IcmpPacket<'a>> for SerializableIcmpPacket {
             icmp_type: icmp_type_to_string(packet.get_icmp_type()),
             icmp_code: packet.get_icmp_code().0,
             checksum: packet.get_checksum(),
+            original_packet: match packet.get_icmp_type() {
+                IcmpTypes::DestinationUnreachable
+                | IcmpTypes::SourceQuench
+                | IcmpTypes::RedirectMessage
+                | IcmpTypes::TimeExceeded
+                | IcmpTypes::ParameterProblem => embedded_ipv4_packet(packet.payload()),
+                _ => None,
+            },
             length: packet.payload().len(),
         }
     }
 }
 
+/// Representation of the packet embedded in an ICMP error message: its IP header and the ports
+/// found in the first 8 bytes of its payload
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableEmbeddedPacket {
+    pub source: IpAddr,
+    pub destination: IpAddr,
+    pub protocol: String,
+    pub ttl: u8,
+    pub source_port: Option<u16>,
+    pub destination_port: Option<u16>,
+    /// Sequence number of an embedded TCP segment
+    pub tcp_sequence: Option<u32>,
+    /// Identifier and sequence number of an embedded ICMP or ICMPv6 Echo request
+    pub echo_identifier: Option<u16>,
+    pub echo_sequence: Option<u16>,
+}
+
+/// Get the identifier and sequence number following the type, code and checksum of an ICMP or
+/// ICMPv6 Echo message
+fn echo_header(payload: &[u8]) -> Option<(u16, u16)> {
+    match payload {
+        [i0, i1, s0, s1, ..] => Some((
+            u16::from_be_bytes([*i0, *i1]),
+            u16::from_be_bytes([*s0, *s1]),
+        )),
+        _ => None,
+    }
+}
+
+/// Length of the ICMP header part following type, code and checksum (unused, pointer, MTU, ...)
+const ICMP_ERROR_HEADER_REST_LENGTH: usize = 4;
+
+/// Get the IPv4 packet embedded in an ICMP error message
+fn embedded_ipv4_packet(payload: &[u8]) -> Option<SerializableEmbeddedPacket> {
+    let ipv4_packet = Ipv4Packet::new(payload.get(ICMP_ERROR_HEADER_REST_LENGTH..)?)?;
+    let header_length = ipv4_packet.get_header_length() as usize * 4;
+    let transport = payload.get(ICMP_ERROR_HEADER_REST_LENGTH + header_length..)?;
+    let (source_port, destination_port) =
+        embedded_ports(ipv4_packet.get_next_level_protocol(), transport);
+    let echo = embedded_echo_request(ipv4_packet.get_next_level_protocol(), transport);
+
+    Some(SerializableEmbeddedPacket {
+        source: IpAddr::V4(ipv4_packet.get_source()),
+        destination: IpAddr::V4(ipv4_packet.get_destination()),
+        protocol: format!(
+            "{} ({})",
+            ipv4_packet.get_next_level_protocol(),
+            ipv4_packet.get_next_level_protocol().0
+        ),
+        ttl: ipv4_packet.get_ttl(),
+        source_port,
+        destination_port,
+        tcp_sequence: embedded_tcp_sequence(ipv4_packet.get_next_level_protocol(), transport),
+        echo_identifier: echo.map(|(identifier, _)| identifier),
+        echo_sequence: echo.map(|(_, sequence)| sequence),
+    })
+}
+
+/// Get the IPv6 packet embedded in an ICMPv6 error message (extension headers are not skipped)
+fn embedded_ipv6_packet(payload: &[u8]) -> Option<SerializableEmbeddedPacket> {
+    let ipv6_packet = Ipv6Packet::new(payload.get(ICMP_ERROR_HEADER_REST_LENGTH..)?)?;
+    let (source_port, destination_port) =
+        embedded_ports(ipv6_packet.get_next_header(), ipv6_packet.payload());
+    let echo = embedded_echo_request(ipv6_packet.get_next_header(), ipv6_packet.payload());
+
+    Some(SerializableEmbeddedPacket {
+        source: IpAddr::V6(ipv6_packet.get_source()),
+        destination: IpAddr::V6(ipv6_packet.get_destination()),
+        protocol: format!(
+            "{} ({})",
+            ipv6_packet.get_next_header(),
+            ipv6_packet.get_next_header().0
+        ),
+        ttl: ipv6_packet.get_hop_limit(),
+        source_port,
+        destination_port,
+        tcp_sequence: embedded_tcp_sequence(ipv6_packet.get_next_header(), ipv6_packet.payload()),
+        echo_identifier: echo.map(|(identifier, _)| identifier),
+        echo_sequence: echo.map(|(_, sequence)| sequence),
+    })
+}
+
+/// Get the ports of an embedded TCP, UDP or SCTP header, all of them starting with the ports
+fn embedded_ports(protocol: IpNextHeaderProtocol, transport: &[u8]) -> (Option<u16>, Option<u16>) {
+    match (protocol, transport) {
+        (
+            IpNextHeaderProtocols::Tcp | IpNextHeaderProtocols::Udp | IpNextHeaderProtocols::Sctp,
+            [s0, s1, d0, d1, ..],
+        ) => (
+            Some(u16::from_be_bytes([*s0, *s1])),
+            Some(u16::from_be_bytes([*d0, *d1])),
+        ),
+        _ => (None, None),
+    }
+}
+
+/// Get the sequence number of an embedded TCP header, following its ports
+fn embedded_tcp_sequence(protocol: IpNextHeaderProtocol, transport: &[u8]) -> Option<u32> {
+    match (protocol, transport) {
+        (IpNextHeaderProtocols::Tcp, [_, _, _, _, q0, q1, q2, q3, ..]) => {
+            Some(u32::from_be_bytes([*q0, *q1, *q2, *q3]))
+        }
+        _ => None,
+    }
+}
+
+/// Get the identifier and sequence number of an embedded ICMP or ICMPv6 Echo request
+fn embedded_echo_request(protocol: IpNextHeaderProtocol, transport: &[u8]) -> Option<(u16, u16)> {
+    match (protocol, transport.first()) {
+        (IpNextHeaderProtocols::Icmp, Some(&icmp_type))
+            if icmp_type == IcmpTypes::EchoRequest.0 =>
+        {
+            echo_header(transport.get(4..)?)
+        }
+        (IpNextHeaderProtocols::Icmpv6, Some(&icmpv6_type))
+            if icmpv6_type == Icmpv6Types::EchoRequest.0 =>
+        {
+            echo_header(transport.get(4..)?)
+        }
+        _ => None,
+    }
+}
+
 /// Get ICMPv4 Message Type
 pub fn icmp_type_to_string(icmp_type: IcmpType) -> String {
     return match icmp_type {
@@ -201,6 +600,7 @@ pub fn icmp_type_to_string(icmp_type: IcmpType) -> String {
 
 /// ICMP Echo Reply Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableEchoReplyPacket {
     pub icmp_type: u8,
     pub icmp_code: u8,
@@ -225,6 +625,7 @@ impl<'a> From<&EchoReplyPacket<'a>> for SerializableEchoReplyPacket {
 
 /// ICMP Echo Request Packet Representation
 #[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct SerializableEchoRequestPacket {
     pub icmp_type: u8,
     pub icmp_code: u8,
@@ -246,3 +647,308 @@ impl<'a> From<&EchoRequestPacket<'a>> for SerializableEchoRequestPacket {
         }
     }
 }
+
+/// IGMP Packet Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableIgmpPacket {
+    pub igmp_type: String,
+    pub version: u8,
+    /// Maximum response time of a query, in tenths of a second
+    pub max_response_time: u16,
+    pub checksum: u16,
+    /// Group of a query, report or leave (absent from IGMPv3 reports, carried by their records)
+    pub group_address: Option<Ipv4Addr>,
+    /// Sources of an IGMPv3 group-and-source-specific query
+    pub sources: Vec<Ipv4Addr>,
+    pub records: Vec<SerializableIgmpGroupRecord>,
+    /// Length of the packet, header included
+    pub length: usize,
+}
+
+impl fmt::Display for SerializableIgmpPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "IGMP Packet: \n\
+            \tType: {}\n\
+            \tVersion: {}\n\
+            \tMax Response Time: {}\n\
+            \tChecksum: {:#x}\n\
+            \tGroup Address: {}",
+            self.igmp_type,
+            self.version,
+            self.max_response_time,
+            self.checksum,
+            self.group_address
+                .map(|address| address.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        )?;
+        if !self.sources.is_empty() {
+            write!(f, "\n\tSources: {:?}", self.sources)?;
+        }
+        for record in &self.records {
+            write!(
+                f,
+                "\n\tGroup Record: {} {} ({} sources: {:?})",
+                record.record_type,
+                record.multicast_address,
+                record.sources.len(),
+                record.sources
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Group record of an IGMPv3 Membership Report
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableIgmpGroupRecord {
+    pub record_type: String,
+    pub multicast_address: Ipv4Addr,
+    pub sources: Vec<Ipv4Addr>,
+}
+
+/// Get IGMP Message Type
+pub fn igmp_type_to_string(igmp_type: u8, version: u8) -> String {
+    return match igmp_type {
+        IgmpTypes::MEMBERSHIP_QUERY => format!("MembershipQuery v{} ({})", version, igmp_type),
+        IgmpTypes::V1_MEMBERSHIP_REPORT
+        | IgmpTypes::V2_MEMBERSHIP_REPORT
+        | IgmpTypes::V3_MEMBERSHIP_REPORT => {
+            format!("MembershipReport v{} ({})", version, igmp_type)
+        }
+        IgmpTypes::LEAVE_GROUP => format!("LeaveGroup ({})", igmp_type),
+        _ => format!("Unknown ({})", igmp_type),
+    };
+}
+
+/// Get IGMPv3 Group Record Type
+pub fn igmp_record_type_to_string(record_type: u8) -> String {
+    return match record_type {
+        1 => format!("ModeIsInclude ({})", record_type),
+        2 => format!("ModeIsExclude ({})", record_type),
+        3 => format!("ChangeToIncludeMode ({})", record_type),
+        4 => format!("ChangeToExcludeMode ({})", record_type),
+        5 => format!("AllowNewSources ({})", record_type),
+        6 => format!("BlockOldSources ({})", record_type),
+        _ => format!("Unknown ({})", record_type),
+    };
+}
+
+/// OSPFv2 Packet Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableOspfPacket {
+    pub version: u8,
+    pub ospf_type: String,
+    pub packet_length: u16,
+    pub router_id: Ipv4Addr,
+    pub area_id: Ipv4Addr,
+    pub checksum: u16,
+    pub auth_type: String,
+    pub hello: Option<SerializableOspfHello>,
+}
+
+impl fmt::Display for SerializableOspfPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "OSPF Packet: \n\
+            \tVersion: {}\n\
+            \tType: {}\n\
+            \tPacket Length: {}\n\
+            \tRouter ID: {}\n\
+            \tArea ID: {}\n\
+            \tChecksum: {:#x}\n\
+            \tAuth Type: {}",
+            self.version,
+            self.ospf_type,
+            self.packet_length,
+            self.router_id,
+            self.area_id,
+            self.checksum,
+            self.auth_type,
+        )?;
+        if let Some(hello) = &self.hello {
+            write!(
+                f,
+                "\n\tNetwork Mask: {}\n\
+                \tHello Interval: {}\n\
+                \tOptions: {:#x}\n\
+                \tRouter Priority: {}\n\
+                \tRouter Dead Interval: {}\n\
+                \tDesignated Router: {}\n\
+                \tBackup Designated Router: {}\n\
+                \tNeighbors: {:?}",
+                hello.network_mask,
+                hello.hello_interval,
+                hello.options,
+                hello.router_priority,
+                hello.router_dead_interval,
+                hello.designated_router,
+                hello.backup_designated_router,
+                hello.neighbors,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Body of an OSPF Hello packet
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableOspfHello {
+    pub network_mask: Ipv4Addr,
+    pub hello_interval: u16,
+    pub options: u8,
+    pub router_priority: u8,
+    pub router_dead_interval: u32,
+    pub designated_router: Ipv4Addr,
+    pub backup_designated_router: Ipv4Addr,
+    pub neighbors: Vec<Ipv4Addr>,
+}
+
+/// Get OSPF Packet Type
+pub fn ospf_type_to_string(ospf_type: u8) -> String {
+    return match ospf_type {
+        OspfTypes::HELLO => format!("Hello ({})", ospf_type),
+        OspfTypes::DATABASE_DESCRIPTION => format!("DatabaseDescription ({})", ospf_type),
+        OspfTypes::LINK_STATE_REQUEST => format!("LinkStateRequest ({})", ospf_type),
+        OspfTypes::LINK_STATE_UPDATE => format!("LinkStateUpdate ({})", ospf_type),
+        OspfTypes::LINK_STATE_ACKNOWLEDGMENT => {
+            format!("LinkStateAcknowledgment ({})", ospf_type)
+        }
+        _ => format!("Unknown ({})", ospf_type),
+    };
+}
+
+/// SCTP Packet Representation
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableSctpPacket {
+    pub source: u16,
+    pub destination: u16,
+    pub verification_tag: u32,
+    pub checksum: u32,
+    pub chunks: Vec<SerializableSctpChunk>,
+    /// Length of the packet, common header included
+    pub length: usize,
+}
+
+impl fmt::Display for SerializableSctpPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "SCTP Packet: \n\
+            \tSource Port: {}\n\
+            \tDestination Port: {}\n\
+            \tVerification Tag: {:#010x}\n\
+            \tChecksum: {:#010x}\n\
+            \tLength: {}",
+            self.source, self.destination, self.verification_tag, self.checksum, self.length,
+        )?;
+        for chunk in &self.chunks {
+            write!(
+                f,
+                "\n\tChunk: {} (flags: {:#x}, length: {})",
+                chunk.chunk_type, chunk.flags, chunk.length
+            )?;
+            if let Some(data) = &chunk.data {
+                write!(
+                    f,
+                    " TSN: {}, Stream: {}/{}, Payload Protocol: {}, Payload Length: {}",
+                    data.tsn,
+                    data.stream_identifier,
+                    data.stream_sequence_number,
+                    data.payload_protocol,
+                    data.payload_length
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Chunk of a SCTP packet
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableSctpChunk {
+    pub chunk_type: String,
+    pub flags: u8,
+    /// Length of the chunk, header included and padding excluded
+    pub length: u16,
+    pub data: Option<SerializableSctpData>,
+}
+
+/// Fields of a SCTP DATA chunk
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableSctpData {
+    pub tsn: u32,
+    pub stream_identifier: u16,
+    pub stream_sequence_number: u16,
+    pub payload_protocol_identifier: u32,
+    /// Name of the protocol of the payload, e.g. `S1AP (18)`
+    pub payload_protocol: String,
+    pub payload_length: usize,
+}
+
+/// Get SCTP Chunk Type
+pub fn sctp_chunk_type_to_string(chunk_type: u8) -> String {
+    let name = match chunk_type {
+        SctpChunkTypes::DATA => "DATA",
+        SctpChunkTypes::INIT => "INIT",
+        SctpChunkTypes::INIT_ACK => "INIT ACK",
+        SctpChunkTypes::SACK => "SACK",
+        SctpChunkTypes::HEARTBEAT => "HEARTBEAT",
+        SctpChunkTypes::HEARTBEAT_ACK => "HEARTBEAT ACK",
+        SctpChunkTypes::ABORT => "ABORT",
+        SctpChunkTypes::SHUTDOWN => "SHUTDOWN",
+        SctpChunkTypes::SHUTDOWN_ACK => "SHUTDOWN ACK",
+        SctpChunkTypes::ERROR => "ERROR",
+        SctpChunkTypes::COOKIE_ECHO => "COOKIE ECHO",
+        SctpChunkTypes::COOKIE_ACK => "COOKIE ACK",
+        SctpChunkTypes::SHUTDOWN_COMPLETE => "SHUTDOWN COMPLETE",
+        _ => "Unknown",
+    };
+
+    format!("{} ({})", name, chunk_type)
+}
+
+/// Get SCTP Payload Protocol Identifier
+pub fn sctp_ppid_to_string(ppid: u32) -> String {
+    let name = match ppid {
+        PayloadProtocolIdentifiers::UNSPECIFIED => "Unspecified",
+        PayloadProtocolIdentifiers::IUA => "IUA",
+        PayloadProtocolIdentifiers::M2UA => "M2UA",
+        PayloadProtocolIdentifiers::M3UA => "M3UA",
+        PayloadProtocolIdentifiers::SUA => "SUA",
+        PayloadProtocolIdentifiers::M2PA => "M2PA",
+        PayloadProtocolIdentifiers::H248 => "H.248",
+        PayloadProtocolIdentifiers::S1AP => "S1AP",
+        PayloadProtocolIdentifiers::X2AP => "X2AP",
+        PayloadProtocolIdentifiers::DIAMETER => "Diameter",
+        PayloadProtocolIdentifiers::DIAMETER_DTLS => "Diameter over DTLS",
+        PayloadProtocolIdentifiers::WEBRTC_DCEP => "WebRTC DCEP",
+        PayloadProtocolIdentifiers::WEBRTC_STRING => "WebRTC String",
+        PayloadProtocolIdentifiers::WEBRTC_BINARY => "WebRTC Binary",
+        PayloadProtocolIdentifiers::NGAP => "NGAP",
+        PayloadProtocolIdentifiers::XNAP => "XnAP",
+        PayloadProtocolIdentifiers::F1AP => "F1AP",
+        _ => "Unknown",
+    };
+
+    format!("{} ({})", name, ppid)
+}
+
+/// Get OSPF Authentication Type
+pub fn ospf_auth_type_to_string(auth_type: u16) -> String {
+    return match auth_type {
+        0 => format!("Null ({})", auth_type),
+        1 => format!("SimplePassword ({})", auth_type),
+        2 => format!("Cryptographic ({})", auth_type),
+        _ => format!("Unknown ({})", auth_type),
+    };
+}
@@ -1,6 +1,19 @@
 //! Utility functions to retrieve specific fields of packets
 
+use std::net::IpAddr;
+
 use super::{ParsedPacket, SerializablePacket};
+use crate::pppoe::PPPOE_HEADER_LENGTH;
+use crate::sll::SLL_HEADER_LENGTH;
+use crate::HeaderLength;
+
+const ARP_PACKET_LENGTH: usize = 28;
+const IPV6_HEADER_LENGTH: usize = 40;
+
+/// Number of bytes shown on each hexdump line
+const HEXDUMP_LINE_WIDTH: usize = 16;
+/// Maximum number of bytes rendered by a hexdump, the remaining ones are elided
+const HEXDUMP_MAX_LENGTH: usize = 1024;
 
 /// Get Source MAC address (Link layer sender)
 pub fn get_source_mac(packet: &ParsedPacket) -> Option<String> {
@@ -10,6 +23,20 @@ pub fn get_source_mac(packet: &ParsedPacket) -> Option<String> {
         return Some(ethernet_packet.source.to_string());
     }
 
+    if let Some(SerializablePacket::PppoePacket(pppoe_packet)) = packet.get_link_layer_packet() {
+        return Some(pppoe_packet.source.to_string());
+    }
+
+    if let Some(SerializablePacket::SllPacket(sll_packet)) = packet.get_link_layer_packet() {
+        return sll_packet
+            .link_layer_address
+            .map(|address| address.to_string());
+    }
+
+    if let Some(SerializablePacket::Dot11Packet(dot11_packet)) = packet.get_link_layer_packet() {
+        return dot11_packet.transmitter.map(|address| address.to_string());
+    }
+
     return None;
 }
 
@@ -21,6 +48,14 @@ pub fn get_dest_mac(packet: &ParsedPacket) -> Option<String> {
         return Some(ethernet_packet.destination.to_string());
     }
 
+    if let Some(SerializablePacket::PppoePacket(pppoe_packet)) = packet.get_link_layer_packet() {
+        return Some(pppoe_packet.destination.to_string());
+    }
+
+    if let Some(SerializablePacket::Dot11Packet(dot11_packet)) = packet.get_link_layer_packet() {
+        return Some(dot11_packet.receiver.to_string());
+    }
+
     return None;
 }
 
@@ -56,6 +91,14 @@ pub fn get_dest_ip(packet: &ParsedPacket) -> Option<String> {
     };
 }
 
+/// Get IPv6 Flow Label (Network layer flow identifier)
+pub fn get_flow_label(packet: &ParsedPacket) -> Option<u32> {
+    return match packet.get_network_layer_packet() {
+        Some(SerializablePacket::Ipv6Packet(network_packet)) => Some(network_packet.flow_label),
+        _ => None,
+    };
+}
+
 /// Get Source Port (Transport layer sender)
 pub fn get_source_port(packet: &ParsedPacket) -> Option<String> {
     return match packet.get_transport_layer_packet() {
@@ -65,6 +108,9 @@ pub fn get_source_port(packet: &ParsedPacket) -> Option<String> {
         Some(SerializablePacket::UdpPacket(transport_packet)) => {
             Some(transport_packet.source.to_string())
         }
+        Some(SerializablePacket::SctpPacket(transport_packet)) => {
+            Some(transport_packet.source.to_string())
+        }
         _ => None,
     };
 }
@@ -78,10 +124,80 @@ pub fn get_dest_port(packet: &ParsedPacket) -> Option<String> {
         Some(SerializablePacket::UdpPacket(transport_packet)) => {
             Some(transport_packet.destination.to_string())
         }
+        Some(SerializablePacket::SctpPacket(transport_packet)) => {
+            Some(transport_packet.destination.to_string())
+        }
         _ => None,
     };
 }
 
+/// Get Source endpoint, IP address and port (Network and Transport layers sender)
+pub fn get_source_endpoint(packet: &ParsedPacket) -> Option<(IpAddr, u16)> {
+    Some((get_ip_addresses(packet)?.0, get_ports(packet)?.0))
+}
+
+/// Get Destination endpoint, IP address and port (Network and Transport layers receiver)
+pub fn get_dest_endpoint(packet: &ParsedPacket) -> Option<(IpAddr, u16)> {
+    Some((get_ip_addresses(packet)?.1, get_ports(packet)?.1))
+}
+
+/// Get the source and destination addresses of an IPv4 or IPv6 packet
+pub(crate) fn get_ip_addresses(packet: &ParsedPacket) -> Option<(IpAddr, IpAddr)> {
+    match packet.get_network_layer_packet()? {
+        SerializablePacket::Ipv4Packet(ipv4_packet) => Some((
+            IpAddr::V4(ipv4_packet.source),
+            IpAddr::V4(ipv4_packet.destination),
+        )),
+        SerializablePacket::Ipv6Packet(ipv6_packet) => Some((
+            IpAddr::V6(ipv6_packet.source),
+            IpAddr::V6(ipv6_packet.destination),
+        )),
+        _ => None,
+    }
+}
+
+/// Get the source and destination ports of a TCP, UDP or SCTP packet
+pub(crate) fn get_ports(packet: &ParsedPacket) -> Option<(u16, u16)> {
+    match packet.get_transport_layer_packet()? {
+        SerializablePacket::TcpPacket(tcp_packet) => {
+            Some((tcp_packet.source, tcp_packet.destination))
+        }
+        SerializablePacket::UdpPacket(udp_packet) => {
+            Some((udp_packet.source, udp_packet.destination))
+        }
+        SerializablePacket::SctpPacket(sctp_packet) => {
+            Some((sctp_packet.source, sctp_packet.destination))
+        }
+        _ => None,
+    }
+}
+
+/// Get the length of a packet from its headers: the Ethernet (or SLL) header plus the length of
+/// the ARP or IP packet, or of the PPPoE packet, without the Ethernet padding; the length of the
+/// 802.11 frames is the captured one
+pub fn get_frame_length(packet: &ParsedPacket) -> usize {
+    let link_layer_length = match packet.get_link_layer_packet() {
+        Some(SerializablePacket::EthernetPacket(_)) => HeaderLength::ETHERNET,
+        Some(SerializablePacket::SllPacket(_)) => SLL_HEADER_LENGTH,
+        Some(SerializablePacket::PppoePacket(pppoe_packet)) => {
+            return HeaderLength::ETHERNET + PPPOE_HEADER_LENGTH + pppoe_packet.length as usize;
+        }
+        Some(SerializablePacket::Dot11Packet(dot11_packet)) => return dot11_packet.length,
+        _ => 0,
+    };
+
+    let network_layer_length = match packet.get_network_layer_packet() {
+        Some(SerializablePacket::ArpPacket(_)) => ARP_PACKET_LENGTH,
+        Some(SerializablePacket::Ipv4Packet(ipv4_packet)) => ipv4_packet.total_length as usize,
+        Some(SerializablePacket::Ipv6Packet(ipv6_packet)) => {
+            IPV6_HEADER_LENGTH + ipv6_packet.payload_length as usize
+        }
+        _ => 0,
+    };
+
+    link_layer_length + network_layer_length
+}
+
 /// Check if packet type is unknown
 pub fn contains_unknokn(packet: &ParsedPacket) -> bool {
     if let Some(SerializablePacket::UnknownPacket(_)) = packet.get_link_layer_packet() {
@@ -121,6 +237,33 @@ pub fn contains_ethernet(packet: &ParsedPacket) -> bool {
     return false;
 }
 
+/// Check if packet contains a Linux cooked capture header (Link Layer)
+pub fn contains_sll(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::SllPacket(_)) = packet.get_link_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains an IEEE 802.11 header (Link Layer)
+pub fn contains_dot11(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::Dot11Packet(_)) = packet.get_link_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains PPPoE (Link Layer protocol)
+pub fn contains_pppoe(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::PppoePacket(_)) = packet.get_link_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
 /// Check if packet contains TCP (Transport Layer protocol)
 pub fn contains_tcp(packet: &ParsedPacket) -> bool {
     if let Some(SerializablePacket::TcpPacket(_)) = packet.get_transport_layer_packet() {
@@ -160,6 +303,33 @@ pub fn contains_icmp6(packet: &ParsedPacket) -> bool {
     return false;
 }
 
+/// Check if packet contains IGMP
+pub fn contains_igmp(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::IgmpPacket(_)) = packet.get_transport_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains SCTP
+pub fn contains_sctp(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::SctpPacket(_)) = packet.get_transport_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains OSPF
+pub fn contains_ospf(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::OspfPacket(_)) = packet.get_transport_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
 /// Check if packet contains ARP
 pub fn contains_arp(packet: &ParsedPacket) -> bool {
     if let Some(SerializablePacket::ArpPacket(_)) = packet.get_network_layer_packet() {
@@ -196,6 +366,141 @@ pub fn contains_tls(packet: &ParsedPacket) -> bool {
     return false;
 }
 
+/// Check if packet contains DTLS protocol (Application layer)
+pub fn contains_dtls(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::DtlsPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains QUIC protocol (Application layer)
+pub fn contains_quic(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::QuicPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains SMTP protocol (Application layer)
+pub fn contains_smtp(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::SmtpPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains POP3 protocol (Application layer)
+pub fn contains_pop3(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::Pop3Packet(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains IMAP protocol (Application layer)
+pub fn contains_imap(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::ImapPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains Kerberos protocol (Application layer)
+pub fn contains_kerberos(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::KerberosPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains LDAP protocol (Application layer)
+pub fn contains_ldap(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::LdapPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains STUN protocol (Application layer)
+pub fn contains_stun(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::StunPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains Telnet protocol (Application layer)
+pub fn contains_telnet(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::TelnetPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains NBNS protocol (Application layer)
+pub fn contains_nbns(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::NbnsPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains WireGuard protocol (Application layer)
+pub fn contains_wireguard(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::WireGuardPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains Modbus TCP protocol (Application layer)
+pub fn contains_modbus(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::ModbusPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains GTP-U protocol (Application layer)
+pub fn contains_gtp(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::GtpPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains Syslog protocol (Application layer)
+pub fn contains_syslog(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::SyslogPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains DHCPv6 protocol (Application layer)
+pub fn contains_dhcpv6(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::Dhcpv6Packet(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
 /// Check if packet contains DNS protocol (Application layer)
 pub fn contains_dns(packet: &ParsedPacket) -> bool {
     if let Some(SerializablePacket::DnsPacket(_)) = packet.get_application_layer_packet() {
@@ -215,3 +520,184 @@ pub fn contains_http(packet: &ParsedPacket) -> bool {
 
     return false;
 }
+
+/// Render bytes in the classic offset/hex/ASCII layout, eliding data past `HEXDUMP_MAX_LENGTH` bytes
+pub fn hexdump(bytes: &[u8]) -> String {
+    let mut lines = vec![];
+
+    for (i, chunk) in bytes
+        .chunks(HEXDUMP_LINE_WIDTH)
+        .take(HEXDUMP_MAX_LENGTH / HEXDUMP_LINE_WIDTH)
+        .enumerate()
+    {
+        let mut hex = String::new();
+        for j in 0..HEXDUMP_LINE_WIDTH {
+            if j == HEXDUMP_LINE_WIDTH / 2 {
+                hex.push(' ');
+            }
+            match chunk.get(j) {
+                Some(byte) => hex.push_str(&format!("{:02x} ", byte)),
+                None => hex.push_str("   "),
+            }
+        }
+
+        let ascii: String = chunk
+            .iter()
+            .map(|&byte| match byte {
+                0x20..=0x7e => byte as char,
+                _ => '.',
+            })
+            .collect();
+
+        lines.push(format!(
+            "{:08x}  {} |{}|",
+            i * HEXDUMP_LINE_WIDTH,
+            hex,
+            ascii
+        ));
+    }
+
+    if bytes.len() > HEXDUMP_MAX_LENGTH {
+        lines.push(format!(
+            "... ({} more bytes)",
+            bytes.len() - HEXDUMP_MAX_LENGTH
+        ));
+    }
+
+    lines.join("\n")
+}
+
+/// Extract the runs of at least `min_len` printable ASCII characters of a payload, like `strings`
+pub fn extract_strings(payload: &[u8], min_len: usize) -> Vec<String> {
+    payload
+        .split(|byte| !matches!(byte, 0x20..=0x7e | b'\t'))
+        .filter(|run| !run.is_empty() && run.len() >= min_len)
+        .map(|run| String::from_utf8_lossy(run).into_owned())
+        .collect()
+}
+
+/// Decode standard base64, padded or not; `None` on a character out of the alphabet
+pub fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0);
+
+    for byte in encoded.trim_end_matches('=').bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = ((buffer << 6) | value as u32) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(decoded)
+}
+
+/// Get the MD5 digest of bytes (RFC 1321) in lowercase hexadecimal, for fingerprints only
+pub fn md5_hex(data: &[u8]) -> String {
+    const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+
+    // Message padded with a 1 bit, then zeros up to 56 bytes modulo 64, then its bit length
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    for block in message.chunks_exact(64) {
+        let words: Vec<u32> = block
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+
+        for i in 0..64 {
+            let (function, index) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            // Constants of the rounds, the integer part of 2^32 * |sin(i + 1)|
+            let constant = ((i as f64 + 1.0).sin().abs() * 4_294_967_296.0) as u32;
+            let rotated = a
+                .wrapping_add(function)
+                .wrapping_add(constant)
+                .wrapping_add(words[index])
+                .rotate_left(SHIFTS[i / 16 * 4 + i % 4]);
+            (a, b, c, d) = (d, b.wrapping_add(rotated), b, c);
+        }
+
+        for (word, value) in state.iter_mut().zip([a, b, c, d]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    state
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_strings, hexdump, md5_hex};
+
+    #[test]
+    fn hexdump_short_slice() {
+        assert_eq!(
+            hexdump(b"GET / HTTP/1.1\r\nHost"),
+            "00000000  47 45 54 20 2f 20 48 54  54 50 2f 31 2e 31 0d 0a  |GET / HTTP/1.1..|\n\
+             00000010  48 6f 73 74                                       |Host|"
+        );
+    }
+
+    #[test]
+    fn hexdump_empty_slice() {
+        assert_eq!(hexdump(&[]), "");
+    }
+
+    #[test]
+    fn hexdump_large_slice_elided() {
+        let dump = hexdump(&[0u8; 2048]);
+
+        assert_eq!(dump.lines().count(), 1024 / 16 + 1);
+        assert!(dump.ends_with("... (1024 more bytes)"));
+    }
+
+    #[test]
+    fn printable_strings_extracted() {
+        assert_eq!(
+            extract_strings(b"\x00\x01GET /foo\r\n\xffab\x02Host: x", 4),
+            vec!["GET /foo", "Host: x"]
+        );
+        let random_bytes = [
+            0x3f, 0x9a, 0x71, 0x04, 0xd2, 0x5e, 0x0b, 0xc8, 0x66, 0x13, 0xaf, 0x2d, 0x90, 0x47,
+            0x1c, 0xe5,
+        ];
+        assert!(extract_strings(&random_bytes, 4).is_empty());
+    }
+
+    #[test]
+    fn md5_test_suite() {
+        assert_eq!(md5_hex(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(md5_hex(b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            md5_hex(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            ),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! Traversal of the layers of parsed packets
+//!
+//! A `Visitor` gets called with the representation held by each layer of a packet, through the
+//! method of its type; the methods do nothing by default, so a visitor only implements the ones of
+//! the representations it handles, without matching `SerializablePacket` itself. The packets
+//! encapsulated in a representation (e.g. tunneled in an IP packet) are not visited
+
+use super::application::{
+    SerializableDhcpv6Packet, SerializableDnsPacket, SerializableDtlsPacket, SerializableGtpPacket,
+    SerializableHttpRequestPacket, SerializableHttpResponsePacket, SerializableImapPacket,
+    SerializableKerberosPacket, SerializableLdapPacket, SerializableModbusPacket,
+    SerializableNbnsPacket, SerializablePop3Packet, SerializableQuicPacket, SerializableSmtpPacket,
+    SerializableStunPacket, SerializableSyslogPacket, SerializableTelnetPacket,
+    SerializableTlsPacket, SerializableWireGuardPacket,
+};
+use super::network::{SerializableArpPacket, SerializableIpv4Packet, SerializableIpv6Packet};
+use super::transport::{
+    SerializableEchoReplyPacket, SerializableEchoRequestPacket, SerializableIcmpPacket,
+    SerializableIcmpv6Packet, SerializableIgmpPacket, SerializableOspfPacket,
+    SerializableSctpPacket, SerializableTcpPacket, SerializableUdpPacket,
+};
+use super::{
+    ParsedPacket, SerializableDot11Packet, SerializableEthernetPacket, SerializablePacket,
+    SerializablePppoePacket, SerializableSllPacket, SerializableUnknownPacket,
+};
+
+/// Operation on the representations of the layers, one method per representation
+pub trait Visitor {
+    /// Visit an Ethernet packet
+    fn visit_ethernet(&mut self, _packet: &SerializableEthernetPacket) {}
+
+    /// Visit a PPPoE packet
+    fn visit_pppoe(&mut self, _packet: &SerializablePppoePacket) {}
+
+    /// Visit an SLL packet
+    fn visit_sll(&mut self, _packet: &SerializableSllPacket) {}
+
+    /// Visit an 802.11 packet
+    fn visit_dot11(&mut self, _packet: &SerializableDot11Packet) {}
+
+    /// Visit an ARP packet
+    fn visit_arp(&mut self, _packet: &SerializableArpPacket) {}
+
+    /// Visit an IPv4 packet
+    fn visit_ipv4(&mut self, _packet: &SerializableIpv4Packet) {}
+
+    /// Visit an IPv6 packet
+    fn visit_ipv6(&mut self, _packet: &SerializableIpv6Packet) {}
+
+    /// Visit an ICMP echo reply packet
+    fn visit_echo_reply(&mut self, _packet: &SerializableEchoReplyPacket) {}
+
+    /// Visit an ICMP echo request packet
+    fn visit_echo_request(&mut self, _packet: &SerializableEchoRequestPacket) {}
+
+    /// Visit an ICMP packet
+    fn visit_icmp(&mut self, _packet: &SerializableIcmpPacket) {}
+
+    /// Visit an ICMPv6 packet
+    fn visit_icmpv6(&mut self, _packet: &SerializableIcmpv6Packet) {}
+
+    /// Visit an IGMP packet
+    fn visit_igmp(&mut self, _packet: &SerializableIgmpPacket) {}
+
+    /// Visit an OSPF packet
+    fn visit_ospf(&mut self, _packet: &SerializableOspfPacket) {}
+
+    /// Visit an SCTP packet
+    fn visit_sctp(&mut self, _packet: &SerializableSctpPacket) {}
+
+    /// Visit a TCP packet
+    fn visit_tcp(&mut self, _packet: &SerializableTcpPacket) {}
+
+    /// Visit a UDP packet
+    fn visit_udp(&mut self, _packet: &SerializableUdpPacket) {}
+
+    /// Visit an HTTP request packet
+    fn visit_http_request(&mut self, _packet: &SerializableHttpRequestPacket) {}
+
+    /// Visit an HTTP response packet
+    fn visit_http_response(&mut self, _packet: &SerializableHttpResponsePacket) {}
+
+    /// Visit a TLS packet
+    fn visit_tls(&mut self, _packet: &SerializableTlsPacket) {}
+
+    /// Visit a DTLS packet
+    fn visit_dtls(&mut self, _packet: &SerializableDtlsPacket) {}
+
+    /// Visit a QUIC packet
+    fn visit_quic(&mut self, _packet: &SerializableQuicPacket) {}
+
+    /// Visit a DNS packet
+    fn visit_dns(&mut self, _packet: &SerializableDnsPacket) {}
+
+    /// Visit a Modbus packet
+    fn visit_modbus(&mut self, _packet: &SerializableModbusPacket) {}
+
+    /// Visit an SMTP packet
+    fn visit_smtp(&mut self, _packet: &SerializableSmtpPacket) {}
+
+    /// Visit a POP3 packet
+    fn visit_pop3(&mut self, _packet: &SerializablePop3Packet) {}
+
+    /// Visit an IMAP packet
+    fn visit_imap(&mut self, _packet: &SerializableImapPacket) {}
+
+    /// Visit an LDAP packet
+    fn visit_ldap(&mut self, _packet: &SerializableLdapPacket) {}
+
+    /// Visit a Kerberos packet
+    fn visit_kerberos(&mut self, _packet: &SerializableKerberosPacket) {}
+
+    /// Visit a STUN packet
+    fn visit_stun(&mut self, _packet: &SerializableStunPacket) {}
+
+    /// Visit a Telnet packet
+    fn visit_telnet(&mut self, _packet: &SerializableTelnetPacket) {}
+
+    /// Visit an NBNS packet
+    fn visit_nbns(&mut self, _packet: &SerializableNbnsPacket) {}
+
+    /// Visit a WireGuard packet
+    fn visit_wireguard(&mut self, _packet: &SerializableWireGuardPacket) {}
+
+    /// Visit a GTP-U packet
+    fn visit_gtp(&mut self, _packet: &SerializableGtpPacket) {}
+
+    /// Visit a Syslog packet
+    fn visit_syslog(&mut self, _packet: &SerializableSyslogPacket) {}
+
+    /// Visit a DHCPv6 packet
+    fn visit_dhcpv6(&mut self, _packet: &SerializableDhcpv6Packet) {}
+
+    /// Visit a packet which could not be parsed, with the reason
+    fn visit_malformed(&mut self, _reason: &str) {}
+
+    /// Visit a link-layer packet of unknown type
+    fn visit_unknown(&mut self, _packet: &SerializableUnknownPacket) {}
+}
+
+impl SerializablePacket {
+    /// Call the method of the visitor handling this representation
+    pub fn accept<V: Visitor>(&self, visitor: &mut V) {
+        match self {
+            SerializablePacket::EthernetPacket(packet) => visitor.visit_ethernet(packet),
+            SerializablePacket::PppoePacket(packet) => visitor.visit_pppoe(packet),
+            SerializablePacket::SllPacket(packet) => visitor.visit_sll(packet),
+            SerializablePacket::Dot11Packet(packet) => visitor.visit_dot11(packet),
+            SerializablePacket::ArpPacket(packet) => visitor.visit_arp(packet),
+            SerializablePacket::Ipv4Packet(packet) => visitor.visit_ipv4(packet),
+            SerializablePacket::Ipv6Packet(packet) => visitor.visit_ipv6(packet),
+            SerializablePacket::EchoReplyPacket(packet) => visitor.visit_echo_reply(packet),
+            SerializablePacket::EchoRequestPacket(packet) => visitor.visit_echo_request(packet),
+            SerializablePacket::IcmpPacket(packet) => visitor.visit_icmp(packet),
+            SerializablePacket::Icmpv6Packet(packet) => visitor.visit_icmpv6(packet),
+            SerializablePacket::IgmpPacket(packet) => visitor.visit_igmp(packet),
+            SerializablePacket::OspfPacket(packet) => visitor.visit_ospf(packet),
+            SerializablePacket::SctpPacket(packet) => visitor.visit_sctp(packet),
+            SerializablePacket::TcpPacket(packet) => visitor.visit_tcp(packet),
+            SerializablePacket::UdpPacket(packet) => visitor.visit_udp(packet),
+            SerializablePacket::HttpRequestPacket(packet) => visitor.visit_http_request(packet),
+            SerializablePacket::HttpResponsePacket(packet) => visitor.visit_http_response(packet),
+            SerializablePacket::TlsPacket(packet) => visitor.visit_tls(packet),
+            SerializablePacket::DtlsPacket(packet) => visitor.visit_dtls(packet),
+            SerializablePacket::QuicPacket(packet) => visitor.visit_quic(packet),
+            SerializablePacket::DnsPacket(packet) => visitor.visit_dns(packet),
+            SerializablePacket::ModbusPacket(packet) => visitor.visit_modbus(packet),
+            SerializablePacket::SmtpPacket(packet) => visitor.visit_smtp(packet),
+            SerializablePacket::Pop3Packet(packet) => visitor.visit_pop3(packet),
+            SerializablePacket::ImapPacket(packet) => visitor.visit_imap(packet),
+            SerializablePacket::LdapPacket(packet) => visitor.visit_ldap(packet),
+            SerializablePacket::KerberosPacket(packet) => visitor.visit_kerberos(packet),
+            SerializablePacket::StunPacket(packet) => visitor.visit_stun(packet),
+            SerializablePacket::TelnetPacket(packet) => visitor.visit_telnet(packet),
+            SerializablePacket::NbnsPacket(packet) => visitor.visit_nbns(packet),
+            SerializablePacket::WireGuardPacket(packet) => visitor.visit_wireguard(packet),
+            SerializablePacket::GtpPacket(packet) => visitor.visit_gtp(packet),
+            SerializablePacket::SyslogPacket(packet) => visitor.visit_syslog(packet),
+            SerializablePacket::Dhcpv6Packet(packet) => visitor.visit_dhcpv6(packet),
+            SerializablePacket::MalformedPacket(reason) => visitor.visit_malformed(reason),
+            SerializablePacket::UnknownPacket(packet) => visitor.visit_unknown(packet),
+        }
+    }
+}
+
+impl ParsedPacket {
+    /// Call the visitor with each populated layer, from the link layer to the application layer
+    pub fn accept<V: Visitor>(&self, visitor: &mut V) {
+        [
+            &self.link_layer_packet,
+            &self.network_layer_packet,
+            &self.transport_layer_packet,
+            &self.application_layer_packet,
+        ]
+        .into_iter()
+        .flatten()
+        .for_each(|layer| layer.accept(visitor));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pnet::packet::tcp::TcpFlags;
+
+    use super::Visitor;
+    use crate::serializable_packet::network::SerializableIpv4Packet;
+    use crate::serializable_packet::transport::{SerializableTcpPacket, SerializableUdpPacket};
+    use crate::serializable_packet::{ParsedPacket, SerializableEthernetPacket};
+    use crate::test_util::{self, CLIENT, SERVER};
+
+    /// Count the layers visited, by protocol
+    #[derive(Default)]
+    struct LayerCounter {
+        ethernet: usize,
+        ipv4: usize,
+        tcp: usize,
+        udp: usize,
+        malformed: usize,
+        destination_port: Option<u16>,
+    }
+
+    impl Visitor for LayerCounter {
+        fn visit_ethernet(&mut self, _packet: &SerializableEthernetPacket) {
+            self.ethernet += 1;
+        }
+
+        fn visit_ipv4(&mut self, _packet: &SerializableIpv4Packet) {
+            self.ipv4 += 1;
+        }
+
+        fn visit_tcp(&mut self, packet: &SerializableTcpPacket) {
+            self.tcp += 1;
+            self.destination_port = Some(packet.destination);
+        }
+
+        fn visit_udp(&mut self, _packet: &SerializableUdpPacket) {
+            self.udp += 1;
+        }
+
+        fn visit_malformed(&mut self, _reason: &str) {
+            self.malformed += 1;
+        }
+    }
+
+    #[test]
+    fn visit_tcp_ipv4_layers() {
+        let mut counter = LayerCounter::default();
+        build_test_tcp_packet().accept(&mut counter);
+
+        assert_eq!((counter.ethernet, counter.ipv4, counter.tcp), (1, 1, 1));
+        assert_eq!((counter.udp, counter.malformed), (0, 0));
+        assert_eq!(counter.destination_port, Some(9999));
+    }
+
+    ///////////////////// Utils
+
+    /// Build a TCP segment without payload from 10.10.10.10:4444 to 11.11.11.11:9999
+    fn build_test_tcp_packet() -> ParsedPacket {
+        test_util::build_test_tcp_packet((CLIENT, 4444), (SERVER, 9999), TcpFlags::SYN, 0, &[])
+    }
+}
@@ -0,0 +1,172 @@
+//! Linux cooked capture (SLL) parsing
+//!
+//! Captures on the "any" interface of Linux (LINKTYPE_LINUX_SLL) replace the Ethernet header of
+//! every frame by a 16-byte pseudo-header: packet type (direction), ARPHRD type of the interface,
+//! link-layer address of the sender, and the ethertype of the network-layer packet which follows
+
+use log::{debug, warn};
+use pnet::packet::ethernet::{EtherType, EtherTypes};
+use pnet::util::MacAddr;
+
+use crate::network::{handle_arp_packet, handle_ipv4_packet, handle_ipv6_packet};
+use crate::serializable_packet::{
+    sll_packet_type_to_string, ParsedPacket, SerializablePacket, SerializableSllPacket,
+};
+use crate::RAW_FRAME_RETENTION;
+
+pub(crate) const SLL_HEADER_LENGTH: usize = 16;
+const SLL_ADDRESS_MAX_LENGTH: usize = 8;
+
+/// SLL Packet Types
+#[allow(non_snake_case)]
+pub mod SllPacketTypes {
+    pub const HOST: u16 = 0;
+    pub const BROADCAST: u16 = 1;
+    pub const MULTICAST: u16 = 2;
+    pub const OTHER_HOST: u16 = 3;
+    pub const OUTGOING: u16 = 4;
+}
+
+/// Parse a Linux cooked capture frame obtaining the packet link-layer and network-layer
+/// representations
+pub fn parse_sll_frame(frame: &[u8], id: usize) -> ParsedPacket {
+    let mut parsed_packet = ParsedPacket::new(id);
+
+    if RAW_FRAME_RETENTION.with(|retention| retention.get()) {
+        parsed_packet.set_raw_frame(Some(frame.to_vec()));
+    }
+
+    let header = match frame.get(..SLL_HEADER_LENGTH) {
+        Some(header) => header,
+        None => {
+            warn!("Malformed SLL Packet: length: {}", frame.len());
+            parsed_packet.set_link_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed SLL Packet".to_string(),
+            )));
+            return parsed_packet;
+        }
+    };
+    let payload = &frame[SLL_HEADER_LENGTH..];
+
+    let read_u16 = |offset: usize| u16::from_be_bytes([header[offset], header[offset + 1]]);
+    let address_length = read_u16(4) as usize;
+    let link_layer_address = match address_length {
+        6 => Some(MacAddr::new(
+            header[6], header[7], header[8], header[9], header[10], header[11],
+        )),
+        _ => None,
+    };
+    let protocol = EtherType(read_u16(14));
+
+    parsed_packet.set_link_layer_packet(Some(SerializablePacket::SllPacket(
+        SerializableSllPacket {
+            packet_type: sll_packet_type_to_string(read_u16(0)),
+            arphrd_type: read_u16(2),
+            address_length: address_length.min(SLL_ADDRESS_MAX_LENGTH) as u16,
+            link_layer_address,
+            protocol: protocol.to_string(),
+            length: frame.len(),
+        },
+    )));
+
+    match protocol {
+        EtherTypes::Ipv4 => handle_ipv4_packet(payload, &mut parsed_packet),
+        EtherTypes::Ipv6 => handle_ipv6_packet(payload, &mut parsed_packet),
+        EtherTypes::Arp => handle_arp_packet(
+            payload,
+            link_layer_address.unwrap_or_else(MacAddr::zero),
+            MacAddr::zero(),
+            &mut parsed_packet,
+        ),
+        _ => debug!(
+            "Unknown SLL packet: protocol: {:?} length: {}",
+            protocol,
+            frame.len()
+        ),
+    }
+
+    parsed_packet
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::util::MacAddr;
+
+    use crate::pcap::tests::build_test_pcap_with_link_type;
+    use crate::serializable_packet::SerializablePacket;
+    use crate::test_util::{build_test_ipv4_packet, build_test_udp_datagram, CLIENT, SERVER};
+    use crate::{parse_pcap_record, parse_sll_frame, LinkTypes, PcapReader};
+
+    #[test]
+    fn sll_ipv4_packet() {
+        let pcap = build_test_pcap_with_link_type(
+            LinkTypes::LINUX_SLL,
+            &[(1_600_000_000, 0, build_test_sll_frame(0x0800))],
+        );
+        let mut reader = PcapReader::new(pcap.as_slice()).unwrap();
+        let record = reader.next_record().unwrap().unwrap();
+        assert_eq!(record.link_type, LinkTypes::LINUX_SLL);
+        let parsed_packet = parse_pcap_record(&record, 0);
+
+        match parsed_packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::SllPacket(sll_packet) => {
+                assert_eq!(sll_packet.packet_type, "Outgoing (4)");
+                assert_eq!(sll_packet.arphrd_type, 1);
+                assert_eq!(
+                    sll_packet.link_layer_address,
+                    Some(MacAddr::new(10, 10, 10, 10, 10, 10))
+                );
+                assert_eq!(sll_packet.protocol, "Ipv4");
+            }
+            _ => unreachable!(),
+        }
+        match parsed_packet.get_network_layer_packet().unwrap() {
+            SerializablePacket::Ipv4Packet(ipv4_packet) => {
+                assert_eq!(ipv4_packet.source, Ipv4Addr::new(10, 10, 10, 10));
+                assert_eq!(ipv4_packet.destination, Ipv4Addr::new(11, 11, 11, 11));
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(parsed_packet.protocol_stack(), vec!["SLL", "IPv4", "UDP"]);
+    }
+
+    #[test]
+    fn malformed_sll_packet() {
+        match parse_sll_frame(&build_test_sll_frame(0x0800)[..10], 0)
+            .get_link_layer_packet()
+            .unwrap()
+        {
+            SerializablePacket::MalformedPacket(str) => assert_eq!(str, "Malformed SLL Packet"),
+            _ => unreachable!(),
+        }
+
+        // Unknown protocol: the network layer is left empty
+        let parsed_packet = parse_sll_frame(&build_test_sll_frame(0x9999), 0);
+        assert!(parsed_packet.get_network_layer_packet().is_none());
+    }
+
+    ///////////////////// Utils
+
+    /// Build an outgoing SLL frame of an Ethernet interface, carrying a UDP datagram from
+    /// 10.10.10.10 to 11.11.11.11 when the protocol is IPv4
+    fn build_test_sll_frame(protocol: u16) -> Vec<u8> {
+        let udp_datagram = build_test_udp_datagram(4444, 9999, &[]);
+        let ip_packet = build_test_ipv4_packet(
+            CLIENT,
+            SERVER,
+            64,
+            IpNextHeaderProtocols::Udp,
+            &udp_datagram,
+        );
+
+        let mut frame = vec![0x00, 0x04, 0x00, 0x01, 0x00, 0x06];
+        frame.extend_from_slice(&[10, 10, 10, 10, 10, 10, 0, 0]);
+        frame.extend_from_slice(&protocol.to_be_bytes());
+        frame.extend_from_slice(&ip_packet);
+
+        frame
+    }
+}
@@ -0,0 +1,471 @@
+//! TCP relative sequence numbers
+//!
+//! The initial sequence number (ISN) of each direction of a connection is recorded from its SYN
+//! (or SYN-ACK); the following segments get their sequence number relative to it, and their
+//! acknowledgement number relative to the ISN of the peer, like Wireshark shows them. The
+//! segments of connections whose handshake was not captured keep their absolute numbers only.
+//!
+//! The next sequence number expected in each direction is also followed, the highest one seen:
+//! segments of at most a byte sent one byte before it are flagged as keep-alive probes, which do
+//! not advance it; the other segments starting before it resend data already seen and are
+//! flagged as retransmissions, those starting after it leave a gap and are flagged as out of
+//! order
+//!
+//! A connection is forgotten after its reset, after the segment acknowledging the FIN of both
+//! directions, or when it stays idle for longer than the timeout
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+use pnet::packet::tcp::TcpFlags;
+
+use crate::serializable_packet::transport::SerializableTcpPacket;
+use crate::serializable_packet::util::{get_dest_endpoint, get_source_endpoint};
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+/// Direction of a TCP connection: source endpoint, destination endpoint
+pub type TcpDirection = ((IpAddr, u16), (IpAddr, u16));
+
+/// Initial sequence numbers of the TCP connections, fed with every parsed packet in capture order
+#[derive(Debug)]
+pub struct TcpSeqTracker {
+    idle_timeout: Duration,
+    initial_sequences: HashMap<TcpDirection, u32>,
+    next_sequences: HashMap<TcpDirection, u32>,
+    /// Directions whose FIN was seen
+    finished: HashSet<TcpDirection>,
+    /// Timestamp of the last segment of each connection, keyed by its lower endpoint first
+    last_seen: HashMap<TcpDirection, SystemTime>,
+    /// Connections by the timestamp of their last segment, the least recent first
+    idle: BTreeSet<(SystemTime, TcpDirection)>,
+}
+
+impl TcpSeqTracker {
+    /// Build a tracker forgetting the connections idle for longer than `idle_timeout`
+    pub fn new(idle_timeout: Duration) -> Self {
+        TcpSeqTracker {
+            idle_timeout,
+            initial_sequences: HashMap::new(),
+            next_sequences: HashMap::new(),
+            finished: HashSet::new(),
+            last_seen: HashMap::new(),
+            idle: BTreeSet::new(),
+        }
+    }
+
+    /// Record the ISN of a SYN, set the relative numbers of a TCP segment when known and flag it
+    /// when it is a keep-alive probe, a retransmission or out of order
+    pub fn update(&mut self, packet: &mut ParsedPacket) {
+        let timestamp = packet.get_timestamp();
+        if let Some(timestamp) = timestamp {
+            self.expire(timestamp);
+        }
+
+        let source = get_source_endpoint(packet);
+        let dest = get_dest_endpoint(packet);
+
+        let tcp_packet = packet.layers_mut().find_map(|layer| match layer {
+            SerializablePacket::TcpPacket(tcp_packet) => Some(tcp_packet),
+            _ => None,
+        });
+        let (tcp_packet, source, dest) = match (tcp_packet, source, dest) {
+            (Some(tcp_packet), Some(source), Some(dest)) => (tcp_packet, source, dest),
+            _ => return,
+        };
+        let connection = match source <= dest {
+            true => (source, dest),
+            false => (dest, source),
+        };
+
+        // A new SYN starts a new connection, possibly reusing the endpoints of a previous one
+        if tcp_packet.flags & (TcpFlags::SYN | TcpFlags::ACK) as u16 == TcpFlags::SYN as u16 {
+            self.forget(connection);
+        }
+        if let Some(timestamp) = timestamp {
+            if let Some(last_seen) = self.last_seen.insert(connection, timestamp) {
+                self.idle.remove(&(last_seen, connection));
+            }
+            self.idle.insert((timestamp, connection));
+        }
+
+        self.annotate(tcp_packet, source, dest);
+
+        let closed = match tcp_packet.flags & (TcpFlags::FIN | TcpFlags::RST) as u16 {
+            0 => self.finished.contains(&(source, dest)) && self.finished.contains(&(dest, source)),
+            flags if flags & TcpFlags::RST as u16 != 0 => true,
+            _ => {
+                self.finished.insert((source, dest));
+                false
+            }
+        };
+        if closed {
+            self.forget(connection);
+        }
+    }
+
+    /// Set the relative numbers and the flags of a segment from `source` to `dest`, following
+    /// the sequence numbers of its direction
+    fn annotate(
+        &mut self,
+        tcp_packet: &mut SerializableTcpPacket,
+        source: (IpAddr, u16),
+        dest: (IpAddr, u16),
+    ) {
+        if tcp_packet.flags & TcpFlags::SYN as u16 != 0 {
+            self.initial_sequences
+                .insert((source, dest), tcp_packet.sequence);
+            if tcp_packet.flags & TcpFlags::ACK as u16 == 0 {
+                self.initial_sequences.remove(&(dest, source));
+            }
+        }
+
+        tcp_packet.relative_seq = self
+            .initial_sequences
+            .get(&(source, dest))
+            .map(|isn| tcp_packet.sequence.wrapping_sub(*isn));
+        tcp_packet.relative_ack = match tcp_packet.flags & TcpFlags::ACK as u16 {
+            0 => None,
+            _ => self
+                .initial_sequences
+                .get(&(dest, source))
+                .map(|isn| tcp_packet.acknowledgement.wrapping_sub(*isn)),
+        };
+
+        let control = (TcpFlags::SYN | TcpFlags::FIN | TcpFlags::RST) as u16;
+        let next_sequence = match tcp_packet.flags & TcpFlags::SYN as u16 {
+            0 => self.next_sequences.get(&(source, dest)).copied(),
+            _ => None,
+        };
+        tcp_packet.is_keep_alive = tcp_packet.flags & control == 0
+            && tcp_packet.length <= 1
+            && next_sequence == Some(tcp_packet.sequence.wrapping_add(1));
+        if tcp_packet.is_keep_alive {
+            return;
+        }
+
+        // SYN and FIN take a sequence number each
+        let consumed = tcp_packet.length as u32
+            + u32::from(tcp_packet.flags & TcpFlags::SYN as u16 != 0)
+            + u32::from(tcp_packet.flags & TcpFlags::FIN as u16 != 0);
+        let end = tcp_packet.sequence.wrapping_add(consumed);
+        match next_sequence {
+            Some(next_sequence) if tcp_packet.flags & TcpFlags::RST as u16 == 0 => {
+                let offset = sequence_offset(tcp_packet.sequence, next_sequence);
+                tcp_packet.is_retransmission = offset < 0 && consumed > 0;
+                tcp_packet.is_out_of_order = offset > 0;
+                if sequence_offset(end, next_sequence) > 0 {
+                    self.next_sequences.insert((source, dest), end);
+                }
+            }
+            _ => {
+                self.next_sequences.insert((source, dest), end);
+            }
+        }
+    }
+
+    /// Get the initial sequence number of a direction of a connection, when its SYN was seen
+    pub fn initial_sequence(&self, direction: &TcpDirection) -> Option<u32> {
+        self.initial_sequences.get(direction).copied()
+    }
+
+    /// Forget the connections idle for longer than the timeout at the given time
+    pub fn expire(&mut self, now: SystemTime) {
+        while let Some(&(last_seen, connection)) = self.idle.first() {
+            match now.duration_since(last_seen) {
+                Ok(elapsed) if elapsed > self.idle_timeout => self.forget(connection),
+                _ => break,
+            }
+        }
+    }
+
+    /// Forget both directions of a connection, given its lower endpoint first
+    fn forget(&mut self, connection: TcpDirection) {
+        let (lower, upper) = connection;
+        for direction in [(lower, upper), (upper, lower)] {
+            self.initial_sequences.remove(&direction);
+            self.next_sequences.remove(&direction);
+            self.finished.remove(&direction);
+        }
+        if let Some(last_seen) = self.last_seen.remove(&connection) {
+            self.idle.remove(&(last_seen, connection));
+        }
+    }
+}
+
+/// Get the signed distance from a sequence number to another, across the wrap-around
+fn sequence_offset(sequence: u32, reference: u32) -> i32 {
+    sequence.wrapping_sub(reference) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::packet::tcp::TcpFlags;
+
+    use super::TcpSeqTracker;
+    use crate::serializable_packet::transport::SerializableTcpPacket;
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+    use crate::test_util::{build_test_ipv4_frame, build_test_tcp_segment, CLIENT, SERVER};
+
+    const CLIENT_ISN: u32 = 0xfffffff0;
+    const SERVER_ISN: u32 = 3_000_000_000;
+    const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+    #[test]
+    fn handshake_then_data() {
+        let mut tracker = TcpSeqTracker::new(IDLE_TIMEOUT);
+        let mut packets = [
+            build_test_tcp_packet(false, TcpFlags::SYN, CLIENT_ISN, 0, 0),
+            build_test_tcp_packet(
+                true,
+                TcpFlags::SYN | TcpFlags::ACK,
+                SERVER_ISN,
+                CLIENT_ISN.wrapping_add(1),
+                0,
+            ),
+            build_test_tcp_packet(
+                false,
+                TcpFlags::ACK,
+                CLIENT_ISN.wrapping_add(1),
+                SERVER_ISN + 1,
+                0,
+            ),
+            build_test_tcp_packet(
+                false,
+                TcpFlags::ACK | TcpFlags::PSH,
+                CLIENT_ISN.wrapping_add(1),
+                SERVER_ISN + 1,
+                100,
+            ),
+            build_test_tcp_packet(
+                true,
+                TcpFlags::ACK,
+                SERVER_ISN + 1,
+                CLIENT_ISN.wrapping_add(101),
+                0,
+            ),
+        ];
+        for packet in packets.iter_mut() {
+            tracker.update(packet);
+        }
+
+        let relative = |packet: &ParsedPacket| {
+            let tcp_packet = tcp_packet(packet);
+            (tcp_packet.relative_seq, tcp_packet.relative_ack)
+        };
+        assert_eq!(relative(&packets[0]), (Some(0), None));
+        assert_eq!(relative(&packets[1]), (Some(0), Some(1)));
+        assert_eq!(relative(&packets[2]), (Some(1), Some(1)));
+        assert_eq!(relative(&packets[3]), (Some(1), Some(1)));
+        assert_eq!(relative(&packets[4]), (Some(1), Some(101)));
+        assert_eq!(tcp_packet(&packets[3]).sequence, CLIENT_ISN.wrapping_add(1));
+
+        assert_eq!(
+            tracker.initial_sequence(&(
+                (IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)), 4444),
+                (IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)), 5555)
+            )),
+            Some(CLIENT_ISN)
+        );
+    }
+
+    #[test]
+    fn handshake_not_captured() {
+        let mut tracker = TcpSeqTracker::new(IDLE_TIMEOUT);
+        let mut packet = build_test_tcp_packet(false, TcpFlags::ACK, 1234, 5678, 10);
+        tracker.update(&mut packet);
+
+        let tcp_packet = tcp_packet(&packet);
+        assert_eq!(
+            (tcp_packet.relative_seq, tcp_packet.relative_ack),
+            (None, None)
+        );
+        assert_eq!(tcp_packet.sequence, 1234);
+    }
+
+    #[test]
+    fn keep_alive_probe() {
+        let mut tracker = TcpSeqTracker::new(IDLE_TIMEOUT);
+        let mut packets = [
+            build_test_tcp_packet(false, TcpFlags::ACK | TcpFlags::PSH, 1000, 5000, 100),
+            build_test_tcp_packet(true, TcpFlags::ACK, 5000, 1100, 0),
+            build_test_tcp_packet(false, TcpFlags::ACK, 1099, 5000, 1),
+            build_test_tcp_packet(false, TcpFlags::ACK, 1099, 5000, 0),
+            build_test_tcp_packet(false, TcpFlags::ACK | TcpFlags::PSH, 1100, 5000, 10),
+        ];
+        for packet in packets.iter_mut() {
+            tracker.update(packet);
+        }
+
+        let keep_alives: Vec<bool> = packets
+            .iter()
+            .map(|packet| tcp_packet(packet).is_keep_alive)
+            .collect();
+        assert_eq!(keep_alives, [false, false, true, true, false]);
+    }
+
+    #[test]
+    fn retransmitted_segment() {
+        let mut tracker = TcpSeqTracker::new(IDLE_TIMEOUT);
+        let mut packets = [
+            build_test_tcp_packet(false, TcpFlags::ACK | TcpFlags::PSH, 1000, 5000, 100),
+            build_test_tcp_packet(false, TcpFlags::ACK | TcpFlags::PSH, 1100, 5000, 100),
+            build_test_tcp_packet(false, TcpFlags::ACK | TcpFlags::PSH, 1100, 5000, 100),
+            build_test_tcp_packet(true, TcpFlags::ACK, 5000, 1200, 0),
+            build_test_tcp_packet(false, TcpFlags::ACK | TcpFlags::PSH, 1200, 5000, 100),
+        ];
+        for packet in packets.iter_mut() {
+            tracker.update(packet);
+        }
+
+        assert_eq!(
+            flags(&packets),
+            [
+                (false, false),
+                (false, false),
+                (true, false),
+                (false, false),
+                (false, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn out_of_order_segment() {
+        let mut tracker = TcpSeqTracker::new(IDLE_TIMEOUT);
+        let mut packets = [
+            build_test_tcp_packet(false, TcpFlags::ACK | TcpFlags::PSH, 1000, 5000, 100),
+            // The segment from 1100 to 1200 is not captured yet
+            build_test_tcp_packet(false, TcpFlags::ACK | TcpFlags::PSH, 1200, 5000, 100),
+            build_test_tcp_packet(false, TcpFlags::ACK | TcpFlags::PSH, 1300, 5000, 100),
+            // Starting before the next expected sequence number, the missing segment captured
+            // late cannot be told apart from a retransmission
+            build_test_tcp_packet(false, TcpFlags::ACK | TcpFlags::PSH, 1100, 5000, 100),
+        ];
+        for packet in packets.iter_mut() {
+            tracker.update(packet);
+        }
+
+        assert_eq!(
+            flags(&packets),
+            [(false, false), (false, true), (false, false), (true, false)]
+        );
+    }
+
+    #[test]
+    fn closed_connection_forgotten() {
+        let mut tracker = TcpSeqTracker::new(IDLE_TIMEOUT);
+        let mut packets = [
+            build_test_tcp_packet(false, TcpFlags::SYN, 1000, 0, 0),
+            build_test_tcp_packet(true, TcpFlags::SYN | TcpFlags::ACK, 5000, 1001, 0),
+            build_test_tcp_packet(false, TcpFlags::FIN | TcpFlags::ACK, 1001, 5001, 0),
+            build_test_tcp_packet(true, TcpFlags::FIN | TcpFlags::ACK, 5001, 1002, 0),
+            build_test_tcp_packet(false, TcpFlags::ACK, 1002, 5002, 0),
+            build_test_tcp_packet(false, TcpFlags::ACK, 1002, 5002, 0),
+        ];
+        for packet in packets.iter_mut() {
+            tracker.update(packet);
+        }
+
+        // The last ACK is still numbered, the segments after it are not
+        assert_eq!(tcp_packet(&packets[4]).relative_seq, Some(2));
+        assert_eq!(tcp_packet(&packets[5]).relative_seq, None);
+    }
+
+    #[test]
+    fn reset_connection_forgotten() {
+        let mut tracker = TcpSeqTracker::new(IDLE_TIMEOUT);
+        let mut packets = [
+            build_test_tcp_packet(false, TcpFlags::SYN, 1000, 0, 0),
+            build_test_tcp_packet(true, TcpFlags::SYN | TcpFlags::ACK, 5000, 1001, 0),
+            build_test_tcp_packet(true, TcpFlags::RST | TcpFlags::ACK, 5001, 1001, 0),
+            build_test_tcp_packet(false, TcpFlags::ACK, 1001, 5001, 10),
+        ];
+        for packet in packets.iter_mut() {
+            tracker.update(packet);
+        }
+
+        assert_eq!(tcp_packet(&packets[2]).relative_seq, Some(1));
+        assert_eq!(tcp_packet(&packets[3]).relative_seq, None);
+        assert!(!tcp_packet(&packets[3]).is_retransmission);
+    }
+
+    #[test]
+    fn idle_connection_forgotten() {
+        let mut tracker = TcpSeqTracker::new(IDLE_TIMEOUT);
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let mut packets = [
+            build_test_tcp_packet(false, TcpFlags::SYN, 1000, 0, 0),
+            build_test_tcp_packet(true, TcpFlags::SYN | TcpFlags::ACK, 5000, 1001, 0),
+            build_test_tcp_packet(false, TcpFlags::ACK, 1001, 5001, 10),
+            build_test_tcp_packet(false, TcpFlags::ACK, 1011, 5001, 10),
+        ];
+        let elapsed = [
+            0,
+            1,
+            1 + IDLE_TIMEOUT.as_secs(),
+            2 + 2 * IDLE_TIMEOUT.as_secs(),
+        ];
+        for (packet, elapsed) in packets.iter_mut().zip(elapsed) {
+            packet.set_timestamp(Some(start + Duration::from_secs(elapsed)));
+            tracker.update(packet);
+        }
+
+        assert_eq!(tcp_packet(&packets[2]).relative_seq, Some(1));
+        assert_eq!(tcp_packet(&packets[3]).relative_seq, None);
+    }
+
+    ///////////////////// Utils
+
+    /// Get the retransmission and out-of-order flags of the segments
+    fn flags(packets: &[ParsedPacket]) -> Vec<(bool, bool)> {
+        packets
+            .iter()
+            .map(|packet| {
+                let tcp_packet = tcp_packet(packet);
+                (tcp_packet.is_retransmission, tcp_packet.is_out_of_order)
+            })
+            .collect()
+    }
+
+    fn tcp_packet(packet: &ParsedPacket) -> &SerializableTcpPacket {
+        match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Build a segment of the connection between 10.10.10.10:4444 and 11.11.11.11:5555
+    fn build_test_tcp_packet(
+        from_server: bool,
+        flags: u8,
+        sequence: u32,
+        acknowledgement: u32,
+        payload_length: usize,
+    ) -> ParsedPacket {
+        let (source, destination, source_port, destination_port) = match from_server {
+            true => (SERVER, CLIENT, 5555, 4444),
+            false => (CLIENT, SERVER, 4444, 5555),
+        };
+
+        let payload = vec![0u8; payload_length];
+        let tcp_segment = build_test_tcp_segment(
+            source_port,
+            destination_port,
+            flags,
+            sequence,
+            acknowledgement,
+            &payload,
+        );
+        build_test_ipv4_frame(
+            source,
+            destination,
+            IpNextHeaderProtocols::Tcp,
+            &tcp_segment,
+        )
+    }
+}
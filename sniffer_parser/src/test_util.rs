@@ -0,0 +1,190 @@
+//! Builders of the packets parsed by the tests
+//!
+//! Frames are sent from 10:10:10:10:10:10 to 11:11:11:11:11:11, and built layer by layer: each
+//! builder gets the bytes of the layer it carries, so that a test only spells out the fields it
+//! checks
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::SystemTime;
+
+use pnet::packet::arp::{ArpHardwareTypes, ArpOperation, MutableArpPacket};
+use pnet::packet::ethernet::{EtherType, EtherTypes, EthernetPacket, MutableEthernetPacket};
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::ipv4::MutableIpv4Packet;
+use pnet::packet::ipv6::MutableIpv6Packet;
+use pnet::packet::tcp::MutableTcpPacket;
+use pnet::packet::udp::MutableUdpPacket;
+use pnet::util::MacAddr;
+
+use crate::parse_ethernet_frame;
+use crate::serializable_packet::ParsedPacket;
+
+/// Address of the host opening the connections
+pub(crate) const CLIENT: Ipv4Addr = Ipv4Addr::new(10, 10, 10, 10);
+/// Address of the host answering them
+pub(crate) const SERVER: Ipv4Addr = Ipv4Addr::new(11, 11, 11, 11);
+
+/// Build an Ethernet frame carrying a payload of the given EtherType
+pub(crate) fn build_test_ethernet_frame(ethertype: EtherType, payload: &[u8]) -> Vec<u8> {
+    let mut ethernet_buffer = vec![0u8; 14 + payload.len()];
+    let mut ethernet_packet = MutableEthernetPacket::new(&mut ethernet_buffer).unwrap();
+    ethernet_packet.set_destination(MacAddr::new(11, 11, 11, 11, 11, 11));
+    ethernet_packet.set_source(MacAddr::new(10, 10, 10, 10, 10, 10));
+    ethernet_packet.set_ethertype(ethertype);
+    ethernet_packet.set_payload(payload);
+    ethernet_buffer
+}
+
+/// Build an IPv4 packet without options
+pub(crate) fn build_test_ipv4_packet(
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+    ttl: u8,
+    protocol: IpNextHeaderProtocol,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut ip_buffer = vec![0u8; 20 + payload.len()];
+    let mut ipv4_packet = MutableIpv4Packet::new(&mut ip_buffer).unwrap();
+    ipv4_packet.set_version(4);
+    ipv4_packet.set_header_length(5);
+    ipv4_packet.set_total_length((20 + payload.len()) as u16);
+    ipv4_packet.set_ttl(ttl);
+    ipv4_packet.set_next_level_protocol(protocol);
+    ipv4_packet.set_source(source);
+    ipv4_packet.set_destination(destination);
+    ipv4_packet.set_payload(payload);
+    ip_buffer
+}
+
+/// Build an IPv6 packet with a hop limit of 64
+pub(crate) fn build_test_ipv6_packet(
+    source: Ipv6Addr,
+    destination: Ipv6Addr,
+    flow_label: u32,
+    next_header: IpNextHeaderProtocol,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut ip_buffer = vec![0u8; 40 + payload.len()];
+    let mut ipv6_packet = MutableIpv6Packet::new(&mut ip_buffer).unwrap();
+    ipv6_packet.set_version(6);
+    ipv6_packet.set_flow_label(flow_label);
+    ipv6_packet.set_payload_length(payload.len() as u16);
+    ipv6_packet.set_next_header(next_header);
+    ipv6_packet.set_hop_limit(64);
+    ipv6_packet.set_source(source);
+    ipv6_packet.set_destination(destination);
+    ipv6_packet.set_payload(payload);
+    ip_buffer
+}
+
+/// Build a UDP datagram without checksum
+pub(crate) fn build_test_udp_datagram(
+    source_port: u16,
+    destination_port: u16,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut udp_buffer = vec![0u8; 8 + payload.len()];
+    let mut udp_packet = MutableUdpPacket::new(&mut udp_buffer).unwrap();
+    udp_packet.set_source(source_port);
+    udp_packet.set_destination(destination_port);
+    udp_packet.set_length((8 + payload.len()) as u16);
+    udp_packet.set_payload(payload);
+    udp_buffer
+}
+
+/// Build a TCP segment without options nor checksum, with a window of 1024 bytes
+pub(crate) fn build_test_tcp_segment(
+    source_port: u16,
+    destination_port: u16,
+    flags: u8,
+    sequence: u32,
+    acknowledgement: u32,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut tcp_buffer = vec![0u8; 20 + payload.len()];
+    let mut tcp_packet = MutableTcpPacket::new(&mut tcp_buffer).unwrap();
+    tcp_packet.set_source(source_port);
+    tcp_packet.set_destination(destination_port);
+    tcp_packet.set_sequence(sequence);
+    tcp_packet.set_acknowledgement(acknowledgement);
+    tcp_packet.set_data_offset(5);
+    tcp_packet.set_flags(flags);
+    tcp_packet.set_window(1024);
+    tcp_packet.set_payload(payload);
+    tcp_buffer
+}
+
+/// Build an ARP packet mapping the IPv4 addresses of the sender and the target to their MAC
+/// addresses
+pub(crate) fn build_test_arp_packet(
+    operation: ArpOperation,
+    sender: (MacAddr, Ipv4Addr),
+    target: (MacAddr, Ipv4Addr),
+) -> Vec<u8> {
+    let mut arp_buffer = vec![0u8; 28];
+    let mut arp_packet = MutableArpPacket::new(&mut arp_buffer).unwrap();
+    arp_packet.set_hardware_type(ArpHardwareTypes::Ethernet);
+    arp_packet.set_protocol_type(EtherTypes::Ipv4);
+    arp_packet.set_hw_addr_len(6);
+    arp_packet.set_proto_addr_len(4);
+    arp_packet.set_operation(operation);
+    arp_packet.set_sender_hw_addr(sender.0);
+    arp_packet.set_sender_proto_addr(sender.1);
+    arp_packet.set_target_hw_addr(target.0);
+    arp_packet.set_target_proto_addr(target.1);
+    arp_buffer
+}
+
+/// Parse an Ethernet frame carrying an IPv4 packet with a TTL of 64
+pub(crate) fn build_test_ipv4_frame(
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+    protocol: IpNextHeaderProtocol,
+    payload: &[u8],
+) -> ParsedPacket {
+    let ip_packet = build_test_ipv4_packet(source, destination, 64, protocol, payload);
+    parse_test_frame(&build_test_ethernet_frame(EtherTypes::Ipv4, &ip_packet), 0)
+}
+
+/// Parse a UDP datagram between two IPv4 endpoints
+pub(crate) fn build_test_udp_packet(
+    source: (Ipv4Addr, u16),
+    destination: (Ipv4Addr, u16),
+    payload: &[u8],
+) -> ParsedPacket {
+    let udp_datagram = build_test_udp_datagram(source.1, destination.1, payload);
+    build_test_ipv4_frame(
+        source.0,
+        destination.0,
+        IpNextHeaderProtocols::Udp,
+        &udp_datagram,
+    )
+}
+
+/// Parse a TCP segment between two IPv4 endpoints, acknowledging nothing
+pub(crate) fn build_test_tcp_packet(
+    source: (Ipv4Addr, u16),
+    destination: (Ipv4Addr, u16),
+    flags: u8,
+    sequence: u32,
+    payload: &[u8],
+) -> ParsedPacket {
+    let tcp_segment = build_test_tcp_segment(source.1, destination.1, flags, sequence, 0, payload);
+    build_test_ipv4_frame(
+        source.0,
+        destination.0,
+        IpNextHeaderProtocols::Tcp,
+        &tcp_segment,
+    )
+}
+
+/// Parse an Ethernet frame as the packet `id`
+pub(crate) fn parse_test_frame(frame: &[u8], id: usize) -> ParsedPacket {
+    parse_ethernet_frame(&EthernetPacket::new(frame).unwrap(), id)
+}
+
+/// Set the capture time of a parsed packet
+pub(crate) fn timestamped(mut packet: ParsedPacket, timestamp: SystemTime) -> ParsedPacket {
+    packet.set_timestamp(Some(timestamp));
+    packet
+}
@@ -0,0 +1,222 @@
+//! Type-length-value parsing
+//!
+//! Many protocols carry a sequence of elements made of a type, the length of the value and the
+//! value itself: 802.11 information elements, STUN attributes, pcapng options... The type and
+//! length fields are 1, 2 or 4 bytes long in either byte order, and the values may be padded to a
+//! multiple of a few bytes. Elements are read with bounds checking: a truncated element is an
+//! error, never a panic
+//!
+//! The TCP and DHCPv4 options do not follow this layout: their padding and end options are a
+//! single type byte without length, and the length of the TCP options counts the type and length
+//! bytes. The TCP options are kept as raw bytes, and DHCPv4 is not parsed
+
+use std::fmt;
+
+/// Byte order of the type and length fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+/// Type-length-value element, borrowing its value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tlv<'a> {
+    pub tlv_type: u32,
+    pub value: &'a [u8],
+    /// Offset of the element in the parsed bytes
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlvError {
+    /// Type or length field of 1, 2 or 4 bytes expected
+    InvalidFieldSize(usize),
+    /// Type and length of the element at this offset cut by the end of the bytes
+    TruncatedHeader(usize),
+    /// Value of the element at this offset longer than the bytes left
+    TruncatedValue(usize),
+}
+
+impl fmt::Display for TlvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlvError::InvalidFieldSize(size) => write!(f, "invalid TLV field size: {}", size),
+            TlvError::TruncatedHeader(offset) => {
+                write!(f, "truncated TLV header at offset {}", offset)
+            }
+            TlvError::TruncatedValue(offset) => {
+                write!(f, "truncated TLV value at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TlvError {}
+
+/// Iterator over the elements of a TLV sequence, ending after the first error
+#[derive(Debug, Clone)]
+pub struct TlvParser<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    type_size: usize,
+    len_size: usize,
+    endianness: Endianness,
+    alignment: usize,
+    failed: bool,
+}
+
+impl<'a> TlvParser<'a> {
+    /// Build a parser of elements with a type of `type_size` bytes and a length of `len_size`
+    /// bytes, the length counting the bytes of the value only
+    pub fn new(bytes: &'a [u8], type_size: usize, len_size: usize, endianness: Endianness) -> Self {
+        TlvParser {
+            bytes,
+            offset: 0,
+            type_size,
+            len_size,
+            endianness,
+            alignment: 1,
+            failed: false,
+        }
+    }
+
+    /// Skip the padding of the values to a multiple of `alignment` bytes; the padding of the last
+    /// value may be missing
+    pub fn aligned(mut self, alignment: usize) -> Self {
+        self.alignment = alignment.max(1);
+        self
+    }
+
+    fn read_field(&self, field: &[u8]) -> u32 {
+        let shift_in = |value: u32, byte: &u8| value << 8 | *byte as u32;
+        match self.endianness {
+            Endianness::Big => field.iter().fold(0, shift_in),
+            Endianness::Little => field.iter().rev().fold(0, shift_in),
+        }
+    }
+
+    fn parse_next(&mut self) -> Result<Tlv<'a>, TlvError> {
+        for size in [self.type_size, self.len_size] {
+            if !matches!(size, 1 | 2 | 4) {
+                return Err(TlvError::InvalidFieldSize(size));
+            }
+        }
+
+        let offset = self.offset;
+        let header_length = self.type_size + self.len_size;
+        let header = self
+            .bytes
+            .get(offset..offset + header_length)
+            .ok_or(TlvError::TruncatedHeader(offset))?;
+        let tlv_type = self.read_field(&header[..self.type_size]);
+        let length = self.read_field(&header[self.type_size..]) as usize;
+
+        let start = offset + header_length;
+        let value = start
+            .checked_add(length)
+            .and_then(|end| self.bytes.get(start..end))
+            .ok_or(TlvError::TruncatedValue(offset))?;
+
+        self.offset = (start + length.next_multiple_of(self.alignment)).min(self.bytes.len());
+        Ok(Tlv {
+            tlv_type,
+            value,
+            offset,
+        })
+    }
+}
+
+impl<'a> Iterator for TlvParser<'a> {
+    type Item = Result<Tlv<'a>, TlvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.offset >= self.bytes.len() {
+            return None;
+        }
+
+        let tlv = self.parse_next();
+        self.failed = tlv.is_err();
+        Some(tlv)
+    }
+}
+
+/// Parse every element of a TLV sequence, with a type of `type_size` bytes and a length of
+/// `len_size` bytes counting the bytes of the value only
+pub fn parse_tlvs(
+    bytes: &[u8],
+    type_size: usize,
+    len_size: usize,
+    endianness: Endianness,
+) -> Result<Vec<Tlv<'_>>, TlvError> {
+    TlvParser::new(bytes, type_size, len_size, endianness).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_tlvs, Endianness, Tlv, TlvError, TlvParser};
+
+    #[test]
+    fn well_formed_tlvs() {
+        let bytes = [0x00, 0x03, b'a', b'b', b'c', 0xdd, 0x00, 0x01, 0x01, 0xff];
+        assert_eq!(
+            parse_tlvs(&bytes, 1, 1, Endianness::Big),
+            Ok(vec![
+                Tlv {
+                    tlv_type: 0,
+                    value: b"abc",
+                    offset: 0,
+                },
+                Tlv {
+                    tlv_type: 0xdd,
+                    value: &[],
+                    offset: 5,
+                },
+                Tlv {
+                    tlv_type: 1,
+                    value: &[0xff],
+                    offset: 7,
+                },
+            ])
+        );
+
+        // 2-byte little-endian fields, values padded to 4 bytes
+        let bytes = [
+            0x09, 0x00, 0x01, 0x00, 0x06, 0, 0, 0, 0x02, 0x01, 0x04, 0x00, 1, 2, 3, 4,
+        ];
+        let tlvs: Vec<_> = TlvParser::new(&bytes, 2, 2, Endianness::Little)
+            .aligned(4)
+            .map(|tlv| tlv.map(|tlv| (tlv.tlv_type, tlv.value)))
+            .collect();
+        assert_eq!(
+            tlvs,
+            [Ok((9, &[0x06][..])), Ok((0x0102, &[1, 2, 3, 4][..]))]
+        );
+    }
+
+    #[test]
+    fn truncated_tlvs() {
+        assert_eq!(
+            parse_tlvs(&[0x01, 0x01, 0xff, 0x02], 1, 1, Endianness::Big),
+            Err(TlvError::TruncatedHeader(3))
+        );
+        assert_eq!(
+            parse_tlvs(&[0x01, 0x01, 0xff, 0x02, 0x05, 0xaa], 1, 1, Endianness::Big),
+            Err(TlvError::TruncatedValue(3))
+        );
+        assert_eq!(
+            parse_tlvs(&[0x00, 0x01, 0xff, 0xff, 0xaa], 2, 2, Endianness::Big),
+            Err(TlvError::TruncatedValue(0))
+        );
+        assert_eq!(
+            parse_tlvs(&[0x01, 0x00, 0x00], 1, 3, Endianness::Big),
+            Err(TlvError::InvalidFieldSize(3))
+        );
+
+        // The elements before the truncated one are read, then the parsing ends
+        let mut parser = TlvParser::new(&[0x01, 0x00, 0x02, 0x09], 1, 1, Endianness::Big);
+        assert!(matches!(parser.next(), Some(Ok(Tlv { tlv_type: 1, .. }))));
+        assert_eq!(parser.next(), Some(Err(TlvError::TruncatedValue(2))));
+        assert_eq!(parser.next(), None);
+    }
+}
@@ -0,0 +1,221 @@
+//! Top talkers statistics
+//!
+//! Parsed packets are aggregated per source and per destination IP address, and the addresses
+//! exchanging the most bytes are ranked, with a bar proportional to their bytes. Bytes are
+//! counted from the headers, like the protocol hierarchy. The packets without an IP layer (e.g.
+//! ARP) are not accounted
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::serializable_packet::util::{
+    contains_ipv4, contains_ipv6, get_dest_ip, get_frame_length, get_source_ip,
+};
+use crate::serializable_packet::ParsedPacket;
+
+/// Width of the bar of the address with the most bytes
+const BAR_WIDTH: usize = 40;
+
+/// IP address with the packets sent or received by it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Talker {
+    pub address: String,
+    pub packets: usize,
+    pub bytes: usize,
+}
+
+/// Packets and bytes per source and per destination IP address
+#[derive(Debug)]
+pub struct TopTalkers {
+    /// Number of addresses reported in each direction
+    top: usize,
+    sources: HashMap<String, Talker>,
+    destinations: HashMap<String, Talker>,
+}
+
+impl TopTalkers {
+    /// Build an aggregator reporting the `top` addresses of each direction
+    pub fn new(top: usize) -> Self {
+        TopTalkers {
+            top,
+            sources: HashMap::new(),
+            destinations: HashMap::new(),
+        }
+    }
+
+    /// Account a packet to its source and destination IP addresses
+    pub fn add(&mut self, packet: &ParsedPacket) {
+        if !contains_ipv4(packet) && !contains_ipv6(packet) {
+            return;
+        }
+
+        let bytes = get_frame_length(packet);
+        if let Some(source) = get_source_ip(packet) {
+            account(&mut self.sources, source, bytes);
+        }
+        if let Some(destination) = get_dest_ip(packet) {
+            account(&mut self.destinations, destination, bytes);
+        }
+    }
+
+    /// Get the sources sending the most bytes, the most packets breaking ties
+    pub fn top_sources(&self) -> Vec<Talker> {
+        ranked(&self.sources, self.top)
+    }
+
+    /// Get the destinations receiving the most bytes, the most packets breaking ties
+    pub fn top_destinations(&self) -> Vec<Talker> {
+        ranked(&self.destinations, self.top)
+    }
+}
+
+impl fmt::Display for TopTalkers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Top Sources:")?;
+        fmt_talkers(f, &self.top_sources())?;
+        writeln!(f, "Top Destinations:")?;
+        fmt_talkers(f, &self.top_destinations())
+    }
+}
+
+fn account(talkers: &mut HashMap<String, Talker>, address: String, bytes: usize) {
+    let talker = talkers.entry(address).or_insert_with_key(|address| Talker {
+        address: address.clone(),
+        packets: 0,
+        bytes: 0,
+    });
+    talker.packets += 1;
+    talker.bytes += bytes;
+}
+
+/// Get the `top` talkers with the most bytes, the address ordering the exact ties
+fn ranked(talkers: &HashMap<String, Talker>, top: usize) -> Vec<Talker> {
+    let mut ranking: Vec<_> = talkers.values().cloned().collect();
+    ranking.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then(b.packets.cmp(&a.packets))
+            .then(a.address.cmp(&b.address))
+    });
+    ranking.truncate(top);
+    ranking
+}
+
+/// Write a line per talker, its bar scaled to the bytes of the first one
+fn fmt_talkers(f: &mut fmt::Formatter<'_>, talkers: &[Talker]) -> fmt::Result {
+    let max_bytes = talkers.first().map_or(0, |talker| talker.bytes);
+    for talker in talkers {
+        let bar = match max_bytes {
+            0 => 0,
+            max_bytes => (BAR_WIDTH * talker.bytes).div_ceil(max_bytes),
+        };
+        writeln!(
+            f,
+            "  {:<39} packets: {:>8}  bytes: {:>10}  {}",
+            talker.address,
+            talker.packets,
+            talker.bytes,
+            "#".repeat(bar),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use pnet::packet::arp::ArpOperations;
+    use pnet::packet::ethernet::EtherTypes;
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::util::MacAddr;
+
+    use super::{Talker, TopTalkers};
+    use crate::serializable_packet::ParsedPacket;
+    use crate::test_util::{
+        self, build_test_ethernet_frame, build_test_ipv4_frame, parse_test_frame,
+    };
+
+    #[test]
+    fn ranking_by_bytes() {
+        let mut top_talkers = TopTalkers::new(3);
+        for _ in 0..3 {
+            top_talkers.add(&build_test_ip_packet(Ipv4Addr::new(10, 0, 0, 1), 100));
+        }
+        top_talkers.add(&build_test_ip_packet(Ipv4Addr::new(10, 0, 0, 2), 1000));
+        for _ in 0..5 {
+            top_talkers.add(&build_test_ip_packet(Ipv4Addr::new(10, 0, 0, 3), 20));
+        }
+        top_talkers.add(&build_test_arp_packet());
+
+        assert_eq!(
+            top_talkers.top_sources(),
+            vec![
+                Talker {
+                    address: "10.0.0.2".to_owned(),
+                    packets: 1,
+                    bytes: 14 + 1000,
+                },
+                Talker {
+                    address: "10.0.0.1".to_owned(),
+                    packets: 3,
+                    bytes: 3 * (14 + 100),
+                },
+                Talker {
+                    address: "10.0.0.3".to_owned(),
+                    packets: 5,
+                    bytes: 5 * (14 + 20),
+                },
+            ]
+        );
+        assert_eq!(
+            top_talkers.top_destinations(),
+            vec![Talker {
+                address: "192.168.1.1".to_owned(),
+                packets: 9,
+                bytes: 9 * 14 + 3 * 100 + 1000 + 5 * 20,
+            }]
+        );
+    }
+
+    #[test]
+    fn top_talkers_display() {
+        let mut top_talkers = TopTalkers::new(5);
+        top_talkers.add(&build_test_ip_packet(Ipv4Addr::new(10, 0, 0, 1), 66));
+        top_talkers.add(&build_test_ip_packet(Ipv4Addr::new(10, 0, 0, 2), 26));
+
+        let display = top_talkers.to_string();
+        let lines: Vec<_> = display.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "Top Sources:");
+        assert!(lines[1].starts_with("  10.0.0.1 "));
+        assert!(lines[1].ends_with(&format!("bytes:         80  {}", "#".repeat(40))));
+        assert!(lines[2].ends_with(&format!("bytes:         40  {}", "#".repeat(20))));
+        assert_eq!(lines[3], "Top Destinations:");
+    }
+
+    ///////////////////// Utils
+
+    /// Build an IPv4 packet of `length` bytes to 192.168.1.1, carrying no transport-layer packet
+    fn build_test_ip_packet(source: Ipv4Addr, length: usize) -> ParsedPacket {
+        build_test_ipv4_frame(
+            source,
+            Ipv4Addr::new(192, 168, 1, 1),
+            IpNextHeaderProtocols::Reserved,
+            &vec![0u8; length - 20],
+        )
+    }
+
+    fn build_test_arp_packet() -> ParsedPacket {
+        let arp_packet = test_util::build_test_arp_packet(
+            ArpOperations::Request,
+            (
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                Ipv4Addr::new(10, 0, 0, 9),
+            ),
+            (MacAddr::zero(), Ipv4Addr::new(192, 168, 1, 1)),
+        );
+        parse_test_frame(&build_test_ethernet_frame(EtherTypes::Arp, &arp_packet), 0)
+    }
+}
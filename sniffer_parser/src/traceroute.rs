@@ -0,0 +1,409 @@
+//! Traceroute path reconstruction
+//!
+//! The UDP, TCP and ICMP (ICMPv6) Echo request packets sent with a TTL (hop limit) of at most
+//! `MAX_PROBE_TTL` are recorded as probes with their capture timestamp; UDP probes are keyed by
+//! endpoints, TCP probes also by sequence number, since traceroute may send them all from the same
+//! port, and Echo probes by identifier and sequence number. A Time Exceeded ICMP or ICMPv6 message
+//! embedding the header of a probe reveals the router at the TTL of the probe, along with the round
+//! trip time. Probes left unanswered for longer than the timeout are forgotten, in capture order
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+use pnet::packet::icmp::IcmpTypes;
+use pnet::packet::icmpv6::Icmpv6Types;
+
+use crate::serializable_packet::transport::{
+    icmp_type_to_string, icmpv6_type_to_string, SerializableEmbeddedPacket,
+};
+use crate::serializable_packet::util::{get_dest_endpoint, get_ip_addresses, get_source_endpoint};
+use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+/// Highest TTL of the recorded probes, the default maximum number of hops of traceroute
+pub const MAX_PROBE_TTL: u8 = 30;
+
+/// Identifier of a probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ProbeKey {
+    /// Source and destination endpoints
+    Udp((IpAddr, u16), (IpAddr, u16)),
+    /// Source and destination endpoints, and sequence number
+    Tcp((IpAddr, u16), (IpAddr, u16), u32),
+    /// Source and destination addresses, identifier and sequence number
+    Echo(IpAddr, IpAddr, u16, u16),
+}
+
+impl ProbeKey {
+    /// Get the key of the probe embedded in a Time Exceeded message
+    fn embedded(embedded: &SerializableEmbeddedPacket) -> Option<Self> {
+        let source = embedded.source;
+        let destination = embedded.destination;
+        if let (Some(identifier), Some(sequence)) =
+            (embedded.echo_identifier, embedded.echo_sequence)
+        {
+            return Some(ProbeKey::Echo(source, destination, identifier, sequence));
+        }
+
+        let source = (source, embedded.source_port?);
+        let destination = (destination, embedded.destination_port?);
+        Some(match embedded.tcp_sequence {
+            Some(sequence) => ProbeKey::Tcp(source, destination, sequence),
+            None => ProbeKey::Udp(source, destination),
+        })
+    }
+
+    /// Get the key of a packet which could be a probe
+    fn probe(packet: &ParsedPacket) -> Option<Self> {
+        let (source, destination) = get_ip_addresses(packet)?;
+
+        match packet.get_transport_layer_packet()? {
+            SerializablePacket::TcpPacket(tcp_packet) => Some(ProbeKey::Tcp(
+                get_source_endpoint(packet)?,
+                get_dest_endpoint(packet)?,
+                tcp_packet.sequence,
+            )),
+            SerializablePacket::UdpPacket(_) => Some(ProbeKey::Udp(
+                get_source_endpoint(packet)?,
+                get_dest_endpoint(packet)?,
+            )),
+            SerializablePacket::EchoRequestPacket(echo_request_packet) => Some(ProbeKey::Echo(
+                source,
+                destination,
+                echo_request_packet.identifier,
+                echo_request_packet.sequence_number,
+            )),
+            SerializablePacket::Icmpv6Packet(icmpv6_packet)
+                if icmpv6_packet.icmpv6_type == icmpv6_type_to_string(Icmpv6Types::EchoRequest) =>
+            {
+                Some(ProbeKey::Echo(
+                    source,
+                    destination,
+                    icmpv6_packet.echo_identifier?,
+                    icmpv6_packet.echo_sequence?,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the source and destination addresses of the probe
+    fn addresses(&self) -> (IpAddr, IpAddr) {
+        match *self {
+            ProbeKey::Udp((source, _), (destination, _))
+            | ProbeKey::Tcp((source, _), (destination, _), _)
+            | ProbeKey::Echo(source, destination, _, _) => (source, destination),
+        }
+    }
+}
+
+/// Router answering the probes of a TTL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TracerouteHop {
+    pub ttl: u8,
+    pub router: IpAddr,
+    /// Time elapsed between the probe and the Time Exceeded message
+    pub rtt: Duration,
+}
+
+/// Pending probes and reconstructed paths, fed with every parsed packet in capture order
+#[derive(Debug)]
+pub struct TracerouteTracker {
+    timeout: Duration,
+    /// TTL and timestamp of the pending probes
+    probes: HashMap<ProbeKey, (u8, SystemTime)>,
+    /// Probes in capture order, with their timestamp
+    deadlines: VecDeque<(SystemTime, ProbeKey)>,
+    /// Hops by TTL, keyed by source and destination of the probes
+    paths: HashMap<(IpAddr, IpAddr), BTreeMap<u8, TracerouteHop>>,
+}
+
+impl TracerouteTracker {
+    /// Build a tracker forgetting the probes unanswered after `timeout`
+    pub fn new(timeout: Duration) -> Self {
+        TracerouteTracker {
+            timeout,
+            probes: HashMap::new(),
+            deadlines: VecDeque::new(),
+            paths: HashMap::new(),
+        }
+    }
+
+    /// Record a probe, or correlate a Time Exceeded message with its probe, getting the hop it
+    /// reveals; packets without timestamp are ignored
+    pub fn update(&mut self, packet: &ParsedPacket) -> Option<TracerouteHop> {
+        let timestamp = packet.get_timestamp()?;
+        self.expire(timestamp);
+
+        if let Some(embedded) = time_exceeded_packet(packet) {
+            let (router, _) = get_ip_addresses(packet)?;
+            let key = ProbeKey::embedded(embedded)?;
+            let (ttl, probe_timestamp) = self.probes.remove(&key)?;
+            let hop = TracerouteHop {
+                ttl,
+                router,
+                rtt: timestamp
+                    .duration_since(probe_timestamp)
+                    .unwrap_or_default(),
+            };
+
+            self.paths
+                .entry(key.addresses())
+                .or_default()
+                .entry(ttl)
+                .or_insert(hop);
+            return Some(hop);
+        }
+
+        let ttl = ttl(packet).filter(|&ttl| ttl <= MAX_PROBE_TTL)?;
+        let key = ProbeKey::probe(packet)?;
+        self.probes.insert(key, (ttl, timestamp));
+        self.deadlines.push_back((timestamp, key));
+        None
+    }
+
+    /// Forget the probes older than the timeout at the given time; a probe sent again meanwhile
+    /// is kept until its last timestamp expires
+    pub fn expire(&mut self, now: SystemTime) {
+        while let Some((probe_timestamp, key)) = self.deadlines.front() {
+            if now
+                .duration_since(*probe_timestamp)
+                .map_or(true, |elapsed| elapsed <= self.timeout)
+            {
+                break;
+            }
+            // The probe may have been answered, or sent again since
+            if self.probes.get(key).map(|(_, timestamp)| timestamp) == Some(probe_timestamp) {
+                self.probes.remove(key);
+            }
+            self.deadlines.pop_front();
+        }
+    }
+
+    /// Get the hops revealed so far between a source and a destination, by increasing TTL; the
+    /// first router answering a TTL is kept
+    pub fn path(&self, source: IpAddr, destination: IpAddr) -> Vec<TracerouteHop> {
+        self.paths
+            .get(&(source, destination))
+            .map(|hops| hops.values().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Get the number of probes waiting for a Time Exceeded message
+    pub fn pending(&self) -> usize {
+        self.probes.len()
+    }
+}
+
+/// Get the packet embedded in a Time Exceeded ICMP or ICMPv6 message
+fn time_exceeded_packet(packet: &ParsedPacket) -> Option<&SerializableEmbeddedPacket> {
+    match packet.get_transport_layer_packet()? {
+        SerializablePacket::IcmpPacket(icmp_packet)
+            if icmp_packet.icmp_type == icmp_type_to_string(IcmpTypes::TimeExceeded) =>
+        {
+            icmp_packet.original_packet.as_ref()
+        }
+        SerializablePacket::Icmpv6Packet(icmpv6_packet)
+            if icmpv6_packet.icmpv6_type == icmpv6_type_to_string(Icmpv6Types::TimeExceeded) =>
+        {
+            icmpv6_packet.original_packet.as_ref()
+        }
+        _ => None,
+    }
+}
+
+/// Get the TTL, or hop limit, of the IP header of a packet
+fn ttl(packet: &ParsedPacket) -> Option<u8> {
+    match packet.get_network_layer_packet()? {
+        SerializablePacket::Ipv4Packet(ipv4_packet) => Some(ipv4_packet.ttl),
+        SerializablePacket::Ipv6Packet(ipv6_packet) => Some(ipv6_packet.hop_limit),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use pnet::packet::ethernet::EtherTypes;
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::packet::tcp::TcpFlags;
+
+    use super::{TracerouteHop, TracerouteTracker};
+    use crate::serializable_packet::ParsedPacket;
+    use crate::test_util::{
+        build_test_ethernet_frame, build_test_ipv4_packet, build_test_tcp_segment,
+        build_test_udp_datagram, parse_test_frame, timestamped,
+    };
+
+    const SOURCE: Ipv4Addr = Ipv4Addr::new(10, 10, 10, 10);
+    const DESTINATION: Ipv4Addr = Ipv4Addr::new(203, 0, 113, 9);
+
+    #[test]
+    fn three_hop_path() {
+        let mut tracker = TracerouteTracker::new(Duration::from_secs(5));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let routers = [
+            Ipv4Addr::new(192, 168, 1, 1),
+            Ipv4Addr::new(198, 51, 100, 1),
+            Ipv4Addr::new(198, 51, 100, 77),
+        ];
+
+        let mut hops = vec![];
+        for (hop, router) in routers.into_iter().enumerate() {
+            let ttl = hop as u8 + 1;
+            let probe = build_test_udp_probe(ttl, 33434 + hop as u16);
+            let sent = start + Duration::from_millis(100 * hop as u64);
+            assert_eq!(tracker.update(&captured(&probe, sent)), None);
+
+            let mut icmp = vec![11, 0, 0, 0, 0, 0, 0, 0];
+            icmp.extend_from_slice(&probe[..28]);
+            let reply =
+                build_test_ipv4_packet(router, SOURCE, 64, IpNextHeaderProtocols::Icmp, &icmp);
+            let received = sent + Duration::from_millis(5 * ttl as u64);
+            hops.extend(tracker.update(&captured(&reply, received)));
+        }
+
+        let expected: Vec<TracerouteHop> = routers
+            .into_iter()
+            .enumerate()
+            .map(|(hop, router)| TracerouteHop {
+                ttl: hop as u8 + 1,
+                router: IpAddr::V4(router),
+                rtt: Duration::from_millis(5 * (hop as u64 + 1)),
+            })
+            .collect();
+        assert_eq!(hops, expected);
+        assert_eq!(
+            tracker.path(IpAddr::V4(SOURCE), IpAddr::V4(DESTINATION)),
+            expected
+        );
+        assert_eq!(tracker.pending(), 0);
+    }
+
+    #[test]
+    fn regular_packets_not_probes() {
+        let mut tracker = TracerouteTracker::new(Duration::from_secs(5));
+        let packet = build_test_ipv4_packet(
+            SOURCE,
+            DESTINATION,
+            64,
+            IpNextHeaderProtocols::Udp,
+            &[0x9c, 0x40, 0x00, 0x35, 0x00, 0x08, 0x00, 0x00],
+        );
+
+        tracker.update(&captured(&packet, UNIX_EPOCH));
+        assert_eq!(tracker.pending(), 0);
+    }
+
+    #[test]
+    fn echo_probe_answered() {
+        let mut tracker = TracerouteTracker::new(Duration::from_secs(5));
+        let router = Ipv4Addr::new(192, 168, 1, 1);
+        let probe = build_test_ipv4_packet(
+            SOURCE,
+            DESTINATION,
+            1,
+            IpNextHeaderProtocols::Icmp,
+            &[8, 0, 0, 0, 0x12, 0x34, 0x00, 0x01, 0xaa, 0xbb],
+        );
+        assert_eq!(tracker.update(&captured(&probe, UNIX_EPOCH)), None);
+        assert_eq!(tracker.pending(), 1);
+
+        let mut icmp = vec![11, 0, 0, 0, 0, 0, 0, 0];
+        icmp.extend_from_slice(&probe[..28]);
+        let reply = build_test_ipv4_packet(router, SOURCE, 64, IpNextHeaderProtocols::Icmp, &icmp);
+        let received = UNIX_EPOCH + Duration::from_millis(3);
+        assert_eq!(
+            tracker.update(&captured(&reply, received)),
+            Some(TracerouteHop {
+                ttl: 1,
+                router: IpAddr::V4(router),
+                rtt: Duration::from_millis(3),
+            })
+        );
+        assert_eq!(tracker.pending(), 0);
+    }
+
+    #[test]
+    fn same_port_tcp_probes() {
+        let mut tracker = TracerouteTracker::new(Duration::from_secs(5));
+        let routers = [
+            Ipv4Addr::new(192, 168, 1, 1),
+            Ipv4Addr::new(198, 51, 100, 1),
+        ];
+        let probes = [build_test_tcp_probe(1, 1000), build_test_tcp_probe(2, 2000)];
+        for probe in &probes {
+            tracker.update(&captured(probe, UNIX_EPOCH));
+        }
+        assert_eq!(tracker.pending(), 2);
+
+        for (probe, router) in probes.iter().zip(routers).rev() {
+            let mut icmp = vec![11, 0, 0, 0, 0, 0, 0, 0];
+            icmp.extend_from_slice(&probe[..28]);
+            let reply =
+                build_test_ipv4_packet(router, SOURCE, 64, IpNextHeaderProtocols::Icmp, &icmp);
+            tracker.update(&captured(&reply, UNIX_EPOCH));
+        }
+
+        let routers: Vec<(u8, IpAddr)> = tracker
+            .path(IpAddr::V4(SOURCE), IpAddr::V4(DESTINATION))
+            .into_iter()
+            .map(|hop| (hop.ttl, hop.router))
+            .collect();
+        assert_eq!(
+            routers,
+            vec![
+                (1, IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1))),
+                (2, IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1))),
+            ]
+        );
+        assert_eq!(tracker.pending(), 0);
+    }
+
+    #[test]
+    fn resent_probe_kept() {
+        let mut tracker = TracerouteTracker::new(Duration::from_secs(5));
+        let probe = build_test_udp_probe(1, 33434);
+        tracker.update(&captured(&probe, UNIX_EPOCH));
+        tracker.update(&captured(&probe, UNIX_EPOCH + Duration::from_secs(4)));
+
+        tracker.expire(UNIX_EPOCH + Duration::from_secs(6));
+        assert_eq!(tracker.pending(), 1);
+        tracker.expire(UNIX_EPOCH + Duration::from_secs(10));
+        assert_eq!(tracker.pending(), 0);
+    }
+
+    ///////////////////// Utils
+
+    /// Build an empty UDP datagram from the source to the destination, in an IPv4 packet
+    fn build_test_udp_probe(ttl: u8, dest_port: u16) -> Vec<u8> {
+        let udp_datagram = build_test_udp_datagram(40000, dest_port, &[]);
+        build_test_ipv4_packet(
+            SOURCE,
+            DESTINATION,
+            ttl,
+            IpNextHeaderProtocols::Udp,
+            &udp_datagram,
+        )
+    }
+
+    /// Build a TCP SYN segment from the source to the destination port 80, in an IPv4 packet
+    fn build_test_tcp_probe(ttl: u8, sequence: u32) -> Vec<u8> {
+        let tcp_segment = build_test_tcp_segment(40000, 80, TcpFlags::SYN, sequence, 0, &[]);
+        build_test_ipv4_packet(
+            SOURCE,
+            DESTINATION,
+            ttl,
+            IpNextHeaderProtocols::Tcp,
+            &tcp_segment,
+        )
+    }
+
+    /// Parse an IPv4 packet in an Ethernet frame, captured at the given time
+    fn captured(ip_packet: &[u8], timestamp: SystemTime) -> ParsedPacket {
+        let frame = build_test_ethernet_frame(EtherTypes::Ipv4, ip_packet);
+        timestamped(parse_test_frame(&frame, 0), timestamp)
+    }
+}
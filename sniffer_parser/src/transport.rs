@@ -1,28 +1,52 @@
-//! UDP, TCP, ICMP, and ICMPv6 Packet parsing
+//! UDP, TCP, ICMP, ICMPv6, and IGMP Packet parsing, OSPF and SCTP being dispatched to their
+//! modules
 
 use pnet::packet::icmp::{echo_reply, echo_request, IcmpPacket, IcmpTypes};
 use pnet::packet::icmpv6::Icmpv6Packet;
-use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols::{
+use pnet::packet::ip::{
+    IpNextHeaderProtocol,
+    IpNextHeaderProtocols::{
+        Icmp as ICMP, Icmpv6 as ICMPV6, Igmp as IGMP, OspfigP as OSPF, Sctp as SCTP, Tcp as TCP,
         Udp as UDP,
-        Tcp as TCP,
-        Icmp as ICMP,
-        Icmpv6 as ICMPV6,
-    }
+    },
 };
-use pnet::packet::tcp::TcpPacket;
-use pnet::packet::udp::UdpPacket;
+use pnet::packet::tcp::{self, TcpPacket};
+use pnet::packet::udp::{self, UdpPacket};
 
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr};
 
+use crate::application::dns::{handle_dns_packet, is_dns_message};
+use crate::application::dtls::{handle_dtls_packet, is_dtls_record};
 use crate::application::handle_application_protocol;
+use crate::application::hint::{rtp_sequence_number, udp_protocol_hint};
+use crate::application::quic::{handle_quic_packet, is_quic_long_header};
+use crate::extracted_strings;
+use crate::ospf::handle_ospf_packet;
+use crate::sctp::handle_sctp_packet;
 use crate::serializable_packet::transport::{
-    SerializableEchoReplyPacket, SerializableEchoRequestPacket, SerializableIcmpPacket,
-    SerializableIcmpv6Packet, SerializableTcpPacket, SerializableUdpPacket,
+    igmp_record_type_to_string, igmp_type_to_string, SerializableEchoReplyPacket,
+    SerializableEchoRequestPacket, SerializableIcmpPacket, SerializableIcmpv6Packet,
+    SerializableIgmpGroupRecord, SerializableIgmpPacket, SerializableTcpPacket,
+    SerializableUdpPacket,
 };
 
 const ACK_BIT_SHIFT: usize = 4;
 const FIN_BIT_SHIFT: usize = 0;
 
+const IGMP_HEADER_LENGTH: usize = 8;
+const IGMPV3_QUERY_HEADER_LENGTH: usize = 12;
+const IGMPV3_GROUP_RECORD_HEADER_LENGTH: usize = 8;
+
+/// IGMP Message Types
+#[allow(non_snake_case)]
+pub mod IgmpTypes {
+    pub const MEMBERSHIP_QUERY: u8 = 0x11;
+    pub const V1_MEMBERSHIP_REPORT: u8 = 0x12;
+    pub const V2_MEMBERSHIP_REPORT: u8 = 0x16;
+    pub const LEAVE_GROUP: u8 = 0x17;
+    pub const V3_MEMBERSHIP_REPORT: u8 = 0x22;
+}
+
 use super::*;
 
 /// Build a UDP packet from a network-layer packet, save it in a Parsed Packet
@@ -44,19 +68,54 @@ pub fn handle_udp_packet(
             udp.get_length()
         );
 
-        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::UdpPacket(
-            SerializableUdpPacket::from(&udp),
-        )));
+        let mut udp_packet = SerializableUdpPacket::from(&udp);
+        let checksum = udp.get_checksum();
+        // A zero checksum means no checksum over IPv4 only
+        udp_packet.checksum_valid = (checksum == 0 && source.is_ipv4())
+            || udp_checksum(&udp, source, destination) == Some(checksum);
+        udp_packet.checksum_offload_suspected = !udp_packet.checksum_valid && checksum == 0;
+
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::UdpPacket(udp_packet)));
 
-        handle_application_protocol(
+        let flow = FlowContext::track(
             source,
             udp.get_source(),
             destination,
             udp.get_destination(),
-            false,
-            udp.payload(),
-            parsed_packet,
+            udp.payload().len(),
         );
+
+        profiled(ProfileLayer::Application, || {
+            if is_dtls_record(udp.payload()) {
+                handle_dtls_packet(&flow, udp.payload(), parsed_packet);
+            } else if is_quic_long_header(udp.get_source(), udp.get_destination(), udp.payload()) {
+                handle_quic_packet(&flow, udp.payload(), parsed_packet);
+            } else {
+                handle_application_protocol(&flow, false, udp.payload(), parsed_packet);
+                // DNS on another port, recognized from the content of the datagram
+                if parsed_packet.get_application_layer_packet().is_none()
+                    && is_dns_message(udp.payload())
+                {
+                    handle_dns_packet(&flow, udp.payload(), parsed_packet);
+                }
+            }
+        });
+
+        if parsed_packet.get_application_layer_packet().is_none() {
+            let protocol_hint = udp_protocol_hint(udp.payload());
+            let rtp_sequence = match protocol_hint.as_deref() {
+                Some("RTP?") => rtp_sequence_number(udp.payload()),
+                _ => None,
+            };
+            if let Some(SerializablePacket::UdpPacket(udp_packet)) = parsed_packet
+                .layers_mut()
+                .find(|layer| matches!(layer, SerializablePacket::UdpPacket(_)))
+            {
+                udp_packet.protocol_hint = protocol_hint;
+                udp_packet.rtp_sequence = rtp_sequence;
+                udp_packet.strings = extracted_strings(udp.payload());
+            }
+        }
     } else {
         debug!("Malformed UDP Packet");
         parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
@@ -65,6 +124,19 @@ pub fn handle_udp_packet(
     }
 }
 
+/// Compute the checksum of a UDP packet, over its IP pseudo-header
+fn udp_checksum(udp: &UdpPacket, source: IpAddr, destination: IpAddr) -> Option<u16> {
+    match (source, destination) {
+        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+            Some(udp::ipv4_checksum(udp, &source, &destination))
+        }
+        (IpAddr::V6(source), IpAddr::V6(destination)) => {
+            Some(udp::ipv6_checksum(udp, &source, &destination))
+        }
+        _ => None,
+    }
+}
+
 /// Build a TCP packet from a network-layer packet, save it in a Parsed Packet
 pub fn handle_tcp_packet(
     source: IpAddr,
@@ -83,22 +155,37 @@ pub fn handle_tcp_packet(
             packet.len()
         );
 
-        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::TcpPacket(
-            SerializableTcpPacket::from(&tcp),
-        )));
+        let mut tcp_packet = SerializableTcpPacket::from(&tcp);
+        let checksum = tcp.get_checksum();
+        tcp_packet.checksum_valid = tcp_checksum(&tcp, source, destination) == Some(checksum);
+        tcp_packet.checksum_offload_suspected = !tcp_packet.checksum_valid && checksum == 0;
+
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::TcpPacket(tcp_packet)));
 
         let flags = tcp.get_flags();
         let is_fin = (flags & (1 << ACK_BIT_SHIFT)) != 0 && (flags & (1 << FIN_BIT_SHIFT)) != 0;
 
-        handle_application_protocol(
+        let flow = FlowContext::track(
             source,
             tcp.get_source(),
             destination,
             tcp.get_destination(),
-            is_fin,
-            tcp.payload(),
-            parsed_packet,
+            tcp.payload().len(),
         );
+
+        profiled(ProfileLayer::Application, || {
+            handle_application_protocol(&flow, is_fin, tcp.payload(), parsed_packet)
+        });
+
+        if parsed_packet.get_application_layer_packet().is_none() {
+            let strings = extracted_strings(tcp.payload());
+            if let Some(SerializablePacket::TcpPacket(tcp_packet)) = parsed_packet
+                .layers_mut()
+                .find(|layer| matches!(layer, SerializablePacket::TcpPacket(_)))
+            {
+                tcp_packet.strings = strings;
+            }
+        }
     } else {
         debug!("Malformed TCP Packet");
         parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
@@ -107,6 +194,19 @@ pub fn handle_tcp_packet(
     }
 }
 
+/// Compute the checksum of a TCP packet, over its IP pseudo-header
+fn tcp_checksum(tcp: &TcpPacket, source: IpAddr, destination: IpAddr) -> Option<u16> {
+    match (source, destination) {
+        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+            Some(tcp::ipv4_checksum(tcp, &source, &destination))
+        }
+        (IpAddr::V6(source), IpAddr::V6(destination)) => {
+            Some(tcp::ipv6_checksum(tcp, &source, &destination))
+        }
+        _ => None,
+    }
+}
+
 /// Build a Transport-layer packet from a network-layer packet, save it in a Parsed Packet
 pub fn handle_transport_protocol(
     source: IpAddr,
@@ -115,15 +215,14 @@ pub fn handle_transport_protocol(
     packet: &[u8],
     parsed_packet: &mut ParsedPacket,
 ) {
-    return match protocol {
+    profiled(ProfileLayer::Transport, || match protocol {
         UDP => handle_udp_packet(source, destination, packet, parsed_packet),
         TCP => handle_tcp_packet(source, destination, packet, parsed_packet),
-        ICMP => {
-            handle_icmp_packet(source, destination, packet, parsed_packet)
-        }
-        ICMPV6 => {
-            handle_icmpv6_packet(source, destination, packet, parsed_packet)
-        }
+        ICMP => handle_icmp_packet(source, destination, packet, parsed_packet),
+        ICMPV6 => handle_icmpv6_packet(source, destination, packet, parsed_packet),
+        IGMP => handle_igmp_packet(source, destination, packet, parsed_packet),
+        OSPF => handle_ospf_packet(source, destination, packet, parsed_packet),
+        SCTP => handle_sctp_packet(source, destination, packet, parsed_packet),
         _ => {
             debug!(
                 "Unknown {} packet: {} > {}; protocol: {:?} length: {}",
@@ -137,7 +236,7 @@ pub fn handle_transport_protocol(
                 packet.len()
             );
         }
-    };
+    });
 }
 
 /// Build a ICMP packet from a network-layer packet, save it in a Parsed Packet
@@ -231,17 +330,135 @@ pub fn handle_icmpv6_packet(
     }
 }
 
+/// Build a IGMP packet from a network-layer packet, save it in a Parsed Packet
+pub fn handle_igmp_packet(
+    source: IpAddr,
+    destination: IpAddr,
+    packet: &[u8],
+    parsed_packet: &mut ParsedPacket,
+) {
+    if let Some(igmp_packet) = parse_igmp_packet(packet) {
+        debug!(
+            "IGMP packet {} -> {} (type={}, group={:?})",
+            source, destination, igmp_packet.igmp_type, igmp_packet.group_address
+        );
+
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::IgmpPacket(igmp_packet)));
+    } else {
+        debug!("Malformed IGMP Packet");
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed IGMP Packet".to_string(),
+        )));
+    }
+}
+
+/// Parse an IGMP message; the version of a query is told by its length (RFC 3376, section 7.1)
+fn parse_igmp_packet(packet: &[u8]) -> Option<SerializableIgmpPacket> {
+    if packet.len() < IGMP_HEADER_LENGTH {
+        return None;
+    }
+
+    let igmp_type = packet[0];
+    let max_response_code = packet[1];
+    let checksum = u16::from_be_bytes([packet[2], packet[3]]);
+    let group_address = read_ipv4_address(&packet[4..]);
+
+    let version = match igmp_type {
+        IgmpTypes::MEMBERSHIP_QUERY if packet.len() >= IGMPV3_QUERY_HEADER_LENGTH => 3,
+        IgmpTypes::MEMBERSHIP_QUERY if max_response_code == 0 => 1,
+        IgmpTypes::V1_MEMBERSHIP_REPORT => 1,
+        IgmpTypes::V3_MEMBERSHIP_REPORT => 3,
+        _ => 2,
+    };
+
+    let mut igmp_packet = SerializableIgmpPacket {
+        igmp_type: igmp_type_to_string(igmp_type, version),
+        version,
+        max_response_time: match version {
+            3 => igmpv3_max_response_time(max_response_code),
+            _ => max_response_code as u16,
+        },
+        checksum,
+        group_address,
+        sources: vec![],
+        records: vec![],
+        length: packet.len(),
+    };
+
+    match (igmp_type, version) {
+        (IgmpTypes::MEMBERSHIP_QUERY, 3) => {
+            let number_of_sources = u16::from_be_bytes([packet[10], packet[11]]) as usize;
+            igmp_packet.sources =
+                read_ipv4_addresses(&packet[IGMPV3_QUERY_HEADER_LENGTH..], number_of_sources)?;
+        }
+        (IgmpTypes::V3_MEMBERSHIP_REPORT, _) => {
+            let number_of_records = u16::from_be_bytes([packet[6], packet[7]]) as usize;
+            let mut records = &packet[IGMP_HEADER_LENGTH..];
+
+            igmp_packet.group_address = None;
+            for _ in 0..number_of_records {
+                let header = records.get(..IGMPV3_GROUP_RECORD_HEADER_LENGTH)?;
+                let auxiliary_data_length = header[1] as usize * 4;
+                let number_of_sources = u16::from_be_bytes([header[2], header[3]]) as usize;
+                let record_length = IGMPV3_GROUP_RECORD_HEADER_LENGTH
+                    + 4 * number_of_sources
+                    + auxiliary_data_length;
+
+                igmp_packet.records.push(SerializableIgmpGroupRecord {
+                    record_type: igmp_record_type_to_string(header[0]),
+                    multicast_address: read_ipv4_address(&header[4..])?,
+                    sources: read_ipv4_addresses(
+                        &records[IGMPV3_GROUP_RECORD_HEADER_LENGTH..],
+                        number_of_sources,
+                    )?,
+                });
+                records = records.get(record_length..)?;
+            }
+        }
+        _ => (),
+    }
+
+    Some(igmp_packet)
+}
+
+/// Decode the IGMPv3 Max Resp Code, a floating-point value above 128 (RFC 3376, section 4.1.1)
+fn igmpv3_max_response_time(code: u8) -> u16 {
+    match code {
+        0..=127 => code as u16,
+        _ => {
+            let mantissa = (code & 0x0f) as u16 | 0x10;
+            let exponent = (code >> 4) & 0x07;
+            mantissa << (exponent + 3)
+        }
+    }
+}
+
+pub(crate) fn read_ipv4_address(bytes: &[u8]) -> Option<Ipv4Addr> {
+    match bytes {
+        [a, b, c, d, ..] => Some(Ipv4Addr::new(*a, *b, *c, *d)),
+        _ => None,
+    }
+}
+
+pub(crate) fn read_ipv4_addresses(bytes: &[u8], count: usize) -> Option<Vec<Ipv4Addr>> {
+    let bytes = bytes.get(..4 * count)?;
+    bytes.chunks(4).map(read_ipv4_address).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use std::net::IpAddr;
     use std::net::Ipv4Addr;
+    use std::net::Ipv6Addr;
 
     use pnet::packet::icmp::IcmpType;
     use pnet::packet::icmp::MutableIcmpPacket;
     use pnet::packet::icmpv6::echo_reply::Icmpv6Codes;
     use pnet::packet::icmpv6::Icmpv6Types;
     use pnet::packet::icmpv6::MutableIcmpv6Packet;
+    use pnet::packet::ip::IpNextHeaderProtocols;
     use pnet::packet::tcp::MutableTcpPacket;
+    use pnet::packet::tcp::TcpFlags;
     use pnet::packet::tcp::TcpPacket;
     use pnet::packet::udp::MutableUdpPacket;
     use pnet::packet::udp::UdpPacket;
@@ -249,6 +466,8 @@ mod tests {
 
     use crate::serializable_packet::transport::icmp_type_to_string;
     use crate::serializable_packet::transport::icmpv6_type_to_string;
+    use crate::serializable_packet::util::hexdump;
+    use crate::set_payload_retention;
 
     use super::*;
 
@@ -293,6 +512,145 @@ mod tests {
         }
     }
 
+    #[test]
+    fn udp_protocol_hint() {
+        // RTP version 2 header (payload type 0, sequence 1), then 4 samples
+        let rtp_payload = [
+            0x80, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xa0, 0x12, 0x34, 0x56, 0x78, 0xff, 0xfe,
+            0x7f, 0x7e,
+        ];
+        let random_payload = [
+            0x3f, 0x9a, 0x71, 0x04, 0xd2, 0x5e, 0x0b, 0xc8, 0x66, 0x13, 0xaf, 0x2d, 0x90, 0x47,
+            0x1c, 0xe5,
+        ];
+
+        for (payload, protocol_hint, rtp_sequence) in [
+            (rtp_payload, Some("RTP?"), Some(1)),
+            (random_payload, None, None),
+        ] {
+            let mut udp_buffer = [0u8; 8 + 16];
+            let mut udp_packet = MutableUdpPacket::new(&mut udp_buffer).unwrap();
+            udp_packet.set_source(40_002);
+            udp_packet.set_destination(51_234);
+            udp_packet.set_length(8 + 16);
+            udp_packet.set_payload(&payload);
+
+            let mut parsed_packet = ParsedPacket::new(0);
+            handle_udp_packet(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                udp_packet.packet(),
+                &mut parsed_packet,
+            );
+
+            assert!(parsed_packet.get_application_layer_packet().is_none());
+            match parsed_packet.get_transport_layer_packet().unwrap() {
+                SerializablePacket::UdpPacket(new_udp_packet) => {
+                    assert_eq!(new_udp_packet.protocol_hint.as_deref(), protocol_hint);
+                    assert_eq!(new_udp_packet.rtp_sequence, rtp_sequence);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn dns_confirmed_by_content() {
+        // Query of one question for "a.io", type A, class IN
+        let dns_query = [
+            0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, b'a',
+            0x02, b'i', b'o', 0x00, 0x00, 0x01, 0x00, 0x01,
+        ];
+        let not_dns = [0xffu8; 22];
+
+        for (payload, port, is_dns) in [
+            (dns_query, 5300, true),
+            (dns_query, 53, true),
+            (not_dns, 53, false),
+        ] {
+            let mut udp_buffer = [0u8; 8 + 22];
+            let mut udp_packet = MutableUdpPacket::new(&mut udp_buffer).unwrap();
+            udp_packet.set_source(40_002);
+            udp_packet.set_destination(port);
+            udp_packet.set_length(8 + 22);
+            udp_packet.set_payload(&payload);
+
+            let mut parsed_packet = ParsedPacket::new(0);
+            handle_udp_packet(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                udp_packet.packet(),
+                &mut parsed_packet,
+            );
+
+            match parsed_packet.get_application_layer_packet() {
+                Some(SerializablePacket::DnsPacket(dns_packet)) => {
+                    assert!(is_dns);
+                    assert_eq!(dns_packet.header.id, 0x1234);
+                }
+                None => assert!(!is_dns),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn udp_payload_strings() {
+        let payload = b"\x00\x17GET /foo\x00\x01";
+        let mut udp_buffer = [0u8; 8 + 12];
+        let mut udp_packet = MutableUdpPacket::new(&mut udp_buffer).unwrap();
+        udp_packet.set_source(40_002);
+        udp_packet.set_destination(51_234);
+        udp_packet.set_length(8 + 12);
+        udp_packet.set_payload(payload);
+
+        crate::set_string_extraction(Some(4));
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_udp_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+            udp_packet.packet(),
+            &mut parsed_packet,
+        );
+        crate::set_string_extraction(None);
+
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::UdpPacket(new_udp_packet) => {
+                assert_eq!(new_udp_packet.strings, Some(vec!["GET /foo".to_owned()]))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn udp_payload_display() {
+        let payload = b"\x00\x01payload";
+        let mut udp_buffer = [0u8; 8 + 9];
+        let mut udp_packet = MutableUdpPacket::new(&mut udp_buffer).unwrap();
+        udp_packet.set_source(40_002);
+        udp_packet.set_destination(51_234);
+        udp_packet.set_length(8 + 9);
+        udp_packet.set_payload(payload);
+
+        set_payload_retention(true);
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_udp_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+            udp_packet.packet(),
+            &mut parsed_packet,
+        );
+        set_payload_retention(false);
+
+        let new_udp_packet = parsed_packet.get_transport_layer_packet().unwrap();
+        let without_payload = format!("{}", new_udp_packet);
+        assert!(!without_payload.contains("Payload:"));
+        assert_eq!(
+            format!("{:#}", new_udp_packet),
+            expected_payload_display(&without_payload, payload)
+        );
+    }
+
     #[test]
     fn valid_tcp_packet() {
         let mut tcp_buffer = [0u8; 42];
@@ -317,7 +675,7 @@ mod tests {
                 );
                 assert_eq!(new_tcp_packet.data_offset, tcp_packet.get_data_offset());
                 assert_eq!(new_tcp_packet.reserved, tcp_packet.get_reserved());
-                assert_eq!(new_tcp_packet.flags, tcp_packet.get_flags());
+                assert_eq!(new_tcp_packet.flags, tcp_packet.get_flags() as u16);
                 assert_eq!(new_tcp_packet.window, tcp_packet.get_window());
                 assert_eq!(new_tcp_packet.checksum, tcp_packet.get_checksum());
                 assert_eq!(new_tcp_packet.urgent_ptr, tcp_packet.get_urgent_ptr());
@@ -328,6 +686,126 @@ mod tests {
         }
     }
 
+    #[test]
+    fn zero_window_tcp_packet() {
+        for (window, flags, is_zero_window) in [
+            (0, TcpFlags::ACK, true),
+            (0, TcpFlags::RST, false),
+            (1024, TcpFlags::ACK, false),
+        ] {
+            let mut tcp_buffer = [0u8; 20];
+            let mut tcp_packet = MutableTcpPacket::new(&mut tcp_buffer).unwrap();
+            tcp_packet.set_source(4444);
+            tcp_packet.set_destination(4445);
+            tcp_packet.set_data_offset(5);
+            tcp_packet.set_flags(flags);
+            tcp_packet.set_window(window);
+
+            let mut parsed_packet = ParsedPacket::new(0);
+            handle_tcp_packet(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                tcp_packet.packet(),
+                &mut parsed_packet,
+            );
+
+            match parsed_packet.get_transport_layer_packet().unwrap() {
+                SerializablePacket::TcpPacket(new_tcp_packet) => {
+                    assert_eq!(new_tcp_packet.is_zero_window, is_zero_window);
+                    assert!(!new_tcp_packet.is_keep_alive);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn urgent_tcp_packet() {
+        for (flags, urgent_ptr, urgent_data_end, is_zero_urgent_pointer) in [
+            (TcpFlags::ACK | TcpFlags::URG, 3, Some(3), false),
+            (TcpFlags::ACK | TcpFlags::URG, 0, None, true),
+            (TcpFlags::ACK, 3, None, false),
+        ] {
+            let mut tcp_buffer = [0u8; 24];
+            let mut tcp_packet = MutableTcpPacket::new(&mut tcp_buffer).unwrap();
+            tcp_packet.set_source(4444);
+            tcp_packet.set_destination(4445);
+            tcp_packet.set_data_offset(5);
+            tcp_packet.set_flags(flags);
+            tcp_packet.set_urgent_ptr(urgent_ptr);
+            tcp_packet.set_payload(b"\xffabc");
+
+            let mut parsed_packet = ParsedPacket::new(0);
+            handle_tcp_packet(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                tcp_packet.packet(),
+                &mut parsed_packet,
+            );
+
+            match parsed_packet.get_transport_layer_packet().unwrap() {
+                SerializablePacket::TcpPacket(new_tcp_packet) => {
+                    assert_eq!(new_tcp_packet.urgent_ptr, urgent_ptr);
+                    assert_eq!(new_tcp_packet.urgent_data_end, urgent_data_end);
+                    assert_eq!(
+                        new_tcp_packet.is_zero_urgent_pointer,
+                        is_zero_urgent_pointer
+                    );
+                    assert_eq!(
+                        new_tcp_packet
+                            .to_string()
+                            .contains("Zero Urgent Pointer With URG Set"),
+                        is_zero_urgent_pointer
+                    );
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn zero_checksum_tcp_packet() {
+        let (source, destination) = (Ipv4Addr::new(10, 10, 10, 10), Ipv4Addr::new(11, 11, 11, 11));
+        let mut tcp_buffer = [0u8; 42];
+
+        let tcp_packet = build_test_tcp_packet(tcp_buffer.as_mut_slice());
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_tcp_packet(
+            IpAddr::V4(source),
+            IpAddr::V4(destination),
+            tcp_packet.packet(),
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::TcpPacket(new_tcp_packet) => {
+                assert_eq!(new_tcp_packet.checksum, 0);
+                assert!(!new_tcp_packet.checksum_valid);
+                assert!(new_tcp_packet.checksum_offload_suspected);
+            }
+            _ => unreachable!(),
+        }
+
+        let checksum = tcp::ipv4_checksum(&tcp_packet, &source, &destination);
+        let mut tcp_packet = MutableTcpPacket::new(&mut tcp_buffer).unwrap();
+        tcp_packet.set_checksum(checksum);
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_tcp_packet(
+            IpAddr::V4(source),
+            IpAddr::V4(destination),
+            tcp_packet.packet(),
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::TcpPacket(new_tcp_packet) => {
+                assert!(new_tcp_packet.checksum_valid);
+                assert!(!new_tcp_packet.checksum_offload_suspected);
+            }
+            _ => unreachable!(),
+        }
+    }
+
     #[test]
     fn malformed_tcp_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
@@ -464,6 +942,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn port_unreachable_icmp_packet() {
+        let icmp_packet = [
+            // Destination Unreachable, Port Unreachable, checksum, unused
+            0x03, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // Embedded IPv4 header: UDP 10.10.10.10 > 11.11.11.11, TTL 1
+            0x45, 0x00, 0x00, 0x24, 0x12, 0x34, 0x00, 0x00, 0x01, 0x11, 0x00, 0x00, 0x0a, 0x0a,
+            0x0a, 0x0a, 0x0b, 0x0b, 0x0b, 0x0b,
+            // First 8 bytes of the UDP header: 45281 > 33434
+            0xb0, 0xe1, 0x82, 0x9a, 0x00, 0x10, 0x00, 0x00,
+        ];
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_icmp_packet(
+            IpAddr::V4(Ipv4Addr::new(12, 12, 12, 12)),
+            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+            &icmp_packet,
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::IcmpPacket(new_icmp_packet) => {
+                let original_packet = new_icmp_packet.original_packet.as_ref().unwrap();
+                assert_eq!(
+                    original_packet.destination,
+                    IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11))
+                );
+                assert_eq!(original_packet.protocol, "Udp (17)");
+                assert_eq!(original_packet.ttl, 1);
+                assert_eq!(original_packet.source_port, Some(45281));
+                assert_eq!(original_packet.destination_port, Some(33434));
+            }
+            _ => unreachable!(),
+        }
+    }
+
     #[test]
     fn malformed_icmp_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
@@ -510,6 +1024,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn time_exceeded_icmpv6_packet() {
+        let mut icmpv6_packet = vec![0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        // Embedded IPv6 header: TCP, hop limit 1
+        icmpv6_packet.extend([0x60, 0x00, 0x00, 0x00, 0x00, 0x08, 0x06, 0x01]);
+        icmpv6_packet.extend(Ipv6Addr::new(10, 10, 10, 10, 10, 10, 10, 10).octets());
+        icmpv6_packet.extend(Ipv6Addr::new(11, 11, 11, 11, 11, 11, 11, 11).octets());
+        // First 8 bytes of the TCP header: 4444 > 443
+        icmpv6_packet.extend([0x11, 0x5c, 0x01, 0xbb, 0x00, 0x00, 0x00, 0x01]);
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_icmpv6_packet(
+            IpAddr::V6(Ipv6Addr::new(12, 12, 12, 12, 12, 12, 12, 12)),
+            IpAddr::V6(Ipv6Addr::new(10, 10, 10, 10, 10, 10, 10, 10)),
+            &icmpv6_packet,
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::Icmpv6Packet(new_icmpv6_packet) => {
+                let original_packet = new_icmpv6_packet.original_packet.as_ref().unwrap();
+                assert_eq!(original_packet.protocol, "Tcp (6)");
+                assert_eq!(original_packet.source_port, Some(4444));
+                assert_eq!(original_packet.destination_port, Some(443));
+            }
+            _ => unreachable!(),
+        }
+    }
+
     #[test]
     fn malformed_icmpv6_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
@@ -526,8 +1069,125 @@ mod tests {
         }
     }
 
+    #[test]
+    fn igmpv2_membership_report() {
+        // Membership Report v2 for 239.1.2.3
+        let igmp_buffer = [0x16, 0x00, 0xfa, 0x04, 239, 1, 2, 3];
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_transport_protocol(
+            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+            IpAddr::V4(Ipv4Addr::new(239, 1, 2, 3)),
+            IpNextHeaderProtocols::Igmp,
+            &igmp_buffer,
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::IgmpPacket(igmp_packet) => {
+                assert_eq!(igmp_packet.igmp_type, "MembershipReport v2 (22)");
+                assert_eq!(igmp_packet.version, 2);
+                assert_eq!(igmp_packet.checksum, 0xfa04);
+                assert_eq!(igmp_packet.group_address, Some(Ipv4Addr::new(239, 1, 2, 3)));
+                assert!(igmp_packet.records.is_empty());
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn igmpv3_membership_report() {
+        // Two records: ChangeToIncludeMode 232.1.1.1 from 10.0.0.1, ChangeToExcludeMode 239.0.0.1
+        let igmp_buffer = [
+            0x22, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x00, 0x00, 0x01, 232, 1, 1, 1,
+            10, 0, 0, 1, 0x04, 0x00, 0x00, 0x00, 239, 0, 0, 1,
+        ];
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_igmp_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            IpAddr::V4(Ipv4Addr::new(224, 0, 0, 22)),
+            &igmp_buffer,
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::IgmpPacket(igmp_packet) => {
+                assert_eq!(igmp_packet.version, 3);
+                assert_eq!(igmp_packet.group_address, None);
+                assert_eq!(igmp_packet.records.len(), 2);
+                assert_eq!(
+                    igmp_packet.records[0].record_type,
+                    "ChangeToIncludeMode (3)"
+                );
+                assert_eq!(
+                    igmp_packet.records[0].multicast_address,
+                    Ipv4Addr::new(232, 1, 1, 1)
+                );
+                assert_eq!(
+                    igmp_packet.records[0].sources,
+                    vec![Ipv4Addr::new(10, 0, 0, 1)]
+                );
+                assert!(igmp_packet.records[1].sources.is_empty());
+            }
+            _ => unreachable!(),
+        }
+
+        // Truncated second record
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_igmp_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            IpAddr::V4(Ipv4Addr::new(224, 0, 0, 22)),
+            &igmp_buffer[..24],
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::MalformedPacket(str) => assert_eq!(str, "Malformed IGMP Packet"),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn tcp_payload_display() {
+        let payload = b"\x00\x01payload";
+        let mut tcp_buffer = [0u8; 20 + 9];
+        let mut tcp_packet = MutableTcpPacket::new(&mut tcp_buffer).unwrap();
+        tcp_packet.set_source(40_002);
+        tcp_packet.set_destination(51_234);
+        tcp_packet.set_data_offset(5);
+        tcp_packet.set_flags(TcpFlags::ACK);
+        tcp_packet.set_window(512);
+        tcp_packet.set_payload(payload);
+
+        set_payload_retention(true);
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_tcp_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+            tcp_packet.packet(),
+            &mut parsed_packet,
+        );
+        set_payload_retention(false);
+
+        let new_tcp_packet = parsed_packet.get_transport_layer_packet().unwrap();
+        let without_payload = format!("{}", new_tcp_packet);
+        assert!(!without_payload.contains("Payload:"));
+        assert_eq!(
+            format!("{:#}", new_tcp_packet),
+            expected_payload_display(&without_payload, payload)
+        );
+    }
+
     ///////////////////// Utils
 
+    /// Expect the display without payload followed by the hexdump of the payload
+    fn expected_payload_display(without_payload: &str, payload: &[u8]) -> String {
+        let hexdump: String = hexdump(payload)
+            .lines()
+            .map(|line| format!("\n\t\t{}", line))
+            .collect();
+        format!("{}\n\tPayload:{}", without_payload, hexdump)
+    }
+
     fn build_test_udp_packet<'a>(udp_buffer: &'a mut [u8]) -> UdpPacket<'a> {
         let mut udp_packet = MutableUdpPacket::new(udp_buffer).unwrap();
 
@@ -0,0 +1,215 @@
+//! Extraction of the HTTP response bodies to files
+//!
+//! The path of each request is queued by client and server endpoints; as HTTP/1.1 answers the
+//! requests of a connection in order, the body of each response, its chunks merged and its
+//! `Content-Encoding` decoded but its bytes left as sent, is written to a new file of the dump
+//! directory named after the last segment of the path of the oldest pending request. The
+//! names are sanitized so that no file is written outside of the directory, and suffixed with a
+//! counter rather than overwriting a file
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use sniffer_parser::http_tracker::HttpConnectionKey;
+use sniffer_parser::serializable_packet::util::{get_dest_endpoint, get_source_endpoint};
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+/// Name of the files of the bodies answering unknown requests or paths without a file name
+const DEFAULT_FILE_NAME: &str = "body";
+const MAX_FILE_NAME_LENGTH: usize = 128;
+/// Paths kept for the requests of a connection left unanswered
+const MAX_PENDING_REQUESTS: usize = 64;
+
+/// Writer of the HTTP response bodies into a directory, fed with every parsed packet in capture
+/// order
+#[derive(Debug)]
+pub struct PayloadCarver {
+    dir: PathBuf,
+    requests: HashMap<HttpConnectionKey, VecDeque<String>>,
+}
+
+impl PayloadCarver {
+    /// Build a carver writing into a directory, created if missing
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        Ok(PayloadCarver {
+            dir,
+            requests: HashMap::new(),
+        })
+    }
+
+    /// Record the path of a request, or write the body of a response to a new file, getting its
+    /// path
+    pub fn carve(&mut self, packet: &ParsedPacket) -> io::Result<Option<PathBuf>> {
+        let (source, dest) = match (get_source_endpoint(packet), get_dest_endpoint(packet)) {
+            (Some(source), Some(dest)) => (source, dest),
+            _ => return Ok(None),
+        };
+
+        match packet.get_application_layer_packet() {
+            Some(SerializablePacket::HttpRequestPacket(http_packet)) => {
+                let requests = self.requests.entry((source, dest)).or_default();
+                if requests.len() == MAX_PENDING_REQUESTS {
+                    requests.pop_front();
+                }
+                requests.push_back(http_packet.path.clone());
+                Ok(None)
+            }
+            Some(SerializablePacket::HttpResponsePacket(http_packet)) => {
+                let key = (dest, source);
+                let path = self.requests.get_mut(&key).and_then(VecDeque::pop_front);
+                if self.requests.get(&key).is_some_and(VecDeque::is_empty) {
+                    self.requests.remove(&key);
+                }
+                if http_packet.body.is_empty() {
+                    return Ok(None);
+                }
+
+                let file_name = path
+                    .as_deref()
+                    .map_or(DEFAULT_FILE_NAME.to_owned(), sanitized_file_name);
+                let (file_path, mut file) = self.create_unique(&file_name)?;
+                file.write_all(&http_packet.body)?;
+                Ok(Some(file_path))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Create a new file of the directory, suffixing its name with a counter if it exists
+    fn create_unique(&self, file_name: &str) -> io::Result<(PathBuf, File)> {
+        let (stem, extension) = match file_name.rfind('.') {
+            Some(dot) if dot > 0 => file_name.split_at(dot),
+            _ => (file_name, ""),
+        };
+
+        let mut counter = 0;
+        loop {
+            let candidate = match counter {
+                0 => file_name.to_owned(),
+                _ => format!("{}-{}{}", stem, counter, extension),
+            };
+            let file_path = self.dir.join(candidate);
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&file_path)
+            {
+                Ok(file) => return Ok((file_path, file)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => counter += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Get a file name from the last segment of a request path, query and fragment excluded: only
+/// ASCII letters, digits, `-`, `_` and `.` are kept, the others being replaced by `_`, and the
+/// leading dots are dropped, so that it names a file of the dump directory
+pub fn sanitized_file_name(path: &str) -> String {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let segment = path.rsplit('/').next().unwrap_or_default();
+    let file_name: String = segment
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || "-_.".contains(c) {
+            true => c,
+            false => '_',
+        })
+        .take(MAX_FILE_NAME_LENGTH)
+        .collect();
+
+    match file_name.trim_start_matches('.') {
+        "" => DEFAULT_FILE_NAME.to_owned(),
+        file_name => file_name.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use pnet::packet::tcp::TcpFlags;
+    use sniffer_parser::serializable_packet::ParsedPacket;
+
+    use super::{sanitized_file_name, PayloadCarver};
+    use crate::test_util::{build_test_tcp_packet, CLIENT, SERVER};
+
+    #[test]
+    fn http_response_bodies_extracted() {
+        let dir = env::temp_dir().join(format!("packetdump-carve-{}", std::process::id()));
+        let mut carver = PayloadCarver::new(&dir).unwrap();
+
+        let request = b"GET /files/../../etc/report.txt?download=1 HTTP/1.1\r\n\r\n";
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+        let mut carved = vec![];
+        for id in 0..2 {
+            for packet in [
+                build_test_packet(2 * id, 4444, 80, request),
+                build_test_packet(2 * id + 1, 80, 4444, response),
+            ] {
+                carved.push(carver.carve(&packet).unwrap());
+            }
+        }
+
+        assert_eq!(
+            carved,
+            vec![
+                None,
+                Some(dir.join("report.txt")),
+                None,
+                Some(dir.join("report-1.txt")),
+            ]
+        );
+        assert_eq!(fs::read(dir.join("report-1.txt")).unwrap(), b"hello");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn chunked_text_body_carved_raw() {
+        let dir = env::temp_dir().join(format!("packetdump-carve-raw-{}", std::process::id()));
+        let mut carver = PayloadCarver::new(&dir).unwrap();
+
+        let request = b"GET /caf.txt HTTP/1.1\r\n\r\n";
+        let response = b"HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=iso-8859-1\r\n\
+            Transfer-Encoding: chunked\r\n\r\n3\r\ncaf\r\n1\r\n\xe9\r\n0\r\n\r\n";
+        carver
+            .carve(&build_test_packet(0, 4444, 80, request))
+            .unwrap();
+        let carved = carver
+            .carve(&build_test_packet(1, 80, 4444, response))
+            .unwrap();
+
+        assert_eq!(carved, Some(dir.join("caf.txt")));
+        assert_eq!(fs::read(dir.join("caf.txt")).unwrap(), b"caf\xe9");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn file_names_sanitized() {
+        assert_eq!(sanitized_file_name("/"), "body");
+        assert_eq!(sanitized_file_name("/.."), "body");
+        assert_eq!(sanitized_file_name("/a/..%2f..%2fetc"), "_2f.._2fetc");
+        assert_eq!(sanitized_file_name("/.htaccess"), "htaccess");
+        assert_eq!(
+            sanitized_file_name("/img/logo image.png#top"),
+            "logo_image.png"
+        );
+        assert_eq!(sanitized_file_name("..\\..\\boot.ini"), "_.._boot.ini");
+    }
+
+    ///////////////////// Utils
+
+    /// Build an Ethernet frame carrying an IPv4 packet with a TCP segment, parsed
+    fn build_test_packet(id: usize, source: u16, destination: u16, payload: &[u8]) -> ParsedPacket {
+        let (client, server) = match destination {
+            80 => ((CLIENT, source), (SERVER, destination)),
+            _ => ((SERVER, source), (CLIENT, destination)),
+        };
+        build_test_tcp_packet(id, client, server, TcpFlags::ACK | TcpFlags::PSH, payload)
+    }
+}
@@ -0,0 +1,685 @@
+//! Command line options of packetdump
+
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::LevelFilter;
+use sniffer_parser::decode_as::DecodeAsRule;
+use sniffer_parser::filter::PacketFilter;
+use sniffer_parser::TimeWindow;
+
+use crate::color::ColorMode;
+use crate::output::{JsonFields, LayerSelection, OutputFormat};
+use crate::trigger::TriggerConfig;
+
+pub const USAGE: &str =
+    "USAGE: packetdump [OPTIONS] <NETWORK INTERFACE> | packetdump [OPTIONS] -r <PCAP FILE | ->
+       packetdump [OPTIONS] --ip <ADDR>
+       packetdump [OPTIONS] --merge <PCAP FILE>...
+       packetdump --replay <NETWORK INTERFACE> [--speed <FACTOR>] -r <PCAP FILE>
+
+OPTIONS:
+    --ip <ADDR>                    Capture on the network interface having this IP address
+    --format <FORMAT>              Output format: text, explain (text describing each header
+                                   field), json (one object per line), json-pretty, json-array
+                                   (a single array), msgpack (length-delimited records) or csv
+                                   (a header line, then a row per packet) (default: text)
+    --explain                      Describe the meaning of each header field, same as
+                                   --format explain
+    --color <auto|always|never>    Color the output of each layer (default: auto)
+    --layers <LAYERS>              Print only these layers, comma-separated among link, network,
+                                   transport and application (default: all of them)
+    --log-level <LEVEL>            Log messages up to this level: off, error, warn, info, debug,
+                                   trace (default: RUST_LOG, or warn)
+    --start-trigger <FILTER>       Emit packets from the first one matching the filter
+    --stop-trigger <FILTER>        Stop after the first emitted packet matching the filter
+    --pre-trigger <COUNT>          Also emit this many packets preceding the start trigger
+    --hierarchy                    Print the protocol hierarchy of the emitted packets at the
+                                   end of the capture, instead of the packets
+    --meter                        Show the packet and byte rates over the last 10 s, with the
+                                   top protocol, on a single updating line
+    --top <N>                      Print the N IP addresses sending and receiving the most
+                                   bytes at the end of the capture, instead of the packets
+    --anonymize                    Replace the IP and MAC addresses by consistent,
+                                   prefix-preserving pseudonyms
+    --keep-oui                     With --anonymize, keep the vendor part of the MAC addresses
+    --ebpf-offload-check           Flag the invalid checksums of the hosts never sending a valid
+                                   one as offloaded to the NIC, rather than corrupted
+    --json-fields <FIELDS>         With a JSON format, keep only these fields, comma-separated
+                                   (e.g. source,destination), at any depth of the objects
+    --strings                      Show the printable strings of the payloads left unparsed
+    --redact-credentials           Replace the HTTP cookie values and credentials by their length
+    --decode-as <RULE>             Parse the packets of a port or flow with a dissector, whatever
+                                   their ports, e.g. tcp.port==9999,http or
+                                   udp.flow==10.0.0.1:5000-10.0.0.2:514,syslog (repeatable)
+    --profile                      Print the time spent parsing each layer at the end of the
+                                   capture, on standard error
+    --flush-every <N>              Flush the output every N packets (default: 1 on a terminal,
+                                   64 otherwise)
+    --filter <FILTER>              Emit only the packets matching the filter
+    --dns-only, --http-only,       Emit only the DNS, HTTP, TLS or Modbus packets; combined,
+    --tls-only, --modbus-only      emit the packets of any of these protocols
+    -v, --invert-match             Emit only the packets not selected by --filter and the
+                                   --<protocol>-only flags, e.g. -v --dns-only for all but DNS
+    --reassemble-only              Emit only the packets completing an application message,
+                                   carrying the whole message, rather than every segment
+    --dump-payload-to <DIR>        Write the body of every HTTP response to a file of this
+                                   directory, named after the path of its request
+    --capture-then-filter          Keep every packet in memory, then print the ones matching
+                                   each filter read from standard input (an empty line
+                                   printing all of them), until its end
+    --since <TIME>                 With -r, skip the packets captured before this time, in
+                                   RFC 3339 (e.g. 2024-05-01T12:00:00Z) or epoch seconds
+    --until <TIME>                 With -r, stop at the first packet captured after this time
+    --merge <PCAP FILE>...         Read the packets of these pcap files, up to the next option,
+                                   merged in timestamp order
+    --replay <NETWORK INTERFACE>   Send the Ethernet frames of the pcap file on the interface, at
+                                   their original timing, instead of printing them
+    --speed <FACTOR>               With --replay, speed up the timing by this factor (default: 1)
+
+SIGUSR1 prints the protocol hierarchy or the top talkers of the packets so far on standard error,
+without stopping the capture.
+
+FILTER: protocols (tcp, udp, dns, ...), host <ADDR>, port <PORT>, optionally prefixed with
+src/dst, negated with not and combined with and, e.g. \"tcp and dst port 80\"";
+
+/// Protocol of the packets kept by a `--<protocol>-only` flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolFocus {
+    Dns,
+    Http,
+    Tls,
+    Modbus,
+}
+
+/// Options given on the command line
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub interface: Option<String>,
+    /// Address of the network interface to capture on
+    pub interface_ip: Option<IpAddr>,
+    pub pcap_file: Option<String>,
+    /// Pcap files read merged in timestamp order
+    pub merge: Vec<String>,
+    pub format: OutputFormat,
+    pub color: ColorMode,
+    pub layers: LayerSelection,
+    pub json_fields: Option<JsonFields>,
+    pub log_level: Option<LevelFilter>,
+    pub trigger: TriggerConfig,
+    pub hierarchy: bool,
+    pub meter: bool,
+    /// Number of top talkers reported instead of the packets
+    pub top: Option<usize>,
+    pub anonymize: bool,
+    pub keep_oui: bool,
+    pub offload_check: bool,
+    pub strings: bool,
+    pub redact_credentials: bool,
+    /// Dissectors forced on some ports or flows, in priority order
+    pub decode_as: Vec<DecodeAsRule>,
+    /// Time the parse of each layer
+    pub profile: bool,
+    /// Filter of the emitted packets
+    pub filter: Option<PacketFilter>,
+    /// Protocols of the emitted packets, all of them when empty
+    pub focus: Vec<ProtocolFocus>,
+    /// Emit the packets not selected by the filter and the focused protocols instead
+    pub invert_match: bool,
+    /// Emit only the packets carrying a complete application message
+    pub reassemble_only: bool,
+    /// Directory of the files of the extracted HTTP response bodies
+    pub dump_payload_to: Option<String>,
+    /// Keep the packets in a capture session, filtered after the capture
+    pub capture_then_filter: bool,
+    /// Number of packets written between two flushes of the output
+    pub flush_every: Option<usize>,
+    /// Capture times of the packets read from the pcap file
+    pub window: TimeWindow,
+    /// Interface on which the pcap file is replayed
+    pub replay: Option<String>,
+    pub speed: f64,
+}
+
+/// Parse the command line arguments, program name excluded
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut options = Options {
+        interface: None,
+        interface_ip: None,
+        pcap_file: None,
+        merge: vec![],
+        format: OutputFormat::Text,
+        color: ColorMode::Auto,
+        layers: LayerSelection::default(),
+        json_fields: None,
+        log_level: None,
+        trigger: TriggerConfig::default(),
+        hierarchy: false,
+        meter: false,
+        top: None,
+        anonymize: false,
+        keep_oui: false,
+        offload_check: false,
+        strings: false,
+        redact_credentials: false,
+        decode_as: vec![],
+        profile: false,
+        filter: None,
+        focus: vec![],
+        invert_match: false,
+        reassemble_only: false,
+        dump_payload_to: None,
+        capture_then_filter: false,
+        flush_every: None,
+        window: TimeWindow::default(),
+        replay: None,
+        speed: 1.0,
+    };
+    let mut args = args.into_iter().peekable();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_owned(), Some(value.to_owned()))
+            }
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or(format!("missing value for {}", name))
+        };
+
+        match flag.as_str() {
+            "-r" => options.pcap_file = Some(value("-r")?),
+            "--merge" => {
+                options.merge.push(value("--merge")?);
+                while let Some(file_name) = args.next_if(|arg| !arg.starts_with('-')) {
+                    options.merge.push(file_name);
+                }
+            }
+            "--format" => options.format = value("--format")?.parse()?,
+            "--explain" => options.format = OutputFormat::Explain,
+            "--color" => options.color = value("--color")?.parse()?,
+            "--layers" => options.layers = value("--layers")?.parse()?,
+            "--log-level" => {
+                let level = value("--log-level")?;
+                options.log_level = Some(
+                    level
+                        .parse()
+                        .map_err(|_| format!("invalid log level: {}", level))?,
+                );
+            }
+            "--start-trigger" => options.trigger.start = Some(value("--start-trigger")?.parse()?),
+            "--stop-trigger" => options.trigger.stop = Some(value("--stop-trigger")?.parse()?),
+            "--pre-trigger" => {
+                let count = value("--pre-trigger")?;
+                options.trigger.pre_trigger = count
+                    .parse()
+                    .map_err(|_| format!("invalid packet count: {}", count))?;
+            }
+            "--hierarchy" => options.hierarchy = true,
+            "--meter" => options.meter = true,
+            "--top" => {
+                let count = value("--top")?;
+                options.top = Some(
+                    count
+                        .parse()
+                        .ok()
+                        .filter(|&count| count > 0)
+                        .ok_or(format!("invalid top talkers count: {}", count))?,
+                );
+            }
+            "--anonymize" => options.anonymize = true,
+            "--keep-oui" => options.keep_oui = true,
+            "--ebpf-offload-check" => options.offload_check = true,
+            "--json-fields" => options.json_fields = Some(value("--json-fields")?.parse()?),
+            "--strings" => options.strings = true,
+            "--redact-credentials" => options.redact_credentials = true,
+            "--decode-as" => options.decode_as.push(value("--decode-as")?.parse()?),
+            "--profile" => options.profile = true,
+            "--filter" => options.filter = Some(value("--filter")?.parse()?),
+            "--dns-only" | "--http-only" | "--tls-only" | "--modbus-only" => {
+                let protocol = match flag.as_str() {
+                    "--dns-only" => ProtocolFocus::Dns,
+                    "--http-only" => ProtocolFocus::Http,
+                    "--tls-only" => ProtocolFocus::Tls,
+                    _ => ProtocolFocus::Modbus,
+                };
+                if !options.focus.contains(&protocol) {
+                    options.focus.push(protocol);
+                }
+            }
+            "-v" | "--invert-match" => options.invert_match = true,
+            "--reassemble-only" => options.reassemble_only = true,
+            "--dump-payload-to" => options.dump_payload_to = Some(value("--dump-payload-to")?),
+            "--capture-then-filter" => options.capture_then_filter = true,
+            "--flush-every" => {
+                let count = value("--flush-every")?;
+                options.flush_every = Some(
+                    count
+                        .parse()
+                        .ok()
+                        .filter(|&count| count > 0)
+                        .ok_or(format!("invalid packet count: {}", count))?,
+                );
+            }
+            "--since" => options.window.since = Some(parse_timestamp(&value("--since")?)?),
+            "--until" => options.window.until = Some(parse_timestamp(&value("--until")?)?),
+            "--replay" => options.replay = Some(value("--replay")?),
+            "--ip" => {
+                let address = value("--ip")?;
+                options.interface_ip = Some(
+                    address
+                        .parse()
+                        .map_err(|_| format!("invalid IP address: {}", address))?,
+                );
+            }
+            "--speed" => {
+                let speed = value("--speed")?;
+                options.speed = speed
+                    .parse()
+                    .ok()
+                    .filter(|speed: &f64| *speed > 0.0 && speed.is_finite())
+                    .ok_or(format!("invalid speed factor: {}", speed))?;
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option: {}", flag)),
+            _ => options.interface = Some(arg),
+        }
+    }
+
+    if options.interface.is_some() && options.interface_ip.is_some() {
+        return Err("--ip excludes a network interface".to_owned());
+    }
+    if options.interface.is_none()
+        && options.interface_ip.is_none()
+        && options.pcap_file.is_none()
+        && options.merge.is_empty()
+    {
+        return Err("missing network interface or pcap file".to_owned());
+    }
+    if !options.merge.is_empty()
+        && (options.interface.is_some()
+            || options.interface_ip.is_some()
+            || options.pcap_file.is_some())
+    {
+        return Err("--merge excludes -r and a network interface".to_owned());
+    }
+    if [options.hierarchy, options.meter, options.top.is_some()]
+        .iter()
+        .filter(|&&summary| summary)
+        .count()
+        > 1
+    {
+        return Err("--hierarchy, --meter and --top are exclusive".to_owned());
+    }
+    if options.capture_then_filter && (options.hierarchy || options.meter || options.top.is_some())
+    {
+        return Err("--capture-then-filter excludes --hierarchy, --meter and --top".to_owned());
+    }
+    if options.capture_then_filter && options.pcap_file.as_deref() == Some("-") {
+        return Err("--capture-then-filter reads the filters from standard input".to_owned());
+    }
+    if options.window != TimeWindow::default() && options.pcap_file.is_none() {
+        return Err("--since and --until need -r".to_owned());
+    }
+    if let (Some(since), Some(until)) = (options.window.since, options.window.until) {
+        if since > until {
+            return Err("--since is after --until".to_owned());
+        }
+    }
+    if options.replay.is_some() && options.pcap_file.is_none() {
+        return Err("--replay needs a pcap file".to_owned());
+    }
+    if options.json_fields.is_some()
+        && matches!(
+            options.format,
+            OutputFormat::Text | OutputFormat::Explain | OutputFormat::Msgpack | OutputFormat::Csv
+        )
+    {
+        return Err("--json-fields needs a JSON format".to_owned());
+    }
+
+    Ok(options)
+}
+
+/// Parse a time in RFC 3339 (e.g. `2024-05-01T12:00:00.5+02:00`) or in seconds since the epoch
+fn parse_timestamp(value: &str) -> Result<SystemTime, String> {
+    let timestamp = match value.parse::<f64>() {
+        Ok(seconds) => Duration::try_from_secs_f64(seconds)
+            .ok()
+            .and_then(|since_epoch| UNIX_EPOCH.checked_add(since_epoch)),
+        Err(_) => parse_rfc3339(value),
+    };
+    timestamp.ok_or(format!("invalid timestamp: {}", value))
+}
+
+fn parse_rfc3339(value: &str) -> Option<SystemTime> {
+    let (date, time) = value.split_once(['T', 't', ' '])?;
+    let mut date_fields = date.splitn(3, '-');
+    let year: i64 = date_fields.next()?.parse().ok()?;
+    let month: i64 = date_fields.next()?.parse().ok()?;
+    let day: i64 = date_fields.next()?.parse().ok()?;
+
+    let (time, offset) = match time.strip_suffix(['Z', 'z']) {
+        Some(time) => (time, 0),
+        None => {
+            let (time, offset) = time.split_at(time.rfind(['+', '-'])?);
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let (hours, minutes): (i64, i64) = (hours.parse().ok()?, minutes.parse().ok()?);
+            if !(0..=23).contains(&hours) || !(0..=59).contains(&minutes) {
+                return None;
+            }
+            let seconds = hours * 3600 + minutes * 60;
+            match offset.starts_with('-') {
+                true => (time, -seconds),
+                false => (time, seconds),
+            }
+        }
+    };
+    let mut time_fields = time.splitn(3, ':');
+    let hour: i64 = time_fields.next()?.parse().ok()?;
+    let minute: i64 = time_fields.next()?.parse().ok()?;
+    let second = time_fields.next()?;
+    let (second, fraction) = second.split_once('.').unwrap_or((second, ""));
+    let second: i64 = second.parse().ok()?;
+    if !(0..=9999).contains(&year) || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    if !(0..=23).contains(&hour) || !(0..=59).contains(&minute) || !(0..=60).contains(&second) {
+        return None;
+    }
+    if !fraction.bytes().all(|digit| digit.is_ascii_digit()) {
+        return None;
+    }
+    // Nanoseconds from the first 9 digits of the fraction
+    let nanoseconds = format!("{:0<9}", &fraction[..fraction.len().min(9)])
+        .parse()
+        .ok()?;
+
+    let seconds =
+        days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second - offset;
+    UNIX_EPOCH.checked_add(Duration::new(u64::try_from(seconds).ok()?, nanoseconds))
+}
+
+/// Get the number of days between the epoch and a date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use log::LevelFilter;
+
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use sniffer_parser::TimeWindow;
+
+    use super::{parse_args, parse_timestamp, Options, ProtocolFocus};
+    use crate::color::ColorMode;
+    use crate::output::{LayerSelection, OutputFormat};
+    use crate::trigger::TriggerConfig;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_interface_and_color() {
+        assert_eq!(
+            parse_args(args(&["--color", "never", "eth0"])),
+            Ok(Options {
+                interface: Some("eth0".to_owned()),
+                interface_ip: None,
+                pcap_file: None,
+                merge: vec![],
+                format: OutputFormat::Text,
+                color: ColorMode::Never,
+                layers: LayerSelection::default(),
+                json_fields: None,
+                log_level: None,
+                trigger: TriggerConfig::default(),
+                hierarchy: false,
+                meter: false,
+                top: None,
+                anonymize: false,
+                keep_oui: false,
+                offload_check: false,
+                strings: false,
+                redact_credentials: false,
+                decode_as: vec![],
+                profile: false,
+                filter: None,
+                focus: vec![],
+                invert_match: false,
+                reassemble_only: false,
+                dump_payload_to: None,
+                capture_then_filter: false,
+                flush_every: None,
+                window: TimeWindow::default(),
+                replay: None,
+                speed: 1.0,
+            })
+        );
+        assert_eq!(
+            parse_args(args(&["-r", "capture.pcap", "--color=always"])).map(|o| o.color),
+            Ok(ColorMode::Always)
+        );
+        assert_eq!(
+            parse_args(args(&["--explain", "eth0"])).map(|o| o.format),
+            Ok(OutputFormat::Explain)
+        );
+        assert_eq!(
+            parse_args(args(&["--log-level=debug", "eth0"])).map(|o| o.log_level),
+            Ok(Some(LevelFilter::Debug))
+        );
+        assert_eq!(
+            parse_args(args(&["--format", "json-pretty", "eth0"])).map(|o| o.format),
+            Ok(OutputFormat::JsonPretty)
+        );
+        assert_eq!(
+            parse_args(args(&["--layers", "network,transport", "eth0"])).map(|o| o.layers),
+            Ok(LayerSelection {
+                link: false,
+                network: true,
+                transport: true,
+                application: false,
+            })
+        );
+        assert_eq!(
+            parse_args(args(&["--hierarchy", "-r", "capture.pcap"])).map(|o| o.hierarchy),
+            Ok(true)
+        );
+        assert_eq!(
+            parse_args(args(&["--meter", "eth0"])).map(|o| o.meter),
+            Ok(true)
+        );
+        assert_eq!(
+            parse_args(args(&["--top", "5", "-r", "capture.pcap"])).map(|o| o.top),
+            Ok(Some(5))
+        );
+        assert_eq!(
+            parse_args(args(&["--anonymize", "--keep-oui", "eth0"]))
+                .map(|o| (o.anonymize, o.keep_oui)),
+            Ok((true, true))
+        );
+        assert_eq!(
+            parse_args(args(&["--ebpf-offload-check", "eth0"])).map(|o| o.offload_check),
+            Ok(true)
+        );
+        assert_eq!(
+            parse_args(args(&["--strings", "eth0"])).map(|o| o.strings),
+            Ok(true)
+        );
+        assert_eq!(
+            parse_args(args(&[
+                "--decode-as",
+                "tcp.port==9999,http",
+                "--decode-as=udp.port==5514,syslog",
+                "eth0"
+            ]))
+            .map(|o| o.decode_as.len()),
+            Ok(2)
+        );
+        assert_eq!(
+            parse_args(args(&["--dns-only", "--tls-only", "--dns-only", "eth0"])).map(|o| o.focus),
+            Ok(vec![ProtocolFocus::Dns, ProtocolFocus::Tls])
+        );
+        assert_eq!(
+            parse_args(args(&["--filter", "tcp and port 80", "eth0"])).map(|o| o.filter),
+            Ok(Some("tcp and port 80".parse().unwrap()))
+        );
+        assert_eq!(
+            parse_args(args(&["-v", "eth0"])).map(|o| o.invert_match),
+            Ok(true)
+        );
+        assert!(parse_args(args(&["--filter", "port http", "eth0"])).is_err());
+        assert_eq!(
+            parse_args(args(&["--reassemble-only", "eth0"])).map(|o| o.reassemble_only),
+            Ok(true)
+        );
+        assert_eq!(
+            parse_args(args(&["--dump-payload-to", "bodies", "eth0"])).map(|o| o.dump_payload_to),
+            Ok(Some("bodies".to_owned()))
+        );
+        assert_eq!(
+            parse_args(args(&["--capture-then-filter", "eth0"])).map(|o| o.capture_then_filter),
+            Ok(true)
+        );
+        assert_eq!(
+            parse_args(args(&["--flush-every", "16", "eth0"])).map(|o| o.flush_every),
+            Ok(Some(16))
+        );
+        assert_eq!(
+            parse_args(args(&[
+                "--format=json",
+                "--json-fields",
+                "source,destPort",
+                "eth0"
+            ]))
+            .map(|o| o.json_fields),
+            Ok(Some("source,destPort".parse().unwrap()))
+        );
+        assert_eq!(
+            parse_args(args(&[
+                "--replay",
+                "eth0",
+                "--speed=2.5",
+                "-r",
+                "capture.pcap"
+            ]))
+            .map(|o| (o.replay, o.speed)),
+            Ok((Some("eth0".to_owned()), 2.5))
+        );
+        assert_eq!(
+            parse_args(args(&["--ip", "192.168.1.10"])).map(|o| o.interface_ip),
+            Ok(Some("192.168.1.10".parse().unwrap()))
+        );
+        assert_eq!(
+            parse_args(args(&["--merge", "a.pcap", "b.pcap", "--format=json"]))
+                .map(|o| (o.merge, o.format)),
+            Ok((
+                vec!["a.pcap".to_owned(), "b.pcap".to_owned()],
+                OutputFormat::Json
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_triggers() {
+        let options = parse_args(args(&[
+            "--start-trigger",
+            "tcp and dst port 80",
+            "--stop-trigger=dns",
+            "--pre-trigger",
+            "10",
+            "eth0",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            options.trigger.start.map(|f| f.to_string()),
+            Some("tcp and dst port 80".to_owned())
+        );
+        assert_eq!(
+            options.trigger.stop.map(|f| f.to_string()),
+            Some("dns".to_owned())
+        );
+        assert_eq!(options.trigger.pre_trigger, 10);
+        assert!(parse_args(args(&["--start-trigger", "port", "eth0"])).is_err());
+    }
+
+    #[test]
+    fn parse_time_window() {
+        assert_eq!(
+            parse_args(args(&[
+                "--since",
+                "2024-05-01T12:00:00Z",
+                "--until=1714568400.5",
+                "-r",
+                "capture.pcap"
+            ]))
+            .map(|o| o.window),
+            Ok(TimeWindow {
+                since: Some(UNIX_EPOCH + Duration::from_secs(1_714_564_800)),
+                until: Some(UNIX_EPOCH + Duration::from_millis(1_714_568_400_500)),
+            })
+        );
+        assert_eq!(
+            parse_timestamp("2024-05-01T14:30:00.25+02:00"),
+            Ok(UNIX_EPOCH + Duration::from_millis(1_714_566_600_250))
+        );
+        assert_eq!(
+            parse_timestamp("1970-01-01t00:00:00-01:00"),
+            Ok(UNIX_EPOCH + Duration::from_secs(3600))
+        );
+        assert!(parse_timestamp("2024-13-01T00:00:00Z").is_err());
+        assert!(parse_timestamp("2024-05-01").is_err());
+        assert!(parse_timestamp("yesterday").is_err());
+        for out_of_range in [
+            "1e30",
+            "-1",
+            "NaN",
+            "inf",
+            "2024-05-01T00:00:00+9999999999999999:00",
+            "2024-05-01T00:00:00-24:00",
+            "999999999999999-05-01T00:00:00Z",
+            "2024-05-01T99999999999999999:00:00Z",
+        ] {
+            assert!(parse_timestamp(out_of_range).is_err(), "{}", out_of_range);
+        }
+        assert!(parse_args(args(&["--since", "100", "eth0"])).is_err());
+        assert!(parse_args(args(&["--since=200", "--until=100", "-r", "capture.pcap"])).is_err());
+    }
+
+    #[test]
+    fn parse_invalid_arguments() {
+        assert!(parse_args(args(&[])).is_err());
+        assert!(parse_args(args(&["--color", "rainbow", "eth0"])).is_err());
+        assert!(parse_args(args(&["--unknown", "eth0"])).is_err());
+        assert!(parse_args(args(&["-r"])).is_err());
+        assert!(parse_args(args(&["--log-level", "loud", "eth0"])).is_err());
+        assert!(parse_args(args(&["--hierarchy", "--meter", "eth0"])).is_err());
+        assert!(parse_args(args(&["--top", "0", "eth0"])).is_err());
+        assert!(parse_args(args(&["--top", "3", "--meter", "eth0"])).is_err());
+        assert!(parse_args(args(&["--layers", "physical", "eth0"])).is_err());
+        assert!(parse_args(args(&["--replay", "eth0"])).is_err());
+        assert!(parse_args(args(&["--json-fields", "source", "eth0"])).is_err());
+        assert!(parse_args(args(&["--speed", "0", "-r", "capture.pcap"])).is_err());
+        assert!(parse_args(args(&["--merge"])).is_err());
+        assert!(parse_args(args(&["--merge", "a.pcap", "-r", "b.pcap"])).is_err());
+        assert!(parse_args(args(&["--capture-then-filter", "--meter", "eth0"])).is_err());
+        assert!(parse_args(args(&["--capture-then-filter", "-r", "-"])).is_err());
+        assert!(parse_args(args(&["--flush-every", "0", "eth0"])).is_err());
+        assert!(parse_args(args(&["--ip", "192.168.1"])).is_err());
+        assert!(parse_args(args(&["--decode-as", "tcp.port==9999", "eth0"])).is_err());
+        assert!(parse_args(args(&["--ip", "192.168.1.10", "eth0"])).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! ANSI coloring of the per-layer packet output
+
+use std::env;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+const RESET: &str = "\x1b[0m";
+
+/// When to color the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            other => Err(format!(
+                "invalid color mode: {} (expected auto, always or never)",
+                other
+            )),
+        }
+    }
+}
+
+impl ColorMode {
+    /// Decide whether `Auto` colors stdout, honoring `NO_COLOR`
+    pub fn resolve(self) -> ColorMode {
+        let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        self.resolve_with(no_color, io::stdout().is_terminal())
+    }
+
+    fn resolve_with(self, no_color: bool, is_tty: bool) -> ColorMode {
+        match self {
+            ColorMode::Auto if !no_color && is_tty => ColorMode::Always,
+            ColorMode::Auto => ColorMode::Never,
+            mode => mode,
+        }
+    }
+
+    /// Wrap the text in the color of its layer, only when the mode is `Always`
+    pub fn colorize(self, layer: Layer, text: &str) -> String {
+        match self {
+            ColorMode::Always => format!("{}{}{}", layer.ansi_code(), text, RESET),
+            _ => text.to_owned(),
+        }
+    }
+}
+
+/// Protocol families of the TCP/IP stack, each one printed with its own color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Link,
+    Network,
+    Transport,
+    Application,
+}
+
+impl Layer {
+    fn ansi_code(self) -> &'static str {
+        match self {
+            Layer::Link => "\x1b[34m",
+            Layer::Network => "\x1b[32m",
+            Layer::Transport => "\x1b[33m",
+            Layer::Application => "\x1b[35m",
+        }
+    }
+}
+
+/// Render a parsed packet like its `Display` implementation, coloring each layer
+pub fn format_parsed_packet(packet: &ParsedPacket, mode: ColorMode) -> String {
+    let mut output = format_capture_header(packet);
+
+    for (label, layer, layer_packet) in labelled_layers(packet) {
+        let text = match layer_packet {
+            Some(layer_packet) => format!("{}: {}", label, layer_packet),
+            None => format!("{}: None", label),
+        };
+        output.push_str(&mode.colorize(layer, &text));
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Render the layers of a packet with each header field followed by a short description of its
+/// meaning, the representations without explanation being rendered as usual
+pub fn format_explained_packet(packet: &ParsedPacket, mode: ColorMode) -> String {
+    let mut output = format_capture_header(packet);
+
+    for (label, layer, layer_packet) in labelled_layers(packet) {
+        let text = match layer_packet.map(|layer_packet| (layer_packet, layer_packet.explain())) {
+            Some((layer_packet, explanation)) if explanation.is_empty() => {
+                format!("{}: {}", label, layer_packet)
+            }
+            Some((layer_packet, explanation)) => {
+                let mut text = format!("{}: {} Packet:", label, layer_packet.protocol_name());
+                for (field, value, description) in explanation {
+                    text.push_str(&format!("\n\t{}: {} ({})", field, value, description));
+                }
+                text
+            }
+            None => format!("{}: None", label),
+        };
+        output.push_str(&mode.colorize(layer, &text));
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Render the lines preceding the layers of a packet: its capture time, the index of the file it
+/// was read from and whether its capture is truncated
+fn format_capture_header(packet: &ParsedPacket) -> String {
+    let mut output = String::new();
+
+    if let Some(timestamp) = packet.get_timestamp() {
+        let since_epoch = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        output.push_str(&format!(
+            "Timestamp: {}.{:06}\n",
+            since_epoch.as_secs(),
+            since_epoch.subsec_micros()
+        ));
+    }
+    if let Some(source_index) = packet.get_source_index() {
+        output.push_str(&format!("Source File: {}\n", source_index));
+    }
+    if packet.is_truncated_capture() {
+        output.push_str("Truncated Capture\n");
+    }
+
+    output
+}
+
+/// Get the layers of a packet along with their label
+fn labelled_layers(
+    packet: &ParsedPacket,
+) -> [(&'static str, Layer, Option<&SerializablePacket>); 4] {
+    [
+        (
+            "Link Layer Packet",
+            Layer::Link,
+            packet.get_link_layer_packet(),
+        ),
+        (
+            "   Network Layer Packet",
+            Layer::Network,
+            packet.get_network_layer_packet(),
+        ),
+        (
+            "       Transport Layer Packet",
+            Layer::Transport,
+            packet.get_transport_layer_packet(),
+        ),
+        (
+            "           Application Layer Packet",
+            Layer::Application,
+            packet.get_application_layer_packet(),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use sniffer_parser::serializable_packet::ParsedPacket;
+
+    use super::{format_explained_packet, format_parsed_packet, ColorMode, Layer};
+
+    #[test]
+    fn never_has_no_escape_codes() {
+        assert_eq!(
+            ColorMode::Never.colorize(Layer::Link, "Ethernet"),
+            "Ethernet"
+        );
+    }
+
+    #[test]
+    fn always_wraps_with_escape_codes() {
+        assert_eq!(
+            ColorMode::Always.colorize(Layer::Network, "IPv4"),
+            "\x1b[32mIPv4\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn auto_resolution() {
+        assert_eq!(ColorMode::Auto.resolve_with(false, true), ColorMode::Always);
+        assert_eq!(ColorMode::Auto.resolve_with(true, true), ColorMode::Never);
+        assert_eq!(ColorMode::Auto.resolve_with(false, false), ColorMode::Never);
+        assert_eq!(
+            ColorMode::Always.resolve_with(true, false),
+            ColorMode::Always
+        );
+    }
+
+    #[test]
+    fn uncolored_output_matches_display() {
+        let mut packet = ParsedPacket::new(0);
+        packet.set_timestamp(Some(UNIX_EPOCH + Duration::from_micros(1_500_000)));
+
+        assert_eq!(
+            format_parsed_packet(&packet, ColorMode::Never),
+            packet.to_string()
+        );
+        assert!(format_parsed_packet(&packet, ColorMode::Always).contains("\x1b[35m"));
+    }
+
+    #[test]
+    fn explained_output_keeps_capture_header() {
+        let mut packet = ParsedPacket::new(0);
+        packet.set_timestamp(Some(UNIX_EPOCH + Duration::from_micros(1_500_000)));
+        packet.set_source_index(Some(1));
+        packet.set_truncated_capture(true);
+
+        assert!(format_explained_packet(&packet, ColorMode::Never)
+            .starts_with("Timestamp: 1.500000\nSource File: 1\nTruncated Capture\nLink Layer"));
+    }
+}
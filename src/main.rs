@@ -10,56 +10,896 @@
 extern crate pnet;
 extern crate sniffer_parser;
 
+mod carve;
+mod cli;
+mod color;
+mod output;
+mod replay;
+mod session;
+#[cfg(test)]
+mod test_util;
+mod trigger;
 
-use sniffer_parser::parse_ethernet_frame;
+use sniffer_parser::anonymize::Anonymizer;
+use sniffer_parser::arp_monitor::ArpMonitor;
+use sniffer_parser::dns_tracker::DnsTracker;
+use sniffer_parser::filter::PacketFilter;
+use sniffer_parser::hierarchy::ProtocolHierarchy;
+use sniffer_parser::http_tracker::HttpTracker;
+use sniffer_parser::merge::PcapMerger;
+use sniffer_parser::meter::ThroughputMeter;
+use sniffer_parser::modbus_tracker::ModbusTransactionTracker;
+use sniffer_parser::offload::ChecksumOffloadDetector;
+use sniffer_parser::pipeline::Pipeline;
+use sniffer_parser::profile::take_parse_profile;
+use sniffer_parser::serializable_packet::util::{
+    contains_dns, contains_http, contains_modbus, contains_tls,
+};
+use sniffer_parser::serializable_packet::ParsedPacket;
+use sniffer_parser::tcp_seq_tracker::TcpSeqTracker;
+use sniffer_parser::top_talkers::TopTalkers;
+use sniffer_parser::{
+    is_pcapng, parse_ethernet_frame, parse_pcap_record, prune_stale, PcapReader, PcapngReader,
+    TimeWindow,
+};
 
 use pnet::datalink::{self, NetworkInterface};
 
 use pnet::packet::ethernet::EthernetPacket;
 
+use carve::PayloadCarver;
+use cli::{parse_args, ProtocolFocus};
+use color::ColorMode;
+use output::{LayerSelection, OutputFormat, PacketWriter};
+use replay::replay_pcap_file;
+use session::{write_packets, CaptureSession};
+use trigger::Trigger;
+
 use std::env;
-use std::io::{self, Write};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::iter;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 
 use std::process;
 
-fn main() {
-    use pnet::datalink::Channel::Ethernet;
+use env_logger::Env;
+use log::{warn, LevelFilter};
+
+/// Number of packets between two sweeps of the idle reassembly buffers
+const PRUNE_INTERVAL: usize = 1000;
+/// Capture time after which an idle reassembly buffer is dropped
+const PARSER_MAX_AGE: Duration = Duration::from_secs(120);
+/// Time after which an unanswered DNS query is forgotten
+const DNS_QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time after which an unanswered HTTP request is forgotten
+const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Time after which an unanswered Modbus TCP request is orphaned
+const MODBUS_TRANSACTION_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time after which an idle TCP connection is forgotten
+const TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+/// Time after which an IP address may be bound to another MAC address without conflict
+const ARP_REBIND_AFTER: Duration = Duration::from_secs(3600);
+/// Minimum length of the strings extracted from the unparsed payloads with --strings
+const STRINGS_MIN_LENGTH: usize = 4;
+/// Minimum time between two refreshes of the meter line
+const METER_REFRESH: Duration = Duration::from_millis(100);
+/// Maximum time waiting for a frame on the interface before checking for an interruption
+const CAPTURE_READ_TIMEOUT: Duration = Duration::from_millis(200);
+/// Number of packets written between two flushes of the output when it is not a terminal
+const PIPE_FLUSH_EVERY: usize = 64;
+
+/// Set on SIGINT, to stop the capture and finish the output
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// Set on SIGUSR1, to print the aggregates of the packets so far, then cleared
+static SNAPSHOT_REQUESTED: AtomicBool = AtomicBool::new(false);
 
-    let iface_name = match env::args().nth(1) {
-        Some(n) => n,
-        None => {
-            writeln!(io::stderr(), "USAGE: packetdump <NETWORK INTERFACE>").unwrap();
+fn main() {
+    let options = parse_args(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("packetdump: {}\n{}", e, cli::USAGE);
+        process::exit(1);
+    });
+    init_logger(options.log_level);
+    if let (Some(iface_name), Some(file_name)) = (&options.replay, &options.pcap_file) {
+        match replay_pcap_file(file_name, iface_name, options.speed) {
+            Ok(sent) => println!("{} packets sent on {}", sent, iface_name),
+            Err(e) => {
+                eprintln!("packetdump: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    if options.strings {
+        sniffer_parser::set_string_extraction(Some(STRINGS_MIN_LENGTH));
+    }
+    sniffer_parser::set_credential_redaction(options.redact_credentials);
+    sniffer_parser::decode_as::set_decode_as_rules(options.decode_as.clone());
+    sniffer_parser::set_parse_profiling(options.profile);
+    sniffer_parser::set_raw_frame_retention(options.capture_then_filter);
+    sniffer_parser::set_ipv6_reassembly(true);
+    let anonymizer = options.anonymize.then(|| Anonymizer::new(options.keep_oui));
+    let flush_every = options
+        .flush_every
+        .unwrap_or(match io::stdout().is_terminal() {
+            true => 1,
+            false => PIPE_FLUSH_EVERY,
+        });
+    let mut sink = match (options.hierarchy, options.meter, options.top) {
+        (true, ..) => Sink::Hierarchy(ProtocolHierarchy::new()),
+        (_, true, _) => Sink::Meter(ThroughputMeter::default(), None, None),
+        (.., Some(top)) => Sink::TopTalkers(TopTalkers::new(top), anonymizer),
+        _ if options.capture_then_filter => Sink::Session {
+            session: CaptureSession::new(options.layers, anonymizer),
+            format: options.format,
+            color: options.color.resolve(),
+        },
+        _ => Sink::Print(
+            PacketWriter::new(
+                BufWriter::new(io::stdout()),
+                options.format,
+                options.color.resolve(),
+                options.json_fields,
+            )
+            .flush_every(flush_every),
+            options.layers,
+            anonymizer,
+        ),
+    };
+    let mut trigger = Trigger::new(options.trigger);
+    ctrlc::set_handler(|| {
+        // A second interruption quits without waiting for the output to be finished
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            process::exit(130);
+        }
+    })
+    .unwrap_or_else(|e| panic!("packetdump: unable to handle SIGINT: {}", e));
+    #[cfg(unix)]
+    request_snapshot_on_sigusr1();
+    let carver = options.dump_payload_to.as_ref().map(|dir| {
+        PayloadCarver::new(dir).unwrap_or_else(|e| {
+            eprintln!("packetdump: unable to create {}: {}", dir, e);
             process::exit(1);
+        })
+    });
+    let analysis = selected(
+        reassembled(
+            analysis_pipeline(options.offload_check, carver),
+            options.reassemble_only,
+        ),
+        &options.focus,
+        options.filter.clone(),
+        options.invert_match,
+    );
+
+    let iface_name = match options.interface_ip {
+        Some(ip) => Some(
+            interface_by_ip(&datalink::interfaces(), ip).unwrap_or_else(|e| {
+                eprintln!("packetdump: {}", e);
+                process::exit(1);
+            }),
+        ),
+        None => options.interface,
+    };
+
+    let packet_count = match options.pcap_file {
+        Some(file_name) => read_pcap_file(
+            &file_name,
+            options.window,
+            analysis,
+            &mut trigger,
+            &mut sink,
+        ),
+        None if !options.merge.is_empty() => {
+            merge_pcap_files(&options.merge, analysis, &mut trigger, &mut sink)
         }
+        None => capture_interface(&iface_name.unwrap(), analysis, &mut trigger, &mut sink),
     };
+
+    sink.finish();
+    if options.profile {
+        eprint!("{}", take_parse_profile());
+    }
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        eprintln!("packetdump: interrupted after {} packets", packet_count);
+    }
+}
+
+/// Destination of the packets emitted by the trigger
+enum Sink<W: Write> {
+    /// Print the selected layers of every packet, anonymized when an anonymizer is given
+    Print(PacketWriter<W>, LayerSelection, Option<Anonymizer>),
+    /// Aggregate the packets, printing their protocol hierarchy at the end of the capture
+    Hierarchy(ProtocolHierarchy),
+    /// Aggregate the packets, anonymized when an anonymizer is given, printing their top talkers
+    /// at the end of the capture
+    TopTalkers(TopTalkers, Option<Anonymizer>),
+    /// Meter the packets, refreshing a line with the current rates; the time of the last refresh
+    /// and the timestamp of the last packet are kept
+    Meter(ThroughputMeter, Option<Instant>, Option<SystemTime>),
+    /// Keep every packet, printing the ones matching the filters read from standard input at the
+    /// end of the capture
+    Session {
+        session: CaptureSession,
+        format: OutputFormat,
+        color: ColorMode,
+    },
+}
+
+impl<W: Write> Sink<W> {
+    fn emit(&mut self, mut packet: ParsedPacket) {
+        match self {
+            Sink::Print(writer, layers, anonymizer) => {
+                layers.apply(&mut packet);
+                if let Some(anonymizer) = anonymizer {
+                    anonymizer.anonymize(&mut packet);
+                }
+                writer
+                    .write(&packet)
+                    .unwrap_or_else(|e| panic!("packetdump: unable to write packet: {}", e));
+            }
+            Sink::Session { session, .. } => session.add(packet),
+            Sink::Hierarchy(hierarchy) => hierarchy.add(&packet),
+            Sink::TopTalkers(top_talkers, anonymizer) => {
+                if let Some(anonymizer) = anonymizer {
+                    anonymizer.anonymize(&mut packet);
+                }
+                top_talkers.add(&packet);
+            }
+            Sink::Meter(meter, last_refresh, last_timestamp) => {
+                meter.add(&packet);
+                *last_timestamp = packet.get_timestamp().or(*last_timestamp);
+                if last_refresh.is_none_or(|refresh| refresh.elapsed() >= METER_REFRESH) {
+                    *last_refresh = Some(Instant::now());
+                    print_throughput(meter, *last_timestamp);
+                }
+            }
+        }
+    }
+
+    /// Get the aggregates of the packets emitted so far, for the sinks aggregating them, without
+    /// resetting them
+    fn snapshot(&self) -> Option<String> {
+        match self {
+            Sink::Hierarchy(hierarchy) => Some(hierarchy.to_string()),
+            Sink::TopTalkers(top_talkers, _) => Some(top_talkers.to_string()),
+            Sink::Session { session, .. } => {
+                Some(format!("packetdump: {} packets captured\n", session.len()))
+            }
+            Sink::Print(..) | Sink::Meter(..) => None,
+        }
+    }
+
+    /// Finish the output: close the JSON array, print the hierarchy, the top talkers or the last
+    /// rates, or filter the capture session
+    fn finish(self) {
+        match self {
+            Sink::Hierarchy(hierarchy) => print!("{}", hierarchy),
+            Sink::TopTalkers(top_talkers, _) => print!("{}", top_talkers),
+            Sink::Meter(meter, _, last_timestamp) => {
+                print_throughput(&meter, last_timestamp);
+                println!();
+            }
+            Sink::Print(mut writer, ..) => writer
+                .finish()
+                .unwrap_or_else(|e| panic!("packetdump: unable to write packet: {}", e)),
+            Sink::Session {
+                mut session,
+                format,
+                color,
+            } => {
+                eprintln!("packetdump: {} packets captured", session.len());
+                filter_session(&mut session, format, color);
+            }
+        }
+    }
+}
+
+/// Print the packets of a capture session matching each filter read from standard input, every
+/// packet for an empty line, until its end
+fn filter_session(session: &mut CaptureSession, format: OutputFormat, color: ColorMode) {
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap_or_else(|e| panic!("packetdump: unable to read filter: {}", e));
+        let written = match line.trim() {
+            "" => session.export(format, io::stdout()),
+            filter => match filter.parse::<PacketFilter>() {
+                Ok(filter) => write_packets(&session.query(&filter), format, color, io::stdout()),
+                Err(e) => {
+                    eprintln!("packetdump: {}", e);
+                    continue;
+                }
+            },
+        };
+        written.unwrap_or_else(|e| panic!("packetdump: unable to write packet: {}", e));
+    }
+}
+
+/// Overwrite the current line with the rates of the window ending at `now`
+fn print_throughput(meter: &ThroughputMeter, now: Option<SystemTime>) {
+    let throughput = meter.throughput(now.unwrap_or_else(SystemTime::now));
+    print!(
+        "\r{:>10.1} pkt/s {:>14.1} B/s   top: {:<12}",
+        throughput.packets_per_second,
+        throughput.bytes_per_second,
+        throughput.top_protocol.unwrap_or("-"),
+    );
+    let _ = io::stdout().flush();
+}
+
+/// Get the name of the only network interface among `interfaces` having an IP address
+fn interface_by_ip(interfaces: &[NetworkInterface], ip: IpAddr) -> Result<String, String> {
+    let names: Vec<_> = interfaces
+        .iter()
+        .filter(|iface| iface.ips.iter().any(|network| network.ip() == ip))
+        .map(|iface| iface.name.as_str())
+        .collect();
+
+    match names[..] {
+        [name] => Ok(name.to_owned()),
+        [] => Err(format!("no network interface has the address {}", ip)),
+        _ => Err(format!(
+            "several network interfaces have the address {}: {}",
+            ip,
+            names.join(", ")
+        )),
+    }
+}
+
+/// Capture on a network interface, until the trigger is stopped or the capture interrupted,
+/// getting the number of packets captured
+fn capture_interface(
+    iface_name: &str,
+    analysis: Pipeline,
+    trigger: &mut Trigger,
+    sink: &mut Sink<BufWriter<io::Stdout>>,
+) -> usize {
+    use pnet::datalink::Channel::Ethernet;
+
     let interface_names_match = |iface: &NetworkInterface| iface.name == iface_name;
 
     // Find the network interface with the provided name
     let interfaces = datalink::interfaces();
     let interface = interfaces
         .into_iter()
-        .filter(interface_names_match)
-        .next()
+        .find(interface_names_match)
         .unwrap_or_else(|| panic!("No such network interface: {}", iface_name));
 
     // Create a channel to receive on
-    let (_, mut rx) = match datalink::channel(&interface, Default::default()) {
+    let config = datalink::Config {
+        read_timeout: Some(CAPTURE_READ_TIMEOUT),
+        ..Default::default()
+    };
+    let (_, mut rx) = match datalink::channel(&interface, config) {
         Ok(Ethernet(tx, rx)) => (tx, rx),
         Ok(_) => panic!("packetdump: unhandled channel type"),
         Err(e) => panic!("packetdump: unable to create channel: {}", e),
     };
 
-    loop {
+    let mut packet_id = 0;
+    let packets = iter::from_fn(|| loop {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return None;
+        }
         match rx.next() {
             Ok(packet) => {
                 let ethernet_packet = EthernetPacket::new(packet).unwrap();
-                let mut _info =0;
-                let new_packet = parse_ethernet_frame(&ethernet_packet, _info);
-                _info += 1;
-                println!("{}", new_packet);
-
+                let new_packet = parse_ethernet_frame(&ethernet_packet, packet_id);
+                packet_id += 1;
+                return Some(new_packet);
             }
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => (),
             Err(e) => panic!("packetdump: unable to receive packet: {}", e),
         }
+    });
+
+    let pipeline = Pipeline::new().map(|mut packet| {
+        packet.set_timestamp(Some(SystemTime::now()));
+        packet
+    });
+
+    emit_packets(
+        analysis.run(pruning(pipeline.run(packets))),
+        trigger,
+        sink,
+        &INTERRUPTED,
+        &SNAPSHOT_REQUESTED,
+    )
+}
+
+/// Log to stderr, the level given on the command line overriding `RUST_LOG`
+fn init_logger(log_level: Option<LevelFilter>) {
+    let mut builder = env_logger::Builder::from_env(Env::default().default_filter_or("warn"));
+    if let Some(log_level) = log_level {
+        builder.filter_level(log_level);
+    }
+    builder.init();
+}
+
+/// Parse every record of a pcap or pcapng file, or of standard input when named `-`, sending the
+/// ones emitted by the trigger to the sink, getting the number of packets read
+fn read_pcap_file(
+    file_name: &str,
+    window: TimeWindow,
+    analysis: Pipeline,
+    trigger: &mut Trigger,
+    sink: &mut Sink<BufWriter<io::Stdout>>,
+) -> usize {
+    let input: Box<dyn Read> = match file_name {
+        "-" => Box::new(io::stdin().lock()),
+        _ => Box::new(
+            File::open(file_name)
+                .unwrap_or_else(|e| panic!("packetdump: unable to open {}: {}", file_name, e)),
+        ),
+    };
+    let mut input = BufReader::new(input);
+    let is_pcapng = input.fill_buf().map(is_pcapng).unwrap_or(false);
+    let records: Box<dyn Iterator<Item = _>> = match is_pcapng {
+        true => Box::new(
+            PcapngReader::new(input)
+                .unwrap_or_else(|e| panic!("packetdump: unable to read {}: {}", file_name, e))
+                .records_within(window),
+        ),
+        false => Box::new(
+            PcapReader::new(input)
+                .unwrap_or_else(|e| panic!("packetdump: unable to read {}: {}", file_name, e))
+                .records_within(window),
+        ),
+    };
+
+    let packets = records.map(|record| match record {
+        Ok((packet_id, record)) => parse_pcap_record(&record, packet_id),
+        Err(e) => panic!("packetdump: unable to read record: {}", e),
+    });
+
+    emit_packets(
+        analysis.run(pruning(packets)),
+        trigger,
+        sink,
+        &INTERRUPTED,
+        &SNAPSHOT_REQUESTED,
+    )
+}
+
+/// Parse the records of several pcap files in timestamp order, sending the ones emitted by the
+/// trigger to the sink, getting the number of packets read
+fn merge_pcap_files(
+    file_names: &[String],
+    analysis: Pipeline,
+    trigger: &mut Trigger,
+    sink: &mut Sink<BufWriter<io::Stdout>>,
+) -> usize {
+    let readers = file_names
+        .iter()
+        .map(|file_name| {
+            let file = File::open(file_name)
+                .unwrap_or_else(|e| panic!("packetdump: unable to open {}: {}", file_name, e));
+            PcapReader::new(BufReader::new(file))
+                .unwrap_or_else(|e| panic!("packetdump: unable to read {}: {}", file_name, e))
+        })
+        .collect();
+    let merger = PcapMerger::new(readers).unwrap_or_else(|e| {
+        eprintln!("packetdump: unable to merge the pcap files: {}", e);
+        process::exit(1);
+    });
+
+    let packets = merger.into_packets().map(|packet| match packet {
+        Ok(packet) => packet,
+        Err(e) => panic!("packetdump: unable to read record: {}", e),
+    });
+
+    emit_packets(
+        analysis.run(pruning(packets)),
+        trigger,
+        sink,
+        &INTERRUPTED,
+        &SNAPSHOT_REQUESTED,
+    )
+}
+
+/// Sweep the idle reassembly buffers every `PRUNE_INTERVAL` parsed packets, aged at the capture
+/// time of the packet
+fn pruning(packets: impl Iterator<Item = ParsedPacket>) -> impl Iterator<Item = ParsedPacket> {
+    packets.enumerate().map(|(index, packet)| {
+        if (index + 1) % PRUNE_INTERVAL == 0 {
+            let now = packet.get_timestamp().unwrap_or_else(SystemTime::now);
+            prune_stale(PARSER_MAX_AGE, now);
+        }
+        packet
+    })
+}
+
+/// Stages annotating the parsed packets: response time of the DNS and HTTP responses, relative
+/// TCP sequence numbers, and checksum offload when checked; the ARP conflicts and the orphaned
+/// Modbus TCP requests are warned about, and the HTTP response bodies written to files when a carver is given
+fn analysis_pipeline<'a>(offload_check: bool, carver: Option<PayloadCarver>) -> Pipeline<'a> {
+    let mut dns_tracker = DnsTracker::new(DNS_QUERY_TIMEOUT);
+    let mut http_tracker = HttpTracker::new(HTTP_REQUEST_TIMEOUT);
+    let mut modbus_tracker = ModbusTransactionTracker::new(MODBUS_TRANSACTION_TIMEOUT);
+    let mut tcp_seq_tracker = TcpSeqTracker::new(TCP_IDLE_TIMEOUT);
+    let mut arp_monitor = ArpMonitor::new(ARP_REBIND_AFTER);
+    let pipeline = Pipeline::new()
+        .map(move |mut packet| {
+            dns_tracker.update(&mut packet);
+            packet
+        })
+        .map(move |mut packet| {
+            http_tracker.update(&mut packet);
+            packet
+        })
+        .map(move |mut packet| {
+            let orphaned = modbus_tracker.orphaned();
+            modbus_tracker.update(&mut packet);
+            if modbus_tracker.orphaned() > orphaned {
+                warn!(
+                    "Modbus TCP request unanswered: {} orphaned so far (packet {})",
+                    modbus_tracker.orphaned(),
+                    packet.get_id()
+                );
+            }
+            packet
+        })
+        .map(move |mut packet| {
+            tcp_seq_tracker.update(&mut packet);
+            packet
+        })
+        .inspect(move |packet| {
+            if let Some(conflict) = arp_monitor.update(packet) {
+                warn!(
+                    "ARP conflict: {} moved from {} to {} (packet {})",
+                    conflict.ip,
+                    conflict.old_mac,
+                    conflict.new_mac,
+                    packet.get_id()
+                );
+            }
+        });
+
+    let pipeline = match offload_check {
+        true => {
+            let mut offload_detector = ChecksumOffloadDetector::new();
+            pipeline.map(move |mut packet| {
+                offload_detector.update(&mut packet);
+                packet
+            })
+        }
+        false => pipeline,
+    };
+
+    match carver {
+        Some(mut carver) => pipeline.inspect(move |packet| {
+            if let Err(e) = carver.carve(packet) {
+                warn!(
+                    "Unable to dump the payload of packet {}: {}",
+                    packet.get_id(),
+                    e
+                );
+            }
+        }),
+        None => pipeline,
+    }
+}
+
+/// Keep only the packets matching the filter and of one of the focused protocols, when any, or
+/// only the other ones when the match is inverted
+fn selected<'a>(
+    pipeline: Pipeline<'a>,
+    focus: &[ProtocolFocus],
+    filter: Option<PacketFilter>,
+    invert_match: bool,
+) -> Pipeline<'a> {
+    if focus.is_empty() && filter.is_none() {
+        return pipeline;
+    }
+
+    let predicates: Vec<fn(&ParsedPacket) -> bool> = focus
+        .iter()
+        .map(|protocol| match protocol {
+            ProtocolFocus::Dns => contains_dns as fn(&ParsedPacket) -> bool,
+            ProtocolFocus::Http => contains_http,
+            ProtocolFocus::Tls => contains_tls,
+            ProtocolFocus::Modbus => contains_modbus,
+        })
+        .collect();
+    pipeline.filter(move |packet| {
+        let matching = filter.as_ref().is_none_or(|filter| filter.matches(packet))
+            && (predicates.is_empty() || predicates.iter().any(|contains| contains(packet)));
+        matching != invert_match
+    })
+}
+
+/// Drop the packets not completing an application message when only the reassembled messages are
+/// emitted: the application parsers accumulate the segments of a message, setting the application
+/// layer of the packet ending it only, with the whole message
+fn reassembled(pipeline: Pipeline<'_>, reassemble_only: bool) -> Pipeline<'_> {
+    match reassemble_only {
+        true => pipeline.filter(|packet| packet.get_application_layer_packet().is_some()),
+        false => pipeline,
+    }
+}
+
+/// Handle SIGUSR1 by setting the snapshot flag, checked between two packets
+#[cfg(unix)]
+fn request_snapshot_on_sigusr1() {
+    extern "C" fn on_sigusr1(_: libc::c_int) {
+        SNAPSHOT_REQUESTED.store(true, Ordering::SeqCst);
+    }
+
+    // Storing to an atomic is async-signal-safe
+    let handler = on_sigusr1 as extern "C" fn(libc::c_int);
+    if unsafe { libc::signal(libc::SIGUSR1, handler as libc::sighandler_t) } == libc::SIG_ERR {
+        warn!("Unable to handle SIGUSR1: {}", io::Error::last_os_error());
+    }
+}
+
+/// Send the packets emitted by the trigger to the sink, until it is stopped or the interrupted
+/// flag is set, getting the number of packets processed; the aggregates of the sink are printed
+/// on standard error whenever the snapshot flag is set
+fn emit_packets<I: Iterator<Item = ParsedPacket>, W: Write>(
+    packets: I,
+    trigger: &mut Trigger,
+    sink: &mut Sink<W>,
+    interrupted: &AtomicBool,
+    snapshot_requested: &AtomicBool,
+) -> usize {
+    let mut packet_count = 0;
+    for new_packet in packets {
+        packet_count += 1;
+        for packet in trigger.process(new_packet) {
+            sink.emit(packet);
+        }
+        if snapshot_requested.swap(false, Ordering::SeqCst) {
+            if let Some(snapshot) = sink.snapshot() {
+                eprint!("{}", snapshot);
+            }
+        }
+
+        if trigger.is_stopped() || interrupted.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+
+    packet_count
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use pnet::datalink::NetworkInterface;
+    use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+    use pnet::packet::tcp::TcpFlags;
+    use sniffer_parser::anonymize::Anonymizer;
+    use sniffer_parser::hierarchy::ProtocolHierarchy;
+    use sniffer_parser::pipeline::Pipeline;
+    use sniffer_parser::serializable_packet::application::HttpContentType;
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+    use sniffer_parser::top_talkers::TopTalkers;
+
+    use super::{emit_packets, interface_by_ip, reassembled, selected, Sink};
+    use crate::cli::parse_args;
+    use crate::color::ColorMode;
+    use crate::output::{LayerSelection, OutputFormat, PacketWriter};
+    use crate::test_util::{build_test_tcp_packet, build_test_udp_packet, CLIENT, SERVER};
+    use crate::trigger::{Trigger, TriggerConfig};
+
+    #[test]
+    fn interrupted_output_finished() {
+        let interrupted = AtomicBool::new(false);
+        let packets = (0..10).map(|id| {
+            // Interruption while the third packet is read
+            if id == 2 {
+                interrupted.store(true, Ordering::SeqCst);
+            }
+            ParsedPacket::new(id)
+        });
+
+        let mut output = vec![];
+        let mut sink = Sink::Print(
+            PacketWriter::new(&mut output, OutputFormat::JsonArray, ColorMode::Never, None),
+            LayerSelection::default(),
+            None,
+        );
+        let mut trigger = Trigger::new(TriggerConfig::default());
+        let packet_count = emit_packets(
+            packets,
+            &mut trigger,
+            &mut sink,
+            &interrupted,
+            &AtomicBool::new(false),
+        );
+        sink.finish();
+
+        assert_eq!(packet_count, 3);
+        let emitted: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        let ids: Vec<_> = emitted
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|packet| packet["id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn top_talkers_anonymized() {
+        let mut sink: Sink<Vec<u8>> =
+            Sink::TopTalkers(TopTalkers::new(10), Some(Anonymizer::new(false)));
+        sink.emit(build_test_packet(0, IpNextHeaderProtocols::Udp, 53, &[]));
+
+        let top_talkers = sink.snapshot().unwrap();
+        assert!(!top_talkers.contains("10.10.10.10"));
+        assert!(!top_talkers.contains("11.11.11.11"));
+    }
+
+    #[test]
+    fn snapshot_keeps_aggregates() {
+        let snapshot_requested = AtomicBool::new(false);
+        let packets: Vec<_> = (0..4)
+            .map(|id| build_test_packet(id, IpNextHeaderProtocols::Udp, 53, &[]))
+            .collect();
+        let mut packets = packets.into_iter().inspect(|packet| {
+            // Snapshot requested while the second packet is read
+            if packet.get_id() == 1 {
+                snapshot_requested.store(true, Ordering::SeqCst);
+            }
+        });
+
+        let mut sink: Sink<Vec<u8>> = Sink::Hierarchy(ProtocolHierarchy::new());
+        let mut trigger = Trigger::new(TriggerConfig::default());
+        let interrupted = AtomicBool::new(false);
+        let packet_count = emit_packets(
+            packets.by_ref().take(2),
+            &mut trigger,
+            &mut sink,
+            &interrupted,
+            &snapshot_requested,
+        );
+        assert_eq!(packet_count, 2);
+        assert!(!snapshot_requested.load(Ordering::SeqCst));
+        assert!(sink
+            .snapshot()
+            .unwrap()
+            .starts_with("Protocol Hierarchy: 2 packets"));
+
+        emit_packets(
+            packets,
+            &mut trigger,
+            &mut sink,
+            &interrupted,
+            &snapshot_requested,
+        );
+        assert!(sink
+            .snapshot()
+            .unwrap()
+            .starts_with("Protocol Hierarchy: 4 packets"));
+    }
+
+    #[test]
+    fn dns_only_drops_http() {
+        let packets = vec![
+            build_test_packet(0, IpNextHeaderProtocols::Udp, 53, DNS_QUERY),
+            build_test_packet(1, IpNextHeaderProtocols::Tcp, 80, b"GET / HTTP/1.1\r\n\r\n"),
+        ];
+        let options = parse_args(["--dns-only", "eth0"].map(str::to_owned)).unwrap();
+
+        let ids: Vec<_> = selected(Pipeline::new(), &options.focus, None, false)
+            .run(packets)
+            .map(|packet| packet.get_id())
+            .collect();
+        assert_eq!(ids, vec![0]);
+    }
+
+    #[test]
+    fn inverted_filter_drops_tcp() {
+        let packet_ids = |arguments: &[&str]| -> Vec<usize> {
+            let packets = vec![
+                build_test_packet(0, IpNextHeaderProtocols::Udp, 5353, &[]),
+                build_test_packet(1, IpNextHeaderProtocols::Tcp, 8080, &[]),
+                build_test_packet(2, IpNextHeaderProtocols::Udp, 53, DNS_QUERY),
+            ];
+            let options = parse_args(arguments.iter().map(|arg| arg.to_string())).unwrap();
+            selected(
+                Pipeline::new(),
+                &options.focus,
+                options.filter,
+                options.invert_match,
+            )
+            .run(packets)
+            .map(|packet| packet.get_id())
+            .collect()
+        };
+
+        assert_eq!(packet_ids(&["--filter", "tcp", "eth0"]), vec![1]);
+        assert_eq!(packet_ids(&["--filter", "tcp", "-v", "eth0"]), vec![0, 2]);
+        assert_eq!(
+            packet_ids(&["--invert-match", "--dns-only", "eth0"]),
+            vec![0, 1]
+        );
+        assert_eq!(
+            packet_ids(&["-v", "--filter", "udp", "--dns-only", "eth0"]),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn reassemble_only_emits_complete_request() {
+        let segments: [&[u8]; 2] = [
+            b"POST /upload HTTP/1.1\r\nContent-Length: 5\r\n\r\nhel",
+            b"lo",
+        ];
+        let packets: Vec<_> = segments
+            .iter()
+            .enumerate()
+            .map(|(id, segment)| build_test_packet(id, IpNextHeaderProtocols::Tcp, 80, segment))
+            .collect();
+        let options = parse_args(["--reassemble-only", "eth0"].map(str::to_owned)).unwrap();
+
+        let emitted: Vec<_> = reassembled(Pipeline::new(), options.reassemble_only)
+            .run(packets)
+            .collect();
+        assert_eq!(emitted.len(), 1);
+        assert_eq!(emitted[0].get_id(), 1);
+        match emitted[0].get_application_layer_packet() {
+            Some(SerializablePacket::HttpRequestPacket(http_packet)) => {
+                assert_eq!(http_packet.path, "/upload");
+                assert!(
+                    matches!(&http_packet.payload, HttpContentType::Unknown(body) if body == b"hello")
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn interface_selected_by_ip() {
+        let interface = |name: &str, ips: &[&str]| NetworkInterface {
+            name: name.to_owned(),
+            description: String::new(),
+            index: 0,
+            mac: None,
+            ips: ips.iter().map(|ip| ip.parse().unwrap()).collect(),
+            flags: 0,
+        };
+        let interfaces = [
+            interface("lo", &["127.0.0.1/8", "::1/128"]),
+            interface("eth0", &["192.168.1.10/24", "fe80::1/64"]),
+            interface("eth1", &["10.0.0.2/8", "fe80::1/64"]),
+        ];
+
+        let by_ip = |ip: &str| interface_by_ip(&interfaces, ip.parse().unwrap());
+        assert_eq!(by_ip("192.168.1.10"), Ok("eth0".to_owned()));
+        assert_eq!(by_ip("::1"), Ok("lo".to_owned()));
+        assert!(by_ip("192.168.1.11").is_err());
+        assert_eq!(
+            by_ip("fe80::1"),
+            Err("several network interfaces have the address fe80::1: eth0, eth1".to_owned())
+        );
+    }
+
+    ///////////////////// Utils
+
+    /// Query of the A record of example.com
+    const DNS_QUERY: &[u8] = b"\x12\x34\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\
+    \x07example\x03com\x00\x00\x01\x00\x01";
+
+    /// Build an Ethernet frame carrying an IPv4 packet with a TCP or UDP segment, parsed
+    fn build_test_packet(
+        id: usize,
+        protocol: IpNextHeaderProtocol,
+        dest_port: u16,
+        payload: &[u8],
+    ) -> ParsedPacket {
+        match protocol {
+            IpNextHeaderProtocols::Tcp => build_test_tcp_packet(
+                id,
+                (CLIENT, 4444),
+                (SERVER, dest_port),
+                TcpFlags::PSH | TcpFlags::ACK,
+                payload,
+            ),
+            _ => build_test_udp_packet(id, (CLIENT, 4444), (SERVER, dest_port), payload),
+        }
     }
 }
@@ -0,0 +1,579 @@
+//! Output formats of the parsed packets
+
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::Value;
+use sniffer_parser::msgpack::write_msgpack_record;
+use sniffer_parser::serializable_packet::row::{ToRow, ROW_COLUMNS};
+use sniffer_parser::serializable_packet::ParsedPacket;
+
+use crate::color::{format_explained_packet, format_parsed_packet, ColorMode};
+
+/// How each parsed packet is printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Layer by layer description, optionally colored
+    Text,
+    /// Layer by layer description with the meaning of each header field
+    Explain,
+    /// One JSON object per line
+    Json,
+    /// Indented JSON objects
+    JsonPretty,
+    /// A single JSON array of objects, one per line
+    JsonArray,
+    /// Length-delimited MessagePack records
+    Msgpack,
+    /// Comma-separated columns, one line per packet after a header line
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "explain" => Ok(OutputFormat::Explain),
+            "json" => Ok(OutputFormat::Json),
+            "json-pretty" => Ok(OutputFormat::JsonPretty),
+            "json-array" => Ok(OutputFormat::JsonArray),
+            "msgpack" => Ok(OutputFormat::Msgpack),
+            "csv" => Ok(OutputFormat::Csv),
+            other => Err(format!(
+                "invalid output format: {} (expected text, explain, json, json-pretty, json-array, msgpack or csv)",
+                other
+            )),
+        }
+    }
+}
+
+/// Layers of the parsed packets kept in the output, all of them by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerSelection {
+    pub link: bool,
+    pub network: bool,
+    pub transport: bool,
+    pub application: bool,
+}
+
+impl Default for LayerSelection {
+    fn default() -> Self {
+        LayerSelection {
+            link: true,
+            network: true,
+            transport: true,
+            application: true,
+        }
+    }
+}
+
+impl LayerSelection {
+    /// Remove the unselected layers of a packet
+    pub fn apply(&self, packet: &mut ParsedPacket) {
+        if !self.link {
+            packet.set_link_layer_packet(None);
+        }
+        if !self.network {
+            packet.set_network_layer_packet(None);
+        }
+        if !self.transport {
+            packet.set_transport_layer_packet(None);
+        }
+        if !self.application {
+            packet.set_application_layer_packet(None);
+        }
+    }
+}
+
+/// Comma-separated list of layer names, e.g. `network,transport`
+impl FromStr for LayerSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut selection = LayerSelection {
+            link: false,
+            network: false,
+            transport: false,
+            application: false,
+        };
+        for layer in s.split(',').map(str::trim) {
+            match layer {
+                "link" => selection.link = true,
+                "network" => selection.network = true,
+                "transport" => selection.transport = true,
+                "application" => selection.application = true,
+                other => {
+                    return Err(format!(
+                        "invalid layer: {} (expected link, network, transport or application)",
+                        other
+                    ))
+                }
+            }
+        }
+
+        Ok(selection)
+    }
+}
+
+/// Names of the fields kept in the JSON objects, at any depth
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonFields {
+    names: HashSet<String>,
+}
+
+impl JsonFields {
+    /// Remove the leaf fields (scalars, arrays of scalars) not in the list from a JSON value, and
+    /// the objects and arrays left empty
+    pub fn apply(&self, value: &mut Value) {
+        self.retain(None, value);
+    }
+
+    /// Filter a value, checking if it is kept in its parent under the field name
+    fn retain(&self, name: Option<&str>, value: &mut Value) -> bool {
+        match value {
+            Value::Object(fields) => {
+                fields.retain(|name, field| self.retain(Some(name), field));
+                !fields.is_empty()
+            }
+            Value::Array(elements) if elements.iter().any(is_container) => {
+                elements.retain_mut(|element| self.retain(name, element));
+                !elements.is_empty()
+            }
+            _ => name.is_some_and(|name| self.names.contains(name)),
+        }
+    }
+}
+
+/// Check if a JSON value holds fields
+fn is_container(value: &Value) -> bool {
+    matches!(value, Value::Object(_) | Value::Array(_))
+}
+
+/// Comma-separated list of field names, e.g. `source,destination`
+impl FromStr for JsonFields {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let names: HashSet<String> = s.split(',').map(|name| name.trim().to_owned()).collect();
+        if names.contains("") {
+            return Err(format!("invalid field list: {:?}", s));
+        }
+
+        Ok(JsonFields { names })
+    }
+}
+
+/// Writer of the parsed packets in an output format, delimiting the elements of the JSON array
+/// and keeping only the listed fields of the JSON objects
+pub struct PacketWriter<W: Write> {
+    writer: W,
+    format: OutputFormat,
+    color: ColorMode,
+    fields: Option<JsonFields>,
+    written: usize,
+    /// Number of packets written between two flushes of the writer
+    flush_every: usize,
+}
+
+impl<W: Write> PacketWriter<W> {
+    pub fn new(
+        writer: W,
+        format: OutputFormat,
+        color: ColorMode,
+        fields: Option<JsonFields>,
+    ) -> Self {
+        PacketWriter {
+            writer,
+            format,
+            color,
+            fields,
+            written: 0,
+            flush_every: 1,
+        }
+    }
+
+    /// Flush the writer every `flush_every` packets rather than after each one (at least 1)
+    pub fn flush_every(mut self, flush_every: usize) -> Self {
+        self.flush_every = flush_every.max(1);
+        self
+    }
+
+    /// Write a parsed packet, flushing the writer when `flush_every` packets were written since
+    /// the last flush
+    pub fn write(&mut self, packet: &ParsedPacket) -> io::Result<()> {
+        let filtered = match (&self.fields, self.format) {
+            (
+                Some(_),
+                OutputFormat::Text
+                | OutputFormat::Explain
+                | OutputFormat::Msgpack
+                | OutputFormat::Csv,
+            )
+            | (None, _) => None,
+            (Some(fields), _) => {
+                let mut value = serde_json::to_value(packet)?;
+                fields.apply(&mut value);
+                Some(value)
+            }
+        };
+
+        if self.format == OutputFormat::Csv && self.written == 0 {
+            write_csv_line(&mut self.writer, ROW_COLUMNS)?;
+        }
+        match (self.format, filtered) {
+            (OutputFormat::JsonArray, filtered) => {
+                let separator = match self.written {
+                    0 => "[\n",
+                    _ => ",\n",
+                };
+                self.writer.write_all(separator.as_bytes())?;
+                match filtered {
+                    Some(value) => serde_json::to_writer(&mut self.writer, &value)?,
+                    None => serde_json::to_writer(&mut self.writer, packet)?,
+                }
+            }
+            (format, Some(value)) => {
+                write_json(&mut self.writer, &value, format == OutputFormat::JsonPretty)?
+            }
+            (format, None) => write_packet(&mut self.writer, packet, format, self.color)?,
+        }
+        self.written += 1;
+
+        match self.written.is_multiple_of(self.flush_every) {
+            true => self.writer.flush(),
+            false => Ok(()),
+        }
+    }
+
+    /// Close the JSON array, an empty one when no packet was written, write the CSV header when
+    /// no packet was written, and flush the writer
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.format == OutputFormat::JsonArray {
+            let closing = match self.written {
+                0 => "[]\n",
+                _ => "\n]\n",
+            };
+            self.writer.write_all(closing.as_bytes())?;
+        }
+        if self.format == OutputFormat::Csv && self.written == 0 {
+            write_csv_line(&mut self.writer, ROW_COLUMNS)?;
+        }
+
+        self.writer.flush()
+    }
+}
+
+/// Write a parsed packet in the output format, the textual ones ending with a newline; colors
+/// only apply to text, and the elements of a JSON array and the CSV rows are written alone,
+/// without their enclosing array or header
+pub fn write_packet<W: Write>(
+    writer: &mut W,
+    packet: &ParsedPacket,
+    format: OutputFormat,
+    color: ColorMode,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => writeln!(writer, "{}", format_parsed_packet(packet, color)),
+        OutputFormat::Explain => writeln!(writer, "{}", format_explained_packet(packet, color)),
+        OutputFormat::Json | OutputFormat::JsonArray => write_json(writer, packet, false),
+        OutputFormat::JsonPretty => write_json(writer, packet, true),
+        OutputFormat::Msgpack => write_msgpack_record(writer, packet),
+        OutputFormat::Csv => {
+            let row = packet.to_row();
+            write_csv_line(writer, row.iter().map(|(_, value)| value.as_str()))
+        }
+    }
+}
+
+/// Write a line of comma-separated fields, quoting the ones holding a comma, a quote or a line
+/// break, their quotes doubled (RFC 4180)
+fn write_csv_line<'a, W: Write>(
+    writer: &mut W,
+    fields: impl IntoIterator<Item = &'a str>,
+) -> io::Result<()> {
+    let fields: Vec<String> = fields
+        .into_iter()
+        .map(|field| match field.contains([',', '"', '\r', '\n']) {
+            true => format!("\"{}\"", field.replace('"', "\"\"")),
+            false => field.to_owned(),
+        })
+        .collect();
+
+    writeln!(writer, "{}", fields.join(","))
+}
+
+/// Write a value as a JSON line, or as indented JSON ending with a newline
+fn write_json<W: Write, T: Serialize>(writer: &mut W, value: &T, pretty: bool) -> io::Result<()> {
+    let rendered = match pretty {
+        true => serde_json::to_string_pretty(value)?,
+        false => serde_json::to_string(value)?,
+    };
+
+    writeln!(writer, "{}", rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use sniffer_parser::msgpack::read_msgpack_records;
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use std::io::{self, Write};
+
+    use pnet::packet::tcp::TcpFlags;
+
+    use serde_json::json;
+
+    use super::{write_packet, JsonFields, LayerSelection, OutputFormat, PacketWriter};
+    use crate::color::ColorMode;
+    use crate::test_util::{build_test_tcp_packet, CLIENT, SERVER};
+
+    fn render_packet(packet: &ParsedPacket, format: OutputFormat, color: ColorMode) -> String {
+        let mut output = vec![];
+        write_packet(&mut output, packet, format, color).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn json_formats() {
+        let packet = ParsedPacket::new(7);
+
+        let json = render_packet(&packet, OutputFormat::Json, ColorMode::Always);
+        assert_eq!(json.find('\n'), Some(json.len() - 1));
+
+        let pretty = render_packet(&packet, OutputFormat::JsonPretty, ColorMode::Always);
+        assert!(pretty.contains("\n  \"id\": 7,"));
+        assert!(pretty.contains("\"linkLayerPacket\": null"));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&pretty).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()
+        );
+    }
+
+    #[test]
+    fn flush_every_n_packets() {
+        let mut output = FlushCounter::default();
+        let mut writer = PacketWriter::new(&mut output, OutputFormat::Json, ColorMode::Never, None)
+            .flush_every(3);
+        let mut flushes = vec![];
+        for id in 0..7 {
+            writer.write(&ParsedPacket::new(id)).unwrap();
+            flushes.push(writer.writer.flushes);
+        }
+        assert_eq!(flushes, [0, 0, 1, 1, 1, 2, 2]);
+
+        writer.finish().unwrap();
+        assert_eq!(output.flushes, 3);
+        assert_eq!(
+            output.written.iter().filter(|&&byte| byte == b'\n').count(),
+            7
+        );
+    }
+
+    #[test]
+    fn json_array_format() {
+        let mut output = vec![];
+        let mut writer =
+            PacketWriter::new(&mut output, OutputFormat::JsonArray, ColorMode::Never, None);
+        for id in 0..3 {
+            writer.write(&ParsedPacket::new(id)).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let json = serde_json::from_slice::<serde_json::Value>(&output).unwrap();
+        let packets = json.as_array().unwrap();
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[2]["id"], 2);
+
+        let mut output = vec![];
+        PacketWriter::new(&mut output, OutputFormat::JsonArray, ColorMode::Never, None)
+            .finish()
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&output).unwrap(),
+            serde_json::json!([])
+        );
+    }
+
+    #[test]
+    fn json_field_allowlist() {
+        let fields: JsonFields = "source, destination,sourcePort,destPort".parse().unwrap();
+        let mut packet = json!({
+            "id": 3,
+            "linkLayerPacket": null,
+            "networkLayerPacket": {
+                "type": "Ipv4Packet",
+                "packet": {
+                    "source": "10.10.10.10",
+                    "destination": "11.11.11.11",
+                    "checksum": 1234,
+                    "options": [1, 2, 3],
+                },
+            },
+            "transportLayerPacket": {
+                "type": "TcpPacket",
+                "packet": { "source": 4444, "destination": 80, "window": 0 },
+            },
+            "applicationLayerPacket": {
+                "type": "DnsPacket",
+                "packet": { "questions": [{ "queryName": "example.com" }] },
+            },
+        });
+        fields.apply(&mut packet);
+
+        assert_eq!(
+            packet,
+            json!({
+                "networkLayerPacket": {
+                    "packet": { "source": "10.10.10.10", "destination": "11.11.11.11" },
+                },
+                "transportLayerPacket": {
+                    "packet": { "source": 4444, "destination": 80 },
+                },
+            })
+        );
+
+        let mut output = vec![];
+        let id_only = Some("id".parse().unwrap());
+        let mut writer =
+            PacketWriter::new(&mut output, OutputFormat::Json, ColorMode::Never, id_only);
+        writer.write(&ParsedPacket::new(7)).unwrap();
+        assert_eq!(output, b"{\"id\":7}\n");
+
+        assert!("source,,destination".parse::<JsonFields>().is_err());
+    }
+
+    #[test]
+    fn msgpack_format() {
+        let packet = ParsedPacket::new(7);
+
+        let mut output = vec![];
+        write_packet(
+            &mut output,
+            &packet,
+            OutputFormat::Msgpack,
+            ColorMode::Always,
+        )
+        .unwrap();
+        write_packet(
+            &mut output,
+            &packet,
+            OutputFormat::Msgpack,
+            ColorMode::Always,
+        )
+        .unwrap();
+
+        let records: Vec<serde_json::Value> = read_msgpack_records(output.as_slice())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0], serde_json::to_value(&packet).unwrap());
+    }
+
+    #[test]
+    fn select_network_layer() {
+        let mut packet = ParsedPacket::new(7);
+        for layer in 0..4 {
+            let malformed = Some(SerializablePacket::MalformedPacket(format!(
+                "layer {}",
+                layer
+            )));
+            match layer {
+                0 => packet.set_link_layer_packet(malformed),
+                1 => packet.set_network_layer_packet(malformed),
+                2 => packet.set_transport_layer_packet(malformed),
+                _ => packet.set_application_layer_packet(malformed),
+            }
+        }
+
+        let selection: LayerSelection = "network".parse().unwrap();
+        selection.apply(&mut packet);
+        assert!(packet.get_link_layer_packet().is_none());
+        assert!(packet.get_network_layer_packet().is_some());
+        assert!(packet.get_transport_layer_packet().is_none());
+        assert!(packet.get_application_layer_packet().is_none());
+
+        let json = render_packet(&packet, OutputFormat::Json, ColorMode::Never);
+        let json = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert!(json["linkLayerPacket"].is_null());
+        assert_eq!(json["networkLayerPacket"]["packet"], "layer 1");
+        assert!(json["transportLayerPacket"].is_null());
+        assert!(json["applicationLayerPacket"].is_null());
+    }
+
+    #[test]
+    fn invalid_layers() {
+        assert!("network,session".parse::<LayerSelection>().is_err());
+        assert!("".parse::<LayerSelection>().is_err());
+        assert_eq!(
+            "link, transport".parse(),
+            Ok(LayerSelection {
+                link: true,
+                network: false,
+                transport: true,
+                application: false,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_format() {
+        assert!("yaml".parse::<OutputFormat>().is_err());
+        assert_eq!("json-pretty".parse(), Ok(OutputFormat::JsonPretty));
+        assert_eq!("msgpack".parse(), Ok(OutputFormat::Msgpack));
+        assert_eq!("explain".parse(), Ok(OutputFormat::Explain));
+        assert_eq!("csv".parse(), Ok(OutputFormat::Csv));
+    }
+
+    #[test]
+    fn csv_format() {
+        let packet = build_test_tcp_packet(
+            0,
+            (SERVER, 80),
+            (CLIENT, 4444),
+            TcpFlags::SYN | TcpFlags::ACK,
+            &[],
+        );
+
+        let mut output = vec![];
+        let mut writer = PacketWriter::new(&mut output, OutputFormat::Csv, ColorMode::Never, None);
+        writer.write(&packet).unwrap();
+        writer.write(&ParsedPacket::new(1)).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "timestamp,source_ip,dest_ip,source_port,dest_port,protocol,length,info\n\
+            ,11.11.11.11,10.10.10.10,80,4444,TCP,54,\"[ACK, SYN] Seq=0 Ack=0 Win=1024 Len=0\"\n\
+            ,,,,,,,\n"
+        );
+
+        let mut output = vec![];
+        PacketWriter::new(&mut output, OutputFormat::Csv, ColorMode::Never, None)
+            .finish()
+            .unwrap();
+        assert_eq!(output.iter().filter(|&&byte| byte == b'\n').count(), 1);
+    }
+
+    ///////////////////// Utils
+
+    /// Writer keeping the bytes written and counting the flushes
+    #[derive(Default)]
+    struct FlushCounter {
+        written: Vec<u8>,
+        flushes: usize,
+    }
+
+    impl Write for FlushCounter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+}
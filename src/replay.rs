@@ -0,0 +1,103 @@
+//! Replay of a pcap file of Ethernet frames onto a network interface, at the pace of the capture
+
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use pnet::datalink::{self, Channel};
+use sniffer_parser::{LinkTypes, PcapReader};
+
+/// Send every record of a pcap file on an interface, sleeping between two records for the time
+/// separating them in the capture divided by the speed; the number of sent records is returned
+pub fn replay_pcap_file(file_name: &str, iface_name: &str, speed: f64) -> Result<usize, String> {
+    let file = File::open(file_name).map_err(|e| format!("unable to open {}: {}", file_name, e))?;
+    let reader = PcapReader::new(BufReader::new(file))
+        .map_err(|e| format!("unable to read {}: {}", file_name, e))?;
+    check_link_type(reader.get_link_type())
+        .map_err(|e| format!("unable to replay {}: {}", file_name, e))?;
+
+    let interface = datalink::interfaces()
+        .into_iter()
+        .find(|iface| iface.name == iface_name)
+        .ok_or(format!("no such network interface: {}", iface_name))?;
+
+    let mut tx = match datalink::channel(&interface, Default::default()) {
+        Ok(Channel::Ethernet(tx, _)) => tx,
+        Ok(_) => return Err("unhandled channel type".to_owned()),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            return Err(format!(
+                "permission denied to send on {}: run as root or grant CAP_NET_RAW",
+                iface_name
+            ))
+        }
+        Err(e) => return Err(format!("unable to create channel: {}", e)),
+    };
+
+    let mut previous_timestamp = None;
+    let mut sent = 0;
+    for record in reader {
+        let record = record.map_err(|e| format!("unable to read record: {}", e))?;
+        if let Some(previous_timestamp) = previous_timestamp {
+            thread::sleep(replay_delay(previous_timestamp, record.timestamp, speed));
+        }
+        previous_timestamp = Some(record.timestamp);
+
+        match tx.send_to(&record.data, None) {
+            Some(Ok(())) => sent += 1,
+            Some(Err(e)) => return Err(format!("unable to send packet: {}", e)),
+            None => return Err("unable to send packet: buffer too small".to_owned()),
+        }
+    }
+
+    Ok(sent)
+}
+
+/// Check that the records of a capture can be sent as they are on an Ethernet channel
+pub fn check_link_type(link_type: u32) -> Result<(), String> {
+    match link_type {
+        LinkTypes::ETHERNET => Ok(()),
+        LinkTypes::LINUX_SLL => {
+            Err("Linux cooked captures (SLL) have no Ethernet header".to_owned())
+        }
+        link_type => Err(format!("link type {} is not Ethernet", link_type)),
+    }
+}
+
+/// Time to wait between two records, none when the capture goes back in time
+pub fn replay_delay(previous: SystemTime, current: SystemTime, speed: f64) -> Duration {
+    current
+        .duration_since(previous)
+        .unwrap_or_default()
+        .div_f64(speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use sniffer_parser::LinkTypes;
+
+    use super::{check_link_type, replay_delay};
+
+    #[test]
+    fn non_ethernet_link_type_rejected() {
+        assert!(check_link_type(LinkTypes::ETHERNET).is_ok());
+        assert!(check_link_type(LinkTypes::LINUX_SLL).is_err());
+        assert!(check_link_type(LinkTypes::IEEE802_11_RADIOTAP).is_err());
+    }
+
+    #[test]
+    fn inter_packet_delay() {
+        let first = UNIX_EPOCH + Duration::new(1_600_000_000, 250_000_000);
+        let second = first + Duration::from_millis(500);
+
+        assert_eq!(replay_delay(first, second, 1.0), Duration::from_millis(500));
+        assert_eq!(replay_delay(first, second, 2.0), Duration::from_millis(250));
+        assert_eq!(
+            replay_delay(first, second, 0.5),
+            Duration::from_millis(1000)
+        );
+        assert_eq!(replay_delay(second, first, 1.0), Duration::ZERO);
+    }
+}
@@ -0,0 +1,171 @@
+//! Capture sessions, keeping every parsed packet in memory to filter them after the capture
+
+use std::io::{self, Write};
+
+use sniffer_parser::anonymize::Anonymizer;
+use sniffer_parser::filter::PacketFilter;
+use sniffer_parser::serializable_packet::ParsedPacket;
+
+use crate::color::ColorMode;
+use crate::output::{LayerSelection, OutputFormat, PacketWriter};
+
+/// Packets of a capture, in capture order, with their raw frame when retained
+///
+/// The packets are stored whole, so that the filters see every layer; the layers selected are
+/// only kept, and the addresses anonymized, in the packets queried or exported
+pub struct CaptureSession {
+    packets: Vec<ParsedPacket>,
+    layers: LayerSelection,
+    anonymizer: Option<Anonymizer>,
+}
+
+impl CaptureSession {
+    /// Build a session giving out the selected layers of the packets, anonymized when an
+    /// anonymizer is given
+    pub fn new(layers: LayerSelection, anonymizer: Option<Anonymizer>) -> Self {
+        CaptureSession {
+            packets: Vec::new(),
+            layers,
+            anonymizer,
+        }
+    }
+
+    /// Store a parsed packet, for the lifetime of the session
+    pub fn add(&mut self, packet: ParsedPacket) {
+        self.packets.push(packet);
+    }
+
+    /// Get the number of packets stored
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// Get the stored packets matching a filter, in capture order, with the selected layers
+    pub fn query(&mut self, filter: &PacketFilter) -> Vec<ParsedPacket> {
+        let (layers, anonymizer) = (&self.layers, &mut self.anonymizer);
+        self.packets
+            .iter()
+            .filter(|packet| filter.matches(packet))
+            .map(|packet| selected_layers(packet, layers, anonymizer))
+            .collect()
+    }
+
+    /// Write every stored packet, with the selected layers, in an output format, uncolored
+    pub fn export<W: Write>(&mut self, format: OutputFormat, writer: W) -> io::Result<()> {
+        let (layers, anonymizer) = (&self.layers, &mut self.anonymizer);
+        let packets: Vec<_> = self
+            .packets
+            .iter()
+            .map(|packet| selected_layers(packet, layers, anonymizer))
+            .collect();
+
+        write_packets(&packets, format, ColorMode::Never, writer)
+    }
+}
+
+/// Copy a stored packet with only the selected layers, anonymized when an anonymizer is given
+fn selected_layers(
+    packet: &ParsedPacket,
+    layers: &LayerSelection,
+    anonymizer: &mut Option<Anonymizer>,
+) -> ParsedPacket {
+    let mut packet = packet.clone();
+    layers.apply(&mut packet);
+    if let Some(anonymizer) = anonymizer {
+        anonymizer.anonymize(&mut packet);
+    }
+
+    packet
+}
+
+/// Write packets in an output format, a JSON array holding all of them
+pub fn write_packets<'a, W: Write>(
+    packets: impl IntoIterator<Item = &'a ParsedPacket>,
+    format: OutputFormat,
+    color: ColorMode,
+    writer: W,
+) -> io::Result<()> {
+    let mut writer = PacketWriter::new(writer, format, color, None);
+    for packet in packets {
+        writer.write(packet)?;
+    }
+
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+    use sniffer_parser::anonymize::Anonymizer;
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use super::CaptureSession;
+    use crate::output::{LayerSelection, OutputFormat};
+    use crate::test_util::{build_test_tcp_packet, build_test_udp_packet, CLIENT, SERVER};
+
+    #[test]
+    fn udp_packets_queried() {
+        let mut session = CaptureSession::new(LayerSelection::default(), None);
+        for (id, protocol) in [
+            IpNextHeaderProtocols::Tcp,
+            IpNextHeaderProtocols::Udp,
+            IpNextHeaderProtocols::Tcp,
+            IpNextHeaderProtocols::Udp,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            session.add(build_test_packet(id, protocol));
+        }
+        assert_eq!(session.len(), 4);
+
+        let udp_ids: Vec<_> = session
+            .query(&"udp".parse().unwrap())
+            .iter()
+            .map(|packet| packet.get_id())
+            .collect();
+        assert_eq!(udp_ids, vec![1, 3]);
+        assert!(session.query(&"icmp".parse().unwrap()).is_empty());
+
+        let mut output = vec![];
+        session
+            .export(OutputFormat::JsonArray, &mut output)
+            .unwrap();
+        let exported = serde_json::from_slice::<serde_json::Value>(&output).unwrap();
+        assert_eq!(exported.as_array().map(Vec::len), Some(4));
+    }
+
+    #[test]
+    fn filters_see_unselected_layers() {
+        let layers = LayerSelection {
+            transport: false,
+            ..LayerSelection::default()
+        };
+        let mut session = CaptureSession::new(layers, Some(Anonymizer::new(false)));
+        session.add(build_test_packet(0, IpNextHeaderProtocols::Udp));
+
+        let udp_packets = session.query(&"udp and src host 10.10.10.10".parse().unwrap());
+        assert_eq!(udp_packets.len(), 1);
+        assert!(udp_packets[0].get_transport_layer_packet().is_none());
+        match udp_packets[0].get_network_layer_packet() {
+            Some(SerializablePacket::Ipv4Packet(ipv4_packet)) => {
+                assert_ne!(ipv4_packet.source, Ipv4Addr::new(10, 10, 10, 10))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    ///////////////////// Utils
+
+    /// Build an Ethernet frame carrying an IPv4 packet with an empty TCP or UDP header, parsed
+    fn build_test_packet(id: usize, protocol: IpNextHeaderProtocol) -> ParsedPacket {
+        match protocol {
+            IpNextHeaderProtocols::Tcp => {
+                build_test_tcp_packet(id, (CLIENT, 4444), (SERVER, 53), 0, &[])
+            }
+            _ => build_test_udp_packet(id, (CLIENT, 4444), (SERVER, 53), &[]),
+        }
+    }
+}
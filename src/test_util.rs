@@ -0,0 +1,94 @@
+//! Builders of the packets handled by the tests
+//!
+//! Frames carry an IPv4 packet with a TTL of 64, and are parsed as they would be when captured
+
+use std::net::Ipv4Addr;
+
+use pnet::packet::ethernet::{EtherTypes, MutableEthernetPacket};
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::ipv4::MutableIpv4Packet;
+use pnet::packet::tcp::MutableTcpPacket;
+use pnet::packet::udp::MutableUdpPacket;
+use pnet::packet::Packet;
+use sniffer_parser::parse_ethernet_frame;
+use sniffer_parser::serializable_packet::ParsedPacket;
+
+/// Address of the host opening the connections
+pub(crate) const CLIENT: Ipv4Addr = Ipv4Addr::new(10, 10, 10, 10);
+/// Address of the host answering them
+pub(crate) const SERVER: Ipv4Addr = Ipv4Addr::new(11, 11, 11, 11);
+
+/// Parse a TCP segment without options, acknowledging nothing with a window of 1024 bytes
+pub(crate) fn build_test_tcp_packet(
+    id: usize,
+    source: (Ipv4Addr, u16),
+    destination: (Ipv4Addr, u16),
+    flags: u8,
+    payload: &[u8],
+) -> ParsedPacket {
+    let mut tcp_buffer = vec![0u8; 20 + payload.len()];
+    let mut tcp_packet = MutableTcpPacket::new(&mut tcp_buffer).unwrap();
+    tcp_packet.set_source(source.1);
+    tcp_packet.set_destination(destination.1);
+    tcp_packet.set_data_offset(5);
+    tcp_packet.set_flags(flags);
+    tcp_packet.set_window(1024);
+    tcp_packet.set_payload(payload);
+
+    build_test_ipv4_frame(
+        id,
+        source.0,
+        destination.0,
+        IpNextHeaderProtocols::Tcp,
+        &tcp_buffer,
+    )
+}
+
+/// Parse a UDP datagram without checksum
+pub(crate) fn build_test_udp_packet(
+    id: usize,
+    source: (Ipv4Addr, u16),
+    destination: (Ipv4Addr, u16),
+    payload: &[u8],
+) -> ParsedPacket {
+    let mut udp_buffer = vec![0u8; 8 + payload.len()];
+    let mut udp_packet = MutableUdpPacket::new(&mut udp_buffer).unwrap();
+    udp_packet.set_source(source.1);
+    udp_packet.set_destination(destination.1);
+    udp_packet.set_length((8 + payload.len()) as u16);
+    udp_packet.set_payload(payload);
+
+    build_test_ipv4_frame(
+        id,
+        source.0,
+        destination.0,
+        IpNextHeaderProtocols::Udp,
+        &udp_buffer,
+    )
+}
+
+fn build_test_ipv4_frame(
+    id: usize,
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+    protocol: IpNextHeaderProtocol,
+    payload: &[u8],
+) -> ParsedPacket {
+    let mut ip_buffer = vec![0u8; 20 + payload.len()];
+    let mut ipv4_packet = MutableIpv4Packet::new(&mut ip_buffer).unwrap();
+    ipv4_packet.set_version(4);
+    ipv4_packet.set_header_length(5);
+    ipv4_packet.set_total_length((20 + payload.len()) as u16);
+    ipv4_packet.set_ttl(64);
+    ipv4_packet.set_next_level_protocol(protocol);
+    ipv4_packet.set_source(source);
+    ipv4_packet.set_destination(destination);
+    ipv4_packet.set_payload(payload);
+
+    let mut ethernet_buffer = vec![0u8; 14 + ipv4_packet.packet().len()];
+    let mut ethernet_packet = MutableEthernetPacket::new(&mut ethernet_buffer).unwrap();
+    ethernet_packet.set_ethertype(EtherTypes::Ipv4);
+    ethernet_packet.set_payload(ipv4_packet.packet());
+
+    parse_ethernet_frame(&ethernet_packet.to_immutable(), id)
+}
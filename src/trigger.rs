@@ -0,0 +1,149 @@
+//! Capture triggers, emitting only the packets between a start and a stop packet
+
+use std::collections::VecDeque;
+
+use sniffer_parser::filter::PacketFilter;
+use sniffer_parser::serializable_packet::ParsedPacket;
+
+/// Filters starting and stopping the emission of packets
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TriggerConfig {
+    /// Packet starting the emission, every packet is emitted from the beginning when `None`
+    pub start: Option<PacketFilter>,
+    /// Packet following the start one and stopping the emission (emitted itself), never
+    /// stopping when `None`
+    pub stop: Option<PacketFilter>,
+    /// Number of packets preceding the start packet emitted along with it
+    pub pre_trigger: usize,
+}
+
+/// Progression of a trigger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TriggerState {
+    Waiting,
+    Recording,
+    Stopped,
+}
+
+/// Trigger state, fed with every parsed packet in capture order
+pub struct Trigger {
+    config: TriggerConfig,
+    state: TriggerState,
+    pre_trigger_buffer: VecDeque<ParsedPacket>,
+}
+
+impl Trigger {
+    pub fn new(config: TriggerConfig) -> Self {
+        let state = match config.start {
+            Some(_) => TriggerState::Waiting,
+            None => TriggerState::Recording,
+        };
+
+        Trigger {
+            config,
+            state,
+            pre_trigger_buffer: VecDeque::new(),
+        }
+    }
+
+    /// Process the next packet, returning the packets to emit
+    pub fn process(&mut self, packet: ParsedPacket) -> Vec<ParsedPacket> {
+        match self.state {
+            TriggerState::Waiting => {
+                if self
+                    .config
+                    .start
+                    .as_ref()
+                    .is_some_and(|s| s.matches(&packet))
+                {
+                    self.state = TriggerState::Recording;
+
+                    let mut packets: Vec<ParsedPacket> =
+                        self.pre_trigger_buffer.drain(..).collect();
+                    packets.push(packet);
+                    packets
+                } else {
+                    if self.config.pre_trigger > 0 {
+                        if self.pre_trigger_buffer.len() == self.config.pre_trigger {
+                            self.pre_trigger_buffer.pop_front();
+                        }
+                        self.pre_trigger_buffer.push_back(packet);
+                    }
+                    vec![]
+                }
+            }
+            TriggerState::Recording => self.record(packet),
+            TriggerState::Stopped => vec![],
+        }
+    }
+
+    /// Check if the stop packet was seen, no packet being emitted anymore
+    pub fn is_stopped(&self) -> bool {
+        self.state == TriggerState::Stopped
+    }
+
+    fn record(&mut self, packet: ParsedPacket) -> Vec<ParsedPacket> {
+        if self
+            .config
+            .stop
+            .as_ref()
+            .is_some_and(|s| s.matches(&packet))
+        {
+            self.state = TriggerState::Stopped;
+        }
+
+        vec![packet]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use super::{Trigger, TriggerConfig};
+
+    #[test]
+    fn packets_outside_triggers_suppressed() {
+        let mut trigger = Trigger::new(TriggerConfig {
+            start: Some("malformed".parse().unwrap()),
+            stop: Some("malformed".parse().unwrap()),
+            pre_trigger: 0,
+        });
+
+        let emitted = emitted_ids(&mut trigger, &[false, false, true, false, true, false]);
+        assert_eq!(emitted, vec![2, 3, 4]);
+        assert!(trigger.is_stopped());
+    }
+
+    #[test]
+    fn pre_trigger_buffering() {
+        let mut trigger = Trigger::new(TriggerConfig {
+            start: Some("malformed".parse().unwrap()),
+            stop: None,
+            pre_trigger: 2,
+        });
+
+        let emitted = emitted_ids(&mut trigger, &[false, false, false, true, false]);
+        assert_eq!(emitted, vec![1, 2, 3, 4]);
+        assert!(!trigger.is_stopped());
+    }
+
+    ///////////////////// Utils
+
+    /// Feed packets to the trigger, the matching ones being malformed, getting the emitted ids
+    fn emitted_ids(trigger: &mut Trigger, matching: &[bool]) -> Vec<usize> {
+        let mut emitted = vec![];
+
+        for (id, matching) in matching.iter().enumerate() {
+            let mut packet = ParsedPacket::new(id);
+            if *matching {
+                packet.set_link_layer_packet(Some(SerializablePacket::MalformedPacket(
+                    "Malformed Ethernet Packet".to_string(),
+                )));
+            }
+            emitted.extend(trigger.process(packet).iter().map(|p| p.get_id()));
+        }
+
+        emitted
+    }
+}